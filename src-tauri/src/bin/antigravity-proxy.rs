@@ -0,0 +1,8 @@
+// 独立的 headless 反代入口：不创建窗口 / 托盘，
+// 仅启动反代服务、Token 管理器与配额调度器，读取现有数据目录中的账号。
+//
+//   antigravity-proxy --port 8045 --bind 0.0.0.0 --data-dir ~/.antigravity_tools
+
+fn main() {
+    antigravity_tools_lib::run_headless()
+}
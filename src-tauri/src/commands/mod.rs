@@ -134,6 +134,24 @@ pub async fn switch_account(
     Ok(())
 }
 
+/// 切换账号并自动重启 Antigravity (保留原工作区，进度通过 account://switch-progress 推送)
+#[tauri::command]
+pub async fn switch_account_and_restart(
+    app: tauri::AppHandle,
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+) -> Result<(), String> {
+    modules::switch_restart::switch_account_with_restart(&account_id, app.clone()).await?;
+
+    // 同步托盘
+    crate::modules::tray::update_tray_menus(&app);
+
+    // [FIX #820] Notify proxy to clear stale session bindings and reload accounts
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+
+    Ok(())
+}
+
 /// 获取当前账号
 #[tauri::command]
 pub async fn get_current_account() -> Result<Option<Account>, String> {
@@ -160,6 +178,44 @@ pub async fn export_accounts(account_ids: Vec<String>) -> Result<AccountExportRe
     modules::account::export_accounts_by_ids(&account_ids)
 }
 
+/// 导出选中账号为口令加密的账号包
+#[tauri::command]
+pub async fn export_accounts_bundle(
+    account_ids: Vec<String>,
+    passphrase: String,
+    path: String,
+) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || {
+        modules::account_bundle::export_bundle(&account_ids, &passphrase, std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))?
+}
+
+/// 导入加密账号包 (strategy: skip / overwrite)
+#[tauri::command]
+pub async fn import_accounts_bundle(
+    app: tauri::AppHandle,
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    path: String,
+    passphrase: String,
+    strategy: Option<modules::account_bundle::BundleMergeStrategy>,
+) -> Result<modules::account_bundle::BundleImportResult, String> {
+    let strategy = strategy.unwrap_or_default();
+    let result = tokio::task::spawn_blocking(move || {
+        modules::account_bundle::import_bundle(std::path::Path::new(&path), &passphrase, strategy)
+    })
+    .await
+    .map_err(|e| format!("Import task failed: {}", e))??;
+
+    crate::modules::tray::update_tray_menus(&app);
+
+    // Reload token pool
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+
+    Ok(result)
+}
+
 /// 内部辅助功能：在添加或导入账号后自动刷新一次额度
 async fn internal_refresh_account_quota(
     app: &tauri::AppHandle,
@@ -339,48 +395,31 @@ pub async fn save_config(
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
     config: AppConfig,
 ) -> Result<(), String> {
+    crate::proxy::safety_settings::validate_safety_settings_config(&config.proxy.safety_settings)?;
+    crate::proxy::mcp_bridge::validate_mcp_bridge_config(&config.proxy.mcp_bridge)?;
+    crate::proxy::common::context_trim::validate_context_trim_config(&config.proxy.context_trim)?;
+    crate::proxy::redaction::validate_redaction_config(&config.proxy.redaction)?;
+    config.proxy.account_groups.validate()?;
+    crate::proxy::common::model_mapping::validate_quota_group_overrides(&config.proxy.quota_group_overrides)?;
+    modules::migration::validate_token_encryption_setting(config.encrypt_tokens_at_rest)?;
     modules::save_app_config(&config)?;
 
+    // [NEW] Token 静态加密开关变化时迁移账号文件
+    if config.encrypt_tokens_at_rest != crate::utils::crypto::is_token_encryption_enabled() {
+        modules::migration::apply_token_encryption_setting(config.encrypt_tokens_at_rest)?;
+    }
+
+    // 日志级别热更新
+    modules::logger::apply_logging_config(&config.logging);
+
     // 通知托盘配置已更新
     let _ = app.emit("config://updated", ());
 
     // 热更新正在运行的服务
     let instance_lock = proxy_state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
-        // 更新模型映射
-        instance.axum_server.update_mapping(&config.proxy).await;
-        // 更新上游代理
-        instance
-            .axum_server
-            .update_proxy(config.proxy.upstream_proxy.clone())
-            .await;
-        // 更新安全策略 (auth)
-        instance.axum_server.update_security(&config.proxy).await;
-        // 更新 z.ai 配置
-        instance.axum_server.update_zai(&config.proxy).await;
-        // 更新实验性配置
-        instance
-            .axum_server
-            .update_experimental(&config.proxy)
-            .await;
-        // 更新调试日志配置
-        instance
-            .axum_server
-            .update_debug_logging(&config.proxy)
-            .await;
-        // [NEW] 更新 User-Agent 配置
-        instance.axum_server.update_user_agent(&config.proxy).await;
-        // 更新 Thinking Budget 配置
-        crate::proxy::update_thinking_budget_config(config.proxy.thinking_budget.clone());
-        // [NEW] 更新全局系统提示词配置
-        crate::proxy::update_global_system_prompt_config(config.proxy.global_system_prompt.clone());
-        // [NEW] 更新全局图像思维模式配置
-        crate::proxy::update_image_thinking_mode(config.proxy.image_thinking_mode.clone());
-        // 更新代理池配置
-        instance
-            .axum_server
-            .update_proxy_pool(config.proxy.proxy_pool.clone())
-            .await;
+        // 整体热更新反代配置 (映射、上游代理/客户端、鉴权、z.ai、实验性、调试日志、UA、代理池等)
+        instance.axum_server.apply_config(&config.proxy).await;
         // 更新熔断配置
         instance
             .token_manager
@@ -392,6 +431,174 @@ pub async fn save_config(
     Ok(())
 }
 
+/// 备份账号数据库 (未指定路径时保存到数据目录下的 backups/)
+#[tauri::command]
+pub async fn backup_account_db(
+    target_path: Option<String>,
+) -> Result<modules::account_db::AccountDbMaintenance, String> {
+    let target = target_path
+        .filter(|p| !p.trim().is_empty())
+        .map(std::path::PathBuf::from);
+    tokio::task::spawn_blocking(move || modules::account_db::backup_account_db(target))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 整理账号数据库 (合并 WAL 并 VACUUM)
+#[tauri::command]
+pub async fn vacuum_account_db() -> Result<modules::account_db::AccountDbMaintenance, String> {
+    tokio::task::spawn_blocking(modules::account_db::vacuum_account_db)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// --- 自动备份 / 恢复 ---
+
+#[tauri::command]
+pub async fn list_backup_snapshots() -> Result<Vec<modules::backup::BackupSnapshot>, String> {
+    tokio::task::spawn_blocking(modules::backup::list_snapshots)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 立即生成一次快照 (按配置的保留数量轮转)
+#[tauri::command]
+pub async fn create_backup_snapshot() -> Result<modules::backup::BackupSnapshot, String> {
+    let keep = modules::load_app_config()?.backup.keep as usize;
+    tokio::task::spawn_blocking(move || {
+        let snapshot = modules::backup::create_snapshot(modules::backup::SnapshotReason::Manual)?;
+        modules::backup::prune_snapshots(keep)?;
+        Ok(snapshot)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn delete_backup_snapshot(snapshot_id: String) -> Result<(), String> {
+    modules::backup::delete_snapshot(&snapshot_id)
+}
+
+/// 恢复指定快照 (前端需先让用户确认)，返回恢复前自动生成的快照以便撤销
+#[tauri::command]
+pub async fn restore_backup_snapshot(
+    app: tauri::AppHandle,
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    snapshot_id: String,
+) -> Result<modules::backup::BackupSnapshot, String> {
+    let pre_restore = tokio::task::spawn_blocking(move || modules::backup::restore_snapshot(&snapshot_id))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    // 恢复后的配置与账号立即生效
+    let config = modules::load_app_config()?;
+    crate::utils::crypto::set_token_encryption_enabled(config.encrypt_tokens_at_rest);
+    {
+        let instance_lock = proxy_state.instance.read().await;
+        if let Some(instance) = instance_lock.as_ref() {
+            instance.axum_server.apply_config(&config.proxy).await;
+            instance
+                .token_manager
+                .update_circuit_breaker_config(config.circuit_breaker.clone())
+                .await;
+        }
+    }
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+
+    let _ = app.emit("config://updated", ());
+    let _ = app.emit("accounts://refreshed", ());
+    crate::modules::tray::update_tray_menus(&app);
+
+    Ok(pre_restore)
+}
+
+// --- Gemini 上下文缓存 (cachedContents) ---
+
+#[tauri::command]
+pub async fn list_cached_contents(
+    account_id: Option<String>,
+) -> Result<Vec<crate::proxy::context_cache::CachedContentInfo>, String> {
+    crate::proxy::context_cache::list_cached_contents(account_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn create_cached_content(
+    request: crate::proxy::context_cache::CreateCachedContentRequest,
+) -> Result<crate::proxy::context_cache::CachedContentInfo, String> {
+    crate::proxy::context_cache::create_cached_content(&request).await
+}
+
+/// cache_id 可以是 "cachedContents/xxx" 或仅 id
+#[tauri::command]
+pub async fn delete_cached_content(account_id: Option<String>, cache_id: String) -> Result<(), String> {
+    crate::proxy::context_cache::delete_cached_content(account_id.as_deref(), &cache_id).await
+}
+
+/// 本次启动执行过的存储 schema 迁移 (含备份路径)
+#[tauri::command]
+pub async fn get_migration_reports() -> Result<Vec<modules::migration::MigrationReport>, String> {
+    Ok(modules::migration::get_migration_reports())
+}
+
+// --- 配置档案命令 ---
+
+#[tauri::command]
+pub async fn list_config_profiles() -> Result<modules::config_profiles::ProfileStore, String> {
+    modules::config_profiles::load_profiles()
+}
+
+/// 以当前反代配置为模板创建档案
+#[tauri::command]
+pub async fn create_config_profile(
+    name: String,
+) -> Result<Vec<modules::config_profiles::ConfigProfileSummary>, String> {
+    Ok(modules::config_profiles::create_profile(&name)?.summaries())
+}
+
+/// 将当前反代配置保存到正在使用的档案
+#[tauri::command]
+pub async fn save_active_config_profile(
+) -> Result<Vec<modules::config_profiles::ConfigProfileSummary>, String> {
+    Ok(modules::config_profiles::save_active_profile()?.summaries())
+}
+
+#[tauri::command]
+pub async fn delete_config_profile(
+    name: String,
+) -> Result<Vec<modules::config_profiles::ConfigProfileSummary>, String> {
+    Ok(modules::config_profiles::delete_profile(&name)?.summaries())
+}
+
+/// 切换配置档案: 端口 / 监听地址 / TLS 变化时重启反代监听，否则整体热更新并按账号子集重新加载账号
+#[tauri::command]
+pub async fn switch_config_profile(
+    app: tauri::AppHandle,
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    cf_state: tauri::State<'_, crate::commands::cloudflared::CloudflaredState>,
+    name: String,
+) -> Result<AppConfig, String> {
+    let (old_config, new_config) = modules::config_profiles::switch_profile(&name)?;
+    let _ = app.emit("config://updated", ());
+
+    let running = proxy_state.instance.read().await.is_some();
+    if running {
+        if modules::config_profiles::requires_listener_restart(&old_config.proxy, &new_config.proxy) {
+            let status = crate::commands::proxy::internal_restart_proxy_service(
+                new_config.proxy.clone(),
+                &proxy_state,
+                crate::modules::integration::SystemManager::Desktop(app.clone()),
+                std::sync::Arc::new(cf_state.inner().clone()),
+            )
+            .await?;
+            crate::commands::proxy::emit_proxy_status(&app, &status);
+        } else if let Some(instance) = proxy_state.instance.read().await.as_ref() {
+            instance.axum_server.apply_config(&new_config.proxy).await;
+        }
+    }
+
+    Ok(new_config)
+}
+
 // --- OAuth 命令 ---
 
 #[tauri::command]
@@ -459,6 +666,94 @@ pub async fn submit_oauth_code(code: String, state: Option<String>) -> Result<()
     modules::oauth_server::submit_oauth_code(code, state).await
 }
 
+/// 手动粘贴登录 (无浏览器环境): 生成授权链接，用户在任意设备完成授权后粘贴回调 URL
+#[tauri::command]
+pub async fn begin_manual_oauth_login(
+    app_handle: tauri::AppHandle,
+    oauth_client_key: Option<String>,
+) -> Result<modules::oauth_server::ManualOAuthStart, String> {
+    let service = modules::account_service::AccountService::new(
+        crate::modules::integration::SystemManager::Desktop(app_handle),
+    );
+    service.begin_manual_oauth_login(oauth_client_key)
+}
+
+/// 手动粘贴登录: 提交回调 URL 或授权码并添加账号
+#[tauri::command]
+pub async fn finish_manual_oauth_login(
+    app_handle: tauri::AppHandle,
+    input: String,
+    state: Option<String>,
+) -> Result<Account, String> {
+    modules::logger::log_info("完成手动粘贴 OAuth 授权流程...");
+    let service = modules::account_service::AccountService::new(
+        crate::modules::integration::SystemManager::Desktop(app_handle.clone()),
+    );
+
+    let mut account = service.finish_manual_oauth_login(&input, state).await?;
+
+    let _ = internal_refresh_account_quota(&app_handle, &mut account).await;
+    let _ = crate::commands::proxy::reload_proxy_accounts(
+        app_handle.state::<crate::commands::proxy::ProxyServiceState>(),
+    )
+    .await;
+
+    Ok(account)
+}
+
+/// 一键重新授权 (invalid_grant 账号): 打开浏览器并预选该账号邮箱
+#[tauri::command]
+pub async fn reauthorize_account(app_handle: tauri::AppHandle, account_id: String) -> Result<Account, String> {
+    modules::logger::log_info(&format!("重新授权账号: {}", account_id));
+    let service = modules::account_service::AccountService::new(
+        crate::modules::integration::SystemManager::Desktop(app_handle.clone()),
+    );
+
+    let mut account = service.reauthorize_account(&account_id).await?;
+
+    let _ = internal_refresh_account_quota(&app_handle, &mut account).await;
+    let _ = crate::commands::proxy::reload_proxy_accounts(
+        app_handle.state::<crate::commands::proxy::ProxyServiceState>(),
+    )
+    .await;
+
+    Ok(account)
+}
+
+/// 批量登录: 按顺序为每个邮箱打开授权页面，进度通过 login-queue://progress 事件推送
+#[tauri::command]
+pub async fn start_login_queue(
+    app_handle: tauri::AppHandle,
+    emails: Vec<String>,
+    oauth_client_key: Option<String>,
+) -> Result<modules::login_queue::LoginQueueProgress, String> {
+    modules::login_queue::start(app_handle, emails, oauth_client_key)
+}
+
+/// 批量登录: 重试失败 / 中断的条目
+#[tauri::command]
+pub async fn resume_login_queue(
+    app_handle: tauri::AppHandle,
+) -> Result<modules::login_queue::LoginQueueProgress, String> {
+    modules::login_queue::resume(app_handle)
+}
+
+#[tauri::command]
+pub async fn cancel_login_queue() -> Result<(), String> {
+    modules::login_queue::cancel();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_login_queue() -> Result<(), String> {
+    modules::login_queue::clear()
+}
+
+#[tauri::command]
+pub async fn get_login_queue_status() -> Result<modules::login_queue::LoginQueueProgress, String> {
+    Ok(modules::login_queue::get_status())
+}
+
 #[tauri::command]
 pub async fn list_oauth_clients() -> Result<Vec<crate::modules::oauth::OAuthClientDescriptor>, String> {
     crate::modules::oauth::list_oauth_clients()
@@ -629,6 +924,34 @@ pub async fn clear_log_cache() -> Result<(), String> {
     modules::logger::clear_logs()
 }
 
+/// 分页查询日志文件 (新的在前)，支持最低级别、模块前缀与子串过滤
+/// 实时日志仍通过 log-event 事件推送 (调试控制台)
+#[tauri::command]
+pub async fn query_logs(
+    level: Option<String>,
+    search: Option<String>,
+    module: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<modules::logger::LogPage, String> {
+    let query = modules::logger::LogQuery {
+        level,
+        search,
+        module,
+        offset,
+        limit,
+    };
+    tokio::task::spawn_blocking(move || modules::logger::query_logs(&query))
+        .await
+        .map_err(|e| format!("Log query task failed: {}", e))?
+}
+
+/// 列出日志文件
+#[tauri::command]
+pub async fn list_log_files() -> Result<Vec<modules::logger::LogFileInfo>, String> {
+    modules::logger::list_log_files()
+}
+
 /// 清理 Antigravity 应用缓存
 /// 用于解决登录失败、版本验证错误等问题
 #[tauri::command]
@@ -727,6 +1050,27 @@ pub async fn get_antigravity_path(bypass_config: Option<bool>) -> Result<String,
     }
 }
 
+/// 检测已安装的 Antigravity 版本，可选与已知最新版本比较 (默认比较)
+#[tauri::command]
+pub async fn check_antigravity_version(
+    check_latest: Option<bool>,
+) -> Result<crate::modules::version::AntigravityVersionReport, String> {
+    // 与 get_antigravity_path 一致：优先使用配置的路径，否则实时探测
+    let configured = crate::modules::config::load_app_config()
+        .ok()
+        .and_then(|config| config.antigravity_executable)
+        .map(std::path::PathBuf::from)
+        .filter(|path| path.exists());
+
+    tokio::task::spawn_blocking(move || {
+        let exe_path = configured.or_else(crate::modules::process::get_antigravity_executable_path);
+        let latest = (check_latest != Some(false)).then(crate::constants::latest_antigravity_version);
+        crate::modules::version::build_version_report(exe_path, latest)
+    })
+    .await
+    .map_err(|e| format!("Failed to check Antigravity version: {}", e))
+}
+
 /// 获取 Antigravity 启动参数
 #[tauri::command]
 pub async fn get_antigravity_args() -> Result<Vec<String>, String> {
@@ -805,40 +1149,30 @@ pub async fn toggle_proxy_status(
     ));
 
     // 1. 读取账号文件
-    let data_dir = modules::account::get_data_dir()?;
-    let account_path = data_dir
-        .join("accounts")
-        .join(format!("{}.json", account_id));
+    let accounts_dir = modules::account::get_accounts_dir()?;
 
-    if !account_path.exists() {
+    if !modules::account_db::account_exists(&accounts_dir, &account_id) {
         return Err(format!("账号文件不存在: {}", account_id));
     }
 
-    let content =
-        std::fs::read_to_string(&account_path).map_err(|e| format!("读取账号文件失败: {}", e))?;
-
-    let mut account_json: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("解析账号文件失败: {}", e))?;
-
-    // 2. 更新 proxy_disabled 字段
-    if enable {
-        // 启用反代
-        account_json["proxy_disabled"] = serde_json::Value::Bool(false);
-        account_json["proxy_disabled_reason"] = serde_json::Value::Null;
-        account_json["proxy_disabled_at"] = serde_json::Value::Null;
-    } else {
-        // 禁用反代
-        let now = chrono::Utc::now().timestamp();
-        account_json["proxy_disabled"] = serde_json::Value::Bool(true);
-        account_json["proxy_disabled_at"] = serde_json::Value::Number(now.into());
-        account_json["proxy_disabled_reason"] =
-            serde_json::Value::String(reason.unwrap_or_else(|| "用户手动禁用".to_string()));
-    }
-
-    // 3. 保存到磁盘
-    let json_str = serde_json::to_string_pretty(&account_json)
-        .map_err(|e| format!("序列化账号数据失败: {}", e))?;
-    std::fs::write(&account_path, json_str).map_err(|e| format!("写入账号文件失败: {}", e))?;
+    // 2. 更新 proxy_disabled 字段 (事务内读-改-写，避免覆盖并发的 Token 刷新)
+    modules::account_db::update_account(&accounts_dir, &account_id, |account_json| {
+        if enable {
+            // 启用反代
+            account_json["proxy_disabled"] = serde_json::Value::Bool(false);
+            account_json["proxy_disabled_reason"] = serde_json::Value::Null;
+            account_json["proxy_disabled_at"] = serde_json::Value::Null;
+        } else {
+            // 禁用反代
+            let now = chrono::Utc::now().timestamp();
+            account_json["proxy_disabled"] = serde_json::Value::Bool(true);
+            account_json["proxy_disabled_at"] = serde_json::Value::Number(now.into());
+            account_json["proxy_disabled_reason"] =
+                serde_json::Value::String(reason.unwrap_or_else(|| "用户手动禁用".to_string()));
+        }
+        Ok(())
+    })
+    .map_err(|e| format!("写入账号文件失败: {}", e))?;
 
     modules::logger::log_info(&format!(
         "账号反代状态已更新: {} ({})",
@@ -885,6 +1219,26 @@ pub async fn warm_up_all_accounts() -> Result<String, String> {
     modules::quota::warm_up_all_accounts().await
 }
 
+/// 获取后台配额刷新状态 (最近成功/失败时间)
+#[tauri::command]
+pub async fn get_quota_refresh_status() -> Result<Vec<modules::scheduler::QuotaRefreshStatus>, String> {
+    Ok(modules::scheduler::get_quota_refresh_status())
+}
+
+/// 测试配额告警 Webhook (未传入 URL 时使用已保存的配置)
+#[tauri::command]
+pub async fn test_quota_webhook(webhook_url: Option<String>) -> Result<(), String> {
+    let url = match webhook_url.filter(|u| !u.trim().is_empty()) {
+        Some(url) => url,
+        None => modules::config::load_app_config()?
+            .quota_alert
+            .webhook_url
+            .filter(|u| !u.trim().is_empty())
+            .ok_or_else(|| "Webhook URL is not configured".to_string())?,
+    };
+    modules::quota::test_quota_webhook(&url).await
+}
+
 /// 预热指定账号
 #[tauri::command]
 pub async fn warm_up_account(account_id: String) -> Result<String, String> {
@@ -906,32 +1260,22 @@ pub async fn update_account_label(account_id: String, label: String) -> Result<(
     ));
 
     // 1. 读取账号文件
-    let data_dir = modules::account::get_data_dir()?;
-    let account_path = data_dir
-        .join("accounts")
-        .join(format!("{}.json", account_id));
+    let accounts_dir = modules::account::get_accounts_dir()?;
 
-    if !account_path.exists() {
+    if !modules::account_db::account_exists(&accounts_dir, &account_id) {
         return Err(format!("账号文件不存在: {}", account_id));
     }
 
-    let content =
-        std::fs::read_to_string(&account_path).map_err(|e| format!("读取账号文件失败: {}", e))?;
-
-    let mut account_json: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("解析账号文件失败: {}", e))?;
-
-    // 2. 更新 custom_label 字段
-    if label.is_empty() {
-        account_json["custom_label"] = serde_json::Value::Null;
-    } else {
-        account_json["custom_label"] = serde_json::Value::String(label.clone());
-    }
-
-    // 3. 保存到磁盘
-    let json_str = serde_json::to_string_pretty(&account_json)
-        .map_err(|e| format!("序列化账号数据失败: {}", e))?;
-    std::fs::write(&account_path, json_str).map_err(|e| format!("写入账号文件失败: {}", e))?;
+    // 2. 更新 custom_label 字段并保存
+    modules::account_db::update_account(&accounts_dir, &account_id, |account_json| {
+        if label.is_empty() {
+            account_json["custom_label"] = serde_json::Value::Null;
+        } else {
+            account_json["custom_label"] = serde_json::Value::String(label.clone());
+        }
+        Ok(())
+    })
+    .map_err(|e| format!("写入账号文件失败: {}", e))?;
 
     modules::logger::log_info(&format!(
         "账号标签已更新: {} ({})",
@@ -946,6 +1290,99 @@ pub async fn update_account_label(account_id: String, label: String) -> Result<(
     Ok(())
 }
 
+/// 设置账号专属上游代理 (None 表示清除，改用代理池/全局上游代理)
+#[tauri::command]
+pub async fn update_account_upstream_proxy(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+    upstream_proxy: Option<crate::proxy::config::UpstreamProxyConfig>,
+) -> Result<(), String> {
+    let accounts_dir = modules::account::get_accounts_dir()?;
+
+    if !modules::account_db::account_exists(&accounts_dir, &account_id) {
+        return Err(format!("账号文件不存在: {}", account_id));
+    }
+
+    modules::account_db::update_account(&accounts_dir, &account_id, |account_json| {
+        match &upstream_proxy {
+            Some(proxy) => {
+                account_json["upstream_proxy"] =
+                    serde_json::to_value(proxy).map_err(|e| format!("序列化代理配置失败: {}", e))?;
+            }
+            None => {
+                if let Some(obj) = account_json.as_object_mut() {
+                    obj.remove("upstream_proxy");
+                }
+            }
+        }
+        Ok(())
+    })
+    .map_err(|e| format!("写入账号文件失败: {}", e))?;
+
+    // 同步到运行中的反代服务（如果已启动），绑定随账号数据重新加载
+    let instance_lock = proxy_state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        let _ = instance.token_manager.reload_account(&account_id).await;
+    }
+
+    modules::logger::log_info(&format!(
+        "账号专属上游代理已更新: {} -> {}",
+        account_id,
+        upstream_proxy
+            .as_ref()
+            .filter(|p| p.enabled)
+            .map(|p| p.url.as_str())
+            .unwrap_or("未设置")
+    ));
+
+    Ok(())
+}
+
+/// 设置账号专属的 Antigravity 启动参数 (如 --user-data-dir)，切换到该账号时生效
+#[tauri::command]
+pub async fn update_account_launch_args(
+    account_id: String,
+    launch_args: Option<Vec<String>>,
+) -> Result<(), String> {
+    let accounts_dir = modules::account::get_accounts_dir()?;
+
+    if !modules::account_db::account_exists(&accounts_dir, &account_id) {
+        return Err(format!("账号文件不存在: {}", account_id));
+    }
+
+    // 去除空白参数，空列表视为清除
+    let launch_args = launch_args
+        .map(|args| {
+            args.into_iter()
+                .map(|arg| arg.trim().to_string())
+                .filter(|arg| !arg.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|args| !args.is_empty());
+
+    modules::account_db::update_account(&accounts_dir, &account_id, |account_json| {
+        match &launch_args {
+            Some(args) => {
+                account_json["launch_args"] = serde_json::json!(args);
+            }
+            None => {
+                if let Some(obj) = account_json.as_object_mut() {
+                    obj.remove("launch_args");
+                }
+            }
+        }
+        Ok(())
+    })
+    .map_err(|e| format!("写入账号文件失败: {}", e))?;
+
+    modules::logger::log_info(&format!(
+        "账号启动参数已更新: {} -> {:?}",
+        account_id, launch_args
+    ));
+
+    Ok(())
+}
+
 // ============================================================================
 // HTTP API 设置命令
 // ============================================================================
@@ -1023,9 +1460,68 @@ pub async fn get_token_stats_account_trend_hourly(
     crate::modules::token_stats::get_account_trend_hourly(hours)
 }
 
+/// 按账号/模型汇总的用量 (最近 days 天，含思考 token)
+#[tauri::command]
+pub async fn get_token_usage_totals(
+    days: i64,
+    account_email: Option<String>,
+) -> Result<Vec<crate::modules::token_stats::AccountModelUsage>, String> {
+    crate::modules::token_stats::get_usage_totals(days, account_email.as_deref())
+}
+
+/// 重置用量计数 (days 为空表示全部周期)
+#[tauri::command]
+pub async fn reset_token_usage(
+    days: Option<i64>,
+    account_email: Option<String>,
+) -> Result<usize, String> {
+    let removed = crate::modules::token_stats::reset_usage(days, account_email.as_deref())?;
+    modules::logger::log_info(&format!(
+        "已重置用量计数: {} 条 (账号: {}, 周期: {:?} 天)",
+        removed,
+        account_email.as_deref().unwrap_or("全部"),
+        days
+    ));
+    Ok(removed)
+}
+
 #[tauri::command]
 pub async fn get_token_stats_account_trend_daily(
     days: i64,
 ) -> Result<Vec<crate::modules::token_stats::AccountTrendPoint>, String> {
     crate::modules::token_stats::get_account_trend_daily(days)
 }
+
+// ============================================================================
+// Quota History Commands
+// ============================================================================
+
+/// 配额快照时间序列 (可按账号 / 模型分组过滤)，附带识别出的重置事件
+#[tauri::command]
+pub async fn get_quota_history(
+    hours: i64,
+    account_id: Option<String>,
+    model_group: Option<String>,
+) -> Result<crate::modules::quota_history::QuotaHistory, String> {
+    crate::modules::quota_history::get_quota_history(
+        hours,
+        account_id.as_deref(),
+        model_group.as_deref(),
+    )
+}
+
+/// 预测配额重置倒计时 (按重置时间升序)
+#[tauri::command]
+pub async fn get_quota_reset_predictions(
+    account_id: Option<String>,
+) -> Result<Vec<crate::modules::quota_history::QuotaResetPrediction>, String> {
+    crate::modules::quota_history::predict_resets(account_id.as_deref())
+}
+
+/// 各模型分组每日平均剩余配额
+#[tauri::command]
+pub async fn get_quota_trend_daily(
+    days: i64,
+) -> Result<Vec<crate::modules::quota_history::QuotaTrendPoint>, String> {
+    crate::modules::quota_history::get_quota_trend_daily(days)
+}
@@ -3,7 +3,7 @@ use crate::proxy::{ProxyConfig, ProxyPoolConfig, TokenManager};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::State;
+use tauri::{Emitter, State};
 use tokio::sync::RwLock;
 use tokio::time::Duration;
 
@@ -16,6 +16,24 @@ pub struct ProxyStatus {
     pub active_accounts: usize,
 }
 
+/// [NEW] 反代服务启停后推送给前端与托盘的状态事件 (payload: ProxyStatus)
+pub const PROXY_STATUS_EVENT: &str = "proxy://status";
+
+/// [NEW] 广播反代服务状态，命令与托盘启停后统一调用
+pub fn emit_proxy_status(app_handle: &tauri::AppHandle, status: &ProxyStatus) {
+    let _ = app_handle.emit(PROXY_STATUS_EVENT, status);
+}
+
+/// 停止后的状态 (与 get_proxy_status 未运行时一致)
+pub fn stopped_proxy_status() -> ProxyStatus {
+    ProxyStatus {
+        running: false,
+        port: 0,
+        base_url: String::new(),
+        active_accounts: 0,
+    }
+}
+
 /// 反代服务全局状态
 #[derive(Clone)]
 pub struct ProxyServiceState {
@@ -27,7 +45,6 @@ pub struct ProxyServiceState {
 
 pub struct AdminServerInstance {
     pub axum_server: crate::proxy::AxumServer,
-    #[allow(dead_code)] // 保留句柄以便未来支持显式停服/诊断
     pub server_handle: tokio::task::JoinHandle<()>,
 }
 
@@ -59,13 +76,15 @@ pub async fn start_proxy_service(
     cf_state: State<'_, crate::commands::cloudflared::CloudflaredState>,
     app_handle: tauri::AppHandle,
 ) -> Result<ProxyStatus, String> {
-    internal_start_proxy_service(
+    let status = internal_start_proxy_service(
         config,
         &state,
-        crate::modules::integration::SystemManager::Desktop(app_handle),
+        crate::modules::integration::SystemManager::Desktop(app_handle.clone()),
         Arc::new(cf_state.inner().clone()),
     )
-    .await
+    .await?;
+    emit_proxy_status(&app_handle, &status);
+    Ok(status)
 }
 
 struct StartingGuard(Arc<AtomicBool>);
@@ -90,6 +109,14 @@ pub async fn internal_start_proxy_service(
         }
     }
 
+    // [NEW] 开放局域网访问时必须配置 API Key
+    if config.allow_lan_access && config.api_key.trim().is_empty() {
+        return Err("开启局域网访问时必须设置 API Key".to_string());
+    }
+    if config.allow_lan_access && matches!(config.auth_mode, crate::proxy::ProxyAuthMode::Off) {
+        tracing::warn!("局域网访问已开启，auth_mode=off 将被强制提升为 all_except_health");
+    }
+
     // 2. 检查是否正在启动中 (防止死锁 & 并发启动)
     if state
         .starting
@@ -147,6 +174,18 @@ pub async fn internal_start_proxy_service(
     token_manager
         .update_sticky_config(config.scheduling.clone())
         .await;
+    token_manager
+        .update_usage_limits(config.usage_limits.clone())
+        .await;
+    token_manager
+        .update_account_groups(config.account_groups.clone())
+        .await;
+    token_manager
+        .update_auto_disable_config(config.auto_disable.clone())
+        .await;
+    token_manager
+        .update_account_subset(&config.account_subset)
+        .await;
 
     // [NEW] 加载熔断配置 (从主配置加载)
     let app_config = crate::modules::config::load_app_config()
@@ -165,6 +204,8 @@ pub async fn internal_start_proxy_service(
 
     // 3. 加載賬號
     let active_accounts = token_manager.load_accounts().await.unwrap_or(0);
+    // [NEW] 恢复上次停止时的冷却 / 会话绑定，避免重启后立即命中已知限流账号
+    token_manager.restore_runtime_state();
 
     if active_accounts == 0 {
         let zai_enabled = config.zai.enabled
@@ -174,7 +215,7 @@ pub async fn internal_start_proxy_service(
             return Ok(ProxyStatus {
                 running: false,
                 port: config.port,
-                base_url: format!("http://127.0.0.1:{}", config.port),
+                base_url: format!("{}://127.0.0.1:{}", config.get_scheme(), config.port),
                 active_accounts: 0,
             });
         }
@@ -202,7 +243,7 @@ pub async fn internal_start_proxy_service(
     Ok(ProxyStatus {
         running: true,
         port: config.port,
-        base_url: format!("http://127.0.0.1:{}", config.port),
+        base_url: format!("{}://127.0.0.1:{}", config.get_scheme(), config.port),
         active_accounts,
     })
 }
@@ -246,6 +287,9 @@ pub async fn ensure_admin_server(
         token_manager,
         config.custom_mapping.clone(),
         config.request_timeout,
+        config.max_body_size_mb,
+        config.tls.clone(),
+        config.cors.clone(),
         config.upstream_proxy.clone(),
         config.user_agent_override.clone(),
         crate::proxy::ProxySecurityConfig::from_proxy_config(&config),
@@ -262,6 +306,10 @@ pub async fn ensure_admin_server(
         Ok((server, handle)) => (server, handle),
         Err(e) => return Err(format!("启动管理服务器失败: {}", e)),
     };
+    // [NEW] 初始化上游端点 (自定义主端点 / 备用端点)
+    axum_server.update_upstream_endpoints(&config);
+    axum_server.update_upstream_client(&config);
+    axum_server.update_user_agent(&config).await;
 
     *admin_lock = Some(AdminServerInstance {
         axum_server,
@@ -274,13 +322,36 @@ pub async fn ensure_admin_server(
     crate::proxy::update_global_system_prompt_config(config.global_system_prompt.clone());
     // [NEW] 初始化全局图像思维模式配置
     crate::proxy::update_image_thinking_mode(config.image_thinking_mode.clone());
+    // [NEW] 初始化上下文缓存模型绑定
+    crate::proxy::context_cache::update_bindings(config.context_cache.bindings.clone());
+    crate::proxy::common::model_mapping::update_mapping_rules(&config.model_mapping_rules);
+    crate::proxy::common::model_mapping::update_quota_group_overrides(&config.quota_group_overrides);
+    crate::proxy::middleware::client_rate_limit::update_client_rate_limit_config(
+        config.client_rate_limit.clone(),
+    );
+    crate::proxy::upstream::retry::update_retry_policy_config(config.retry_policy.clone());
+    crate::proxy::safety_settings::update_safety_settings_config(config.safety_settings.clone());
+    crate::proxy::mcp_bridge::update_mcp_bridge_config(config.mcp_bridge.clone());
+    crate::proxy::common::context_trim::update_context_trim_config(config.context_trim.clone());
+    crate::proxy::redaction::update_redaction_config(config.redaction.clone());
+    crate::proxy::common::system_prompt::update_system_prompt_injection_config(config.system_prompt_injection.clone());
 
     Ok(())
 }
 
 /// 停止反代服务
 #[tauri::command]
-pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(), String> {
+pub async fn stop_proxy_service(
+    state: State<'_, ProxyServiceState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    internal_stop_proxy_service(&state).await?;
+    emit_proxy_status(&app_handle, &stopped_proxy_status());
+    Ok(())
+}
+
+/// 内部停止反代服务逻辑 (供托盘等非命令入口复用)
+pub async fn internal_stop_proxy_service(state: &ProxyServiceState) -> Result<(), String> {
     let mut instance_lock = state.instance.write().await;
 
     if instance_lock.is_none() {
@@ -289,6 +360,7 @@ pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(
 
     // 停止 Axum 服务器 (仅逻辑停止，不杀死进程)
     if let Some(instance) = instance_lock.take() {
+        instance.token_manager.save_runtime_state();
         instance.token_manager.abort_background_tasks().await;
         instance.axum_server.set_running(false).await;
         // 已移除 instance.axum_server.stop() 调用，防止杀死 Admin Server
@@ -297,6 +369,102 @@ pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(
     Ok(())
 }
 
+/// 重启反代服务 (Tauri 命令)
+/// 会重新绑定监听地址与端口，存量请求在宽限期内排空
+#[tauri::command]
+pub async fn restart_proxy_service(
+    config: ProxyConfig,
+    state: State<'_, ProxyServiceState>,
+    cf_state: State<'_, crate::commands::cloudflared::CloudflaredState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ProxyStatus, String> {
+    let status = internal_restart_proxy_service(
+        config,
+        &state,
+        crate::modules::integration::SystemManager::Desktop(app_handle.clone()),
+        Arc::new(cf_state.inner().clone()),
+    )
+    .await?;
+    emit_proxy_status(&app_handle, &status);
+    Ok(status)
+}
+
+/// 将反代服务改绑到新的端口 / 监听地址 (Tauri 命令)
+/// 新设置会持久化到配置文件
+#[tauri::command]
+pub async fn rebind_proxy_service(
+    port: u16,
+    allow_lan_access: Option<bool>,
+    state: State<'_, ProxyServiceState>,
+    cf_state: State<'_, crate::commands::cloudflared::CloudflaredState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ProxyStatus, String> {
+    if port == 0 {
+        return Err("端口无效".to_string());
+    }
+
+    let mut app_config = crate::modules::config::load_app_config()?;
+    app_config.proxy.port = port;
+    if let Some(allow_lan) = allow_lan_access {
+        app_config.proxy.allow_lan_access = allow_lan;
+    }
+    crate::modules::config::save_app_config(&app_config)?;
+
+    let status = internal_restart_proxy_service(
+        app_config.proxy,
+        &state,
+        crate::modules::integration::SystemManager::Desktop(app_handle.clone()),
+        Arc::new(cf_state.inner().clone()),
+    )
+    .await?;
+    emit_proxy_status(&app_handle, &status);
+    Ok(status)
+}
+
+/// 内部重启逻辑: 逻辑停止 -> 优雅关闭监听 (排空存量连接) -> 按新配置重新启动
+pub async fn internal_restart_proxy_service(
+    config: ProxyConfig,
+    state: &ProxyServiceState,
+    integration: crate::modules::integration::SystemManager,
+    cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>,
+) -> Result<ProxyStatus, String> {
+    if state.starting.load(Ordering::SeqCst) {
+        return Err("服务正在启动中，请稍候...".to_string());
+    }
+
+    // 1. 逻辑停止
+    let was_running = {
+        let mut instance_lock = state.instance.write().await;
+        if let Some(instance) = instance_lock.take() {
+            instance.token_manager.save_runtime_state();
+            instance.token_manager.abort_background_tasks().await;
+            instance.axum_server.set_running(false).await;
+            true
+        } else {
+            false
+        }
+    };
+
+    // 2. 关闭监听并排空存量连接，释放旧端口
+    let admin = state.admin_server.write().await.take();
+    if let Some(admin) = admin {
+        let grace = Duration::from_secs(config.shutdown_grace_secs);
+        tracing::info!(
+            "正在重启反代服务 (was_running: {}, 宽限期: {}s)",
+            was_running,
+            config.shutdown_grace_secs
+        );
+        let remaining = admin.axum_server.shutdown_gracefully(grace).await;
+        if remaining > 0 {
+            tracing::warn!("{} 个连接在宽限期后仍未完成，已强制关闭", remaining);
+        }
+        admin.server_handle.abort();
+    }
+
+    // 3. 按新配置启动 (会重建管理服务器并绑定新的地址与端口)
+    internal_start_proxy_service(config, state, integration, cloudflared_state).await
+}
+
 /// 获取反代服务状态
 #[tauri::command]
 pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<ProxyStatus, String> {
@@ -318,15 +486,10 @@ pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<Pro
             Some(instance) => Ok(ProxyStatus {
                 running: true,
                 port: instance.config.port,
-                base_url: format!("http://127.0.0.1:{}", instance.config.port),
+                base_url: format!("{}://127.0.0.1:{}", instance.config.get_scheme(), instance.config.port),
                 active_accounts: instance.token_manager.len(),
             }),
-            None => Ok(ProxyStatus {
-                running: false,
-                port: 0,
-                base_url: String::new(),
-                active_accounts: 0,
-            }),
+            None => Ok(stopped_proxy_status()),
         },
         Err(_) => {
             // 如果拿不到锁，说明正在进行写操作（可能是正在启动或停止中）
@@ -340,6 +503,22 @@ pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<Pro
     }
 }
 
+/// 对运行中的反代服务执行压测
+#[tauri::command]
+pub async fn run_proxy_benchmark(
+    state: State<'_, ProxyServiceState>,
+    request: crate::proxy::benchmark::BenchmarkRequest,
+) -> Result<crate::proxy::benchmark::BenchmarkReport, String> {
+    let config = {
+        let instance_lock = state.instance.read().await;
+        match instance_lock.as_ref() {
+            Some(instance) => instance.config.clone(),
+            None => return Err("服务未运行".to_string()),
+        }
+    };
+    crate::proxy::benchmark::run_benchmark(&config, &request).await
+}
+
 /// 获取反代服务统计
 #[tauri::command]
 pub async fn get_proxy_stats(state: State<'_, ProxyServiceState>) -> Result<ProxyStats, String> {
@@ -365,6 +544,43 @@ pub async fn get_proxy_logs(
     }
 }
 
+/// 获取最近的实时活动事件 (活动面板打开时补齐，之后通过 proxy://activity 事件增量推送)
+#[tauri::command]
+pub async fn get_proxy_activity(
+    limit: Option<usize>,
+) -> Result<Vec<crate::proxy::activity::ProxyActivity>, String> {
+    Ok(crate::proxy::activity::recent_events(limit.unwrap_or(100)))
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ClientVersionInfo {
+    /// 本地安装的 Antigravity 版本 (未安装或探测失败时为空)
+    pub installed_version: Option<String>,
+    /// 自动解析的版本 max(本地, 远程, 内置稳定版)
+    pub resolved_version: String,
+    /// 按当前配置实际上报的版本
+    pub effective_version: String,
+    /// 按当前配置生成的默认 User-Agent (未设置覆盖时使用)
+    pub default_user_agent: String,
+}
+
+/// 获取客户端版本 / User-Agent 指纹信息 (探测本地安装与远程版本，首次调用可能耗时数秒)
+#[tauri::command]
+pub async fn get_client_version_info() -> Result<ClientVersionInfo, String> {
+    let config = crate::modules::config::load_app_config()?.proxy.client_version;
+    tokio::task::spawn_blocking(move || {
+        let effective_version = crate::constants::resolve_client_version(&config);
+        ClientVersionInfo {
+            installed_version: crate::constants::INSTALLED_VERSION.clone(),
+            resolved_version: crate::constants::CURRENT_VERSION.clone(),
+            default_user_agent: crate::constants::build_user_agent(&effective_version),
+            effective_version,
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to resolve client version: {}", e))
+}
+
 /// 设置监控开启状态
 #[tauri::command]
 pub async fn set_proxy_monitor_enabled(
@@ -403,6 +619,21 @@ pub async fn get_proxy_log_detail(log_id: String) -> Result<ProxyRequestLog, Str
     crate::modules::proxy_db::get_log_detail(&log_id)
 }
 
+/// 按请求 ID 重放抓包中的 v1internal 请求，并与记录的响应对比 (需开启调试日志)
+#[tauri::command]
+pub async fn replay_captured_request(
+    trace_id: String,
+    account_id: Option<String>,
+    attempt: Option<u64>,
+) -> Result<crate::proxy::replay::ReplayResult, String> {
+    crate::proxy::replay::replay_captured_request(&crate::proxy::replay::ReplayRequest {
+        trace_id,
+        account_id,
+        attempt,
+    })
+    .await
+}
+
 /// 获取日志总数
 #[tauri::command]
 pub async fn get_proxy_logs_count() -> Result<u64, String> {
@@ -512,6 +743,35 @@ pub async fn update_model_mapping(
     Ok(())
 }
 
+/// 列出通配符 / 正则映射规则
+#[tauri::command]
+pub async fn list_model_mapping_rules() -> Result<Vec<crate::proxy::config::ModelMappingRule>, String> {
+    crate::proxy::common::model_mapping::list_mapping_rules()
+}
+
+/// 新增或更新映射规则 (id 为空时新增)
+#[tauri::command]
+pub async fn save_model_mapping_rule(
+    rule: crate::proxy::config::ModelMappingRule,
+) -> Result<Vec<crate::proxy::config::ModelMappingRule>, String> {
+    crate::proxy::common::model_mapping::save_mapping_rule(rule)
+}
+
+#[tauri::command]
+pub async fn delete_model_mapping_rule(
+    rule_id: String,
+) -> Result<Vec<crate::proxy::config::ModelMappingRule>, String> {
+    crate::proxy::common::model_mapping::delete_mapping_rule(&rule_id)
+}
+
+/// 试算模型路由 (返回命中的映射方式与规则)
+#[tauri::command]
+pub async fn resolve_model_mapping(
+    model: String,
+) -> Result<crate::proxy::common::model_mapping::ModelRouteResolution, String> {
+    crate::proxy::common::model_mapping::dry_run_model_route(&model)
+}
+
 fn join_base_url(base: &str, path: &str) -> String {
     let base = base.trim_end_matches('/');
     let path = if path.starts_with('/') {
@@ -728,6 +988,43 @@ pub async fn get_preferred_account(
     }
 }
 
+/// [NEW] 调试锁定：所有请求固定走指定账号 (账号 ID 或邮箱)，不回退到轮询
+#[tauri::command]
+pub async fn pin_proxy_account(
+    state: State<'_, ProxyServiceState>,
+    account_id: String,
+) -> Result<String, String> {
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        instance.token_manager.pin_account(account_id.trim()).await
+    } else {
+        Err("服务未运行".to_string())
+    }
+}
+
+/// [NEW] 解除调试锁定
+#[tauri::command]
+pub async fn unpin_proxy_account(state: State<'_, ProxyServiceState>) -> Result<(), String> {
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        instance.token_manager.unpin_account().await;
+    }
+    Ok(())
+}
+
+/// [NEW] 获取当前调试锁定的账号 ID
+#[tauri::command]
+pub async fn get_pinned_proxy_account(
+    state: State<'_, ProxyServiceState>,
+) -> Result<Option<String>, String> {
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        Ok(instance.token_manager.get_pinned_account().await)
+    } else {
+        Ok(None)
+    }
+}
+
 /// 清除指定账号的限流记录
 #[tauri::command]
 pub async fn clear_proxy_rate_limit(
@@ -742,6 +1039,19 @@ pub async fn clear_proxy_rate_limit(
     }
 }
 
+/// [NEW] 获取各账号的运行指标 (成功率、连续失败、平均耗时、最近使用时间)
+#[tauri::command]
+pub async fn get_account_metrics(
+    state: State<'_, ProxyServiceState>,
+) -> Result<Vec<crate::proxy::token_manager::AccountMetrics>, String> {
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        Ok(instance.token_manager.get_account_metrics())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
 /// 清除所有限流记录
 #[tauri::command]
 pub async fn clear_all_proxy_rate_limits(
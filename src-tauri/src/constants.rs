@@ -125,29 +125,24 @@ fn resolve_version_config() -> (VersionConfig, VersionSource) {
     let mut source = VersionSource::KnownStableFallback;
 
     // 1. Try Local Installation
-    if let Ok(local_ver) = crate::modules::version::get_antigravity_version() {
-        let local_parsed = parse_version(&local_ver.short_version)
-            .or_else(|| parse_version(&local_ver.bundle_version));
-
-        if let Some(local_v) = local_parsed {
-            if compare_semver(&local_v, &best_version) > std::cmp::Ordering::Equal {
-                // Local is newer than the floor — use it
-                tracing::debug!(
-                    local_version = %local_v,
-                    "Local installation version is newer than known-stable floor; using local"
-                );
-                best_version = local_v;
-                source = VersionSource::LocalInstallation;
-            } else {
-                // Local is older than or equal to the floor (e.g. user hasn't updated yet)
-                tracing::info!(
-                    local_version = %local_v,
-                    floor_version = %best_version,
-                    "Local Antigravity version is older than known-stable floor; \
-                     using floor to avoid upstream model rejection"
-                );
-                // source stays KnownStableFallback — the local version is intentionally ignored
-            }
+    if let Some(local_v) = INSTALLED_VERSION.clone() {
+        if compare_semver(&local_v, &best_version) > std::cmp::Ordering::Equal {
+            // Local is newer than the floor — use it
+            tracing::debug!(
+                local_version = %local_v,
+                "Local installation version is newer than known-stable floor; using local"
+            );
+            best_version = local_v;
+            source = VersionSource::LocalInstallation;
+        } else {
+            // Local is older than or equal to the floor (e.g. user hasn't updated yet)
+            tracing::info!(
+                local_version = %local_v,
+                floor_version = %best_version,
+                "Local Antigravity version is older than known-stable floor; \
+                 using floor to avoid upstream model rejection"
+            );
+            // source stays KnownStableFallback — the local version is intentionally ignored
         }
     }
 
@@ -174,12 +169,35 @@ fn resolve_version_config() -> (VersionConfig, VersionSource) {
     )
 }
 
+/// 版本解析只执行一次 (本地探测 + 远程查询)，CURRENT_VERSION 与 USER_AGENT 共用同一结果
+static RESOLVED_VERSION: LazyLock<(VersionConfig, VersionSource)> = LazyLock::new(|| {
+    let (config, source) = resolve_version_config();
+    tracing::info!(
+        version = %config.version,
+        source = ?source,
+        "Client version resolved"
+    );
+    (config, source)
+});
+
+/// Locally installed Antigravity version, probed once via the executable path
+pub static INSTALLED_VERSION: LazyLock<Option<String>> = LazyLock::new(|| {
+    let local_ver = crate::modules::version::get_antigravity_version().ok()?;
+    parse_version(&local_ver.short_version).or_else(|| parse_version(&local_ver.bundle_version))
+});
+
+/// Latest known Antigravity release: max(remote latest, known stable).
+/// Performs a network request (up to ~6s); call from a blocking context.
+pub fn latest_antigravity_version() -> String {
+    match try_fetch_remote_version() {
+        Some(remote) if compare_semver(&remote, KNOWN_STABLE_VERSION) > std::cmp::Ordering::Equal => remote,
+        _ => KNOWN_STABLE_VERSION.to_string(),
+    }
+}
+
 /// Current resolved Antigravity version (e.g., "4.1.31")
 /// Always >= KNOWN_STABLE_VERSION, and >= remote latest when reachable.
-pub static CURRENT_VERSION: LazyLock<String> = LazyLock::new(|| {
-    let (config, _) = resolve_version_config();
-    config.version
-});
+pub static CURRENT_VERSION: LazyLock<String> = LazyLock::new(|| RESOLVED_VERSION.0.version.clone());
 
 /// Native OAuth Authorization User-Agent
 pub static NATIVE_OAUTH_USER_AGENT: LazyLock<String> = LazyLock::new(|| {
@@ -202,33 +220,51 @@ pub static SESSION_ID: LazyLock<String> = LazyLock::new(|| {
     uuid::Uuid::new_v4().to_string()
 });
 
-/// Returns the best version choice between local and remote
-/// Version selection: max(local installation, remote latest, known stable 4.1.31)
-/// This prevents model rejection due to outdated client version headers.
-pub static USER_AGENT: LazyLock<String> = LazyLock::new(|| {
-    let (config, source) = resolve_version_config();
-
-    tracing::info!(
-        version = %config.version,
-        source = ?source,
-        "User-Agent initialized"
-    );
-
-    let platform_info = match std::env::consts::OS {
+/// Platform portion of the User-Agent, derived from the running OS
+pub fn platform_info() -> &'static str {
+    match std::env::consts::OS {
         "macos" => "Macintosh; Intel Mac OS X 10_15_7",
         "windows" => "Windows NT 10.0; Win64; x64",
-        "linux" => "X11; Linux x86_64",
         _ => "X11; Linux x86_64",
-    };
+    }
+}
 
+/// Build the Antigravity User-Agent for a given client version
+pub fn build_user_agent(version: &str) -> String {
+    let config = &RESOLVED_VERSION.0;
     format!(
         "Antigravity/{} ({}) Chrome/{} Electron/{}",
-        config.version,
-        platform_info,
+        version,
+        platform_info(),
         config.chrome,
         config.electron
     )
-});
+}
+
+/// 按配置解析上报给上游的客户端版本
+pub fn resolve_client_version(config: &crate::proxy::config::ClientVersionConfig) -> String {
+    use crate::proxy::config::ClientVersionMode;
+    match config.mode {
+        ClientVersionMode::Auto => CURRENT_VERSION.clone(),
+        ClientVersionMode::Installed => INSTALLED_VERSION.clone().unwrap_or_else(|| {
+            tracing::warn!("Installed Antigravity version not found, falling back to {}", CURRENT_VERSION.as_str());
+            CURRENT_VERSION.clone()
+        }),
+        ClientVersionMode::Fixed => config
+            .version
+            .as_deref()
+            .and_then(parse_version)
+            .unwrap_or_else(|| {
+                tracing::warn!("Invalid fixed client version {:?}, falling back to {}", config.version, CURRENT_VERSION.as_str());
+                CURRENT_VERSION.clone()
+            }),
+    }
+}
+
+/// Returns the best version choice between local and remote
+/// Version selection: max(local installation, remote latest, known stable 4.1.31)
+/// This prevents model rejection due to outdated client version headers.
+pub static USER_AGENT: LazyLock<String> = LazyLock::new(|| build_user_agent(&CURRENT_VERSION));
 
 #[cfg(test)]
 mod tests {
@@ -261,6 +297,16 @@ mod tests {
         assert_eq!(parse_version(text), Some("1.15.8".to_string()));
     }
 
+    #[test]
+    fn test_fixed_client_version_is_normalized() {
+        use crate::proxy::config::{ClientVersionConfig, ClientVersionMode};
+        let config = ClientVersionConfig {
+            mode: ClientVersionMode::Fixed,
+            version: Some("v1.15.8".to_string()),
+        };
+        assert_eq!(resolve_client_version(&config), "1.15.8");
+    }
+
     #[test]
     fn test_compare_semver() {
         assert_eq!(compare_semver("4.1.31", "4.1.22"), std::cmp::Ordering::Greater);
@@ -105,11 +105,125 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// 终端手动粘贴登录: 打印授权链接，读取用户粘贴的回调 URL 并保存账号
+async fn run_cli_add_account() -> Result<String, String> {
+    let service =
+        modules::account_service::AccountService::new(modules::integration::SystemManager::Headless);
+    let start = service.begin_manual_oauth_login(None)?;
+
+    println!("Open this URL in a browser on any device and sign in:\n\n{}\n", start.auth_url);
+    println!(
+        "After authorizing, the browser is redirected to {} (the page will not load).",
+        start.redirect_uri
+    );
+    println!("Copy the full URL from the address bar and paste it here:");
+
+    let input = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| line)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to read input: {}", e))?;
+
+    let account = service
+        .finish_manual_oauth_login(&input, Some(start.state))
+        .await?;
+    Ok(account.email)
+}
+
+const HEADLESS_USAGE: &str = "\
+Usage: antigravity_tools --headless [OPTIONS]
+       antigravity-proxy [OPTIONS]
+
+Runs only the proxy server, token manager and quota scheduler (no window / tray),
+using accounts from the existing data directory.
+
+Options:
+  --port <PORT>        Override the proxy port (same as AGM_PROXY_PORT)
+  --bind <ADDR>        Override the listen address (same as AGM_BIND_ADDR)
+  --data-dir <PATH>    Use a custom data directory (same as ABV_DATA_DIR)
+  --add-account        Add an account by pasting the OAuth redirect URL, then exit
+  -h, --help           Print this help
+
+Other settings can be overridden with AGM_* environment variables.";
+
+/// 解析 headless 命令行参数，映射为对应的环境变量覆盖 (在加载配置前调用)
+fn apply_headless_args(args: &[String]) -> Result<(), String> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((f, v)) => (f, Some(v.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let env_name = match flag {
+            "--port" => "AGM_PROXY_PORT",
+            "--bind" => "AGM_BIND_ADDR",
+            "--data-dir" => "ABV_DATA_DIR",
+            "-h" | "--help" => {
+                println!("{}", HEADLESS_USAGE);
+                std::process::exit(0);
+            }
+            // 启动器自身的模式开关，由 run_with_args 处理
+            "--headless" | "--add-account" if inline.is_none() => continue,
+            _ => {
+                return Err(format!(
+                    "Unrecognized option: {} (run with --help to see supported options)",
+                    arg
+                ))
+            }
+        };
+        let value = inline
+            .or_else(|| iter.next().cloned())
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+        std::env::set_var(env_name, value);
+    }
+    Ok(())
+}
+
+/// 等待退出信号 (Ctrl-C，Unix 下还包括 systemd / docker stop 发送的 SIGTERM)
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Failed to install SIGTERM handler: {}", e),
+        }
+    }
+    tokio::signal::ctrl_c().await.ok();
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    run_with_args(std::env::args().collect());
+}
+
+/// [NEW] 独立 headless 入口 (bin/antigravity-proxy)：不创建窗口 / 托盘，
+/// 仅启动反代服务、Token 管理器与配额调度器，适合在 VPS 上运行
+pub fn run_headless() {
+    let mut args: Vec<String> = std::env::args().collect();
+    if !args.iter().any(|arg| arg == "--headless") {
+        args.push("--headless".to_string());
+    }
+    run_with_args(args);
+}
+
+fn run_with_args(args: Vec<String>) {
     // Check for headless mode
-    let args: Vec<String> = std::env::args().collect();
     let is_headless = args.iter().any(|arg| arg == "--headless");
+    if is_headless {
+        if let Err(e) = apply_headless_args(&args) {
+            eprintln!("{}\n\n{}", e, HEADLESS_USAGE);
+            std::process::exit(2);
+        }
+    }
 
     // Increase file descriptor limit (macOS only)
     #[cfg(target_os = "macos")]
@@ -126,6 +240,11 @@ pub fn run() {
         error!("Failed to initialize token stats database: {}", e);
     }
 
+    // Initialize quota history database
+    if let Err(e) = modules::quota_history::init_db() {
+        error!("Failed to initialize quota history database: {}", e);
+    }
+
     // Initialize security database
     if let Err(e) = modules::security_db::init_db() {
         error!("Failed to initialize security database: {}", e);
@@ -136,6 +255,9 @@ pub fn run() {
         error!("Failed to initialize user token database: {}", e);
     }
 
+    // [NEW] 存储 schema 迁移 (gui_config.json / accounts.json)，迁移前自动备份
+    modules::migration::run_startup_migrations();
+
     if is_headless {
         info!("Starting in HEADLESS mode...");
 
@@ -156,12 +278,37 @@ pub fn run() {
             // And `logger::init_logger()` adds the layer?
             // Let's check `modules::logger`.
 
+            // [NEW] `--headless --add-account`: 终端内完成手动粘贴登录后退出 (SSH / 无浏览器服务器)
+            if args.iter().any(|arg| arg == "--add-account") {
+                let code = match run_cli_add_account().await {
+                    Ok(email) => {
+                        println!("Account added: {}", email);
+                        0
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to add account: {}", e);
+                        1
+                    }
+                };
+                std::process::exit(code);
+            }
+
             let proxy_state = commands::proxy::ProxyServiceState::new();
             let cf_state = Arc::new(commands::cloudflared::CloudflaredState::new());
 
+            // [NEW] 环境变量覆盖 (AGM_PROXY_PORT / AGM_BIND_ADDR / AGM_API_KEY / AGM_UPSTREAM_PROXY ...)
+            // 兼容旧变量：ABV_API_KEY / API_KEY、ABV_WEB_PASSWORD / WEB_PASSWORD、ABV_AUTH_MODE / AUTH_MODE
+            // 仅作用于内存中的配置，移除变量后即恢复 gui_config.json 中的值
+            let env_applied = modules::env_overrides::activate();
+
             // Load config
             match modules::config::load_app_config() {
                 Ok(mut config) => {
+                    if let Err(e) = modules::migration::apply_token_encryption_setting(config.encrypt_tokens_at_rest) {
+                        error!("Failed to apply token encryption setting: {}", e);
+                    }
+                    crate::proxy::common::model_mapping::update_quota_group_overrides(&config.proxy.quota_group_overrides);
+
                     let mut modified = false;
                     // Headless/docker 默认允许 LAN 访问（绑定 0.0.0.0）
                     // 若设置 ABV_BIND_LOCAL_ONLY，则仅绑定 127.0.0.1
@@ -183,56 +330,10 @@ pub fn run() {
                         modified = true;
                     }
 
-                    // [NEW] 支持通过环境变量注入 API Key
-                    // 优先级：ABV_API_KEY > API_KEY > 配置文件
-                    let env_key = std::env::var("ABV_API_KEY")
-                        .or_else(|_| std::env::var("API_KEY"))
-                        .ok();
-
-                    if let Some(key) = env_key {
-                        if !key.trim().is_empty() {
-                            info!("Using API Key from environment variable");
-                            config.proxy.api_key = key;
-                            modified = true;
-                        }
-                    }
-
-                    // [NEW] 支持通过环境变量注入 Web UI 密码
-                    // 优先级：ABV_WEB_PASSWORD > WEB_PASSWORD > 配置文件
-                    let env_web_password = std::env::var("ABV_WEB_PASSWORD")
-                        .or_else(|_| std::env::var("WEB_PASSWORD"))
-                        .ok();
-
-                    if let Some(pwd) = env_web_password {
-                        if !pwd.trim().is_empty() {
-                            info!("Using Web UI Password from environment variable");
-                            config.proxy.admin_password = Some(pwd);
-                            modified = true;
-                        }
-                    }
-
-                    // [NEW] 支持通过环境变量注入鉴权模式
-                    // 优先级：ABV_AUTH_MODE > AUTH_MODE > 配置文件
-                    let env_auth_mode = std::env::var("ABV_AUTH_MODE")
-                        .or_else(|_| std::env::var("AUTH_MODE"))
-                        .ok();
-
-                    if let Some(mode_str) = env_auth_mode {
-                        let mode = match mode_str.to_lowercase().as_str() {
-                            "off" => Some(crate::proxy::ProxyAuthMode::Off),
-                            "strict" => Some(crate::proxy::ProxyAuthMode::Strict),
-                            "all_except_health" => Some(crate::proxy::ProxyAuthMode::AllExceptHealth),
-                            "auto" => Some(crate::proxy::ProxyAuthMode::Auto),
-                            _ => {
-                                warn!("Invalid AUTH_MODE: {}, ignoring", mode_str);
-                                None
-                            }
-                        };
-                        if let Some(m) = mode {
-                            info!("Using Auth Mode from environment variable: {:?}", m);
-                            config.proxy.auth_mode = m;
-                            modified = true;
-                        }
+                    // 环境变量优先于上面的 headless 默认值
+                    modules::env_overrides::apply_active_overrides(&mut config);
+                    if !env_applied.is_empty() {
+                        info!("Applied environment overrides: {}", env_applied.join(", "));
                     }
 
                     info!("--------------------------------------------------");
@@ -248,12 +349,13 @@ pub fn run() {
                     info!("💡 Search docker logs or grep gui_config.json to find them.");
                     info!("--------------------------------------------------");
 
-                    // [FIX #1460] Persist environment overrides to ensure they are visible in Web UI/load_config
+                    // [FIX #1460] Persist headless defaults so they are visible in Web UI/load_config
+                    // (环境变量覆盖的字段由 save_app_config 还原，不会写入文件)
                     if modified {
                         if let Err(e) = modules::config::save_app_config(&config) {
-                            error!("Failed to persist environment overrides: {}", e);
+                            error!("Failed to persist headless config: {}", e);
                         } else {
-                            info!("Environment overrides persisted to gui_config.json");
+                            info!("Headless config persisted to gui_config.json");
                         }
                     }
 
@@ -272,6 +374,8 @@ pub fn run() {
 
                     // [DISABLED] Start smart scheduler (Automatic warmup disabled as per user request)
                     // modules::scheduler::start_scheduler(None, proxy_state.clone());
+                    modules::scheduler::start_quota_refresh_scheduler(None, proxy_state.clone());
+                    modules::backup::start_backup_scheduler();
                     info!("Smart scheduler (Automatic Warmup) is DISABLED.");
                     info!("Smart scheduler started in headless mode.");
                 }
@@ -281,9 +385,29 @@ pub fn run() {
                 }
             }
 
-            // Wait for Ctrl-C
-            tokio::signal::ctrl_c().await.ok();
+            // Wait for Ctrl-C / SIGTERM
+            wait_for_shutdown_signal().await;
             info!("Headless mode shutting down");
+
+            // 优雅关闭：停止后台任务并在宽限期内排空存量请求
+            if let Some(instance) = proxy_state.instance.write().await.take() {
+                instance.token_manager.save_runtime_state();
+                instance.token_manager.abort_background_tasks().await;
+                instance.axum_server.set_running(false).await;
+            }
+            if let Some(admin) = proxy_state.admin_server.write().await.take() {
+                let grace_secs = modules::config::load_app_config()
+                    .map(|c| c.proxy.shutdown_grace_secs)
+                    .unwrap_or(10);
+                let remaining = admin
+                    .axum_server
+                    .shutdown_gracefully(std::time::Duration::from_secs(grace_secs))
+                    .await;
+                if remaining > 0 {
+                    warn!("{} connection(s) still open after grace period were force-closed", remaining);
+                }
+                admin.server_handle.abort();
+            }
         });
         return;
     }
@@ -300,6 +424,7 @@ pub fn run() {
         ))
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_window_state::Builder::default().build())
         .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
             let _ = app.get_webview_window("main")
@@ -318,6 +443,16 @@ pub fn run() {
 
             // Initialize log bridge with app handle for debug console
             modules::log_bridge::init_log_bridge(app.handle().clone());
+            modules::quota::init_quota_alerts(app.handle().clone());
+
+            // Token encryption at rest: apply setting and migrate existing account files
+            if let Ok(config) = modules::config::load_app_config() {
+                if let Err(e) = modules::migration::apply_token_encryption_setting(config.encrypt_tokens_at_rest) {
+                    error!("Failed to apply token encryption setting: {}", e);
+                }
+                // 配额刷新 / 托盘展示在反代启动前也会用到模型 -> 配额组映射
+                crate::proxy::common::model_mapping::update_quota_group_overrides(&config.proxy.quota_group_overrides);
+            }
 
             // Linux: Workaround for transparent window crash/freeze
             // The transparent window feature is unstable on Linux with WebKitGTK
@@ -393,6 +528,19 @@ pub fn run() {
             // modules::scheduler::start_scheduler(Some(app.handle().clone()), scheduler_state.inner().clone());
             info!("Smart scheduler (Automatic Warmup) is DISABLED.");
 
+            // Background quota refresh (auto_refresh / refresh_interval)
+            let refresh_state = app.handle().state::<commands::proxy::ProxyServiceState>();
+            modules::scheduler::start_quota_refresh_scheduler(
+                Some(app.handle().clone()),
+                refresh_state.inner().clone(),
+            );
+
+            // [NEW] 定时快照账号数据库与配置 (backup.enabled / interval_hours / keep)
+            modules::backup::start_backup_scheduler();
+
+            // [NEW] Antigravity 守护模式 (watchdog.enabled，意外退出时自动重启)
+            modules::watchdog::start_watchdog(app.handle().clone());
+
             // [PHASE 1] 已整合至 Axum 端口 (8045)，不再单独启动 19527 端口
             info!("Management API integrated into main proxy server (port 8045)");
 
@@ -429,7 +577,10 @@ pub fn run() {
             commands::delete_accounts,
             commands::reorder_accounts,
             commands::switch_account,
+            commands::switch_account_and_restart,
             commands::export_accounts,
+            commands::export_accounts_bundle,
+            commands::import_accounts_bundle,
             // Device fingerprint
             commands::get_device_profiles,
             commands::bind_device_profile,
@@ -454,6 +605,29 @@ pub fn run() {
             commands::complete_oauth_login,
             commands::cancel_oauth_login,
             commands::submit_oauth_code,
+            commands::begin_manual_oauth_login,
+            commands::finish_manual_oauth_login,
+            commands::reauthorize_account,
+            commands::get_migration_reports,
+            commands::backup_account_db,
+            commands::vacuum_account_db,
+            commands::list_backup_snapshots,
+            commands::create_backup_snapshot,
+            commands::delete_backup_snapshot,
+            commands::restore_backup_snapshot,
+            commands::list_cached_contents,
+            commands::create_cached_content,
+            commands::delete_cached_content,
+            commands::list_config_profiles,
+            commands::create_config_profile,
+            commands::save_active_config_profile,
+            commands::delete_config_profile,
+            commands::switch_config_profile,
+            commands::start_login_queue,
+            commands::resume_login_queue,
+            commands::cancel_login_queue,
+            commands::clear_login_queue,
+            commands::get_login_queue_status,
             commands::list_oauth_clients,
             commands::get_active_oauth_client,
             commands::set_active_oauth_client,
@@ -464,6 +638,8 @@ pub fn run() {
             commands::save_text_file,
             commands::read_text_file,
             commands::clear_log_cache,
+            commands::query_logs,
+            commands::list_log_files,
             commands::clear_antigravity_cache,
             commands::get_antigravity_cache_paths,
             commands::open_data_folder,
@@ -471,6 +647,7 @@ pub fn run() {
             commands::show_main_window,
             commands::set_window_theme,
             commands::get_antigravity_path,
+            commands::check_antigravity_version,
             commands::get_antigravity_args,
             commands::check_for_updates,
             commands::check_homebrew_installation,
@@ -483,11 +660,17 @@ pub fn run() {
             // Proxy service commands
             commands::proxy::start_proxy_service,
             commands::proxy::stop_proxy_service,
+            commands::proxy::restart_proxy_service,
+            commands::proxy::rebind_proxy_service,
             commands::proxy::get_proxy_status,
             commands::proxy::get_proxy_stats,
+            commands::proxy::run_proxy_benchmark,
             commands::proxy::get_proxy_logs,
+            commands::proxy::get_proxy_activity,
+            commands::proxy::get_client_version_info,
             commands::proxy::get_proxy_logs_paginated,
             commands::proxy::get_proxy_log_detail,
+            commands::proxy::replay_captured_request,
             commands::proxy::get_proxy_logs_count,
             commands::proxy::export_proxy_logs,
             commands::proxy::export_proxy_logs_json,
@@ -498,6 +681,10 @@ pub fn run() {
             commands::proxy::generate_api_key,
             commands::proxy::reload_proxy_accounts,
             commands::proxy::update_model_mapping,
+            commands::proxy::list_model_mapping_rules,
+            commands::proxy::save_model_mapping_rule,
+            commands::proxy::delete_model_mapping_rule,
+            commands::proxy::resolve_model_mapping,
             commands::proxy::check_proxy_health,
             commands::proxy::get_proxy_pool_config,
             commands::proxy::fetch_zai_models,
@@ -506,8 +693,12 @@ pub fn run() {
             commands::proxy::clear_proxy_session_bindings,
             commands::proxy::set_preferred_account,
             commands::proxy::get_preferred_account,
+            commands::proxy::pin_proxy_account,
+            commands::proxy::unpin_proxy_account,
+            commands::proxy::get_pinned_proxy_account,
             commands::proxy::clear_proxy_rate_limit,
             commands::proxy::clear_all_proxy_rate_limits,
+            commands::proxy::get_account_metrics,
             commands::proxy::check_proxy_health,
             // Proxy Pool Binding commands
             commands::proxy_pool::bind_account_proxy,
@@ -520,7 +711,11 @@ pub fn run() {
             // Warmup commands
             commands::warm_up_all_accounts,
             commands::warm_up_account,
+            commands::test_quota_webhook,
+            commands::get_quota_refresh_status,
             commands::update_account_label,
+            commands::update_account_upstream_proxy,
+            commands::update_account_launch_args,
             // HTTP API settings commands
             commands::get_http_api_settings,
             commands::save_http_api_settings,
@@ -535,6 +730,11 @@ pub fn run() {
             commands::get_token_stats_model_trend_daily,
             commands::get_token_stats_account_trend_hourly,
             commands::get_token_stats_account_trend_daily,
+            commands::get_quota_history,
+            commands::get_quota_trend_daily,
+            commands::get_quota_reset_predictions,
+            commands::get_token_usage_totals,
+            commands::reset_token_usage,
             proxy::cli_sync::get_cli_sync_status,
             proxy::cli_sync::execute_cli_sync,
             proxy::cli_sync::execute_cli_restore,
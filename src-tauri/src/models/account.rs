@@ -25,6 +25,12 @@ pub struct Account {
     /// Unix timestamp when the account was disabled.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disabled_at: Option<i64>,
+    /// [NEW] refresh_token 已失效 (invalid_grant)，需要重新授权登录
+    #[serde(default)]
+    pub needs_reauth: bool,
+    /// [NEW] 连续 401/403 后由反代自动停用 (重新授权后恢复)
+    #[serde(default)]
+    pub auto_disabled: bool,
     /// User manually disabled proxy feature (does not affect app usage).
     #[serde(default)]
     pub proxy_disabled: bool,
@@ -60,6 +66,12 @@ pub struct Account {
     /// 用户自定义标签
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_label: Option<String>,
+    /// 账号专属上游代理 (优先于代理池与全局上游代理)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_proxy: Option<crate::proxy::config::UpstreamProxyConfig>,
+    /// 切换到该账号时 Antigravity 的专属启动参数 (如 --user-data-dir / 工作区路径)，用于多 Profile 隔离
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch_args: Option<Vec<String>>,
 }
 
 impl Account {
@@ -76,6 +88,8 @@ impl Account {
             disabled: false,
             disabled_reason: None,
             disabled_at: None,
+            needs_reauth: false,
+            auto_disabled: false,
             proxy_disabled: false,
             proxy_disabled_reason: None,
             proxy_disabled_at: None,
@@ -89,6 +103,8 @@ impl Account {
             proxy_id: None,
             proxy_bound_at: None,
             custom_label: None,
+            upstream_proxy: None,
+            launch_args: None,
         }
     }
 
@@ -105,6 +121,9 @@ impl Account {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountIndex {
     pub version: String,
+    /// [NEW] 存储 schema 版本，由 modules::migration 维护 (旧索引缺省为 0)
+    #[serde(default)]
+    pub schema_version: u32,
     pub accounts: Vec<AccountSummary>,
     pub current_account_id: Option<String>,
 }
@@ -119,17 +138,24 @@ pub struct AccountSummary {
     pub disabled: bool,
     #[serde(default)]
     pub proxy_disabled: bool,
+    /// [NEW] 需要重新授权 (invalid_grant)，供 UI 显示重新登录提示
+    #[serde(default)]
+    pub needs_reauth: bool,
     /// 受保护的模型列表 [NEW] 供 UI 显示锁定图标
     #[serde(default, skip_serializing_if = "HashSet::is_empty")]
     pub protected_models: HashSet<String>,
     pub created_at: i64,
     pub last_used: i64,
+    /// [NEW] 预计最近一次配额重置时间 (Unix 秒)，随配额刷新更新
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_quota_reset_at: Option<i64>,
 }
 
 impl AccountIndex {
     pub fn new() -> Self {
         Self {
             version: "2.0".to_string(),
+            schema_version: crate::modules::migration::ACCOUNT_SCHEMA_VERSION,
             accounts: Vec::new(),
             current_account_id: None,
         }
@@ -30,6 +30,18 @@ pub struct AppConfig {
     pub hidden_menu_items: Vec<String>, // Hidden menu item path list
     #[serde(default)]
     pub cloudflared: CloudflaredConfig, // [NEW] Cloudflared configuration
+    #[serde(default)]
+    pub quota_alert: QuotaAlertConfig, // [NEW] Low quota alert configuration
+    #[serde(default)]
+    pub encrypt_tokens_at_rest: bool, // [NEW] Encrypt OAuth tokens in account files (OS keychain / device key)
+    #[serde(default)]
+    pub schema_version: u32, // [NEW] Config schema version, maintained by modules::migration
+    #[serde(default)]
+    pub backup: BackupConfig, // [NEW] Automatic data snapshots
+    #[serde(default)]
+    pub logging: LoggingConfig, // [NEW] Log level / rotation settings
+    #[serde(default)]
+    pub watchdog: WatchdogConfig, // [NEW] Relaunch Antigravity after unexpected exits
 }
 
 /// Scheduled warmup configuration
@@ -106,6 +118,181 @@ impl Default for QuotaProtectionConfig {
     }
 }
 
+/// Low quota alert configuration (desktop notification / webhook)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaAlertConfig {
+    /// Whether low quota alerts are enabled
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Alert when remaining quota percentage drops to or below this value (1-99)
+    #[serde(default = "default_alert_threshold")]
+    pub threshold_percentage: u32,
+
+    /// Models to watch (standard IDs, empty = all models)
+    #[serde(default)]
+    pub monitored_models: Vec<String>,
+
+    /// Show a desktop notification
+    #[serde(default = "default_true")]
+    pub desktop_notification: bool,
+
+    /// Optional webhook URL receiving a JSON POST (Slack / Discord / self-hosted)
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+fn default_alert_threshold() -> u32 {
+    20
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl QuotaAlertConfig {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            threshold_percentage: default_alert_threshold(),
+            monitored_models: Vec::new(),
+            desktop_notification: true,
+            webhook_url: None,
+        }
+    }
+}
+
+impl Default for QuotaAlertConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Automatic backup configuration (accounts DB + config snapshots)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupConfig {
+    /// Whether scheduled snapshots are enabled
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Hours between scheduled snapshots
+    #[serde(default = "default_backup_interval_hours")]
+    pub interval_hours: u32,
+
+    /// Number of scheduled snapshots to keep (oldest are removed first; manual and pre-restore snapshots are never pruned)
+    #[serde(default = "default_backup_keep")]
+    pub keep: u32,
+}
+
+fn default_backup_interval_hours() -> u32 {
+    24
+}
+
+fn default_backup_keep() -> u32 {
+    7
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: default_backup_interval_hours(),
+            keep: default_backup_keep(),
+        }
+    }
+}
+
+/// Antigravity watchdog configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    /// Whether to relaunch Antigravity when it exits unexpectedly
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Seconds between process checks
+    #[serde(default = "default_watchdog_check_interval_secs")]
+    pub check_interval_secs: u64,
+
+    /// Maximum relaunches within `window_minutes` before giving up (crash-loop guard)
+    #[serde(default = "default_watchdog_max_restarts")]
+    pub max_restarts: u32,
+
+    /// Sliding window for counting relaunches
+    #[serde(default = "default_watchdog_window_minutes")]
+    pub window_minutes: u32,
+}
+
+fn default_watchdog_check_interval_secs() -> u64 {
+    15
+}
+
+fn default_watchdog_max_restarts() -> u32 {
+    3
+}
+
+fn default_watchdog_window_minutes() -> u32 {
+    10
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_interval_secs: default_watchdog_check_interval_secs(),
+            max_restarts: default_watchdog_max_restarts(),
+            window_minutes: default_watchdog_window_minutes(),
+        }
+    }
+}
+
+/// Log output configuration (levels + file rotation)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Default level: trace / debug / info / warn / error
+    #[serde(default = "default_log_level")]
+    pub level: String,
+
+    /// Per-module level overrides (target -> level), e.g. "antigravity_tools_lib::proxy" -> "debug"
+    #[serde(default)]
+    pub module_levels: std::collections::HashMap<String, String>,
+
+    /// Roll to a new file once the current one exceeds this size (MB, 0 = daily only)
+    #[serde(default = "default_log_max_file_size_mb")]
+    pub max_file_size_mb: u64,
+
+    /// Days to keep log files
+    #[serde(default = "default_log_retention_days")]
+    pub retention_days: u64,
+
+    /// Write log files as JSON lines (for Loki / ELK), takes effect after restart
+    #[serde(default)]
+    pub json_format: bool,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_log_max_file_size_mb() -> u64 {
+    50
+}
+
+fn default_log_retention_days() -> u64 {
+    7
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            module_levels: std::collections::HashMap::new(),
+            max_file_size_mb: default_log_max_file_size_mb(),
+            retention_days: default_log_retention_days(),
+            json_format: false,
+        }
+    }
+}
+
 /// Pinned quota models configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PinnedQuotaModelsConfig {
@@ -188,6 +375,12 @@ impl AppConfig {
             circuit_breaker: CircuitBreakerConfig::default(),
             hidden_menu_items: Vec::new(),
             cloudflared: CloudflaredConfig::default(),
+            quota_alert: QuotaAlertConfig::default(),
+            encrypt_tokens_at_rest: false,
+            schema_version: crate::modules::migration::CONFIG_SCHEMA_VERSION,
+            backup: BackupConfig::default(),
+            logging: LoggingConfig::default(),
+            watchdog: WatchdogConfig::default(),
         }
     }
 }
@@ -6,5 +6,5 @@ pub mod config;
 pub use account::{Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion, AccountExportItem, AccountExportResponse};
 pub use token::TokenData;
 pub use quota::QuotaData;
-pub use config::{AppConfig, QuotaProtectionConfig, CircuitBreakerConfig};
+pub use config::{AppConfig, QuotaProtectionConfig, CircuitBreakerConfig, QuotaAlertConfig, LoggingConfig, WatchdogConfig};
 
@@ -50,6 +50,7 @@ mod tests {
 
     impl Drop for TestDataDir {
         fn drop(&mut self) {
+            crate::modules::account_db::close_db(&self.path.join("accounts"));
             let _ = fs::remove_dir_all(&self.path);
         }
     }
@@ -203,14 +204,17 @@ mod tests {
         assert!(emails.contains(&"user1@example.com".to_string()));
         assert!(emails.contains(&"user2@example.com".to_string()));
 
-        // Verify account files still exist
+        // Verify account data was imported into the account DB and legacy files archived
         let accounts_dir = dir.path().join("accounts");
-        let account_files: Vec<_> = fs::read_dir(&accounts_dir)
+        let stored = crate::modules::account_db::list_account_ids(&accounts_dir).unwrap();
+        assert_eq!(stored.len(), 2, "Accounts should be stored in the account DB");
+        let names: Vec<String> = fs::read_dir(&accounts_dir)
             .unwrap()
             .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext == "json"))
+            .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
             .collect();
-        assert_eq!(account_files.len(), 2, "Account files should still exist on disk");
+        assert!(!names.iter().any(|n| n.ends_with(".json")), "Legacy account files should not be re-imported");
+        assert_eq!(names.iter().filter(|n| n.ends_with(".json.imported")).count(), 2);
         
         println!("Missing index with existing accounts: successfully recovered {} accounts", index.accounts.len());
     }
@@ -224,6 +228,7 @@ mod tests {
         let now = chrono::Utc::now().timestamp();
         let index = AccountIndex {
             version: "2.0".to_string(),
+            schema_version: crate::modules::migration::ACCOUNT_SCHEMA_VERSION,
             accounts: vec![
                 AccountSummary {
                     id: "acc-1".to_string(),
@@ -231,9 +236,11 @@ mod tests {
                     name: Some("User One".to_string()),
                     disabled: false,
                     proxy_disabled: false,
+                    needs_reauth: false,
                     protected_models: HashSet::new(),
                     created_at: now,
                     last_used: now,
+                    next_quota_reset_at: None,
                 },
                 AccountSummary {
                     id: "acc-2".to_string(),
@@ -241,16 +248,22 @@ mod tests {
                     name: None,
                     disabled: true,
                     proxy_disabled: true,
+                    needs_reauth: false,
                     protected_models: HashSet::new(),
                     created_at: now - 100,
                     last_used: now - 50,
+                    next_quota_reset_at: None,
                 },
             ],
             current_account_id: Some("acc-1".to_string()),
         };
 
+        // A legacy index file is replaced by the DB copy on save
+        write_corrupted_index(dir.path(), b"{}");
+
         // Save the index
         save_account_index_in_dir(dir.path(), &index).expect("Failed to save account index");
+        assert!(!dir.path().join("accounts.json").exists(), "Legacy index file should be removed");
 
         // Load it back
         let loaded = load_account_index_in_dir(dir.path()).expect("Failed to load account index");
@@ -368,32 +381,45 @@ pub fn get_accounts_dir() -> Result<PathBuf, String> {
 
 /// Load account index from a specific directory (internal helper)
 fn load_account_index_in_dir(data_dir: &PathBuf) -> Result<AccountIndex, String> {
+    // [NEW] 索引保存在账号数据库，旧版 accounts.json 仅在数据库尚无索引时读取并导入
+    let accounts_dir = data_dir.join(ACCOUNTS_DIR);
+    if let Some(content) = crate::modules::account_db::read_index(&accounts_dir)? {
+        return parse_index_or_recover(data_dir, content.as_bytes());
+    }
+
     let index_path = data_dir.join(ACCOUNTS_INDEX);
 
     if !index_path.exists() {
         crate::modules::logger::log_warn(
-            "Account index file not found, attempting recovery from accounts directory",
+            "Account index not found, attempting recovery from accounts directory",
         );
         let recovered = rebuild_index_from_accounts_in_dir(data_dir)?;
-        try_save_recovered_index(data_dir, &index_path, &recovered, None)?;
+        try_save_recovered_index(data_dir, &recovered, None)?;
         return Ok(recovered);
     }
 
     let raw_content = fs::read(&index_path)
         .map_err(|e| format!("failed_to_read_account_index: {}", e))?;
+    let index = parse_index_or_recover(data_dir, &raw_content)?;
+    // 导入数据库后删除旧索引文件
+    try_save_recovered_index(data_dir, &index, None)?;
+    Ok(index)
+}
 
-    // If file is empty, attempt recovery
+/// Parse raw index content, rebuilding from account data when it is empty or corrupt
+fn parse_index_or_recover(data_dir: &PathBuf, raw_content: &[u8]) -> Result<AccountIndex, String> {
+    // If content is empty, attempt recovery
     if raw_content.is_empty() {
         crate::modules::logger::log_warn(
             "Account index is empty, attempting recovery from accounts directory",
         );
         let recovered = rebuild_index_from_accounts_in_dir(data_dir)?;
-        try_save_recovered_index(data_dir, &index_path, &recovered, None)?;
+        try_save_recovered_index(data_dir, &recovered, None)?;
         return Ok(recovered);
     }
 
     // Sanitize content: strip BOM and leading NUL bytes
-    let sanitized = sanitize_index_content(&raw_content);
+    let sanitized = sanitize_index_content(raw_content);
 
     // If sanitized content is empty/whitespace, attempt recovery
     if sanitized.trim().is_empty() {
@@ -401,7 +427,7 @@ fn load_account_index_in_dir(data_dir: &PathBuf) -> Result<AccountIndex, String>
             "Account index is empty after sanitization, attempting recovery from accounts directory",
         );
         let recovered = rebuild_index_from_accounts_in_dir(data_dir)?;
-        try_save_recovered_index(data_dir, &index_path, &recovered, None)?;
+        try_save_recovered_index(data_dir, &recovered, None)?;
         return Ok(recovered);
     }
 
@@ -420,70 +446,65 @@ fn load_account_index_in_dir(data_dir: &PathBuf) -> Result<AccountIndex, String>
                 parse_err
             ));
             let recovered = rebuild_index_from_accounts_in_dir(data_dir)?;
-            try_save_recovered_index(data_dir, &index_path, &recovered, Some(&raw_content))?;
+            try_save_recovered_index(data_dir, &recovered, Some(raw_content))?;
             Ok(recovered)
         }
     }
 }
 
-/// Save account index to a specific directory (internal helper)
-fn save_account_index_in_dir(data_dir: &PathBuf, index: &AccountIndex) -> Result<(), String> {
-    let index_path = data_dir.join(ACCOUNTS_INDEX);
-    // Use unique temp file name per write to avoid collision
-    let temp_filename = format!("{}.tmp.{}", ACCOUNTS_INDEX, Uuid::new_v4());
-    let temp_path = data_dir.join(&temp_filename);
-
-    let content = serde_json::to_string_pretty(index)
-        .map_err(|e| format!("failed_to_serialize_account_index: {}", e))?;
-
-    // Write to temporary file
-    if let Err(e) = fs::write(&temp_path, content) {
-        // Clean up temp file on failure
-        let _ = fs::remove_file(&temp_path);
-        return Err(format!("failed_to_write_temp_index_file: {}", e));
-    }
+fn index_value(index: &AccountIndex) -> Result<serde_json::Value, String> {
+    serde_json::to_value(index).map_err(|e| format!("failed_to_serialize_account_index: {}", e))
+}
 
-    // Atomic rename with platform-specific handling
-    if let Err(e) = atomic_replace_file(&temp_path, &index_path) {
-        // Clean up temp file on failure
-        let _ = fs::remove_file(&temp_path);
-        return Err(format!("failed_to_replace_index_file: {}", e));
+/// 索引写入数据库后删除旧版 accounts.json，避免下次启动再被导入
+fn remove_legacy_index(data_dir: &PathBuf) {
+    let index_path = data_dir.join(ACCOUNTS_INDEX);
+    if index_path.exists() {
+        if let Err(e) = fs::remove_file(&index_path) {
+            crate::modules::logger::log_warn(&format!(
+                "Failed to remove legacy account index: {}",
+                e
+            ));
+        }
     }
+}
 
+/// Save account index to a specific directory (internal helper)
+fn save_account_index_in_dir(data_dir: &PathBuf, index: &AccountIndex) -> Result<(), String> {
+    crate::modules::account_db::write_index(&data_dir.join(ACCOUNTS_DIR), &index_value(index)?)?;
+    remove_legacy_index(data_dir);
     Ok(())
 }
 
-/// Rebuild AccountIndex by scanning accounts/*.json files in specific directory
+/// Rebuild AccountIndex by scanning the account database in specific directory
 fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex, String> {
     let accounts_dir = data_dir.join(ACCOUNTS_DIR);
     let mut summaries = Vec::new();
 
+    // [NEW] 账号数据已迁入 SQLite，list_account_ids 会顺带导入遗留的 JSON 文件
     if accounts_dir.exists() {
-        if let Ok(entries) = fs::read_dir(&accounts_dir) {
-            for entry in entries.filter_map(|e| e.ok()) {
-                let path = entry.path();
-                if path.extension().map_or(false, |ext| ext == "json") {
-                    if let Some(account_id) = path.file_stem().and_then(|s| s.to_str()) {
-                        match load_account_at_path(&path) {
-                            Ok(account) => {
-                                    summaries.push(AccountSummary {
-                                        id: account.id,
-                                        email: account.email,
-                                        name: account.name,
-                                        disabled: account.disabled,
-                                        proxy_disabled: account.proxy_disabled,
-                                        protected_models: account.protected_models,
-                                        created_at: account.created_at,
-                                        last_used: account.last_used,
-                                    });
-                            }
-                            Err(e) => {
-                                crate::modules::logger::log_warn(&format!(
-                                    "Failed to load account {} during recovery: {}",
-                                    account_id, e
-                                ));
-                            }
-                        }
+        if let Ok(account_ids) = crate::modules::account_db::list_account_ids(&accounts_dir) {
+            for account_id in account_ids {
+                match load_account_in_dir(&accounts_dir, &account_id) {
+                    Ok(account) => {
+                        summaries.push(AccountSummary {
+                            id: account.id,
+                            email: account.email,
+                            name: account.name,
+                            disabled: account.disabled,
+                            proxy_disabled: account.proxy_disabled,
+                            needs_reauth: account.needs_reauth,
+                            protected_models: account.protected_models,
+                            created_at: account.created_at,
+                            last_used: account.last_used,
+                            next_quota_reset_at: None,
+                        });
+                    }
+                    Err(e) => {
+                        crate::modules::logger::log_warn(&format!(
+                            "Failed to load account {} during recovery: {}",
+                            account_id, e
+                        ));
                     }
                 }
             }
@@ -506,16 +527,20 @@ fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex
 
     Ok(AccountIndex {
         version: "2.0".to_string(),
+        // 从账号数据重建的摘要已包含最新字段
+        schema_version: crate::modules::migration::ACCOUNT_SCHEMA_VERSION,
         accounts: summaries,
         current_account_id,
     })
 }
 
-/// Load account from a specific path (internal helper)
-fn load_account_at_path(account_path: &PathBuf) -> Result<Account, String> {
-    let content = fs::read_to_string(account_path)
-        .map_err(|e| format!("failed_to_read_account_data: {}", e))?;
-    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+/// Load account from a specific accounts directory (internal helper)
+fn load_account_in_dir(accounts_dir: &PathBuf, account_id: &str) -> Result<Account, String> {
+    let mut value = crate::modules::account_db::read_account_value(accounts_dir, account_id)?
+        .ok_or_else(|| format!("failed_to_read_account_data: account not found: {}", account_id))?;
+    // [NEW] 透明解密静态加密的 Token (明文旧数据原样读取)
+    crate::utils::crypto::unseal_account_tokens(&mut value)?;
+    serde_json::from_value(value).map_err(|e| format!("failed_to_parse_account_data: {}", e))
 }
 
 /// Load account index with recovery support
@@ -547,11 +572,10 @@ fn sanitize_index_content(raw: &[u8]) -> String {
 /// Best-effort save of recovered index without deadlocking
 fn try_save_recovered_index(
     data_dir: &PathBuf,
-    _index_path: &PathBuf,
     index: &AccountIndex,
     corrupt_content: Option<&[u8]>,
 ) -> Result<(), String> {
-    // Backup corrupt file if content provided
+    // Backup corrupt index if content provided
     if let Some(content) = corrupt_content {
         let timestamp = chrono::Utc::now().timestamp();
         let backup_name = format!("accounts.json.corrupt-{}-{}", timestamp, Uuid::new_v4());
@@ -591,87 +615,56 @@ fn try_save_recovered_index(
     Ok(())
 }
 
-/// Save account index (atomic write)
+/// Save account index (stored in the account database)
 pub fn save_account_index(index: &AccountIndex) -> Result<(), String> {
     let data_dir = get_data_dir()?;
     save_account_index_in_dir(&data_dir, index)
 }
 
-/// Platform-specific atomic file replacement
-#[cfg(target_os = "windows")]
-fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
-    use std::os::windows::ffi::OsStrExt;
-
-    type Bool = i32;
-    type Dword = u32;
-
-    #[link(name = "Kernel32")]
-    extern "system" {
-        fn MoveFileExW(lp_existing_file_name: *const u16, lp_new_file_name: *const u16, dw_flags: Dword) -> Bool;
-    }
-
-    let src_wide: Vec<u16> = src
-        .as_os_str()
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
-    let dst_wide: Vec<u16> = dst
-        .as_os_str()
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
-
-    // MOVEFILE_REPLACE_EXISTING = 0x1
-    // MOVEFILE_WRITE_THROUGH = 0x8
-    const MOVEFILE_REPLACE_EXISTING: u32 = 0x1;
-    const MOVEFILE_WRITE_THROUGH: u32 = 0x8;
-    let flags = MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH;
-
-    let result = unsafe { MoveFileExW(src_wide.as_ptr(), dst_wide.as_ptr(), flags) };
-    if result == 0 {
-        let err = std::io::Error::last_os_error();
-        // Clean up source file on failure
-        let _ = fs::remove_file(src);
-        return Err(format!("MoveFileExW failed: {}", err));
-    }
-
-    Ok(())
-}
-
-/// Non-Windows: use standard rename
-#[cfg(not(target_os = "windows"))]
-fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
-    fs::rename(src, dst).map_err(|e| format!("rename failed: {}", e))
-}
-
 /// Load account data
 pub fn load_account(account_id: &str) -> Result<Account, String> {
     let accounts_dir = get_accounts_dir()?;
-    let account_path = accounts_dir.join(format!("{}.json", account_id));
-    load_account_at_path(&account_path)
+    load_account_in_dir(&accounts_dir, account_id)
+}
+
+/// Serialize account data for storage, sealing tokens when encryption is enabled
+fn sealed_account_value(account: &Account) -> Result<serde_json::Value, String> {
+    let mut value = serde_json::to_value(account)
+        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+    // [NEW] 开启静态加密时写盘前加密 Token
+    crate::utils::crypto::seal_account_tokens(&mut value)?;
+    Ok(value)
 }
 
 /// Save account data
 pub fn save_account(account: &Account) -> Result<(), String> {
     let accounts_dir = get_accounts_dir()?;
-    let account_path = accounts_dir.join(format!("{}.json", account.id));
-
-    let temp_filename = format!("{}.tmp.{}", account.id, Uuid::new_v4());
-    let temp_path = accounts_dir.join(&temp_filename);
-
-    let content = serde_json::to_string_pretty(account)
-        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
-
-    if let Err(e) = std::fs::write(&temp_path, content) {
-        let _ = std::fs::remove_file(&temp_path);
-        return Err(format!("failed_to_write_temp_account_file: {}", e));
-    }
+    // [NEW] 账号数据存储在 SQLite (WAL + 事务)，替代逐文件原子替换
+    crate::modules::account_db::write_account(&accounts_dir, &account.id, &sealed_account_value(account)?)
+}
 
-    if let Err(e) = atomic_replace_file(&temp_path, &account_path) {
-        let _ = std::fs::remove_file(&temp_path);
-        return Err(format!("failed_to_replace_account_file: {}", e));
-    }
+/// Save account data and index in one transaction
+fn save_account_with_index(account: &Account, index: &AccountIndex) -> Result<(), String> {
+    let data_dir = get_data_dir()?;
+    crate::modules::account_db::write_account_and_index(
+        &data_dir.join(ACCOUNTS_DIR),
+        &account.id,
+        &sealed_account_value(account)?,
+        &index_value(index)?,
+    )?;
+    remove_legacy_index(&data_dir);
+    Ok(())
+}
 
+/// Delete account data and save index in one transaction
+fn delete_accounts_with_index(account_ids: &[String], index: &AccountIndex) -> Result<(), String> {
+    let data_dir = get_data_dir()?;
+    crate::modules::account_db::delete_accounts_and_index(
+        &data_dir.join(ACCOUNTS_DIR),
+        account_ids,
+        &index_value(index)?,
+    )?;
+    remove_legacy_index(&data_dir);
     Ok(())
 }
 
@@ -720,9 +713,6 @@ pub fn add_account(
     let mut account = Account::new(account_id.clone(), email.clone(), token);
     account.name = name.clone();
 
-    // Save account data
-    save_account(&account)?;
-
     // Update index
     index.accounts.push(AccountSummary {
         id: account.id.clone(),
@@ -730,9 +720,11 @@ pub fn add_account(
         name: account.name.clone(),
         disabled: account.disabled,
         proxy_disabled: account.proxy_disabled,
+        needs_reauth: account.needs_reauth,
         protected_models: account.protected_models.clone(),
         created_at: account.created_at,
         last_used: account.last_used,
+        next_quota_reset_at: None,
     });
 
     // If first account, set as current
@@ -740,7 +732,8 @@ pub fn add_account(
         index.current_account_id = Some(account_id);
     }
 
-    save_account_index(&index)?;
+    // Save account data and index together
+    save_account_with_index(&account, &index)?;
 
     Ok(account)
 }
@@ -780,15 +773,18 @@ pub fn upsert_account(
                     account.disabled = false;
                     account.disabled_reason = None;
                     account.disabled_at = None;
+                    account.needs_reauth = false;
+                    account.auto_disabled = false;
                 }
                 account.update_last_used();
-                save_account(&account)?;
 
-                // Sync name in index
+                // Sync name / reauth state in index
                 if let Some(idx_summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
                     idx_summary.name = name;
-                    save_account_index(&index)?;
+                    idx_summary.disabled = account.disabled;
+                    idx_summary.needs_reauth = account.needs_reauth;
                 }
+                save_account_with_index(&account, &index)?;
 
                 return Ok(account);
             }
@@ -800,13 +796,12 @@ pub fn upsert_account(
                 // Index exists but file is missing, recreating
                 let mut account = Account::new(account_id.clone(), email.clone(), token);
                 account.name = name.clone();
-                save_account(&account)?;
 
                 // Sync name in index
                 if let Some(idx_summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
                     idx_summary.name = name;
-                    save_account_index(&index)?;
                 }
+                save_account_with_index(&account, &index)?;
 
                 return Ok(account);
             }
@@ -842,16 +837,8 @@ pub fn delete_account(account_id: &str) -> Result<(), String> {
         index.current_account_id = index.accounts.first().map(|s| s.id.clone());
     }
 
-    save_account_index(&index)?;
-
-    // Delete account file
-    let accounts_dir = get_accounts_dir()?;
-    let account_path = accounts_dir.join(format!("{}.json", account_id));
-
-    if account_path.exists() {
-        fs::remove_file(&account_path)
-            .map_err(|e| format!("failed_to_delete_account_file: {}", e))?;
-    }
+    // Delete account data and save index together
+    delete_accounts_with_index(&[account_id.to_string()], &index)?;
 
     // [FIX #1477] Trigger TokenManager cache cleanup signal
     crate::proxy::server::trigger_account_delete(account_id);
@@ -866,8 +853,6 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
         .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
     let mut index = load_account_index()?;
 
-    let accounts_dir = get_accounts_dir()?;
-
     for account_id in account_ids {
         // Remove from index
         index.accounts.retain(|s| &s.id != account_id);
@@ -876,15 +861,6 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
         if index.current_account_id.as_deref() == Some(account_id) {
             index.current_account_id = None;
         }
-
-        // Delete account file
-        let account_path = accounts_dir.join(format!("{}.json", account_id));
-        if account_path.exists() {
-            let _ = fs::remove_file(&account_path);
-        }
-
-        // [FIX #1477] Trigger TokenManager cache cleanup signal
-        crate::proxy::server::trigger_account_delete(account_id);
     }
 
     // If current account is empty, use first one as default
@@ -892,7 +868,15 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
         index.current_account_id = index.accounts.first().map(|s| s.id.clone());
     }
 
-    save_account_index(&index)
+    // Delete account data and save index together
+    delete_accounts_with_index(account_ids, &index)?;
+
+    // [FIX #1477] Trigger TokenManager cache cleanup signal
+    for account_id in account_ids {
+        crate::proxy::server::trigger_account_delete(account_id);
+    }
+
+    Ok(())
 }
 
 /// Reorder account list
@@ -1454,6 +1438,15 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
     // Save account first
     save_account(&account)?;
 
+    // [NEW] 低配额告警 (桌面通知 / Webhook)
+    if let Some(ref q) = account.quota {
+        crate::modules::quota::check_quota_alerts(&account.id, &account.email, q);
+        // [NEW] 记录配额快照，用于趋势图与重置时间分析
+        if let Err(e) = crate::modules::quota_history::record_snapshot(&account.id, &account.email, q) {
+            crate::modules::logger::log_warn(&format!("[Quota] Failed to record quota history: {}", e));
+        }
+    }
+
     // [FIX] 同时更新索引文件中的摘要信息，确保列表页图标即时刷新
     {
         let _lock = ACCOUNT_INDEX_LOCK
@@ -1462,6 +1455,7 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
         if let Ok(mut index) = load_account_index() {
             if let Some(summary) = index.accounts.iter_mut().find(|a| a.id == account_id) {
                 summary.protected_models = account.protected_models.clone();
+                summary.next_quota_reset_at = crate::modules::quota_history::next_reset_at(account_id);
                 let _ = save_account_index(&index);
             }
         }
@@ -1470,6 +1464,8 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
     // [FIX] Trigger TokenManager account reload signal
     // This ensures in-memory protected_models are updated
     crate::proxy::server::trigger_account_reload(account_id);
+    // [NEW] 模型能力元数据随配额刷新更新
+    crate::proxy::model_registry::invalidate();
 
     Ok(())
 }
@@ -1510,6 +1506,50 @@ pub fn toggle_proxy_status(
     Ok(())
 }
 
+/// [NEW] refresh_token 失效 (invalid_grant): 禁用账号并标记需要重新授权，
+/// 使其退出轮询而不是反复刷新失败
+pub fn mark_needs_reauth(account: &mut Account, error: &str) -> Result<(), String> {
+    account.disabled = true;
+    account.needs_reauth = true;
+    account.disabled_at = Some(chrono::Utc::now().timestamp());
+    account.disabled_reason = Some(format!("invalid_grant: {}", error));
+    save_account(account)?;
+    sync_needs_reauth_summary(&account.id)
+}
+
+/// [NEW] 连续 401/403 (刷新 token 后仍失败) 的账号: 自动停用并通知前端
+pub fn auto_disable_account(account_id: &str, reason: &str) -> Result<(), String> {
+    let mut account = load_account(account_id)?;
+    if account.disabled {
+        return Ok(());
+    }
+    account.disabled = true;
+    account.auto_disabled = true;
+    account.disabled_at = Some(chrono::Utc::now().timestamp());
+    account.disabled_reason = Some(format!("auto_disabled: {}", reason));
+    save_account(&account)?;
+    sync_needs_reauth_summary(account_id)?;
+
+    crate::modules::log_bridge::emit_account_auto_disabled(account_id, &account.email, reason);
+    crate::modules::log_bridge::emit_accounts_refreshed();
+    Ok(())
+}
+
+/// 将账号文件中的 disabled / needs_reauth 状态同步到索引摘要
+pub fn sync_needs_reauth_summary(account_id: &str) -> Result<(), String> {
+    let _lock = ACCOUNT_INDEX_LOCK
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let account = load_account(account_id)?;
+    let mut index = load_account_index()?;
+    if let Some(summary) = index.accounts.iter_mut().find(|a| a.id == account_id) {
+        summary.disabled = account.disabled;
+        summary.needs_reauth = account.needs_reauth;
+        save_account_index(&index)?;
+    }
+    Ok(())
+}
+
 /// Find account ID by email (from index)
 pub fn find_account_id_by_email(email: &str) -> Option<String> {
     load_account_index().ok()?.accounts.into_iter()
@@ -1606,10 +1646,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                     "Disabling account {} due to invalid_grant during token refresh (quota check)",
                     account.email
                 ));
-                account.disabled = true;
-                account.disabled_at = Some(chrono::Utc::now().timestamp());
-                account.disabled_reason = Some(format!("invalid_grant: {}", e));
-                let _ = save_account(account);
+                let _ = mark_needs_reauth(account, &e);
                 crate::proxy::server::trigger_account_reload(&account.id);
             }
             return Err(AppError::OAuth(e));
@@ -1708,10 +1745,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                                 "Disabling account {} due to invalid_grant during forced refresh (quota check)",
                                 account.email
                             ));
-                            account.disabled = true;
-                            account.disabled_at = Some(chrono::Utc::now().timestamp());
-                            account.disabled_reason = Some(format!("invalid_grant: {}", e));
-                            let _ = save_account(account);
+                            let _ = mark_needs_reauth(account, &e);
                             crate::proxy::server::trigger_account_reload(&account.id);
                         }
                         return Err(AppError::OAuth(e));
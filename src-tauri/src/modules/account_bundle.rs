@@ -0,0 +1,333 @@
+// 加密账号包 (迁移 / 备份)
+// 将选中账号 (Token + 元数据) 以口令加密 (PBKDF2-SHA256 + AES-256-GCM) 写入单个文件，
+// 避免迁移时手动拷贝包含明文 refresh_token 的 JSON
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::models::Account;
+use crate::modules::{account, logger};
+
+const BUNDLE_FORMAT: &str = "antigravity-account-bundle";
+const BUNDLE_VERSION: u32 = 1;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// 加密包文件结构 (明文头 + 密文)
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleFile {
+    format: String,
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 解密后的载荷
+#[derive(Debug, Serialize, Deserialize)]
+struct BundlePayload {
+    exported_at: i64,
+    accounts: Vec<Account>,
+}
+
+/// 导入时遇到同邮箱账号的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleMergeStrategy {
+    /// 保留本地账号，跳过包内同名账号
+    #[default]
+    Skip,
+    /// 用包内 Token 与元数据覆盖本地账号
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BundleImportResult {
+    pub imported: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    /// 本地已存在的邮箱 (重复检测结果)
+    pub duplicates: Vec<String>,
+    pub accounts: Vec<Account>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn encrypt_payload(
+    payload: &BundlePayload,
+    passphrase: &str,
+    iterations: u32,
+) -> Result<BundleFile, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let key = derive_key(passphrase, &salt, iterations);
+    let cipher = Aes256Gcm::new(&key.into());
+    let plaintext =
+        serde_json::to_vec(payload).map_err(|e| format!("Failed to serialize bundle: {}", e))?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    Ok(BundleFile {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        kdf: "pbkdf2-sha256".to_string(),
+        iterations,
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+fn decrypt_payload(file: &BundleFile, passphrase: &str) -> Result<BundlePayload, String> {
+    if file.format != BUNDLE_FORMAT {
+        return Err("Not an account bundle file".to_string());
+    }
+    if file.version > BUNDLE_VERSION || file.kdf != "pbkdf2-sha256" || file.iterations > 10_000_000 {
+        return Err(format!(
+            "Unsupported bundle version {} ({})",
+            file.version, file.kdf
+        ));
+    }
+
+    let decode = |field: &str, value: &str| {
+        general_purpose::STANDARD
+            .decode(value)
+            .map_err(|e| format!("Invalid bundle {}: {}", field, e))
+    };
+    let salt = decode("salt", &file.salt)?;
+    let nonce = decode("nonce", &file.nonce)?;
+    let ciphertext = decode("ciphertext", &file.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err("Invalid bundle nonce".to_string());
+    }
+
+    let key = derive_key(passphrase, &salt, file.iterations);
+    let cipher = Aes256Gcm::new(&key.into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "Wrong passphrase or corrupted bundle".to_string())?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid bundle payload: {}", e))
+}
+
+/// 导出指定账号到加密包，返回导出数量
+pub fn export_bundle(account_ids: &[String], passphrase: &str, path: &Path) -> Result<usize, String> {
+    let accounts: Vec<Account> = account::list_accounts()?
+        .into_iter()
+        .filter(|acc| account_ids.contains(&acc.id))
+        .collect();
+    if accounts.is_empty() {
+        return Err("No accounts selected for export".to_string());
+    }
+
+    let count = accounts.len();
+    let file = encrypt_payload(
+        &BundlePayload {
+            exported_at: chrono::Utc::now().timestamp(),
+            accounts,
+        },
+        passphrase,
+        PBKDF2_ITERATIONS,
+    )?;
+    let content = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize bundle: {}", e))?;
+    write_bundle_file(path, content.as_bytes())?;
+
+    logger::log_info(&format!(
+        "Exported {} accounts to encrypted bundle: {}",
+        count,
+        path.display()
+    ));
+    Ok(count)
+}
+
+/// 写入加密包文件，Unix 下权限为 0600 (包内含 Token，仅依赖口令保护不够)
+fn write_bundle_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to write bundle: {}", e))?;
+    // 覆盖已有文件时 mode 不生效，显式收紧权限
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict bundle permissions: {}", e))?;
+    }
+    file.write_all(content)
+        .map_err(|e| format!("Failed to write bundle: {}", e))
+}
+
+/// 将包内账号的迁移元数据合并到本地账号
+/// 代理池绑定不随包迁移 (proxy_id 指向源机器的代理池，在这里无意义)：
+/// 覆盖已有账号时保留本地绑定，新导入的账号不绑定代理
+fn merge_bundle_metadata(saved: &mut Account, incoming: Account, is_duplicate: bool) {
+    saved.device_profile = incoming.device_profile;
+    saved.device_history = incoming.device_history;
+    saved.custom_label = incoming.custom_label;
+    if !is_duplicate {
+        saved.proxy_id = None;
+        saved.proxy_bound_at = None;
+    }
+    saved.upstream_proxy = incoming.upstream_proxy;
+    if saved.quota.is_none() {
+        saved.quota = incoming.quota;
+    }
+}
+
+/// 从加密包导入账号
+pub fn import_bundle(
+    path: &Path,
+    passphrase: &str,
+    strategy: BundleMergeStrategy,
+) -> Result<BundleImportResult, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read bundle: {}", e))?;
+    let file: BundleFile =
+        serde_json::from_str(&content).map_err(|_| "Not an account bundle file".to_string())?;
+    let payload = decrypt_payload(&file, passphrase)?;
+
+    let existing: std::collections::HashSet<String> = account::list_accounts()?
+        .into_iter()
+        .map(|acc| acc.email)
+        .collect();
+
+    let mut result = BundleImportResult::default();
+    for incoming in payload.accounts {
+        let is_duplicate = existing.contains(&incoming.email);
+        if is_duplicate {
+            result.duplicates.push(incoming.email.clone());
+            if strategy == BundleMergeStrategy::Skip {
+                result.skipped += 1;
+                continue;
+            }
+        }
+
+        let mut saved = account::upsert_account(
+            incoming.email.clone(),
+            incoming.name.clone(),
+            incoming.token.clone(),
+        )?;
+
+        // 保留迁移相关的元数据 (设备指纹 / 标签 / 代理绑定)
+        merge_bundle_metadata(&mut saved, incoming, is_duplicate);
+        account::save_account(&saved)?;
+
+        if is_duplicate {
+            result.updated += 1;
+        } else {
+            result.imported += 1;
+        }
+        result.accounts.push(saved);
+    }
+
+    logger::log_info(&format!(
+        "Imported account bundle: {} new, {} updated, {} skipped",
+        result.imported, result.updated, result.skipped
+    ));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TokenData;
+
+    // 测试中降低迭代次数以加快速度
+    const TEST_ITERATIONS: u32 = 1_000;
+
+    fn sample_payload() -> BundlePayload {
+        let token = TokenData::new(
+            "access".to_string(),
+            "refresh-secret".to_string(),
+            3600,
+            Some("a@x.com".to_string()),
+            None,
+            None,
+            false,
+        );
+        BundlePayload {
+            exported_at: 0,
+            accounts: vec![Account::new("id1".to_string(), "a@x.com".to_string(), token)],
+        }
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let file = encrypt_payload(&sample_payload(), "correct horse", TEST_ITERATIONS).unwrap();
+        // 密文中不应出现明文 refresh_token
+        assert!(!serde_json::to_string(&file).unwrap().contains("refresh-secret"));
+
+        let decrypted = decrypt_payload(&file, "correct horse").unwrap();
+        assert_eq!(decrypted.accounts.len(), 1);
+        assert_eq!(decrypted.accounts[0].token.refresh_token, "refresh-secret");
+    }
+
+    #[test]
+    fn test_bundle_rejects_wrong_passphrase() {
+        let file = encrypt_payload(&sample_payload(), "correct horse", TEST_ITERATIONS).unwrap();
+        assert!(decrypt_payload(&file, "wrong passphrase").is_err());
+        assert!(encrypt_payload(&sample_payload(), "short", TEST_ITERATIONS).is_err());
+    }
+
+    #[test]
+    fn test_overwrite_keeps_local_proxy_binding() {
+        let mut incoming = sample_payload().accounts.remove(0);
+        incoming.proxy_id = Some("source-proxy".to_string());
+        incoming.proxy_bound_at = Some(1_700_000_000);
+        incoming.custom_label = Some("work".to_string());
+
+        let mut local = sample_payload().accounts.remove(0);
+        local.proxy_id = Some("local-proxy".to_string());
+        merge_bundle_metadata(&mut local, incoming.clone(), true);
+        assert_eq!(local.proxy_id.as_deref(), Some("local-proxy"));
+        assert_eq!(local.custom_label.as_deref(), Some("work"));
+
+        let mut fresh = sample_payload().accounts.remove(0);
+        merge_bundle_metadata(&mut fresh, incoming, false);
+        assert_eq!(fresh.proxy_id, None);
+        assert_eq!(fresh.proxy_bound_at, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bundle_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("agm-bundle-test-{}.agbundle", uuid::Uuid::new_v4()));
+        write_bundle_file(&path, b"{}").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,593 @@
+//! Account Database Module
+//! 账号数据 (Token / 配额 / 状态) 与账号索引的 SQLite 存储
+//!
+//! 数据库位于 `<accounts_dir>/accounts.db` 并启用 WAL，每个数据库只保持一个共享连接，
+//! pragma 与建表仅在首次打开时执行。账号按 ID 存取，读-改-写在同一个 IMMEDIATE 事务内完成，
+//! Token 刷新与 UI 编辑并发时不会再互相覆盖。
+//! 账号索引 (原 accounts.json) 保存在 meta 表，增删账号时与账号数据在同一事务内写入。
+//! 旧版 `<id>.json` 文件在打开数据库时导入，确认记录已写入后重命名为 `*.imported` (权限收紧为 0600)，
+//! 保留降级回旧版本的途径；缺少 id / email / token 的 JSON 不视为账号文件。
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+pub const ACCOUNT_DB_FILE: &str = "accounts.db";
+const LEGACY_FILE_SUFFIX: &str = ".json";
+/// 早期版本导入后保留的旧文件备份
+const LEGACY_MIGRATED_SUFFIX: &str = ".json.migrated";
+/// 导入后旧文件追加的后缀
+const IMPORTED_SUFFIX: &str = ".imported";
+const INDEX_KEY: &str = "account_index";
+
+type SharedConnection = Arc<Mutex<Connection>>;
+
+/// 每个数据库文件一个共享连接
+static CONNECTIONS: OnceLock<Mutex<HashMap<PathBuf, SharedConnection>>> = OnceLock::new();
+
+/// 数据库维护结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountDbMaintenance {
+    pub path: String,
+    pub size_before: u64,
+    pub size_after: u64,
+    pub account_count: usize,
+}
+
+pub fn get_account_db_path(accounts_dir: &Path) -> PathBuf {
+    accounts_dir.join(ACCOUNT_DB_FILE)
+}
+
+fn open_db(accounts_dir: &Path) -> Result<Connection, String> {
+    std::fs::create_dir_all(accounts_dir)
+        .map_err(|e| format!("failed_to_create_accounts_dir: {}", e))?;
+    let conn = Connection::open(get_account_db_path(accounts_dir)).map_err(|e| e.to_string())?;
+
+    // Enable WAL mode for better concurrency
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+
+    // Set busy timeout
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS accounts (
+            id TEXT PRIMARY KEY,
+            email TEXT NOT NULL DEFAULT '',
+            data TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_accounts_email ON accounts (email);
+        CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    )
+    .map_err(|e| e.to_string())?;
+
+    import_legacy_files(&conn, accounts_dir);
+    Ok(conn)
+}
+
+/// 获取 (必要时打开) 账号目录对应的共享连接
+fn connection(accounts_dir: &Path) -> Result<SharedConnection, String> {
+    let db_path = get_account_db_path(accounts_dir);
+    let mut pool = CONNECTIONS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(conn) = pool.get(&db_path) {
+        return Ok(conn.clone());
+    }
+    let conn = Arc::new(Mutex::new(open_db(accounts_dir)?));
+    pool.insert(db_path, conn.clone());
+    Ok(conn)
+}
+
+fn with_conn<T>(
+    accounts_dir: &Path,
+    f: impl FnOnce(&mut Connection) -> Result<T, String>,
+) -> Result<T, String> {
+    let conn = connection(accounts_dir)?;
+    let mut guard = conn.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+/// 关闭共享连接 (测试清理等场景)，下次访问时重新打开；替换数据库文件请用 replace_db_file
+pub fn close_db(accounts_dir: &Path) {
+    let Some(pool) = CONNECTIONS.get() else {
+        return;
+    };
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&get_account_db_path(accounts_dir));
+    // 等待进行中的操作结束后再释放
+    if let Some(conn) = conn {
+        drop(conn.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+/// 用 `source` 替换数据库文件 (恢复快照)
+///
+/// 整个替换过程持有连接池锁与共享连接锁，期间 connection() 无法重新打开数据库，
+/// 已克隆连接的调用方也会阻塞；替换完成后在同一共享连接内换入新打开的连接，
+/// 持有旧 Arc 的调用方之后访问的也是新数据库。先复制到同目录临时文件再 rename，避免半写入。
+pub fn replace_db_file(accounts_dir: &Path, source: &Path) -> Result<(), String> {
+    let db_path = get_account_db_path(accounts_dir);
+    let tmp_path = db_path.with_extension("db.restore");
+    std::fs::copy(source, &tmp_path).map_err(|e| format!("failed_to_restore_account_db: {}", e))?;
+
+    let pool = CONNECTIONS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let shared = pool.get(&db_path).cloned();
+    let mut guard = shared
+        .as_ref()
+        .map(|conn| conn.lock().unwrap_or_else(|e| e.into_inner()));
+
+    // 关闭旧连接 (释放文件句柄并完成 WAL 检查点)
+    if let Some(guard) = guard.as_mut() {
+        let placeholder = Connection::open_in_memory().map_err(|e| e.to_string())?;
+        drop(std::mem::replace(&mut **guard, placeholder));
+    }
+
+    // 旧的 WAL/SHM 属于被替换的数据库，必须一并删除
+    for suffix in ["db-wal", "db-shm"] {
+        let _ = std::fs::remove_file(db_path.with_extension(suffix));
+    }
+    let renamed = std::fs::rename(&tmp_path, &db_path);
+    if renamed.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+
+    // 无论替换成功与否都重新打开，共享连接不能停留在占位连接上
+    if let Some(guard) = guard.as_mut() {
+        **guard = open_db(accounts_dir)?;
+    }
+    drop(guard);
+    drop(pool);
+    renamed.map_err(|e| format!("failed_to_restore_account_db: {}", e))
+}
+
+fn email_of(data: &Value) -> String {
+    data.get("email")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn upsert(conn: &Connection, id: &str, data: &Value) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+    conn.execute(
+        "INSERT INTO accounts (id, email, data, updated_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(id) DO UPDATE SET email = excluded.email, data = excluded.data, updated_at = excluded.updated_at",
+        params![id, email_of(data), content, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("failed_to_write_account_data: {}", e))?;
+    Ok(())
+}
+
+fn select(conn: &Connection, id: &str) -> Result<Option<String>, String> {
+    conn.query_row("SELECT data FROM accounts WHERE id = ?1", params![id], |row| {
+        row.get::<_, String>(0)
+    })
+    .optional()
+    .map_err(|e| format!("failed_to_read_account_data: {}", e))
+}
+
+fn put_index(conn: &Connection, index: &Value) -> Result<(), String> {
+    let content = serde_json::to_string(index)
+        .map_err(|e| format!("failed_to_serialize_account_index: {}", e))?;
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![INDEX_KEY, content],
+    )
+    .map_err(|e| format!("failed_to_write_account_index: {}", e))?;
+    Ok(())
+}
+
+/// 旧版账号文件至少包含与文件名一致的 id，以及 email 与 token
+fn is_legacy_account(value: &Value, id: &str) -> bool {
+    value.get("id").and_then(|v| v.as_str()) == Some(id)
+        && value.get("email").is_some_and(|v| v.is_string())
+        && value.get("token").is_some_and(|v| v.is_object())
+}
+
+/// 导入后保留旧文件备份 (`<name>.imported`)，文件中可能是明文 Token，权限收紧为仅本人可读
+fn archive_imported_file(path: &Path) -> Result<(), String> {
+    let mut archived = path.as_os_str().to_owned();
+    archived.push(IMPORTED_SUFFIX);
+    let archived = PathBuf::from(archived);
+    std::fs::rename(path, &archived)
+        .map_err(|e| format!("failed_to_archive_imported_account_file: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&archived, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+/// 导入单个旧版账号文件 (已存在的数据库记录优先)，确认记录存在后归档文件
+fn import_legacy_file(conn: &Connection, path: &Path, id: &str) -> Result<(), String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed_to_read_account_data: {}", e))?;
+    let value: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+    if !is_legacy_account(&value, id) {
+        // 非账号 JSON 原样保留
+        return Err("not_an_account_file".to_string());
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO accounts (id, email, data, updated_at) VALUES (?1, ?2, ?3, ?4)",
+        params![id, email_of(&value), content, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("failed_to_import_account_file: {}", e))?;
+
+    if select(conn, id)?.is_none() {
+        return Err(format!("account_import_not_verified: {}", id));
+    }
+    archive_imported_file(path)
+}
+
+/// 导入账号目录下遗留的 `<id>.json` 与 `<id>.json.migrated` 文件
+/// 同一 ID 两者并存时 `<id>.json` 优先 (先导入)，`.migrated` 随后因记录已存在只做归档
+fn import_legacy_files(conn: &Connection, accounts_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(accounts_dir) else {
+        return;
+    };
+    let mut current = Vec::new();
+    let mut migrated = Vec::new();
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if let Some(id) = name.strip_suffix(LEGACY_MIGRATED_SUFFIX).filter(|id| !id.is_empty()) {
+            migrated.push((id.to_string(), path.clone()));
+        } else if let Some(id) = name.strip_suffix(LEGACY_FILE_SUFFIX).filter(|id| !id.is_empty()) {
+            current.push((id.to_string(), path.clone()));
+        }
+    }
+    for (id, path) in current.into_iter().chain(migrated) {
+        if let Err(e) = import_legacy_file(conn, &path, &id) {
+            tracing::warn!("Failed to import legacy account file {:?}: {}", path, e);
+        }
+    }
+}
+
+/// 读取账号原始 JSON (Token 保持磁盘上的加密形态)，不存在时返回 None
+pub fn read_account(accounts_dir: &Path, account_id: &str) -> Result<Option<String>, String> {
+    if !accounts_dir.exists() {
+        return Ok(None);
+    }
+    with_conn(accounts_dir, |conn| select(conn, account_id))
+}
+
+/// 读取并解析账号 JSON
+pub fn read_account_value(accounts_dir: &Path, account_id: &str) -> Result<Option<Value>, String> {
+    read_account(accounts_dir, account_id)?
+        .map(|content| {
+            serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+        })
+        .transpose()
+}
+
+pub fn account_exists(accounts_dir: &Path, account_id: &str) -> bool {
+    matches!(read_account(accounts_dir, account_id), Ok(Some(_)))
+}
+
+/// 整体写入账号数据
+pub fn write_account(accounts_dir: &Path, account_id: &str, data: &Value) -> Result<(), String> {
+    with_conn(accounts_dir, |conn| upsert(conn, account_id, data))
+}
+
+/// 在事务内对账号数据执行读-改-写，返回修改后的数据
+/// 适用于只修改部分字段的场景 (刷新 Token、更新配额、禁用标记等)，避免覆盖并发写入的其他字段
+pub fn update_account<F>(accounts_dir: &Path, account_id: &str, f: F) -> Result<Value, String>
+where
+    F: FnOnce(&mut Value) -> Result<(), String>,
+{
+    with_conn(accounts_dir, |conn| {
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("failed_to_begin_transaction: {}", e))?;
+        let content = select(&tx, account_id)?
+            .ok_or_else(|| format!("account_not_found: {}", account_id))?;
+        let mut value: Value = serde_json::from_str(&content)
+            .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+        f(&mut value)?;
+        upsert(&tx, account_id, &value)?;
+        tx.commit()
+            .map_err(|e| format!("failed_to_commit_transaction: {}", e))?;
+        Ok(value)
+    })
+}
+
+/// 删除账号
+pub fn delete_account(accounts_dir: &Path, account_id: &str) -> Result<(), String> {
+    if !accounts_dir.exists() {
+        return Ok(());
+    }
+    with_conn(accounts_dir, |conn| {
+        conn.execute("DELETE FROM accounts WHERE id = ?1", params![account_id])
+            .map_err(|e| format!("failed_to_delete_account_data: {}", e))?;
+        Ok(())
+    })
+}
+
+/// 列出目录下所有账号 ID (会先导入新出现的旧版 JSON 文件)
+pub fn list_account_ids(accounts_dir: &Path) -> Result<Vec<String>, String> {
+    if !accounts_dir.exists() {
+        return Ok(Vec::new());
+    }
+    with_conn(accounts_dir, |conn| {
+        import_legacy_files(conn, accounts_dir);
+        let mut stmt = conn
+            .prepare("SELECT id FROM accounts ORDER BY id")
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(ids)
+    })
+}
+
+/// 读取账号索引原始 JSON，尚未写入时返回 None
+pub fn read_index(accounts_dir: &Path) -> Result<Option<String>, String> {
+    with_conn(accounts_dir, |conn| {
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", params![INDEX_KEY], |row| {
+            row.get::<_, String>(0)
+        })
+        .optional()
+        .map_err(|e| format!("failed_to_read_account_index: {}", e))
+    })
+}
+
+/// 写入账号索引
+pub fn write_index(accounts_dir: &Path, index: &Value) -> Result<(), String> {
+    with_conn(accounts_dir, |conn| put_index(conn, index))
+}
+
+/// 在同一事务内写入账号数据与索引 (新增 / 更新账号)
+pub fn write_account_and_index(
+    accounts_dir: &Path,
+    account_id: &str,
+    data: &Value,
+    index: &Value,
+) -> Result<(), String> {
+    with_conn(accounts_dir, |conn| {
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("failed_to_begin_transaction: {}", e))?;
+        upsert(&tx, account_id, data)?;
+        put_index(&tx, index)?;
+        tx.commit()
+            .map_err(|e| format!("failed_to_commit_transaction: {}", e))
+    })
+}
+
+/// 在同一事务内删除账号并写入索引
+pub fn delete_accounts_and_index(
+    accounts_dir: &Path,
+    account_ids: &[String],
+    index: &Value,
+) -> Result<(), String> {
+    with_conn(accounts_dir, |conn| {
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("failed_to_begin_transaction: {}", e))?;
+        for id in account_ids {
+            tx.execute("DELETE FROM accounts WHERE id = ?1", params![id])
+                .map_err(|e| format!("failed_to_delete_account_data: {}", e))?;
+        }
+        put_index(&tx, index)?;
+        tx.commit()
+            .map_err(|e| format!("failed_to_commit_transaction: {}", e))
+    })
+}
+
+fn db_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn count_accounts(conn: &Connection) -> usize {
+    conn.query_row("SELECT COUNT(*) FROM accounts", [], |row| row.get::<_, i64>(0))
+        .map(|n| n as usize)
+        .unwrap_or(0)
+}
+
+/// 将账号数据库在线备份到指定文件 (VACUUM INTO，得到紧凑且一致的快照)
+pub fn backup_db(accounts_dir: &Path, target: &Path) -> Result<AccountDbMaintenance, String> {
+    if target.exists() {
+        return Err(format!("Backup target already exists: {:?}", target));
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("failed_to_create_backup_dir: {}", e))?;
+    }
+    with_conn(accounts_dir, |conn| {
+        let size_before = db_size(&get_account_db_path(accounts_dir));
+        conn.execute("VACUUM INTO ?1", params![target.to_string_lossy().to_string()])
+            .map_err(|e| format!("failed_to_backup_account_db: {}", e))?;
+        Ok(AccountDbMaintenance {
+            path: target.to_string_lossy().to_string(),
+            size_before,
+            size_after: db_size(target),
+            account_count: count_accounts(conn),
+        })
+    })
+}
+
+/// 整理数据库文件 (合并 WAL 并 VACUUM)
+pub fn vacuum_db(accounts_dir: &Path) -> Result<AccountDbMaintenance, String> {
+    let db_path = get_account_db_path(accounts_dir);
+    let wal_path = db_path.with_extension("db-wal");
+    with_conn(accounts_dir, |conn| {
+        let size_before = db_size(&db_path) + db_size(&wal_path);
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE); VACUUM;")
+            .map_err(|e| format!("failed_to_vacuum_account_db: {}", e))?;
+        Ok(AccountDbMaintenance {
+            path: db_path.to_string_lossy().to_string(),
+            size_before,
+            size_after: db_size(&db_path) + db_size(&wal_path),
+            account_count: count_accounts(conn),
+        })
+    })
+}
+
+/// 备份当前数据目录的账号数据库，未指定目标时保存到 `<data_dir>/backups/accounts-<时间>.db`
+pub fn backup_account_db(target: Option<PathBuf>) -> Result<AccountDbMaintenance, String> {
+    let accounts_dir = crate::modules::account::get_accounts_dir()?;
+    let target = match target {
+        Some(path) => path,
+        None => crate::modules::account::get_data_dir()?.join("backups").join(format!(
+            "accounts-{}.db",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        )),
+    };
+    let result = backup_db(&accounts_dir, &target)?;
+    crate::modules::logger::log_info(&format!(
+        "[AccountDb] Backed up {} accounts to {}",
+        result.account_count, result.path
+    ));
+    Ok(result)
+}
+
+/// 整理当前数据目录的账号数据库
+pub fn vacuum_account_db() -> Result<AccountDbMaintenance, String> {
+    let accounts_dir = crate::modules::account::get_accounts_dir()?;
+    let result = vacuum_db(&accounts_dir)?;
+    crate::modules::logger::log_info(&format!(
+        "[AccountDb] Vacuumed account database: {} -> {} bytes",
+        result.size_before, result.size_after
+    ));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_accounts_dir() -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("antigravity-account-db-test-{}", uuid::Uuid::new_v4()))
+            .join("accounts");
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_legacy_files_are_imported_and_archived() {
+        let dir = temp_accounts_dir();
+        let path = dir.join("acc1.json");
+        let stale = dir.join("acc1.json.migrated");
+        let migrated = dir.join("acc3.json.migrated");
+        let stray = dir.join("settings.json");
+        std::fs::write(&path, r#"{"id":"acc1","email":"a@test.com","token":{},"disabled":false}"#).unwrap();
+        std::fs::write(&stale, r#"{"id":"acc1","email":"old@test.com","token":{}}"#).unwrap();
+        std::fs::write(&migrated, r#"{"id":"acc3","email":"c@test.com","token":{}}"#).unwrap();
+        std::fs::write(&stray, r#"{"theme":"dark"}"#).unwrap();
+
+        let ids = list_account_ids(&dir).unwrap();
+        assert_eq!(ids, vec!["acc1".to_string(), "acc3".to_string()]);
+        // 导入后归档为 *.imported，保留降级途径
+        assert!(!path.exists());
+        assert!(dir.join("acc1.json.imported").exists());
+        assert!(dir.join("acc1.json.migrated.imported").exists());
+        assert!(dir.join("acc3.json.migrated.imported").exists());
+        // 非账号 JSON 不受影响
+        assert!(stray.exists());
+
+        // <id>.json 优先于 <id>.json.migrated
+        let value = read_account_value(&dir, "acc1").unwrap().unwrap();
+        assert_eq!(value["email"], "a@test.com");
+
+        delete_account(&dir, "acc1").unwrap();
+        assert!(read_account(&dir, "acc1").unwrap().is_none());
+        close_db(&dir);
+        let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_update_account_preserves_other_fields() {
+        let dir = temp_accounts_dir();
+        write_account(
+            &dir,
+            "acc2",
+            &serde_json::json!({ "id": "acc2", "email": "b@test.com", "token": { "access_token": "old" } }),
+        )
+        .unwrap();
+
+        // 模拟 UI 修改标签的同时刷新 Token：两次局部更新互不覆盖
+        update_account(&dir, "acc2", |v| {
+            v["custom_label"] = Value::String("work".to_string());
+            Ok(())
+        })
+        .unwrap();
+        let updated = update_account(&dir, "acc2", |v| {
+            v["token"]["access_token"] = Value::String("new".to_string());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(updated["custom_label"], "work");
+        assert_eq!(updated["token"]["access_token"], "new");
+        assert!(update_account(&dir, "missing", |_| Ok(())).is_err());
+        close_db(&dir);
+        let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_account_and_index_written_together() {
+        let dir = temp_accounts_dir();
+        let index = serde_json::json!({ "version": "2.0", "accounts": [{ "id": "acc4" }] });
+        write_account_and_index(&dir, "acc4", &serde_json::json!({ "id": "acc4" }), &index).unwrap();
+        assert!(account_exists(&dir, "acc4"));
+        let stored: Value = serde_json::from_str(&read_index(&dir).unwrap().unwrap()).unwrap();
+        assert_eq!(stored, index);
+
+        let empty = serde_json::json!({ "version": "2.0", "accounts": [] });
+        delete_accounts_and_index(&dir, &["acc4".to_string()], &empty).unwrap();
+        assert!(!account_exists(&dir, "acc4"));
+        let stored: Value = serde_json::from_str(&read_index(&dir).unwrap().unwrap()).unwrap();
+        assert_eq!(stored, empty);
+        close_db(&dir);
+        let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_replace_db_file_swaps_shared_connection() {
+        let snapshot_dir = temp_accounts_dir();
+        write_account(&snapshot_dir, "restored", &serde_json::json!({ "id": "restored" })).unwrap();
+        close_db(&snapshot_dir);
+
+        let dir = temp_accounts_dir();
+        write_account(&dir, "current", &serde_json::json!({ "id": "current" })).unwrap();
+        // 替换前克隆的连接在替换后也指向新数据库
+        let held = connection(&dir).unwrap();
+
+        replace_db_file(&dir, &get_account_db_path(&snapshot_dir)).unwrap();
+        assert!(account_exists(&dir, "restored"));
+        assert!(!account_exists(&dir, "current"));
+        let guard = held.lock().unwrap();
+        assert!(select(&guard, "restored").unwrap().is_some());
+        drop(guard);
+
+        close_db(&dir);
+        let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+        let _ = std::fs::remove_dir_all(snapshot_dir.parent().unwrap());
+    }
+}
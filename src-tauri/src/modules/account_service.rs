@@ -121,6 +121,40 @@ impl AccountService {
         self.process_oauth_token(token_res).await
     }
 
+    /// 带 login_hint 的 OAuth 登录 (Google 账号选择页会预选该邮箱)
+    pub async fn start_oauth_login_with_hint(
+        &self,
+        oauth_client_key: Option<String>,
+        login_hint: Option<String>,
+    ) -> Result<Account, String> {
+        let handle = match &self.integration {
+            modules::integration::SystemManager::Desktop(h) => Some(h.clone()),
+            modules::integration::SystemManager::Headless => None,
+        };
+        let token_res =
+            modules::oauth_server::start_oauth_flow_with_hint(handle, oauth_client_key, login_hint).await?;
+        self.process_oauth_token(token_res).await
+    }
+
+    /// [NEW] 一键重新授权: 使用账号邮箱作为 login_hint，沿用原 OAuth Client
+    pub async fn reauthorize_account(&self, account_id: &str) -> Result<Account, String> {
+        let existing = modules::load_account(account_id)?;
+        let account = self
+            .start_oauth_login_with_hint(
+                existing.token.oauth_client_key.clone(),
+                Some(existing.email.clone()),
+            )
+            .await?;
+
+        if !account.email.eq_ignore_ascii_case(&existing.email) {
+            return Err(format!(
+                "Signed in as {} instead of {}; it was saved as a separate account",
+                account.email, existing.email
+            ));
+        }
+        Ok(account)
+    }
+
     pub async fn complete_oauth_login(&self) -> Result<Account, String> {
         let handle = match &self.integration {
             modules::integration::SystemManager::Desktop(h) => Some(h.clone()),
@@ -142,6 +176,24 @@ impl AccountService {
         modules::oauth_server::submit_oauth_code(code, state).await
     }
 
+    /// 手动粘贴授权码登录 (无浏览器 / 远程服务器场景): 第一步生成授权链接
+    pub fn begin_manual_oauth_login(
+        &self,
+        oauth_client_key: Option<String>,
+    ) -> Result<modules::oauth_server::ManualOAuthStart, String> {
+        modules::oauth_server::begin_manual_oauth(oauth_client_key)
+    }
+
+    /// 手动粘贴授权码登录: 第二步提交回调 URL 或授权码并保存账号
+    pub async fn finish_manual_oauth_login(
+        &self,
+        input: &str,
+        state: Option<String>,
+    ) -> Result<Account, String> {
+        let token_res = modules::oauth_server::finish_manual_oauth(input, state).await?;
+        self.process_oauth_token(token_res).await
+    }
+
     async fn process_oauth_token(
         &self,
         token_res: modules::oauth::TokenResponse,
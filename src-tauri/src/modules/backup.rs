@@ -0,0 +1,325 @@
+// 应用数据自动备份
+// 定期将账号数据库 (accounts.db) 与配置文件快照到 backups/snapshots/<id>/，按数量轮转保留 (仅定时快照参与轮转)。
+// 恢复前会先为当前数据生成一次 pre_restore 快照，恢复操作本身也可以撤销。
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::time::{self, Duration};
+
+use super::{account, account_db, config, logger};
+
+const SNAPSHOT_DIR: &str = "backups/snapshots";
+const MANIFEST_FILE: &str = "manifest.json";
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+/// 调度器检查间隔 (是否到达 interval_hours 由上次快照时间决定)
+const BACKUP_TICK_SECS: u64 = 600;
+
+/// 随快照一起保存的数据目录文件 (存在才复制)，账号索引已包含在 accounts.db 中
+const SNAPSHOT_FILES: &[&str] = &["gui_config.json", "config_profiles.json"];
+/// 旧版快照中的独立索引文件，仅在恢复时写回 (随后由 load_account_index 导入数据库)
+const LEGACY_SNAPSHOT_FILES: &[&str] = &["accounts.json"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotReason {
+    Scheduled,
+    Manual,
+    PreRestore,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSnapshot {
+    pub id: String,
+    pub created_at: i64,
+    pub reason: SnapshotReason,
+    pub account_count: usize,
+    pub files: Vec<String>,
+    /// 快照目录总大小 (字节)，列表时计算
+    #[serde(default)]
+    pub size: u64,
+}
+
+fn snapshots_root() -> Result<PathBuf, String> {
+    let root = account::get_data_dir()?.join(SNAPSHOT_DIR);
+    fs::create_dir_all(&root).map_err(|e| format!("failed_to_create_backup_dir: {}", e))?;
+    Ok(root)
+}
+
+/// 快照 ID 只能是本模块生成的目录名，防止通过 ID 访问其他路径
+fn snapshot_dir(id: &str) -> Result<PathBuf, String> {
+    let valid = id.starts_with(SNAPSHOT_PREFIX)
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid snapshot id: {}", id));
+    }
+    let dir = snapshots_root()?.join(id);
+    if !dir.join(MANIFEST_FILE).exists() {
+        return Err(format!("Snapshot not found: {}", id));
+    }
+    Ok(dir)
+}
+
+/// 快照包含账号令牌，目录与文件仅允许当前用户访问
+#[cfg(unix)]
+fn restrict_permissions(path: &Path, mode: u32) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| format!("failed_to_set_snapshot_permissions: {}", e))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path, _mode: u32) -> Result<(), String> {
+    Ok(())
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.metadata().ok())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn read_manifest(dir: &Path) -> Option<BackupSnapshot> {
+    let content = fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+    let mut snapshot: BackupSnapshot = serde_json::from_str(&content).ok()?;
+    snapshot.size = dir_size(dir);
+    Some(snapshot)
+}
+
+/// 列出全部快照 (新的在前)
+pub fn list_snapshots() -> Result<Vec<BackupSnapshot>, String> {
+    let root = snapshots_root()?;
+    let mut snapshots: Vec<BackupSnapshot> = fs::read_dir(&root)
+        .map_err(|e| format!("failed_to_read_backup_dir: {}", e))?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| read_manifest(&e.path()))
+        .collect();
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+    Ok(snapshots)
+}
+
+/// 生成一次快照
+pub fn create_snapshot(reason: SnapshotReason) -> Result<BackupSnapshot, String> {
+    let root = snapshots_root()?;
+    let data_dir = account::get_data_dir()?;
+    let now = chrono::Local::now();
+
+    let mut id = format!("{}{}", SNAPSHOT_PREFIX, now.format("%Y%m%d-%H%M%S"));
+    if root.join(&id).exists() {
+        id = format!("{}-{}", id, &uuid::Uuid::new_v4().simple().to_string()[..6]);
+    }
+    let dir = root.join(&id);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed_to_create_snapshot_dir: {}", e))?;
+
+    let result = (|| {
+        restrict_permissions(&dir, 0o700)?;
+        let mut files = Vec::new();
+        // 账号数据库使用 VACUUM INTO 得到一致的快照，不受并发写入影响
+        let db = account_db::backup_db(
+            &account::get_accounts_dir()?,
+            &dir.join(account_db::ACCOUNT_DB_FILE),
+        )?;
+        restrict_permissions(&dir.join(account_db::ACCOUNT_DB_FILE), 0o600)?;
+        files.push(account_db::ACCOUNT_DB_FILE.to_string());
+
+        for name in SNAPSHOT_FILES {
+            let src = data_dir.join(name);
+            if src.is_file() {
+                fs::copy(&src, dir.join(name))
+                    .map_err(|e| format!("failed_to_copy_{}: {}", name, e))?;
+                restrict_permissions(&dir.join(name), 0o600)?;
+                files.push(name.to_string());
+            }
+        }
+
+        let snapshot = BackupSnapshot {
+            id: id.clone(),
+            created_at: now.timestamp(),
+            reason,
+            account_count: db.account_count,
+            files,
+            size: 0,
+        };
+        let manifest = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| format!("failed_to_serialize_manifest: {}", e))?;
+        fs::write(dir.join(MANIFEST_FILE), manifest)
+            .map_err(|e| format!("failed_to_write_manifest: {}", e))?;
+        restrict_permissions(&dir.join(MANIFEST_FILE), 0o600)?;
+        Ok(snapshot)
+    })();
+
+    match result {
+        Ok(mut snapshot) => {
+            snapshot.size = dir_size(&dir);
+            logger::log_info(&format!(
+                "[Backup] Created snapshot {} ({:?}, {} accounts)",
+                snapshot.id, reason, snapshot.account_count
+            ));
+            Ok(snapshot)
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&dir);
+            Err(e)
+        }
+    }
+}
+
+/// 从列表中挑出超出保留数量的定时快照 (列表已按新到旧排序)
+/// 手动快照与恢复前的撤销点不参与轮转，只能由用户删除
+fn snapshots_to_prune(snapshots: &[BackupSnapshot], keep: usize) -> Vec<String> {
+    snapshots
+        .iter()
+        .filter(|s| s.reason == SnapshotReason::Scheduled)
+        .skip(keep.max(1))
+        .map(|s| s.id.clone())
+        .collect()
+}
+
+/// 按保留数量删除旧的定时快照，返回删除的数量
+pub fn prune_snapshots(keep: usize) -> Result<usize, String> {
+    let snapshots = list_snapshots()?;
+    let mut removed = 0;
+    for id in snapshots_to_prune(&snapshots, keep) {
+        if let Ok(dir) = snapshot_dir(&id) {
+            match fs::remove_dir_all(&dir) {
+                Ok(()) => removed += 1,
+                Err(e) => logger::log_warn(&format!("[Backup] Failed to remove snapshot {}: {}", id, e)),
+            }
+        }
+    }
+    Ok(removed)
+}
+
+pub fn delete_snapshot(id: &str) -> Result<(), String> {
+    let dir = snapshot_dir(id)?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("failed_to_delete_snapshot: {}", e))
+}
+
+/// 用指定快照覆盖当前数据，返回恢复前自动生成的快照 (可用于撤销)
+/// 调用方负责在恢复后重新加载账号池与配置
+pub fn restore_snapshot(id: &str) -> Result<BackupSnapshot, String> {
+    let dir = snapshot_dir(id)?;
+    let manifest = read_manifest(&dir).ok_or_else(|| format!("Snapshot manifest is invalid: {}", id))?;
+    let data_dir = account::get_data_dir()?;
+    let accounts_dir = account::get_accounts_dir()?;
+
+    let pre_restore = create_snapshot(SnapshotReason::PreRestore)?;
+
+    for name in &manifest.files {
+        let src = dir.join(name);
+        if !src.is_file() {
+            return Err(format!("Snapshot file missing: {}", name));
+        }
+        if name == account_db::ACCOUNT_DB_FILE {
+            account_db::replace_db_file(&accounts_dir, &src)?;
+        } else if SNAPSHOT_FILES.contains(&name.as_str())
+            || LEGACY_SNAPSHOT_FILES.contains(&name.as_str())
+        {
+            fs::copy(&src, data_dir.join(name))
+                .map_err(|e| format!("failed_to_restore_{}: {}", name, e))?;
+        }
+    }
+
+    logger::log_info(&format!(
+        "[Backup] Restored snapshot {} (previous data saved as {})",
+        id, pre_restore.id
+    ));
+    Ok(pre_restore)
+}
+
+/// 定时快照：距上次快照超过 interval_hours 时生成新快照并轮转
+pub fn start_backup_scheduler() {
+    tauri::async_runtime::spawn(async move {
+        logger::log_info("[Backup] Scheduler started");
+        let mut interval = time::interval(Duration::from_secs(BACKUP_TICK_SECS));
+
+        loop {
+            interval.tick().await;
+
+            let Ok(app_config) = config::load_app_config() else {
+                continue;
+            };
+            let backup = app_config.backup;
+            if !backup.enabled || backup.interval_hours == 0 {
+                continue;
+            }
+
+            let result = tokio::task::spawn_blocking(move || -> Result<(), String> {
+                let last = list_snapshots()?
+                    .into_iter()
+                    .find(|s| s.reason == SnapshotReason::Scheduled)
+                    .map(|s| s.created_at)
+                    .unwrap_or(0);
+                let due = chrono::Utc::now().timestamp() - last >= backup.interval_hours as i64 * 3600;
+                if due {
+                    create_snapshot(SnapshotReason::Scheduled)?;
+                    prune_snapshots(backup.keep as usize)?;
+                }
+                Ok(())
+            })
+            .await;
+
+            match result {
+                Ok(Err(e)) => logger::log_error(&format!("[Backup] Scheduled snapshot failed: {}", e)),
+                Err(e) => logger::log_error(&format!("[Backup] Scheduled snapshot task failed: {}", e)),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str, created_at: i64) -> BackupSnapshot {
+        snapshot_with_reason(id, created_at, SnapshotReason::Scheduled)
+    }
+
+    fn snapshot_with_reason(id: &str, created_at: i64, reason: SnapshotReason) -> BackupSnapshot {
+        BackupSnapshot {
+            id: id.to_string(),
+            created_at,
+            reason,
+            account_count: 0,
+            files: vec![],
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_snapshots_to_prune_keeps_newest() {
+        let snapshots = vec![snapshot("c", 3), snapshot("b", 2), snapshot("a", 1)];
+        assert_eq!(snapshots_to_prune(&snapshots, 2), vec!["a".to_string()]);
+        // keep=0 仍至少保留最新的一份
+        assert_eq!(snapshots_to_prune(&snapshots, 0), vec!["b".to_string(), "a".to_string()]);
+        assert!(snapshots_to_prune(&snapshots, 5).is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_to_prune_skips_manual_and_pre_restore() {
+        let snapshots = vec![
+            snapshot("e", 5),
+            snapshot_with_reason("d", 4, SnapshotReason::PreRestore),
+            snapshot("c", 3),
+            snapshot_with_reason("b", 2, SnapshotReason::Manual),
+            snapshot("a", 1),
+        ];
+        assert_eq!(snapshots_to_prune(&snapshots, 1), vec!["c".to_string(), "a".to_string()]);
+        assert!(snapshots_to_prune(&snapshots, 3).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_id_rejects_path_traversal() {
+        assert!(snapshot_dir("../accounts").is_err());
+        assert!(snapshot_dir("snapshot-../../etc").is_err());
+    }
+}
@@ -9,6 +9,14 @@ const CONFIG_FILE: &str = "gui_config.json";
 
 /// Load application configuration
 pub fn load_app_config() -> Result<AppConfig, String> {
+    let mut config = load_persisted_app_config()?;
+    // [NEW] 环境变量覆盖只作用于内存中的配置 (headless 部署)，不会写回配置文件
+    super::env_overrides::apply_active_overrides(&mut config);
+    Ok(config)
+}
+
+/// Load configuration as stored in gui_config.json (without environment overrides)
+fn load_persisted_app_config() -> Result<AppConfig, String> {
     let data_dir = get_data_dir()?;
     let config_path = data_dir.join(CONFIG_FILE);
     
@@ -25,55 +33,8 @@ pub fn load_app_config() -> Result<AppConfig, String> {
     let mut v: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("failed_to_parse_config_file: {}", e))?;
     
-    let mut modified = false;
-
-    // Migration logic
-    if let Some(proxy) = v.get_mut("proxy") {
-        // [FIX #1738] Enhanced type checking for custom_mapping
-        // Ensures the field is always parsed as an object, preventing type mismatch errors
-        let mut custom_mapping = match proxy.get("custom_mapping") {
-            Some(m) if m.is_object() => m.as_object().unwrap().clone(),
-            Some(m) => {
-                // If custom_mapping is not an object type (e.g., string), log warning and reset to empty
-                tracing::warn!("Invalid custom_mapping type (expected object, got {:?}), resetting to empty", m);
-                serde_json::Map::new()
-            }
-            None => serde_json::Map::new(),
-        };
-
-        // Migrate Anthropic mapping
-        if let Some(anthropic) = proxy.get_mut("anthropic_mapping").and_then(|m| m.as_object_mut()) {
-            for (k, v) in anthropic.iter() {
-                // Only move non-series fields, as series fields are now handled by Preset logic or builtin tables
-                if !k.ends_with("-series") {
-                    if !custom_mapping.contains_key(k) {
-                        custom_mapping.insert(k.clone(), v.clone());
-                    }
-                }
-            }
-            // Remove old field
-            proxy.as_object_mut().unwrap().remove("anthropic_mapping");
-            modified = true;
-        }
-
-        // Migrate OpenAI mapping
-        if let Some(openai) = proxy.get_mut("openai_mapping").and_then(|m| m.as_object_mut()) {
-            for (k, v) in openai.iter() {
-                if !k.ends_with("-series") {
-                    if !custom_mapping.contains_key(k) {
-                        custom_mapping.insert(k.clone(), v.clone());
-                    }
-                }
-            }
-            // Remove old field
-            proxy.as_object_mut().unwrap().remove("openai_mapping");
-            modified = true;
-        }
-
-        if modified {
-            proxy.as_object_mut().unwrap().insert("custom_mapping".to_string(), serde_json::Value::Object(custom_mapping));
-        }
-    }
+    // [NEW] 版本化迁移：按 schema_version 执行未应用的步骤 (迁移前自动备份)
+    let modified = super::migration::migrate_config_value(&config_path, &mut v)?;
 
     let config: AppConfig = serde_json::from_value(v)
         .map_err(|e| format!("failed_to_convert_config_after_migration: {}", e))?;
@@ -91,7 +52,18 @@ pub fn save_app_config(config: &AppConfig) -> Result<(), String> {
     let data_dir = get_data_dir()?;
     let config_path = data_dir.join(CONFIG_FILE);
     
-    let content = serde_json::to_string_pretty(config)
+    // 由当前结构体序列化的内容即为最新 schema (前端回传的配置可能缺少该字段)
+    let mut config = config.clone();
+    config.schema_version = super::migration::CONFIG_SCHEMA_VERSION;
+    // [NEW] 被环境变量覆盖的字段保留文件中的原值
+    if super::env_overrides::is_active() {
+        let persisted = fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<AppConfig>(&content).ok())
+            .unwrap_or_else(AppConfig::new);
+        super::env_overrides::restore_persisted_fields(&mut config, &persisted);
+    }
+    let content = serde_json::to_string_pretty(&config)
         .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
     
     fs::write(&config_path, content)
@@ -0,0 +1,249 @@
+// 多配置档案 (例如 "work" / "home" / "benchmark")
+// 每个档案保存一份完整的反代配置快照 (端口、模型映射、账号子集等)，
+// 切换时先把当前配置写回正在使用的档案，再用目标档案覆盖 gui_config.json 中的 proxy 部分
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use super::account::get_data_dir;
+use super::config::{load_app_config, save_app_config};
+use crate::models::AppConfig;
+use crate::proxy::ProxyConfig;
+
+const PROFILES_FILE: &str = "config_profiles.json";
+const MAX_PROFILE_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    pub name: String,
+    pub proxy: ProxyConfig,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileStore {
+    #[serde(default)]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub profiles: Vec<ConfigProfile>,
+}
+
+/// 列表展示用的档案摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigProfileSummary {
+    pub name: String,
+    pub port: u16,
+    pub mapping_count: usize,
+    /// 0 表示使用全部账号
+    pub account_count: usize,
+    pub is_active: bool,
+    pub updated_at: i64,
+}
+
+impl ProfileStore {
+    fn find_mut(&mut self, name: &str) -> Option<&mut ConfigProfile> {
+        self.profiles.iter_mut().find(|p| p.name == name)
+    }
+
+    pub fn summaries(&self) -> Vec<ConfigProfileSummary> {
+        self.profiles
+            .iter()
+            .map(|p| ConfigProfileSummary {
+                name: p.name.clone(),
+                port: p.proxy.port,
+                mapping_count: p.proxy.custom_mapping.len(),
+                account_count: p.proxy.account_subset.len(),
+                is_active: self.active.as_deref() == Some(p.name.as_str()),
+                updated_at: p.updated_at,
+            })
+            .collect()
+    }
+
+    /// 将当前反代配置写回正在使用的档案，避免切换时丢失未保存到档案的修改
+    fn snapshot_active(&mut self, proxy: &ProxyConfig) {
+        let now = chrono::Utc::now().timestamp();
+        if let Some(name) = self.active.clone() {
+            if let Some(profile) = self.find_mut(&name) {
+                profile.proxy = proxy.clone();
+                profile.updated_at = now;
+            }
+        }
+    }
+
+    fn create(&mut self, name: &str, proxy: ProxyConfig) -> Result<(), String> {
+        let name = validate_name(name)?;
+        if self.profiles.iter().any(|p| p.name == name) {
+            return Err(format!("Profile '{}' already exists", name));
+        }
+        let now = chrono::Utc::now().timestamp();
+        self.profiles.push(ConfigProfile {
+            name,
+            proxy,
+            created_at: now,
+            updated_at: now,
+        });
+        Ok(())
+    }
+
+    /// 切换档案，返回目标档案的反代配置
+    fn switch(&mut self, name: &str, current: &ProxyConfig) -> Result<ProxyConfig, String> {
+        if !self.profiles.iter().any(|p| p.name == name) {
+            return Err(format!("Profile '{}' not found", name));
+        }
+        self.snapshot_active(current);
+        self.active = Some(name.to_string());
+        Ok(self
+            .profiles
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.proxy.clone())
+            .unwrap_or_else(|| current.clone()))
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), String> {
+        if self.active.as_deref() == Some(name) {
+            return Err("Cannot delete the active profile; switch to another one first".to_string());
+        }
+        let before = self.profiles.len();
+        self.profiles.retain(|p| p.name != name);
+        if self.profiles.len() == before {
+            return Err(format!("Profile '{}' not found", name));
+        }
+        Ok(())
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_LEN {
+        return Err(format!(
+            "Profile name must be at most {} characters",
+            MAX_PROFILE_NAME_LEN
+        ));
+    }
+    Ok(name.to_string())
+}
+
+pub fn load_profiles() -> Result<ProfileStore, String> {
+    let path = get_data_dir()?.join(PROFILES_FILE);
+    if !path.exists() {
+        return Ok(ProfileStore::default());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("failed_to_read_profiles: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_profiles: {}", e))
+}
+
+fn save_profiles(store: &ProfileStore) -> Result<(), String> {
+    let path = get_data_dir()?.join(PROFILES_FILE);
+    let content = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("failed_to_serialize_profiles: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("failed_to_save_profiles: {}", e))
+}
+
+/// 以当前反代配置为模板创建新档案
+pub fn create_profile(name: &str) -> Result<ProfileStore, String> {
+    let config = load_app_config()?;
+    let mut store = load_profiles()?;
+    store.snapshot_active(&config.proxy);
+    store.create(name, config.proxy)?;
+    save_profiles(&store)?;
+    Ok(store)
+}
+
+/// 将当前反代配置保存到正在使用的档案
+pub fn save_active_profile() -> Result<ProfileStore, String> {
+    let config = load_app_config()?;
+    let mut store = load_profiles()?;
+    if store.active.is_none() {
+        return Err("No active profile".to_string());
+    }
+    store.snapshot_active(&config.proxy);
+    save_profiles(&store)?;
+    Ok(store)
+}
+
+pub fn delete_profile(name: &str) -> Result<ProfileStore, String> {
+    let mut store = load_profiles()?;
+    store.delete(name)?;
+    save_profiles(&store)?;
+    Ok(store)
+}
+
+/// 切换到指定档案并持久化，返回 (切换前配置, 切换后配置)
+pub fn switch_profile(name: &str) -> Result<(AppConfig, AppConfig), String> {
+    let old_config = load_app_config()?;
+    let mut store = load_profiles()?;
+    let proxy = store.switch(name, &old_config.proxy)?;
+
+    let mut new_config = old_config.clone();
+    new_config.proxy = proxy;
+    save_app_config(&new_config)?;
+    save_profiles(&store)?;
+
+    crate::modules::logger::log_info(&format!("Switched config profile to '{}'", name));
+    Ok((old_config, new_config))
+}
+
+/// 判断切换后是否需要重启监听 (端口 / 监听地址 / TLS / CORS 变化无法热更新)
+pub fn requires_listener_restart(old: &ProxyConfig, new: &ProxyConfig) -> bool {
+    old.port != new.port
+        || old.allow_lan_access != new.allow_lan_access
+        || old.bind_address != new.bind_address
+        || serde_json::to_value(&old.tls).ok() != serde_json::to_value(&new.tls).ok()
+        || old.max_body_size_mb != new.max_body_size_mb
+        || old.cors != new.cors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy_with_port(port: u16) -> ProxyConfig {
+        let mut proxy = ProxyConfig::default();
+        proxy.port = port;
+        proxy
+    }
+
+    #[test]
+    fn test_switch_snapshots_active_profile() {
+        let mut store = ProfileStore::default();
+        store.create("work", proxy_with_port(8045)).unwrap();
+        store.create("home", proxy_with_port(9000)).unwrap();
+        assert!(store.create(" work ", proxy_with_port(1)).is_err());
+
+        let proxy = store.switch("work", &proxy_with_port(1234)).unwrap();
+        assert_eq!(proxy.port, 8045);
+
+        // 在 work 档案下修改了映射，切换到 home 时应写回 work
+        let mut edited = proxy.clone();
+        edited
+            .custom_mapping
+            .insert("gpt-4".to_string(), "gemini-3-flash".to_string());
+        let proxy = store.switch("home", &edited).unwrap();
+        assert_eq!(proxy.port, 9000);
+
+        let summaries = store.summaries();
+        let work = summaries.iter().find(|s| s.name == "work").unwrap();
+        assert_eq!(work.mapping_count, 1);
+        assert!(!work.is_active);
+        assert!(summaries.iter().find(|s| s.name == "home").unwrap().is_active);
+
+        assert!(store.delete("home").is_err());
+        assert!(store.delete("work").is_ok());
+        assert!(store.switch("missing", &edited).is_err());
+    }
+
+    #[test]
+    fn test_requires_listener_restart() {
+        let a = proxy_with_port(8045);
+        let mut b = a.clone();
+        b.custom_mapping.insert("a".to_string(), "b".to_string());
+        assert!(!requires_listener_restart(&a, &b));
+        b.port = 9000;
+        assert!(requires_listener_restart(&a, &b));
+    }
+}
@@ -0,0 +1,291 @@
+// 环境变量覆盖 (Docker / systemd 等无界面部署)
+// 启动时用 AGM_* 环境变量覆盖 AppConfig，无需先由 GUI 写出配置文件。
+// 覆盖只作用于内存中的配置：load_app_config 每次加载后重新应用，save_app_config 写盘前还原被覆盖的字段，
+// 移除环境变量后即恢复配置文件中的值。
+// 兼容旧变量名：AGM_X > ABV_X > X (仅部分变量支持无前缀写法)
+use crate::models::AppConfig;
+use crate::proxy::ProxyAuthMode;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// headless 启动时捕获的有效覆盖 (变量名 -> 取值)，未启用时为空
+static ACTIVE_OVERRIDES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// 单个变量的查找顺序 (前者优先)
+fn lookup<F>(get: &F, names: &[&str]) -> Option<(String, String)>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    names.iter().find_map(|name| {
+        get(name)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .map(|v| (name.to_string(), v))
+    })
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_auth_mode(value: &str) -> Option<ProxyAuthMode> {
+    match value.to_lowercase().as_str() {
+        "off" => Some(ProxyAuthMode::Off),
+        "strict" => Some(ProxyAuthMode::Strict),
+        "all_except_health" => Some(ProxyAuthMode::AllExceptHealth),
+        "auto" => Some(ProxyAuthMode::Auto),
+        _ => None,
+    }
+}
+
+/// 从进程环境变量应用覆盖，返回生效的变量名 (不含取值，避免日志泄露密钥)
+pub fn apply_env_overrides(config: &mut AppConfig) -> Vec<String> {
+    apply_env_overrides_with(config, |name| std::env::var(name).ok())
+}
+
+/// 启用环境变量覆盖 (headless 启动时调用一次)，返回生效的变量名
+/// 只保留有效的变量，之后每次加载配置不再重复告警
+pub fn activate() -> Vec<String> {
+    let applied = apply_env_overrides(&mut AppConfig::new());
+    let snapshot = applied
+        .iter()
+        .filter_map(|name| std::env::var(name).ok().map(|v| (name.clone(), v)))
+        .collect();
+    let _ = ACTIVE_OVERRIDES.set(snapshot);
+    applied
+}
+
+pub fn is_active() -> bool {
+    ACTIVE_OVERRIDES.get().map_or(false, |o| !o.is_empty())
+}
+
+/// 对内存中的配置应用已启用的覆盖
+pub fn apply_active_overrides(config: &mut AppConfig) {
+    if let Some(overrides) = ACTIVE_OVERRIDES.get().filter(|o| !o.is_empty()) {
+        apply_env_overrides_with(config, |name| overrides.get(name).cloned());
+    }
+}
+
+/// 写盘前将被覆盖的字段还原为配置文件中的值，环境变量不会写入 gui_config.json
+pub fn restore_persisted_fields(config: &mut AppConfig, persisted: &AppConfig) {
+    if let Some(overrides) = ACTIVE_OVERRIDES.get() {
+        restore_persisted_fields_for(config, persisted, overrides.keys());
+    }
+}
+
+fn restore_persisted_fields_for<'a>(
+    config: &mut AppConfig,
+    persisted: &AppConfig,
+    names: impl Iterator<Item = &'a String>,
+) {
+    let (proxy, saved) = (&mut config.proxy, &persisted.proxy);
+    for name in names {
+        let key = name
+            .strip_prefix("AGM_")
+            .or_else(|| name.strip_prefix("ABV_"))
+            .unwrap_or(name);
+        match key {
+            "PROXY_PORT" => proxy.port = saved.port,
+            "ALLOW_LAN" => proxy.allow_lan_access = saved.allow_lan_access,
+            "BIND_ADDR" => {
+                proxy.allow_lan_access = saved.allow_lan_access;
+                proxy.bind_address = saved.bind_address.clone();
+            }
+            "API_KEY" => proxy.api_key = saved.api_key.clone(),
+            "WEB_PASSWORD" => proxy.admin_password = saved.admin_password.clone(),
+            "AUTH_MODE" => proxy.auth_mode = saved.auth_mode.clone(),
+            "UPSTREAM_PROXY" => {
+                proxy.upstream_proxy.enabled = saved.upstream_proxy.enabled;
+                proxy.upstream_proxy.url = saved.upstream_proxy.url.clone();
+            }
+            "REQUEST_TIMEOUT" => proxy.request_timeout = saved.request_timeout,
+            "ENABLE_LOGGING" => proxy.enable_logging = saved.enable_logging,
+            "USER_AGENT" => proxy.user_agent_override = saved.user_agent_override.clone(),
+            "AUTO_REFRESH" => config.auto_refresh = persisted.auto_refresh,
+            _ => {}
+        }
+    }
+}
+
+pub fn apply_env_overrides_with<F>(config: &mut AppConfig, get: F) -> Vec<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut applied = Vec::new();
+    let proxy = &mut config.proxy;
+
+    if let Some((name, value)) = lookup(&get, &["AGM_PROXY_PORT", "ABV_PROXY_PORT"]) {
+        match value.parse::<u16>() {
+            Ok(port) if port > 0 => {
+                proxy.port = port;
+                applied.push(name);
+            }
+            _ => tracing::warn!("Invalid {}: {}, ignoring", name, value),
+        }
+    }
+
+    if let Some((name, value)) = lookup(&get, &["AGM_ALLOW_LAN", "ABV_ALLOW_LAN"]) {
+        match parse_bool(&value) {
+            Some(allow) => {
+                proxy.allow_lan_access = allow;
+                applied.push(name);
+            }
+            None => tracing::warn!("Invalid {}: {}, ignoring", name, value),
+        }
+    }
+
+    // 指定监听地址时同步 LAN 开关：回环地址仅本机，其余地址需要允许 LAN 才会生效
+    if let Some((name, value)) = lookup(&get, &["AGM_BIND_ADDR", "ABV_BIND_ADDR"]) {
+        match value.parse::<std::net::IpAddr>() {
+            Ok(ip) => {
+                proxy.allow_lan_access = !ip.is_loopback();
+                proxy.bind_address = if ip.is_loopback() { None } else { Some(value) };
+                applied.push(name);
+            }
+            Err(_) => tracing::warn!("Invalid {}: {}, ignoring", name, value),
+        }
+    }
+
+    // 优先级：AGM_API_KEY > ABV_API_KEY > API_KEY > 配置文件
+    if let Some((name, value)) = lookup(&get, &["AGM_API_KEY", "ABV_API_KEY", "API_KEY"]) {
+        proxy.api_key = value;
+        applied.push(name);
+    }
+
+    if let Some((name, value)) = lookup(
+        &get,
+        &["AGM_WEB_PASSWORD", "ABV_WEB_PASSWORD", "WEB_PASSWORD"],
+    ) {
+        proxy.admin_password = Some(value);
+        applied.push(name);
+    }
+
+    if let Some((name, value)) = lookup(&get, &["AGM_AUTH_MODE", "ABV_AUTH_MODE", "AUTH_MODE"]) {
+        match parse_auth_mode(&value) {
+            Some(mode) => {
+                proxy.auth_mode = mode;
+                applied.push(name);
+            }
+            None => tracing::warn!("Invalid {}: {}, ignoring", name, value),
+        }
+    }
+
+    // 上游代理：设置为 "off"/"none" 时关闭
+    if let Some((name, value)) = lookup(&get, &["AGM_UPSTREAM_PROXY", "ABV_UPSTREAM_PROXY"]) {
+        if matches!(value.to_lowercase().as_str(), "off" | "none" | "false") {
+            proxy.upstream_proxy.enabled = false;
+        } else {
+            proxy.upstream_proxy.enabled = true;
+            proxy.upstream_proxy.url = value;
+        }
+        applied.push(name);
+    }
+
+    if let Some((name, value)) = lookup(&get, &["AGM_REQUEST_TIMEOUT", "ABV_REQUEST_TIMEOUT"]) {
+        match value.parse::<u64>() {
+            Ok(secs) if secs > 0 => {
+                proxy.request_timeout = secs;
+                applied.push(name);
+            }
+            _ => tracing::warn!("Invalid {}: {}, ignoring", name, value),
+        }
+    }
+
+    if let Some((name, value)) = lookup(&get, &["AGM_ENABLE_LOGGING", "ABV_ENABLE_LOGGING"]) {
+        match parse_bool(&value) {
+            Some(enabled) => {
+                proxy.enable_logging = enabled;
+                applied.push(name);
+            }
+            None => tracing::warn!("Invalid {}: {}, ignoring", name, value),
+        }
+    }
+
+    if let Some((name, value)) = lookup(&get, &["AGM_USER_AGENT", "ABV_USER_AGENT"]) {
+        proxy.user_agent_override = Some(value);
+        applied.push(name);
+    }
+
+    if let Some((name, value)) = lookup(&get, &["AGM_AUTO_REFRESH", "ABV_AUTO_REFRESH"]) {
+        match parse_bool(&value) {
+            Some(enabled) => {
+                config.auto_refresh = enabled;
+                applied.push(name);
+            }
+            None => tracing::warn!("Invalid {}: {}, ignoring", name, value),
+        }
+    }
+
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn apply(vars: &[(&str, &str)]) -> (AppConfig, Vec<String>) {
+        let env: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut config = AppConfig::new();
+        let applied = apply_env_overrides_with(&mut config, |k| env.get(k).cloned());
+        (config, applied)
+    }
+
+    #[test]
+    fn test_env_overrides_apply_with_precedence() {
+        let (config, applied) = apply(&[
+            ("AGM_PROXY_PORT", "9100"),
+            ("AGM_BIND_ADDR", "10.0.0.5"),
+            ("AGM_API_KEY", "sk-agm"),
+            ("ABV_API_KEY", "sk-abv"),
+            ("AGM_UPSTREAM_PROXY", "socks5://127.0.0.1:1080"),
+            ("AUTH_MODE", "strict"),
+        ]);
+        assert_eq!(config.proxy.port, 9100);
+        assert!(config.proxy.allow_lan_access);
+        assert_eq!(config.proxy.bind_address.as_deref(), Some("10.0.0.5"));
+        assert_eq!(config.proxy.api_key, "sk-agm");
+        assert!(config.proxy.upstream_proxy.enabled);
+        assert_eq!(config.proxy.upstream_proxy.url, "socks5://127.0.0.1:1080");
+        assert!(matches!(config.proxy.auth_mode, ProxyAuthMode::Strict));
+        assert!(applied.contains(&"AUTH_MODE".to_string()));
+    }
+
+    #[test]
+    fn test_env_overrides_ignore_invalid_values() {
+        let (config, applied) = apply(&[
+            ("AGM_PROXY_PORT", "not-a-port"),
+            ("AGM_BIND_ADDR", "127.0.0.1"),
+            ("ABV_API_KEY", "  "),
+        ]);
+        assert_eq!(config.proxy.port, AppConfig::new().proxy.port);
+        assert!(!config.proxy.allow_lan_access);
+        assert_eq!(applied, vec!["AGM_BIND_ADDR".to_string()]);
+    }
+
+    #[test]
+    fn test_overridden_fields_restored_before_save() {
+        let persisted = AppConfig::new();
+        let (mut config, applied) = apply(&[
+            ("AGM_PROXY_PORT", "9100"),
+            ("AGM_UPSTREAM_PROXY", "socks5://127.0.0.1:1080"),
+            ("API_KEY", "sk-env"),
+        ]);
+        // 用户在界面上修改的其他字段照常保存
+        config.proxy.request_timeout = 321;
+
+        restore_persisted_fields_for(&mut config, &persisted, applied.iter());
+        assert_eq!(config.proxy.port, persisted.proxy.port);
+        assert_eq!(config.proxy.upstream_proxy.enabled, persisted.proxy.upstream_proxy.enabled);
+        assert_eq!(config.proxy.upstream_proxy.url, persisted.proxy.upstream_proxy.url);
+        assert_eq!(config.proxy.api_key, persisted.proxy.api_key);
+        assert_eq!(config.proxy.request_timeout, 321);
+    }
+}
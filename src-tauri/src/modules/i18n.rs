@@ -13,6 +13,15 @@ pub struct TrayTexts {
     pub no_account: String,
     pub unknown_quota: String,
     pub forbidden: String,
+    pub proxy_running: String,
+    pub proxy_stopped: String,
+    pub start_proxy: String,
+    pub stop_proxy: String,
+    pub healthy_accounts: String,
+    pub claude_mapping: String,
+    pub mapping_default: String,
+    pub quota_summary: String,
+    pub quota_details: String,
 }
 
 /// Load translations from JSON
@@ -53,5 +62,14 @@ pub fn get_tray_texts(lang: &str) -> TrayTexts {
         no_account: t.get("no_account").cloned().unwrap_or_else(|| "No Account".to_string()),
         unknown_quota: t.get("unknown_quota").cloned().unwrap_or_else(|| "Unknown".to_string()),
         forbidden: t.get("forbidden").cloned().unwrap_or_else(|| "Account Forbidden".to_string()),
+        proxy_running: t.get("proxy_running").cloned().unwrap_or_else(|| "Proxy Running, Port".to_string()),
+        proxy_stopped: t.get("proxy_stopped").cloned().unwrap_or_else(|| "Proxy Stopped".to_string()),
+        start_proxy: t.get("start_proxy").cloned().unwrap_or_else(|| "Start Proxy".to_string()),
+        stop_proxy: t.get("stop_proxy").cloned().unwrap_or_else(|| "Stop Proxy".to_string()),
+        healthy_accounts: t.get("healthy_accounts").cloned().unwrap_or_else(|| "Healthy Accounts".to_string()),
+        claude_mapping: t.get("claude_mapping").cloned().unwrap_or_else(|| "Claude Mapping".to_string()),
+        mapping_default: t.get("mapping_default").cloned().unwrap_or_else(|| "Default (My Mapping)".to_string()),
+        quota_summary: t.get("quota_summary").cloned().unwrap_or_else(|| "Quota Overview".to_string()),
+        quota_details: t.get("quota_details").cloned().unwrap_or_else(|| "View Details...".to_string()),
     }
 }
@@ -13,6 +13,49 @@ pub trait SystemIntegration: Send + Sync {
     fn show_notification(&self, title: &str, body: &str);
 }
 
+/// 将账号写入本地 Antigravity 安装 (设备 Profile + 数据库 Token)，调用方需保证进程已关闭
+pub fn apply_account_to_local_install(account: &Account) -> Result<(), String> {
+    let (storage_path, db_path) = match account
+        .launch_args
+        .as_deref()
+        .and_then(process::user_data_dir_from_args)
+    {
+        // 账号配置了独立的 --user-data-dir: 写入该 Profile 而不是默认安装
+        Some(user_data_dir) => {
+            let global_storage = user_data_dir.join("User").join("globalStorage");
+            let db_path = global_storage.join("state.vscdb");
+            if !db_path.exists() {
+                return Err(format!(
+                    "profile_not_initialized: {:?} (launch Antigravity once with this --user-data-dir first)",
+                    user_data_dir
+                ));
+            }
+            (global_storage.join("storage.json"), db_path)
+        }
+        None => (device::get_storage_path()?, db::get_db_path()?),
+    };
+
+    if let Some(ref profile) = account.device_profile {
+        device::write_profile(&storage_path, profile)?;
+    }
+
+    if db_path.exists() {
+        let backup_path = db_path.with_extension("vscdb.backup");
+        let _ = fs::copy(&db_path, &backup_path);
+    }
+
+    db::inject_token(
+        &db_path,
+        &account.token.access_token,
+        &account.token.refresh_token,
+        account.token.expiry_timestamp,
+        &account.email,
+        account.token.is_gcp_tos,
+        account.token.project_id.as_deref(),
+    )?;
+    Ok(())
+}
+
 /// 桌面版实现：包含完整的进程控制和 UI 同步
 pub struct DesktopIntegration {
     pub app_handle: tauri::AppHandle,
@@ -22,40 +65,18 @@ impl SystemIntegration for DesktopIntegration {
     async fn on_account_switch(&self, account: &crate::models::Account) -> Result<(), String> {
         crate::modules::logger::log_info(&format!("[Desktop] Executing system switch for: {}", account.email));
         
-        // 1. 获取存储路径
-        let storage_path = device::get_storage_path()?;
-
-        // 2. 关闭外部进程
+        // 1. 关闭外部进程
         if process::is_antigravity_running() {
             process::close_antigravity(20)?;
         }
 
-        // 3. 写入设备 Profile
-        if let Some(ref profile) = account.device_profile {
-            device::write_profile(&storage_path, profile)?;
-        }
+        // 2. 写入设备 Profile 并注入 Token
+        apply_account_to_local_install(account)?;
 
-        // 4. 数据库处理与 Token 注入
-        let db_path = db::get_db_path()?;
-        if db_path.exists() {
-            let backup_path = db_path.with_extension("vscdb.backup");
-            let _ = fs::copy(&db_path, &backup_path);
-        }
-        
-        db::inject_token(
-            &db_path,
-            &account.token.access_token,
-            &account.token.refresh_token,
-            account.token.expiry_timestamp,
-            &account.email,
-            account.token.is_gcp_tos,
-            account.token.project_id.as_deref(),
-        )?;
-
-        // 5. 重启外部进程
-        process::start_antigravity()?;
+        // 3. 重启外部进程 (带上账号专属启动参数)
+        process::start_antigravity_with_args(account.launch_args.as_deref().unwrap_or_default())?;
         
-        // 6. 更新托盘
+        // 4. 更新托盘
         let _ = crate::modules::tray::update_tray_menus(&self.app_handle);
         
         Ok(())
@@ -66,8 +87,18 @@ impl SystemIntegration for DesktopIntegration {
     }
 
     fn show_notification(&self, title: &str, body: &str) {
-        // 使用 tauri-plugin-dialog 或原生通知（此处简化）
+        use tauri_plugin_notification::NotificationExt;
         crate::modules::logger::log_info(&format!("[Notification] {}: {}", title, body));
+        if let Err(e) = self
+            .app_handle
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+        {
+            crate::modules::logger::log_warn(&format!("[Notification] Failed to show: {}", e));
+        }
     }
 }
 
@@ -94,6 +94,23 @@ pub fn emit_accounts_refreshed() {
     }
 }
 
+/// Emit accounts://auto-disabled when the proxy disables an account after repeated 401/403
+pub fn emit_account_auto_disabled(account_id: &str, email: &str, reason: &str) {
+    if let Some(handle) = APP_HANDLE.get() {
+        let _ = handle.emit(
+            "accounts://auto-disabled",
+            serde_json::json!({ "account_id": account_id, "email": email, "reason": reason }),
+        );
+    }
+}
+
+/// Emit an arbitrary event to the frontend (no-op before the app handle is attached, e.g. headless mode)
+pub fn emit_event<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(handle) = APP_HANDLE.get() {
+        let _ = handle.emit(event, payload);
+    }
+}
+
 /// Visitor to extract fields from tracing events
 struct FieldVisitor {
     message: Option<String>,
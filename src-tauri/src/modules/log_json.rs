@@ -0,0 +1,205 @@
+//! JSON Lines 日志输出 (便于 Loki / ELK 等采集)
+//! 每个事件输出一行 JSON: timestamp / level / module / request_id / account / message，
+//! request_id、account 优先取事件字段，其次沿 span 链向上查找 (例如 request_id 中间件创建的 span)。
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// 视为账号标识的字段名 (按优先级)
+const ACCOUNT_FIELDS: &[&str] = &["account", "email", "account_id"];
+
+#[derive(Debug, Serialize)]
+pub struct JsonLogLine {
+    pub timestamp: String,
+    pub level: String,
+    pub module: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    pub message: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Default)]
+struct JsonVisitor {
+    message: Option<String>,
+    fields: BTreeMap<String, String>,
+}
+
+impl JsonVisitor {
+    fn insert(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+
+    fn account(&self) -> Option<String> {
+        ACCOUNT_FIELDS
+            .iter()
+            .find_map(|name| self.fields.get(*name).cloned())
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, format!("{:?}", value).trim_matches('"').to_string());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.to_string());
+    }
+}
+
+/// 保存在 span extensions 中的关联字段
+#[derive(Default, Clone)]
+struct SpanCorrelation {
+    request_id: Option<String>,
+    account: Option<String>,
+}
+
+impl SpanCorrelation {
+    fn merge(&mut self, visitor: &JsonVisitor) {
+        if let Some(id) = visitor.fields.get("request_id") {
+            self.request_id = Some(id.clone());
+        }
+        if let Some(account) = visitor.account() {
+            self.account = Some(account);
+        }
+    }
+}
+
+pub struct JsonLogLayer<W> {
+    make_writer: W,
+}
+
+impl<W> JsonLogLayer<W> {
+    pub fn new(make_writer: W) -> Self {
+        Self { make_writer }
+    }
+}
+
+impl<S, W> Layer<S> for JsonLogLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = JsonVisitor::default();
+        attrs.record(&mut visitor);
+        let mut correlation = SpanCorrelation::default();
+        correlation.merge(&visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(correlation);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = JsonVisitor::default();
+        values.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if let Some(correlation) = extensions.get_mut::<SpanCorrelation>() {
+                correlation.merge(&visitor);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+
+        let mut request_id = visitor.fields.remove("request_id");
+        let mut account = visitor.account();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope {
+                if request_id.is_some() && account.is_some() {
+                    break;
+                }
+                if let Some(correlation) = span.extensions().get::<SpanCorrelation>() {
+                    request_id = request_id.or_else(|| correlation.request_id.clone());
+                    account = account.or_else(|| correlation.account.clone());
+                }
+            }
+        }
+        for name in ACCOUNT_FIELDS {
+            visitor.fields.remove(*name);
+        }
+
+        let line = JsonLogLine {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            level: event.metadata().level().to_string(),
+            module: event.metadata().target().to_string(),
+            request_id,
+            account,
+            message: visitor.message.unwrap_or_default(),
+            fields: visitor.fields,
+        };
+
+        if let Ok(mut json) = serde_json::to_vec(&line) {
+            json.push(b'\n');
+            let _ = self.make_writer.make_writer().write_all(&json);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_line_picks_up_span_request_id() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(JsonLogLayer::new(buffer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = %"req_123");
+            let _guard = span.enter();
+            tracing::warn!(email = %"a@example.com", status = 429, "rate limited");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["request_id"], "req_123");
+        assert_eq!(line["account"], "a@example.com");
+        assert_eq!(line["message"], "rate limited");
+        assert_eq!(line["fields"]["status"], "429");
+        assert!(line["module"].as_str().unwrap().contains("log_json"));
+    }
+}
@@ -1,9 +1,21 @@
 use tracing::{info, warn, error};
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry};
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use crate::models::LoggingConfig;
 use crate::modules::account::get_data_dir;
 
+const LOG_FILE_PREFIX: &str = "app.log";
+const LOG_LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error", "off"];
+/// 单次查询最多返回的条数
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// 运行时调整日志级别用的句柄 (init_logger 成功后设置)
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
 // Custom local timezone time formatter
 struct LocalTimer;
 
@@ -25,6 +37,129 @@ pub fn get_log_dir() -> Result<PathBuf, String> {
     Ok(log_dir)
 }
 
+/// 按日期 + 大小轮转的日志文件
+/// 文件名: app.log.YYYY-MM-DD，超过大小上限后依次写入 app.log.YYYY-MM-DD.1、.2 ...
+struct RotatingFileWriter {
+    dir: PathBuf,
+    max_bytes: u64,
+    date: String,
+    index: u32,
+    size: u64,
+    file: Option<fs::File>,
+}
+
+impl RotatingFileWriter {
+    fn new(dir: PathBuf, max_file_size_mb: u64) -> Self {
+        Self {
+            dir,
+            max_bytes: max_file_size_mb * 1024 * 1024,
+            date: String::new(),
+            index: 0,
+            size: 0,
+            file: None,
+        }
+    }
+
+    fn file_name(date: &str, index: u32) -> String {
+        if index == 0 {
+            format!("{}.{}", LOG_FILE_PREFIX, date)
+        } else {
+            format!("{}.{}.{}", LOG_FILE_PREFIX, date, index)
+        }
+    }
+
+    /// 打开当天可继续写入的文件 (跳过已写满的分片)
+    fn open(&mut self, date: String) -> std::io::Result<()> {
+        let mut index = if date == self.date { self.index + 1 } else { 0 };
+        loop {
+            let path = self.dir.join(Self::file_name(&date, index));
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if self.max_bytes == 0 || size < self.max_bytes {
+                self.file = Some(fs::OpenOptions::new().create(true).append(true).open(&path)?);
+                self.size = size;
+                self.index = index;
+                self.date = date;
+                return Ok(());
+            }
+            index += 1;
+        }
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let full = self.max_bytes > 0 && self.size + buf.len() as u64 > self.max_bytes && self.size > 0;
+        if self.file.is_none() || today != self.date || full {
+            self.open(today)?;
+        }
+        let written = match self.file.as_mut() {
+            Some(file) => file.write(buf)?,
+            None => return Ok(0),
+        };
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 由日志配置生成 EnvFilter 指令，无效的级别会被忽略
+pub fn build_filter_directives(config: &LoggingConfig) -> String {
+    let valid = |level: &str| LOG_LEVELS.contains(&level.to_lowercase().as_str());
+    let default_level = if valid(&config.level) { config.level.to_lowercase() } else { "info".to_string() };
+
+    let mut modules: Vec<(&String, &String)> = config
+        .module_levels
+        .iter()
+        .filter(|(target, level)| !target.trim().is_empty() && valid(level))
+        .collect();
+    modules.sort();
+
+    let mut directives = vec![default_level];
+    directives.extend(
+        modules
+            .into_iter()
+            .map(|(target, level)| format!("{}={}", target.trim(), level.to_lowercase())),
+    );
+    directives.join(",")
+}
+
+/// 启动时直接读取配置文件中的 logging 段 (此时日志系统尚未就绪，不走 load_app_config 的迁移流程)
+fn load_logging_config() -> LoggingConfig {
+    get_data_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join("gui_config.json")).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|v| v.get("logging").cloned())
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+/// 热更新日志级别 (RUST_LOG 环境变量存在时以环境变量为准)
+pub fn apply_logging_config(config: &LoggingConfig) {
+    if std::env::var("RUST_LOG").is_ok() {
+        return;
+    }
+    let Some(handle) = FILTER_HANDLE.get() else {
+        return;
+    };
+    let directives = build_filter_directives(config);
+    match EnvFilter::try_new(&directives) {
+        Ok(filter) => {
+            if handle.reload(filter).is_ok() {
+                info!("Log filter updated: {}", directives);
+            }
+        }
+        Err(e) => warn!("Invalid log filter '{}': {}", directives, e),
+    }
+}
+
 /// Initialize the log system
 pub fn init_logger() {
     // Capture log macro logs
@@ -38,9 +173,10 @@ pub fn init_logger() {
         }
     };
     
-    // 1. Set up file Appender (using tracing-appender for rolling logs)
-    // Using a daily rolling strategy here
-    let file_appender = tracing_appender::rolling::daily(log_dir, "app.log");
+    let logging = load_logging_config();
+
+    // 1. Set up file Appender (daily + size based rolling)
+    let file_appender = RotatingFileWriter::new(log_dir, logging.max_file_size_mb);
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
     
     // 2. Console output layer (using local timezone)
@@ -51,36 +187,52 @@ pub fn init_logger() {
         .with_timer(LocalTimer);
         
     // 3. File output layer (disable ANSI formatting, use local timezone)
-    let file_layer = fmt::Layer::new()
-        .with_writer(non_blocking)
-        .with_ansi(false)
-        .with_target(true)
-        .with_level(true)
-        .with_timer(LocalTimer);
+    // JSON 模式下改为输出 JSON Lines，控制台保持文本格式
+    let (file_layer, json_layer) = if logging.json_format {
+        (None, Some(crate::modules::log_json::JsonLogLayer::new(non_blocking)))
+    } else {
+        let layer = fmt::Layer::new()
+            .with_writer(non_blocking)
+            .with_ansi(false)
+            .with_target(true)
+            .with_level(true)
+            .with_timer(LocalTimer);
+        (Some(layer), None)
+    };
 
-    // 4. Set filtering layer (default to INFO level to reduce log size)
-    let filter_layer = EnvFilter::try_from_default_env()
+    // 4. Set filtering layer (RUST_LOG > logging config, default INFO to reduce log size)
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(build_filter_directives(&logging)))
         .unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter_layer, filter_handle) = reload::Layer::new(filter);
 
     // 6. Log bridge layer
     let bridge_layer = crate::modules::log_bridge::TauriLogBridgeLayer::new();
 
     // 5. Initialize global subscriber (use try_init to avoid crash on repeated initialization)
-    let _ = tracing_subscriber::registry()
+    if tracing_subscriber::registry()
         .with(filter_layer)
         .with(console_layer)
         .with(file_layer)
+        .with(json_layer)
         .with(bridge_layer)
-        .try_init();
+        .try_init()
+        .is_ok()
+    {
+        let _ = FILTER_HANDLE.set(filter_handle);
+    }
 
     // Leak _guard to ensure its lifetime lasts until program exit
     // Recommended practice when using tracing_appender::non_blocking (if manual flushing is not needed)
     std::mem::forget(_guard);
     
-    info!("Log system initialized (Console + File persistence)");
+    info!(
+        "Log system initialized (Console + File persistence, format: {})",
+        if logging.json_format { "json" } else { "text" }
+    );
     
-    // Auto-cleanup logs older than 7 days
-    if let Err(e) = cleanup_old_logs(7) {
+    // Auto-cleanup logs older than retention_days
+    if let Err(e) = cleanup_old_logs(logging.retention_days.max(1)) {
         warn!("Failed to cleanup old logs: {}", e);
     }
 }
@@ -208,6 +360,220 @@ pub fn clear_logs() -> Result<(), String> {
     Ok(())
 }
 
+/// 日志文件中解析出的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogRecord {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogQuery {
+    /// 最低级别 (例如 warn 返回 warn + error)
+    #[serde(default)]
+    pub level: Option<String>,
+    /// 消息 / 模块子串过滤 (不区分大小写)
+    #[serde(default)]
+    pub search: Option<String>,
+    /// 模块前缀过滤 (target 以此开头)
+    #[serde(default)]
+    pub module: Option<String>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogPage {
+    /// 新的在前
+    pub entries: Vec<LogRecord>,
+    pub offset: usize,
+    pub limit: usize,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogFileInfo {
+    pub name: String,
+    pub size: u64,
+    pub modified: i64,
+}
+
+fn level_rank(level: &str) -> Option<u8> {
+    match level.to_uppercase().as_str() {
+        "TRACE" => Some(0),
+        "DEBUG" => Some(1),
+        "INFO" => Some(2),
+        "WARN" => Some(3),
+        "ERROR" => Some(4),
+        _ => None,
+    }
+}
+
+/// 解析文件层输出的一行: "<rfc3339>  INFO target: message" 或 JSON Lines 格式
+/// 不符合格式的行视为上一条记录的续行 (返回 None)
+fn parse_log_line(line: &str) -> Option<(String, String, String, String)> {
+    if line.starts_with('{') {
+        return parse_json_log_line(line);
+    }
+    let (timestamp, rest) = line.split_once(' ')?;
+    chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    let (level, rest) = rest.trim_start().split_once(' ')?;
+    level_rank(level)?;
+    let (target, message) = rest.split_once(": ").unwrap_or(("", rest));
+    Some((
+        timestamp.to_string(),
+        level.to_string(),
+        target.to_string(),
+        message.to_string(),
+    ))
+}
+
+fn parse_json_log_line(line: &str) -> Option<(String, String, String, String)> {
+    let v: serde_json::Value = serde_json::from_str(line).ok()?;
+    let field = |name: &str| v.get(name).and_then(|f| f.as_str()).unwrap_or_default().to_string();
+    let level = field("level");
+    level_rank(&level)?;
+
+    // 查看器中保留关联字段，便于按请求 ID / 账号搜索
+    let mut message = field("message");
+    for key in ["request_id", "account"] {
+        if let Some(value) = v.get(key).and_then(|f| f.as_str()) {
+            message.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    Some((field("timestamp"), level, field("module"), message))
+}
+
+fn parse_log_file(path: &Path) -> Vec<LogRecord> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let file = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut records: Vec<LogRecord> = Vec::new();
+    for line in content.lines() {
+        match parse_log_line(line) {
+            Some((timestamp, level, target, message)) => records.push(LogRecord {
+                timestamp,
+                level,
+                target,
+                message,
+                file: file.clone(),
+            }),
+            None => {
+                if let Some(last) = records.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+    records
+}
+
+/// 日志文件按 (日期, 分片序号) 从新到旧排序
+fn sorted_log_files(log_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<(String, u32, PathBuf)> = fs::read_dir(log_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file())
+                .filter_map(|p| {
+                    let name = p.file_name()?.to_string_lossy().to_string();
+                    let suffix = name.strip_prefix(LOG_FILE_PREFIX)?.trim_start_matches('.');
+                    let (date, index) = match suffix.split_once('.') {
+                        Some((date, index)) => (date.to_string(), index.parse().unwrap_or(0)),
+                        None => (suffix.to_string(), 0),
+                    };
+                    Some((date, index, p))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_by(|a, b| (&b.0, b.1).cmp(&(&a.0, a.1)));
+    files.into_iter().map(|(_, _, p)| p).collect()
+}
+
+fn record_matches(record: &LogRecord, min_rank: u8, search: Option<&str>, module: Option<&str>) -> bool {
+    if level_rank(&record.level).unwrap_or(0) < min_rank {
+        return false;
+    }
+    if let Some(module) = module {
+        if !record.target.starts_with(module) {
+            return false;
+        }
+    }
+    if let Some(search) = search {
+        if !record.message.to_lowercase().contains(search) && !record.target.to_lowercase().contains(search) {
+            return false;
+        }
+    }
+    true
+}
+
+/// 分页查询日志文件 (从最新的记录开始)，只读取满足本页所需的文件
+pub fn query_logs(query: &LogQuery) -> Result<LogPage, String> {
+    let log_dir = get_log_dir()?;
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(200).clamp(1, MAX_QUERY_LIMIT);
+    let min_rank = query.level.as_deref().and_then(level_rank).unwrap_or(0);
+    let search = query
+        .search
+        .as_deref()
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty());
+    let module = query.module.as_deref().map(str::trim).filter(|s| !s.is_empty());
+
+    let mut matched = 0usize;
+    let mut entries = Vec::new();
+    for path in sorted_log_files(&log_dir) {
+        for record in parse_log_file(&path).into_iter().rev() {
+            if !record_matches(&record, min_rank, search.as_deref(), module) {
+                continue;
+            }
+            if matched >= offset {
+                if entries.len() == limit {
+                    return Ok(LogPage { entries, offset, limit, has_more: true });
+                }
+                entries.push(record);
+            }
+            matched += 1;
+        }
+    }
+    Ok(LogPage { entries, offset, limit, has_more: false })
+}
+
+/// 列出日志文件 (新的在前)
+pub fn list_log_files() -> Result<Vec<LogFileInfo>, String> {
+    let log_dir = get_log_dir()?;
+    Ok(sorted_log_files(&log_dir)
+        .into_iter()
+        .filter_map(|path| {
+            let meta = fs::metadata(&path).ok()?;
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            Some(LogFileInfo {
+                name: path.file_name()?.to_string_lossy().to_string(),
+                size: meta.len(),
+                modified,
+            })
+        })
+        .collect())
+}
+
 /// Log info message (backward compatibility)
 pub fn log_info(message: &str) {
     info!("{}", message);
@@ -222,3 +588,69 @@ pub fn log_warn(message: &str) {
 pub fn log_error(message: &str) {
     error!("{}", message);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_filter_directives() {
+        let mut config = LoggingConfig::default();
+        config.level = "WARN".to_string();
+        config
+            .module_levels
+            .insert("antigravity_tools_lib::proxy".to_string(), "debug".to_string());
+        config.module_levels.insert("hyper".to_string(), "loud".to_string());
+        assert_eq!(
+            build_filter_directives(&config),
+            "warn,antigravity_tools_lib::proxy=debug"
+        );
+    }
+
+    #[test]
+    fn test_parse_log_file_and_filter() {
+        let dir = std::env::temp_dir().join(format!("agm-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log.2026-01-02");
+        fs::write(
+            &path,
+            "2026-01-02T10:00:00+08:00  INFO antigravity_tools_lib::proxy: request ok\n\
+             2026-01-02T10:00:01+08:00 ERROR antigravity_tools_lib::modules::account: refresh failed\n\
+             caused by: timeout\n\
+             2026-01-02T10:00:02+08:00  WARN hyper: slow upstream\n",
+        )
+        .unwrap();
+        fs::write(dir.join("app.log.2026-01-02.1"), "").unwrap();
+        fs::write(dir.join("app.log.2026-01-01"), "").unwrap();
+
+        let files = sorted_log_files(&dir);
+        let names: Vec<String> = files
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["app.log.2026-01-02.1", "app.log.2026-01-02", "app.log.2026-01-01"]);
+
+        let records = parse_log_file(&path);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].level, "ERROR");
+        assert_eq!(records[1].message, "refresh failed\ncaused by: timeout");
+
+        let warn = level_rank("warn").unwrap();
+        let matched: Vec<&LogRecord> = records
+            .iter()
+            .filter(|r| record_matches(r, warn, None, Some("antigravity_tools_lib")))
+            .collect();
+        assert_eq!(matched.len(), 1);
+        assert!(record_matches(&records[0], 0, Some("request"), None));
+        assert!(!record_matches(&records[0], 0, Some("timeout"), None));
+
+        let json = parse_log_line(
+            r#"{"timestamp":"2026-01-02T10:00:03+08:00","level":"INFO","module":"antigravity_tools_lib::proxy","request_id":"req_1","message":"done"}"#,
+        )
+        .unwrap();
+        assert_eq!(json.2, "antigravity_tools_lib::proxy");
+        assert_eq!(json.3, "done request_id=req_1");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,373 @@
+// 批量账号登录队列
+// 按顺序为一批邮箱逐个走 OAuth 授权流程，进度持久化到 login_queue.json，
+// 失败不会中断队列，应用重启或失败后可继续 (resume)
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::modules::{account, account_service::AccountService, integration, logger, oauth_server};
+
+const QUEUE_FILE: &str = "login_queue.json";
+/// 单个账号等待浏览器授权的最长时间，超时后标记失败并继续下一个
+const LOGIN_TIMEOUT_SECS: u64 = 300;
+pub const LOGIN_QUEUE_PROGRESS_EVENT: &str = "login-queue://progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginItemStatus {
+    Pending,
+    InProgress,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginQueueItem {
+    /// 期望登录的邮箱 (用户输入)
+    pub email: String,
+    pub status: LoginItemStatus,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub account_id: Option<String>,
+    /// 实际完成授权的邮箱 (可能与期望邮箱不同)
+    #[serde(default)]
+    pub authenticated_email: Option<String>,
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoginQueueState {
+    pub items: Vec<LoginQueueItem>,
+    #[serde(default)]
+    pub oauth_client_key: Option<String>,
+    /// 运行时状态，不持久化
+    #[serde(skip)]
+    pub running: bool,
+    #[serde(skip)]
+    pub current: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginQueueProgress {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub pending: usize,
+    pub running: bool,
+    pub current_email: Option<String>,
+    pub items: Vec<LoginQueueItem>,
+}
+
+impl LoginQueueState {
+    fn count(&self, status: LoginItemStatus) -> usize {
+        self.items.iter().filter(|i| i.status == status).count()
+    }
+
+    pub fn progress(&self) -> LoginQueueProgress {
+        LoginQueueProgress {
+            total: self.items.len(),
+            succeeded: self.count(LoginItemStatus::Succeeded),
+            failed: self.count(LoginItemStatus::Failed),
+            pending: self.count(LoginItemStatus::Pending),
+            running: self.running,
+            current_email: self
+                .current
+                .and_then(|i| self.items.get(i))
+                .map(|i| i.email.clone()),
+            items: self.items.clone(),
+        }
+    }
+
+    /// 将失败 / 取消 / 中断 (上次运行中崩溃) 的条目重新置为待处理
+    fn reset_unfinished(&mut self) {
+        for item in &mut self.items {
+            if matches!(
+                item.status,
+                LoginItemStatus::Failed | LoginItemStatus::Cancelled | LoginItemStatus::InProgress
+            ) {
+                item.status = LoginItemStatus::Pending;
+            }
+        }
+    }
+
+    fn next_pending(&self) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|i| i.status == LoginItemStatus::Pending)
+    }
+}
+
+static LOGIN_QUEUE: OnceLock<Mutex<LoginQueueState>> = OnceLock::new();
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn queue() -> &'static Mutex<LoginQueueState> {
+    LOGIN_QUEUE.get_or_init(|| Mutex::new(load_queue().unwrap_or_default()))
+}
+
+fn queue_path() -> Result<PathBuf, String> {
+    Ok(account::get_data_dir()?.join(QUEUE_FILE))
+}
+
+fn load_queue() -> Result<LoginQueueState, String> {
+    let path = queue_path()?;
+    if !path.exists() {
+        return Ok(LoginQueueState::default());
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse login queue: {}", e))
+}
+
+fn save_queue(state: &LoginQueueState) {
+    let result = queue_path().and_then(|path| {
+        let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+        std::fs::write(path, content).map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        logger::log_warn(&format!("[LoginQueue] Failed to persist queue: {}", e));
+    }
+}
+
+/// 规范化输入邮箱: 去空白、转小写、去重
+fn normalize_emails(emails: &[String]) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    emails
+        .iter()
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty() && seen.insert(e.clone()))
+        .collect()
+}
+
+/// 在锁内修改队列状态，持久化并推送进度事件
+fn update_queue<F: FnOnce(&mut LoginQueueState)>(app_handle: &tauri::AppHandle, f: F) -> LoginQueueProgress {
+    let progress = {
+        let mut state = queue().lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut state);
+        save_queue(&state);
+        state.progress()
+    };
+    let _ = app_handle.emit(LOGIN_QUEUE_PROGRESS_EVENT, &progress);
+    progress
+}
+
+pub fn get_status() -> LoginQueueProgress {
+    queue().lock().unwrap_or_else(|e| e.into_inner()).progress()
+}
+
+/// 用新的邮箱列表替换队列并开始处理
+pub fn start(
+    app_handle: tauri::AppHandle,
+    emails: Vec<String>,
+    oauth_client_key: Option<String>,
+) -> Result<LoginQueueProgress, String> {
+    let emails = normalize_emails(&emails);
+    if emails.is_empty() {
+        return Err("No accounts to log in".to_string());
+    }
+
+    {
+        let mut state = queue().lock().map_err(|e| e.to_string())?;
+        if state.running {
+            return Err("Login queue is already running".to_string());
+        }
+        state.items = emails
+            .into_iter()
+            .map(|email| LoginQueueItem {
+                email,
+                status: LoginItemStatus::Pending,
+                error: None,
+                account_id: None,
+                authenticated_email: None,
+                attempts: 0,
+            })
+            .collect();
+        state.oauth_client_key = oauth_client_key;
+        state.running = true;
+        state.current = None;
+        save_queue(&state);
+    }
+
+    logger::log_info("[LoginQueue] Starting bulk login queue");
+    spawn_runner(app_handle.clone());
+    Ok(update_queue(&app_handle, |_| {}))
+}
+
+/// 重试失败 / 未完成的条目，已成功的保持不变
+pub fn resume(app_handle: tauri::AppHandle) -> Result<LoginQueueProgress, String> {
+    {
+        let mut state = queue().lock().map_err(|e| e.to_string())?;
+        if state.running {
+            return Err("Login queue is already running".to_string());
+        }
+        state.reset_unfinished();
+        if state.next_pending().is_none() {
+            return Err("No pending accounts in login queue".to_string());
+        }
+        state.running = true;
+        save_queue(&state);
+    }
+
+    logger::log_info("[LoginQueue] Resuming bulk login queue");
+    spawn_runner(app_handle.clone());
+    Ok(update_queue(&app_handle, |_| {}))
+}
+
+/// 停止队列 (当前授权会被取消并标记为 Cancelled，可稍后 resume)
+pub fn cancel() {
+    CANCEL_REQUESTED.store(true, Ordering::SeqCst);
+    oauth_server::cancel_oauth_flow();
+}
+
+/// 清空已结束的队列
+pub fn clear() -> Result<(), String> {
+    let mut state = queue().lock().map_err(|e| e.to_string())?;
+    if state.running {
+        return Err("Login queue is running; cancel it first".to_string());
+    }
+    *state = LoginQueueState::default();
+    save_queue(&state);
+    Ok(())
+}
+
+fn spawn_runner(app_handle: tauri::AppHandle) {
+    CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+    tauri::async_runtime::spawn(async move {
+        run_queue(&app_handle).await;
+    });
+}
+
+async fn run_queue(app_handle: &tauri::AppHandle) {
+    let service = AccountService::new(integration::SystemManager::Desktop(app_handle.clone()));
+
+    loop {
+        if CANCEL_REQUESTED.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let mut next = None;
+        update_queue(app_handle, |state| {
+            next = state.next_pending().map(|idx| {
+                let item = &mut state.items[idx];
+                item.status = LoginItemStatus::InProgress;
+                item.attempts += 1;
+                item.error = None;
+                state.current = Some(idx);
+                (idx, item.email.clone(), state.oauth_client_key.clone())
+            });
+        });
+        let Some((idx, email, client_key)) = next else {
+            break;
+        };
+
+        logger::log_info(&format!("[LoginQueue] Waiting for authorization of {}", email));
+        let result = tokio::time::timeout(
+            Duration::from_secs(LOGIN_TIMEOUT_SECS),
+            service.start_oauth_login_with_hint(client_key, Some(email.clone())),
+        )
+        .await;
+        let cancelled = CANCEL_REQUESTED.load(Ordering::SeqCst);
+
+        let result = match result {
+            Ok(r) => r,
+            Err(_) => {
+                // 超时: 释放回调监听，避免下一个账号复用旧的授权流程
+                oauth_server::cancel_oauth_flow();
+                Err(format!("Timed out after {}s waiting for authorization", LOGIN_TIMEOUT_SECS))
+            }
+        };
+
+        match &result {
+            Ok(acc) => logger::log_info(&format!("[LoginQueue] {} authorized as {}", email, acc.email)),
+            Err(e) => logger::log_warn(&format!("[LoginQueue] {} failed: {}", email, e)),
+        }
+
+        update_queue(app_handle, |state| {
+            let item = &mut state.items[idx];
+            match result {
+                Ok(acc) => {
+                    item.status = LoginItemStatus::Succeeded;
+                    if !acc.email.eq_ignore_ascii_case(&item.email) {
+                        item.error = Some(format!("Signed in as {} instead of {}", acc.email, item.email));
+                    }
+                    item.account_id = Some(acc.id);
+                    item.authenticated_email = Some(acc.email);
+                }
+                Err(e) => {
+                    item.status = if cancelled {
+                        LoginItemStatus::Cancelled
+                    } else {
+                        LoginItemStatus::Failed
+                    };
+                    item.error = Some(e);
+                }
+            }
+            state.current = None;
+        });
+
+        let _ = app_handle.emit("accounts://refreshed", ());
+    }
+
+    // 批量添加结束后统一刷新反代账号池
+    let proxy_state = app_handle.state::<crate::commands::proxy::ProxyServiceState>();
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+
+    let progress = update_queue(app_handle, |state| {
+        state.running = false;
+        state.current = None;
+    });
+    logger::log_info(&format!(
+        "[LoginQueue] Finished: {} succeeded, {} failed, {} pending",
+        progress.succeeded, progress.failed, progress.pending
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(email: &str, status: LoginItemStatus) -> LoginQueueItem {
+        LoginQueueItem {
+            email: email.to_string(),
+            status,
+            error: None,
+            account_id: None,
+            authenticated_email: None,
+            attempts: 0,
+        }
+    }
+
+    #[test]
+    fn test_normalize_emails() {
+        let emails = vec![
+            " A@x.com ".to_string(),
+            "a@x.com".to_string(),
+            "".to_string(),
+            "b@x.com".to_string(),
+        ];
+        assert_eq!(normalize_emails(&emails), vec!["a@x.com", "b@x.com"]);
+    }
+
+    #[test]
+    fn test_resume_retries_unfinished_only() {
+        let mut state = LoginQueueState {
+            items: vec![
+                item("a@x.com", LoginItemStatus::Succeeded),
+                item("b@x.com", LoginItemStatus::Failed),
+                item("c@x.com", LoginItemStatus::InProgress),
+                item("d@x.com", LoginItemStatus::Cancelled),
+            ],
+            ..Default::default()
+        };
+        state.reset_unfinished();
+
+        assert_eq!(state.items[0].status, LoginItemStatus::Succeeded);
+        assert_eq!(state.count(LoginItemStatus::Pending), 3);
+        assert_eq!(state.next_pending(), Some(1));
+        assert_eq!(state.progress().succeeded, 1);
+    }
+}
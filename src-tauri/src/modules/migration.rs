@@ -13,6 +13,72 @@ struct ImportedOAuthState {
     project_id: Option<String>,
 }
 
+/// 校验 Token 静态加密设置：没有钥匙串也没有主密码时拒绝开启，避免以可推导的设备密钥冒充加密
+pub fn validate_token_encryption_setting(enabled: bool) -> Result<(), String> {
+    if enabled && !crate::utils::crypto::has_secure_token_key() {
+        return Err(
+            "Token encryption needs the OS keychain or the AGM_TOKEN_PASSPHRASE environment variable".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// 应用 Token 静态加密设置，并迁移已有账号文件 (明文 <-> 密文)
+/// 仅重写状态与目标不一致的文件，返回迁移的账号数
+pub fn apply_token_encryption_setting(enabled: bool) -> Result<usize, String> {
+    if let Err(e) = validate_token_encryption_setting(enabled) {
+        crate::utils::crypto::set_token_encryption_enabled(false);
+        crate::modules::logger::log_error(&format!(
+            "[TokenEncryption] {}; tokens stay UNENCRYPTED",
+            e
+        ));
+        return Err(e);
+    }
+    crate::utils::crypto::set_token_encryption_enabled(enabled);
+
+    let accounts_dir = account::get_accounts_dir()?;
+    let index = account::load_account_index()?;
+    let mut migrated = 0;
+
+    for summary in &index.accounts {
+        let Ok(Some(raw)) = crate::modules::account_db::read_account_value(&accounts_dir, &summary.id) else {
+            continue;
+        };
+
+        let needs_migration = ["access_token", "refresh_token"].iter().any(|field| {
+            raw["token"][field]
+                .as_str()
+                .map_or(false, |v| {
+                    !v.is_empty()
+                        && (crate::utils::crypto::is_encrypted_token(v) != enabled
+                            // 旧版设备密钥密文改用安全密钥重新加密
+                            || (enabled && crate::utils::crypto::is_device_key_token(v)))
+                })
+        });
+        if !needs_migration {
+            continue;
+        }
+
+        // load_account 透明解密，save_account 按当前设置重新写入
+        match account::load_account(&summary.id).and_then(|acc| account::save_account(&acc)) {
+            Ok(()) => migrated += 1,
+            Err(e) => crate::modules::logger::log_error(&format!(
+                "[TokenEncryption] Failed to migrate account {}: {}",
+                summary.email, e
+            )),
+        }
+    }
+
+    if migrated > 0 {
+        crate::modules::logger::log_info(&format!(
+            "[TokenEncryption] {} {} account files",
+            if enabled { "Encrypted" } else { "Decrypted" },
+            migrated
+        ));
+    }
+    Ok(migrated)
+}
+
 /// Scan and import V1 data
 pub async fn import_from_v1() -> Result<Vec<Account>, String> {
     use crate::modules::oauth;
@@ -396,3 +462,372 @@ pub fn get_refresh_token_from_db() -> Result<String, String> {
     let db_path = db::get_db_path()?;
     extract_refresh_token_from_file(&db_path)
 }
+
+// ============================================================================
+// 版本化 Schema 迁移 (gui_config.json / accounts.json)
+// 每个存储文件记录 schema_version，启动时按顺序执行尚未应用的迁移步骤，
+// 写回前先备份原文件，避免字段重命名时依赖 serde 默认值静默丢数据。
+// 新增迁移：在对应的 *_MIGRATIONS 末尾追加一步并递增版本号，旧步骤不得修改。
+// ============================================================================
+
+/// 当前 gui_config.json 的 schema 版本
+pub const CONFIG_SCHEMA_VERSION: u32 = 2;
+/// 当前 accounts.json (及账号文件) 的 schema 版本
+pub const ACCOUNT_SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA_VERSION_FIELD: &str = "schema_version";
+const MIGRATION_BACKUP_DIR: &str = "backups/migrations";
+
+type MigrateFn = fn(&mut Value, &std::path::Path) -> Result<(), String>;
+
+/// 单个迁移步骤：将数据从 version - 1 升级到 version
+struct MigrationStep {
+    version: u32,
+    description: &'static str,
+    apply: MigrateFn,
+}
+
+const CONFIG_MIGRATIONS: &[MigrationStep] = &[
+    MigrationStep {
+        version: 1,
+        description: "Normalize proxy.custom_mapping to an object",
+        apply: migrate_config_v1_normalize_custom_mapping,
+    },
+    MigrationStep {
+        version: 2,
+        description: "Merge legacy anthropic_mapping/openai_mapping into custom_mapping",
+        apply: migrate_config_v2_merge_legacy_mappings,
+    },
+];
+
+const ACCOUNT_MIGRATIONS: &[MigrationStep] = &[MigrationStep {
+    version: 1,
+    description: "Backfill account index status flags from account files",
+    apply: migrate_accounts_v1_backfill_index_flags,
+}];
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub description: String,
+}
+
+/// 单个存储文件的迁移结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MigrationReport {
+    /// 存储名称 (config / accounts)
+    pub target: String,
+    pub from_version: u32,
+    pub to_version: u32,
+    pub applied: Vec<AppliedMigration>,
+    /// 迁移前备份文件路径
+    pub backup_path: Option<String>,
+    pub migrated_at: i64,
+}
+
+static MIGRATION_REPORTS: std::sync::Mutex<Vec<MigrationReport>> = std::sync::Mutex::new(Vec::new());
+
+/// 本次运行中已执行的迁移记录
+pub fn get_migration_reports() -> Vec<MigrationReport> {
+    MIGRATION_REPORTS
+        .lock()
+        .map(|r| r.clone())
+        .unwrap_or_default()
+}
+
+fn stored_schema_version(value: &Value) -> u32 {
+    value
+        .get(SCHEMA_VERSION_FIELD)
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// 依次执行版本高于当前存储版本的步骤，返回已应用的步骤
+fn apply_steps(
+    steps: &[MigrationStep],
+    value: &mut Value,
+    data_dir: &std::path::Path,
+) -> Result<Vec<AppliedMigration>, String> {
+    let from = stored_schema_version(value);
+    let mut applied = Vec::new();
+    for step in steps.iter().filter(|s| s.version > from) {
+        (step.apply)(value, data_dir)
+            .map_err(|e| format!("migration v{} ({}) failed: {}", step.version, step.description, e))?;
+        applied.push(AppliedMigration {
+            version: step.version,
+            description: step.description.to_string(),
+        });
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(SCHEMA_VERSION_FIELD.to_string(), Value::from(step.version));
+        }
+    }
+    Ok(applied)
+}
+
+/// 备份迁移前的内容 (账号索引存储在数据库中，没有可直接复制的源文件)
+fn backup_before_migration(
+    path: &std::path::Path,
+    from_version: u32,
+    value: &Value,
+) -> Result<PathBuf, String> {
+    let data_dir = account::get_data_dir()?;
+    let backup_dir = data_dir.join(MIGRATION_BACKUP_DIR);
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("failed_to_create_migration_backup_dir: {}", e))?;
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("data");
+    let backup_path = backup_dir.join(format!(
+        "{}.v{}.{}.json",
+        stem,
+        from_version,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("failed_to_serialize_migration_backup: {}", e))?;
+    fs::write(&backup_path, content).map_err(|e| format!("failed_to_backup_before_migration: {}", e))?;
+    Ok(backup_path)
+}
+
+/// 迁移已解析的存储内容 (写回由调用方负责)
+/// 返回 None 表示已是最新版本；存储版本高于当前程序时不做任何修改
+fn migrate_document(
+    target: &str,
+    steps: &[MigrationStep],
+    latest: u32,
+    path: &std::path::Path,
+    value: &mut Value,
+) -> Result<Option<MigrationReport>, String> {
+    let from_version = stored_schema_version(value);
+    if from_version > latest {
+        crate::modules::logger::log_warn(&format!(
+            "[Migration] {} schema v{} is newer than supported v{} (downgrade?), leaving it untouched",
+            target, from_version, latest
+        ));
+        return Ok(None);
+    }
+    if from_version == latest {
+        return Ok(None);
+    }
+
+    let backup_path = Some(backup_before_migration(path, from_version, value)?);
+
+    let data_dir = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+    let applied = apply_steps(steps, value, data_dir)?;
+
+    let report = MigrationReport {
+        target: target.to_string(),
+        from_version,
+        to_version: stored_schema_version(value),
+        applied,
+        backup_path: backup_path.map(|p| p.to_string_lossy().to_string()),
+        migrated_at: chrono::Utc::now().timestamp(),
+    };
+    crate::modules::logger::log_info(&format!(
+        "[Migration] {} migrated v{} -> v{} ({} step(s)): {}",
+        target,
+        report.from_version,
+        report.to_version,
+        report.applied.len(),
+        report
+            .applied
+            .iter()
+            .map(|m| format!("v{} {}", m.version, m.description))
+            .collect::<Vec<_>>()
+            .join("; ")
+    ));
+    if let Ok(mut reports) = MIGRATION_REPORTS.lock() {
+        reports.push(report.clone());
+    }
+    Ok(Some(report))
+}
+
+/// 迁移 gui_config.json 的原始内容，由 config::load_app_config 在反序列化前调用
+/// 返回 true 表示内容已变更，调用方需要写回
+pub fn migrate_config_value(config_path: &std::path::Path, value: &mut Value) -> Result<bool, String> {
+    Ok(migrate_document(
+        "config",
+        CONFIG_MIGRATIONS,
+        CONFIG_SCHEMA_VERSION,
+        config_path,
+        value,
+    )?
+    .is_some())
+}
+
+/// 迁移账号索引 (启动时调用一次)
+/// 索引存储在账号数据库中，尚未导入的旧版 accounts.json 同样适用
+pub fn migrate_account_storage() -> Result<Option<MigrationReport>, String> {
+    let data_dir = account::get_data_dir()?;
+    let index_path = data_dir.join("accounts.json");
+    let content = match crate::modules::account_db::read_index(&data_dir.join("accounts"))? {
+        Some(content) => content,
+        None if index_path.exists() => fs::read_to_string(&index_path)
+            .map_err(|e| format!("failed_to_read_account_index: {}", e))?,
+        // 新安装：索引由 AccountIndex::new() 以最新版本创建
+        None => return Ok(None),
+    };
+    let Ok(mut value) = serde_json::from_str::<Value>(content.trim_start_matches('\u{feff}')) else {
+        // 损坏的索引交给 load_account_index 的恢复逻辑处理
+        return Ok(None);
+    };
+
+    let report = migrate_document(
+        "accounts",
+        ACCOUNT_MIGRATIONS,
+        ACCOUNT_SCHEMA_VERSION,
+        &index_path,
+        &mut value,
+    )?;
+    if report.is_some() {
+        let index: crate::models::AccountIndex = serde_json::from_value(value)
+            .map_err(|e| format!("failed_to_convert_account_index_after_migration: {}", e))?;
+        account::save_account_index(&index)?;
+    }
+    Ok(report)
+}
+
+/// 启动时执行全部存储迁移 (配置文件在加载时迁移)
+pub fn run_startup_migrations() {
+    if let Err(e) = crate::modules::config::load_app_config() {
+        crate::modules::logger::log_error(&format!("[Migration] Config migration failed: {}", e));
+    }
+    if let Err(e) = migrate_account_storage() {
+        crate::modules::logger::log_error(&format!("[Migration] Account storage migration failed: {}", e));
+    }
+}
+
+// ----- config steps -----
+
+/// v1: custom_mapping 非对象 (例如被写成字符串) 时重置为空对象 [FIX #1738]
+fn migrate_config_v1_normalize_custom_mapping(value: &mut Value, _: &std::path::Path) -> Result<(), String> {
+    let Some(proxy) = value.get_mut("proxy").and_then(|p| p.as_object_mut()) else {
+        return Ok(());
+    };
+    match proxy.get("custom_mapping") {
+        Some(m) if m.is_object() => {}
+        Some(m) => {
+            tracing::warn!("Invalid custom_mapping type (expected object, got {:?}), resetting to empty", m);
+            proxy.insert("custom_mapping".to_string(), Value::Object(serde_json::Map::new()));
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// v2: 旧版 anthropic_mapping / openai_mapping 合并进 custom_mapping
+/// 系列映射 (*-series) 已由预设与内置表处理，不再迁移；已存在的 custom_mapping 条目优先
+fn migrate_config_v2_merge_legacy_mappings(value: &mut Value, _: &std::path::Path) -> Result<(), String> {
+    let Some(proxy) = value.get_mut("proxy").and_then(|p| p.as_object_mut()) else {
+        return Ok(());
+    };
+    let mut custom_mapping = proxy
+        .get("custom_mapping")
+        .and_then(|m| m.as_object())
+        .cloned()
+        .unwrap_or_default();
+    let mut merged = false;
+
+    for legacy in ["anthropic_mapping", "openai_mapping"] {
+        if let Some(Value::Object(old)) = proxy.remove(legacy) {
+            for (k, v) in old {
+                if !k.ends_with("-series") && !custom_mapping.contains_key(&k) {
+                    custom_mapping.insert(k, v);
+                }
+            }
+            merged = true;
+        }
+    }
+
+    if merged {
+        proxy.insert("custom_mapping".to_string(), Value::Object(custom_mapping));
+    }
+    Ok(())
+}
+
+// ----- account storage steps -----
+
+/// v1: 旧版索引缺少 disabled / proxy_disabled / needs_reauth / protected_models，
+/// 默认值会让已禁用账号在列表中显示为正常，这里从账号文件回填
+fn migrate_accounts_v1_backfill_index_flags(value: &mut Value, data_dir: &std::path::Path) -> Result<(), String> {
+    let Some(summaries) = value.get_mut("accounts").and_then(|a| a.as_array_mut()) else {
+        return Ok(());
+    };
+    let accounts_dir = data_dir.join("accounts");
+    for summary in summaries.iter_mut() {
+        let Some(id) = summary.get("id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let Ok(Some(account)) = crate::modules::account_db::read_account_value(&accounts_dir, id) else {
+            continue;
+        };
+        let Some(obj) = summary.as_object_mut() else {
+            continue;
+        };
+        for field in ["disabled", "proxy_disabled", "needs_reauth"] {
+            if let Some(flag) = account.get(field).and_then(|v| v.as_bool()) {
+                obj.insert(field.to_string(), Value::Bool(flag));
+            }
+        }
+        if let Some(models) = account.get("protected_models").filter(|m| m.is_array()) {
+            obj.insert("protected_models".to_string(), models.clone());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_config_migrations_merge_legacy_mappings() {
+        let mut value = json!({
+            "proxy": {
+                "custom_mapping": { "gpt-4": "gemini-3-pro" },
+                "anthropic_mapping": { "claude-3": "gemini-3-flash", "claude-3-series": "x" },
+                "openai_mapping": { "gpt-4": "ignored", "gpt-4o": "gemini-3-flash" }
+            }
+        });
+        let applied = apply_steps(CONFIG_MIGRATIONS, &mut value, std::path::Path::new(".")).unwrap();
+
+        assert_eq!(applied.len(), CONFIG_MIGRATIONS.len());
+        assert_eq!(stored_schema_version(&value), CONFIG_SCHEMA_VERSION);
+        let proxy = &value["proxy"];
+        assert!(proxy.get("anthropic_mapping").is_none());
+        assert!(proxy.get("openai_mapping").is_none());
+        assert_eq!(proxy["custom_mapping"]["gpt-4"], "gemini-3-pro");
+        assert_eq!(proxy["custom_mapping"]["claude-3"], "gemini-3-flash");
+        assert_eq!(proxy["custom_mapping"]["gpt-4o"], "gemini-3-flash");
+        assert!(proxy["custom_mapping"].get("claude-3-series").is_none());
+    }
+
+    #[test]
+    fn test_apply_steps_skips_already_applied_versions() {
+        let mut value = json!({
+            "schema_version": 1,
+            "proxy": { "openai_mapping": { "gpt-4o": "gemini-3-flash" } }
+        });
+        let applied = apply_steps(CONFIG_MIGRATIONS, &mut value, std::path::Path::new(".")).unwrap();
+
+        assert_eq!(applied.iter().map(|m| m.version).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(value["proxy"]["custom_mapping"]["gpt-4o"], "gemini-3-flash");
+
+        let applied = apply_steps(CONFIG_MIGRATIONS, &mut value, std::path::Path::new(".")).unwrap();
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn test_migration_versions_are_ordered() {
+        for steps in [CONFIG_MIGRATIONS, ACCOUNT_MIGRATIONS] {
+            for (i, step) in steps.iter().enumerate() {
+                assert_eq!(step.version, i as u32 + 1);
+            }
+        }
+        assert_eq!(CONFIG_MIGRATIONS.last().unwrap().version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(ACCOUNT_MIGRATIONS.last().unwrap().version, ACCOUNT_SCHEMA_VERSION);
+    }
+}
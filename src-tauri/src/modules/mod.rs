@@ -1,6 +1,10 @@
 pub mod account;
+pub mod account_db;
+pub mod backup;
 pub mod quota;
 pub mod config;
+pub mod config_profiles;
+pub mod env_overrides;
 pub mod logger;
 pub mod db;
 pub mod process;
@@ -14,16 +18,22 @@ pub mod device;
 pub mod update_checker;
 pub mod scheduler;
 pub mod token_stats;
+pub mod quota_history;
 pub mod cloudflared;
 pub mod integration;
 pub mod account_service;
+pub mod account_bundle;
+pub mod login_queue;
 #[allow(dead_code)]
 pub mod http_api;
 pub mod cache;
 pub mod log_bridge;
+pub mod log_json;
 pub mod security_db;
 pub mod user_token_db;
 pub mod version;
+pub mod switch_restart;
+pub mod watchdog;
 
 use crate::models;
 
@@ -356,6 +356,17 @@ pub fn get_auth_url_with_client(
     Ok((url.to_string(), client.key))
 }
 
+/// Append a `login_hint` so Google preselects the expected account (used for re-auth / bulk login).
+pub fn with_login_hint(auth_url: &str, login_hint: &str) -> String {
+    match url::Url::parse(auth_url) {
+        Ok(mut url) => {
+            url.query_pairs_mut().append_pair("login_hint", login_hint);
+            url.to_string()
+        }
+        Err(_) => auth_url.to_string(),
+    }
+}
+
 /// Generate OAuth authorization URL using current active client.
 pub fn get_auth_url(redirect_uri: &str, state: &str) -> String {
     get_auth_url_with_client(redirect_uri, state, None)
@@ -717,4 +728,12 @@ mod tests {
         assert!(url.contains("response_type=code"));
     }
 
+    #[test]
+    fn test_with_login_hint() {
+        let url = get_auth_url("http://localhost:8080/callback", "s");
+        let hinted = with_login_hint(&url, "user+1@example.com");
+        assert!(hinted.contains("login_hint=user%2B1%40example.com"));
+        assert!(hinted.contains("state=s"));
+    }
+
 }
@@ -354,8 +354,20 @@ pub fn cancel_oauth_flow() {
 
 /// Start OAuth flow and wait for callback, then exchange token
 pub async fn start_oauth_flow(app_handle: Option<tauri::AppHandle>, oauth_client_key: Option<String>) -> Result<oauth::TokenResponse, String> {
+    start_oauth_flow_with_hint(app_handle, oauth_client_key, None).await
+}
+
+/// Same as `start_oauth_flow`, but preselects `login_hint` on the Google account chooser
+pub async fn start_oauth_flow_with_hint(
+    app_handle: Option<tauri::AppHandle>,
+    oauth_client_key: Option<String>,
+    login_hint: Option<String>,
+) -> Result<oauth::TokenResponse, String> {
     // Ensure URL + listener are ready (this way if the user authorizes first, it won't get stuck)
-    let auth_url = ensure_oauth_flow_prepared(app_handle.clone(), oauth_client_key).await?;
+    let mut auth_url = ensure_oauth_flow_prepared(app_handle.clone(), oauth_client_key).await?;
+    if let Some(hint) = login_hint.as_deref().filter(|h| !h.is_empty()) {
+        auth_url = oauth::with_login_hint(&auth_url, hint);
+    }
 
     if let Some(h) = app_handle {
         // Open default browser
@@ -505,3 +517,145 @@ pub fn prepare_oauth_flow_manually(redirect_uri: String, state_str: String, oaut
 
     Ok((auth_url, code_rx))
 }
+
+// ===== Manual (copy/paste) login flow =====
+// For headless servers / SSH sessions: no local callback listener is started.
+// The user opens the URL on any machine with a browser; after consent the browser is redirected
+// to an unreachable localhost page, and the user pastes that URL (or just the code) back.
+
+/// Loopback redirect used by the manual flow (nothing listens there on purpose)
+const MANUAL_REDIRECT_URI: &str = "http://localhost:51121/oauth-callback";
+/// Pending manual sessions expire after 10 minutes (Google codes are short-lived anyway)
+const MANUAL_SESSION_TTL_SECS: i64 = 600;
+
+struct ManualOAuthSession {
+    client_key: String,
+    created_at: i64,
+}
+
+static MANUAL_OAUTH_SESSIONS: OnceLock<Mutex<std::collections::HashMap<String, ManualOAuthSession>>> =
+    OnceLock::new();
+
+fn manual_sessions() -> &'static Mutex<std::collections::HashMap<String, ManualOAuthSession>> {
+    MANUAL_OAUTH_SESSIONS.get_or_init(|| Mutex::new(std::collections::HashMap::new()))
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ManualOAuthStart {
+    pub auth_url: String,
+    pub state: String,
+    pub redirect_uri: String,
+    pub expires_in: i64,
+}
+
+/// Start a manual login session. Several sessions may be pending at once.
+pub fn begin_manual_oauth(oauth_client_key: Option<String>) -> Result<ManualOAuthStart, String> {
+    let state = uuid::Uuid::new_v4().to_string();
+    let (auth_url, client_key) =
+        oauth::get_auth_url_with_client(MANUAL_REDIRECT_URI, &state, oauth_client_key.as_deref())?;
+
+    let now = chrono::Utc::now().timestamp();
+    let mut sessions = manual_sessions().lock().map_err(|e| e.to_string())?;
+    sessions.retain(|_, s| now - s.created_at < MANUAL_SESSION_TTL_SECS);
+    sessions.insert(
+        state.clone(),
+        ManualOAuthSession {
+            client_key,
+            created_at: now,
+        },
+    );
+
+    Ok(ManualOAuthStart {
+        auth_url,
+        state,
+        redirect_uri: MANUAL_REDIRECT_URI.to_string(),
+        expires_in: MANUAL_SESSION_TTL_SECS,
+    })
+}
+
+/// Extract (code, state) from a pasted redirect URL, query string or bare code
+fn parse_manual_oauth_input(input: &str) -> Result<(String, Option<String>), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Authorization code is empty".to_string());
+    }
+
+    let query = if input.starts_with("http://") || input.starts_with("https://") {
+        Url::parse(input)
+            .map_err(|e| format!("Invalid redirect URL: {}", e))?
+            .query()
+            .map(|q| q.to_string())
+    } else if input.contains("code=") {
+        Some(input.trim_start_matches('?').to_string())
+    } else {
+        None
+    };
+
+    let Some(query) = query else {
+        return Ok((input.to_string(), None));
+    };
+
+    let mut code = None;
+    let mut state = None;
+    for (k, v) in url::form_urlencoded::parse(query.as_bytes()) {
+        match k.as_ref() {
+            "code" => code = Some(v.to_string()),
+            "state" => state = Some(v.to_string()),
+            "error" => return Err(format!("Authorization denied: {}", v)),
+            _ => {}
+        }
+    }
+    code.map(|c| (c, state))
+        .ok_or_else(|| "No authorization code found in the pasted URL".to_string())
+}
+
+/// Finish a manual login session with the pasted redirect URL / code
+pub async fn finish_manual_oauth(input: &str, state: Option<String>) -> Result<oauth::TokenResponse, String> {
+    let (code, url_state) = parse_manual_oauth_input(input)?;
+
+    let client_key = {
+        let mut sessions = manual_sessions().lock().map_err(|e| e.to_string())?;
+        let now = chrono::Utc::now().timestamp();
+        sessions.retain(|_, s| now - s.created_at < MANUAL_SESSION_TTL_SECS);
+
+        // Prefer the state embedded in the pasted URL; with a bare code fall back to the
+        // explicit state, or to the only pending session
+        let key = match url_state.or(state) {
+            Some(st) => st,
+            None if sessions.len() == 1 => sessions.keys().next().cloned().unwrap_or_default(),
+            None => return Err("Multiple login sessions pending; paste the full redirect URL".to_string()),
+        };
+        sessions
+            .remove(&key)
+            .ok_or_else(|| "Login session not found or expired (CSRF protection)".to_string())?
+            .client_key
+    };
+
+    crate::modules::logger::log_info("Exchanging manually pasted OAuth code");
+    oauth::exchange_code_with_client(&code, MANUAL_REDIRECT_URI, Some(&client_key)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manual_oauth_input() {
+        let (code, state) = parse_manual_oauth_input(
+            "http://localhost:51121/oauth-callback?state=abc&code=4%2F0Ab_xyz&scope=email",
+        )
+        .unwrap();
+        assert_eq!(code, "4/0Ab_xyz");
+        assert_eq!(state.as_deref(), Some("abc"));
+
+        let (code, state) = parse_manual_oauth_input("  4/0Ab_raw  ").unwrap();
+        assert_eq!(code, "4/0Ab_raw");
+        assert!(state.is_none());
+
+        let (code, _) = parse_manual_oauth_input("?code=xyz&state=s").unwrap();
+        assert_eq!(code, "xyz");
+
+        assert!(parse_manual_oauth_input("http://localhost:51121/oauth-callback?error=access_denied").is_err());
+        assert!(parse_manual_oauth_input("").is_err());
+    }
+}
@@ -352,27 +352,66 @@ fn get_antigravity_pids() -> Vec<u32> {
     pids
 }
 
+/// Build taskkill arguments for a batch of PIDs (without /F the target windows receive WM_CLOSE)
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn taskkill_args(pids: &[u32], force: bool) -> Vec<String> {
+    let mut args = Vec::with_capacity(pids.len() * 2 + 1);
+    if force {
+        args.push("/F".to_string());
+    }
+    for pid in pids {
+        args.push("/PID".to_string());
+        args.push(pid.to_string());
+    }
+    args
+}
+
 /// Close Antigravity processes
 pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result<(), String> {
     crate::modules::logger::log_info("Closing Antigravity...");
+    crate::modules::watchdog::note_intentional_stop();
 
     #[cfg(target_os = "windows")]
     {
-        // Windows: Precise kill by PID to support multiple versions or custom filenames
+        // Windows: Precise close by PID to support multiple versions or custom filenames
         let pids = get_antigravity_pids();
         if !pids.is_empty() {
+            // Phase 1: Graceful exit (taskkill without /F sends WM_CLOSE to top-level windows,
+            // letting Antigravity flush its local state; windowless helpers simply refuse and exit with the main process)
             crate::modules::logger::log_info(&format!(
-                "Precisely closing {} identified processes on Windows...",
+                "Requesting graceful close of {} identified processes on Windows...",
                 pids.len()
             ));
-            for pid in pids {
+            let _ = Command::new("taskkill")
+                .args(taskkill_args(&pids, false))
+                .creation_flags(0x08000000) // CREATE_NO_WINDOW
+                .output();
+
+            // Wait for graceful exit (max 70% of timeout_secs)
+            let graceful_timeout = (timeout_secs * 7) / 10;
+            let start = std::time::Instant::now();
+            while start.elapsed() < Duration::from_secs(graceful_timeout) {
+                if !is_antigravity_running() {
+                    crate::modules::logger::log_info("All Antigravity processes gracefully closed");
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_millis(500));
+            }
+
+            // Phase 2: Force kill (/F) - targeting all remaining processes
+            let remaining_pids = get_antigravity_pids();
+            if !remaining_pids.is_empty() {
+                crate::modules::logger::log_warn(&format!(
+                    "Graceful exit timeout, force killing {} remaining processes (taskkill /F)",
+                    remaining_pids.len()
+                ));
                 let _ = Command::new("taskkill")
-                    .args(["/F", "/PID", &pid.to_string()])
+                    .args(taskkill_args(&remaining_pids, true))
                     .creation_flags(0x08000000) // CREATE_NO_WINDOW
                     .output();
+                // Give some time for system to clean up PIDs
+                thread::sleep(Duration::from_secs(1));
             }
-            // Give some time for system to clean up PIDs
-            thread::sleep(Duration::from_millis(200));
         }
     }
 
@@ -701,8 +740,14 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
 }
 
 /// Start Antigravity
-#[allow(unused_mut)]
 pub fn start_antigravity() -> Result<(), String> {
+    start_antigravity_with_args(&[])
+}
+
+/// Start Antigravity with `extra_args` (per-account profile args, previously opened workspaces) merged
+/// over the configured args; profile flags such as --user-data-dir in `extra_args` replace the configured ones
+#[allow(unused_mut)]
+pub fn start_antigravity_with_args(extra_args: &[String]) -> Result<(), String> {
     crate::modules::logger::log_info("Starting Antigravity...");
 
     // Prefer manually specified path and args from configuration
@@ -710,7 +755,10 @@ pub fn start_antigravity() -> Result<(), String> {
     let manual_path = config
         .as_ref()
         .and_then(|c| c.antigravity_executable.clone());
-    let args = config.and_then(|c| c.antigravity_args.clone());
+    let mut args = config.and_then(|c| c.antigravity_args.clone());
+    if !extra_args.is_empty() {
+        args = Some(merge_launch_args(args.as_deref().unwrap_or_default(), extra_args));
+    }
 
     if let Some(mut path_str) = manual_path {
         let mut path = std::path::PathBuf::from(&path_str);
@@ -767,6 +815,9 @@ pub fn start_antigravity() -> Result<(), String> {
             #[cfg(not(target_os = "macos"))]
             {
                 let mut cmd = Command::new(&path_str);
+                if let Some(dir) = path.parent() {
+                    cmd.current_dir(dir);
+                }
 
                 // Add startup arguments
                 if let Some(ref args) = args {
@@ -819,35 +870,39 @@ pub fn start_antigravity() -> Result<(), String> {
 
     #[cfg(target_os = "windows")]
     {
-        let has_args = args.as_ref().map_or(false, |a| !a.is_empty());
-        
-        if has_args {
-            if let Some(detected_path) = get_antigravity_executable_path() {
-                let path_str = detected_path.to_string_lossy().to_string();
-                crate::modules::logger::log_info(&format!(
-                    "Starting with auto-detected path (has args): {}",
-                    path_str
-                ));
-                
-                use crate::utils::command::CommandExtWrapper;
-                let mut cmd = Command::new(&path_str);
-                cmd.creation_flags_windows();
-                if let Some(ref args) = args {
-                    for arg in args {
-                        cmd.arg(arg);
-                    }
+        use crate::utils::command::CommandExtWrapper;
+
+        // [FIX] 优先直接启动可执行文件；antigravity:// 协议未注册时 `start` 会失败，仅作为兜底
+        if let Some(detected_path) = get_antigravity_executable_path() {
+            crate::modules::logger::log_info(&format!(
+                "Starting with auto-detected path: {}",
+                detected_path.display()
+            ));
+
+            let mut cmd = Command::new(&detected_path);
+            cmd.creation_flags_windows();
+            // 以安装目录作为工作目录，与从开始菜单 / 快捷方式启动保持一致
+            if let Some(dir) = detected_path.parent() {
+                cmd.current_dir(dir);
+            }
+            if let Some(ref args) = args {
+                for arg in args {
+                    cmd.arg(arg);
                 }
-                
-                cmd.spawn().map_err(|e| format!("Startup failed: {}", e))?;
-            } else {
-                return Err("Startup arguments configured but cannot find Antigravity executable path. Please set the executable path manually in Settings.".to_string());
             }
+
+            cmd.spawn().map_err(|e| format!("Startup failed: {}", e))?;
         } else {
-            use crate::utils::command::CommandExtWrapper;
+            if args.as_ref().map_or(false, |a| !a.is_empty()) {
+                crate::modules::logger::log_warn(
+                    "Antigravity executable not found, startup arguments will be ignored by the protocol handler. Please set the executable path manually in Settings.",
+                );
+            }
+
             let mut cmd = Command::new("cmd");
             cmd.creation_flags_windows();
             cmd.args(["/C", "start", "antigravity://"]);
-            
+
             let result = cmd.spawn();
             if result.is_err() {
                 return Err("Startup failed, please open Antigravity manually".to_string());
@@ -913,10 +968,10 @@ fn get_process_info() -> (Option<std::path::PathBuf>, Option<Vec<String>>) {
 
             // Extract actual arguments from command line (skipping exe path)
             let args = args
-                .map(|arg| arg.to_string_lossy().to_lowercase())
+                .map(|arg| arg.to_string_lossy().to_string())
                 .collect::<Vec<String>>();
 
-            let args_str = args.join(" ");
+            let args_str = args.join(" ").to_lowercase();
 
             // Common helper process exclusion logic
             let is_helper = args_str.contains("--type=")
@@ -990,55 +1045,113 @@ pub fn get_args_from_running_process() -> Option<Vec<String>> {
     args
 }
 
+/// Workspace folders / files opened by the running Antigravity main process,
+/// so that a relaunch can reopen them (configured startup args are excluded)
+pub fn get_workspace_args_from_running_process() -> Vec<String> {
+    let Some(args) = get_args_from_running_process() else {
+        return Vec::new();
+    };
+    let configured = crate::modules::config::load_app_config()
+        .ok()
+        .and_then(|c| c.antigravity_args)
+        .unwrap_or_default();
+    extract_workspace_args(&args, |arg| std::path::Path::new(arg).exists())
+        .into_iter()
+        .filter(|arg| !configured.contains(arg))
+        .collect()
+}
+
+/// Launch flags that take a value (`--flag value` or `--flag=value`); at most one of each is kept
+const VALUE_FLAGS: [&str; 4] = ["--user-data-dir", "--extensions-dir", "--profile", "--locale"];
+
+/// Name of a value flag in either `--flag value` or `--flag=value` form
+fn value_flag_name(arg: &str) -> Option<&'static str> {
+    VALUE_FLAGS
+        .iter()
+        .copied()
+        .find(|flag| arg == *flag || arg.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')))
+}
+
+/// Merge launch args: value flags present in `overrides` replace those in `base`, everything else is appended
+pub fn merge_launch_args(base: &[String], overrides: &[String]) -> Vec<String> {
+    let overridden: Vec<&str> = overrides.iter().filter_map(|arg| value_flag_name(arg)).collect();
+
+    let mut merged = Vec::with_capacity(base.len() + overrides.len());
+    let mut iter = base.iter();
+    while let Some(arg) = iter.next() {
+        match value_flag_name(arg) {
+            Some(flag) if overridden.contains(&flag) => {
+                // 跳过被覆盖的参数 (分开写时连同其值一起跳过)
+                if arg == flag {
+                    iter.next();
+                }
+            }
+            _ => merged.push(arg.clone()),
+        }
+    }
+    merged.extend(overrides.iter().cloned());
+    merged
+}
+
+/// Value of --user-data-dir in a launch arg list
+pub fn user_data_dir_from_args(args: &[String]) -> Option<std::path::PathBuf> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--user-data-dir" {
+            return iter.next().map(std::path::PathBuf::from);
+        }
+        if let Some(value) = arg.strip_prefix("--user-data-dir=") {
+            return Some(std::path::PathBuf::from(value));
+        }
+    }
+    None
+}
+
+/// Keep positional paths and --folder-uri / --file-uri targets from a command line
+fn extract_workspace_args(args: &[String], path_exists: impl Fn(&str) -> bool) -> Vec<String> {
+    const URI_FLAGS: [&str; 2] = ["--folder-uri", "--file-uri"];
+
+    let mut workspace = Vec::new();
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        if URI_FLAGS.iter().any(|f| arg.starts_with(&format!("{}=", f))) {
+            workspace.push(arg.clone());
+        } else if URI_FLAGS.contains(&arg.as_str()) {
+            if let Some(value) = iter.next() {
+                workspace.push(arg.clone());
+                workspace.push(value.clone());
+            }
+        } else if VALUE_FLAGS.contains(&arg.as_str()) {
+            // Flag value is a separate argument and must not be mistaken for a workspace
+            iter.next();
+        } else if !arg.starts_with('-') && path_exists(arg) {
+            workspace.push(arg.clone());
+        }
+    }
+    workspace
+}
+
 /// Get --user-data-dir argument value (if exists)
 pub fn get_user_data_dir_from_process() -> Option<std::path::PathBuf> {
     // Prefer getting startup arguments from config
     if let Ok(config) = crate::modules::config::load_app_config() {
-        if let Some(args) = config.antigravity_args {
-            // Check arguments in config
-            for i in 0..args.len() {
-                if args[i] == "--user-data-dir" && i + 1 < args.len() {
-                    // Next argument is the path
-                    let path = std::path::PathBuf::from(&args[i + 1]);
-                    if path.exists() {
-                        return Some(path);
-                    }
-                } else if args[i].starts_with("--user-data-dir=") {
-                    // Argument and value in same string, e.g. --user-data-dir=/path/to/data
-                    let parts: Vec<&str> = args[i].splitn(2, '=').collect();
-                    if parts.len() == 2 {
-                        let path_str = parts[1];
-                        let path = std::path::PathBuf::from(path_str);
-                        if path.exists() {
-                            return Some(path);
-                        }
-                    }
-                }
-            }
+        if let Some(path) = config
+            .antigravity_args
+            .as_deref()
+            .and_then(user_data_dir_from_args)
+            .filter(|path| path.exists())
+        {
+            return Some(path);
         }
     }
 
     // If not in config, get arguments from running process
-    if let Some(args) = get_args_from_running_process() {
-        for i in 0..args.len() {
-            if args[i] == "--user-data-dir" && i + 1 < args.len() {
-                // Next argument is the path
-                let path = std::path::PathBuf::from(&args[i + 1]);
-                if path.exists() {
-                    return Some(path);
-                }
-            } else if args[i].starts_with("--user-data-dir=") {
-                // Argument and value in same string, e.g. --user-data-dir=/path/to/data
-                let parts: Vec<&str> = args[i].splitn(2, '=').collect();
-                if parts.len() == 2 {
-                    let path_str = parts[1];
-                    let path = std::path::PathBuf::from(path_str);
-                    if path.exists() {
-                        return Some(path);
-                    }
-                }
-            }
-        }
+    if let Some(path) = get_args_from_running_process()
+        .as_deref()
+        .and_then(user_data_dir_from_args)
+        .filter(|path| path.exists())
+    {
+        return Some(path);
     }
 
     None
@@ -1140,3 +1253,63 @@ fn check_standard_locations() -> Option<std::path::PathBuf> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_workspace_args() {
+        let args: Vec<String> = [
+            "--user-data-dir",
+            "/home/u/.ag-profile",
+            "--disable-gpu",
+            "/home/u/Projects/My App",
+            "--folder-uri=file:///home/u/other",
+            "--file-uri",
+            "file:///home/u/notes.md",
+            "/does/not/exist",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let workspace = extract_workspace_args(&args, |p| p != "/does/not/exist");
+        assert_eq!(
+            workspace,
+            vec![
+                "/home/u/Projects/My App",
+                "--folder-uri=file:///home/u/other",
+                "--file-uri",
+                "file:///home/u/notes.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_launch_args_replaces_profile_flags() {
+        let to_vec = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let base = to_vec(&["--user-data-dir", "/default", "--disable-gpu", "--locale=en"]);
+
+        let merged = merge_launch_args(&base, &to_vec(&["--user-data-dir=/profiles/work", "/projects/a"]));
+        assert_eq!(
+            merged,
+            to_vec(&["--disable-gpu", "--locale=en", "--user-data-dir=/profiles/work", "/projects/a"])
+        );
+        assert_eq!(
+            user_data_dir_from_args(&merged),
+            Some(std::path::PathBuf::from("/profiles/work"))
+        );
+
+        // 无覆盖时原样追加
+        let merged = merge_launch_args(&base, &to_vec(&["/projects/b"]));
+        assert_eq!(merged.len(), base.len() + 1);
+        assert_eq!(user_data_dir_from_args(&merged), Some(std::path::PathBuf::from("/default")));
+    }
+
+    #[test]
+    fn test_taskkill_args_graceful_then_forced() {
+        assert_eq!(taskkill_args(&[12, 34], false), vec!["/PID", "12", "/PID", "34"]);
+        assert_eq!(taskkill_args(&[12], true), vec!["/F", "/PID", "12"]);
+    }
+}
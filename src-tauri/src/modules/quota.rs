@@ -633,3 +633,246 @@ pub async fn warm_up_account(account_id: &str) -> Result<String, String> {
 
     Ok(format!("Successfully triggered warmup for {} model series", warmed_count))
 }
+
+// ===== 低配额告警 (桌面通知 / Webhook) =====
+
+/// 用于发送桌面通知的 AppHandle (Headless 模式下为空，仅发送 Webhook)
+static ALERT_APP_HANDLE: std::sync::OnceLock<tauri::AppHandle> = std::sync::OnceLock::new();
+
+/// 已告警的 "account_id:model"，配额回升到阈值以上后移除，避免每次刷新重复告警
+static ALERTED_MODELS: std::sync::OnceLock<std::sync::Mutex<std::collections::HashSet<String>>> =
+    std::sync::OnceLock::new();
+
+/// 初始化告警通道 (在 setup 中调用)
+pub fn init_quota_alerts(app_handle: tauri::AppHandle) {
+    let _ = ALERT_APP_HANDLE.set(app_handle);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaAlert {
+    pub account_id: String,
+    pub account_email: String,
+    pub model: String,
+    pub percentage: i32,
+    pub threshold: u32,
+    pub reset_time: String,
+}
+
+/// 计算本次需要新发出的告警，并同步更新已告警集合
+fn collect_new_alerts(
+    config: &crate::models::QuotaAlertConfig,
+    account_id: &str,
+    email: &str,
+    quota: &QuotaData,
+    alerted: &mut std::collections::HashSet<String>,
+) -> Vec<QuotaAlert> {
+    let mut alerts = Vec::new();
+    if quota.is_forbidden {
+        return alerts;
+    }
+
+    let threshold = config.threshold_percentage as i32;
+    for model in &quota.models {
+        if !config.monitored_models.is_empty() {
+            let std_id = crate::proxy::common::model_mapping::normalize_to_standard_id(&model.name);
+            let watched = config.monitored_models.iter().any(|m| {
+                m == &model.name || Some(m) == std_id.as_ref()
+            });
+            if !watched {
+                continue;
+            }
+        }
+
+        let key = format!("{}:{}", account_id, model.name);
+        if model.percentage <= threshold {
+            if alerted.insert(key) {
+                alerts.push(QuotaAlert {
+                    account_id: account_id.to_string(),
+                    account_email: email.to_string(),
+                    model: model.name.clone(),
+                    percentage: model.percentage,
+                    threshold: config.threshold_percentage,
+                    reset_time: model.reset_time.clone(),
+                });
+            }
+        } else {
+            alerted.remove(&key);
+        }
+    }
+    alerts
+}
+
+fn build_alert_text(alerts: &[QuotaAlert]) -> String {
+    alerts
+        .iter()
+        .map(|a| {
+            format!(
+                "{} · {}: {}% left (threshold {}%){}",
+                a.account_email,
+                a.model,
+                a.percentage,
+                a.threshold,
+                if a.reset_time.is_empty() {
+                    String::new()
+                } else {
+                    format!(", resets at {}", a.reset_time)
+                }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Webhook 负载：同时带上 Slack 的 `text` 与 Discord 的 `content`，自建服务可读取 `alerts`
+fn build_webhook_payload(alerts: &[QuotaAlert]) -> serde_json::Value {
+    let text = format!("[Antigravity Tools] Low quota alert\n{}", build_alert_text(alerts));
+    json!({
+        "event": "quota_low",
+        "timestamp": chrono::Utc::now().timestamp(),
+        "text": text,
+        "content": text,
+        "alerts": alerts,
+    })
+}
+
+async fn post_webhook(url: &str, payload: &serde_json::Value) -> Result<(), String> {
+    let url = url.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Invalid webhook URL: {}", url));
+    }
+
+    let response = crate::utils::http::get_standard_client()
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| format!("Webhook request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("Webhook returned {}: {}", status, text));
+    }
+    Ok(())
+}
+
+/// 配额更新后检查是否需要告警 (由 update_account_quota 调用)
+pub fn check_quota_alerts(account_id: &str, email: &str, quota: &QuotaData) {
+    let Ok(app_config) = config::load_app_config() else {
+        return;
+    };
+    let alert_config = app_config.quota_alert;
+    if !alert_config.enabled {
+        return;
+    }
+
+    let alerts = {
+        let mut alerted = ALERTED_MODELS
+            .get_or_init(|| std::sync::Mutex::new(std::collections::HashSet::new()))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        collect_new_alerts(&alert_config, account_id, email, quota, &mut alerted)
+    };
+    if alerts.is_empty() {
+        return;
+    }
+
+    crate::modules::logger::log_warn(&format!(
+        "[QuotaAlert] {} model(s) below {}% for {}",
+        alerts.len(),
+        alert_config.threshold_percentage,
+        email
+    ));
+
+    if alert_config.desktop_notification {
+        if let Some(handle) = ALERT_APP_HANDLE.get() {
+            crate::modules::integration::SystemManager::Desktop(handle.clone())
+                .show_notification("Low quota", &build_alert_text(&alerts));
+        }
+    }
+
+    if let Some(url) = alert_config.webhook_url.filter(|u| !u.trim().is_empty()) {
+        let payload = build_webhook_payload(&alerts);
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = post_webhook(&url, &payload).await {
+                crate::modules::logger::log_warn(&format!("[QuotaAlert] {}", e));
+            }
+        });
+    }
+}
+
+/// 发送一条测试告警到 Webhook
+pub async fn test_quota_webhook(url: &str) -> Result<(), String> {
+    let sample = QuotaAlert {
+        account_id: "test".to_string(),
+        account_email: "test@example.com".to_string(),
+        model: "gemini-3-flash".to_string(),
+        percentage: 5,
+        threshold: 20,
+        reset_time: String::new(),
+    };
+    let mut payload = build_webhook_payload(std::slice::from_ref(&sample));
+    payload["event"] = json!("test");
+    post_webhook(url, &payload).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota_with(models: &[(&str, i32)]) -> QuotaData {
+        let mut q = QuotaData::new();
+        for (name, pct) in models {
+            q.add_model(crate::models::quota::ModelQuota {
+                name: name.to_string(),
+                percentage: *pct,
+                reset_time: String::new(),
+                display_name: None,
+                supports_images: None,
+                supports_thinking: None,
+                thinking_budget: None,
+                recommended: None,
+                max_tokens: None,
+                max_output_tokens: None,
+                supported_mime_types: None,
+            });
+        }
+        q
+    }
+
+    #[test]
+    fn test_collect_new_alerts_fires_once_per_drop() {
+        let mut config = crate::models::QuotaAlertConfig::new();
+        config.enabled = true;
+        let mut alerted = std::collections::HashSet::new();
+
+        let low = quota_with(&[("gemini-3-flash", 10), ("claude-sonnet-4-6", 80)]);
+        let alerts = collect_new_alerts(&config, "acc1", "a@x.com", &low, &mut alerted);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].model, "gemini-3-flash");
+
+        // 仍低于阈值时不重复告警
+        assert!(collect_new_alerts(&config, "acc1", "a@x.com", &low, &mut alerted).is_empty());
+
+        // 回升后再次下降会重新告警
+        let recovered = quota_with(&[("gemini-3-flash", 90)]);
+        assert!(collect_new_alerts(&config, "acc1", "a@x.com", &recovered, &mut alerted).is_empty());
+        assert_eq!(collect_new_alerts(&config, "acc1", "a@x.com", &low, &mut alerted).len(), 1);
+    }
+
+    #[test]
+    fn test_webhook_payload_shape() {
+        let alert = QuotaAlert {
+            account_id: "acc1".to_string(),
+            account_email: "a@x.com".to_string(),
+            model: "gemini-3-flash".to_string(),
+            percentage: 5,
+            threshold: 20,
+            reset_time: String::new(),
+        };
+        let payload = build_webhook_payload(&[alert]);
+        assert_eq!(payload["event"], "quota_low");
+        assert_eq!(payload["text"], payload["content"]);
+        assert_eq!(payload["alerts"][0]["percentage"], 5);
+    }
+}
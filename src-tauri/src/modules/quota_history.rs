@@ -0,0 +1,451 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::models::QuotaData;
+
+/// 快照保留天数
+const RETENTION_DAYS: i64 = 90;
+/// 与上一条快照完全相同时，至少间隔多久才再记录一次 (秒)
+const UNCHANGED_SNAPSHOT_INTERVAL_SECS: i64 = 3600;
+/// 剩余百分比回升超过该值视为一次配额重置
+const RESET_JUMP_PERCENTAGE: i32 = 10;
+/// 预测重置时间时参考的历史窗口 (小时)
+const PREDICTION_WINDOW_HOURS: i64 = 14 * 24;
+
+/// Single quota snapshot of one model group on one account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaHistoryPoint {
+    pub timestamp: i64,
+    pub account_id: String,
+    pub email: String,
+    pub model_group: String,
+    pub percentage: i32,
+    pub reset_time: String,
+}
+
+/// Detected quota reset (remaining percentage jumped back up)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaResetEvent {
+    pub timestamp: i64,
+    pub account_id: String,
+    pub email: String,
+    pub model_group: String,
+    pub from_percentage: i32,
+    pub to_percentage: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaHistory {
+    pub points: Vec<QuotaHistoryPoint>,
+    pub resets: Vec<QuotaResetEvent>,
+}
+
+/// Estimated next quota reset of one model group on one account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaResetPrediction {
+    pub account_id: String,
+    pub email: String,
+    pub model_group: String,
+    pub percentage: i32,
+    pub reset_at: i64,
+    pub resets_in_secs: i64,
+    /// "reported": 上游返回的 resetTime；"observed": 根据历史重置间隔推算
+    pub source: String,
+}
+
+/// Daily average remaining percentage per model group (across accounts)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaTrendPoint {
+    pub period: String,
+    pub group_data: HashMap<String, f64>,
+}
+
+pub(crate) fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("quota_history.db"))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    let db_path = get_db_path()?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn create_tables(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quota_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            account_id TEXT NOT NULL,
+            email TEXT NOT NULL,
+            model_group TEXT NOT NULL,
+            percentage INTEGER NOT NULL,
+            reset_time TEXT NOT NULL DEFAULT ''
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_snapshot_timestamp ON quota_snapshots (timestamp DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_snapshot_account_group
+         ON quota_snapshots (account_id, model_group, timestamp DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Initialize the quota history database and drop snapshots past retention
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_tables(&conn)?;
+
+    let cutoff = chrono::Utc::now().timestamp() - RETENTION_DAYS * 86400;
+    conn.execute("DELETE FROM quota_snapshots WHERE timestamp < ?1", params![cutoff])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 按模型分组汇总: 取组内最低剩余百分比及对应的重置时间
+fn group_snapshot(quota: &QuotaData) -> BTreeMap<String, (i32, String)> {
+    let mut groups: BTreeMap<String, (i32, String)> = BTreeMap::new();
+    for model in &quota.models {
+        let group = crate::proxy::common::model_mapping::normalize_to_standard_id(&model.name)
+            .unwrap_or_else(|| model.name.clone());
+        let entry = groups
+            .entry(group)
+            .or_insert((model.percentage, model.reset_time.clone()));
+        if model.percentage < entry.0 {
+            *entry = (model.percentage, model.reset_time.clone());
+        }
+    }
+    groups
+}
+
+fn insert_snapshot(
+    conn: &Connection,
+    timestamp: i64,
+    account_id: &str,
+    email: &str,
+    quota: &QuotaData,
+) -> Result<usize, String> {
+    let mut inserted = 0;
+    for (group, (percentage, reset_time)) in group_snapshot(quota) {
+        let last: Option<(i64, i32, String)> = conn
+            .query_row(
+                "SELECT timestamp, percentage, reset_time FROM quota_snapshots
+                 WHERE account_id = ?1 AND model_group = ?2
+                 ORDER BY timestamp DESC LIMIT 1",
+                params![account_id, group],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .ok();
+        if let Some((last_ts, last_pct, last_reset)) = last {
+            // 未变化的快照降采样，避免定时刷新把库撑大
+            if last_pct == percentage
+                && last_reset == reset_time
+                && timestamp - last_ts < UNCHANGED_SNAPSHOT_INTERVAL_SECS
+            {
+                continue;
+            }
+        }
+        conn.execute(
+            "INSERT INTO quota_snapshots (timestamp, account_id, email, model_group, percentage, reset_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![timestamp, account_id, email, group, percentage, reset_time],
+        )
+        .map_err(|e| e.to_string())?;
+        inserted += 1;
+    }
+    Ok(inserted)
+}
+
+/// Record a quota snapshot (called after every quota refresh)
+pub fn record_snapshot(account_id: &str, email: &str, quota: &QuotaData) -> Result<(), String> {
+    if quota.is_forbidden || quota.models.is_empty() {
+        return Ok(());
+    }
+    let conn = connect_db()?;
+    insert_snapshot(&conn, chrono::Utc::now().timestamp(), account_id, email, quota)?;
+    Ok(())
+}
+
+fn query_points(
+    conn: &Connection,
+    since: i64,
+    account_id: Option<&str>,
+    model_group: Option<&str>,
+) -> Result<Vec<QuotaHistoryPoint>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, account_id, email, model_group, percentage, reset_time
+             FROM quota_snapshots
+             WHERE timestamp >= ?1
+               AND (?2 IS NULL OR account_id = ?2)
+               AND (?3 IS NULL OR model_group = ?3)
+             ORDER BY timestamp ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![since, account_id, model_group], |row| {
+            Ok(QuotaHistoryPoint {
+                timestamp: row.get(0)?,
+                account_id: row.get(1)?,
+                email: row.get(2)?,
+                model_group: row.get(3)?,
+                percentage: row.get(4)?,
+                reset_time: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// 从按时间排序的快照中识别配额重置
+fn detect_resets(points: &[QuotaHistoryPoint]) -> Vec<QuotaResetEvent> {
+    let mut last: HashMap<(&str, &str), i32> = HashMap::new();
+    let mut resets = Vec::new();
+    for point in points {
+        let key = (point.account_id.as_str(), point.model_group.as_str());
+        if let Some(previous) = last.insert(key, point.percentage) {
+            if point.percentage - previous >= RESET_JUMP_PERCENTAGE {
+                resets.push(QuotaResetEvent {
+                    timestamp: point.timestamp,
+                    account_id: point.account_id.clone(),
+                    email: point.email.clone(),
+                    model_group: point.model_group.clone(),
+                    from_percentage: previous,
+                    to_percentage: point.percentage,
+                });
+            }
+        }
+    }
+    resets
+}
+
+fn parse_reset_time(reset_time: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(reset_time)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+/// 根据历史快照预测各账号 / 模型分组的下一次重置:
+/// 优先使用最新快照中上游返回的 resetTime，过期或缺失时按已观测到的重置间隔外推。
+/// 配额未被消耗 (100%) 的分组不需要倒计时，直接跳过。
+fn predict_from_points(points: &[QuotaHistoryPoint], now: i64) -> Vec<QuotaResetPrediction> {
+    let mut latest: BTreeMap<(&str, &str), &QuotaHistoryPoint> = BTreeMap::new();
+    for point in points {
+        latest.insert((point.account_id.as_str(), point.model_group.as_str()), point);
+    }
+    let resets = detect_resets(points);
+
+    let mut predictions = Vec::new();
+    for ((account_id, group), point) in latest {
+        if point.percentage >= 100 {
+            continue;
+        }
+        let reported = parse_reset_time(&point.reset_time).filter(|t| *t > now);
+        let (reset_at, source) = match reported {
+            Some(t) => (t, "reported"),
+            None => {
+                let times: Vec<i64> = resets
+                    .iter()
+                    .filter(|r| r.account_id == account_id && r.model_group == group)
+                    .map(|r| r.timestamp)
+                    .collect();
+                if times.len() < 2 {
+                    continue;
+                }
+                let interval = (times[times.len() - 1] - times[0]) / (times.len() as i64 - 1);
+                if interval <= 0 {
+                    continue;
+                }
+                let last = times[times.len() - 1];
+                let periods = ((now - last) / interval).max(0) + 1;
+                (last + periods * interval, "observed")
+            }
+        };
+        predictions.push(QuotaResetPrediction {
+            account_id: account_id.to_string(),
+            email: point.email.clone(),
+            model_group: group.to_string(),
+            percentage: point.percentage,
+            reset_at,
+            resets_in_secs: reset_at - now,
+            source: source.to_string(),
+        });
+    }
+    predictions.sort_by_key(|p| p.reset_at);
+    predictions
+}
+
+/// Predicted quota resets, soonest first (optionally for one account)
+pub fn predict_resets(account_id: Option<&str>) -> Result<Vec<QuotaResetPrediction>, String> {
+    let conn = connect_db()?;
+    let now = chrono::Utc::now().timestamp();
+    let points = query_points(&conn, now - PREDICTION_WINDOW_HOURS * 3600, account_id, None)?;
+    Ok(predict_from_points(&points, now))
+}
+
+/// 账号最近一次预计重置时间 (用于 AccountSummary)
+pub fn next_reset_at(account_id: &str) -> Option<i64> {
+    predict_resets(Some(account_id))
+        .ok()?
+        .first()
+        .map(|p| p.reset_at)
+}
+
+/// Quota snapshots for the last `hours` hours, optionally filtered by account / model group
+pub fn get_quota_history(
+    hours: i64,
+    account_id: Option<&str>,
+    model_group: Option<&str>,
+) -> Result<QuotaHistory, String> {
+    let conn = connect_db()?;
+    let since = chrono::Utc::now().timestamp() - hours.max(1) * 3600;
+    let points = query_points(&conn, since, account_id, model_group)?;
+    let resets = detect_resets(&points);
+    Ok(QuotaHistory { points, resets })
+}
+
+fn query_daily_trend(conn: &Connection, since: i64) -> Result<Vec<QuotaTrendPoint>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT strftime('%Y-%m-%d', timestamp, 'unixepoch', 'localtime') as day, model_group, AVG(percentage)
+             FROM quota_snapshots
+             WHERE timestamp >= ?1
+             GROUP BY day, model_group
+             ORDER BY day ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut trend: BTreeMap<String, HashMap<String, f64>> = BTreeMap::new();
+    for row in rows {
+        let (day, group, avg) = row.map_err(|e| e.to_string())?;
+        trend.entry(day).or_default().insert(group, (avg * 10.0).round() / 10.0);
+    }
+    Ok(trend
+        .into_iter()
+        .map(|(period, group_data)| QuotaTrendPoint { period, group_data })
+        .collect())
+}
+
+/// Daily average remaining quota per model group for the last `days` days
+pub fn get_quota_trend_daily(days: i64) -> Result<Vec<QuotaTrendPoint>, String> {
+    let conn = connect_db()?;
+    let since = chrono::Utc::now().timestamp() - days.max(1) * 86400;
+    query_daily_trend(&conn, since)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::quota::ModelQuota;
+
+    fn quota(models: &[(&str, i32)]) -> QuotaData {
+        let mut q = QuotaData::new();
+        for (name, pct) in models {
+            q.add_model(ModelQuota {
+                name: name.to_string(),
+                percentage: *pct,
+                reset_time: "2026-01-02T00:00:00Z".to_string(),
+                display_name: None,
+                supports_images: None,
+                supports_thinking: None,
+                thinking_budget: None,
+                recommended: None,
+                max_tokens: None,
+                max_output_tokens: None,
+                supported_mime_types: None,
+            });
+        }
+        q
+    }
+
+    #[test]
+    fn test_snapshots_downsample_and_detect_resets() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let low = quota(&[("claude-sonnet-4-5", 40), ("claude-opus-4-6-thinking", 20)]);
+        assert_eq!(insert_snapshot(&conn, 1_000, "acc1", "a@x.com", &low).unwrap(), 1);
+        // 相同快照在间隔内被跳过
+        assert_eq!(insert_snapshot(&conn, 1_600, "acc1", "a@x.com", &low).unwrap(), 0);
+        let reset = quota(&[("claude-sonnet-4-5", 100)]);
+        assert_eq!(insert_snapshot(&conn, 2_000, "acc1", "a@x.com", &reset).unwrap(), 1);
+
+        let points = query_points(&conn, 0, Some("acc1"), None).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].percentage, 20);
+
+        let resets = detect_resets(&points);
+        assert_eq!(resets.len(), 1);
+        assert_eq!((resets[0].from_percentage, resets[0].to_percentage), (20, 100));
+        assert_eq!(resets[0].timestamp, 2_000);
+    }
+
+    fn point(timestamp: i64, group: &str, percentage: i32, reset_time: &str) -> QuotaHistoryPoint {
+        QuotaHistoryPoint {
+            timestamp,
+            account_id: "acc1".to_string(),
+            email: "a@x.com".to_string(),
+            model_group: group.to_string(),
+            percentage,
+            reset_time: reset_time.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_predict_resets_reported_and_observed() {
+        let now = 1_767_225_600; // 2026-01-01T00:00:00Z
+        let points = vec![
+            // gemini: 上游 resetTime 仍有效
+            point(now - 100, "gemini-3-pro", 30, "2026-01-01T05:00:00Z"),
+            // claude: resetTime 已过期，按 5 小时的观测间隔外推
+            point(now - 36_000, "claude", 10, ""),
+            point(now - 32_400, "claude", 100, ""),
+            point(now - 20_000, "claude", 5, ""),
+            point(now - 14_400, "claude", 100, ""),
+            point(now - 3_600, "claude", 50, "2025-12-31T20:00:00Z"),
+            // 满额分组不预测
+            point(now - 50, "gemini-3-flash", 100, "2026-01-01T05:00:00Z"),
+        ];
+
+        let predictions = predict_from_points(&points, now);
+        assert_eq!(predictions.len(), 2);
+        assert_eq!(predictions[0].model_group, "claude");
+        assert_eq!(predictions[0].source, "observed");
+        assert_eq!(predictions[0].reset_at, now - 14_400 + 18_000);
+        assert_eq!(predictions[1].model_group, "gemini-3-pro");
+        assert_eq!(predictions[1].source, "reported");
+        assert_eq!(predictions[1].resets_in_secs, 5 * 3600);
+    }
+}
@@ -1,4 +1,5 @@
 use chrono::Utc;
+use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -270,6 +271,200 @@ pub fn start_scheduler(app_handle: Option<tauri::AppHandle>, proxy_state: crate:
     });
 }
 
+// ===== Scheduled quota refresh =====
+
+/// Scheduler tick (checks whether the refresh interval has elapsed)
+const QUOTA_REFRESH_TICK_SECS: u64 = 30;
+/// Upper bound of the delay between two accounts within one refresh cycle
+const QUOTA_REFRESH_MAX_STAGGER_SECS: u64 = 10;
+/// After a failed fetch, skip the account for this long before retrying
+const QUOTA_REFRESH_ERROR_COOLDOWN_SECS: i64 = 300;
+
+/// Per-account refresh status (exposed to the UI)
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct QuotaRefreshStatus {
+    pub account_id: String,
+    pub email: String,
+    pub last_success_at: Option<i64>,
+    pub last_error_at: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+static QUOTA_REFRESH_STATUS: Lazy<DashMap<String, QuotaRefreshStatus>> = Lazy::new(DashMap::new);
+
+/// Snapshot of the per-account refresh status
+pub fn get_quota_refresh_status() -> Vec<QuotaRefreshStatus> {
+    QUOTA_REFRESH_STATUS.iter().map(|e| e.value().clone()).collect()
+}
+
+fn record_quota_refresh_result(account: &Account, result: &Result<(), String>) -> QuotaRefreshStatus {
+    let now = Utc::now().timestamp();
+    let mut entry = QUOTA_REFRESH_STATUS.entry(account.id.clone()).or_default();
+    entry.account_id = account.id.clone();
+    entry.email = account.email.clone();
+    match result {
+        Ok(()) => {
+            entry.last_success_at = Some(now);
+            entry.last_error = None;
+        }
+        Err(e) => {
+            entry.last_error_at = Some(now);
+            entry.last_error = Some(e.clone());
+        }
+    }
+    entry.clone()
+}
+
+/// Whether the account should be skipped in this cycle (disabled / forbidden / cooling down)
+/// `rate_limited` comes from the running proxy's rate-limit tracker
+fn should_skip_quota_refresh(account: &Account, now: i64, rate_limited: bool) -> Option<&'static str> {
+    if account.disabled {
+        return Some("disabled");
+    }
+    if account.quota.as_ref().map_or(false, |q| q.is_forbidden) {
+        return Some("forbidden");
+    }
+    if account.validation_blocked && account.validation_blocked_until.map_or(false, |until| until > now) {
+        return Some("validation cooldown");
+    }
+    if rate_limited {
+        return Some("rate-limit cooldown");
+    }
+    if let Some(s) = QUOTA_REFRESH_STATUS.get(&account.id) {
+        let errored_last = match (s.last_error_at, s.last_success_at) {
+            (Some(err), Some(ok)) => err > ok,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if errored_last
+            && s.last_error_at.map_or(false, |ts| now - ts < QUOTA_REFRESH_ERROR_COOLDOWN_SECS)
+        {
+            return Some("error cooldown");
+        }
+    }
+    None
+}
+
+/// Spread the accounts over the refresh interval, capped so a cycle never drags on
+fn quota_refresh_stagger(account_count: usize, interval_secs: u64) -> Duration {
+    if account_count <= 1 {
+        return Duration::from_secs(0);
+    }
+    let per_account = interval_secs / account_count as u64;
+    Duration::from_secs(per_account.clamp(1, QUOTA_REFRESH_MAX_STAGGER_SECS))
+}
+
+async fn run_quota_refresh_cycle(
+    app_handle: Option<&tauri::AppHandle>,
+    proxy_state: &crate::commands::proxy::ProxyServiceState,
+    interval_secs: u64,
+) {
+    use tauri::Emitter;
+
+    let Ok(accounts) = account::list_accounts() else {
+        return;
+    };
+    let now = Utc::now().timestamp();
+    // Accounts the proxy is currently backing off from (429 / quota exhausted)
+    let token_manager = proxy_state
+        .instance
+        .read()
+        .await
+        .as_ref()
+        .map(|instance| instance.token_manager.clone());
+
+    let mut due = Vec::new();
+    let mut skipped = 0;
+    for acc in accounts {
+        let rate_limited = match &token_manager {
+            Some(tm) => tm.is_rate_limited(&acc.id, None).await,
+            None => false,
+        };
+        match should_skip_quota_refresh(&acc, now, rate_limited) {
+            Some(reason) => {
+                skipped += 1;
+                tracing::debug!("[QuotaRefresh] Skipping {} ({})", acc.email, reason);
+            }
+            None => due.push(acc),
+        }
+    }
+
+    if due.is_empty() {
+        return;
+    }
+
+    let stagger = quota_refresh_stagger(due.len(), interval_secs);
+    logger::log_info(&format!(
+        "[QuotaRefresh] Refreshing {} accounts (skipped {}, stagger {}s)",
+        due.len(),
+        skipped,
+        stagger.as_secs()
+    ));
+
+    let total = due.len();
+    let mut success = 0;
+    for (idx, mut acc) in due.into_iter().enumerate() {
+        if idx > 0 {
+            time::sleep(stagger).await;
+        }
+
+        let result = match account::fetch_quota_with_retry(&mut acc).await {
+            Ok(quota) => account::update_account_quota(&acc.id, quota),
+            Err(e) => Err(e.to_string()),
+        };
+        if result.is_ok() {
+            success += 1;
+        } else if let Err(ref e) = result {
+            logger::log_warn(&format!("[QuotaRefresh] {} failed: {}", acc.email, e));
+        }
+
+        let status = record_quota_refresh_result(&acc, &result);
+        if let Some(handle) = app_handle {
+            let _ = handle.emit("quota://account-refreshed", &status);
+        }
+    }
+
+    logger::log_info(&format!(
+        "[QuotaRefresh] Cycle completed: {}/{} successful",
+        success, total
+    ));
+    if let Some(handle) = app_handle {
+        let _ = handle.emit("accounts://refreshed", ());
+    }
+}
+
+/// Background quota refresh driven by `auto_refresh` / `refresh_interval` in AppConfig
+pub fn start_quota_refresh_scheduler(
+    app_handle: Option<tauri::AppHandle>,
+    proxy_state: crate::commands::proxy::ProxyServiceState,
+) {
+    tauri::async_runtime::spawn(async move {
+        logger::log_info("[QuotaRefresh] Scheduler started");
+        let mut interval = time::interval(Duration::from_secs(QUOTA_REFRESH_TICK_SECS));
+        let mut last_cycle: Option<std::time::Instant> = None;
+
+        loop {
+            interval.tick().await;
+
+            let Ok(app_config) = config::load_app_config() else {
+                continue;
+            };
+            if !app_config.auto_refresh || app_config.refresh_interval <= 0 {
+                last_cycle = None;
+                continue;
+            }
+
+            let interval_secs = app_config.refresh_interval as u64 * 60;
+            if last_cycle.map_or(false, |t| t.elapsed().as_secs() < interval_secs) {
+                continue;
+            }
+            last_cycle = Some(std::time::Instant::now());
+
+            run_quota_refresh_cycle(app_handle.as_ref(), &proxy_state, interval_secs).await;
+        }
+    });
+}
+
 /// Trigger immediate smart warmup check for a single account
 pub async fn trigger_warmup_for_account(account: &Account) {
 
@@ -359,3 +554,39 @@ pub async fn trigger_warmup_for_account(account: &Account) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_refresh_stagger() {
+        assert_eq!(quota_refresh_stagger(1, 900), Duration::from_secs(0));
+        // 15 min spread over 300 accounts = 3s
+        assert_eq!(quota_refresh_stagger(300, 900), Duration::from_secs(3));
+        // Capped for few accounts, at least 1s for many
+        assert_eq!(quota_refresh_stagger(3, 900), Duration::from_secs(QUOTA_REFRESH_MAX_STAGGER_SECS));
+        assert_eq!(quota_refresh_stagger(5000, 900), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_rate_limited_account_skipped() {
+        let token = crate::models::TokenData::new(
+            "access".to_string(),
+            "refresh".to_string(),
+            3600,
+            None,
+            None,
+            None,
+            false,
+        );
+        let acc = Account::new(
+            format!("sched-test-{}", uuid::Uuid::new_v4()),
+            "a@x.com".to_string(),
+            token,
+        );
+        let now = Utc::now().timestamp();
+        assert_eq!(should_skip_quota_refresh(&acc, now, false), None);
+        assert_eq!(should_skip_quota_refresh(&acc, now, true), Some("rate-limit cooldown"));
+    }
+}
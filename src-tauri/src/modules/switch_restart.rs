@@ -0,0 +1,137 @@
+// 账号切换 + Antigravity 自动重启编排
+// 关闭 Antigravity → 切换账号 (写入 Profile / 注入 Token) → 带上原工作区重新启动，作为一个整体执行，
+// 每个阶段通过 `account://switch-progress` 事件推送进度。切换失败时用原账号重新启动，保持切换前的状态。
+// 账号配置了专属启动参数 (launch_args) 时以其启动；若其中指定了独立的 --user-data-dir，则不沿用原工作区。
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::modules::integration::{apply_account_to_local_install, SystemIntegration};
+use crate::modules::{logger, process};
+
+pub const SWITCH_PROGRESS_EVENT: &str = "account://switch-progress";
+
+static SWITCH_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchStage {
+    Closing,
+    Switching,
+    Relaunching,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SwitchProgress {
+    pub account_id: String,
+    pub stage: SwitchStage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+fn report(account_id: &str, stage: SwitchStage, message: Option<String>) {
+    crate::modules::log_bridge::emit_event(
+        SWITCH_PROGRESS_EVENT,
+        SwitchProgress {
+            account_id: account_id.to_string(),
+            stage,
+            message,
+        },
+    );
+}
+
+/// 同一时间只允许一次切换，离开作用域时释放
+struct SwitchGuard;
+
+impl SwitchGuard {
+    fn acquire() -> Option<Self> {
+        SWITCH_IN_PROGRESS
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| SwitchGuard)
+    }
+}
+
+impl Drop for SwitchGuard {
+    fn drop(&mut self) {
+        SWITCH_IN_PROGRESS.store(false, Ordering::Release);
+    }
+}
+
+/// 进程的关闭与启动由编排流程负责，这里只写入本地安装
+struct InjectOnlyIntegration {
+    app_handle: tauri::AppHandle,
+}
+
+impl SystemIntegration for InjectOnlyIntegration {
+    async fn on_account_switch(&self, account: &crate::models::Account) -> Result<(), String> {
+        apply_account_to_local_install(account)
+    }
+
+    fn update_tray(&self) {
+        let _ = crate::modules::tray::update_tray_menus(&self.app_handle);
+    }
+
+    fn show_notification(&self, title: &str, body: &str) {
+        logger::log_info(&format!("[Notification] {}: {}", title, body));
+    }
+}
+
+/// 关闭 Antigravity、切换账号并带原工作区重新启动
+pub async fn switch_account_with_restart(account_id: &str, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let _guard = SwitchGuard::acquire().ok_or_else(|| "account_switch_in_progress".to_string())?;
+
+    let account_launch_args = crate::modules::account::load_account(account_id)?
+        .launch_args
+        .unwrap_or_default();
+    let isolated_profile = process::user_data_dir_from_args(&account_launch_args).is_some();
+
+    let was_running = process::is_antigravity_running();
+    let workspace = if was_running {
+        process::get_workspace_args_from_running_process()
+    } else {
+        Vec::new()
+    };
+    logger::log_info(&format!(
+        "[Switch] Switching to {} with restart (running: {}, workspace: {:?}, launch args: {:?})",
+        account_id, was_running, workspace, account_launch_args
+    ));
+
+    // 1. 关闭 Antigravity
+    report(account_id, SwitchStage::Closing, None);
+    if was_running {
+        if let Err(e) = process::close_antigravity(20) {
+            report(account_id, SwitchStage::Failed, Some(e.clone()));
+            return Err(e);
+        }
+    }
+
+    // 2. 切换账号
+    report(account_id, SwitchStage::Switching, None);
+    let integration = InjectOnlyIntegration { app_handle };
+    if let Err(e) = crate::modules::account::switch_account(account_id, &integration).await {
+        // 回滚: 本地数据未被改写 (或已备份)，用原账号重新打开，恢复切换前的状态
+        if was_running {
+            if let Err(restart_err) = process::start_antigravity_with_args(&workspace) {
+                logger::log_warn(&format!("[Switch] Failed to restore Antigravity after error: {}", restart_err));
+            }
+        }
+        report(account_id, SwitchStage::Failed, Some(e.clone()));
+        return Err(e);
+    }
+
+    // 3. 重新启动并恢复工作区 (独立 Profile 有自己的工作区记录，不沿用原工作区)
+    report(account_id, SwitchStage::Relaunching, None);
+    let mut relaunch_args = account_launch_args;
+    if !isolated_profile {
+        relaunch_args.extend(workspace);
+    }
+    if let Err(e) = process::start_antigravity_with_args(&relaunch_args) {
+        report(account_id, SwitchStage::Failed, Some(e.clone()));
+        return Err(e);
+    }
+
+    report(account_id, SwitchStage::Completed, None);
+    Ok(())
+}
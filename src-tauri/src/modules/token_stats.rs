@@ -1,6 +1,7 @@
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::collections::HashMap;
 
 /// Aggregated token statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +56,27 @@ pub struct AccountTrendPoint {
     pub account_data: std::collections::HashMap<String, u64>,
 }
 
+/// Per-account, per-model daily usage counters (includes thinking tokens)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountModelUsage {
+    pub account_email: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub thoughts_tokens: u64,
+    pub total_tokens: u64,
+    pub request_count: u64,
+}
+
+/// Per-account usage in the current day / month window (used by usage limits)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AccountUsageWindow {
+    pub daily_tokens: u64,
+    pub daily_requests: u64,
+    pub monthly_tokens: u64,
+    pub monthly_requests: u64,
+}
+
 pub(crate) fn get_db_path() -> Result<PathBuf, String> {
     let data_dir = crate::modules::account::get_data_dir()?;
     Ok(data_dir.join("token_stats.db"))
@@ -122,6 +144,53 @@ pub fn init_db() -> Result<(), String> {
     )
     .map_err(|e| e.to_string())?;
 
+    create_daily_usage_table(&conn)?;
+
+    Ok(())
+}
+
+/// Per-account, per-model daily counters (foundation for quota budgeting)
+fn create_daily_usage_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS token_usage_daily (
+            day_bucket TEXT NOT NULL,
+            account_email TEXT NOT NULL,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            thoughts_tokens INTEGER NOT NULL DEFAULT 0,
+            total_tokens INTEGER NOT NULL DEFAULT 0,
+            request_count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (day_bucket, account_email, model)
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn record_daily_usage(
+    conn: &Connection,
+    day_bucket: &str,
+    account_email: &str,
+    model: &str,
+    input_tokens: u32,
+    output_tokens: u32,
+    thoughts_tokens: u32,
+) -> Result<(), String> {
+    let total_tokens = input_tokens + output_tokens;
+    conn.execute(
+        "INSERT INTO token_usage_daily (day_bucket, account_email, model, input_tokens, output_tokens, thoughts_tokens, total_tokens, request_count)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1)
+         ON CONFLICT(day_bucket, account_email, model) DO UPDATE SET
+            input_tokens = input_tokens + ?4,
+            output_tokens = output_tokens + ?5,
+            thoughts_tokens = thoughts_tokens + ?6,
+            total_tokens = total_tokens + ?7,
+            request_count = request_count + 1",
+        params![day_bucket, account_email, model, input_tokens, output_tokens, thoughts_tokens, total_tokens],
+    )
+    .map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -131,11 +200,23 @@ pub fn record_usage(
     model: &str,
     input_tokens: u32,
     output_tokens: u32,
+    thoughts_tokens: u32,
 ) -> Result<(), String> {
     let conn = connect_db()?;
     let timestamp = chrono::Local::now().timestamp();
     let total_tokens = input_tokens + output_tokens;
 
+    let day_bucket = chrono::Local::now().format("%Y-%m-%d").to_string();
+    record_daily_usage(
+        &conn,
+        &day_bucket,
+        account_email,
+        model,
+        input_tokens,
+        output_tokens,
+        thoughts_tokens,
+    )?;
+
     // Insert into raw usage table
     conn.execute(
         "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens)
@@ -553,6 +634,129 @@ pub fn get_account_trend_daily(days: i64) -> Result<Vec<AccountTrendPoint>, Stri
         .collect())
 }
 
+fn query_usage_totals(
+    conn: &Connection,
+    since_day: &str,
+    account_email: Option<&str>,
+) -> Result<Vec<AccountModelUsage>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_email, model,
+                SUM(input_tokens), SUM(output_tokens), SUM(thoughts_tokens),
+                SUM(total_tokens), SUM(request_count)
+         FROM token_usage_daily
+         WHERE day_bucket >= ?1 AND (?2 IS NULL OR account_email = ?2)
+         GROUP BY account_email, model
+         ORDER BY SUM(total_tokens) DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![since_day, account_email], |row| {
+            Ok(AccountModelUsage {
+                account_email: row.get(0)?,
+                model: row.get(1)?,
+                input_tokens: row.get(2)?,
+                output_tokens: row.get(3)?,
+                thoughts_tokens: row.get(4)?,
+                total_tokens: row.get(5)?,
+                request_count: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(result)
+}
+
+/// Per-account, per-model usage totals for the last `days` days (days = 1 → today only)
+pub fn get_usage_totals(
+    days: i64,
+    account_email: Option<&str>,
+) -> Result<Vec<AccountModelUsage>, String> {
+    let conn = connect_db()?;
+    let since = chrono::Local::now() - chrono::Duration::days(days.max(1) - 1);
+    query_usage_totals(&conn, &since.format("%Y-%m-%d").to_string(), account_email)
+}
+
+fn delete_daily_usage(
+    conn: &Connection,
+    since_day: Option<&str>,
+    account_email: Option<&str>,
+) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM token_usage_daily
+         WHERE (?1 IS NULL OR day_bucket >= ?1) AND (?2 IS NULL OR account_email = ?2)",
+        params![since_day, account_email],
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Reset daily usage counters for the last `days` days (None → all history),
+/// optionally limited to one account. Raw request history is kept.
+pub fn reset_usage(days: Option<i64>, account_email: Option<&str>) -> Result<usize, String> {
+    let conn = connect_db()?;
+    let since = days.map(|d| {
+        (chrono::Local::now() - chrono::Duration::days(d.max(1) - 1))
+            .format("%Y-%m-%d")
+            .to_string()
+    });
+    delete_daily_usage(&conn, since.as_deref(), account_email)
+}
+
+fn query_usage_windows(
+    conn: &Connection,
+    today: &str,
+    month_start: &str,
+) -> Result<HashMap<String, AccountUsageWindow>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_email,
+                SUM(CASE WHEN day_bucket >= ?1 THEN total_tokens ELSE 0 END),
+                SUM(CASE WHEN day_bucket >= ?1 THEN request_count ELSE 0 END),
+                SUM(total_tokens), SUM(request_count)
+         FROM token_usage_daily
+         WHERE day_bucket >= ?2
+         GROUP BY account_email",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![today, month_start], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                AccountUsageWindow {
+                    daily_tokens: row.get(1)?,
+                    daily_requests: row.get(2)?,
+                    monthly_tokens: row.get(3)?,
+                    monthly_requests: row.get(4)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = HashMap::new();
+    for row in rows {
+        let (email, window) = row.map_err(|e| e.to_string())?;
+        result.insert(email, window);
+    }
+    Ok(result)
+}
+
+/// Current day / month usage per account (local time windows)
+pub fn get_usage_windows() -> Result<HashMap<String, AccountUsageWindow>, String> {
+    let conn = connect_db()?;
+    let now = chrono::Local::now();
+    query_usage_windows(
+        &conn,
+        &now.format("%Y-%m-%d").to_string(),
+        &now.format("%Y-%m-01").to_string(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -563,4 +767,31 @@ mod tests {
         // For now, just verify the module compiles
         assert!(true);
     }
+
+    #[test]
+    fn test_daily_usage_counters() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_daily_usage_table(&conn).unwrap();
+
+        record_daily_usage(&conn, "2026-01-01", "a@x.com", "gemini-2.5-pro", 100, 50, 20).unwrap();
+        record_daily_usage(&conn, "2026-01-02", "a@x.com", "gemini-2.5-pro", 10, 5, 0).unwrap();
+        record_daily_usage(&conn, "2026-01-02", "b@x.com", "claude-sonnet-4-5", 1, 1, 0).unwrap();
+
+        let all = query_usage_totals(&conn, "2026-01-01", None).unwrap();
+        assert_eq!(all.len(), 2);
+        let a = all.iter().find(|u| u.account_email == "a@x.com").unwrap();
+        assert_eq!((a.input_tokens, a.output_tokens, a.thoughts_tokens), (110, 55, 20));
+        assert_eq!((a.total_tokens, a.request_count), (165, 2));
+
+        let today_a = query_usage_totals(&conn, "2026-01-02", Some("a@x.com")).unwrap();
+        assert_eq!(today_a[0].request_count, 1);
+
+        let windows = query_usage_windows(&conn, "2026-01-02", "2026-01-01").unwrap();
+        let a = &windows["a@x.com"];
+        assert_eq!((a.daily_tokens, a.daily_requests), (15, 1));
+        assert_eq!((a.monthly_tokens, a.monthly_requests), (165, 2));
+
+        assert_eq!(delete_daily_usage(&conn, Some("2026-01-02"), Some("a@x.com")).unwrap(), 1);
+        assert_eq!(delete_daily_usage(&conn, None, None).unwrap(), 2);
+    }
 }
@@ -1,10 +1,129 @@
 use tauri::{
     image::Image,
-    menu::{Menu, MenuItem, PredefinedMenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
     Manager, Emitter, Listener,
 };
 use crate::modules;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 托盘 Claude 映射预设：目标模型取自账号额度中可用的 Gemini 文本模型，
+/// "默认" 表示恢复切换前用户自己的 `claude-*` 映射
+const CLAUDE_WILDCARD: &str = "claude-*";
+const PRESET_ID_PREFIX: &str = "mapping_preset:";
+const PRESET_DEFAULT: &str = "default";
+
+/// 托盘额度汇总展示的模型组 (标准保护组 ID, 显示名)
+const QUOTA_GROUPS: &[(&str, &str)] = &[
+    ("gemini-3-pro-high", "Gemini Pro"),
+    ("gemini-3-flash", "Gemini Flash"),
+    ("gemini-3-pro-image", "Gemini Image"),
+    ("claude", "Claude"),
+];
+
+/// 单个模型组在全部账号上的额度汇总
+#[derive(Debug, Clone, PartialEq)]
+struct QuotaGroupSummary {
+    label: &'static str,
+    /// 各账号剩余百分比的平均值
+    average: i32,
+    /// 已耗尽 (0%) 的账号数
+    exhausted: usize,
+    accounts: usize,
+}
+
+/// 按模型组聚合剩余额度，每个账号取组内剩余最多的模型
+fn summarize_quota<'a>(quotas: impl IntoIterator<Item = &'a crate::models::QuotaData>) -> Vec<QuotaGroupSummary> {
+    let mut per_group: HashMap<&str, Vec<i32>> = HashMap::new();
+    for quota in quotas {
+        if quota.is_forbidden {
+            continue;
+        }
+        let mut best: HashMap<&str, i32> = HashMap::new();
+        for m in &quota.models {
+            let Some(group) = crate::proxy::common::model_mapping::normalize_to_standard_id(&m.name) else {
+                continue;
+            };
+            if let Some((id, _)) = QUOTA_GROUPS.iter().find(|(id, _)| *id == group) {
+                let entry = best.entry(*id).or_insert(0);
+                *entry = (*entry).max(m.percentage);
+            }
+        }
+        for (group, pct) in best {
+            per_group.entry(group).or_default().push(pct);
+        }
+    }
+
+    QUOTA_GROUPS
+        .iter()
+        .filter_map(|(id, label)| {
+            let values = per_group.get(id)?;
+            Some(QuotaGroupSummary {
+                label: *label,
+                average: values.iter().sum::<i32>() / values.len() as i32,
+                exhausted: values.iter().filter(|p| **p <= 0).count(),
+                accounts: values.len(),
+            })
+        })
+        .collect()
+}
+
+fn format_quota_summary(summary: &QuotaGroupSummary) -> String {
+    if summary.exhausted > 0 {
+        format!(
+            "{}: {}% ({}/{} 0%)",
+            summary.label, summary.average, summary.exhausted, summary.accounts
+        )
+    } else {
+        format!("{}: {}%", summary.label, summary.average)
+    }
+}
+
+/// 可选的预设目标：各账号额度中出现过的 Gemini 文本模型，按名称排序去重
+fn claude_preset_targets<'a>(quotas: impl IntoIterator<Item = &'a crate::models::QuotaData>) -> Vec<String> {
+    let mut targets: Vec<String> = quotas
+        .into_iter()
+        .filter(|q| !q.is_forbidden)
+        .flat_map(|q| q.models.iter())
+        .map(|m| m.name.to_lowercase())
+        .filter(|name| name.starts_with("gemini-") && !name.contains("image"))
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// 当前生效的托盘预设 (None 表示使用用户自己的映射)
+fn current_claude_preset(proxy: &crate::proxy::ProxyConfig) -> Option<&str> {
+    proxy.tray_mapping_backup.as_ref()?;
+    proxy.custom_mapping.get(CLAUDE_WILDCARD).map(|s| s.as_str())
+}
+
+/// 将 Claude 通配映射切换为指定目标模型；首次切换时备份用户原有映射，None 时恢复该备份
+fn apply_claude_preset(proxy: &mut crate::proxy::ProxyConfig, target: Option<&str>) {
+    match target {
+        Some(model) => {
+            if proxy.tray_mapping_backup.is_none() {
+                proxy.tray_mapping_backup =
+                    Some(proxy.custom_mapping.get(CLAUDE_WILDCARD).cloned().unwrap_or_default());
+            }
+            proxy
+                .custom_mapping
+                .insert(CLAUDE_WILDCARD.to_string(), model.to_string());
+        }
+        None => match proxy.tray_mapping_backup.take() {
+            Some(previous) if previous.is_empty() => {
+                proxy.custom_mapping.remove(CLAUDE_WILDCARD);
+            }
+            Some(previous) => {
+                proxy.custom_mapping.insert(CLAUDE_WILDCARD.to_string(), previous);
+            }
+            // 未处于托盘预设，保留用户映射不动
+            None => {}
+        },
+    }
+}
 
 pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
     // 1. Load config to get language settings
@@ -136,6 +255,31 @@ pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
                          }
                     });
                 }
+                "quota_details" => {
+                    // 打开主窗口并跳转到账号列表查看额度详情
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                        #[cfg(target_os = "macos")]
+                        app.set_activation_policy(tauri::ActivationPolicy::Regular).unwrap_or(());
+                    }
+                    let _ = app.emit("tray://navigate", "/accounts");
+                }
+                "proxy_toggle" => {
+                    tauri::async_runtime::spawn(async move {
+                        toggle_proxy(&app_handle).await;
+                        update_tray_menus(&app_handle);
+                    });
+                }
+                id if id.starts_with(PRESET_ID_PREFIX) => {
+                    let preset = id.trim_start_matches(PRESET_ID_PREFIX).to_string();
+                    tauri::async_runtime::spawn(async move {
+                        let target = (preset != PRESET_DEFAULT).then_some(preset.as_str());
+                        if let Err(e) = switch_claude_preset(&app_handle, target).await {
+                            modules::logger::log_error(&format!("Tray mapping switch failed: {}", e));
+                        }
+                    });
+                }
                 _ => {}
             }
         })
@@ -169,6 +313,77 @@ pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
         update_tray_menus(&handle);
     });
 
+    // 额度调度器 / 手动全量刷新完成后更新额度汇总
+    let handle = app.clone();
+    app.listen("accounts://refreshed", move |_event| {
+        update_tray_menus(&handle);
+    });
+
+    // 前端或托盘启停反代后更新代理状态
+    let handle = app.clone();
+    app.listen(crate::commands::proxy::PROXY_STATUS_EVENT, move |_event| {
+        update_tray_menus(&handle);
+    });
+
+    Ok(())
+}
+
+/// 托盘启停反代服务 (使用已保存的配置启动)
+async fn toggle_proxy(app: &tauri::AppHandle) {
+    let state = app.state::<crate::commands::proxy::ProxyServiceState>();
+    let running = state.instance.read().await.is_some();
+
+    let result = if running {
+        crate::commands::proxy::internal_stop_proxy_service(&state)
+            .await
+            .map(|_| crate::commands::proxy::stopped_proxy_status())
+    } else {
+        match modules::load_app_config() {
+            Ok(config) => {
+                let cf_state = app.state::<crate::commands::cloudflared::CloudflaredState>();
+                crate::commands::proxy::internal_start_proxy_service(
+                    config.proxy,
+                    &state,
+                    crate::modules::integration::SystemManager::Desktop(app.clone()),
+                    Arc::new(cf_state.inner().clone()),
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        }
+    };
+
+    match result {
+        Ok(status) => {
+            // 与命令启停一致地广播状态，保持已打开的界面同步
+            crate::commands::proxy::emit_proxy_status(app, &status);
+            modules::logger::log_info(&format!(
+                "Proxy service {} from tray",
+                if running { "stopped" } else { "started" }
+            ));
+        }
+        Err(e) => modules::logger::log_error(&format!("Tray proxy toggle failed: {}", e)),
+    }
+}
+
+/// 切换 Claude 映射预设：保存配置、热更新运行中的服务并通知前端
+async fn switch_claude_preset(app: &tauri::AppHandle, target: Option<&str>) -> Result<(), String> {
+    let mut config = modules::load_app_config()?;
+    apply_claude_preset(&mut config.proxy, target);
+    modules::save_app_config(&config)?;
+
+    let state = app.state::<crate::commands::proxy::ProxyServiceState>();
+    if let Some(instance) = state.instance.read().await.as_ref() {
+        instance.axum_server.apply_config(&config.proxy).await;
+    }
+
+    modules::logger::log_info(&format!(
+        "Claude mapping preset switched from tray: {}",
+        target.unwrap_or(PRESET_DEFAULT)
+    ));
+    // config://updated 会触发托盘重建，tray://mapping-changed 让前端重新加载配置
+    let _ = app.emit("config://updated", ());
+    let _ = app.emit("tray://mapping-changed", ());
     Ok(())
 }
 
@@ -222,6 +437,127 @@ pub fn update_tray_menus(app: &tauri::AppHandle) {
              menu_lines.push(texts.unknown_quota.clone());
          };
 
+         // Aggregated quota across enabled accounts
+         let accounts = modules::list_accounts().unwrap_or_default();
+         let quota_summary = summarize_quota(
+             accounts
+                 .iter()
+                 .filter(|a| !a.disabled)
+                 .filter_map(|a| a.quota.as_ref()),
+         );
+         let summary_lines: Vec<String> = quota_summary.iter().map(format_quota_summary).collect();
+         let mut summary_items = Vec::new();
+         if summary_lines.is_empty() {
+             if let Ok(item) = MenuItem::with_id(&app_clone, "quota_summary_empty", &texts.unknown_quota, false, None::<&str>) {
+                 summary_items.push(item);
+             }
+         }
+         for (i, line) in summary_lines.iter().enumerate() {
+             if let Ok(item) = MenuItem::with_id(&app_clone, format!("quota_summary_{}", i), line, false, None::<&str>) {
+                 summary_items.push(item);
+             }
+         }
+         let summary_sep = PredefinedMenuItem::separator(&app_clone).ok();
+         let quota_details = MenuItem::with_id(&app_clone, "quota_details", &texts.quota_details, true, None::<&str>).ok();
+         let mut summary_refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = summary_items
+             .iter()
+             .map(|i| i as &dyn tauri::menu::IsMenuItem<tauri::Wry>)
+             .collect();
+         if let Some(ref s) = summary_sep { summary_refs.push(s); }
+         if let Some(ref d) = quota_details { summary_refs.push(d); }
+         let summary_menu = Submenu::with_id_and_items(
+             &app_clone,
+             "quota_summary",
+             format!("{} ({})", texts.quota_summary, accounts.iter().filter(|a| !a.disabled).count()),
+             true,
+             &summary_refs,
+         )
+         .ok();
+
+         // Proxy status
+         let (proxy_running, proxy_port, healthy, total) = {
+             let state = app_clone.state::<crate::commands::proxy::ProxyServiceState>();
+             let lock = state.instance.read().await;
+             match lock.as_ref() {
+                 Some(instance) => (
+                     true,
+                     instance.config.port,
+                     instance.token_manager.healthy_count().await,
+                     instance.token_manager.len(),
+                 ),
+                 None => (false, config.proxy.port, 0, 0),
+             }
+         };
+         let proxy_text = if proxy_running {
+             format!("{}: {}", texts.proxy_running, proxy_port)
+         } else {
+             texts.proxy_stopped.clone()
+         };
+         let toggle_text = if proxy_running { &texts.stop_proxy } else { &texts.start_proxy };
+         let proxy_status = MenuItem::with_id(&app_clone, "proxy_status", &proxy_text, false, None::<&str>);
+         let proxy_healthy = if proxy_running {
+             MenuItem::with_id(
+                 &app_clone,
+                 "proxy_healthy",
+                 format!("{}: {}/{}", texts.healthy_accounts, healthy, total),
+                 false,
+                 None::<&str>,
+             )
+             .ok()
+         } else {
+             None
+         };
+         let proxy_toggle = MenuItem::with_id(&app_clone, "proxy_toggle", toggle_text, true, None::<&str>);
+
+         // Claude mapping presets
+         let active_preset = current_claude_preset(&config.proxy);
+         let mut preset_targets = claude_preset_targets(
+             accounts
+                 .iter()
+                 .filter(|a| !a.disabled)
+                 .filter_map(|a| a.quota.as_ref()),
+         );
+         if let Some(active) = active_preset {
+             if !preset_targets.iter().any(|t| t == active) {
+                 preset_targets.push(active.to_string());
+             }
+         }
+         let mut preset_items = Vec::new();
+         if let Ok(item) = CheckMenuItem::with_id(
+             &app_clone,
+             format!("{}{}", PRESET_ID_PREFIX, PRESET_DEFAULT),
+             &texts.mapping_default,
+             true,
+             active_preset.is_none(),
+             None::<&str>,
+         ) {
+             preset_items.push(item);
+         }
+         for model in &preset_targets {
+             if let Ok(item) = CheckMenuItem::with_id(
+                 &app_clone,
+                 format!("{}{}", PRESET_ID_PREFIX, model),
+                 model,
+                 true,
+                 active_preset == Some(model.as_str()),
+                 None::<&str>,
+             ) {
+                 preset_items.push(item);
+             }
+         }
+         let preset_refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = preset_items
+             .iter()
+             .map(|i| i as &dyn tauri::menu::IsMenuItem<tauri::Wry>)
+             .collect();
+         let mapping_menu = Submenu::with_id_and_items(
+             &app_clone,
+             "mapping_presets",
+             &texts.claude_mapping,
+             true,
+             &preset_refs,
+         )
+         .ok();
+
          // Rebuild menu items
          let info_user = MenuItem::with_id(&app_clone, "info_user", &user_text, false, None::<&str>);
          
@@ -244,16 +580,26 @@ pub fn update_tray_menus(app: &tauri::AppHandle) {
              let sep1 = PredefinedMenuItem::separator(&app_clone).ok();
              let sep2 = PredefinedMenuItem::separator(&app_clone).ok();
              let sep3 = PredefinedMenuItem::separator(&app_clone).ok();
+             let sep_proxy = PredefinedMenuItem::separator(&app_clone).ok();
              
              let mut items: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = vec![&i_u];
              // Add dynamic quota items
              for item in &quota_items {
                  items.push(item);
              }
+             if let Some(ref m) = summary_menu { items.push(m); }
              
              if let Some(ref s) = sep1 { items.push(s); }
              items.push(&s_n);
              items.push(&r_c);
+
+             // Proxy section
+             if let Some(ref s) = sep_proxy { items.push(s); }
+             if let Ok(ref p) = proxy_status { items.push(p); }
+             if let Some(ref h) = proxy_healthy { items.push(h); }
+             if let Ok(ref t) = proxy_toggle { items.push(t); }
+             if let Some(ref m) = mapping_menu { items.push(m); }
+
              if let Some(ref s) = sep2 { items.push(s); }
              items.push(&s);
              if let Some(ref s) = sep3 { items.push(s); }
@@ -262,8 +608,103 @@ pub fn update_tray_menus(app: &tauri::AppHandle) {
              if let Ok(menu) = Menu::with_items(&app_clone, &items) {
                  if let Some(tray) = app_clone.tray_by_id("main") {
                      let _ = tray.set_menu(Some(menu));
+                     // 悬停提示显示额度汇总，无需展开菜单
+                     let mut tooltip = vec!["Antigravity Tools".to_string()];
+                     tooltip.extend(summary_lines.iter().cloned());
+                     let _ = tray.set_tooltip(Some(tooltip.join("\n")));
                  }
              }
          }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claude_preset_roundtrip() {
+        let mut proxy = crate::proxy::ProxyConfig::default();
+        proxy
+            .custom_mapping
+            .insert("gpt-4".to_string(), "gemini-3-flash".to_string());
+        assert_eq!(current_claude_preset(&proxy), None);
+
+        apply_claude_preset(&mut proxy, Some("gemini-3-flash"));
+        apply_claude_preset(&mut proxy, Some("gemini-2.5-flash"));
+        assert_eq!(current_claude_preset(&proxy), Some("gemini-2.5-flash"));
+
+        apply_claude_preset(&mut proxy, None);
+        assert_eq!(current_claude_preset(&proxy), None);
+        assert_eq!(proxy.tray_mapping_backup, None);
+        // 原本没有 claude-* 映射，恢复后也没有；其他自定义映射不受影响
+        assert_eq!(proxy.custom_mapping.len(), 1);
+    }
+
+    #[test]
+    fn test_claude_preset_restores_user_mapping() {
+        let mut proxy = crate::proxy::ProxyConfig::default();
+        proxy
+            .custom_mapping
+            .insert(CLAUDE_WILDCARD.to_string(), "gemini-3.1-pro-low".to_string());
+        // 用户自己的映射不视为托盘预设
+        assert_eq!(current_claude_preset(&proxy), None);
+
+        apply_claude_preset(&mut proxy, Some("gemini-3-flash"));
+        assert_eq!(current_claude_preset(&proxy), Some("gemini-3-flash"));
+
+        apply_claude_preset(&mut proxy, None);
+        assert_eq!(
+            proxy.custom_mapping.get(CLAUDE_WILDCARD).map(String::as_str),
+            Some("gemini-3.1-pro-low")
+        );
+
+        // 未处于预设时选择"默认"不会删除用户映射
+        apply_claude_preset(&mut proxy, None);
+        assert!(proxy.custom_mapping.contains_key(CLAUDE_WILDCARD));
+    }
+
+    #[test]
+    fn test_claude_preset_targets_from_quota() {
+        let a = quota(&[("gemini-3-flash", 80), ("gemini-3-pro-image", 100), ("claude-sonnet-4-6", 50)]);
+        let b = quota(&[("gemini-2.5-flash", 40), ("gemini-3-flash", 10)]);
+        assert_eq!(
+            claude_preset_targets([&a, &b]),
+            vec!["gemini-2.5-flash".to_string(), "gemini-3-flash".to_string()]
+        );
+    }
+
+    fn quota(models: &[(&str, i32)]) -> crate::models::QuotaData {
+        let mut q = crate::models::QuotaData::new();
+        for (name, pct) in models {
+            q.add_model(
+                serde_json::from_value(serde_json::json!({
+                    "name": name,
+                    "percentage": pct,
+                    "reset_time": ""
+                }))
+                .unwrap(),
+            );
+        }
+        q
+    }
+
+    #[test]
+    fn test_summarize_quota_by_group() {
+        let a = quota(&[("gemini-3-flash", 80), ("gemini-2.5-flash", 100), ("claude-sonnet-4-6", 0)]);
+        let b = quota(&[("gemini-3-flash", 40), ("claude-opus-4-6-thinking", 50)]);
+        let mut forbidden = quota(&[("gemini-3-flash", 0)]);
+        forbidden.is_forbidden = true;
+
+        let summary = summarize_quota([&a, &b, &forbidden]);
+        assert_eq!(summary.len(), 2);
+        // 每个账号取组内最高值：a=100, b=40
+        assert_eq!(summary[0].label, "Gemini Flash");
+        assert_eq!(summary[0].average, 70);
+        assert_eq!(summary[0].exhausted, 0);
+        assert_eq!(summary[1].label, "Claude");
+        assert_eq!(summary[1].average, 25);
+        assert_eq!(summary[1].exhausted, 1);
+        assert_eq!(format_quota_summary(&summary[1]), "Claude: 25% (1/2 0%)");
+    }
+}
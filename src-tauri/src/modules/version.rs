@@ -1,4 +1,5 @@
 use crate::modules::process;
+use serde::Serialize;
 use std::fs;
 use std::path::PathBuf;
 
@@ -41,19 +42,71 @@ pub fn get_antigravity_version() -> Result<AntigravityVersion, String> {
         .ok_or("Unable to locate Antigravity executable")?;
     
     // 2. 根据平台读取版本信息
+    get_antigravity_version_at(&exe_path)
+}
+
+/// 从指定的可执行文件 (macOS 为 .app) 读取版本信息
+pub fn get_antigravity_version_at(exe_path: &PathBuf) -> Result<AntigravityVersion, String> {
     #[cfg(target_os = "macos")]
     {
-        get_version_macos(&exe_path)
+        get_version_macos(exe_path)
     }
     
     #[cfg(target_os = "windows")]
     {
-        get_version_windows(&exe_path)
+        get_version_windows(exe_path)
     }
     
     #[cfg(target_os = "linux")]
     {
-        get_version_linux(&exe_path)
+        get_version_linux(exe_path)
+    }
+}
+
+/// Antigravity 安装版本检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AntigravityVersionReport {
+    pub executable_path: Option<String>,
+    pub installed_version: Option<String>,
+    /// 已知最新版本 (未请求比较时为空)
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    pub error: Option<String>,
+}
+
+/// 读取指定安装的版本，并可选与已知最新版本比较
+pub fn build_version_report(exe_path: Option<PathBuf>, latest_version: Option<String>) -> AntigravityVersionReport {
+    let mut report = AntigravityVersionReport {
+        executable_path: exe_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+        installed_version: None,
+        latest_version,
+        update_available: false,
+        error: None,
+    };
+    let Some(exe_path) = exe_path else {
+        report.error = Some("Unable to locate Antigravity executable".to_string());
+        return report;
+    };
+
+    match get_antigravity_version_at(&exe_path) {
+        Ok(version) => {
+            let installed = extract_semver(&version.short_version).unwrap_or(version.short_version);
+            report.update_available = report
+                .latest_version
+                .as_deref()
+                .is_some_and(|latest| is_update_available(&installed, latest));
+            report.installed_version = Some(installed);
+        }
+        Err(e) => report.error = Some(e),
+    }
+    report
+}
+
+/// 已安装版本是否落后于最新版本 (无法解析时视为无更新)
+pub fn is_update_available(installed: &str, latest: &str) -> bool {
+    match (extract_semver(installed), extract_semver(latest)) {
+        (Some(installed), Some(latest)) => compare_version(&installed, &latest) == std::cmp::Ordering::Less,
+        _ => false,
     }
 }
 
@@ -250,6 +303,23 @@ mod tests {
         assert!(is_new_version(&newer));
     }
 
+    #[test]
+    fn test_is_update_available() {
+        assert!(is_update_available("1.15.8", "1.16.5"));
+        assert!(!is_update_available("1.16.5", "1.16.5"));
+        // Windows FileVersion 带第四段
+        assert!(!is_update_available("1.16.5.0", "1.16.5"));
+        assert!(!is_update_available("unknown", "1.16.5"));
+    }
+
+    #[test]
+    fn test_report_without_installation() {
+        let report = build_version_report(None, Some("1.16.5".to_string()));
+        assert!(report.installed_version.is_none());
+        assert!(!report.update_available);
+        assert!(report.error.is_some());
+    }
+
     #[test]
     fn test_extract_semver_from_messy_output() {
         let raw = "1.107.0\n1504c8cc4b34dbfbb4a97ebe954b3da2b5634516\nx64";
@@ -0,0 +1,180 @@
+// Antigravity 守护模式
+// 后台定期检查 Antigravity 进程，意外退出时自动重新启动 (适合夜间跑长时间 Agent 任务)。
+// 由本工具主动关闭 (切换账号等) 不视为意外退出；短时间内反复崩溃时停止重启，避免陷入崩溃循环。
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, Ordering};
+use tokio::time::{self, Duration};
+
+use crate::models::WatchdogConfig;
+use crate::modules::{config, logger, process};
+
+/// 主动关闭后的宽限期 (秒)，期间的退出不触发重启
+const INTENTIONAL_STOP_GRACE_SECS: i64 = 60;
+
+static INTENTIONAL_STOP_AT: AtomicI64 = AtomicI64::new(0);
+
+/// 记录一次由本工具发起的关闭 (在 close_antigravity 中调用)
+pub fn note_intentional_stop() {
+    INTENTIONAL_STOP_AT.store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+}
+
+fn is_intentional_stop(now: i64) -> bool {
+    now - INTENTIONAL_STOP_AT.load(Ordering::Relaxed) <= INTENTIONAL_STOP_GRACE_SECS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchdogAction {
+    None,
+    Restart { attempt: usize },
+    GiveUp,
+}
+
+#[derive(Debug, Default)]
+pub struct WatchdogState {
+    was_running: bool,
+    gave_up: bool,
+    /// 窗口内的重启时间戳 (Unix 秒)
+    restarts: VecDeque<i64>,
+}
+
+impl WatchdogState {
+    /// 根据本次检测结果决定动作
+    pub fn observe(&mut self, running: bool, intentional: bool, now: i64, config: &WatchdogConfig) -> WatchdogAction {
+        if running {
+            self.was_running = true;
+            self.gave_up = false;
+            return WatchdogAction::None;
+        }
+        if !self.was_running {
+            return WatchdogAction::None;
+        }
+        self.was_running = false;
+        if intentional || self.gave_up {
+            return WatchdogAction::None;
+        }
+
+        let window = config.window_minutes as i64 * 60;
+        while self.restarts.front().is_some_and(|&t| now - t >= window) {
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= config.max_restarts as usize {
+            self.gave_up = true;
+            return WatchdogAction::GiveUp;
+        }
+        self.restarts.push_back(now);
+        WatchdogAction::Restart {
+            attempt: self.restarts.len(),
+        }
+    }
+
+    /// 关闭守护时清空状态，重新开启后从头计数
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+fn notify(app_handle: &tauri::AppHandle, title: &str, body: &str) {
+    crate::modules::integration::SystemManager::Desktop(app_handle.clone()).show_notification(title, body);
+}
+
+/// 启动守护任务 (仅桌面模式；是否生效由 watchdog.enabled 控制，可热切换)
+pub fn start_watchdog(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        logger::log_info("[Watchdog] Started");
+        let mut state = WatchdogState::default();
+
+        loop {
+            let config = config::load_app_config().map(|c| c.watchdog).unwrap_or_default();
+            time::sleep(Duration::from_secs(config.check_interval_secs.max(5))).await;
+
+            if !config.enabled {
+                state.reset();
+                continue;
+            }
+
+            let running = tokio::task::spawn_blocking(process::is_antigravity_running)
+                .await
+                .unwrap_or(true);
+            let now = chrono::Utc::now().timestamp();
+
+            match state.observe(running, is_intentional_stop(now), now, &config) {
+                WatchdogAction::None => {}
+                WatchdogAction::Restart { attempt } => {
+                    logger::log_warn(&format!(
+                        "[Watchdog] Antigravity exited unexpectedly, relaunching ({}/{})",
+                        attempt, config.max_restarts
+                    ));
+                    match tokio::task::spawn_blocking(process::start_antigravity).await {
+                        Ok(Ok(())) => notify(
+                            &app_handle,
+                            "Antigravity relaunched",
+                            &format!(
+                                "Antigravity exited unexpectedly and was relaunched ({}/{} in {} min)",
+                                attempt, config.max_restarts, config.window_minutes
+                            ),
+                        ),
+                        Ok(Err(e)) => {
+                            logger::log_error(&format!("[Watchdog] Relaunch failed: {}", e));
+                            notify(&app_handle, "Antigravity relaunch failed", &e);
+                        }
+                        Err(e) => logger::log_error(&format!("[Watchdog] Relaunch task failed: {}", e)),
+                    }
+                }
+                WatchdogAction::GiveUp => {
+                    let message = format!(
+                        "Antigravity exited {} times within {} min; automatic relaunch paused until it is started again",
+                        config.max_restarts, config.window_minutes
+                    );
+                    logger::log_error(&format!("[Watchdog] {}", message));
+                    notify(&app_handle, "Antigravity watchdog stopped", &message);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig {
+            enabled: true,
+            check_interval_secs: 15,
+            max_restarts: 2,
+            window_minutes: 10,
+        }
+    }
+
+    #[test]
+    fn test_restarts_until_crash_loop_limit() {
+        let config = config();
+        let mut state = WatchdogState::default();
+
+        // 从未运行过: 不启动
+        assert_eq!(state.observe(false, false, 0, &config), WatchdogAction::None);
+
+        assert_eq!(state.observe(true, false, 10, &config), WatchdogAction::None);
+        assert_eq!(state.observe(false, false, 20, &config), WatchdogAction::Restart { attempt: 1 });
+        // 重启尚未完成时不重复触发
+        assert_eq!(state.observe(false, false, 35, &config), WatchdogAction::None);
+
+        assert_eq!(state.observe(true, false, 50, &config), WatchdogAction::None);
+        assert_eq!(state.observe(false, false, 60, &config), WatchdogAction::Restart { attempt: 2 });
+        assert_eq!(state.observe(true, false, 70, &config), WatchdogAction::None);
+        assert_eq!(state.observe(false, false, 80, &config), WatchdogAction::GiveUp);
+
+        // 窗口过后重新计数
+        assert_eq!(state.observe(true, false, 700, &config), WatchdogAction::None);
+        assert_eq!(state.observe(false, false, 710, &config), WatchdogAction::Restart { attempt: 1 });
+    }
+
+    #[test]
+    fn test_intentional_stop_is_ignored() {
+        let config = config();
+        let mut state = WatchdogState::default();
+        state.observe(true, false, 0, &config);
+        assert_eq!(state.observe(false, true, 10, &config), WatchdogAction::None);
+        assert_eq!(state.observe(false, false, 30, &config), WatchdogAction::None);
+    }
+}
@@ -0,0 +1,150 @@
+// 反代实时活动事件流
+// 通过 Tauri 事件 `proxy://activity` 推送结构化事件 (请求开始 / 结束、账号轮换、冷却、流中断)，
+// 前端无需轮询即可渲染实时活动面板。另保留一个小的环形缓冲，供界面打开时补齐最近的事件。
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+pub const ACTIVITY_EVENT: &str = "proxy://activity";
+const MAX_RECENT_EVENTS: usize = 200;
+
+static EVENT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProxyActivityEvent {
+    RequestStarted {
+        request_id: String,
+        method: String,
+        path: String,
+        model: Option<String>,
+    },
+    RequestFinished {
+        request_id: String,
+        method: String,
+        path: String,
+        status: u16,
+        duration_ms: u64,
+        model: Option<String>,
+        account_email: Option<String>,
+    },
+    AccountRotated {
+        from_account_id: Option<String>,
+        to_account_id: String,
+        to_email: String,
+        /// "scheduled" (调度切换) 或 "failover" (失败后切换)
+        reason: String,
+    },
+    CooldownApplied {
+        account_id: String,
+        model: Option<String>,
+        reason: String,
+        seconds: u64,
+    },
+    StreamAborted {
+        request_id: String,
+        path: String,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyActivity {
+    pub id: u64,
+    /// 毫秒时间戳
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub event: ProxyActivityEvent,
+}
+
+fn recent() -> &'static Mutex<VecDeque<ProxyActivity>> {
+    static RECENT: OnceLock<Mutex<VecDeque<ProxyActivity>>> = OnceLock::new();
+    RECENT.get_or_init(|| Mutex::new(VecDeque::with_capacity(MAX_RECENT_EVENTS)))
+}
+
+/// 记录并推送一条活动事件 (无 AppHandle 时仅写入缓冲，例如 headless 模式)
+pub fn emit(event: ProxyActivityEvent) {
+    let activity = ProxyActivity {
+        id: EVENT_ID.fetch_add(1, Ordering::Relaxed),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        event,
+    };
+    if let Ok(mut buffer) = recent().lock() {
+        if buffer.len() >= MAX_RECENT_EVENTS {
+            buffer.pop_front();
+        }
+        buffer.push_back(activity.clone());
+    }
+    crate::modules::log_bridge::emit_event(ACTIVITY_EVENT, activity);
+}
+
+/// 记录本次选中的账号，与上一次不同时推送 AccountRotated
+pub fn note_account_served(account_id: &str, email: &str, reason: &str) {
+    static LAST_SERVED: Mutex<Option<String>> = Mutex::new(None);
+    let previous = {
+        let Ok(mut last) = LAST_SERVED.lock() else {
+            return;
+        };
+        if last.as_deref() == Some(account_id) {
+            return;
+        }
+        last.replace(account_id.to_string())
+    };
+    emit(ProxyActivityEvent::AccountRotated {
+        from_account_id: previous,
+        to_account_id: account_id.to_string(),
+        to_email: email.to_string(),
+        reason: reason.to_string(),
+    });
+}
+
+/// 最近的活动事件 (旧 -> 新)
+pub fn recent_events(limit: usize) -> Vec<ProxyActivity> {
+    let Ok(buffer) = recent().lock() else {
+        return Vec::new();
+    };
+    let skip = buffer.len().saturating_sub(limit);
+    buffer.iter().skip(skip).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_serialization_is_flat_and_tagged() {
+        let activity = ProxyActivity {
+            id: 1,
+            timestamp: 0,
+            event: ProxyActivityEvent::CooldownApplied {
+                account_id: "acc1".to_string(),
+                model: None,
+                reason: "QuotaExhausted".to_string(),
+                seconds: 60,
+            },
+        };
+        let json = serde_json::to_value(&activity).unwrap();
+        assert_eq!(json["kind"], "cooldown_applied");
+        assert_eq!(json["account_id"], "acc1");
+        assert_eq!(json["seconds"], 60);
+    }
+
+    #[test]
+    fn test_recent_events_keeps_latest() {
+        for i in 0..(MAX_RECENT_EVENTS + 5) {
+            emit(ProxyActivityEvent::StreamAborted {
+                request_id: format!("req-{}", i),
+                path: "/v1/messages".to_string(),
+                reason: "client_disconnected".to_string(),
+            });
+        }
+        let events = recent_events(MAX_RECENT_EVENTS * 2);
+        assert!(events.len() <= MAX_RECENT_EVENTS);
+        let last = format!("req-{}", MAX_RECENT_EVENTS + 4);
+        assert!(events.iter().any(|e| matches!(
+            &e.event,
+            ProxyActivityEvent::StreamAborted { request_id, .. } if *request_id == last
+        )));
+    }
+}
@@ -1,8 +1,58 @@
 use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
 use std::path::Path;
 
 pub struct AudioProcessor;
 
+/// 默认语音合成模型
+pub const DEFAULT_TTS_MODEL: &str = "gemini-2.5-flash-preview-tts";
+/// tts-1-hd 等高质量请求使用的模型
+pub const HD_TTS_MODEL: &str = "gemini-2.5-pro-preview-tts";
+/// Gemini TTS 未声明采样率时的默认值 (24kHz 单声道 16bit PCM)
+const DEFAULT_PCM_SAMPLE_RATE: u32 = 24000;
+
+/// OpenAI /v1/audio/speech 请求体
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpeechRequest {
+    pub model: String,
+    pub input: String,
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// mp3 / opus / aac / flac / wav / pcm，Gemini 只返回 PCM，仅 wav 与 pcm 可原样满足
+    #[serde(default)]
+    pub response_format: Option<String>,
+    /// 语气 / 风格提示 (gpt-4o-mini-tts)
+    #[serde(default)]
+    pub instructions: Option<String>,
+    /// Gemini 不支持语速参数，忽略
+    #[serde(default)]
+    pub speed: Option<f32>,
+}
+
+/// 语音合成输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechFormat {
+    Wav,
+    Pcm,
+}
+
+impl SpeechFormat {
+    /// 无法转码的格式 (mp3 / opus / aac / flac) 回退为 wav
+    pub fn from_request(format: Option<&str>) -> Self {
+        match format.map(|f| f.to_lowercase()).as_deref() {
+            Some("pcm") => SpeechFormat::Pcm,
+            _ => SpeechFormat::Wav,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SpeechFormat::Wav => "audio/wav",
+            SpeechFormat::Pcm => "audio/pcm",
+        }
+    }
+}
+
 impl AudioProcessor {
     /// 检测音频 MIME 类型
     pub fn detect_mime_type(filename: &str) -> Result<String, String> {
@@ -32,6 +82,221 @@ impl AudioProcessor {
         const MAX_SIZE: usize = 15 * 1024 * 1024; // 15MB
         size_bytes > MAX_SIZE
     }
+
+    /// OpenAI input_audio.format -> MIME 类型
+    pub fn mime_type_for_format(format: &str) -> Result<String, String> {
+        match format.to_lowercase().as_str() {
+            "pcm16" | "pcm" => Ok("audio/pcm".to_string()),
+            "aac" => Ok("audio/aac".to_string()),
+            other => Self::detect_mime_type(&format!("audio.{}", other)),
+        }
+    }
+
+    /// MIME 类型 -> OpenAI 音频格式名
+    pub fn format_for_mime_type(mime_type: &str) -> &str {
+        let subtype = mime_type.split(';').next().unwrap_or("").trim();
+        match subtype {
+            "audio/mpeg" | "audio/mp3" => "mp3",
+            "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+            "audio/L16" | "audio/pcm" => "pcm16",
+            other => other.strip_prefix("audio/").unwrap_or("wav"),
+        }
+    }
+
+    pub fn is_audio_mime_type(mime_type: &str) -> bool {
+        mime_type.starts_with("audio/")
+    }
+
+    /// 将 base64 音频转换为 Gemini inlineData part (含大小校验)
+    pub fn inline_audio_part(mime_type: &str, base64_data: &str) -> Result<serde_json::Value, String> {
+        if base64_data.is_empty() {
+            return Err("Audio data is empty".to_string());
+        }
+        let size = base64_data.trim_end_matches('=').len() * 3 / 4;
+        if Self::exceeds_size_limit(size) {
+            return Err(format!("Audio is too large ({} bytes, limit 15MB)", size));
+        }
+        Ok(serde_json::json!({
+            "inlineData": { "mimeType": mime_type, "data": base64_data }
+        }))
+    }
+
+    /// OpenAI 音频内容块 (input_audio / data URI 形式的 audio_url) -> Gemini part
+    /// 返回 Ok(None) 表示无法内联的远程 audio_url
+    pub fn openai_audio_part(
+        block: &crate::proxy::mappers::openai::OpenAIContentBlock,
+    ) -> Result<Option<serde_json::Value>, String> {
+        use crate::proxy::mappers::openai::OpenAIContentBlock;
+        match block {
+            OpenAIContentBlock::InputAudio { input_audio } => {
+                let mime_type = Self::mime_type_for_format(&input_audio.format)?;
+                Self::inline_audio_part(&mime_type, &input_audio.data).map(Some)
+            }
+            OpenAIContentBlock::AudioUrl { audio_url } => {
+                let Some((meta, data)) = audio_url
+                    .url
+                    .strip_prefix("data:")
+                    .and_then(|rest| rest.split_once(','))
+                else {
+                    return Ok(None);
+                };
+                let mime_type = meta.split(';').next().unwrap_or("audio/wav");
+                if !Self::is_audio_mime_type(mime_type) {
+                    return Err(format!("Unsupported audio type: {}", mime_type));
+                }
+                Self::inline_audio_part(mime_type, data).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// OpenAI 语音名 -> Gemini 预置语音；已是 Gemini 语音名时原样使用
+    pub fn map_voice(voice: Option<&str>) -> String {
+        let voice = voice.unwrap_or("alloy").trim();
+        let mapped = match voice.to_lowercase().as_str() {
+            "alloy" => "Kore",
+            "echo" => "Puck",
+            "fable" => "Aoede",
+            "onyx" => "Charon",
+            "nova" => "Leda",
+            "shimmer" => "Zephyr",
+            "ash" => "Fenrir",
+            "coral" => "Callirrhoe",
+            "sage" => "Orus",
+            "ballad" => "Enceladus",
+            "verse" => "Iapetus",
+            _ => "",
+        };
+        if !mapped.is_empty() {
+            return mapped.to_string();
+        }
+        // Gemini 语音名首字母大写，例如 "kore" -> "Kore"
+        let mut chars = voice.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => "Kore".to_string(),
+        }
+    }
+
+    /// 选择语音合成模型：Gemini 模型原样使用，其余经自定义映射后仍非 TTS 模型时使用默认模型
+    pub fn resolve_tts_model(
+        requested: &str,
+        custom_mapping: &std::collections::HashMap<String, String>,
+    ) -> String {
+        if requested.starts_with("gemini") && requested.contains("tts") {
+            return requested.to_string();
+        }
+        let mapped = crate::proxy::common::model_mapping::resolve_model_route(requested, custom_mapping);
+        if mapped.contains("tts") {
+            mapped
+        } else if requested.ends_with("-hd") {
+            HD_TTS_MODEL.to_string()
+        } else {
+            DEFAULT_TTS_MODEL.to_string()
+        }
+    }
+
+    /// 构建 Gemini 语音合成请求
+    pub fn build_speech_request(req: &SpeechRequest) -> serde_json::Value {
+        let text = match req.instructions.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            Some(instructions) => format!("{}: {}", instructions.trim_end_matches(['.', ':']), req.input),
+            None => req.input.clone(),
+        };
+        serde_json::json!({
+            "contents": [{ "role": "user", "parts": [{ "text": text }] }],
+            "generationConfig": {
+                "responseModalities": ["AUDIO"],
+                "speechConfig": {
+                    "voiceConfig": {
+                        "prebuiltVoiceConfig": { "voiceName": Self::map_voice(req.voice.as_deref()) }
+                    }
+                }
+            }
+        })
+    }
+
+    /// 从 "audio/L16;codec=pcm;rate=24000" 中解析采样率
+    pub fn pcm_sample_rate(mime_type: &str) -> u32 {
+        mime_type
+            .split(';')
+            .filter_map(|p| p.trim().strip_prefix("rate="))
+            .find_map(|r| r.parse().ok())
+            .unwrap_or(DEFAULT_PCM_SAMPLE_RATE)
+    }
+
+    /// 为 16bit 单声道 PCM 加上 WAV 头
+    pub fn pcm_to_wav(pcm: &[u8], sample_rate: u32) -> Vec<u8> {
+        const CHANNELS: u16 = 1;
+        const BITS_PER_SAMPLE: u16 = 16;
+        let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+        let byte_rate = sample_rate * block_align as u32;
+        let data_len = pcm.len() as u32;
+
+        let mut wav = Vec::with_capacity(44 + pcm.len());
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&CHANNELS.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&byte_rate.to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend_from_slice(pcm);
+        wav
+    }
+
+    /// 提取 Gemini 响应中的音频，返回 (原始音频字节, mime_type)
+    pub fn extract_audio(response: &serde_json::Value) -> Option<(Vec<u8>, String)> {
+        let parts = response
+            .get("candidates")?
+            .get(0)?
+            .get("content")?
+            .get("parts")?
+            .as_array()?;
+        let mut audio = Vec::new();
+        let mut mime_type = None;
+        for inline in parts.iter().filter_map(|p| p.get("inlineData")) {
+            let mime = inline.get("mimeType").and_then(|v| v.as_str()).unwrap_or("");
+            if !Self::is_audio_mime_type(mime) {
+                continue;
+            }
+            let data = inline.get("data").and_then(|v| v.as_str()).unwrap_or("");
+            if let Ok(bytes) = general_purpose::STANDARD.decode(data) {
+                audio.extend_from_slice(&bytes);
+                mime_type.get_or_insert_with(|| mime.to_string());
+            }
+        }
+        mime_type.filter(|_| !audio.is_empty()).map(|m| (audio, m))
+    }
+
+    /// 按请求格式输出音频，返回 (字节, Content-Type)
+    pub fn encode_speech_output(audio: Vec<u8>, mime_type: &str, format: SpeechFormat) -> (Vec<u8>, &'static str) {
+        let is_wav = mime_type.contains("wav");
+        let bytes = match (format, is_wav) {
+            (SpeechFormat::Wav, false) => Self::pcm_to_wav(&audio, Self::pcm_sample_rate(mime_type)),
+            // WAV 转 PCM: 去掉 44 字节的标准头
+            (SpeechFormat::Pcm, true) if audio.len() > 44 => audio[44..].to_vec(),
+            _ => audio,
+        };
+        (bytes, format.content_type())
+    }
+
+    /// 校验 OpenAI 请求中的音频输入 (格式与大小)
+    pub fn validate_openai_request(request: &crate::proxy::mappers::openai::OpenAIRequest) -> Result<(), String> {
+        use crate::proxy::mappers::openai::OpenAIContent;
+        for msg in &request.messages {
+            if let Some(OpenAIContent::Array(blocks)) = &msg.content {
+                for block in blocks {
+                    Self::openai_audio_part(block)?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -59,6 +324,55 @@ mod tests {
         assert!(!AudioProcessor::exceeds_size_limit(15 * 1024 * 1024)); // 刚好等于限制
     }
 
+    #[test]
+    fn test_openai_input_audio_part() {
+        use crate::proxy::mappers::openai::{InputAudioContent, OpenAIContentBlock};
+
+        let block = OpenAIContentBlock::InputAudio {
+            input_audio: InputAudioContent {
+                data: "UklGRiQAAABXQVZF".to_string(),
+                format: "wav".to_string(),
+            },
+        };
+        let part = AudioProcessor::openai_audio_part(&block).unwrap().unwrap();
+        assert_eq!(part["inlineData"]["mimeType"], "audio/wav");
+
+        let unsupported = OpenAIContentBlock::InputAudio {
+            input_audio: InputAudioContent {
+                data: "AAAA".to_string(),
+                format: "midi".to_string(),
+            },
+        };
+        assert!(AudioProcessor::openai_audio_part(&unsupported).is_err());
+        assert_eq!(AudioProcessor::format_for_mime_type("audio/mpeg"), "mp3");
+    }
+
+    #[test]
+    fn test_speech_output_wraps_pcm_in_wav() {
+        assert_eq!(AudioProcessor::pcm_sample_rate("audio/L16;codec=pcm;rate=16000"), 16000);
+        assert_eq!(AudioProcessor::pcm_sample_rate("audio/L16"), 24000);
+
+        let pcm = vec![0u8, 1, 2, 3];
+        let (wav, content_type) =
+            AudioProcessor::encode_speech_output(pcm.clone(), "audio/L16;codec=pcm;rate=24000", SpeechFormat::Wav);
+        assert_eq!(content_type, "audio/wav");
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 24000);
+        assert_eq!(&wav[44..], &pcm[..]);
+
+        // mp3 等无法转码的格式回退为 wav
+        assert_eq!(SpeechFormat::from_request(Some("mp3")), SpeechFormat::Wav);
+        assert_eq!(SpeechFormat::from_request(Some("pcm")), SpeechFormat::Pcm);
+    }
+
+    #[test]
+    fn test_map_voice() {
+        assert_eq!(AudioProcessor::map_voice(Some("alloy")), "Kore");
+        assert_eq!(AudioProcessor::map_voice(Some("puck")), "Puck");
+        assert_eq!(AudioProcessor::map_voice(None), "Kore");
+    }
+
     #[test]
     fn test_base64_encoding() {
         let data = b"test audio data";
@@ -0,0 +1,379 @@
+// 反代压测 (负载测试)
+// 通过本地反代端点并发发送小请求，完整经过账号轮询 / 重试 / 限流等逻辑，
+// 统计延迟分位数、错误分类与按账号的吞吐，便于调整重试与并发相关配置。
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::proxy::config::ProxyConfig;
+
+const MAX_TOTAL_REQUESTS: u32 = 1000;
+const MAX_CONCURRENCY: u32 = 64;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchmarkProtocol {
+    #[default]
+    OpenAI,
+    Anthropic,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkRequest {
+    #[serde(default = "default_total_requests", alias = "totalRequests")]
+    pub total_requests: u32,
+    #[serde(default = "default_concurrency")]
+    pub concurrency: u32,
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[serde(default)]
+    pub protocol: BenchmarkProtocol,
+    #[serde(default = "default_prompt")]
+    pub prompt: String,
+    #[serde(default = "default_max_tokens", alias = "maxTokens")]
+    pub max_tokens: u32,
+    /// 单个请求超时 (秒)
+    #[serde(default = "default_timeout_secs", alias = "timeoutSecs")]
+    pub timeout_secs: u64,
+}
+
+fn default_total_requests() -> u32 {
+    20
+}
+
+fn default_concurrency() -> u32 {
+    4
+}
+
+fn default_model() -> String {
+    "gemini-2.5-flash".to_string()
+}
+
+fn default_prompt() -> String {
+    "Reply with OK.".to_string()
+}
+
+fn default_max_tokens() -> u32 {
+    16
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+impl Default for BenchmarkRequest {
+    fn default() -> Self {
+        Self {
+            total_requests: default_total_requests(),
+            concurrency: default_concurrency(),
+            model: default_model(),
+            protocol: BenchmarkProtocol::default(),
+            prompt: default_prompt(),
+            max_tokens: default_max_tokens(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+impl BenchmarkRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.total_requests == 0 || self.total_requests > MAX_TOTAL_REQUESTS {
+            return Err(format!("total_requests must be between 1 and {}", MAX_TOTAL_REQUESTS));
+        }
+        if self.concurrency == 0 || self.concurrency > MAX_CONCURRENCY {
+            return Err(format!("concurrency must be between 1 and {}", MAX_CONCURRENCY));
+        }
+        if self.model.trim().is_empty() {
+            return Err("model must not be empty".to_string());
+        }
+        if self.timeout_secs == 0 {
+            return Err("timeout_secs must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 单个请求的结果
+#[derive(Debug, Clone)]
+struct Sample {
+    latency_ms: u64,
+    account: Option<String>,
+    /// None 表示成功，否则为错误分类
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct LatencyStats {
+    pub min_ms: u64,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AccountThroughput {
+    pub account: String,
+    pub requests: u32,
+    pub succeeded: u32,
+    pub failed: u32,
+    pub mean_latency_ms: u64,
+    pub requests_per_second: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub total_requests: u32,
+    pub concurrency: u32,
+    pub model: String,
+    pub succeeded: u32,
+    pub failed: u32,
+    pub duration_ms: u64,
+    pub requests_per_second: f64,
+    /// 成功请求的延迟分布
+    pub latency: LatencyStats,
+    /// 错误分类 ("HTTP 429" / "timeout" / "connection" ...) -> 次数
+    pub errors: BTreeMap<String, u32>,
+    pub per_account: Vec<AccountThroughput>,
+}
+
+/// nearest-rank 分位数 (samples 需已排序)
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn latency_stats(mut latencies: Vec<u64>) -> LatencyStats {
+    if latencies.is_empty() {
+        return LatencyStats::default();
+    }
+    latencies.sort_unstable();
+    LatencyStats {
+        min_ms: latencies[0],
+        mean_ms: latencies.iter().sum::<u64>() / latencies.len() as u64,
+        p50_ms: percentile(&latencies, 50.0),
+        p90_ms: percentile(&latencies, 90.0),
+        p95_ms: percentile(&latencies, 95.0),
+        p99_ms: percentile(&latencies, 99.0),
+        max_ms: latencies[latencies.len() - 1],
+    }
+}
+
+fn per_second(count: u32, duration: Duration) -> f64 {
+    let secs = duration.as_secs_f64();
+    if secs <= 0.0 {
+        return 0.0;
+    }
+    (count as f64 / secs * 100.0).round() / 100.0
+}
+
+fn build_report(req: &BenchmarkRequest, samples: &[Sample], duration: Duration) -> BenchmarkReport {
+    let succeeded = samples.iter().filter(|s| s.error.is_none()).count() as u32;
+    let mut errors: BTreeMap<String, u32> = BTreeMap::new();
+    for error in samples.iter().filter_map(|s| s.error.as_ref()) {
+        *errors.entry(error.clone()).or_default() += 1;
+    }
+
+    let mut by_account: HashMap<String, Vec<&Sample>> = HashMap::new();
+    for sample in samples {
+        let account = sample.account.clone().unwrap_or_else(|| "unknown".to_string());
+        by_account.entry(account).or_default().push(sample);
+    }
+    let mut per_account: Vec<AccountThroughput> = by_account
+        .into_iter()
+        .map(|(account, items)| {
+            let ok = items.iter().filter(|s| s.error.is_none()).count() as u32;
+            AccountThroughput {
+                account,
+                requests: items.len() as u32,
+                succeeded: ok,
+                failed: items.len() as u32 - ok,
+                mean_latency_ms: items.iter().map(|s| s.latency_ms).sum::<u64>() / items.len() as u64,
+                requests_per_second: per_second(items.len() as u32, duration),
+            }
+        })
+        .collect();
+    per_account.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.account.cmp(&b.account)));
+
+    BenchmarkReport {
+        total_requests: samples.len() as u32,
+        concurrency: req.concurrency,
+        model: req.model.clone(),
+        succeeded,
+        failed: samples.len() as u32 - succeeded,
+        duration_ms: duration.as_millis() as u64,
+        requests_per_second: per_second(samples.len() as u32, duration),
+        latency: latency_stats(
+            samples
+                .iter()
+                .filter(|s| s.error.is_none())
+                .map(|s| s.latency_ms)
+                .collect(),
+        ),
+        errors,
+        per_account,
+    }
+}
+
+async fn send_one(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+    req: &BenchmarkRequest,
+) -> Sample {
+    let messages = json!([{ "role": "user", "content": req.prompt }]);
+    let builder = match req.protocol {
+        BenchmarkProtocol::OpenAI => client
+            .post(format!("{}/v1/chat/completions", base_url))
+            .bearer_auth(api_key)
+            .json(&json!({
+                "model": req.model,
+                "messages": messages,
+                "max_tokens": req.max_tokens,
+                "stream": false,
+            })),
+        BenchmarkProtocol::Anthropic => client
+            .post(format!("{}/v1/messages", base_url))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&json!({
+                "model": req.model,
+                "messages": messages,
+                "max_tokens": req.max_tokens,
+            })),
+    };
+
+    let started = Instant::now();
+    let result = builder.send().await;
+    let (account, error) = match result {
+        Ok(resp) => {
+            let account = resp
+                .headers()
+                .get("x-account-email")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+            let status = resp.status();
+            // 读完响应体，延迟包含完整生成时间
+            let body = resp.bytes().await;
+            let error = match body {
+                Ok(_) if status.is_success() => None,
+                Ok(_) => Some(format!("HTTP {}", status.as_u16())),
+                Err(e) if e.is_timeout() => Some("timeout".to_string()),
+                Err(_) => Some("body_read".to_string()),
+            };
+            (account, error)
+        }
+        Err(e) if e.is_timeout() => (None, Some("timeout".to_string())),
+        Err(e) if e.is_connect() => (None, Some("connection".to_string())),
+        Err(_) => (None, Some("request".to_string())),
+    };
+    Sample {
+        latency_ms: started.elapsed().as_millis() as u64,
+        account,
+        error,
+    }
+}
+
+/// 对本地反代执行压测
+pub async fn run_benchmark(proxy: &ProxyConfig, req: &BenchmarkRequest) -> Result<BenchmarkReport, String> {
+    req.validate()?;
+    // 本地 TLS 通常为自签名证书；压测只访问本机，不走上游代理
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_secs(req.timeout_secs))
+        .build()
+        .map_err(|e| format!("Failed to build benchmark client: {}", e))?;
+    let base_url = format!("{}://127.0.0.1:{}", proxy.get_scheme(), proxy.port);
+
+    tracing::info!(
+        "[Benchmark] Sending {} {:?} requests to {} (model {}, concurrency {})",
+        req.total_requests,
+        req.protocol,
+        base_url,
+        req.model,
+        req.concurrency
+    );
+    let started = Instant::now();
+    let samples: Vec<Sample> = futures::stream::iter(0..req.total_requests)
+        .map(|_| send_one(&client, &base_url, &proxy.api_key, req))
+        .buffer_unordered(req.concurrency as usize)
+        .collect()
+        .await;
+    let report = build_report(req, &samples, started.elapsed());
+    tracing::info!(
+        "[Benchmark] Done: {}/{} succeeded, p50 {}ms, p95 {}ms, {:.2} req/s",
+        report.succeeded,
+        report.total_requests,
+        report.latency.p50_ms,
+        report.latency.p95_ms,
+        report.requests_per_second
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(latency_ms: u64, account: &str, error: Option<&str>) -> Sample {
+        Sample {
+            latency_ms,
+            account: Some(account.to_string()),
+            error: error.map(|e| e.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&sorted, 50.0), 50);
+        assert_eq!(percentile(&sorted, 95.0), 95);
+        assert_eq!(percentile(&sorted, 99.0), 99);
+        assert_eq!(percentile(&[7], 99.0), 7);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+
+    #[test]
+    fn test_build_report_breakdowns() {
+        let req = BenchmarkRequest::default();
+        let samples = vec![
+            sample(100, "a@example.com", None),
+            sample(300, "a@example.com", None),
+            sample(200, "b@example.com", None),
+            sample(50, "b@example.com", Some("HTTP 429")),
+            Sample { latency_ms: 10, account: None, error: Some("connection".to_string()) },
+        ];
+        let report = build_report(&req, &samples, Duration::from_secs(2));
+        assert_eq!(report.succeeded, 3);
+        assert_eq!(report.failed, 2);
+        assert_eq!(report.requests_per_second, 2.5);
+        assert_eq!(report.latency.min_ms, 100);
+        assert_eq!(report.latency.p50_ms, 200);
+        assert_eq!(report.latency.max_ms, 300);
+        assert_eq!(report.errors.get("HTTP 429"), Some(&1));
+        assert_eq!(report.errors.get("connection"), Some(&1));
+
+        assert_eq!(report.per_account[0].account, "a@example.com");
+        assert_eq!(report.per_account[0].mean_latency_ms, 200);
+        let b = report.per_account.iter().find(|a| a.account == "b@example.com").unwrap();
+        assert_eq!((b.succeeded, b.failed), (1, 1));
+        assert!(report.per_account.iter().any(|a| a.account == "unknown"));
+    }
+
+    #[test]
+    fn test_validate_limits() {
+        assert!(BenchmarkRequest::default().validate().is_ok());
+        let no_concurrency = BenchmarkRequest { concurrency: 0, ..Default::default() };
+        assert!(no_concurrency.validate().is_err());
+        let too_large = BenchmarkRequest { total_requests: 5000, ..Default::default() };
+        assert!(too_large.validate().is_err());
+    }
+}
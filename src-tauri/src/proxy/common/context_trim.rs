@@ -0,0 +1,335 @@
+// 上下文窗口管理
+// 按映射后的模型配置 (默认关闭)：估算请求超出上下文窗口时丢弃最早的对话轮次，避免上游直接返回 "request too large"。
+// 轮次以不含 tool_result 的 user 消息为起点，保证 tool_use / tool_result 成对保留；最后一轮始终保留。
+use serde_json::Value;
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::{ContextTrimConfig, ContextTrimMode, ContextTrimRule};
+use crate::proxy::mappers::claude::models::{ClaudeRequest, ContentBlock, MessageContent};
+use crate::proxy::mappers::context_manager::{estimate_tokens_from_str, has_tool_result, ContextManager};
+use crate::proxy::mappers::openai::{OpenAIContent, OpenAIContentBlock, OpenAIMessage, OpenAIRequest};
+
+/// 内联 base64 数据 (图片 / 文档) 的估算值，避免按字符数估算导致大量误裁
+const INLINE_DATA_TOKENS: u32 = 1_000;
+/// 每条消息的固定开销
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+/// 裁剪结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrimOutcome {
+    pub dropped: usize,
+    pub before: u32,
+    pub after: u32,
+}
+
+fn config() -> &'static RwLock<ContextTrimConfig> {
+    static CONFIG: OnceLock<RwLock<ContextTrimConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(ContextTrimConfig::default()))
+}
+
+/// 更新上下文窗口配置 (启动与配置热更新时调用)
+pub fn update_context_trim_config(new_config: ContextTrimConfig) {
+    if let Ok(mut current) = config().write() {
+        *current = new_config;
+    }
+}
+
+/// 校验规则: 模型名不能为空，预留 tokens 必须小于窗口
+pub fn validate_context_trim_config(cfg: &ContextTrimConfig) -> Result<(), String> {
+    for (pattern, rule) in &cfg.per_model {
+        if pattern.trim().is_empty() {
+            return Err("Context trim rule model pattern cannot be empty".to_string());
+        }
+        if let Some(max) = rule.max_context_tokens {
+            if rule.reserve_tokens >= max {
+                return Err(format!(
+                    "Context trim reserve_tokens ({}) must be smaller than max_context_tokens ({}) for '{}'",
+                    rule.reserve_tokens, max, pattern
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn rule_from_config(cfg: &ContextTrimConfig, model: &str) -> Option<ContextTrimRule> {
+    let rule = cfg.per_model.get(model).or_else(|| {
+        cfg.per_model
+            .iter()
+            .filter(|(pattern, _)| {
+                pattern.contains('*') && crate::proxy::common::model_mapping::wildcard_match(pattern, model)
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, rule)| rule)
+    })?;
+    (rule.mode != ContextTrimMode::Off).then(|| rule.clone())
+}
+
+/// 查找 (映射后) 模型的规则：精确匹配优先，其次最长通配符；未配置或为 off 时返回 None
+pub fn rule_for_model(model: &str) -> Option<ContextTrimRule> {
+    config().read().ok().and_then(|cfg| rule_from_config(&cfg, model))
+}
+
+/// 可用于输入的 token 预算
+pub fn input_budget(rule: &ContextTrimRule, model: &str) -> u32 {
+    let window = rule
+        .max_context_tokens
+        .map(u64::from)
+        .unwrap_or_else(|| crate::proxy::model_registry::default_context_window(model));
+    window
+        .saturating_sub(u64::from(rule.reserve_tokens))
+        .min(u64::from(u32::MAX)) as u32
+}
+
+/// 计算需要丢弃的前导消息数：只在轮次起点处截断，最后一轮始终保留
+fn leading_messages_to_drop(costs: &[u32], turn_starts: &[bool], fixed: u32, budget: u32) -> usize {
+    let mut total = u64::from(fixed) + costs.iter().map(|c| u64::from(*c)).sum::<u64>();
+    if total <= u64::from(budget) {
+        return 0;
+    }
+    let last_turn = turn_starts.iter().rposition(|s| *s).unwrap_or(0);
+    for (i, cost) in costs.iter().enumerate().take(last_turn) {
+        total -= u64::from(*cost);
+        if turn_starts[i + 1] && total <= u64::from(budget) {
+            return i + 1;
+        }
+    }
+    last_turn
+}
+
+fn trim_notice(dropped: usize) -> String {
+    format!(
+        "[Note: {} earlier messages were removed from this conversation to fit the model's context window.]",
+        dropped
+    )
+}
+
+/// Claude 请求超出预算时丢弃最早的轮次，并在保留的首条消息前标注
+pub fn trim_claude_request(request: &mut ClaudeRequest, budget: u32) -> Option<TrimOutcome> {
+    let before = ContextManager::estimate_token_usage(request);
+    if before <= budget {
+        return None;
+    }
+    let costs: Vec<u32> = request
+        .messages
+        .iter()
+        .map(ContextManager::estimate_message_tokens)
+        .collect();
+    let fixed = before.saturating_sub(costs.iter().sum());
+    let turn_starts: Vec<bool> = request
+        .messages
+        .iter()
+        .map(|m| m.role == "user" && !has_tool_result(&m.content))
+        .collect();
+
+    let dropped = leading_messages_to_drop(&costs, &turn_starts, fixed, budget);
+    if dropped == 0 {
+        return None;
+    }
+    request.messages.drain(..dropped);
+    let notice = trim_notice(dropped);
+    match &mut request.messages[0].content {
+        MessageContent::String(text) => *text = format!("{}\n\n{}", notice, text),
+        MessageContent::Array(blocks) => blocks.insert(0, ContentBlock::Text { text: notice }),
+    }
+
+    Some(TrimOutcome {
+        dropped,
+        before,
+        after: ContextManager::estimate_token_usage(request),
+    })
+}
+
+fn value_tokens(value: &Value) -> u32 {
+    match value {
+        Value::String(s) if s.starts_with("data:") => INLINE_DATA_TOKENS,
+        Value::String(s) => estimate_tokens_from_str(s),
+        Value::Array(items) => items.iter().map(value_tokens).sum(),
+        Value::Object(map) => map.values().map(value_tokens).sum(),
+        _ => 1,
+    }
+}
+
+fn openai_message_tokens(message: &OpenAIMessage) -> u32 {
+    MESSAGE_OVERHEAD_TOKENS + serde_json::to_value(message).map(|v| value_tokens(&v)).unwrap_or(0)
+}
+
+fn is_openai_system(message: &OpenAIMessage) -> bool {
+    message.role == "system" || message.role == "developer"
+}
+
+/// OpenAI 请求超出预算时丢弃最早的轮次 (system / developer 消息始终保留)
+pub fn trim_openai_request(request: &mut OpenAIRequest, budget: u32) -> Option<TrimOutcome> {
+    let tools_tokens: u32 = request.tools.iter().flatten().map(value_tokens).sum();
+    let message_tokens: Vec<u32> = request.messages.iter().map(openai_message_tokens).collect();
+    let before = tools_tokens + message_tokens.iter().sum::<u32>();
+    if before <= budget {
+        return None;
+    }
+
+    // system 消息计入固定开销，不参与裁剪
+    let mut fixed = tools_tokens;
+    let mut costs = Vec::with_capacity(message_tokens.len());
+    for (message, tokens) in request.messages.iter().zip(&message_tokens) {
+        if is_openai_system(message) {
+            fixed += tokens;
+            costs.push(0);
+        } else {
+            costs.push(*tokens);
+        }
+    }
+    let turn_starts: Vec<bool> = request.messages.iter().map(|m| m.role == "user").collect();
+
+    let cut = leading_messages_to_drop(&costs, &turn_starts, fixed, budget);
+    if cut == 0 {
+        return None;
+    }
+    let kept_tail = request.messages.split_off(cut);
+    let dropped = request.messages.len() - request.messages.iter().filter(|m| is_openai_system(m)).count();
+    request.messages.retain(is_openai_system);
+    let first_kept = request.messages.len();
+    request.messages.extend(kept_tail);
+
+    let notice = trim_notice(dropped);
+    let first = &mut request.messages[first_kept];
+    first.content = Some(match first.content.take() {
+        Some(OpenAIContent::String(text)) => OpenAIContent::String(format!("{}\n\n{}", notice, text)),
+        Some(OpenAIContent::Array(mut blocks)) => {
+            blocks.insert(0, OpenAIContentBlock::Text { text: notice });
+            OpenAIContent::Array(blocks)
+        }
+        None => OpenAIContent::String(notice),
+    });
+
+    let after = tools_tokens + request.messages.iter().map(openai_message_tokens).sum::<u32>();
+    Some(TrimOutcome { dropped, before, after })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::mappers::claude::models::Message;
+    use std::collections::HashMap;
+
+    fn claude_message(role: &str, content: MessageContent) -> Message {
+        Message { role: role.to_string(), content }
+    }
+
+    fn long_text(words: usize) -> String {
+        "lorem ipsum ".repeat(words)
+    }
+
+    #[test]
+    fn test_leading_messages_to_drop() {
+        // 三轮: [0,1] [2,3,4] [5]
+        let costs = [100, 100, 100, 100, 100, 100];
+        let starts = [true, false, true, false, false, true];
+        assert_eq!(leading_messages_to_drop(&costs, &starts, 0, 600), 0);
+        assert_eq!(leading_messages_to_drop(&costs, &starts, 0, 450), 2);
+        assert_eq!(leading_messages_to_drop(&costs, &starts, 0, 150), 5);
+        // 只有一轮时不裁剪
+        assert_eq!(leading_messages_to_drop(&[500, 500], &[true, false], 0, 100), 0);
+    }
+
+    #[test]
+    fn test_trim_claude_request_keeps_tool_pairs() {
+        let mut request: ClaudeRequest = serde_json::from_value(serde_json::json!({
+            "model": "gemini-3-flash",
+            "messages": [
+                { "role": "user", "content": long_text(400) },
+                { "role": "assistant", "content": [{ "type": "tool_use", "id": "t1", "name": "read", "input": {} }] },
+                { "role": "user", "content": [{ "type": "tool_result", "tool_use_id": "t1", "content": long_text(400) }] },
+                { "role": "assistant", "content": "done" },
+                { "role": "user", "content": "next question" }
+            ]
+        }))
+        .unwrap();
+
+        let outcome = trim_claude_request(&mut request, 200).unwrap();
+        assert_eq!(outcome.dropped, 4);
+        assert!(outcome.after < outcome.before);
+        assert_eq!(request.messages.len(), 1);
+        match &request.messages[0].content {
+            MessageContent::String(text) => {
+                assert!(text.starts_with("[Note: 4 earlier messages"));
+                assert!(text.ends_with("next question"));
+            }
+            other => panic!("unexpected content: {:?}", other),
+        }
+
+        let mut small = request.clone();
+        assert!(trim_claude_request(&mut small, 100_000).is_none());
+
+        // tool_result 消息不是轮次起点，不会与 tool_use 拆开
+        let mut request = ClaudeRequest {
+            messages: vec![
+                claude_message("user", MessageContent::String(long_text(400))),
+                claude_message("assistant", MessageContent::String("ok".into())),
+                claude_message(
+                    "user",
+                    MessageContent::Array(vec![ContentBlock::ToolResult {
+                        tool_use_id: "t1".into(),
+                        content: Value::String("r".into()),
+                        is_error: None,
+                    }]),
+                ),
+            ],
+            ..request
+        };
+        assert!(trim_claude_request(&mut request, 10).is_none());
+    }
+
+    #[test]
+    fn test_trim_openai_request_keeps_system_messages() {
+        let mut request: OpenAIRequest = serde_json::from_value(serde_json::json!({
+            "model": "gemini-3-flash",
+            "messages": [
+                { "role": "system", "content": "be brief" },
+                { "role": "user", "content": long_text(400) },
+                { "role": "assistant", "content": long_text(400) },
+                { "role": "user", "content": [{ "type": "text", "text": "latest" }] }
+            ]
+        }))
+        .unwrap();
+
+        let outcome = trim_openai_request(&mut request, 300).unwrap();
+        assert_eq!(outcome.dropped, 2);
+        let roles: Vec<&str> = request.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user"]);
+        match &request.messages[1].content {
+            Some(OpenAIContent::Array(blocks)) => {
+                assert!(matches!(&blocks[0], OpenAIContentBlock::Text { text } if text.starts_with("[Note: 2")));
+            }
+            other => panic!("unexpected content: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rule_lookup_and_budget() {
+        let rule = |mode, max| ContextTrimRule {
+            mode,
+            max_context_tokens: max,
+            reserve_tokens: 1_000,
+        };
+        let cfg = ContextTrimConfig {
+            per_model: HashMap::from([
+                ("gemini-*".to_string(), rule(ContextTrimMode::Trim, Some(10_000))),
+                ("gemini-3-pro-*".to_string(), rule(ContextTrimMode::Off, None)),
+                ("claude-sonnet-4-6".to_string(), rule(ContextTrimMode::Summarize, None)),
+            ]),
+        };
+
+        let flash = rule_from_config(&cfg, "gemini-3-flash").unwrap();
+        assert_eq!(input_budget(&flash, "gemini-3-flash"), 9_000);
+        assert!(rule_from_config(&cfg, "gemini-3-pro-high").is_none());
+        let sonnet = rule_from_config(&cfg, "claude-sonnet-4-6").unwrap();
+        assert_eq!(input_budget(&sonnet, "claude-sonnet-4-6"), 199_000);
+        assert!(rule_from_config(&cfg, "gpt-oss-120b").is_none());
+
+        assert!(validate_context_trim_config(&cfg).is_ok());
+        let bad = ContextTrimConfig {
+            per_model: HashMap::from([("x".to_string(), rule(ContextTrimMode::Trim, Some(500)))]),
+        };
+        assert!(validate_context_trim_config(&bad).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+// 文档 (PDF 等) 输入支持
+// Claude document 块与 OpenAI file / input_file 块统一转为 Gemini inlineData (v1internal 支持 application/pdf)。
+// 转换前由 handler 调用 validate_* 校验大小与类型，超限直接返回 400，而不是在转换阶段报错。
+use base64::Engine as _;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::proxy::common::image_fetch::{fetch_remote, is_remote_url};
+use crate::proxy::mappers::claude::models::{ClaudeRequest, ContentBlock, DocumentSource, MessageContent};
+use crate::proxy::mappers::openai::{OpenAIContent, OpenAIContentBlock, OpenAIFileContent, OpenAIRequest};
+
+/// 单个文档大小上限 (解码后)
+pub const MAX_DOCUMENT_BYTES: usize = 20 * 1024 * 1024;
+
+fn is_supported_mime(mime: &str) -> bool {
+    mime == "application/pdf" || mime.starts_with("text/")
+}
+
+fn mime_from_filename(filename: &str) -> Option<&'static str> {
+    let ext = filename.rsplit('.').next()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "pdf" => Some("application/pdf"),
+        "txt" => Some("text/plain"),
+        "md" | "markdown" => Some("text/markdown"),
+        "csv" => Some("text/csv"),
+        "html" | "htm" => Some("text/html"),
+        _ => None,
+    }
+}
+
+/// base64 解码后的字节数 (不实际解码)
+fn decoded_len(data: &str) -> usize {
+    let trimmed = data.trim_end_matches('=');
+    trimmed.len() * 3 / 4
+}
+
+/// 解析 data URI，返回 (mime_type, base64 数据)
+fn parse_data_uri(uri: &str) -> Option<(String, &str)> {
+    let rest = uri.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let mime = meta.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    Some((mime, data))
+}
+
+fn inline_document(mime_type: &str, data: &str) -> Result<Value, String> {
+    if !is_supported_mime(mime_type) {
+        return Err(format!("Unsupported document type: {}", mime_type));
+    }
+    if data.is_empty() {
+        return Err("Document data is empty".to_string());
+    }
+    let size = decoded_len(data);
+    if size > MAX_DOCUMENT_BYTES {
+        return Err(format!(
+            "Document is too large ({} bytes, limit {} bytes)",
+            size, MAX_DOCUMENT_BYTES
+        ));
+    }
+    Ok(json!({ "inlineData": { "mimeType": mime_type, "data": data } }))
+}
+
+/// Claude document 块 -> Gemini parts
+pub fn claude_document_parts(
+    source: &DocumentSource,
+    title: Option<&str>,
+    context: Option<&str>,
+) -> Result<Vec<Value>, String> {
+    let mut parts = Vec::new();
+    if let Some(title) = title.filter(|t| !t.is_empty()) {
+        parts.push(json!({ "text": format!("[Document: {}]", title) }));
+    }
+
+    match source.source_type.as_str() {
+        "base64" => {
+            let mime_type = if source.media_type.is_empty() {
+                "application/pdf".to_string()
+            } else {
+                source.media_type.to_ascii_lowercase()
+            };
+            parts.push(inline_document(&mime_type, &source.data)?);
+        }
+        "text" => parts.push(json!({ "text": source.data })),
+        "content" => {
+            let blocks = source.content.as_ref().and_then(|c| c.as_array()).cloned().unwrap_or_default();
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                            parts.push(json!({ "text": text }));
+                        }
+                    }
+                    Some("image") => {
+                        let source = block.get("source");
+                        let media_type = source.and_then(|s| s.get("media_type")).and_then(|v| v.as_str());
+                        let data = source.and_then(|s| s.get("data")).and_then(|v| v.as_str());
+                        if let (Some(media_type), Some(data)) = (media_type, data) {
+                            parts.push(json!({ "inlineData": { "mimeType": media_type, "data": data } }));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        "url" => {
+            // 下载失败的远程文档 (正常情况下 handler 已内联为 base64)
+            return Err(format!(
+                "Document url could not be fetched: {}",
+                source.url.as_deref().unwrap_or_default()
+            ));
+        }
+        other => return Err(format!("Unsupported document source type: {}", other)),
+    }
+
+    if let Some(context) = context.filter(|c| !c.is_empty()) {
+        parts.push(json!({ "text": context }));
+    }
+    Ok(parts)
+}
+
+/// 统一 OpenAI file / input_file 两种块的字段
+pub fn openai_file_content(block: &OpenAIContentBlock) -> Option<OpenAIFileContent> {
+    match block {
+        OpenAIContentBlock::File { file } => Some(file.clone()),
+        OpenAIContentBlock::InputFile {
+            file_data,
+            filename,
+            file_id,
+            file_url,
+        } => Some(OpenAIFileContent {
+            file_data: file_data.clone(),
+            filename: filename.clone(),
+            file_id: file_id.clone(),
+            file_url: file_url.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// OpenAI 文件块 -> Gemini part
+pub fn openai_file_part(file: &OpenAIFileContent) -> Result<Value, String> {
+    let Some(file_data) = file.file_data.as_deref() else {
+        if let Some(id) = &file.file_id {
+            return Err(format!("file_id references are not supported ({}), send file_data instead", id));
+        }
+        return Err("File block is missing file_data".to_string());
+    };
+
+    let filename_mime = file.filename.as_deref().and_then(mime_from_filename);
+    let (mime_type, data) = match parse_data_uri(file_data) {
+        Some((mime, data)) if !mime.is_empty() && mime != "application/octet-stream" => (mime, data),
+        Some((_, data)) => (filename_mime.unwrap_or("application/pdf").to_string(), data),
+        None => (filename_mime.unwrap_or("application/pdf").to_string(), file_data),
+    };
+    inline_document(&mime_type, data)
+}
+
+/// 校验 Claude 请求中的文档块
+pub fn validate_claude_request(request: &ClaudeRequest) -> Result<(), String> {
+    for msg in &request.messages {
+        let MessageContent::Array(blocks) = &msg.content else {
+            continue;
+        };
+        for block in blocks {
+            if let ContentBlock::Document { source, title, context, .. } = block {
+                claude_document_parts(source, title.as_deref(), context.as_deref())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 校验 OpenAI 请求中的文件块
+pub fn validate_openai_request(request: &OpenAIRequest) -> Result<(), String> {
+    for msg in &request.messages {
+        let Some(OpenAIContent::Array(blocks)) = &msg.content else {
+            continue;
+        };
+        for file in blocks.iter().filter_map(openai_file_content) {
+            openai_file_part(&file)?;
+        }
+    }
+    Ok(())
+}
+
+/// 下载远程 PDF，返回 base64
+async fn fetch_document_as_base64(url: &str) -> Result<String, String> {
+    let (content_type, bytes) = fetch_remote(url, MAX_DOCUMENT_BYTES).await?;
+    let declared_pdf = content_type
+        .as_deref()
+        .is_some_and(|ct| ct.to_ascii_lowercase().starts_with("application/pdf"));
+    if !declared_pdf && !bytes.starts_with(b"%PDF") {
+        return Err(format!(
+            "Remote document is not a PDF ({})",
+            content_type.as_deref().unwrap_or("unknown content type")
+        ));
+    }
+    Ok(base64::engine::general_purpose::STANDARD.encode(&bytes))
+}
+
+/// Claude: 将 url 类型的文档源下载为 base64 PDF，失败时保留原样 (由校验报错)
+pub async fn inline_claude_document_urls(request: &mut ClaudeRequest) {
+    let mut cache: HashMap<String, Option<String>> = HashMap::new();
+    for msg in request.messages.iter_mut() {
+        let MessageContent::Array(blocks) = &mut msg.content else {
+            continue;
+        };
+        for block in blocks.iter_mut() {
+            let ContentBlock::Document { source, .. } = block else {
+                continue;
+            };
+            let Some(url) = source.url.clone().filter(|u| source.source_type == "url" && is_remote_url(u)) else {
+                continue;
+            };
+            if !cache.contains_key(&url) {
+                let result = fetch_document_as_base64(&url).await;
+                if let Err(e) = &result {
+                    tracing::warn!("[Document] Failed to fetch remote document {}: {}", url, e);
+                }
+                cache.insert(url.clone(), result.ok());
+            }
+            if let Some(data) = cache.get(&url).cloned().flatten() {
+                source.source_type = "base64".to_string();
+                source.media_type = "application/pdf".to_string();
+                source.data = data;
+                source.url = None;
+            }
+        }
+    }
+}
+
+/// OpenAI: 仅提供 file_url 的文件块下载为 data URI
+pub async fn inline_openai_file_urls(request: &mut OpenAIRequest) {
+    for msg in request.messages.iter_mut() {
+        let Some(OpenAIContent::Array(blocks)) = msg.content.as_mut() else {
+            continue;
+        };
+        for block in blocks.iter_mut() {
+            let (file_data, file_url) = match block {
+                OpenAIContentBlock::File { file } => (&mut file.file_data, &file.file_url),
+                OpenAIContentBlock::InputFile { file_data, file_url, .. } => (file_data, &*file_url),
+                _ => continue,
+            };
+            let Some(url) = file_url.as_deref().filter(|u| file_data.is_none() && is_remote_url(u)) else {
+                continue;
+            };
+            match fetch_document_as_base64(url).await {
+                Ok(data) => *file_data = Some(format!("data:application/pdf;base64,{}", data)),
+                Err(e) => tracing::warn!("[Document] Failed to fetch remote file {}: {}", url, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_file_part_from_data_uri() {
+        let file = OpenAIFileContent {
+            file_data: Some("data:application/pdf;base64,JVBERi0xLjQK".to_string()),
+            filename: Some("report.pdf".to_string()),
+            ..Default::default()
+        };
+        let part = openai_file_part(&file).unwrap();
+        assert_eq!(part["inlineData"]["mimeType"], "application/pdf");
+        assert_eq!(part["inlineData"]["data"], "JVBERi0xLjQK");
+
+        // 纯 base64 按文件名推断类型
+        let plain = OpenAIFileContent {
+            file_data: Some("aGVsbG8=".to_string()),
+            filename: Some("notes.txt".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(openai_file_part(&plain).unwrap()["inlineData"]["mimeType"], "text/plain");
+
+        let by_id = OpenAIFileContent {
+            file_id: Some("file-abc".to_string()),
+            ..Default::default()
+        };
+        assert!(openai_file_part(&by_id).is_err());
+    }
+
+    #[test]
+    fn test_claude_document_parts_and_size_cap() {
+        let text_source = DocumentSource {
+            source_type: "text".to_string(),
+            media_type: "text/plain".to_string(),
+            data: "hello".to_string(),
+            url: None,
+            content: None,
+        };
+        let parts = claude_document_parts(&text_source, Some("Notes"), None).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1]["text"], "hello");
+
+        let oversized = DocumentSource {
+            source_type: "base64".to_string(),
+            media_type: "application/pdf".to_string(),
+            data: "A".repeat(MAX_DOCUMENT_BYTES / 3 * 4 + 8),
+            url: None,
+            content: None,
+        };
+        assert!(claude_document_parts(&oversized, None, None).is_err());
+
+        let unsupported = DocumentSource {
+            media_type: "application/zip".to_string(),
+            data: "UEsDBA==".to_string(),
+            ..oversized
+        };
+        assert!(claude_document_parts(&unsupported, None, None).unwrap_err().contains("Unsupported"));
+    }
+}
@@ -0,0 +1,186 @@
+// 上游错误转换
+// 将 Google (v1internal) 错误 JSON 转换为各协议客户端可识别的错误结构，
+// 避免把上游原始文本直接透传给 Anthropic / OpenAI SDK
+use serde_json::{json, Value};
+
+/// 客户端协议
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorProtocol {
+    Anthropic,
+    OpenAI,
+    Gemini,
+}
+
+/// 从上游错误文本中解析出的关键信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamErrorInfo {
+    pub message: String,
+    /// Google RPC 状态 (如 RESOURCE_EXHAUSTED)
+    pub status: Option<String>,
+}
+
+/// 解析 Google 错误 JSON (`{"error":{...}}` 或 `[{"error":{...}}]`)，非 JSON 时保留原始文本
+pub fn parse_upstream_error(error_text: &str) -> UpstreamErrorInfo {
+    let parsed: Option<Value> = serde_json::from_str(error_text.trim()).ok();
+    let error_obj = parsed.as_ref().and_then(|v| match v {
+        Value::Array(arr) => arr.first().and_then(|item| item.get("error")).cloned(),
+        _ => v.get("error").cloned(),
+    });
+
+    match error_obj {
+        Some(err) => UpstreamErrorInfo {
+            message: err
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| error_text.to_string()),
+            status: err
+                .get("status")
+                .and_then(|s| s.as_str())
+                .map(|s| s.to_string()),
+        },
+        None => UpstreamErrorInfo {
+            message: if error_text.trim().is_empty() {
+                "Upstream request failed".to_string()
+            } else {
+                error_text.to_string()
+            },
+            status: None,
+        },
+    }
+}
+
+/// Anthropic 错误类型
+pub fn anthropic_error_type(status_code: u16, google_status: Option<&str>) -> &'static str {
+    match google_status {
+        Some("RESOURCE_EXHAUSTED") => return "rate_limit_error",
+        Some("UNAVAILABLE") => return "overloaded_error",
+        Some("UNAUTHENTICATED") => return "authentication_error",
+        Some("PERMISSION_DENIED") => return "permission_error",
+        _ => {}
+    }
+    match status_code {
+        400 => "invalid_request_error",
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        503 | 529 => "overloaded_error",
+        _ => "api_error",
+    }
+}
+
+/// OpenAI 错误类型
+pub fn openai_error_type(status_code: u16, google_status: Option<&str>) -> &'static str {
+    match google_status {
+        Some("RESOURCE_EXHAUSTED") => return "rate_limit_error",
+        Some("UNAUTHENTICATED") => return "authentication_error",
+        Some("PERMISSION_DENIED") => return "permission_error",
+        _ => {}
+    }
+    match status_code {
+        400 | 413 => "invalid_request_error",
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        429 => "rate_limit_error",
+        _ => "server_error",
+    }
+}
+
+/// Gemini (Google RPC) 状态
+pub fn gemini_error_status(status_code: u16) -> &'static str {
+    match status_code {
+        400 | 413 => "INVALID_ARGUMENT",
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        429 => "RESOURCE_EXHAUSTED",
+        503 | 529 => "UNAVAILABLE",
+        504 => "DEADLINE_EXCEEDED",
+        _ => "INTERNAL",
+    }
+}
+
+/// 按协议构造错误响应体
+pub fn build_error_body(
+    protocol: ErrorProtocol,
+    status_code: u16,
+    message: &str,
+    google_status: Option<&str>,
+) -> Value {
+    match protocol {
+        ErrorProtocol::Anthropic => json!({
+            "type": "error",
+            "error": {
+                "type": anthropic_error_type(status_code, google_status),
+                "message": message
+            }
+        }),
+        ErrorProtocol::OpenAI => json!({
+            "error": {
+                "message": message,
+                "type": openai_error_type(status_code, google_status),
+                "param": null,
+                "code": google_status.map(|s| s.to_lowercase())
+            }
+        }),
+        ErrorProtocol::Gemini => json!({
+            "error": {
+                "code": status_code,
+                "message": message,
+                "status": google_status.unwrap_or_else(|| gemini_error_status(status_code))
+            }
+        }),
+    }
+}
+
+/// 将上游错误文本转换为对应协议的错误响应体
+pub fn map_upstream_error(protocol: ErrorProtocol, status_code: u16, error_text: &str) -> Value {
+    let info = parse_upstream_error(error_text);
+    build_error_body(protocol, status_code, &info.message, info.status.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOOGLE_429: &str = r#"{"error":{"code":429,"message":"Resource has been exhausted (e.g. check quota).","status":"RESOURCE_EXHAUSTED"}}"#;
+
+    #[test]
+    fn test_parse_upstream_error() {
+        let info = parse_upstream_error(GOOGLE_429);
+        assert_eq!(info.message, "Resource has been exhausted (e.g. check quota).");
+        assert_eq!(info.status.as_deref(), Some("RESOURCE_EXHAUSTED"));
+
+        let wrapped = format!("[{}]", GOOGLE_429);
+        assert_eq!(parse_upstream_error(&wrapped).status.as_deref(), Some("RESOURCE_EXHAUSTED"));
+
+        let raw = parse_upstream_error("upstream connect error");
+        assert_eq!(raw.message, "upstream connect error");
+        assert!(raw.status.is_none());
+    }
+
+    #[test]
+    fn test_map_upstream_error_per_protocol() {
+        let claude = map_upstream_error(ErrorProtocol::Anthropic, 429, GOOGLE_429);
+        assert_eq!(claude["type"], "error");
+        assert_eq!(claude["error"]["type"], "rate_limit_error");
+
+        let openai = map_upstream_error(ErrorProtocol::OpenAI, 429, GOOGLE_429);
+        assert_eq!(openai["error"]["type"], "rate_limit_error");
+        assert_eq!(openai["error"]["code"], "resource_exhausted");
+
+        let gemini = map_upstream_error(ErrorProtocol::Gemini, 429, GOOGLE_429);
+        assert_eq!(gemini["error"]["code"], 429);
+        assert_eq!(gemini["error"]["status"], "RESOURCE_EXHAUSTED");
+
+        let overloaded = map_upstream_error(ErrorProtocol::Anthropic, 503, "Service Unavailable");
+        assert_eq!(overloaded["error"]["type"], "overloaded_error");
+        assert_eq!(overloaded["error"]["message"], "Service Unavailable");
+
+        let auth = map_upstream_error(ErrorProtocol::OpenAI, 401, "");
+        assert_eq!(auth["error"]["type"], "authentication_error");
+    }
+}
@@ -0,0 +1,341 @@
+// 远程图片下载并内联
+// Gemini 无法直接读取任意 http(s) 图片地址，OpenAI image_url 与 Claude url 图片源需先下载再转为 base64 inlineData。
+// 使用共享 HTTP 客户端，遵循上游代理设置；fetch_remote 也供文档 (PDF) 下载复用。
+// 地址由客户端提供，每一跳 (含重定向) 都解析后拒绝回环 / 私有 / 链路本地地址，防止探测本机与局域网服务。
+use base64::Engine as _;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::proxy::mappers::claude::models::{ClaudeRequest, ContentBlock, MessageContent};
+use crate::proxy::mappers::openai::{OpenAIContent, OpenAIContentBlock, OpenAIRequest};
+
+/// 单张图片大小上限 (Gemini 内联请求整体上限约 20MB)
+const MAX_REMOTE_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+/// 单个请求最多下载的远程图片数
+const MAX_REMOTE_IMAGES_PER_REQUEST: usize = 8;
+/// 单个请求内联的远程图片总字节上限
+const MAX_INLINED_BYTES_PER_REQUEST: usize = 20 * 1024 * 1024;
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+pub(crate) fn is_remote_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// 回环 / 私有 / 链路本地 / 未指定等不允许访问的地址
+fn is_forbidden_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (b & 0xC0) == 64)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_forbidden_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 唯一本地地址
+                || (first & 0xFE00) == 0xFC00
+                // fe80::/10 链路本地地址
+                || (first & 0xFFC0) == 0xFE80
+        }
+    }
+}
+
+/// 解析目标主机，任一地址属于内网时拒绝
+async fn ensure_public_target(url: &url::Url) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Unsupported url scheme: {}", url.scheme()));
+    }
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<IpAddr> = match url.host() {
+        Some(url::Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(url::Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        Some(url::Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|e| format!("Failed to resolve host: {}", e))?
+            .map(|addr| addr.ip())
+            .collect(),
+        None => return Err("Url has no host".to_string()),
+    };
+    if addrs.is_empty() {
+        return Err("Failed to resolve host".to_string());
+    }
+    if addrs.into_iter().any(is_forbidden_ip) {
+        return Err("Url points to a local or private address".to_string());
+    }
+    Ok(())
+}
+
+/// 根据文件头识别常见图片格式
+fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// 确定图片 MIME：优先 Content-Type，其次文件头 (部分 CDN 返回 application/octet-stream)
+fn resolve_image_mime(content_type: Option<&str>, bytes: &[u8]) -> Result<String, String> {
+    let declared = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase())
+        .filter(|ct| !ct.is_empty());
+
+    match declared {
+        Some(ct) if ct.starts_with("image/") => Ok(ct),
+        Some(ct) if ct != "application/octet-stream" && ct != "binary/octet-stream" => {
+            Err(format!("Unsupported content type for image: {}", ct))
+        }
+        _ => sniff_image_mime(bytes)
+            .map(|m| m.to_string())
+            .ok_or_else(|| "Response is not a recognizable image".to_string()),
+    }
+}
+
+/// 下载远程文件，返回 (Content-Type, 内容)，超过 max_bytes 时报错
+pub(crate) async fn fetch_remote(url: &str, max_bytes: usize) -> Result<(Option<String>, Vec<u8>), String> {
+    if !is_remote_url(url) {
+        return Err(format!("Unsupported url scheme: {}", url));
+    }
+
+    // 手动跟随重定向，每一跳都重新校验目标地址
+    let client = crate::utils::http::get_no_redirect_client();
+    let mut current = url::Url::parse(url).map_err(|e| format!("Invalid url: {}", e))?;
+    let mut redirects = 0;
+    let resp = loop {
+        ensure_public_target(&current).await?;
+        let resp = client
+            .get(current.as_str())
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+        if !resp.status().is_redirection() {
+            break resp;
+        }
+        if redirects >= MAX_REDIRECTS {
+            return Err("Too many redirects".to_string());
+        }
+        let location = resp
+            .headers()
+            .get(rquest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| "Redirect without location".to_string())?;
+        current = current
+            .join(location)
+            .map_err(|e| format!("Invalid redirect location: {}", e))?;
+        redirects += 1;
+    };
+    if !resp.status().is_success() {
+        return Err(format!("Request returned {}", resp.status()));
+    }
+    if resp.content_length().is_some_and(|len| len as usize > max_bytes) {
+        return Err(format!("File exceeds {} bytes", max_bytes));
+    }
+
+    let content_type = resp
+        .headers()
+        .get(rquest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    // 分块读取，未声明长度的响应同样受上限约束
+    let mut bytes = Vec::new();
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Failed to read response: {}", e))?;
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(format!("File exceeds {} bytes", max_bytes));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    if bytes.is_empty() {
+        return Err("Response is empty".to_string());
+    }
+    Ok((content_type, bytes))
+}
+
+/// 下载远程图片，返回 (mime_type, base64)
+pub async fn fetch_image_as_base64(url: &str) -> Result<(String, String), String> {
+    fetch_image_limited(url, MAX_REMOTE_IMAGE_BYTES).await
+}
+
+async fn fetch_image_limited(url: &str, max_bytes: usize) -> Result<(String, String), String> {
+    let (content_type, bytes) = fetch_remote(url, max_bytes).await?;
+    let mime_type = resolve_image_mime(content_type.as_deref(), &bytes)?;
+    Ok((mime_type, base64::engine::general_purpose::STANDARD.encode(&bytes)))
+}
+
+/// 单个请求内的下载缓存与额度：相同地址只下载一次，下载次数与总字节数受限
+struct InlineBudget {
+    cache: HashMap<String, Option<(String, String)>>,
+    fetches: usize,
+    bytes: usize,
+}
+
+impl InlineBudget {
+    fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+            fetches: 0,
+            bytes: 0,
+        }
+    }
+
+    /// 剩余可下载字节数，下载次数或字节额度用尽时返回 None
+    fn remaining_bytes(&self) -> Option<usize> {
+        if self.fetches >= MAX_REMOTE_IMAGES_PER_REQUEST {
+            return None;
+        }
+        let remaining = MAX_INLINED_BYTES_PER_REQUEST.saturating_sub(self.bytes);
+        (remaining > 0).then_some(remaining.min(MAX_REMOTE_IMAGE_BYTES))
+    }
+
+    fn record(&mut self, raw_bytes: usize) {
+        self.fetches += 1;
+        self.bytes += raw_bytes;
+    }
+}
+
+/// 失败或超出额度时返回 None，调用方保留原始地址
+async fn fetch_cached(budget: &mut InlineBudget, url: &str) -> Option<(String, String)> {
+    if let Some(cached) = budget.cache.get(url) {
+        return cached.clone();
+    }
+    let Some(max_bytes) = budget.remaining_bytes() else {
+        tracing::warn!("[ImageFetch] Per-request remote image limit reached, skipping {}", url);
+        return None;
+    };
+    let result = match fetch_image_limited(url, max_bytes).await {
+        Ok(image) => {
+            tracing::debug!("[ImageFetch] Inlined remote image {} ({})", url, image.0);
+            // base64 长度按 4/3 折算回原始字节
+            budget.record(image.1.len() / 4 * 3);
+            Some(image)
+        }
+        Err(e) => {
+            tracing::warn!("[ImageFetch] Failed to inline remote image {}: {}", url, e);
+            budget.record(0);
+            None
+        }
+    };
+    budget.cache.insert(url.to_string(), result.clone());
+    result
+}
+
+/// OpenAI: 将 http(s) image_url 替换为 data URI
+pub async fn inline_openai_image_urls(request: &mut OpenAIRequest) {
+    let mut budget = InlineBudget::new();
+    for msg in request.messages.iter_mut() {
+        let Some(OpenAIContent::Array(blocks)) = msg.content.as_mut() else {
+            continue;
+        };
+        for block in blocks.iter_mut() {
+            if let OpenAIContentBlock::ImageUrl { image_url } = block {
+                if !is_remote_url(&image_url.url) {
+                    continue;
+                }
+                if let Some((mime_type, data)) = fetch_cached(&mut budget, &image_url.url).await {
+                    image_url.url = format!("data:{};base64,{}", mime_type, data);
+                }
+            }
+        }
+    }
+}
+
+/// Claude: 将 url 类型的图片源替换为 base64
+pub async fn inline_claude_image_urls(request: &mut ClaudeRequest) {
+    let mut budget = InlineBudget::new();
+    for msg in request.messages.iter_mut() {
+        let MessageContent::Array(blocks) = &mut msg.content else {
+            continue;
+        };
+        for block in blocks.iter_mut() {
+            if let ContentBlock::Image { source, .. } = block {
+                if source.source_type != "url" {
+                    continue;
+                }
+                let Some(url) = source.url.clone().filter(|u| is_remote_url(u)) else {
+                    continue;
+                };
+                if let Some((mime_type, data)) = fetch_cached(&mut budget, &url).await {
+                    source.source_type = "base64".to_string();
+                    source.media_type = mime_type;
+                    source.data = data;
+                    source.url = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_image_mime() {
+        assert_eq!(resolve_image_mime(Some("image/png; charset=binary"), b"").unwrap(), "image/png");
+        // octet-stream 按文件头识别
+        assert_eq!(
+            resolve_image_mime(Some("application/octet-stream"), &[0xFF, 0xD8, 0xFF, 0xE0]).unwrap(),
+            "image/jpeg"
+        );
+        assert_eq!(resolve_image_mime(None, b"GIF89a").unwrap(), "image/gif");
+        assert!(resolve_image_mime(Some("text/html"), b"<html>").is_err());
+        assert!(resolve_image_mime(None, b"plain text").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_local_and_private_targets() {
+        for url in [
+            "http://127.0.0.1:8045/api/accounts",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.5/a.png",
+            "http://192.168.1.10/a.png",
+            "http://[::1]/a.png",
+            "http://[::ffff:127.0.0.1]/a.png",
+            "http://[fd00::1]/a.png",
+            "http://localhost/a.png",
+            "ftp://example.com/a.png",
+        ] {
+            let parsed = url::Url::parse(url).unwrap();
+            assert!(ensure_public_target(&parsed).await.is_err(), "{} should be rejected", url);
+        }
+        let public = url::Url::parse("http://8.8.8.8/a.png").unwrap();
+        assert!(ensure_public_target(&public).await.is_ok());
+    }
+
+    #[test]
+    fn test_inline_budget_limits_count_and_bytes() {
+        let mut budget = InlineBudget::new();
+        assert_eq!(budget.remaining_bytes(), Some(MAX_REMOTE_IMAGE_BYTES));
+        budget.record(MAX_REMOTE_IMAGE_BYTES);
+        budget.record(MAX_REMOTE_IMAGE_BYTES);
+        assert_eq!(budget.remaining_bytes(), None);
+
+        let mut budget = InlineBudget::new();
+        for _ in 0..MAX_REMOTE_IMAGES_PER_REQUEST {
+            budget.record(1);
+        }
+        assert_eq!(budget.remaining_bytes(), None);
+    }
+}
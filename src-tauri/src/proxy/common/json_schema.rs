@@ -33,6 +33,21 @@ static TOOL_ADAPTERS: Lazy<Vec<Box<dyn ToolAdapter>>> = Lazy::new(|| {
 
 const MAX_RECURSION_DEPTH: usize = 10;
 
+/// Gemini 不支持且无法等价转换的组合/条件关键字，清洗时丢弃并记录警告
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "not",
+    "if",
+    "then",
+    "else",
+    "dependentSchemas",
+    "dependentRequired",
+    "patternProperties",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+    "contains",
+    "prefixItems",
+];
+
 /// 递归清理 JSON Schema 以符合 Gemini 接口要求
 ///
 /// 1. [New] 展开 $ref 和 $defs: 将引用替换为实际定义，解决 Gemini 不支持 $ref 的问题
@@ -41,6 +56,8 @@ const MAX_RECURSION_DEPTH: usize = 10;
 /// 4. [NEW] 处理 anyOf 联合类型: anyOf: [{"type": "string"}, {"type": "null"}] -> "type": "string"
 /// 5. 将 type 字段的值转换为小写 (Gemini v1internal 要求)
 /// 6. 移除数字校验字段: multipleOf, exclusiveMinimum, exclusiveMaximum 等
+/// 7. [NEW] const -> enum，全部为字面量的 anyOf/oneOf 合并为 enum，循环 $ref 截断
+/// 8. [NEW] not / if-then-else / patternProperties 等组合关键字丢弃并记录警告
 pub fn clean_json_schema(value: &mut Value) {
     // 0. 预处理：展开 $ref (Schema Flattening)
     // [FIX #952] 递归收集所有层级的 $defs/definitions，而非仅从根层级提取
@@ -56,7 +73,7 @@ pub fn clean_json_schema(value: &mut Value) {
     // [FIX #952] 始终运行 flatten_refs，即使 defs 为空
     // 这样可以捕获并处理无法解析的 $ref (降级为 string 类型)
     if let Value::Object(map) = value {
-        flatten_refs(map, &all_defs, 0);
+        flatten_refs(map, &all_defs, 0, &mut Vec::new());
     }
 
     // 递归清理
@@ -129,22 +146,34 @@ fn collect_all_defs(value: &Value, defs: &mut serde_json::Map<String, Value>) {
 }
 
 /// 递归展开 $ref
+///
+/// `stack` 记录当前路径上正在展开的定义名，用于识别自引用 (如树形结构)，
+/// 命中时截断为 object 并在描述中注明，而不是一直展开到深度上限。
 fn flatten_refs(
     map: &mut serde_json::Map<String, Value>,
     defs: &serde_json::Map<String, Value>,
     depth: usize,
+    stack: &mut Vec<String>,
 ) {
     if depth > MAX_RECURSION_DEPTH {
         tracing::warn!("[Schema-Flatten] Max recursion depth reached, stopping ref expansion.");
         return;
     }
 
-    // 检查并替换 $ref
-    if let Some(Value::String(ref_path)) = map.remove("$ref") {
+    // 检查并替换 $ref (定义本身也可能只是另一个 $ref 的别名，因此循环处理)
+    let mut expanded = 0;
+    while let Some(Value::String(ref_path)) = map.remove("$ref") {
         // 解析引用名 (例如 #/$defs/MyType -> MyType)
-        let ref_name = ref_path.split('/').last().unwrap_or(&ref_path);
+        let ref_name = ref_path.split('/').last().unwrap_or(&ref_path).to_string();
+
+        if stack.contains(&ref_name) {
+            tracing::warn!("[Schema-Flatten] Recursive $ref '{}' truncated", ref_name);
+            map.entry("type".to_string()).or_insert_with(|| json!("object"));
+            append_hint_to_description(map, format!("(Recursive $ref: {})", ref_name));
+            break;
+        }
 
-        if let Some(def_schema) = defs.get(ref_name) {
+        if let Some(def_schema) = defs.get(&ref_name) {
             // 将定义的内容合并到当前 map
             if let Value::Object(def_map) = def_schema {
                 for (k, v) in def_map {
@@ -152,10 +181,8 @@ fn flatten_refs(
                     // 但通常 $ref 节点不应该有其他属性
                     map.entry(k.clone()).or_insert_with(|| v.clone());
                 }
-
-                // 递归处理刚刚合并进来的内容中可能包含的 $ref
-                // 注意：由于引入了 depth 限制，循环引用不再会导致栈溢出
-                flatten_refs(map, defs, depth + 1);
+                stack.push(ref_name);
+                expanded += 1;
             }
         } else {
             // [FIX #952] 无法解析的 $ref: 转换为宽松的 string 类型，避免 API 400 错误
@@ -173,21 +200,24 @@ fn flatten_refs(
                     s.push_str(&hint);
                 }
             }
+            break;
         }
     }
 
-    // 遍历子节点
+    // 遍历子节点 (包括刚合并进来的定义内容)
     for (_, v) in map.iter_mut() {
         if let Value::Object(child_map) = v {
-            flatten_refs(child_map, defs, depth + 1);
+            flatten_refs(child_map, defs, depth + 1, stack);
         } else if let Value::Array(arr) = v {
             for item in arr {
                 if let Value::Object(item_map) = item {
-                    flatten_refs(item_map, defs, depth + 1);
+                    flatten_refs(item_map, defs, depth + 1, stack);
                 }
             }
         }
     }
+
+    stack.truncate(stack.len() - expanded);
 }
 
 fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: usize) -> bool {
@@ -202,6 +232,12 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
             // 0. [NEW] 合并 allOf
             merge_all_of(map);
 
+            // 0.1 [NEW] const -> enum (仅字符串字面量；其余字面量转为描述提示)
+            // 需在“简写对象”启发式之前执行，否则 {"const": "a"} 会被误当成属性定义
+            if is_schema_node {
+                convert_const_to_enum(map);
+            }
+
             // 0.5 [NEW] 结构归一化 (Normalization)
             // 针对某些 MCP 工具（如 pencil）误用 items 定义对象属性的情况进行修复。
             // 如果 type=object 或包含 properties，但又定义了 items，Gemini 会因为 items 只能出现在 array 中而报错。
@@ -271,6 +307,18 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                 }
             }
 
+            // 1.4. [NEW] 先剔除 null 分支并标记为可空
+            // 必须在分支清洗之前执行：清洗会把 {"type": "null"} 降级为 string，导致择优时误选
+            for key in ["anyOf", "oneOf"] {
+                if let Some(Value::Array(branches)) = map.get_mut(key) {
+                    let before = branches.len();
+                    branches.retain(|b| !is_null_schema(b));
+                    if branches.len() != before {
+                        is_effectively_nullable = true;
+                    }
+                }
+            }
+
             // 1.5. [FIX] 递归清理 anyOf/oneOf 数组中的每个分支
             // 必须在合并逻辑之前执行，确保合并的分支已经被清洗
             if let Some(Value::Array(any_of)) = map.get_mut("anyOf") {
@@ -292,6 +340,17 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                 union_to_merge = Some(one_of.clone());
             }
 
+            // 2.1 [NEW] 全部为字面量 (const/enum) 的联合类型直接合并为 enum，避免只保留第一个分支
+            if let Some(values) = union_to_merge.as_ref().and_then(|u| collapse_literal_union(u)) {
+                map.remove("anyOf");
+                map.remove("oneOf");
+                if !map.contains_key("enum") {
+                    map.insert("enum".to_string(), Value::Array(values));
+                }
+                map.entry("type".to_string()).or_insert_with(|| json!("string"));
+                union_to_merge = None;
+            }
+
             if let Some(union_array) = union_to_merge {
                 if let Some((best_branch, all_types)) = extract_best_schema_from_union(&union_array) {
                     if let Value::Object(branch_obj) = best_branch {
@@ -380,6 +439,9 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
             let looks_like_schema = (is_schema_node || has_standard_keyword) && !is_not_schema_payload;
 
             if looks_like_schema {
+                // 3.6 [NEW] 无法转换的组合关键字：记录警告后由白名单移除
+                warn_unsupported_keywords(map);
+
                 // 4. [ROBUST] 约束迁移：在被白名单过滤前，将校验项转为描述 Hint
                 // [NEW] 使用统一的约束回填函数
                 move_constraints_to_description(map);
@@ -588,6 +650,94 @@ fn merge_all_of(map: &mut serde_json::Map<String, Value>) {
     }
 }
 
+/// [NEW] 判断联合类型分支是否只表示 null
+fn is_null_schema(schema: &Value) -> bool {
+    let Value::Object(obj) = schema else {
+        return false;
+    };
+    match obj.get("type") {
+        Some(Value::String(t)) => return t.eq_ignore_ascii_case("null"),
+        Some(Value::Array(types)) => {
+            return !types.is_empty()
+                && types.iter().all(|t| t.as_str().is_some_and(|s| s.eq_ignore_ascii_case("null")))
+        }
+        _ => {}
+    }
+    if obj.get("const").is_some_and(|c| c.is_null()) {
+        return true;
+    }
+    matches!(obj.get("enum"), Some(Value::Array(values)) if values.len() == 1 && values[0].is_null())
+}
+
+/// [NEW] 若联合类型的每个分支都是字符串字面量 (已清洗为 enum)，返回合并后的 enum 值
+fn collapse_literal_union(union_array: &[Value]) -> Option<Vec<Value>> {
+    if union_array.is_empty() {
+        return None;
+    }
+    let mut values: Vec<Value> = Vec::new();
+    for branch in union_array {
+        let obj = branch.as_object()?;
+        if obj.contains_key("properties") || obj.contains_key("items") {
+            return None;
+        }
+        if obj.get("type").and_then(|t| t.as_str()).is_some_and(|t| t != "string") {
+            return None;
+        }
+        for value in obj.get("enum")?.as_array()? {
+            if !values.contains(value) {
+                values.push(value.clone());
+            }
+        }
+    }
+    Some(values)
+}
+
+/// [NEW] const -> enum
+///
+/// Gemini 只支持字符串 enum，因此只有字符串 const (或未声明 type) 才转换为单值 enum；
+/// 数字、布尔等字面量转为描述提示，避免生成 integer + enum 的非法组合。
+fn convert_const_to_enum(map: &mut serde_json::Map<String, Value>) {
+    let Some(value) = map.remove("const") else {
+        return;
+    };
+    if map.contains_key("enum") || map.contains_key("properties") || map.contains_key("items") {
+        return;
+    }
+    let is_string_type = map
+        .get("type")
+        .and_then(|t| t.as_str())
+        .map_or(true, |t| t.eq_ignore_ascii_case("string"));
+    match value {
+        Value::String(_) if is_string_type => {
+            map.insert("enum".to_string(), Value::Array(vec![value]));
+            map.entry("type".to_string()).or_insert_with(|| json!("string"));
+        }
+        Value::Null | Value::Object(_) | Value::Array(_) => {}
+        other => append_hint_to_description(map, format!("[Must be: {}]", other)),
+    }
+}
+
+/// [NEW] 记录将被丢弃的组合关键字；patternProperties 的键模式保留为描述提示
+fn warn_unsupported_keywords(map: &mut serde_json::Map<String, Value>) {
+    let dropped: Vec<&str> = UNSUPPORTED_KEYWORDS
+        .iter()
+        .copied()
+        .filter(|k| map.contains_key(*k))
+        .collect();
+    if dropped.is_empty() {
+        return;
+    }
+    tracing::warn!("[Schema-Clean] Dropping unsupported keywords: {}", dropped.join(", "));
+
+    let patterns: Option<Vec<String>> = map
+        .get("patternProperties")
+        .and_then(|p| p.as_object())
+        .map(|p| p.keys().cloned().collect());
+    if let Some(patterns) = patterns.filter(|p| !p.is_empty()) {
+        append_hint_to_description(map, format!("[Additional keys matching: {}]", patterns.join(", ")));
+    }
+}
+
 /// [NEW] 将提示信息追加到 description 字段
 /// 参考 CLIProxyAPI 的 Lazy Hint 策略
 fn append_hint_to_description(map: &mut serde_json::Map<String, Value>, hint: String) {
@@ -1568,7 +1718,169 @@ mod tests {
         assert!(schema.get("properties").is_some());
         assert_eq!(schema["properties"]["foo"]["type"], "string");
         
-        // 验证描述中增加了类型提示 (注意: null 分支在清洗后变为了带 (nullable) 标记的 string，因此去重后为 string | object)
+        // 验证描述中增加了类型提示 (null 分支在清洗前被剔除，仅标记为 nullable)
         assert!(schema["description"].as_str().unwrap().contains("Accepts: string | object"));
+        assert!(schema["description"].as_str().unwrap().contains("(nullable)"));
+    }
+
+    #[test]
+    fn test_null_branch_first_does_not_win() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": { "anyOf": [{ "type": "null" }, { "type": "integer" }] },
+                "label": { "oneOf": [{ "const": null }, { "type": "string" }] }
+            },
+            "required": ["limit", "label"]
+        });
+
+        clean_json_schema(&mut schema);
+
+        assert_eq!(schema["properties"]["limit"]["type"], "integer");
+        assert_eq!(schema["properties"]["label"]["type"], "string");
+        assert!(schema["properties"]["limit"]["description"]
+            .as_str()
+            .unwrap()
+            .contains("(nullable)"));
+        // 可空字段不再是 required
+        assert!(schema.get("required").is_none());
+    }
+
+    #[test]
+    fn test_const_converted_to_enum() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "kind": { "const": "circle" },
+                "version": { "type": "integer", "const": 2 },
+                "shape": {
+                    "type": "object",
+                    "const": { "r": 1 },
+                    "properties": { "r": { "type": "number" } }
+                }
+            }
+        });
+
+        clean_json_schema(&mut schema);
+
+        let kind = &schema["properties"]["kind"];
+        assert_eq!(kind["type"], "string");
+        assert_eq!(kind["enum"], json!(["circle"]));
+        assert!(kind.get("properties").is_none());
+
+        // 非字符串字面量不生成 enum，转为描述提示
+        let version = &schema["properties"]["version"];
+        assert_eq!(version["type"], "integer");
+        assert!(version.get("enum").is_none());
+        assert!(version["description"].as_str().unwrap().contains("[Must be: 2]"));
+
+        let shape = &schema["properties"]["shape"];
+        assert!(shape.get("const").is_none());
+        assert_eq!(shape["properties"]["r"]["type"], "number");
+    }
+
+    #[test]
+    fn test_literal_union_collapsed_to_enum() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "mode": {
+                    "anyOf": [
+                        { "const": "fast" },
+                        { "const": "slow" },
+                        { "type": "string", "enum": ["auto", "fast"] },
+                        { "type": "null" }
+                    ],
+                    "description": "Execution mode"
+                }
+            }
+        });
+
+        clean_json_schema(&mut schema);
+
+        let mode = &schema["properties"]["mode"];
+        assert_eq!(mode["type"], "string");
+        assert_eq!(mode["enum"], json!(["fast", "slow", "auto"]));
+        assert!(mode.get("anyOf").is_none());
+        let desc = mode["description"].as_str().unwrap();
+        assert!(desc.contains("Execution mode"));
+        assert!(desc.contains("(nullable)"));
+    }
+
+    #[test]
+    fn test_self_referencing_ref_is_truncated() {
+        let mut schema = json!({
+            "definitions": {
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "value": { "type": "string" },
+                        "children": { "type": "array", "items": { "$ref": "#/definitions/Node" } }
+                    }
+                },
+                "Alias": { "$ref": "#/definitions/Node" }
+            },
+            "type": "object",
+            "properties": {
+                "root": { "$ref": "#/definitions/Alias" }
+            }
+        });
+
+        clean_json_schema(&mut schema);
+
+        let root = &schema["properties"]["root"];
+        assert_eq!(root["type"], "object");
+        assert_eq!(root["properties"]["value"]["type"], "string");
+
+        // 第二层的自引用被截断，而不是一直展开到深度上限
+        let child = &root["properties"]["children"]["items"];
+        assert_eq!(child["type"], "object");
+        assert!(child["description"].as_str().unwrap().contains("Recursive $ref: Node"));
+        assert!(child["properties"].as_object().unwrap().is_empty());
+        assert!(schema.get("definitions").is_none());
+    }
+
+    #[test]
+    fn test_unsupported_combinators_dropped() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "env": {
+                    "type": "object",
+                    "patternProperties": { "^[A-Z_]+$": { "type": "string" } }
+                },
+                "count": {
+                    "type": "integer",
+                    "not": { "const": 0 },
+                    "if": { "minimum": 10 },
+                    "then": { "multipleOf": 10 }
+                },
+                "pair": {
+                    "type": "array",
+                    "prefixItems": [{ "type": "string" }, { "type": "number" }],
+                    "items": { "type": "string" }
+                }
+            }
+        });
+
+        clean_json_schema(&mut schema);
+
+        let env = &schema["properties"]["env"];
+        assert!(env.get("patternProperties").is_none());
+        assert_eq!(env["properties"], json!({}));
+        assert!(env["description"]
+            .as_str()
+            .unwrap()
+            .contains("[Additional keys matching: ^[A-Z_]+$]"));
+
+        let count = &schema["properties"]["count"];
+        for key in ["not", "if", "then"] {
+            assert!(count.get(key).is_none(), "{} should be dropped", key);
+        }
+        assert_eq!(count["type"], "integer");
+
+        let pair = &schema["properties"]["pair"];
+        assert!(pair.get("prefixItems").is_none());
+        assert_eq!(pair["items"]["type"], "string");
     }
 }
@@ -0,0 +1,147 @@
+// MALFORMED_FUNCTION_CALL 自动重试
+// Gemini 偶发生成无法解析的函数调用，此时候选项只有 finishReason 而没有任何内容。
+// 这类失败几乎都是随机的，带一句纠正提示重试一次通常就能成功，因此在把结果交给客户端前先探测首个候选块。
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::proxy::common::sse_decoder::{sse_data, SseLineDecoder};
+use crate::proxy::config::SystemPromptRule;
+
+/// 重试时追加到系统提示词末尾的纠正提示
+pub const CORRECTIVE_HINT: &str = "Your previous attempt produced a malformed function call. \
+When calling a tool, emit exactly one well-formed call whose name matches a declared tool and \
+whose arguments are valid JSON matching that tool's schema.";
+
+/// 探测时最多缓存的字节数，超过后视为正常响应
+const PEEK_BUFFER_LIMIT: usize = 64 * 1024;
+const PEEK_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub type ByteStream<E> = Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send>>;
+
+/// 纠正提示对应的系统提示词规则 (复用系统提示词注入逻辑)
+pub fn corrective_rule() -> SystemPromptRule {
+    SystemPromptRule {
+        prefix: None,
+        suffix: Some(CORRECTIVE_HINT.to_string()),
+    }
+}
+
+/// 若 Gemini 响应块 (可带 v1internal 的 response 包装) 是没有任何输出的 MALFORMED_FUNCTION_CALL，返回 finishMessage
+pub fn malformed_call_message(chunk: &Value) -> Option<String> {
+    let raw = chunk.get("response").unwrap_or(chunk);
+    let candidate = raw.get("candidates")?.get(0)?;
+    let reason = candidate.get("finishReason")?.as_str()?;
+    if reason != "MALFORMED_FUNCTION_CALL" && reason != "UNEXPECTED_TOOL_CALL" {
+        return None;
+    }
+    let has_output = candidate
+        .pointer("/content/parts")
+        .and_then(|p| p.as_array())
+        .map(|parts| {
+            parts.iter().any(|p| {
+                p.get("functionCall").is_some()
+                    || p.get("text").and_then(|t| t.as_str()).is_some_and(|t| !t.trim().is_empty())
+            })
+        })
+        .unwrap_or(false);
+    if has_output {
+        return None;
+    }
+    Some(
+        candidate
+            .get("finishMessage")
+            .and_then(|m| m.as_str())
+            .unwrap_or(reason)
+            .to_string(),
+    )
+}
+
+/// 探测上游 SSE 流的首个候选块: 命中 MALFORMED_FUNCTION_CALL 时返回 Err(finishMessage)，
+/// 否则把已读取的数据拼回流头部原样返回
+pub async fn peek_stream<E: Send + 'static>(mut stream: ByteStream<E>) -> Result<ByteStream<E>, String> {
+    let mut buffered: Vec<Result<Bytes, E>> = Vec::new();
+    let mut decoder = SseLineDecoder::new();
+    let mut total = 0usize;
+
+    'peek: while total < PEEK_BUFFER_LIMIT {
+        let chunk = match tokio::time::timeout(PEEK_TIMEOUT, stream.next()).await {
+            Ok(Some(Ok(chunk))) => chunk,
+            Ok(Some(Err(e))) => {
+                buffered.push(Err(e));
+                break;
+            }
+            // 结束或超时交由后续的空响应 / 首包检测处理
+            Ok(None) | Err(_) => break,
+        };
+        total += chunk.len();
+        let lines = decoder.push(&chunk);
+        buffered.push(Ok(chunk));
+
+        for line in lines {
+            let Some(data) = sse_data(line.trim()) else {
+                continue;
+            };
+            let Ok(json) = serde_json::from_str::<Value>(data.trim()) else {
+                continue;
+            };
+            let raw = json.get("response").unwrap_or(&json);
+            if raw.get("candidates").is_none() {
+                continue;
+            }
+            if let Some(message) = malformed_call_message(&json) {
+                return Err(message);
+            }
+            break 'peek;
+        }
+    }
+
+    Ok(Box::pin(futures::stream::iter(buffered).chain(stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_malformed_call_detection() {
+        let chunk = json!({"response": {"candidates": [{
+            "finishReason": "MALFORMED_FUNCTION_CALL",
+            "finishMessage": "Malformed function call: call:default_api:bash{"
+        }]}});
+        assert_eq!(
+            malformed_call_message(&chunk).as_deref(),
+            Some("Malformed function call: call:default_api:bash{")
+        );
+
+        let with_text = json!({"candidates": [{
+            "finishReason": "MALFORMED_FUNCTION_CALL",
+            "content": {"parts": [{"text": "partial answer"}]}
+        }]});
+        assert!(malformed_call_message(&with_text).is_none());
+
+        let normal = json!({"candidates": [{"finishReason": "STOP"}]});
+        assert!(malformed_call_message(&normal).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_peek_stream_replays_normal_chunks() {
+        let chunks: Vec<Result<Bytes, String>> = vec![
+            Ok(Bytes::from("data: {\"response\": {\"candidates\": [{\"content\": ")),
+            Ok(Bytes::from("{\"parts\": [{\"text\": \"hi\"}]}}]}}\n\n")),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let stream: ByteStream<String> = Box::pin(futures::stream::iter(chunks));
+        let replayed: Vec<_> = peek_stream(stream).await.unwrap().collect().await;
+        assert_eq!(replayed.len(), 3);
+
+        let malformed: Vec<Result<Bytes, String>> = vec![Ok(Bytes::from(
+            "data: {\"response\": {\"candidates\": [{\"finishReason\": \"MALFORMED_FUNCTION_CALL\"}]}}\n\n",
+        ))];
+        let stream: ByteStream<String> = Box::pin(futures::stream::iter(malformed));
+        assert_eq!(peek_stream(stream).await.err().as_deref(), Some("MALFORMED_FUNCTION_CALL"));
+    }
+}
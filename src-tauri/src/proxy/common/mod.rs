@@ -11,3 +11,11 @@ pub mod schema_cache;
 pub mod client_adapter;
 pub mod client_adapters;
 pub mod session; // [ADDED v4.1.24] Tools for deriving stable session identifiers
+pub mod error_mapper; // [NEW] 上游错误 -> 协议错误结构转换
+pub mod image_fetch; // [NEW] 远程图片下载并内联为 base64
+pub mod document; // [NEW] PDF / 文档输入转 Gemini inlineData
+pub mod request_validation; // [NEW] 协议化请求体提取与字段级校验错误
+pub mod context_trim; // [NEW] 超出上下文窗口时裁剪最早的对话轮次
+pub mod system_prompt; // [NEW] 全局 / 按 API Key 的系统提示词注入
+pub mod malformed_call; // [NEW] MALFORMED_FUNCTION_CALL 探测与单次纠正重试
+pub mod sse_decoder; // [NEW] 流式响应行解码 (UTF-8 / CRLF 安全)
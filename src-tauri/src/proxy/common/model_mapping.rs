@@ -1,5 +1,6 @@
 // 模型名称映射
 use std::collections::HashMap;
+use crate::proxy::config::{ModelMappingRule, ModelMappingRuleKind, QuotaGroupOverride};
 use once_cell::sync::Lazy;
 use dashmap::DashMap;
 
@@ -132,6 +133,33 @@ pub fn map_claude_model_to_gemini(input: &str) -> String {
     input.to_string()
 }
 
+/// 按请求覆盖模型的请求头 (按顺序取第一个非空值)
+pub const MODEL_OVERRIDE_HEADERS: [&str; 2] = ["x-agm-model", "x-model-override"];
+
+/// 读取请求头中的模型覆盖，用于无需改映射即可让写死模型名的客户端切换模型
+pub fn model_override_from_headers(headers: &axum::http::HeaderMap) -> Option<String> {
+    MODEL_OVERRIDE_HEADERS.iter().find_map(|name| {
+        let value = headers.get(*name)?.to_str().ok()?.trim();
+        let valid = !value.is_empty()
+            && value.len() <= 128
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':' | '/' | '@'));
+        valid.then(|| value.to_string())
+    })
+}
+
+/// 用请求头覆盖请求体中的 model (在映射之前)，返回覆盖后的模型名
+pub fn apply_model_override(headers: &axum::http::HeaderMap, body: &mut serde_json::Value) -> Option<String> {
+    let model = model_override_from_headers(headers)?;
+    let original = body.get("model").and_then(|m| m.as_str()).unwrap_or_default();
+    tracing::info!("[Router] Header model override: {} -> {}", original, model);
+    if let Some(obj) = body.as_object_mut() {
+        obj.insert("model".to_string(), serde_json::Value::String(model.clone()));
+    }
+    Some(model)
+}
+
 /// 获取所有内置支持的模型列表关键字
 pub fn get_supported_models() -> Vec<String> {
     CLAUDE_TO_GEMINI.keys().map(|s| s.to_string()).collect()
@@ -151,10 +179,16 @@ pub async fn get_all_dynamic_models(
     }
 
     // 2. 获取所有自定义映射模型 (Custom)
+    // [FIX] 通配符规则不是可请求的模型名，只列出精确别名；同时列出映射的目标模型
     {
         let mapping = custom_mapping.read().await;
-        for key in mapping.keys() {
-            model_ids.insert(key.clone());
+        for (key, target) in mapping.iter() {
+            if !key.contains('*') {
+                model_ids.insert(key.clone());
+            }
+            if !target.is_empty() && !target.contains('*') {
+                model_ids.insert(target.clone());
+            }
         }
     }
 
@@ -195,6 +229,24 @@ pub async fn get_all_dynamic_models(
     sorted_ids
 }
 
+/// 通配符规则中特异性最高 (非通配字符最多) 的一条，返回 (pattern, target)
+fn best_wildcard_match<'a>(
+    original_model: &str,
+    custom_mapping: &'a std::collections::HashMap<String, String>,
+) -> Option<(&'a str, &'a str)> {
+    let mut best_match: Option<(&str, &str, usize)> = None;
+
+    for (pattern, target) in custom_mapping.iter() {
+        if pattern.contains('*') && wildcard_match(pattern, original_model) {
+            let specificity = pattern.chars().count() - pattern.matches('*').count();
+            if best_match.is_none() || specificity > best_match.unwrap().2 {
+                best_match = Some((pattern.as_str(), target.as_str(), specificity));
+            }
+        }
+    }
+    best_match.map(|(pattern, target, _)| (pattern, target))
+}
+
 /// Wildcard matching - supports multiple wildcards
 ///
 /// **Note**: Matching is **case-sensitive**. Pattern `GPT-4*` will NOT match `gpt-4-turbo`.
@@ -204,7 +256,7 @@ pub async fn get_all_dynamic_models(
 /// - `claude-*-sonnet-*` matches `claude-3-5-sonnet-20241022` ✓
 /// - `*-thinking` matches `claude-opus-4-5-thinking` ✓
 /// - `a*b*c` matches `a123b456c` ✓
-fn wildcard_match(pattern: &str, text: &str) -> bool {
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
     let parts: Vec<&str> = pattern.split('*').collect();
 
     // No wildcard - exact match
@@ -241,61 +293,270 @@ fn wildcard_match(pattern: &str, text: &str) -> bool {
     true
 }
 
-/// 核心模型路由解析引擎
-/// 优先级：精确匹配 > 通配符匹配 > 系统默认映射
-/// 
-/// # 参数
-/// - `original_model`: 原始模型名称
-/// - `custom_mapping`: 用户自定义映射表
-/// 
-/// # 返回
-/// 映射后的目标模型名称
-pub fn resolve_model_route(
+// ===== 优先级映射规则 (ProxyConfig.model_mapping_rules) =====
+
+/// 预编译的映射规则
+struct CompiledRule {
+    rule: ModelMappingRule,
+    regex: Option<regex::Regex>,
+}
+
+impl CompiledRule {
+    /// 匹配成功时返回目标模型 (正则规则展开捕获组)
+    fn apply(&self, model: &str) -> Option<String> {
+        match &self.regex {
+            Some(re) => {
+                let caps = re.captures(model)?;
+                let mut target = String::new();
+                caps.expand(&self.rule.target, &mut target);
+                Some(target)
+            }
+            None => wildcard_match(&self.rule.pattern, model).then(|| self.rule.target.clone()),
+        }
+    }
+}
+
+static MAPPING_RULES: Lazy<std::sync::RwLock<Vec<CompiledRule>>> =
+    Lazy::new(|| std::sync::RwLock::new(Vec::new()));
+
+/// 编译正则规则 (整串匹配)
+fn compile_rule_regex(pattern: &str) -> Result<regex::Regex, String> {
+    regex::Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))
+}
+
+/// 校验单条规则
+pub fn validate_mapping_rule(rule: &ModelMappingRule) -> Result<(), String> {
+    if rule.pattern.trim().is_empty() {
+        return Err("Rule pattern is empty".to_string());
+    }
+    if rule.target.trim().is_empty() {
+        return Err("Rule target is empty".to_string());
+    }
+    if rule.kind == ModelMappingRuleKind::Regex {
+        compile_rule_regex(&rule.pattern)?;
+    }
+    Ok(())
+}
+
+/// 更新映射规则 (启动与配置热更新时调用)，无效规则跳过并记录警告
+pub fn update_mapping_rules(rules: &[ModelMappingRule]) {
+    let mut compiled: Vec<CompiledRule> = Vec::new();
+    for rule in rules.iter().filter(|r| r.enabled) {
+        let regex = match rule.kind {
+            ModelMappingRuleKind::Regex => match compile_rule_regex(&rule.pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    tracing::warn!("[Mapping] Skipping rule {}: {}", rule.id, e);
+                    continue;
+                }
+            },
+            ModelMappingRuleKind::Wildcard => None,
+        };
+        compiled.push(CompiledRule {
+            rule: rule.clone(),
+            regex,
+        });
+    }
+    // 稳定排序：优先级高者在前，同优先级保持配置顺序
+    compiled.sort_by(|a, b| b.rule.priority.cmp(&a.rule.priority));
+    if let Ok(mut current) = MAPPING_RULES.write() {
+        *current = compiled;
+    }
+}
+
+/// 读取已保存的映射规则
+pub fn list_mapping_rules() -> Result<Vec<ModelMappingRule>, String> {
+    Ok(crate::modules::config::load_app_config()?.proxy.model_mapping_rules)
+}
+
+/// 新增或更新规则 (按 id)，保存配置并立即生效
+pub fn save_mapping_rule(mut rule: ModelMappingRule) -> Result<Vec<ModelMappingRule>, String> {
+    validate_mapping_rule(&rule)?;
+    rule.pattern = rule.pattern.trim().to_string();
+    rule.target = rule.target.trim().to_string();
+    if rule.id.is_empty() {
+        rule.id = uuid::Uuid::new_v4().simple().to_string();
+    }
+
+    let mut app_config = crate::modules::config::load_app_config()?;
+    let rules = &mut app_config.proxy.model_mapping_rules;
+    match rules.iter_mut().find(|r| r.id == rule.id) {
+        Some(existing) => *existing = rule,
+        None => rules.push(rule),
+    }
+    crate::modules::config::save_app_config(&app_config)?;
+    update_mapping_rules(&app_config.proxy.model_mapping_rules);
+    Ok(app_config.proxy.model_mapping_rules)
+}
+
+/// 删除规则，保存配置并立即生效
+pub fn delete_mapping_rule(rule_id: &str) -> Result<Vec<ModelMappingRule>, String> {
+    let mut app_config = crate::modules::config::load_app_config()?;
+    let before = app_config.proxy.model_mapping_rules.len();
+    app_config.proxy.model_mapping_rules.retain(|r| r.id != rule_id);
+    if app_config.proxy.model_mapping_rules.len() == before {
+        return Err(format!("Mapping rule not found: {}", rule_id));
+    }
+    crate::modules::config::save_app_config(&app_config)?;
+    update_mapping_rules(&app_config.proxy.model_mapping_rules);
+    Ok(app_config.proxy.model_mapping_rules)
+}
+
+/// 按已保存的配置试算模型路由 (不发起请求)
+pub fn dry_run_model_route(model: &str) -> Result<ModelRouteResolution, String> {
+    let app_config = crate::modules::config::load_app_config()?;
+    Ok(explain_model_route(model.trim(), &app_config.proxy.custom_mapping))
+}
+
+/// 模型路由解析结果 (dry-run 使用)
+#[derive(Debug, Clone, serde::Serialize, PartialEq)]
+pub struct ModelRouteResolution {
+    pub model: String,
+    pub target: String,
+    /// forwarding / exact / rule / wildcard / default
+    pub matched_by: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+/// 核心模型路由解析 (不输出日志)
+/// 优先级：官方淘汰重定向 > 精确匹配 > 优先级规则 > 映射表通配符 > 系统默认映射
+pub fn explain_model_route(
     original_model: &str,
     custom_mapping: &std::collections::HashMap<String, String>,
-) -> String {
+) -> ModelRouteResolution {
+    let resolution = |target: String, matched_by: &'static str, rule_id: Option<String>, pattern: Option<String>| {
+        ModelRouteResolution {
+            model: original_model.to_string(),
+            target,
+            matched_by,
+            rule_id,
+            pattern,
+        }
+    };
+
     // 0. API 热更新废弃模型转发 (最高物理优先级，强制纠正)
     // 如果用户非要用已经被移除的模型，并且官方下发了 fallback path，我们在此拦截并纠正
     if let Some(forwarded) = DYNAMIC_MODEL_FORWARDING_RULES.get(original_model) {
-        crate::modules::logger::log_info(&format!("[Router] 官方淘汰重定向: {} -> {}", original_model, forwarded.value()));
-        return forwarded.value().clone();
+        return resolution(forwarded.value().clone(), "forwarding", None, None);
     }
 
     // 1. 精确匹配 (次高优先级)
     if let Some(target) = custom_mapping.get(original_model) {
-        crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, target));
-        return target.clone();
+        return resolution(target.clone(), "exact", None, None);
     }
-    
-    // 2. Wildcard match - most specific (highest non-wildcard chars) wins
-    // Note: When multiple patterns have the SAME specificity, HashMap iteration order
-    // determines the result (non-deterministic). Users can avoid this by making patterns
-    // more specific. Future improvement: use IndexMap + frontend sorting for full control.
-    let mut best_match: Option<(&str, &str, usize)> = None;
 
-    for (pattern, target) in custom_mapping.iter() {
-        if pattern.contains('*') && wildcard_match(pattern, original_model) {
-            let specificity = pattern.chars().count() - pattern.matches('*').count();
-            if best_match.is_none() || specificity > best_match.unwrap().2 {
-                best_match = Some((pattern.as_str(), target.as_str(), specificity));
+    // 2. 优先级规则 (通配符 / 正则)，按优先级顺序取第一条命中
+    if let Ok(rules) = MAPPING_RULES.read() {
+        for compiled in rules.iter() {
+            if let Some(target) = compiled.apply(original_model) {
+                return resolution(
+                    target,
+                    "rule",
+                    Some(compiled.rule.id.clone()),
+                    Some(compiled.rule.pattern.clone()),
+                );
             }
         }
     }
 
-    if let Some((pattern, target, _)) = best_match {
-        crate::modules::logger::log_info(&format!(
+    // 3. Wildcard match - most specific (highest non-wildcard chars) wins
+    // Note: When multiple patterns have the SAME specificity, HashMap iteration order
+    // determines the result (non-deterministic). 需要确定顺序时请改用优先级规则。
+    if let Some((pattern, target)) = best_wildcard_match(original_model, custom_mapping) {
+        return resolution(target.to_string(), "wildcard", None, Some(pattern.to_string()));
+    }
+
+    // 4. 系统默认映射
+    resolution(map_claude_model_to_gemini(original_model), "default", None, None)
+}
+
+/// 与 resolve_model_route 相同的路由结果，但不输出日志 (用于模型列表等批量查询)
+pub fn peek_model_route(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+) -> String {
+    explain_model_route(original_model, custom_mapping).target
+}
+
+/// 核心模型路由解析引擎
+/// 优先级：精确匹配 > 优先级规则 > 通配符匹配 > 系统默认映射
+/// 
+/// # 参数
+/// - `original_model`: 原始模型名称
+/// - `custom_mapping`: 用户自定义映射表
+/// 
+/// # 返回
+/// 映射后的目标模型名称
+pub fn resolve_model_route(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+) -> String {
+    let route = explain_model_route(original_model, custom_mapping);
+    let message = match route.matched_by {
+        "forwarding" => format!("[Router] 官方淘汰重定向: {} -> {}", original_model, route.target),
+        "exact" => format!("[Router] 精确映射: {} -> {}", original_model, route.target),
+        "rule" => format!(
+            "[Router] Rule match: {} -> {} (rule: {})",
+            original_model,
+            route.target,
+            route.pattern.as_deref().unwrap_or_default()
+        ),
+        "wildcard" => format!(
             "[Router] Wildcard match: {} -> {} (rule: {})",
-            original_model, target, pattern
-        ));
-        return target.to_string();
+            original_model,
+            route.target,
+            route.pattern.as_deref().unwrap_or_default()
+        ),
+        _ if route.target != original_model => {
+            format!("[Router] 系统默认映射: {} -> {}", original_model, route.target)
+        }
+        _ => return route.target,
+    };
+    crate::modules::logger::log_info(&message);
+    route.target
+}
+
+static QUOTA_GROUP_OVERRIDES: Lazy<std::sync::RwLock<Vec<QuotaGroupOverride>>> =
+    Lazy::new(|| std::sync::RwLock::new(Vec::new()));
+
+/// 校验配额组覆盖
+pub fn validate_quota_group_overrides(overrides: &[QuotaGroupOverride]) -> Result<(), String> {
+    for item in overrides {
+        if item.pattern.trim().is_empty() {
+            return Err("Quota group override pattern is empty".to_string());
+        }
+        if item.group.trim().is_empty() {
+            return Err(format!("Quota group override '{}' has an empty group", item.pattern));
+        }
     }
-    
-    // 3. 系统默认映射
-    let result = map_claude_model_to_gemini(original_model);
-    if result != original_model {
-        crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, result));
+    Ok(())
+}
+
+/// 更新配额组覆盖 (启动与配置热更新时调用)
+pub fn update_quota_group_overrides(overrides: &[QuotaGroupOverride]) {
+    let normalized = overrides
+        .iter()
+        .filter(|o| !o.pattern.trim().is_empty() && !o.group.trim().is_empty())
+        .map(|o| QuotaGroupOverride {
+            pattern: o.pattern.trim().to_lowercase(),
+            group: o.group.trim().to_string(),
+        })
+        .collect();
+    if let Ok(mut current) = QUOTA_GROUP_OVERRIDES.write() {
+        *current = normalized;
     }
-    result
+}
+
+/// 在覆盖列表中查找配额组 (pattern 已小写)
+fn match_quota_group_override(overrides: &[QuotaGroupOverride], lower_model: &str) -> Option<String> {
+    overrides
+        .iter()
+        .find(|o| wildcard_match(&o.pattern, lower_model))
+        .map(|o| o.group.clone())
 }
 
 /// Normalize any physical model name to one of the 3 standard protection IDs.
@@ -306,9 +567,19 @@ pub fn resolve_model_route(
 /// - `gemini-3-pro-high`: All Pro variants (1.5-pro, 2.5-pro, etc.)
 /// - `claude-sonnet-4-5`: All Claude Sonnet variants (3-5-sonnet, sonnet-4-5, etc.)
 /// 
+/// User-defined `quota_group_overrides` take precedence over the built-in inference.
 /// Returns `None` if the model doesn't match any of the 3 protected categories.
 pub fn normalize_to_standard_id(model_name: &str) -> Option<String> {
     let lower = model_name.to_lowercase();
+
+    // 0. 用户配置的配额组覆盖
+    if let Some(group) = QUOTA_GROUP_OVERRIDES
+        .read()
+        .ok()
+        .and_then(|overrides| match_quota_group_override(&overrides, &lower))
+    {
+        return Some(group);
+    }
     
     // 1. image 资源 (优先匹配，使用 contains 匹配以支持任何变体，如 gemini-3.1-flash-image)
     if lower.contains("image") {
@@ -487,4 +758,74 @@ mod tests {
         // Multi-wildcard: "a*b*c" (3)
         assert_eq!(resolve_model_route("a-test-b-foo-c", &custom), "multi-wild");
     }
+
+    #[test]
+    fn test_priority_mapping_rules() {
+        let rule = |id: &str, pattern: &str, target: &str, kind, priority| ModelMappingRule {
+            id: id.to_string(),
+            pattern: pattern.to_string(),
+            target: target.to_string(),
+            kind,
+            priority,
+            enabled: true,
+        };
+        update_mapping_rules(&[
+            rule("low", "rules-test-*", "gemini-2.5-flash", ModelMappingRuleKind::Wildcard, 0),
+            rule("high", r"rules-test-(\d+)-pro", "gemini-$1-pro-high", ModelMappingRuleKind::Regex, 10),
+            rule("bad", "rules-test-(", "x", ModelMappingRuleKind::Regex, 100),
+        ]);
+
+        let empty = HashMap::new();
+        let route = explain_model_route("rules-test-3-pro", &empty);
+        assert_eq!(route.target, "gemini-3-pro-high");
+        assert_eq!(route.rule_id.as_deref(), Some("high"));
+
+        let route = explain_model_route("rules-test-mini", &empty);
+        assert_eq!(route.target, "gemini-2.5-flash");
+        assert_eq!(route.matched_by, "rule");
+
+        // 精确映射优先于规则
+        let exact = HashMap::from([("rules-test-mini".to_string(), "gemini-3-flash".to_string())]);
+        assert_eq!(explain_model_route("rules-test-mini", &exact).matched_by, "exact");
+
+        // 正则整串匹配
+        assert_eq!(explain_model_route("x-rules-test-3-pro", &empty).matched_by, "default");
+
+        assert!(validate_mapping_rule(&rule("bad", "(", "x", ModelMappingRuleKind::Regex, 0)).is_err());
+        update_mapping_rules(&[]);
+    }
+
+    #[test]
+    fn test_quota_group_override_matching() {
+        let overrides = vec![
+            QuotaGroupOverride { pattern: "gemini-4-nova*".to_string(), group: "gemini-3-pro-high".to_string() },
+            QuotaGroupOverride { pattern: "*-lite".to_string(), group: "gemini-3-flash".to_string() },
+        ];
+        assert_eq!(
+            match_quota_group_override(&overrides, "gemini-4-nova-preview"),
+            Some("gemini-3-pro-high".to_string())
+        );
+        assert_eq!(match_quota_group_override(&overrides, "gemini-4-lite"), Some("gemini-3-flash".to_string()));
+        assert_eq!(match_quota_group_override(&overrides, "gemini-3-flash"), None);
+
+        let empty = QuotaGroupOverride { pattern: " ".to_string(), group: "claude".to_string() };
+        assert!(validate_quota_group_overrides(&[empty]).is_err());
+    }
+
+    #[test]
+    fn test_model_override_header() {
+        let mut headers = axum::http::HeaderMap::new();
+        let mut body = serde_json::json!({ "model": "gpt-4o", "messages": [] });
+        assert_eq!(apply_model_override(&headers, &mut body), None);
+        assert_eq!(body["model"], "gpt-4o");
+
+        headers.insert("x-model-override", "gemini-3-flash".parse().unwrap());
+        headers.insert("x-agm-model", "claude-sonnet-4-6".parse().unwrap());
+        assert_eq!(apply_model_override(&headers, &mut body).as_deref(), Some("claude-sonnet-4-6"));
+        assert_eq!(body["model"], "claude-sonnet-4-6");
+
+        // 非法值忽略，回退到下一个请求头
+        headers.insert("x-agm-model", axum::http::HeaderValue::from_static("bad model"));
+        assert_eq!(model_override_from_headers(&headers).as_deref(), Some("gemini-3-flash"));
+    }
 }
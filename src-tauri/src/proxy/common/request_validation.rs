@@ -0,0 +1,201 @@
+// 客户端请求校验
+// 1. 协议化的 JSON 提取器：替代 axum::Json，在请求体不是合法 JSON / Content-Type 错误时返回对应协议结构的 400；
+// 2. 反序列化失败时逐层定位出错字段 (如 messages[2].content[1])，而不是只返回 untagged 枚举的笼统报错。
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::error_mapper::{build_error_body, ErrorProtocol};
+
+/// 请求校验失败详情
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidRequest {
+    /// 出错字段路径，例如 `messages[1].content[0]`
+    pub param: Option<String>,
+    pub message: String,
+}
+
+impl InvalidRequest {
+    fn new(param: Option<String>, message: impl Into<String>) -> Self {
+        Self { param, message: message.into() }
+    }
+
+    /// 带字段路径的完整描述
+    pub fn describe(&self) -> String {
+        match &self.param {
+            Some(param) => format!("Invalid request body at `{}`: {}", param, self.message),
+            None => format!("Invalid request body: {}", self.message),
+        }
+    }
+
+    /// 按协议构造错误响应体
+    pub fn to_body(&self, protocol: ErrorProtocol) -> Value {
+        let mut body = build_error_body(protocol, 400, &self.describe(), None);
+        if protocol == ErrorProtocol::OpenAI {
+            body["error"]["param"] = self.param.clone().map(Value::String).unwrap_or(Value::Null);
+        }
+        body
+    }
+
+    pub fn into_response(self, protocol: ErrorProtocol) -> Response {
+        (StatusCode::BAD_REQUEST, Json(self.to_body(protocol))).into_response()
+    }
+}
+
+/// axum Json 提取失败 -> 协议化错误 (保留 415 / 413 等原始状态码)
+pub fn json_rejection_response(protocol: ErrorProtocol, rejection: JsonRejection) -> Response {
+    let status = rejection.status();
+    let message = rejection.body_text();
+    tracing::debug!("[Request-Validation] Rejected request body ({}): {}", status, message);
+    (status, Json(build_error_body(protocol, status.as_u16(), &message, None))).into_response()
+}
+
+macro_rules! protocol_json_extractor {
+    ($(#[$meta:meta])* $name:ident, $protocol:expr) => {
+        $(#[$meta])*
+        pub struct $name(pub Value);
+
+        #[async_trait]
+        impl<S> FromRequest<S> for $name
+        where
+            S: Send + Sync,
+        {
+            type Rejection = Response;
+
+            async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+                match Json::<Value>::from_request(req, state).await {
+                    Ok(Json(value)) => Ok(Self(value)),
+                    Err(rejection) => Err(json_rejection_response($protocol, rejection)),
+                }
+            }
+        }
+    };
+}
+
+protocol_json_extractor!(
+    /// Anthropic 协议请求体
+    ClaudeJson,
+    ErrorProtocol::Anthropic
+);
+protocol_json_extractor!(
+    /// OpenAI 协议请求体
+    OpenAIJson,
+    ErrorProtocol::OpenAI
+);
+protocol_json_extractor!(
+    /// Gemini 原生协议请求体
+    GeminiJson,
+    ErrorProtocol::Gemini
+);
+
+/// 在数组字段中逐个元素反序列化，返回第一个失败元素的路径与错误
+fn first_invalid_item<T: DeserializeOwned>(items: Option<&Value>, path: &str) -> Option<InvalidRequest> {
+    items?.as_array()?.iter().enumerate().find_map(|(i, item)| {
+        serde_json::from_value::<T>(item.clone())
+            .err()
+            .map(|e| InvalidRequest::new(Some(format!("{}[{}]", path, i)), e.to_string()))
+    })
+}
+
+/// 定位 messages[i].content[j] 中出错的内容块
+fn first_invalid_content_block<T: DeserializeOwned>(body: &Value) -> Option<InvalidRequest> {
+    let messages = body.get("messages")?.as_array()?;
+    messages.iter().enumerate().find_map(|(i, message)| {
+        let content = message.get("content").filter(|c| c.is_array());
+        first_invalid_item::<T>(content, &format!("messages[{}].content", i))
+    })
+}
+
+/// 反序列化请求体；失败时由 `locate` 尝试定位具体字段，定位不到则返回 serde 原始错误
+fn parse_with_location<T: DeserializeOwned>(
+    body: &Value,
+    locate: impl FnOnce(&Value) -> Option<InvalidRequest>,
+) -> Result<T, InvalidRequest> {
+    serde_json::from_value::<T>(body.clone()).map_err(|e| {
+        if !body.is_object() {
+            return InvalidRequest::new(None, "request body must be a JSON object");
+        }
+        locate(body).unwrap_or_else(|| InvalidRequest::new(None, e.to_string()))
+    })
+}
+
+/// 解析 Anthropic Messages 请求
+pub fn parse_claude_request(body: &Value) -> Result<crate::proxy::mappers::claude::ClaudeRequest, InvalidRequest> {
+    use crate::proxy::mappers::claude::models::{ContentBlock, Message, SystemBlock, Tool};
+    parse_with_location(body, |body| {
+        first_invalid_content_block::<ContentBlock>(body)
+            .or_else(|| first_invalid_item::<Message>(body.get("messages"), "messages"))
+            .or_else(|| first_invalid_item::<SystemBlock>(body.get("system"), "system"))
+            .or_else(|| first_invalid_item::<Tool>(body.get("tools"), "tools"))
+    })
+}
+
+/// 解析 OpenAI Chat / Completions 请求
+pub fn parse_openai_request(body: &Value) -> Result<crate::proxy::mappers::openai::OpenAIRequest, InvalidRequest> {
+    use crate::proxy::mappers::openai::{OpenAIContentBlock, OpenAIMessage};
+    parse_with_location(body, |body| {
+        first_invalid_content_block::<OpenAIContentBlock>(body)
+            .or_else(|| first_invalid_item::<OpenAIMessage>(body.get("messages"), "messages"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_claude_request_pinpoints_content_block() {
+        let body = json!({
+            "model": "claude-sonnet-4-6",
+            "max_tokens": 10,
+            "messages": [
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": [{ "type": "text", "text": "ok" }, { "type": "tool_use", "id": "t1" }] }
+            ]
+        });
+        let err = parse_claude_request(&body).unwrap_err();
+        assert_eq!(err.param.as_deref(), Some("messages[1].content[1]"));
+
+        let body = json!({ "model": "claude-sonnet-4-6", "messages": [{ "content": "hi" }] });
+        let err = parse_claude_request(&body).unwrap_err();
+        assert_eq!(err.param.as_deref(), Some("messages[0]"));
+        assert!(err.message.contains("role"));
+    }
+
+    #[test]
+    fn test_parse_openai_request_error_body() {
+        let body = json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": [{ "type": "video", "video": "x" }] }]
+        });
+        let err = parse_openai_request(&body).unwrap_err();
+        assert_eq!(err.param.as_deref(), Some("messages[0].content[0]"));
+
+        let error_body = err.to_body(ErrorProtocol::OpenAI);
+        assert_eq!(error_body["error"]["type"], "invalid_request_error");
+        assert_eq!(error_body["error"]["param"], "messages[0].content[0]");
+
+        let claude_body = err.to_body(ErrorProtocol::Anthropic);
+        assert!(claude_body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("`messages[0].content[0]`"));
+    }
+
+    #[test]
+    fn test_parse_request_falls_back_to_serde_error() {
+        let err = parse_openai_request(&json!({ "messages": [] })).unwrap_err();
+        assert_eq!(err.param, None);
+        assert!(err.message.contains("model"));
+
+        let err = parse_openai_request(&json!([1, 2])).unwrap_err();
+        assert_eq!(err.message, "request body must be a JSON object");
+    }
+}
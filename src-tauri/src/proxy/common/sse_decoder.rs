@@ -0,0 +1,117 @@
+// SSE 行解码器 (各协议流式路径共用)
+// 按字节缓冲、只在完整的一行上做 UTF-8 解码，避免多字节字符被网络分片截断后变成乱码；
+// 行结束符兼容 \n、\r\n 与单独的 \r (SSE 规范允许)，且 \r\n 跨分片时不会产生多余空行。
+use bytes::BytesMut;
+
+#[derive(Debug, Default)]
+pub struct SseLineDecoder {
+    buffer: BytesMut,
+    /// 上一个分片以 \r 结尾，下一个分片开头的 \n 属于同一个换行
+    pending_cr: bool,
+}
+
+impl SseLineDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输入一个分片，返回其中完整的行 (不含行结束符，可能为空行)
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut chunk = chunk;
+        if self.pending_cr {
+            self.pending_cr = false;
+            if let Some(rest) = chunk.strip_prefix(b"\n") {
+                chunk = rest;
+            }
+        }
+        self.buffer.extend_from_slice(chunk);
+
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n' || b == b'\r') {
+            let is_cr = self.buffer[pos] == b'\r';
+            let line = self.buffer.split_to(pos);
+            let terminator_len = if is_cr && self.buffer.get(1) == Some(&b'\n') { 2 } else { 1 };
+            // \r 恰好位于分片末尾时无法判断后面是否跟着 \n，先记下
+            if is_cr && terminator_len == 1 && self.buffer.len() == 1 {
+                self.pending_cr = true;
+            }
+            let _ = self.buffer.split_to(terminator_len);
+            lines.push(String::from_utf8_lossy(&line).into_owned());
+        }
+        lines
+    }
+
+    /// 流结束时取出最后一行未以换行结尾的数据
+    pub fn finish(&mut self) -> Option<String> {
+        self.pending_cr = false;
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let rest = self.buffer.split();
+        Some(String::from_utf8_lossy(&rest).into_owned())
+    }
+
+    /// 缓冲区中尚未成行的字节数
+    pub fn pending_len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+/// 提取 `data:` 行的内容 (冒号后的单个空格可选)
+pub fn sse_data(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("data:")?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(pieces: &[&[u8]]) -> Vec<String> {
+        let mut decoder = SseLineDecoder::new();
+        let mut lines = Vec::new();
+        for piece in pieces {
+            lines.extend(decoder.push(piece));
+        }
+        lines.extend(decoder.finish());
+        lines
+    }
+
+    #[test]
+    fn test_multibyte_char_split_across_chunks() {
+        let text = "data: {\"text\":\"你好，世界\"}\n".as_bytes();
+        // 在 "你" 的 3 个字节中间切开
+        let split = text.iter().position(|&b| b >= 0x80).unwrap() + 1;
+        let lines = decode_all(&[&text[..split], &text[split..]]);
+        assert_eq!(lines, vec!["data: {\"text\":\"你好，世界\"}"]);
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let lines = decode_all(&[b"data: a\r\n\r\ndata: b\n\ndata: c\r\rdata: d"]);
+        assert_eq!(lines, vec!["data: a", "", "data: b", "", "data: c", "", "data: d"]);
+
+        // \r\n 跨分片不产生额外空行
+        let lines = decode_all(&[b"data: a\r", b"\ndata: b\r", b"\n"]);
+        assert_eq!(lines, vec!["data: a", "data: b"]);
+    }
+
+    #[test]
+    fn test_finish_flushes_partial_line_and_invalid_utf8_is_not_dropped() {
+        let mut decoder = SseLineDecoder::new();
+        assert!(decoder.push(b"data: {\"usage\"").is_empty());
+        assert_eq!(decoder.pending_len(), 15);
+        assert_eq!(decoder.finish().as_deref(), Some("data: {\"usage\""));
+        assert!(decoder.finish().is_none());
+
+        let lines = decode_all(&[b"data: \xff ok\n"]);
+        assert_eq!(lines, vec!["data: \u{fffd} ok"]);
+    }
+
+    #[test]
+    fn test_sse_data() {
+        assert_eq!(sse_data("data: {}"), Some("{}"));
+        assert_eq!(sse_data("data:{}"), Some("{}"));
+        assert_eq!(sse_data("event: ping"), None);
+    }
+}
@@ -0,0 +1,179 @@
+// 系统提示词注入
+// 在模型映射前为 Claude / OpenAI 请求追加统一的系统提示词前缀 / 后缀 (全局或按 API Key)，
+// 便于在不改动各客户端的情况下统一约束输出语言、团队规范等。
+use axum::http::HeaderMap;
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::{SystemPromptInjectionConfig, SystemPromptRule};
+use crate::proxy::mappers::claude::models::{SystemBlock, SystemPrompt};
+use crate::proxy::mappers::claude::ClaudeRequest;
+use crate::proxy::mappers::openai::{OpenAIContent, OpenAIMessage, OpenAIRequest};
+
+fn config() -> &'static RwLock<SystemPromptInjectionConfig> {
+    static CONFIG: OnceLock<RwLock<SystemPromptInjectionConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(SystemPromptInjectionConfig::default()))
+}
+
+/// 更新系统提示词注入配置 (启动与配置热更新时调用)
+pub fn update_system_prompt_injection_config(new_config: SystemPromptInjectionConfig) {
+    if let Ok(mut current) = config().write() {
+        *current = new_config;
+    }
+}
+
+/// 根据请求头中的 API Key 取得生效规则，未启用或规则为空时返回 None
+pub fn rule_for_headers(headers: &HeaderMap) -> Option<SystemPromptRule> {
+    let cfg = config().read().ok()?;
+    if !cfg.enabled {
+        return None;
+    }
+    let api_key = crate::proxy::middleware::client_rate_limit::api_key_from_headers(headers);
+    let rule = cfg.rule_for(api_key.as_deref());
+    (!rule.is_empty()).then(|| rule.clone())
+}
+
+fn non_blank(text: &Option<String>) -> Option<&str> {
+    text.as_deref().filter(|s| !s.trim().is_empty())
+}
+
+fn text_block(text: &str) -> SystemBlock {
+    SystemBlock {
+        block_type: "text".to_string(),
+        text: text.to_string(),
+        cache_control: None,
+    }
+}
+
+/// 注入到 Claude 请求的 system 字段
+pub fn inject_claude(request: &mut ClaudeRequest, rule: &SystemPromptRule) {
+    let prefix = non_blank(&rule.prefix);
+    let suffix = non_blank(&rule.suffix);
+    request.system = Some(match request.system.take() {
+        None => SystemPrompt::String(
+            prefix.into_iter().chain(suffix).collect::<Vec<_>>().join("\n\n"),
+        ),
+        Some(SystemPrompt::String(text)) => SystemPrompt::String(
+            prefix
+                .into_iter()
+                .chain(Some(text.as_str()).filter(|t| !t.is_empty()))
+                .chain(suffix)
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        ),
+        // 以独立块注入，避免破坏客户端设置的 cache_control 断点
+        Some(SystemPrompt::Array(mut blocks)) => {
+            if let Some(prefix) = prefix {
+                blocks.insert(0, text_block(prefix));
+            }
+            if let Some(suffix) = suffix {
+                blocks.push(text_block(suffix));
+            }
+            SystemPrompt::Array(blocks)
+        }
+    });
+}
+
+fn system_message(text: &str) -> OpenAIMessage {
+    OpenAIMessage {
+        role: "system".to_string(),
+        content: Some(OpenAIContent::String(text.to_string())),
+        reasoning_content: None,
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+        audio: None,
+    }
+}
+
+/// 注入到 OpenAI 请求：前缀作为第一条 system 消息，后缀紧跟在开头的 system / developer 消息之后
+pub fn inject_openai(request: &mut OpenAIRequest, rule: &SystemPromptRule) {
+    if let Some(prefix) = non_blank(&rule.prefix) {
+        request.messages.insert(0, system_message(prefix));
+    }
+    if let Some(suffix) = non_blank(&rule.suffix) {
+        let position = request
+            .messages
+            .iter()
+            .take_while(|m| m.role == "system" || m.role == "developer")
+            .count();
+        request.messages.insert(position, system_message(suffix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(prefix: Option<&str>, suffix: Option<&str>) -> SystemPromptRule {
+        SystemPromptRule {
+            prefix: prefix.map(str::to_string),
+            suffix: suffix.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_inject_claude_string_and_blocks() {
+        let mut request: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-6",
+            "system": "You are helpful.",
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .unwrap();
+        inject_claude(&mut request, &rule(Some("House rules."), Some("Answer in Chinese.")));
+        match request.system {
+            Some(SystemPrompt::String(text)) => {
+                assert_eq!(text, "House rules.\n\nYou are helpful.\n\nAnswer in Chinese.")
+            }
+            other => panic!("unexpected system: {:?}", other),
+        }
+
+        let mut request: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-6",
+            "system": [{ "type": "text", "text": "cached", "cache_control": { "type": "ephemeral" } }],
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .unwrap();
+        inject_claude(&mut request, &rule(None, Some("Answer in Chinese.")));
+        match request.system {
+            Some(SystemPrompt::Array(blocks)) => {
+                assert_eq!(blocks.len(), 2);
+                assert!(blocks[0].cache_control.is_some());
+                assert_eq!(blocks[1].text, "Answer in Chinese.");
+            }
+            other => panic!("unexpected system: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_inject_openai_keeps_order() {
+        let mut request: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [
+                { "role": "system", "content": "client system" },
+                { "role": "user", "content": "hi" }
+            ]
+        }))
+        .unwrap();
+        inject_openai(&mut request, &rule(Some("prefix"), Some("suffix")));
+        let roles: Vec<_> = request.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "system", "system", "user"]);
+        match &request.messages[2].content {
+            Some(OpenAIContent::String(text)) => assert_eq!(text, "suffix"),
+            other => panic!("unexpected content: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_per_key_rule_overrides_global() {
+        let mut cfg = SystemPromptInjectionConfig {
+            enabled: true,
+            global: rule(Some("global"), None),
+            ..Default::default()
+        };
+        cfg.per_key.insert("sk-team".to_string(), rule(None, Some("team")));
+        assert_eq!(cfg.rule_for(Some("sk-team")).suffix.as_deref(), Some("team"));
+        assert_eq!(cfg.rule_for(Some("sk-other")).prefix.as_deref(), Some("global"));
+        assert_eq!(cfg.rule_for(None).prefix.as_deref(), Some("global"));
+    }
+}
@@ -8,13 +8,3 @@ pub fn generate_random_id() -> String {
         .map(char::from)
         .collect()
 }
-
-/// 根据模型名称推测功能类型
-// 注意：此函数已弃用，请改用 mappers::common_utils::resolve_request_config
-pub fn _deprecated_infer_quota_group(model: &str) -> String {
-    if model.to_lowercase().starts_with("claude") {
-        "claude".to_string()
-    } else {
-        "gemini".to_string()
-    }
-}
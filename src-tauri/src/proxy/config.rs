@@ -121,6 +121,262 @@ pub fn update_image_thinking_mode(mode: Option<String>) {
     }
 }
 
+/// 模型映射规则的匹配方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelMappingRuleKind {
+    /// `*` 通配符，例如 `claude-3-5-*`
+    #[default]
+    Wildcard,
+    /// 正则表达式 (整串匹配)，目标可引用捕获组 `$1` / `${name}`
+    Regex,
+}
+
+/// 按优先级匹配的模型映射规则 (精确映射之后、系统默认映射之前生效)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelMappingRule {
+    #[serde(default)]
+    pub id: String,
+    pub pattern: String,
+    pub target: String,
+    #[serde(default)]
+    pub kind: ModelMappingRuleKind,
+    /// 数值越大越先匹配，相同优先级按列表顺序
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// 模型 -> 配额组覆盖 (新模型系列上线时无需等待版本更新即可归入正确的配额组)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaGroupOverride {
+    /// 模型名，支持 `*` 通配符，大小写不敏感
+    pub pattern: String,
+    /// 配额组 ID，例如 "gemini-3-flash" / "gemini-3-pro-high" / "gemini-3-pro-image" / "claude"
+    pub group: String,
+}
+
+/// 一组安全过滤阈值 (简写 off/none/low/medium/high 或 Gemini 原始名称)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SafetyRule {
+    /// 所有类别的统一阈值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<String>,
+    /// 按类别覆盖，例如 "harassment" / "HARM_CATEGORY_DANGEROUS_CONTENT" -> "high"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub categories: HashMap<String, String>,
+}
+
+/// Gemini safetySettings 配置 (未设置时沿用 GEMINI_SAFETY_THRESHOLD 环境变量，默认 OFF)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SafetySettingsConfig {
+    #[serde(default)]
+    pub global: SafetyRule,
+    /// 映射后的模型名 (支持通配符) -> 规则，优先于全局
+    #[serde(default)]
+    pub per_model: HashMap<String, SafetyRule>,
+}
+
+/// 超出上下文窗口时的处理方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextTrimMode {
+    /// 不处理，交由上游返回错误
+    #[default]
+    Off,
+    /// 丢弃最早的对话轮次
+    Trim,
+    /// 先尝试将历史压缩为摘要 (仅 Claude 协议)，失败时回退为裁剪
+    Summarize,
+}
+
+/// 单个模型的上下文窗口规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContextTrimRule {
+    #[serde(default)]
+    pub mode: ContextTrimMode,
+    /// 目标上下文窗口 (tokens)，为空时使用模型默认窗口
+    #[serde(default)]
+    pub max_context_tokens: Option<u32>,
+    /// 为模型输出预留的 tokens
+    #[serde(default = "default_context_reserve_tokens")]
+    pub reserve_tokens: u32,
+}
+
+impl Default for ContextTrimRule {
+    fn default() -> Self {
+        Self {
+            mode: ContextTrimMode::Off,
+            max_context_tokens: None,
+            reserve_tokens: default_context_reserve_tokens(),
+        }
+    }
+}
+
+fn default_context_reserve_tokens() -> u32 {
+    16_384
+}
+
+/// 上下文窗口管理 (按映射后的模型配置，默认全部关闭)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ContextTrimConfig {
+    /// 映射后的模型名 (支持通配符) -> 规则
+    #[serde(default)]
+    pub per_model: HashMap<String, ContextTrimRule>,
+}
+
+/// 注入到系统提示词首尾的文本
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SystemPromptRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+}
+
+impl SystemPromptRule {
+    pub fn is_empty(&self) -> bool {
+        let blank = |s: &Option<String>| s.as_deref().unwrap_or("").trim().is_empty();
+        blank(&self.prefix) && blank(&self.suffix)
+    }
+}
+
+/// 系统提示词注入 (Claude / OpenAI 协议，在模型映射前生效)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SystemPromptInjectionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub global: SystemPromptRule,
+    /// 按 API Key 单独设置，命中时替代全局规则
+    #[serde(default)]
+    pub per_key: HashMap<String, SystemPromptRule>,
+}
+
+impl SystemPromptInjectionConfig {
+    /// 获取指定 API Key 生效的规则
+    pub fn rule_for(&self, api_key: Option<&str>) -> &SystemPromptRule {
+        api_key
+            .and_then(|key| self.per_key.get(key))
+            .unwrap_or(&self.global)
+    }
+}
+
+/// 内置的脱敏规则
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionPreset {
+    /// 电子邮箱地址
+    Email,
+    /// 常见 API Key / Token (sk-、AKIA、ghp_、AIza、xox 等前缀)
+    ApiKey,
+    /// `Bearer xxx` 形式的授权凭据
+    BearerToken,
+    /// PEM 私钥块
+    PrivateKey,
+}
+
+/// 自定义正则脱敏规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RedactionRule {
+    /// 规则名 (用于日志统计)
+    pub name: String,
+    /// Rust regex 语法
+    pub pattern: String,
+    /// 替换文本，支持 `$1` 等捕获组引用
+    #[serde(default = "default_redaction_replacement")]
+    pub replacement: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_redaction_replacement() -> String {
+    "[REDACTED]".to_string()
+}
+
+/// 请求内容脱敏 (DLP)：在发往上游前替换提示词与工具结果中的敏感文本
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub presets: Vec<RedactionPreset>,
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+    /// 同时对流量监控中记录的请求 / 响应内容脱敏
+    #[serde(default)]
+    pub redact_logs: bool,
+}
+
+/// 用户配置的 MCP 服务器 (Streamable HTTP)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerConfig {
+    /// 服务器名，用于生成合成工具名 `mcp__<name>__<tool>`
+    pub name: String,
+    pub url: String,
+    /// 附加请求头 (如 Authorization)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// MCP 工具桥接配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpBridgeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 服务端工具模式: 将 MCP 工具注入请求，由代理执行工具调用并把结果回填给模型
+    #[serde(default)]
+    pub server_side_tools: bool,
+    /// 单次请求内最多执行的工具轮次
+    #[serde(default = "default_mcp_max_tool_rounds")]
+    pub max_tool_rounds: u32,
+    #[serde(default = "default_mcp_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub servers: Vec<McpServerConfig>,
+}
+
+impl Default for McpBridgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server_side_tools: false,
+            max_tool_rounds: default_mcp_max_tool_rounds(),
+            timeout_secs: default_mcp_timeout_secs(),
+            servers: Vec::new(),
+        }
+    }
+}
+
+fn default_mcp_max_tool_rounds() -> u32 {
+    8
+}
+
+fn default_mcp_timeout_secs() -> u64 {
+    60
+}
+
+/// 模型绑定的 Gemini 上下文缓存
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContextCacheBinding {
+    /// 上游缓存名，例如 "cachedContents/abc123"
+    pub name: String,
+    /// 创建缓存的账号；缓存只对该账号可见，其他账号处理请求时不注入
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+/// Gemini 上下文缓存 (cachedContent) 配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContextCacheConfig {
+    /// 模型名 (支持通配符，匹配客户端请求的模型或映射后的模型) -> 缓存
+    #[serde(default)]
+    pub bindings: HashMap<String, ContextCacheBinding>,
+}
+
 /// 全局系统提示词配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalSystemPromptConfig {
@@ -287,6 +543,16 @@ pub struct ExperimentalConfig {
     /// 上下文压缩阈值 L3 (Fork + Summary)
     #[serde(default = "default_threshold_l3")]
     pub context_compression_threshold_l3: f32,
+
+    /// [NEW] 上游返回 MALFORMED_FUNCTION_CALL 时追加纠正提示自动重试一次
+    #[serde(default = "default_true")]
+    pub enable_malformed_call_retry: bool,
+
+    /// [NEW] 代理自行注入联网搜索时 (客户端未声明 web_search 工具) 也以
+    /// server_tool_use / web_search_tool_result 块输出来源；声明了该工具的请求始终输出块
+    /// 默认关闭: Cherry Studio 等客户端不识别这两种块，关闭时以 Markdown 引文文本附在正文之后
+    #[serde(default = "default_false")]
+    pub enable_web_search_blocks: bool,
 }
 
 impl Default for ExperimentalConfig {
@@ -299,6 +565,8 @@ impl Default for ExperimentalConfig {
             context_compression_threshold_l1: 0.4,
             context_compression_threshold_l2: 0.55,
             context_compression_threshold_l3: 0.7,
+            enable_malformed_call_retry: true,
+            enable_web_search_blocks: false,
         }
     }
 }
@@ -334,6 +602,19 @@ impl Default for ThinkingBudgetMode {
     }
 }
 
+/// Claude 协议下 Gemini 思维链 (thought parts) 的输出方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaudeThinkingOutput {
+    /// 原生 thinking 块 (默认)
+    #[default]
+    Blocks,
+    /// 完全丢弃思维链 (签名仍会写入缓存供下一轮恢复)
+    Strip,
+    /// 以 <thinking>...</thinking> 标签包裹后作为普通文本输出 (适配不渲染 thinking 块的客户端)
+    Tagged,
+}
+
 /// Thinking Budget 配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThinkingBudgetConfig {
@@ -346,6 +627,12 @@ pub struct ThinkingBudgetConfig {
     /// 思考强度 (仅在 mode=Adaptive 时生效) : low, medium, high
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub effort: Option<String>,
+    /// OpenAI 协议下隐藏思维链 (不输出 reasoning_content)
+    #[serde(default)]
+    pub hide_openai_reasoning: bool,
+    /// Claude 协议下思维链的输出方式
+    #[serde(default)]
+    pub claude_thinking_output: ClaudeThinkingOutput,
 }
 
 impl Default for ThinkingBudgetConfig {
@@ -354,6 +641,8 @@ impl Default for ThinkingBudgetConfig {
             mode: ThinkingBudgetMode::Auto,
             custom_value: default_thinking_budget_custom_value(),
             effort: None,
+            hide_openai_reasoning: false,
+            claude_thinking_output: ClaudeThinkingOutput::Blocks,
         }
     }
 }
@@ -376,6 +665,19 @@ pub struct DebugLoggingConfig {
     pub enabled: bool,
     #[serde(default)]
     pub output_dir: Option<String>,
+    /// 是否保存上游原始 SSE 响应 (便于排查 mapper 问题)
+    #[serde(default = "default_true")]
+    pub capture_raw_upstream: bool,
+    /// 单个原始响应的最大保存字节数，超出部分截断
+    #[serde(default = "default_max_capture_bytes")]
+    pub max_capture_bytes: usize,
+    /// 是否开放 POST /debug/transform (只做请求转换，不调用上游)
+    #[serde(default)]
+    pub transform_endpoint: bool,
+}
+
+fn default_max_capture_bytes() -> usize {
+    2 * 1024 * 1024 // 2MB
 }
 
 impl Default for DebugLoggingConfig {
@@ -383,6 +685,9 @@ impl Default for DebugLoggingConfig {
         Self {
             enabled: false,
             output_dir: None,
+            capture_raw_upstream: true,
+            max_capture_bytes: default_max_capture_bytes(),
+            transform_endpoint: false,
         }
     }
 }
@@ -466,6 +771,11 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub allow_lan_access: bool,
 
+    /// 自定义监听地址 (可选，仅在 allow_lan_access = true 时生效)
+    /// 例如指定某个网卡 IP "192.168.1.10"；留空则监听 0.0.0.0
+    #[serde(default)]
+    pub bind_address: Option<String>,
+
     /// Authorization policy for the proxy.
     /// - off: no auth required
     /// - strict: auth required for all routes
@@ -490,10 +800,32 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub custom_mapping: std::collections::HashMap<String, String>,
 
+    /// [NEW] 托盘切换 Claude 预设前用户自己的 `claude-*` 映射 (空字符串表示原本没有)
+    /// 为 None 表示当前未处于托盘预设；选择"默认"时据此恢复
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_mapping_backup: Option<String>,
+
+    /// 通配符 / 正则映射规则 (按优先级匹配)
+    #[serde(default)]
+    pub model_mapping_rules: Vec<ModelMappingRule>,
+
+    /// 模型 -> 配额组覆盖 (按列表顺序匹配，优先于内置推断)
+    #[serde(default)]
+    pub quota_group_overrides: Vec<QuotaGroupOverride>,
+
     /// API 请求超时时间(秒)
     #[serde(default = "default_request_timeout")]
     pub request_timeout: u64,
 
+    /// 请求体大小上限 (MB)，超出时返回 413
+    /// 可通过环境变量 ABV_MAX_BODY_SIZE (字节) 覆盖
+    #[serde(default = "default_max_body_size_mb")]
+    pub max_body_size_mb: u64,
+
+    /// 重启/改绑端口时等待存量请求 (含流式) 完成的宽限期(秒)
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+
     /// 是否开启请求日志记录 (监控)
     #[serde(default)]
     pub enable_logging: bool,
@@ -536,6 +868,10 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub saved_user_agent: Option<String>,
 
+    /// [NEW] 上报给上游的客户端版本 (x-client-version 与默认 User-Agent 中的版本号)
+    #[serde(default)]
+    pub client_version: ClientVersionConfig,
+
     /// Thinking Budget 配置
     /// 控制如何处理 AI 深度思考时的 Token 预算
     #[serde(default)]
@@ -546,6 +882,10 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub global_system_prompt: GlobalSystemPromptConfig,
 
+    /// [NEW] Gemini 上下文缓存绑定
+    #[serde(default)]
+    pub context_cache: ContextCacheConfig,
+
     /// 图像思维模式配置
     /// - enabled: 保留思维链 (默认)
     /// - disabled: 移除思维链 (画质优先)
@@ -555,10 +895,505 @@ pub struct ProxyConfig {
     /// 代理池配置
     #[serde(default)]
     pub proxy_pool: ProxyPoolConfig,
+
+    /// 本地 HTTPS 配置
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// 浏览器跨域 (CORS) 配置，修改后需重启服务生效
+    #[serde(default)]
+    pub cors: CorsConfig,
+
+    /// 上游 v1internal 端点覆盖与备用端点 (自建中转/镜像)
+    #[serde(default)]
+    pub upstream_endpoints: UpstreamEndpointsConfig,
+
+    /// 上游 HTTP 客户端连接池 / HTTP2 调优
+    #[serde(default)]
+    pub upstream_client: UpstreamClientConfig,
+
+    /// 用量上限 (按账号 / 全局的每日、每月 token 与请求数)
+    #[serde(default)]
+    pub usage_limits: UsageLimitsConfig,
+
+    /// 连续 401/403 后自动停用账号
+    #[serde(default)]
+    pub auto_disable: AutoDisableConfig,
+
+    /// 客户端限流 (按 API Key / 客户端 IP 的令牌桶与并发流上限)
+    #[serde(default)]
+    pub client_rate_limit: ClientRateLimitConfig,
+
+    /// 上游重试策略 (全局重试预算 / 退避抖动)
+    #[serde(default)]
+    pub retry_policy: RetryPolicyConfig,
+
+    /// Gemini 安全过滤阈值 (全局 / 按模型)
+    #[serde(default)]
+    pub safety_settings: SafetySettingsConfig,
+
+    /// MCP 工具桥接 (连接外部 MCP 服务器并以合成工具形式暴露)
+    #[serde(default)]
+    pub mcp_bridge: McpBridgeConfig,
+
+    /// 上下文窗口管理 (超长对话裁剪 / 摘要)
+    #[serde(default)]
+    pub context_trim: ContextTrimConfig,
+
+    /// 系统提示词注入 (全局 / 按 API Key)
+    #[serde(default)]
+    pub system_prompt_injection: SystemPromptInjectionConfig,
+
+    /// 请求内容脱敏规则 (发往上游前 / 可选作用于监控日志)
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+
+    /// 参与反代轮询的账号 ID 子集 (为空表示全部账号，配合多配置档案使用)
+    #[serde(default)]
+    pub account_subset: Vec<String>,
+
+    /// 账号分组与按模型 / API Key 路由
+    #[serde(default)]
+    pub account_groups: AccountGroupsConfig,
 }
 
-/// 上游代理配置
+/// 单个维度的用量上限，None 表示不限制
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UsageLimit {
+    #[serde(default)]
+    pub daily_tokens: Option<u64>,
+    #[serde(default)]
+    pub daily_requests: Option<u64>,
+    #[serde(default)]
+    pub monthly_tokens: Option<u64>,
+    #[serde(default)]
+    pub monthly_requests: Option<u64>,
+}
+
+impl UsageLimit {
+    /// 返回第一个被突破的上限描述，未超限时返回 None
+    pub fn exceeded_by(
+        &self,
+        daily_tokens: u64,
+        daily_requests: u64,
+        monthly_tokens: u64,
+        monthly_requests: u64,
+    ) -> Option<String> {
+        let checks = [
+            ("daily tokens", self.daily_tokens, daily_tokens),
+            ("daily requests", self.daily_requests, daily_requests),
+            ("monthly tokens", self.monthly_tokens, monthly_tokens),
+            ("monthly requests", self.monthly_requests, monthly_requests),
+        ];
+        checks.iter().find_map(|(name, limit, used)| match limit {
+            Some(limit) if used >= limit => Some(format!("{} {}/{}", name, used, limit)),
+            _ => None,
+        })
+    }
+}
+
+/// 用量上限配置 (基于 token_stats 的每日用量统计，窗口按本地日期/月份重置)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UsageLimitsConfig {
+    /// 是否启用用量上限
+    #[serde(default)]
+    pub enabled: bool,
+    /// 所有账号合计的上限，超限后代理直接返回 429
+    #[serde(default)]
+    pub global: UsageLimit,
+    /// 每个账号默认上限
+    #[serde(default)]
+    pub per_account_default: UsageLimit,
+    /// 按账号邮箱单独覆盖的上限
+    #[serde(default)]
+    pub per_account: HashMap<String, UsageLimit>,
+}
+
+impl UsageLimitsConfig {
+    /// 获取指定账号生效的上限
+    pub fn limit_for(&self, email: &str) -> &UsageLimit {
+        self.per_account.get(email).unwrap_or(&self.per_account_default)
+    }
+}
+
+fn default_auto_disable_threshold() -> u32 {
+    3
+}
+
+/// 自动停用永久失效账号 (连续 401/403，且已强制刷新过 access_token)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AutoDisableConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 连续鉴权失败次数阈值
+    #[serde(default = "default_auto_disable_threshold")]
+    pub consecutive_auth_failures: u32,
+}
+
+impl Default for AutoDisableConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            consecutive_auth_failures: default_auto_disable_threshold(),
+        }
+    }
+}
+
+/// 账号分组 (成员可填账号 ID 或邮箱)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AccountGroup {
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// 模型 -> 分组路由规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountGroupRoute {
+    /// 映射后的模型名，支持通配符 (如 "gemini-*-flash*", "claude-*")
+    pub pattern: String,
+    pub groups: Vec<String>,
+}
+
+/// 账号分组与按分组路由
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AccountGroupsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 分组名 (如 "pro-quota" / "flash-only" / "personal") -> 成员
+    #[serde(default)]
+    pub groups: HashMap<String, AccountGroup>,
+    /// 按列表顺序匹配，命中第一条即生效
+    #[serde(default)]
+    pub model_routes: Vec<AccountGroupRoute>,
+    /// 按 API Key 指定分组，优先于模型路由
+    #[serde(default)]
+    pub key_routes: HashMap<String, Vec<String>>,
+    /// 指定分组内没有可用账号时回退到全部账号 (默认直接报错)
+    #[serde(default)]
+    pub fallback_to_all: bool,
+}
+
+impl AccountGroupsConfig {
+    /// 获取请求应使用的分组，None 表示不限制
+    pub fn groups_for(&self, model: &str, api_key: Option<&str>) -> Option<&[String]> {
+        if !self.enabled {
+            return None;
+        }
+        if let Some(groups) = api_key.and_then(|key| self.key_routes.get(key)) {
+            return Some(groups);
+        }
+        self.model_routes
+            .iter()
+            .find(|route| crate::proxy::common::model_mapping::wildcard_match(&route.pattern, model))
+            .map(|route| route.groups.as_slice())
+    }
+
+    /// 账号是否属于任一分组
+    pub fn contains(&self, groups: &[String], account_id: &str, email: &str) -> bool {
+        groups
+            .iter()
+            .filter_map(|name| self.groups.get(name))
+            .any(|group| {
+                group
+                    .accounts
+                    .iter()
+                    .any(|member| member == account_id || member.eq_ignore_ascii_case(email))
+            })
+    }
+
+    /// 校验路由引用的分组均已定义
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = self.groups.keys().find(|name| name.trim().is_empty()) {
+            return Err(format!("Invalid account group name: '{}'", name));
+        }
+        let check = |groups: &[String], context: &str| {
+            match groups.iter().find(|g| !self.groups.contains_key(*g)) {
+                Some(missing) => Err(format!("{} references unknown account group '{}'", context, missing)),
+                None if groups.is_empty() => Err(format!("{} has no account groups", context)),
+                None => Ok(()),
+            }
+        };
+        for route in &self.model_routes {
+            if route.pattern.trim().is_empty() {
+                return Err("Account group route pattern must not be empty".to_string());
+            }
+            check(&route.groups, &format!("Model route '{}'", route.pattern))?;
+        }
+        for groups in self.key_routes.values() {
+            check(groups, "API key route")?;
+        }
+        Ok(())
+    }
+}
+
+/// 单个维度的客户端限流规则，None 表示不限制
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClientRateLimitRule {
+    /// 每分钟请求数 (令牌桶容量，按秒平滑补充)
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// 同时进行中的请求数 (流式响应在输出结束前持续占用)
+    #[serde(default)]
+    pub max_concurrent_streams: Option<u32>,
+}
+
+/// 客户端限流配置，超限时返回 429 + Retry-After
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClientRateLimitConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 所有客户端合计
+    #[serde(default)]
+    pub global: ClientRateLimitRule,
+    /// 每个 API Key 默认规则
+    #[serde(default)]
+    pub per_key_default: ClientRateLimitRule,
+    /// 按 API Key 单独覆盖
+    #[serde(default)]
+    pub per_key: HashMap<String, ClientRateLimitRule>,
+    /// 每个客户端 IP 默认规则
+    #[serde(default)]
+    pub per_ip_default: ClientRateLimitRule,
+    /// 按客户端 IP 单独覆盖
+    #[serde(default)]
+    pub per_ip: HashMap<String, ClientRateLimitRule>,
+    /// 可信反向代理 IP：仅当 TCP 对端属于其中时才采用 X-Forwarded-For / X-Real-IP，
+    /// 否则按连接对端地址限流，防止客户端伪造转发头绕过限制
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+}
+
+impl ClientRateLimitConfig {
+    pub fn rule_for_key(&self, key: &str) -> &ClientRateLimitRule {
+        self.per_key.get(key).unwrap_or(&self.per_key_default)
+    }
+
+    pub fn rule_for_ip(&self, ip: &str) -> &ClientRateLimitRule {
+        self.per_ip.get(ip).unwrap_or(&self.per_ip_default)
+    }
+
+    pub fn is_trusted_proxy(&self, ip: &str) -> bool {
+        self.trusted_proxies.iter().any(|p| p.trim() == ip)
+    }
+}
+
+/// 上游重试策略调优参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicyConfig {
+    /// 全局每秒允许的重试次数 (所有协议、所有账号共享，0 = 不限制)
+    #[serde(default = "default_retry_budget_per_second")]
+    pub budget_per_second: u32,
+    /// 为线性 / 指数退避加入 ±20% 抖动 (默认关闭：抖动曾导致连接不稳定)
+    #[serde(default)]
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicyConfig {
+    fn default() -> Self {
+        Self {
+            budget_per_second: default_retry_budget_per_second(),
+            jitter: false,
+        }
+    }
+}
+
+fn default_retry_budget_per_second() -> u32 {
+    10
+}
+
+/// 上游 HTTP 客户端调优参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UpstreamClientConfig {
+    /// 每个主机保留的最大空闲连接数
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    /// 空闲连接保留时间 (秒)
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+    /// TCP keepalive 探测间隔 (秒)
+    #[serde(default = "default_tcp_keepalive_secs")]
+    pub tcp_keepalive_secs: u64,
+    /// 建立连接超时 (秒)
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// 直接以 HTTP/2 发起请求 (prior knowledge，不做协议协商)
+    #[serde(default)]
+    pub http2_prior_knowledge: bool,
+    /// [NEW] 向上游请求 gzip / br 压缩响应并自动解压 (部分中转代理会损坏压缩的 SSE，可关闭)
+    #[serde(default = "default_true")]
+    pub accept_compression: bool,
+    /// [NEW] 首字节超时 (秒)：发出请求后等待响应头的最长时间，超时视为网络错误并尝试下一个端点 (0 = 不限制)
+    #[serde(default = "default_first_byte_timeout_secs")]
+    pub first_byte_timeout_secs: u64,
+    /// [NEW] 流空闲超时 (秒)：流式响应连续这么久没有新数据块即中断并返回可重试的超时错误 (0 = 不限制)
+    #[serde(default = "default_stream_idle_timeout_secs")]
+    pub stream_idle_timeout_secs: u64,
+    /// [NEW] 单次请求总超时 (秒)，兜底非流式响应体读取 (0 = 不限制)
+    #[serde(default = "default_request_total_timeout_secs")]
+    pub total_timeout_secs: u64,
+}
+
+impl Default for UpstreamClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+            tcp_keepalive_secs: default_tcp_keepalive_secs(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            http2_prior_knowledge: false,
+            accept_compression: true,
+            first_byte_timeout_secs: default_first_byte_timeout_secs(),
+            stream_idle_timeout_secs: default_stream_idle_timeout_secs(),
+            total_timeout_secs: default_request_total_timeout_secs(),
+        }
+    }
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    16
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_tcp_keepalive_secs() -> u64 {
+    60
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    20
+}
+
+fn default_first_byte_timeout_secs() -> u64 {
+    180
+}
+
+fn default_stream_idle_timeout_secs() -> u64 {
+    120
+}
+
+fn default_request_total_timeout_secs() -> u64 {
+    600
+}
+
+/// 客户端版本来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientVersionMode {
+    /// max(本地安装版本, 远程最新版本, 内置稳定版本)
+    #[default]
+    Auto,
+    /// 跟随本地安装的 Antigravity 版本 (探测失败时回退到 Auto)
+    Installed,
+    /// 固定为 `version` 指定的版本
+    Fixed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClientVersionConfig {
+    #[serde(default)]
+    pub mode: ClientVersionMode,
+    /// Fixed 模式下使用的版本号 (X.Y.Z)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// 上游 v1internal 端点配置
+/// 地址需包含完整路径，例如 `https://relay.example.com/v1internal`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UpstreamEndpointsConfig {
+    /// 主端点，为空时使用内置端点 (Sandbox → Daily → Prod)
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// 主端点不可用时按顺序尝试的备用端点
+    #[serde(default)]
+    pub fallback_urls: Vec<String>,
+}
+
+impl UpstreamEndpointsConfig {
+    /// 按尝试顺序解析出最终端点列表 (去空白、去尾部斜杠、去重)
+    pub fn resolve(&self, defaults: &[&str]) -> Vec<String> {
+        let primary: Vec<String> = match self.base_url.as_deref().map(str::trim) {
+            Some(url) if !url.is_empty() => vec![url.to_string()],
+            _ => defaults.iter().map(|u| u.to_string()).collect(),
+        };
+
+        let mut endpoints: Vec<String> = Vec::new();
+        for url in primary.into_iter().chain(self.fallback_urls.iter().cloned()) {
+            let url = url.trim().trim_end_matches('/').to_string();
+            if !url.is_empty() && !endpoints.contains(&url) {
+                endpoints.push(url);
+            }
+        }
+        endpoints
+    }
+}
+
+/// 跨域配置
+/// 列表为空时使用宽松默认值 (任意来源、镜像请求头)，便于网页 Playground / 浏览器扩展直接调用本地代理
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CorsConfig {
+    /// 允许的来源，支持 `*` 通配 (如 `chrome-extension://*`)；为空或包含 `*` 表示任意来源
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// 允许的请求方法，为空时使用默认列表
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    /// 允许的请求头，为空时镜像预检请求的 Access-Control-Request-Headers
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    /// 额外暴露给浏览器的响应头 (x-agm-request-id 始终暴露)
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    /// 预检结果缓存时间 (秒)
+    #[serde(default = "default_cors_max_age_secs")]
+    pub max_age_secs: u64,
+    /// 是否允许携带凭据 (Cookie)；开启后来源与请求头改为镜像请求值
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// 响应 Chrome 私有网络访问预检 (公网页面访问 localhost 时需要)
+    #[serde(default = "default_true")]
+    pub allow_private_network: bool,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: Vec::new(),
+            allowed_headers: Vec::new(),
+            expose_headers: Vec::new(),
+            max_age_secs: default_cors_max_age_secs(),
+            allow_credentials: false,
+            allow_private_network: true,
+        }
+    }
+}
+
+fn default_cors_max_age_secs() -> u64 {
+    3600
+}
+
+/// 本地 HTTPS 配置
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TlsConfig {
+    /// 是否启用 HTTPS
+    #[serde(default)]
+    pub enabled: bool,
+    /// 证书路径 (PEM)，与 key_path 均为空时自动生成自签名证书
+    #[serde(default)]
+    pub cert_path: Option<String>,
+    /// 私钥路径 (PEM)
+    #[serde(default)]
+    pub key_path: Option<String>,
+}
+
+/// 上游代理配置
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct UpstreamProxyConfig {
     /// 是否启用
     pub enabled: bool,
@@ -571,13 +1406,19 @@ impl Default for ProxyConfig {
         Self {
             enabled: false,
             allow_lan_access: false, // 默认仅本机访问，隐私优先
+            bind_address: None,
             auth_mode: ProxyAuthMode::default(),
             port: 8045,
             api_key: format!("sk-{}", uuid::Uuid::new_v4().simple()),
             admin_password: None,
             auto_start: false,
             custom_mapping: std::collections::HashMap::new(),
+            tray_mapping_backup: None,
+            model_mapping_rules: Vec::new(),
+            quota_group_overrides: Vec::new(),
             request_timeout: default_request_timeout(),
+            max_body_size_mb: default_max_body_size_mb(),
+            shutdown_grace_secs: default_shutdown_grace_secs(),
             enable_logging: true, // 默认开启，支持 token 统计功能
             debug_logging: DebugLoggingConfig::default(),
             upstream_proxy: UpstreamProxyConfig::default(),
@@ -588,10 +1429,27 @@ impl Default for ProxyConfig {
             preferred_account_id: None, // 默认使用轮询模式
             user_agent_override: None,
             saved_user_agent: None,
+            client_version: ClientVersionConfig::default(),
             thinking_budget: ThinkingBudgetConfig::default(),
             global_system_prompt: GlobalSystemPromptConfig::default(),
+            context_cache: ContextCacheConfig::default(),
             proxy_pool: ProxyPoolConfig::default(),
             image_thinking_mode: None,
+            tls: TlsConfig::default(),
+            cors: CorsConfig::default(),
+            upstream_endpoints: UpstreamEndpointsConfig::default(),
+            upstream_client: UpstreamClientConfig::default(),
+            usage_limits: UsageLimitsConfig::default(),
+            auto_disable: AutoDisableConfig::default(),
+            client_rate_limit: ClientRateLimitConfig::default(),
+            retry_policy: RetryPolicyConfig::default(),
+            safety_settings: SafetySettingsConfig::default(),
+            mcp_bridge: McpBridgeConfig::default(),
+            context_trim: ContextTrimConfig::default(),
+            system_prompt_injection: SystemPromptInjectionConfig::default(),
+            redaction: RedactionConfig::default(),
+            account_subset: Vec::new(),
+            account_groups: AccountGroupsConfig::default(),
         }
     }
 }
@@ -600,6 +1458,14 @@ fn default_request_timeout() -> u64 {
     120 // 默认 120 秒,原来 60 秒太短
 }
 
+fn default_max_body_size_mb() -> u64 {
+    crate::proxy::middleware::body_limit::DEFAULT_MAX_BODY_SIZE_MB
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    10
+}
+
 fn default_zai_base_url() -> String {
     "https://api.z.ai/api/anthropic".to_string()
 }
@@ -618,15 +1484,28 @@ fn default_zai_haiku_model() -> String {
 
 impl ProxyConfig {
     /// 获取实际的监听地址
-    /// - allow_lan_access = false: 返回 "127.0.0.1"（默认，隐私优先）
-    /// - allow_lan_access = true: 返回 "0.0.0.0"（允许局域网访问）
+    /// - allow_lan_access = false: 返回 "127.0.0.1"（默认，隐私优先，忽略 bind_address）
+    /// - allow_lan_access = true: 返回 bind_address，未设置时返回 "0.0.0.0"（允许局域网访问）
     pub fn get_bind_address(&self) -> &str {
         if self.allow_lan_access {
-            "0.0.0.0"
+            self.bind_address
+                .as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .unwrap_or("0.0.0.0")
         } else {
             "127.0.0.1"
         }
     }
+
+    /// 获取对外访问协议 (启用 TLS 时为 https)
+    pub fn get_scheme(&self) -> &'static str {
+        if self.tls.enabled {
+            "https"
+        } else {
+            "http"
+        }
+    }
 }
 
 /// 代理认证信息
@@ -721,4 +1600,98 @@ mod tests {
         assert_eq!(normalize_proxy_url(""), "");
         assert_eq!(normalize_proxy_url("   "), "");
     }
+    #[test]
+    fn test_bind_address_resolution() {
+        let mut config = ProxyConfig::default();
+        config.bind_address = Some("192.168.1.10".to_string());
+        // 未开启局域网访问时始终仅监听本机
+        assert_eq!(config.get_bind_address(), "127.0.0.1");
+
+        config.allow_lan_access = true;
+        assert_eq!(config.get_bind_address(), "192.168.1.10");
+
+        config.bind_address = Some("  ".to_string());
+        assert_eq!(config.get_bind_address(), "0.0.0.0");
+    }
+
+    #[test]
+    fn test_usage_limit_exceeded_by() {
+        let mut config = UsageLimitsConfig::default();
+        config.per_account_default.daily_requests = Some(100);
+        config.per_account.insert(
+            "vip@x.com".to_string(),
+            UsageLimit {
+                monthly_tokens: Some(1_000),
+                ..Default::default()
+            },
+        );
+
+        let default_limit = config.limit_for("a@x.com");
+        assert!(default_limit.exceeded_by(0, 99, 0, 0).is_none());
+        assert_eq!(
+            default_limit.exceeded_by(0, 100, 0, 0).as_deref(),
+            Some("daily requests 100/100")
+        );
+
+        // 单独覆盖的账号不再继承默认上限
+        let vip = config.limit_for("vip@x.com");
+        assert!(vip.exceeded_by(0, 500, 999, 500).is_none());
+        assert!(vip.exceeded_by(0, 0, 1_000, 0).is_some());
+    }
+
+    #[test]
+    fn test_account_groups_validate() {
+        let mut config = AccountGroupsConfig {
+            enabled: true,
+            model_routes: vec![AccountGroupRoute {
+                pattern: "claude-*".to_string(),
+                groups: vec!["pro-quota".to_string()],
+            }],
+            ..Default::default()
+        };
+        assert!(config.validate().unwrap_err().contains("pro-quota"));
+
+        config.groups.insert("pro-quota".to_string(), AccountGroup::default());
+        assert!(config.validate().is_ok());
+
+        config.key_routes.insert("sk-a".to_string(), Vec::new());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_upstream_endpoints_resolve() {
+        let defaults = ["https://a/v1internal", "https://b/v1internal"];
+
+        // 未配置时使用内置端点
+        let config = UpstreamEndpointsConfig::default();
+        assert_eq!(config.resolve(&defaults), vec!["https://a/v1internal", "https://b/v1internal"]);
+
+        // 主端点覆盖内置端点，备用端点按顺序追加并去重
+        let config = UpstreamEndpointsConfig {
+            base_url: Some(" https://relay.example.com/v1internal/ ".to_string()),
+            fallback_urls: vec![
+                "https://mirror.example.com/v1internal".to_string(),
+                "".to_string(),
+                "https://relay.example.com/v1internal".to_string(),
+            ],
+        };
+        assert_eq!(
+            config.resolve(&defaults),
+            vec![
+                "https://relay.example.com/v1internal",
+                "https://mirror.example.com/v1internal"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_upstream_client_timeouts_default_for_legacy_config() {
+        let config: UpstreamClientConfig =
+            serde_json::from_str(r#"{"connect_timeout_secs": 10}"#).unwrap();
+        assert_eq!(config.connect_timeout_secs, 10);
+        assert_eq!(config.first_byte_timeout_secs, default_first_byte_timeout_secs());
+        assert_eq!(config.stream_idle_timeout_secs, default_stream_idle_timeout_secs());
+        assert_eq!(config.total_timeout_secs, 600);
+        assert!(config.accept_compression);
+    }
 }
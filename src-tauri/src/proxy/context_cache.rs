@@ -0,0 +1,434 @@
+// Gemini 上下文缓存 (cachedContent) 映射与管理
+// 1. Claude 客户端通过 cache_control 标记可复用的前缀 (通常是超长系统提示词)，
+//    若该前缀已有对应的 cachedContent，请求中改为引用缓存，避免每轮重复发送。
+// 2. 用户可在上游创建 / 列出 / 删除 cachedContent，并在配置中把模型绑定到某个缓存。
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::ContextCacheBinding;
+
+const CACHED_CONTENTS_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+/// 默认缓存有效期
+const DEFAULT_TTL_SECONDS: u64 = 3600;
+
+/// 已知的 cachedContent (由缓存指纹索引)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedContentRef {
+    /// 上游资源名，例如 "cachedContents/abc123"
+    pub name: String,
+    /// 缓存绑定的模型，cachedContent 只能用于创建时的模型
+    pub model: String,
+    /// 过期时间 (Unix 秒)，None 表示未知
+    #[serde(default)]
+    pub expire_time: Option<i64>,
+    /// 创建缓存的账号，缓存对其他账号不可见
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+impl CachedContentRef {
+    fn is_usable_for(&self, model: &str, now: i64) -> bool {
+        let model_matches = self.model.is_empty()
+            || self.model.trim_start_matches("models/") == model.trim_start_matches("models/");
+        let not_expired = self.expire_time.map_or(true, |t| t > now);
+        model_matches && not_expired
+    }
+}
+
+fn registry() -> &'static DashMap<String, CachedContentRef> {
+    static REGISTRY: OnceLock<DashMap<String, CachedContentRef>> = OnceLock::new();
+    REGISTRY.get_or_init(DashMap::new)
+}
+
+/// 缓存内容指纹 (SHA-256 前 16 位)
+pub fn fingerprint(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
+
+pub fn register(fingerprint: &str, cached: CachedContentRef) {
+    tracing::debug!(
+        "[ContextCache] Registered {} for fingerprint {} (model: {})",
+        cached.name,
+        fingerprint,
+        cached.model
+    );
+    registry().insert(fingerprint.to_string(), cached);
+}
+
+/// 查找可用于指定模型与账号的 cachedContent，过期条目顺带清理
+pub fn lookup(fingerprint: &str, model: &str, account_id: Option<&str>) -> Option<String> {
+    let now = chrono::Utc::now().timestamp();
+    let entry = registry().get(fingerprint)?.clone();
+    if entry.expire_time.is_some_and(|t| t <= now) {
+        registry().remove(fingerprint);
+        return None;
+    }
+    let account_matches = entry.account_id.is_none() || entry.account_id.as_deref() == account_id;
+    (account_matches && entry.is_usable_for(model, now)).then_some(entry.name)
+}
+
+/// 上游缓存被删除后同步移除映射
+pub fn remove_by_name(name: &str) {
+    registry().retain(|_, v| v.name != name);
+}
+
+// ===== 模型绑定 (ProxyConfig.context_cache.bindings) =====
+
+fn bindings() -> &'static RwLock<HashMap<String, ContextCacheBinding>> {
+    static BINDINGS: OnceLock<RwLock<HashMap<String, ContextCacheBinding>>> = OnceLock::new();
+    BINDINGS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// 更新模型绑定 (启动与配置热更新时调用)
+pub fn update_bindings(new_bindings: HashMap<String, ContextCacheBinding>) {
+    if let Ok(mut current) = bindings().write() {
+        *current = new_bindings;
+    }
+}
+
+/// 按模型查找绑定的缓存：精确匹配优先，其次通配符
+fn binding_for(models: &[&str], account_id: Option<&str>) -> Option<String> {
+    let current = bindings().read().ok()?;
+    let usable = |b: &ContextCacheBinding| b.account_id.is_none() || b.account_id.as_deref() == account_id;
+
+    for model in models {
+        if let Some(binding) = current.get(*model).filter(|b| usable(b)) {
+            return Some(binding.name.clone());
+        }
+    }
+    for model in models {
+        let matched = current.iter().find(|(pattern, binding)| {
+            pattern.contains('*')
+                && crate::proxy::common::model_mapping::wildcard_match(pattern, model)
+                && usable(binding)
+        });
+        if let Some((_, binding)) = matched {
+            return Some(binding.name.clone());
+        }
+    }
+    None
+}
+
+/// 为 Gemini 请求注入 cachedContent，返回使用的缓存名
+/// 优先使用按模型绑定的缓存，其次按 cache_control 前缀指纹匹配。
+/// 上游不允许 cachedContent 与 systemInstruction / tools / toolConfig 同时出现，这些内容需已包含在缓存中。
+/// 指纹缓存只含系统提示词，因此携带工具的请求不走指纹匹配。
+pub fn apply_to_request(
+    inner_request: &mut Value,
+    request_model: &str,
+    upstream_model: &str,
+    account_id: Option<&str>,
+    cacheable_prefix: Option<&str>,
+) -> Option<String> {
+    let has_tools = inner_request
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .is_some_and(|t| !t.is_empty());
+    let name = binding_for(&[request_model, upstream_model], account_id).or_else(|| {
+        cacheable_prefix
+            .filter(|_| !has_tools)
+            .and_then(|text| lookup(&fingerprint(text), upstream_model, account_id))
+    })?;
+
+    let obj = inner_request.as_object_mut()?;
+    obj.remove("systemInstruction");
+    if obj.remove("tools").is_some() {
+        tracing::warn!("[ContextCache] Dropped request tools in favor of cachedContent {}", name);
+    }
+    obj.remove("toolConfig");
+    obj.insert("cachedContent".to_string(), json!(name));
+    tracing::debug!("[ContextCache] Using cachedContent {} for model {}", name, upstream_model);
+    Some(name)
+}
+
+// ===== 上游 cachedContents 管理 =====
+
+/// 上游 cachedContent 摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedContentInfo {
+    pub name: String,
+    pub model: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub create_time: Option<String>,
+    #[serde(default)]
+    pub expire_time: Option<String>,
+    #[serde(default)]
+    pub total_token_count: Option<u64>,
+}
+
+impl CachedContentInfo {
+    fn from_upstream(value: &Value) -> Option<Self> {
+        let str_field = |key: &str| value.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+        Some(Self {
+            name: str_field("name")?,
+            model: str_field("model").unwrap_or_default(),
+            display_name: str_field("displayName"),
+            create_time: str_field("createTime"),
+            expire_time: str_field("expireTime"),
+            total_token_count: value
+                .get("usageMetadata")
+                .and_then(|u| u.get("totalTokenCount"))
+                .and_then(|v| v.as_u64()),
+        })
+    }
+
+    fn expire_timestamp(&self) -> Option<i64> {
+        self.expire_time
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp())
+    }
+}
+
+/// 创建缓存的参数
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCachedContentRequest {
+    /// 创建缓存使用的账号，缺省为当前账号
+    #[serde(default)]
+    pub account_id: Option<String>,
+    pub model: String,
+    #[serde(default)]
+    pub system_instruction: Option<String>,
+    /// 额外缓存的 Gemini contents (原样透传)
+    #[serde(default)]
+    pub contents: Option<Value>,
+    /// Gemini tools (原样透传)，使用缓存的请求不能再携带 tools
+    #[serde(default)]
+    pub tools: Option<Value>,
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+fn normalize_model_name(model: &str) -> String {
+    if model.starts_with("models/") {
+        model.to_string()
+    } else {
+        format!("models/{}", model)
+    }
+}
+
+fn build_create_body(req: &CreateCachedContentRequest) -> Result<Value, String> {
+    let system = req.system_instruction.as_deref().filter(|s| !s.trim().is_empty());
+    if system.is_none() && req.contents.is_none() {
+        return Err("Either system_instruction or contents is required".to_string());
+    }
+
+    let mut body = json!({
+        "model": normalize_model_name(&req.model),
+        "ttl": format!("{}s", req.ttl_seconds.unwrap_or(DEFAULT_TTL_SECONDS).max(60)),
+    });
+    if let Some(text) = system {
+        body["systemInstruction"] = json!({ "parts": [{ "text": text }] });
+    }
+    if let Some(contents) = &req.contents {
+        body["contents"] = contents.clone();
+    }
+    if let Some(tools) = &req.tools {
+        body["tools"] = tools.clone();
+    }
+    if let Some(name) = req.display_name.as_deref().filter(|s| !s.is_empty()) {
+        body["displayName"] = json!(name);
+    }
+    Ok(body)
+}
+
+/// 解析管理操作使用的账号 (缺省为当前账号)，返回 (account_id, access_token)
+pub async fn resolve_account_token(account_id: Option<&str>) -> Result<(String, String), String> {
+    let account = match account_id.filter(|id| !id.is_empty()) {
+        Some(id) => crate::modules::account::load_account(id)?,
+        None => crate::modules::account::get_current_account()?
+            .ok_or_else(|| "No current account selected".to_string())?,
+    };
+    let (access_token, _project_id) = crate::modules::quota::get_valid_token_for_warmup(&account).await?;
+    Ok((account.id, access_token))
+}
+
+async fn read_upstream_json(resp: rquest::Response) -> Result<Value, String> {
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("Upstream returned {}: {}", status, text));
+    }
+    if text.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(&text).map_err(|e| format!("Failed to parse response: {}", e))
+}
+
+/// 在上游创建 cachedContent，系统提示词同时登记指纹以便 cache_control 请求自动复用
+pub async fn create_cached_content(req: &CreateCachedContentRequest) -> Result<CachedContentInfo, String> {
+    let body = build_create_body(req)?;
+    let (account_id, access_token) = resolve_account_token(req.account_id.as_deref()).await?;
+    let resp = crate::utils::http::get_standard_client()
+        .post(format!("{}/cachedContents", CACHED_CONTENTS_BASE_URL))
+        .header(rquest::header::AUTHORIZATION, format!("Bearer {}", access_token))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Create cachedContent request failed: {}", e))?;
+    let value = read_upstream_json(resp).await?;
+    let info = CachedContentInfo::from_upstream(&value)
+        .ok_or_else(|| "Upstream response missing cachedContent name".to_string())?;
+
+    if let Some(text) = req.system_instruction.as_deref().filter(|s| !s.trim().is_empty()) {
+        register(
+            &fingerprint(text),
+            CachedContentRef {
+                name: info.name.clone(),
+                model: info.model.clone(),
+                expire_time: info.expire_timestamp(),
+                account_id: Some(account_id),
+            },
+        );
+    }
+    Ok(info)
+}
+
+pub async fn list_cached_contents(account_id: Option<&str>) -> Result<Vec<CachedContentInfo>, String> {
+    let (_, access_token) = resolve_account_token(account_id).await?;
+    let mut result = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut url = url::Url::parse(&format!("{}/cachedContents", CACHED_CONTENTS_BASE_URL))
+            .map_err(|e| format!("Invalid cachedContents url: {}", e))?;
+        url.query_pairs_mut().append_pair("pageSize", "100");
+        if let Some(token) = &page_token {
+            url.query_pairs_mut().append_pair("pageToken", token);
+        }
+        let resp = crate::utils::http::get_standard_client()
+            .get(url.as_str())
+            .header(rquest::header::AUTHORIZATION, format!("Bearer {}", access_token))
+            .send()
+            .await
+            .map_err(|e| format!("List cachedContents request failed: {}", e))?;
+        let value = read_upstream_json(resp).await?;
+        if let Some(items) = value.get("cachedContents").and_then(|v| v.as_array()) {
+            result.extend(items.iter().filter_map(CachedContentInfo::from_upstream));
+        }
+        page_token = value
+            .get("nextPageToken")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+        if page_token.is_none() {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+/// 删除上游缓存 (name 形如 "cachedContents/abc123"，也接受不带前缀的 id)
+pub async fn delete_cached_content(account_id: Option<&str>, name: &str) -> Result<(), String> {
+    let name = if name.starts_with("cachedContents/") {
+        name.to_string()
+    } else {
+        format!("cachedContents/{}", name)
+    };
+    if name.contains("..") || name.matches('/').count() != 1 {
+        return Err(format!("Invalid cachedContent name: {}", name));
+    }
+    let (_, access_token) = resolve_account_token(account_id).await?;
+    let resp = crate::utils::http::get_standard_client()
+        .delete(format!("{}/{}", CACHED_CONTENTS_BASE_URL, name))
+        .header(rquest::header::AUTHORIZATION, format!("Bearer {}", access_token))
+        .send()
+        .await
+        .map_err(|e| format!("Delete cachedContent request failed: {}", e))?;
+    read_upstream_json(resp).await?;
+    remove_by_name(&name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_respects_model_and_expiry() {
+        let fp = fingerprint("a very long static system prompt");
+        register(
+            &fp,
+            CachedContentRef {
+                name: "cachedContents/abc".to_string(),
+                model: "models/gemini-2.5-flash".to_string(),
+                expire_time: Some(chrono::Utc::now().timestamp() + 600),
+                account_id: None,
+            },
+        );
+        assert_eq!(lookup(&fp, "gemini-2.5-flash", None).as_deref(), Some("cachedContents/abc"));
+        assert!(lookup(&fp, "gemini-2.5-pro", None).is_none());
+
+        let expired = fingerprint("expired prompt");
+        register(
+            &expired,
+            CachedContentRef {
+                name: "cachedContents/old".to_string(),
+                model: String::new(),
+                expire_time: Some(0),
+                account_id: None,
+            },
+        );
+        assert!(lookup(&expired, "gemini-2.5-flash", None).is_none());
+
+        remove_by_name("cachedContents/abc");
+        assert!(lookup(&fp, "gemini-2.5-flash", None).is_none());
+    }
+
+    #[test]
+    fn test_apply_bound_cache_strips_conflicting_fields() {
+        update_bindings(HashMap::from([(
+            "cache-test-*".to_string(),
+            ContextCacheBinding {
+                name: "cachedContents/bound".to_string(),
+                account_id: Some("acc-1".to_string()),
+            },
+        )]));
+
+        let mut request = json!({
+            "contents": [],
+            "systemInstruction": { "parts": [{ "text": "sys" }] },
+            "tools": [],
+            "toolConfig": {}
+        });
+        // 其他账号不注入
+        assert!(apply_to_request(&mut request, "cache-test-model", "gemini-2.5-flash", Some("acc-2"), None).is_none());
+        assert!(request.get("systemInstruction").is_some());
+
+        let used = apply_to_request(&mut request, "cache-test-model", "gemini-2.5-flash", Some("acc-1"), None);
+        assert_eq!(used.as_deref(), Some("cachedContents/bound"));
+        assert_eq!(request["cachedContent"], "cachedContents/bound");
+        assert!(request.get("systemInstruction").is_none());
+        assert!(request.get("tools").is_none() && request.get("toolConfig").is_none());
+        update_bindings(HashMap::new());
+    }
+
+    #[test]
+    fn test_build_create_body() {
+        let req: CreateCachedContentRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-flash",
+            "systemInstruction": "You are a helpful assistant",
+            "ttlSeconds": 10
+        }))
+        .unwrap();
+        let body = build_create_body(&req).unwrap();
+        assert_eq!(body["model"], "models/gemini-2.5-flash");
+        assert_eq!(body["ttl"], "60s");
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "You are a helpful assistant");
+
+        let empty: CreateCachedContentRequest =
+            serde_json::from_value(json!({ "model": "gemini-2.5-flash" })).unwrap();
+        assert!(build_create_body(&empty).is_err());
+    }
+}
@@ -11,6 +11,14 @@ fn build_filename(prefix: &str, trace_id: Option<&str>) -> String {
     format!("{}_{}_{}.json", ts, tid, prefix)
 }
 
+/// 同一请求的所有抓包文件归档到以 trace_id 命名的子目录中
+fn request_dir(base: PathBuf, trace_id: Option<&str>) -> PathBuf {
+    match trace_id.filter(|t| !t.is_empty() && t.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')) {
+        Some(tid) => base.join(tid),
+        None => base,
+    }
+}
+
 fn resolve_output_dir(cfg: &DebugLoggingConfig) -> Option<PathBuf> {
     if let Some(dir) = cfg.output_dir.as_ref() {
         return Some(PathBuf::from(dir));
@@ -21,6 +29,16 @@ fn resolve_output_dir(cfg: &DebugLoggingConfig) -> Option<PathBuf> {
     None
 }
 
+/// 指定 trace_id 的抓包目录 (用于请求重放)
+pub fn capture_dir(cfg: &DebugLoggingConfig, trace_id: &str) -> Result<PathBuf, String> {
+    let base = resolve_output_dir(cfg).ok_or_else(|| "Debug log directory is not available".to_string())?;
+    let dir = request_dir(base.clone(), Some(trace_id));
+    if dir == base {
+        return Err(format!("Invalid trace id: {}", trace_id));
+    }
+    Ok(dir)
+}
+
 pub async fn write_debug_payload(
     cfg: &DebugLoggingConfig,
     trace_id: Option<&str>,
@@ -32,7 +50,7 @@ pub async fn write_debug_payload(
     }
 
     let output_dir = match resolve_output_dir(cfg) {
-        Some(dir) => dir,
+        Some(dir) => request_dir(dir, trace_id),
         None => {
             tracing::warn!("[Debug-Log] Enabled but output_dir is not available.");
             return;
@@ -64,7 +82,7 @@ pub fn is_enabled(cfg: &DebugLoggingConfig) -> bool {
 }
 
 /// 解析 SSE 流式数据，提取 thinking 和正文内容
-fn parse_sse_stream(raw: &str) -> (String, String) {
+pub(crate) fn parse_sse_stream(raw: &str) -> (String, String) {
     let mut thinking_parts: Vec<String> = Vec::new();
     let mut content_parts: Vec<String> = Vec::new();
 
@@ -144,10 +162,14 @@ where
 
     let wrapped = async_stream::stream! {
         let mut collected: Vec<u8> = Vec::new();
+        let mut total_bytes: usize = 0;
         let mut inner = stream;
         while let Some(item) = inner.next().await {
             if let Ok(bytes) = &item {
-                collected.extend_from_slice(bytes);
+                total_bytes += bytes.len();
+                // [NEW] 超出上限后停止累积，避免长流占用过多内存
+                let remaining = cfg.max_capture_bytes.saturating_sub(collected.len());
+                collected.extend_from_slice(&bytes[..bytes.len().min(remaining)]);
             }
             yield item;
         }
@@ -159,7 +181,13 @@ where
             "kind": "upstream_response",
             "trace_id": trace_id,
             "meta": meta,
+            "raw_bytes": total_bytes,
+            "truncated": total_bytes > collected.len(),
         });
+
+        if cfg.capture_raw_upstream {
+            payload["raw_upstream"] = serde_json::Value::String(raw_text.clone());
+        }
         
         // 只有在有内容时才添加对应字段
         if !thinking_content.is_empty() {
@@ -8,7 +8,10 @@ use serde_json::{json, Value};
 use tracing::{debug, info};
 use uuid::Uuid;
 
-use crate::proxy::{audio::AudioProcessor, server::AppState};
+use crate::proxy::{
+    audio::{AudioProcessor, SpeechFormat, SpeechRequest},
+    server::AppState,
+};
 
 /// 处理音频转录请求 (OpenAI Whisper API 兼容)
 pub async fn handle_audio_transcription(
@@ -168,3 +171,92 @@ pub async fn handle_audio_transcription(
     )
         .into_response())
 }
+
+/// 处理语音合成请求 (OpenAI /v1/audio/speech 兼容)
+/// Gemini TTS 只输出 PCM，返回 wav (默认) 或 pcm；请求 mp3 等格式时回退为 wav
+pub async fn handle_audio_speech(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let speech_req: SpeechRequest = serde_json::from_value(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    if speech_req.input.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "input 不能为空".to_string()));
+    }
+
+    let model = AudioProcessor::resolve_tts_model(&speech_req.model, &*state.custom_mapping.read().await);
+    let format = SpeechFormat::from_request(speech_req.response_format.as_deref());
+    if speech_req.speed.is_some_and(|s| (s - 1.0).abs() > f32::EPSILON) {
+        debug!("Gemini TTS 不支持 speed 参数，已忽略");
+    }
+    info!(
+        "收到语音合成请求: 模型={} -> {}, 字符={}, 格式={:?}",
+        speech_req.model,
+        model,
+        speech_req.input.chars().count(),
+        format
+    );
+
+    let token_manager = state.token_manager;
+    let (access_token, project_id, email, account_id, _wait_ms) = token_manager
+        .get_token("text", false, None, &model)
+        .await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+
+    let wrapped_body = json!({
+        "project": project_id,
+        "requestId": format!("speech-{}", Uuid::new_v4()),
+        "request": AudioProcessor::build_speech_request(&speech_req),
+        "model": model,
+        "userAgent": "antigravity",
+        "requestType": "text"
+    });
+
+    let response = state
+        .upstream
+        .call_v1_internal(
+            "generateContent",
+            &access_token,
+            wrapped_body,
+            None,
+            Some(account_id.as_str()),
+        )
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e)))?
+        .response;
+
+    if !response.status().is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err((
+            StatusCode::BAD_GATEWAY,
+            format!("Gemini API 错误: {}", error_text),
+        ));
+    }
+
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("解析响应失败: {}", e)))?;
+    let inner_response = result.get("response").unwrap_or(&result);
+    let (audio, mime_type) = AudioProcessor::extract_audio(inner_response)
+        .ok_or((StatusCode::BAD_GATEWAY, "上游响应中没有音频数据".to_string()))?;
+    let (bytes, content_type) = AudioProcessor::encode_speech_output(audio, &mime_type, format);
+
+    info!("语音合成完成: {} bytes ({})", bytes.len(), content_type);
+
+    Ok((
+        StatusCode::OK,
+        [
+            (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+            (
+                axum::http::HeaderName::from_static("x-account-email"),
+                email,
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
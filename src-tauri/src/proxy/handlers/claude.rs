@@ -2,7 +2,7 @@
 
 use axum::{
     body::Body,
-    extract::{Json, State},
+    extract::{Json, Path, Query, State},
     http::{header, StatusCode},
     response::{IntoResponse, Response},
 };
@@ -24,6 +24,7 @@ use crate::proxy::mappers::estimation_calibrator::get_calibrator;
 use crate::proxy::debug_logger;
 use crate::proxy::upstream::client::mask_email;
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Import Adapter Registry
+use crate::proxy::common::malformed_call;
 use axum::http::HeaderMap;
 use std::sync::{atomic::Ordering, Arc};
 use crate::proxy::model_specs; // [NEW]
@@ -223,19 +224,12 @@ The structure MUST be as follows:
 4. The thinking signature must be copied exactly, no modifications
 "#;
 
-// ===== Jitter Configuration (REMOVED) =====
-// Jitter was causing connection instability, reverted to fixed delays
-// const JITTER_FACTOR: f64 = 0.2;
-
-
-// ===== 统一退避策略模块 =====
-
-// [REMOVED] apply_jitter function
-// Jitter logic removed to restore stability (v3.3.16 fix)
-
 // ===== 统一退避策略模块 =====
+// 抖动曾导致连接不稳定，默认关闭；开启 retry_policy.jitter 后仅作用于线性/指数退避，固定延迟保持不变
 // 移除本地重复定义，使用 common 中的统一实现
-use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotate_account, RetryStrategy};
+use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotate_account, try_rediscover_project, RetryStrategy};
+use crate::proxy::common::error_mapper::{anthropic_error_type, map_upstream_error, ErrorProtocol};
+use crate::proxy::common::request_validation::{parse_claude_request, ClaudeJson};
 
 // ===== 退避策略模块结束 =====
 
@@ -245,19 +239,32 @@ use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotat
 pub async fn handle_messages(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(body): Json<Value>,
+    ClaudeJson(body): ClaudeJson,
+) -> Response {
+    // [NEW] MCP 服务端工具模式: 由代理执行合成工具调用循环
+    if crate::proxy::mcp_bridge::server_side_tools_enabled() {
+        return super::mcp_bridge::run_claude_tool_loop(state, headers, body).await;
+    }
+    handle_messages_direct(state, headers, body).await
+}
+
+/// 单次 Chat 消息请求 (不经过 MCP 工具循环)
+pub(crate) async fn handle_messages_direct(
+    state: AppState,
+    headers: HeaderMap,
+    mut body: Value,
 ) -> Response {
+    // [NEW] 请求头覆盖模型 (x-agm-model / x-model-override)
+    crate::proxy::common::model_mapping::apply_model_override(&headers, &mut body);
+
     // [FIX] 保存原始请求体的完整副本，用于日志记录
     // 这确保了即使结构体定义遗漏字段，日志也能完整记录所有参数
     let original_body = body.clone();
     
     tracing::debug!("handle_messages called. Body JSON len: {}", body.to_string().len());
     
-    // 生成随机 Trace ID 用户追踪
-    let trace_id: String = rand::Rng::sample_iter(rand::thread_rng(), &rand::distributions::Alphanumeric)
-        .take(6)
-        .map(char::from)
-        .collect::<String>().to_lowercase();
+    // Trace ID 复用中间件生成的请求 ID (同时通过 x-agm-request-id 响应头返回给客户端)
+    let trace_id = crate::proxy::middleware::request_id::request_id_from_headers(&headers);
     let debug_cfg = state.debug_logging.read().await.clone();
     
     // [NEW] Detect Client Adapter
@@ -273,21 +280,34 @@ pub async fn handle_messages(
     let google_accounts = state.token_manager.len();
 
     // [CRITICAL REFACTOR] 优先解析请求以获取模型信息(用于智能兜底判断)
-    let mut request: crate::proxy::mappers::claude::models::ClaudeRequest = match serde_json::from_value(body.clone()) {
+    let mut request = match parse_claude_request(&body) {
         Ok(r) => r,
         Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "type": "error",
-                    "error": {
-                        "type": "invalid_request_error",
-                        "message": format!("Invalid request body: {}", e)
-                    }
-                }))
-            ).into_response();
+            debug!("[Claude] {}", e.describe());
+            return e.into_response(ErrorProtocol::Anthropic);
         }
     };
+    request.report_unknown_fields();
+    // [NEW] 系统提示词注入 (全局 / 按 API Key)，在模型映射前生效
+    if let Some(rule) = crate::proxy::common::system_prompt::rule_for_headers(&headers) {
+        crate::proxy::common::system_prompt::inject_claude(&mut request, &rule);
+    }
+
+    // [NEW] url 类型的图片 / 文档源先下载转为 base64 (Gemini 无法读取远程地址)
+    crate::proxy::common::image_fetch::inline_claude_image_urls(&mut request).await;
+    crate::proxy::common::document::inline_claude_document_urls(&mut request).await;
+    if let Err(e) = crate::proxy::common::document::validate_claude_request(&request) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "type": "error",
+                "error": {
+                    "type": "invalid_request_error",
+                    "message": e
+                }
+            }))
+        ).into_response();
+    }
 
     // [Task #6] Apply OpenCode variants thinking hints from raw JSON
     // 由于此时还没拿到账号，先用模型默认限额兜底
@@ -415,6 +435,8 @@ pub async fn handle_messages(
     let threshold_l1 = experimental.context_compression_threshold_l1;
     let threshold_l2 = experimental.context_compression_threshold_l2;
     let threshold_l3 = experimental.context_compression_threshold_l3;
+    let malformed_retry_enabled = experimental.enable_malformed_call_retry;
+    let web_search_blocks = use_web_search_blocks(&request, experimental.enable_web_search_blocks);
 
     // 获取最新一条“有意义”的消息内容（用于日志记录和后台任务检测）
     // 策略：反向遍历，首先筛选出所有角色为 "user" 的消息，然后从中找到第一条非 "Warmup" 且非空的文本消息
@@ -517,6 +539,7 @@ pub async fn handle_messages(
     // 3. 准备闭包
     let mut request_for_body = request.clone();
     let token_manager = state.token_manager;
+    let client_api_key = crate::proxy::middleware::client_rate_limit::api_key_from_headers(&headers); // [NEW] 用于按 Key 的账号分组路由
     
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries (e.g. stripping signatures)
@@ -528,6 +551,8 @@ pub async fn handle_messages(
     let mut last_email: Option<String> = None;
     let mut last_mapped_model: Option<String> = None;
     let mut last_status = StatusCode::SERVICE_UNAVAILABLE; // Default to 503 if no response reached
+    let mut project_rediscovered = false;
+    let mut malformed_retry_used = false;
     
     for attempt in 0..max_attempts {
         // 2. 模型路由解析
@@ -558,28 +583,14 @@ pub async fn handle_messages(
         let session_id = Some(session_id_str.as_str());
 
         let force_rotate_token = attempt > 0;
-        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager.get_token(&config.request_type, force_rotate_token, session_id, &config.final_model).await {
+        let (access_token, project_id, email, account_id, _wait_ms) = match token_manager.get_token_for_key(&config.request_type, force_rotate_token, session_id, &config.final_model, client_api_key.as_deref()).await {
             Ok(t) => t,
             Err(e) => {
-                let safe_message = if e.contains("invalid_grant") {
-                    "OAuth refresh failed (invalid_grant): refresh_token likely revoked/expired; reauthorize account(s) to restore service.".to_string()
-                } else {
-                    e
-                };
+                let (status, body) = super::common::token_error_response(ErrorProtocol::Anthropic, &e);
                 let headers = [
                     ("X-Mapped-Model", mapped_model.as_str()),
                 ];
-                 return (
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    headers,
-                    Json(json!({
-                        "type": "error",
-                        "error": {
-                            "type": "overloaded_error",
-                            "message": format!("No available accounts: {}", safe_message)
-                        }
-                    }))
-                ).into_response();
+                return (status, headers, body).into_response();
             }
         };
 
@@ -633,6 +644,26 @@ pub async fn handle_messages(
             );
         }
 
+        // ===== [NEW] 按模型配置的上下文窗口管理 (默认关闭) =====
+        if let Some(rule) = crate::proxy::common::context_trim::rule_for_model(&mapped_model) {
+            use crate::proxy::common::context_trim::{input_budget, trim_claude_request};
+            let budget = input_budget(&rule, &mapped_model);
+            if rule.mode == crate::proxy::config::ContextTrimMode::Summarize
+                && ContextManager::estimate_token_usage(&request_with_mapped) > budget
+            {
+                match try_compress_with_summary(&request_with_mapped, &trace_id, &token_manager).await {
+                    Ok(summarized) => request_with_mapped = summarized,
+                    Err(e) => tracing::warn!("[{}] [Context-Trim] Summary failed, falling back to trimming: {}", trace_id, e),
+                }
+            }
+            if let Some(outcome) = trim_claude_request(&mut request_with_mapped, budget) {
+                info!(
+                    "[{}] [Context-Trim] Dropped {} oldest messages for {} (~{} -> ~{} tokens, budget {})",
+                    trace_id, outcome.dropped, mapped_model, outcome.before, outcome.after, budget
+                );
+            }
+        }
+
         // ===== [3-Layer Progressive Compression + Calibrated Estimation] Context Management =====
         // [ENHANCED] 整合 3.3.47 的三层压缩框架 + PR #925 的动态校准机制
         // [NEW] 只有当 scaling_enabled 为 true 时才执行压缩逻辑 (联动机制)
@@ -929,13 +960,29 @@ pub async fn handle_messages(
                     "upstream_url": upstream_url,
                 });
                 let gemini_stream = debug_logger::wrap_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    Box::pin(upstream.body_stream(response)),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
                     meta,
                 );
 
+                // [NEW] 首个候选块为 MALFORMED_FUNCTION_CALL 时带纠正提示重试一次
+                let gemini_stream = if malformed_retry_enabled && !malformed_retry_used && attempt + 1 < max_attempts {
+                    match malformed_call::peek_stream(gemini_stream).await {
+                        Ok(stream) => stream,
+                        Err(message) => {
+                            tracing::warn!("[{}] MALFORMED_FUNCTION_CALL ({}), retrying once with corrective hint", trace_id, message);
+                            malformed_retry_used = true;
+                            crate::proxy::common::system_prompt::inject_claude(&mut request_for_body, &malformed_call::corrective_rule());
+                            last_error = format!("MALFORMED_FUNCTION_CALL: {}", message);
+                            continue;
+                        }
+                    }
+                } else {
+                    gemini_stream
+                };
+
                 let current_message_count = request_with_mapped.messages.len();
 
                 // [FIX #MCP] Extract registered tool names for MCP fuzzy matching
@@ -958,6 +1005,7 @@ pub async fn handle_messages(
                     current_message_count, // [NEW v4.0.0] Pass message count for rewind detection
                     client_adapter.clone(), // [NEW] Pass client adapter
                     registered_tool_names, // [FIX #MCP] Pass tool names for fuzzy matching
+                    web_search_blocks, // [NEW] web search 块 / Markdown 引文
                 );
 
                 let mut first_data_chunk = None;
@@ -1079,6 +1127,17 @@ pub async fn handle_messages(
                     Err(e) => return (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)).into_response(),
                 };
 
+                // [NEW] MALFORMED_FUNCTION_CALL: 带纠正提示重试一次
+                if malformed_retry_enabled && !malformed_retry_used && attempt + 1 < max_attempts {
+                    if let Some(message) = malformed_call::malformed_call_message(&gemini_resp) {
+                        tracing::warn!("[{}] MALFORMED_FUNCTION_CALL ({}), retrying once with corrective hint", trace_id, message);
+                        malformed_retry_used = true;
+                        crate::proxy::common::system_prompt::inject_claude(&mut request_for_body, &malformed_call::corrective_rule());
+                        last_error = format!("MALFORMED_FUNCTION_CALL: {}", message);
+                        continue;
+                    }
+                }
+
                 // 解包 response 字段（v1internal 格式）
                 let raw = gemini_resp.get("response").unwrap_or(&gemini_resp);
 
@@ -1102,6 +1161,7 @@ pub async fn handle_messages(
                     s_id_owned,
                     request_with_mapped.model.clone(),
                     request_with_mapped.messages.len(), // [NEW v4.0.0] Pass message count for rewind detection
+                    web_search_blocks,
                 ) {
                     Ok(r) => r,
                     Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Transform error: {}", e)).into_response(),
@@ -1153,6 +1213,18 @@ pub async fn handle_messages(
             debug_logger::write_debug_payload(&debug_cfg, Some(&trace_id), "upstream_response_error", &payload).await;
         }
         
+        // [NEW] project_id 失效 (403/404): 重新发现后重试，不标记限流 / forbidden
+        if try_rediscover_project(&token_manager, &account_id, status_code, &error_text, &mut project_rediscovered, &trace_id).await {
+            if apply_retry_strategy(RetryStrategy::FixedDelay(Duration::from_millis(200)), attempt, max_attempts, status_code, &trace_id).await {
+                continue;
+            }
+        }
+
+        // [NEW] 连续 401/403 计数，达到阈值后自动停用账号
+        if status_code == 401 || status_code == 403 {
+            token_manager.record_auth_failure(&account_id, status_code, &error_text).await;
+        }
+
         // 3. 标记限流状态(用于 UI 显示) - 使用异步版本以支持实时配额刷新
         // 🆕 传入实际使用的模型,实现模型级别限流,避免不同模型配额互相影响
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 || status_code == 404 {
@@ -1332,7 +1404,7 @@ pub async fn handle_messages(
             return (status, [
                 ("X-Account-Email", email.as_str()),
                 ("X-Mapped-Model", request_with_mapped.model.as_str())
-            ], error_text).into_response();
+            ], Json(map_upstream_error(ErrorProtocol::Anthropic, status_code, &error_text))).into_response();
         }
     }
     
@@ -1347,14 +1419,7 @@ pub async fn handle_messages(
              }
         }
 
-        let error_type = match last_status.as_u16() {
-            400 => "invalid_request_error",
-            401 => "authentication_error",
-            403 => "permission_error",
-            429 => "rate_limit_error",
-            529 => "overloaded_error",
-            _ => "api_error",
-        };
+        let error_type = anthropic_error_type(last_status.as_u16(), None);
 
         // [FIX] 403 时返回 503，避免 Claude Code 客户端退出到登录页
         let response_status = if last_status.as_u16() == 403 {
@@ -1380,14 +1445,7 @@ pub async fn handle_messages(
              }
         }
 
-        let error_type = match last_status.as_u16() {
-            400 => "invalid_request_error",
-            401 => "authentication_error",
-            403 => "permission_error",
-            429 => "rate_limit_error",
-            529 => "overloaded_error",
-            _ => "api_error",
-        };
+        let error_type = anthropic_error_type(last_status.as_u16(), None);
 
         // [FIX] 403 时返回 503，避免 Claude Code 客户端退出到登录页
         let response_status = if last_status.as_u16() == 403 {
@@ -1407,35 +1465,77 @@ pub async fn handle_messages(
     }
 }
 
-/// 列出可用模型
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    let model_ids = get_all_dynamic_models(
-        &state.custom_mapping,
-        Some(&state.token_manager)
-    ).await;
-
-    let data: Vec<_> = model_ids.into_iter().map(|id| {
-        json!({
-            "id": id,
-            "object": "model",
-            "created": 1706745600,
-            "owned_by": "antigravity"
-        })
-    }).collect();
+/// Anthropic Models API 分页参数
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ListModelsQuery {
+    pub limit: Option<usize>,
+    pub after_id: Option<String>,
+    pub before_id: Option<String>,
+}
+
+const DEFAULT_MODELS_PAGE_SIZE: usize = 20;
+const MAX_MODELS_PAGE_SIZE: usize = 1000;
+
+/// Anthropic 模型对象 ({"type":"model","id","display_name","created_at"} + 能力扩展字段)
+fn anthropic_model_entry(m: &crate::proxy::model_registry::ModelInfo) -> Value {
+    let created_at = chrono::DateTime::from_timestamp(crate::proxy::model_registry::MODEL_CREATED_AT, 0)
+        .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .unwrap_or_default();
+    json!({
+        "type": "model",
+        "id": m.id,
+        "display_name": m.display_name,
+        "created_at": created_at,
+        "context_window": m.context_window,
+        "max_output_tokens": m.max_output_tokens,
+        "capabilities": {
+            "vision": m.supports_vision,
+            "tools": m.supports_tools,
+            "thinking": m.supports_thinking
+        }
+    })
+}
+
+/// 列出可用模型 (Anthropic Models API 格式，支持 limit / after_id / before_id 分页)
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    Query(query): Query<ListModelsQuery>,
+) -> impl IntoResponse {
+    let models =
+        crate::proxy::model_registry::list_models(&state.custom_mapping, &state.token_manager).await;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_MODELS_PAGE_SIZE)
+        .clamp(1, MAX_MODELS_PAGE_SIZE);
+    let (page, has_more) = crate::proxy::model_registry::paginate(
+        &models,
+        limit,
+        query.after_id.as_deref(),
+        query.before_id.as_deref(),
+    );
 
     Json(json!({
-        "object": "list",
-        "data": data
+        "data": page.iter().map(anthropic_model_entry).collect::<Vec<_>>(),
+        "has_more": has_more,
+        "first_id": page.first().map(|m| m.id.clone()),
+        "last_id": page.last().map(|m| m.id.clone())
     }))
 }
 
+/// 查询单个模型 (Anthropic Models API 格式)
+pub async fn handle_get_model(
+    State(state): State<AppState>,
+    Path(model): Path<String>,
+) -> impl IntoResponse {
+    let info = crate::proxy::model_registry::get_model(&model, &state.custom_mapping).await;
+    Json(anthropic_model_entry(&info))
+}
+
 /// 计算 tokens (占位符)
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(body): Json<Value>,
+    ClaudeJson(body): ClaudeJson,
 ) -> Response {
     let zai = state.zai.read().await.clone();
     let zai_enabled = zai.enabled && !matches!(zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
@@ -1574,6 +1674,12 @@ fn detect_background_task_type(request: &ClaudeRequest) -> Option<BackgroundTask
     None
 }
 
+/// 是否以 server_tool_use / web_search_tool_result 块输出搜索来源
+/// 客户端声明了 web_search 工具时始终输出块；仅在代理自行注入搜索时由实验开关决定
+fn use_web_search_blocks(request: &ClaudeRequest, injected_blocks_enabled: bool) -> bool {
+    crate::proxy::mappers::claude::declares_web_search_tool(request) || injected_blocks_enabled
+}
+
 /// 辅助函数：关键词匹配
 fn matches_keywords(text: &str, keywords: &[&str]) -> bool {
     keywords.iter().any(|kw| text.contains(kw))
@@ -1871,6 +1977,8 @@ async fn try_compress_with_summary(
         output_config: None,
         size: None,
         quality: None,
+        tool_choice: None,
+        extra: Default::default(),
     };
     
     debug!("[{}] [Layer-3] Calling {} for summary generation", trace_id, INTERNAL_BACKGROUND_TASK);
@@ -1935,5 +2043,35 @@ async fn try_compress_with_summary(
         output_config: original_request.output_config.clone(),
         size: original_request.size.clone(),
         quality: original_request.quality.clone(),
+        tool_choice: original_request.tool_choice.clone(),
+        extra: original_request.extra.clone(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_tools(tools: serde_json::Value) -> ClaudeRequest {
+        serde_json::from_value(json!({
+            "model": "claude-sonnet-4-6",
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": "latest rust release?"}],
+            "tools": tools
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_declared_web_search_tool_enables_blocks() {
+        let declared = request_with_tools(json!([
+            {"type": "web_search_20250305", "name": "web_search", "max_uses": 5}
+        ]));
+        // 即使实验开关关闭，声明了 web_search 工具的请求也输出块
+        assert!(use_web_search_blocks(&declared, false));
+
+        let injected = request_with_tools(json!([]));
+        assert!(!use_web_search_blocks(&injected, false));
+        assert!(use_web_search_blocks(&injected, true));
+    }
+}
@@ -1,5 +1,5 @@
 use tokio::time::{sleep, Duration};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use axum::{http::StatusCode, response::{IntoResponse, Response}, Json, extract::State};
 use serde_json::{json, Value};
 use crate::proxy::server::AppState;
@@ -83,11 +83,24 @@ pub async fn apply_retry_strategy(
     status_code: u16,
     trace_id: &str,
 ) -> bool {
+    if matches!(strategy, RetryStrategy::NoRetry) {
+        debug!("[{}] Non-retryable error {}, stopping", trace_id, status_code);
+        return false;
+    }
+
+    // 全局重试预算耗尽时直接放弃，避免 429 风暴被重试放大
+    if !crate::proxy::upstream::retry::acquire_retry_budget() {
+        warn!(
+            "[{}] Retry budget exhausted ({}/s), giving up on status {}",
+            trace_id,
+            crate::proxy::upstream::retry::retry_policy().budget_per_second,
+            status_code
+        );
+        return false;
+    }
+
     match strategy {
-        RetryStrategy::NoRetry => {
-            debug!("[{}] Non-retryable error {}, stopping", trace_id, status_code);
-            false
-        }
+        RetryStrategy::NoRetry => false,
 
         RetryStrategy::FixedDelay(duration) => {
             let base_ms = duration.as_millis() as u64;
@@ -104,7 +117,8 @@ pub async fn apply_retry_strategy(
         }
 
         RetryStrategy::LinearBackoff { base_ms } => {
-            let calculated_ms = base_ms * (attempt as u64 + 1);
+            let calculated_ms =
+                crate::proxy::upstream::retry::apply_jitter(base_ms * (attempt as u64 + 1));
             info!(
                 "[{}] ⏱️ Retry with linear backoff: status={}, attempt={}/{}, delay={}ms",
                 trace_id,
@@ -118,7 +132,10 @@ pub async fn apply_retry_strategy(
         }
 
         RetryStrategy::ExponentialBackoff { base_ms, max_ms } => {
-            let calculated_ms = (base_ms * 2_u64.pow(attempt as u32)).min(max_ms);
+            let calculated_ms = crate::proxy::upstream::retry::apply_jitter(
+                base_ms.saturating_mul(2_u64.saturating_pow(attempt as u32)),
+            )
+            .min(max_ms);
             info!(
                 "[{}] ⏱️ Retry with exponential backoff: status={}, attempt={}/{}, delay={}ms",
                 trace_id,
@@ -145,6 +162,69 @@ pub fn should_rotate_account(status_code: u16) -> bool {
     }
 }
 
+/// [NEW] 上游 403/404 由 project_id 失效引起时，重新发现 project_id 并返回 true (调用方应立即重试)
+/// 每个请求只尝试一次，且不应再将账号标记为 forbidden / 限流
+pub async fn try_rediscover_project(
+    token_manager: &crate::proxy::TokenManager,
+    account_id: &str,
+    status_code: u16,
+    error_text: &str,
+    already_tried: &mut bool,
+    trace_id: &str,
+) -> bool {
+    if *already_tried || !crate::proxy::project_resolver::is_project_error(status_code, error_text) {
+        return false;
+    }
+    *already_tried = true;
+
+    match token_manager.rediscover_project_id(account_id).await {
+        Ok(project_id) => {
+            info!(
+                "[{}] Upstream {} looked like a stale project, rediscovered project_id {}",
+                trace_id, status_code, project_id
+            );
+            true
+        }
+        Err(e) => {
+            warn!("[{}] Project rediscovery failed: {}", trace_id, e);
+            false
+        }
+    }
+}
+
+/// 获取账号失败时的响应状态码：用量上限返回 429，其余 (无可用账号等) 返回 503
+pub fn token_error_status(error: &str) -> StatusCode {
+    if error.starts_with(crate::proxy::token_manager::USAGE_LIMIT_ERROR_PREFIX) {
+        StatusCode::TOO_MANY_REQUESTS
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+/// 获取账号失败时按协议构造原生错误 JSON (状态码见 `token_error_status`)
+/// 用量上限按 RESOURCE_EXHAUSTED 返回: Gemini `{"error":{code,status,message}}`，OpenAI / Anthropic 为 rate_limit_error
+pub fn token_error_response(
+    protocol: crate::proxy::common::error_mapper::ErrorProtocol,
+    error: &str,
+) -> (StatusCode, Json<Value>) {
+    let status = token_error_status(error);
+    let usage_limited = status == StatusCode::TOO_MANY_REQUESTS;
+    let message = if usage_limited {
+        error.to_string()
+    } else if error.contains("invalid_grant") {
+        "OAuth refresh failed (invalid_grant): refresh_token likely revoked/expired; reauthorize account(s) to restore service.".to_string()
+    } else {
+        format!("No available accounts: {}", error)
+    };
+    let body = crate::proxy::common::error_mapper::build_error_body(
+        protocol,
+        status.as_u16(),
+        &message,
+        usage_limited.then_some("RESOURCE_EXHAUSTED"),
+    );
+    (status, Json(body))
+}
+
 /// Detects model capabilities and configuration
 /// POST /v1/models/detect
 pub async fn handle_detect_model(
@@ -193,3 +273,38 @@ pub async fn handle_detect_model(
 
     Json(response).into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::common::error_mapper::ErrorProtocol;
+    use crate::proxy::token_manager::USAGE_LIMIT_ERROR_PREFIX;
+
+    #[test]
+    fn test_usage_limit_error_per_protocol() {
+        let error = format!("{} (global daily requests 100/100)", USAGE_LIMIT_ERROR_PREFIX);
+
+        let (status, Json(gemini)) = token_error_response(ErrorProtocol::Gemini, &error);
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(gemini["error"]["code"], 429);
+        assert_eq!(gemini["error"]["status"], "RESOURCE_EXHAUSTED");
+        assert_eq!(gemini["error"]["message"], error.as_str());
+
+        let (_, Json(openai)) = token_error_response(ErrorProtocol::OpenAI, &error);
+        assert_eq!(openai["error"]["type"], "rate_limit_error");
+        assert_eq!(openai["error"]["message"], error.as_str());
+
+        let (_, Json(claude)) = token_error_response(ErrorProtocol::Anthropic, &error);
+        assert_eq!(claude["error"]["type"], "rate_limit_error");
+    }
+
+    #[test]
+    fn test_empty_pool_error_is_unavailable() {
+        let (status, Json(gemini)) = token_error_response(ErrorProtocol::Gemini, "Token pool is empty");
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(gemini["error"]["status"], "UNAVAILABLE");
+
+        let (_, Json(claude)) = token_error_response(ErrorProtocol::Anthropic, "Token pool is empty");
+        assert_eq!(claude["error"]["type"], "overloaded_error");
+    }
+}
@@ -0,0 +1,140 @@
+// 调试处理器 - Dry-run 请求转换
+//
+// 提供 /debug/transform 端点：接收 Claude 或 OpenAI 请求，返回完整转换后的 v1internal 请求体，
+// 不调用上游。用于快速验证工具、thinking、图片等映射行为。需开启 debug_logging.transform_endpoint。
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::proxy::mappers::claude::{transform_claude_request_in, ClaudeRequest};
+use crate::proxy::mappers::openai::{transform_openai_request, OpenAIRequest};
+use crate::proxy::server::AppState;
+
+/// Dry-run 转换使用的占位 project_id
+const DRY_RUN_PROJECT_ID: &str = "dry-run-project";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransformProtocol {
+    Claude,
+    Openai,
+}
+
+/// Dry-run 转换请求体
+#[derive(Debug, Deserialize)]
+pub struct TransformRequest {
+    pub protocol: TransformProtocol,
+    /// 原始 Claude Messages / OpenAI Chat Completions 请求
+    pub request: Value,
+    /// 可选：使用指定账号的动态模型规格 (账号 ID 或邮箱)
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// POST /debug/transform
+pub async fn handle_debug_transform(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TransformRequest>,
+) -> Response {
+    if !state.debug_logging.read().await.transform_endpoint {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            "Dry-run transform endpoint is disabled (set proxy.debug_logging.transform_endpoint = true)".to_string(),
+        );
+    }
+
+    let mut body = payload.request;
+    crate::proxy::common::model_mapping::apply_model_override(&headers, &mut body);
+    let original_model = body.get("model").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &original_model,
+        &*state.custom_mapping.read().await,
+    );
+
+    let token = payload.account.as_deref().and_then(|account| {
+        state
+            .token_manager
+            .get_token_by_id(account)
+            .or_else(|| {
+                state
+                    .token_manager
+                    .get_account_id_by_email(account)
+                    .and_then(|id| state.token_manager.get_token_by_id(&id))
+            })
+    });
+    let mapped_model = match &token {
+        Some(t) => {
+            state
+                .token_manager
+                .resolve_dynamic_model_for_account(&t.account_id, &mapped_model)
+                .await
+        }
+        None => mapped_model,
+    };
+    let system_prompt_rule = crate::proxy::common::system_prompt::rule_for_headers(&headers);
+
+    let mut transformed = match payload.protocol {
+        TransformProtocol::Claude => {
+            let mut request: ClaudeRequest = match serde_json::from_value(body) {
+                Ok(r) => r,
+                Err(e) => {
+                    return error_response(StatusCode::BAD_REQUEST, format!("Invalid Claude request: {}", e))
+                }
+            };
+            if let Some(rule) = &system_prompt_rule {
+                crate::proxy::common::system_prompt::inject_claude(&mut request, rule);
+            }
+            request.model = mapped_model.clone();
+            match transform_claude_request_in(
+                &request,
+                DRY_RUN_PROJECT_ID,
+                false,
+                token.as_ref().map(|t| t.account_id.as_str()),
+                "dry-run",
+                token.as_ref(),
+            ) {
+                Ok(v) => v,
+                Err(e) => {
+                    return error_response(StatusCode::BAD_REQUEST, format!("Transform failed: {}", e))
+                }
+            }
+        }
+        TransformProtocol::Openai => {
+            let mut request: OpenAIRequest = match serde_json::from_value(body) {
+                Ok(r) => r,
+                Err(e) => {
+                    return error_response(StatusCode::BAD_REQUEST, format!("Invalid OpenAI request: {}", e))
+                }
+            };
+            if let Some(rule) = &system_prompt_rule {
+                crate::proxy::common::system_prompt::inject_openai(&mut request, rule);
+            }
+            let (v, _session_id, _message_count) =
+                transform_openai_request(&request, DRY_RUN_PROJECT_ID, &mapped_model, token.as_ref());
+            v
+        }
+    };
+
+    // 与实际发往上游的请求保持一致 (脱敏在上游调用处统一执行)
+    let redactions = crate::proxy::redaction::redact_request_body(&mut transformed);
+
+    Json(json!({
+        "protocol": payload.protocol,
+        "original_model": original_model,
+        "mapped_model": mapped_model,
+        "account": token.as_ref().map(|t| t.email.clone()),
+        "redactions": redactions,
+        "v1internal": transformed,
+    }))
+    .into_response()
+}
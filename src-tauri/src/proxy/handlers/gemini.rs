@@ -9,10 +9,14 @@ use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS;
+use crate::proxy::common::error_mapper::{build_error_body, map_upstream_error, ErrorProtocol};
+use crate::proxy::common::request_validation::GeminiJson;
 use crate::proxy::debug_logger;
 use crate::proxy::handlers::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account,
+    apply_retry_strategy, determine_retry_strategy, should_rotate_account, try_rediscover_project,
+    RetryStrategy,
 };
+use crate::proxy::mappers::gemini::streaming::GeminiSseRelay;
 use crate::proxy::mappers::gemini::{unwrap_response, wrap_request};
 use crate::proxy::server::AppState;
 use crate::proxy::session_manager::SessionManager;
@@ -26,8 +30,8 @@ const MAX_RETRY_ATTEMPTS: usize = 3;
 pub async fn handle_generate(
     State(state): State<AppState>,
     Path(model_action): Path<String>,
-    headers: HeaderMap,          // [NEW] Extract headers for adapter detection
-    Json(mut body): Json<Value>, // 改为 mut 以支持修复提示词注入
+    headers: HeaderMap,                // [NEW] Extract headers for adapter detection
+    GeminiJson(mut body): GeminiJson, // 改为 mut 以支持修复提示词注入
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 解析 model:method
     let (model_name, method) = if let Some((m, action)) = model_action.rsplit_once(':') {
@@ -35,12 +39,20 @@ pub async fn handle_generate(
     } else {
         (model_action, "generateContent".to_string())
     };
+    // [NEW] 请求头覆盖路径中的模型 (x-agm-model / x-model-override)
+    let model_name = match crate::proxy::common::model_mapping::model_override_from_headers(&headers) {
+        Some(overridden) => {
+            tracing::info!("[Router] Header model override: {} -> {}", model_name, overridden);
+            overridden
+        }
+        None => model_name,
+    };
 
     crate::modules::logger::log_info(&format!(
         "Received Gemini request: {}/{}",
         model_name, method
     ));
-    let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
+    let trace_id = crate::proxy::middleware::request_id::request_id_from_headers(&headers);
     let debug_cfg = state.debug_logging.read().await.clone();
 
     // [NEW] Detect Client Adapter
@@ -52,6 +64,11 @@ pub async fn handle_generate(
         debug!("[{}] Client Adapter detected", trace_id);
     }
 
+    // countTokens 与生成接口共用 /v1beta/models/{model}:method 路由
+    if method == "countTokens" {
+        return Ok(Json(count_tokens_response(&body)).into_response());
+    }
+
     // 1. 验证方法
     if method != "generateContent" && method != "streamGenerateContent" {
         return Err((
@@ -88,11 +105,13 @@ pub async fn handle_generate(
     // 2. 获取 UpstreamClient 和 TokenManager
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
+    let client_api_key = crate::proxy::middleware::client_rate_limit::api_key_from_headers(&headers); // [NEW] 用于按 Key 的账号分组路由
     let pool_size = token_manager.len();
     let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size).max(1);
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
+    let mut project_rediscovered = false;
 
     for attempt in 0..max_attempts {
         // 3. 模型路由解析
@@ -133,20 +152,18 @@ pub async fn handle_generate(
 
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
         let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-            .get_token(
+            .get_token_for_key(
                 &config.request_type,
                 attempt > 0,
                 Some(&session_id),
                 &config.final_model,
+                client_api_key.as_deref(),
             )
             .await
         {
             Ok(t) => t,
             Err(e) => {
-                return Err((
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    format!("Token error: {}", e),
-                ));
+                return Ok(super::common::token_error_response(ErrorProtocol::Gemini, &e).into_response());
             }
         };
 
@@ -266,7 +283,7 @@ pub async fn handle_generate(
             if is_stream {
                 use axum::body::Body;
                 use axum::response::Response;
-                use bytes::{Bytes, BytesMut};
+                use bytes::Bytes;
                 use futures::StreamExt;
 
                 let meta = json!({
@@ -280,14 +297,14 @@ pub async fn handle_generate(
                     "upstream_url": upstream_url,
                 });
                 let mut response_stream = debug_logger::wrap_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    Box::pin(upstream.body_stream(response)),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
                     meta,
                 );
-                let mut buffer = BytesMut::new();
                 let s_id = session_id.clone(); // Clone for stream closure
+                let mut relay = GeminiSseRelay::new(&s_id, &mapped_model);
 
                 // [FIX #859] Implement peek logic for Gemini stream to prevent 0-token 200 OK
                 let mut first_chunk = None;
@@ -361,74 +378,18 @@ pub async fn handle_generate(
                                 yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
                                 break;
                             }
-                            None => break,
+                            None => {
+                                // 冲刷未以换行结尾的尾块 (通常携带 usageMetadata / finishReason)
+                                for event in relay.finish() {
+                                    yield Ok::<Bytes, String>(event);
+                                }
+                                break;
+                            }
                         };
 
                         debug!("[Gemini-SSE] Received chunk: {} bytes", bytes.len());
-                        buffer.extend_from_slice(&bytes);
-                        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                            let line_raw = buffer.split_to(pos + 1);
-                            if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                                let line = line_str.trim();
-                                if line.is_empty() { continue; }
-
-                                if line.starts_with("data: ") {
-                                    let json_part = line.trim_start_matches("data: ").trim();
-                                    if json_part == "[DONE]" {
-                                        yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
-                                        continue;
-                                    }
-
-                                    match serde_json::from_str::<Value>(json_part) {
-                                        Ok(mut json) => {
-                                            // [FIX #765] Extract thoughtSignature from stream
-                                            let inner_val = if json.get("response").is_some() {
-                                                json.get("response")
-                                            } else {
-                                                Some(&json)
-                                            };
-
-                                            if let Some(resp) = inner_val {
-                                                if let Some(candidates) = resp.get("candidates").and_then(|c| c.as_array()) {
-                                                    for cand in candidates {
-                                                        if let Some(parts) = cand.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
-                                                            for part in parts {
-                                                                if let Some(sig) = part.get("thoughtSignature").and_then(|s| s.as_str()) {
-                                                                    crate::proxy::SignatureCache::global()
-                                                                        .cache_session_signature(&s_id_for_stream, sig.to_string(), 1);
-                                                                    debug!("[Gemini-SSE] Cached signature (len: {}) for session: {}", sig.len(), s_id_for_stream);
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-
-                                            // [FIX #1522] Inject Tool ID into Stream Response
-                                            crate::proxy::mappers::gemini::wrapper::inject_ids_to_response(&mut json, &model_name_for_stream);
-
-                                            // Unwrap v1internal response wrapper
-                                            if let Some(inner) = json.get_mut("response").map(|v| v.take()) {
-                                                let new_line = format!("data: {}\n\n", serde_json::to_string(&inner).unwrap_or_default());
-                                                yield Ok::<Bytes, String>(Bytes::from(new_line));
-                                            } else {
-                                                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&json).unwrap_or_default())));
-                                            }
-                                        }
-                                        Err(e) => {
-                                            debug!("[Gemini-SSE] JSON parse error: {}, passing raw line", e);
-                                            yield Ok::<Bytes, String>(Bytes::from(format!("{}\n\n", line)));
-                                        }
-                                    }
-                                } else {
-                                    // Non-data lines (comments, etc.)
-                                    yield Ok::<Bytes, String>(Bytes::from(format!("{}\n\n", line)));
-                                }
-                            } else {
-                                // Non-UTF8 data? Just pass it through or skip
-                                debug!("[Gemini-SSE] Non-UTF8 line encountered");
-                                yield Ok::<Bytes, String>(line_raw.freeze());
-                            }
+                        for event in relay.push(&bytes) {
+                            yield Ok::<Bytes, String>(event);
                         }
                     }
                 };
@@ -566,6 +527,33 @@ pub async fn handle_generate(
         let strategy = determine_retry_strategy(status_code, &error_text, false);
         let trace_id = format!("gemini_{}", session_id);
 
+        // [NEW] project_id 失效 (403/404): 重新发现后重试
+        if try_rediscover_project(
+            &token_manager,
+            &account_id,
+            status_code,
+            &error_text,
+            &mut project_rediscovered,
+            &trace_id,
+        )
+        .await
+            && apply_retry_strategy(
+                RetryStrategy::FixedDelay(std::time::Duration::from_millis(200)),
+                attempt,
+                max_attempts,
+                status_code,
+                &trace_id,
+            )
+            .await
+        {
+            continue;
+        }
+
+        // [NEW] 连续 401/403 计数，达到阈值后自动停用账号
+        if status_code == 401 || status_code == 403 {
+            token_manager.record_auth_failure(&account_id, status_code, &error_text).await;
+        }
+
         // 执行退避
         if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
             // [NEW] Apply Client Adapter "let_it_crash" strategy
@@ -630,13 +618,7 @@ pub async fn handle_generate(
                 ("X-Mapped-Model", mapped_model.as_str()),
             ],
             // [FIX] Return JSON error
-            Json(json!({
-                "error": {
-                    "code": status_code,
-                    "message": error_text,
-                    "status": "UPSTREAM_ERROR"
-                }
-            })),
+            Json(map_upstream_error(ErrorProtocol::Gemini, status_code, &error_text)),
         )
             .into_response());
     }
@@ -645,71 +627,148 @@ pub async fn handle_generate(
         Ok((
             StatusCode::TOO_MANY_REQUESTS,
             [("X-Account-Email", email)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+            Json(build_error_body(
+                ErrorProtocol::Gemini,
+                429,
+                &format!("All accounts exhausted. Last error: {}", last_error),
+                None,
+            )),
         )
             .into_response())
     } else {
         Ok((
             StatusCode::TOO_MANY_REQUESTS,
-            format!("All accounts exhausted. Last error: {}", last_error),
+            Json(build_error_body(
+                ErrorProtocol::Gemini,
+                429,
+                &format!("All accounts exhausted. Last error: {}", last_error),
+                None,
+            )),
         )
             .into_response())
     }
 }
 
+fn gemini_model_entry(model: &crate::proxy::model_registry::ModelInfo) -> Value {
+    json!({
+        "name": format!("models/{}", model.id),
+        "version": "001",
+        "displayName": model.display_name,
+        "description": "",
+        "inputTokenLimit": model.context_window,
+        "outputTokenLimit": model.max_output_tokens,
+        "supportedGenerationMethods": ["generateContent", "streamGenerateContent", "countTokens"],
+        "thinking": model.supports_thinking,
+        "temperature": 1.0,
+        "topP": 0.95,
+        "topK": 64
+    })
+}
+
 pub async fn handle_list_models(
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    // 获取所有动态模型列表（与 /v1/models 一致）
-    let model_ids = get_all_dynamic_models(&state.custom_mapping, Some(&state.token_manager)).await;
-
-    // 转换为 Gemini API 格式
-    let models: Vec<_> = model_ids
-        .into_iter()
-        .map(|id| {
-            json!({
-                "name": format!("models/{}", id),
-                "version": "001",
-                "displayName": id.clone(),
-                "description": "",
-                "inputTokenLimit": 128000,
-                "outputTokenLimit": 8192,
-                "supportedGenerationMethods": ["generateContent", "countTokens"],
-                "temperature": 1.0,
-                "topP": 0.95,
-                "topK": 64
-            })
-        })
-        .collect();
+    // 与 /v1/models 共用模型注册表
+    let models =
+        crate::proxy::model_registry::list_models(&state.custom_mapping, &state.token_manager).await;
+    let models: Vec<_> = models.iter().map(gemini_model_entry).collect();
 
     Ok(Json(json!({ "models": models })))
 }
 
-pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoResponse {
-    Json(json!({
-        "name": format!("models/{}", model_name),
-        "displayName": model_name
-    }))
+pub async fn handle_get_model(
+    State(state): State<AppState>,
+    Path(model_name): Path<String>,
+) -> impl IntoResponse {
+    let model_name = model_name.strip_prefix("models/").unwrap_or(&model_name).to_string();
+    let info = crate::proxy::model_registry::get_model(&model_name, &state.custom_mapping).await;
+    Json(gemini_model_entry(&info))
+}
+
+/// 内联图片 / 文件按固定 token 数估算 (与官方对单张图片的计费一致)
+const INLINE_MEDIA_TOKENS: u32 = 258;
+
+fn estimate_parts_tokens(content: &Value) -> u32 {
+    use crate::proxy::mappers::context_manager::estimate_tokens_from_str;
+
+    let Some(parts) = content.get("parts").and_then(|p| p.as_array()) else {
+        return 0;
+    };
+    parts
+        .iter()
+        .map(|part| {
+            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                estimate_tokens_from_str(text)
+            } else if part.get("inlineData").is_some() || part.get("fileData").is_some() {
+                INLINE_MEDIA_TOKENS
+            } else {
+                // functionCall / functionResponse 等结构化内容按 JSON 文本估算
+                estimate_tokens_from_str(&part.to_string())
+            }
+        })
+        .sum()
+}
+
+/// 本地估算 countTokens (v1internal 无对应接口)
+/// 支持两种请求体: { contents, systemInstruction, tools } 或 { generateContentRequest: {...} }
+fn estimate_request_tokens(body: &Value) -> u32 {
+    use crate::proxy::mappers::context_manager::estimate_tokens_from_str;
+
+    let request = body.get("generateContentRequest").unwrap_or(body);
+    let mut total = 0u32;
+
+    if let Some(contents) = request.get("contents").and_then(|c| c.as_array()) {
+        total += contents.iter().map(estimate_parts_tokens).sum::<u32>();
+    }
+    for key in ["systemInstruction", "system_instruction"] {
+        if let Some(system) = request.get(key) {
+            total += match system.as_str() {
+                Some(text) => estimate_tokens_from_str(text),
+                None => estimate_parts_tokens(system),
+            };
+        }
+    }
+    if let Some(tools) = request.get("tools") {
+        total += estimate_tokens_from_str(&tools.to_string());
+    }
+    total
+}
+
+fn count_tokens_response(body: &Value) -> Value {
+    json!({ "totalTokens": estimate_request_tokens(body) })
 }
 
 pub async fn handle_count_tokens(
-    State(state): State<AppState>,
     Path(_model_name): Path<String>,
-    Json(_body): Json<Value>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let model_group = "gemini";
-    let (_access_token, _project_id, _, _, _wait_ms) = state
-        .token_manager
-        .get_token(model_group, false, None, "gemini")
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::SERVICE_UNAVAILABLE,
-                format!("Token error: {}", e),
-            )
-        })?;
+    GeminiJson(body): GeminiJson,
+) -> impl IntoResponse {
+    Json(count_tokens_response(&body))
+}
+
+#[cfg(test)]
+mod count_tokens_tests {
+    use super::*;
 
-    Ok(Json(json!({"totalTokens": 0})))
+    #[test]
+    fn test_estimate_request_tokens() {
+        let plain = json!({
+            "contents": [{"role": "user", "parts": [{"text": "Hello, how are you today?"}]}]
+        });
+        let tokens = estimate_request_tokens(&plain);
+        assert!(tokens > 0);
+
+        // generateContentRequest 包装形式与直接形式结果一致
+        let wrapped = json!({ "generateContentRequest": plain.clone() });
+        assert_eq!(estimate_request_tokens(&wrapped), tokens);
+
+        let with_image = json!({
+            "contents": [{"role": "user", "parts": [
+                {"text": "Hello, how are you today?"},
+                {"inlineData": {"mimeType": "image/png", "data": "AAAA"}}
+            ]}],
+            "systemInstruction": {"parts": [{"text": "Be brief"}]}
+        });
+        assert!(estimate_request_tokens(&with_image) > tokens + INLINE_MEDIA_TOKENS);
+        assert_eq!(count_tokens_response(&json!({}))["totalTokens"], 0);
+    }
 }
@@ -0,0 +1,1020 @@
+// MCP 工具桥接端点与服务端工具循环
+// 服务端工具模式: 注入合成工具 -> 请求上游 -> 模型只调用了合成工具时由代理执行并回填结果 -> 重复，
+// 直到模型给出最终回复 (或调用了客户端工具 / 达到轮次上限)。
+// 流式请求逐轮直接转发上游 SSE，只在某一轮出现合成工具调用后暂存该轮剩余事件，最终轮仍是真实流式输出。
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body, BodyDataStream},
+    extract::Json,
+    http::{header, response::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use crate::proxy::common::error_mapper::{map_upstream_error, ErrorProtocol};
+use crate::proxy::common::sse_decoder::{sse_data, SseLineDecoder};
+use crate::proxy::mcp_bridge::{self, is_bridged_tool};
+use crate::proxy::server::AppState;
+
+const MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+/// 执行工具 / 等待下一轮上游响应期间的心跳间隔
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+const STREAM_CHANNEL_CAPACITY: usize = 64;
+
+type StreamSender = mpsc::Sender<Result<Bytes, std::io::Error>>;
+
+/// GET /v1/mcp/tools - 列出桥接的合成工具
+pub async fn handle_list_tools() -> Response {
+    if !mcp_bridge::get_mcp_bridge_config().enabled {
+        return (StatusCode::NOT_FOUND, "MCP bridge is disabled").into_response();
+    }
+    let data: Vec<Value> = mcp_bridge::list_tools()
+        .await
+        .into_iter()
+        .map(|t| {
+            json!({
+                "name": t.name,
+                "server": t.server,
+                "tool": t.tool,
+                "description": t.description,
+                "input_schema": t.input_schema,
+            })
+        })
+        .collect();
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+/// POST /api/mcp/tools/call - 直接执行合成工具 ({"name": ..., "arguments": {...}})，仅挂在管理接口下
+pub async fn handle_call_tool(Json(body): Json<Value>) -> Response {
+    if !mcp_bridge::get_mcp_bridge_config().enabled {
+        return (StatusCode::NOT_FOUND, "MCP bridge is disabled").into_response();
+    }
+    let Some(name) = body.get("name").and_then(|n| n.as_str()) else {
+        return (StatusCode::BAD_REQUEST, "Missing tool name").into_response();
+    };
+    let arguments = body.get("arguments").cloned().unwrap_or_else(|| json!({}));
+    let output = mcp_bridge::call_tool(name, arguments).await;
+    Json(json!({
+        "content": [{ "type": "text", "text": output.text }],
+        "is_error": output.is_error,
+    }))
+    .into_response()
+}
+
+/// 读取内部非流式响应；非 2xx 或非 JSON 时原样返回给客户端
+async fn read_json_response(response: Response) -> Result<(Parts, Value), Response> {
+    let (parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_RESPONSE_BYTES).await {
+        Ok(b) => b,
+        Err(e) => {
+            return Err((StatusCode::BAD_GATEWAY, format!("Failed to read response: {}", e)).into_response());
+        }
+    };
+    if !parts.status.is_success() {
+        return Err(Response::from_parts(parts, Body::from(bytes)));
+    }
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) => Ok((parts, value)),
+        Err(_) => Err(Response::from_parts(parts, Body::from(bytes))),
+    }
+}
+
+/// 用最后一轮响应的头构造最终 JSON 响应
+fn final_response(mut parts: Parts, body: String) -> Response {
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// 用首轮响应的头构造流式响应，后续各轮的事件经由 channel 写入
+fn stream_response(mut parts: Parts, rx: mpsc::Receiver<Result<Bytes, std::io::Error>>) -> Response {
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)))
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("text/event-stream"))
+        .unwrap_or(false)
+}
+
+/// 后续轮次的上游响应: 成功的 SSE 返回响应体，否则返回 (状态码, 错误文本)
+async fn next_round_body(response: Response) -> Result<Body, (u16, String)> {
+    if response.status().is_success() && is_event_stream(&response) {
+        return Ok(response.into_body());
+    }
+    let status = response.status().as_u16();
+    let text = match to_bytes(response.into_body(), MAX_RESPONSE_BYTES).await {
+        Ok(b) => String::from_utf8_lossy(&b).into_owned(),
+        Err(e) => format!("Failed to read response: {}", e),
+    };
+    Err((status, text))
+}
+
+async fn send(tx: &StreamSender, text: String) -> bool {
+    tx.send(Ok(Bytes::from(text))).await.is_ok()
+}
+
+/// 等待期间定期发送 SSE 注释心跳，避免客户端在工具执行 / 下一轮请求期间超时断开
+async fn with_heartbeat<F: Future>(tx: &StreamSender, fut: F) -> F::Output {
+    tokio::pin!(fut);
+    let mut interval =
+        tokio::time::interval_at(tokio::time::Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+    loop {
+        tokio::select! {
+            output = &mut fut => return output,
+            _ = interval.tick() => {
+                let _ = tx.send(Ok(Bytes::from_static(b": ping\n\n"))).await;
+            }
+        }
+    }
+}
+
+/// 一个完整的 SSE 事件 (原始行，不含结尾空行)
+#[derive(Debug, Clone, Default)]
+struct SseEvent {
+    lines: Vec<String>,
+}
+
+impl SseEvent {
+    fn raw(&self) -> String {
+        let mut raw = self.lines.join("\n");
+        raw.push_str("\n\n");
+        raw
+    }
+
+    /// 只有注释行 (心跳)
+    fn is_comment(&self) -> bool {
+        self.lines.iter().all(|l| l.starts_with(':'))
+    }
+
+    fn data(&self) -> Option<String> {
+        let parts: Vec<&str> = self.lines.iter().filter_map(|l| sse_data(l)).collect();
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("\n"))
+        }
+    }
+
+    fn json(&self) -> Option<Value> {
+        self.data().and_then(|d| serde_json::from_str(&d).ok())
+    }
+}
+
+/// 从响应体中逐个读取 SSE 事件
+struct SseEventReader {
+    body: BodyDataStream,
+    decoder: SseLineDecoder,
+    lines: VecDeque<String>,
+    current: Vec<String>,
+    finished: bool,
+}
+
+impl SseEventReader {
+    fn new(body: Body) -> Self {
+        Self {
+            body: body.into_data_stream(),
+            decoder: SseLineDecoder::new(),
+            lines: VecDeque::new(),
+            current: Vec::new(),
+            finished: false,
+        }
+    }
+
+    async fn next_event(&mut self) -> Option<SseEvent> {
+        loop {
+            while let Some(line) = self.lines.pop_front() {
+                if !line.is_empty() {
+                    self.current.push(line);
+                } else if !self.current.is_empty() {
+                    return Some(SseEvent { lines: std::mem::take(&mut self.current) });
+                }
+            }
+            if self.finished {
+                if self.current.is_empty() {
+                    return None;
+                }
+                return Some(SseEvent { lines: std::mem::take(&mut self.current) });
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.lines.extend(self.decoder.push(&chunk)),
+                Some(Err(e)) => {
+                    tracing::warn!("[MCP-Bridge] Upstream stream error: {}", e);
+                    self.finished = true;
+                }
+                None => {
+                    self.lines.extend(self.decoder.finish());
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+/// 一轮流式响应结束后的去向
+enum RoundEnd {
+    /// 模型只调用了合成工具: 执行后进入下一轮
+    ToolCalls {
+        assistant: Value,
+        calls: Vec<(String, String, Value)>,
+    },
+    /// 最终轮: 把暂存的事件转发给客户端
+    Final(Vec<String>),
+}
+
+fn append_str(target: &mut Value, field: &str, piece: &Value) {
+    let mut text = target[field].as_str().unwrap_or_default().to_string();
+    text.push_str(piece.as_str().unwrap_or_default());
+    target[field] = json!(text);
+}
+
+fn add_usage(total: &mut Value, usage: Option<&Value>, fields: &[&str]) {
+    let Some(usage) = usage else {
+        return;
+    };
+    for field in fields {
+        let sum = total[*field].as_u64().unwrap_or(0) + usage.get(*field).and_then(|v| v.as_u64()).unwrap_or(0);
+        total[*field] = json!(sum);
+    }
+}
+
+// ===== Claude =====
+
+/// 模型发起的调用全部是合成工具时返回这些调用；混有客户端工具时返回空 (交还客户端处理)
+fn claude_bridged_calls(message: &Value) -> Vec<(String, String, Value)> {
+    let tool_uses: Vec<&Value> = message
+        .get("content")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+        .collect();
+    let all_bridged = tool_uses
+        .iter()
+        .all(|b| b.get("name").and_then(|n| n.as_str()).map(is_bridged_tool).unwrap_or(false));
+    if !all_bridged {
+        return Vec::new();
+    }
+    tool_uses
+        .into_iter()
+        .map(|b| {
+            (
+                b["id"].as_str().unwrap_or_default().to_string(),
+                b["name"].as_str().unwrap_or_default().to_string(),
+                b.get("input").cloned().unwrap_or_else(|| json!({})),
+            )
+        })
+        .collect()
+}
+
+fn sse_event(event: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", event, data)
+}
+
+async fn execute_claude_calls(round: u32, calls: Vec<(String, String, Value)>) -> Vec<Value> {
+    let mut results = Vec::new();
+    for (id, name, input) in calls {
+        tracing::info!("[MCP-Bridge] Round {}: executing {}", round, name);
+        let output = mcp_bridge::call_tool(&name, input).await;
+        results.push(json!({
+            "type": "tool_result",
+            "tool_use_id": id,
+            "content": output.text,
+            "is_error": output.is_error,
+        }));
+    }
+    results
+}
+
+fn push_claude_round(body: &mut Value, assistant: Value, results: Vec<Value>) {
+    if let Some(messages) = body.get_mut("messages").and_then(|m| m.as_array_mut()) {
+        messages.push(json!({ "role": "assistant", "content": assistant }));
+        messages.push(json!({ "role": "user", "content": results }));
+    }
+}
+
+/// Claude SSE 多轮拼接: 各轮内容块按连续的 index 转发为同一条消息，
+/// 只有 message_start 取自首轮，usage 在最终的 message_delta 中累加
+#[derive(Default)]
+struct ClaudeStreamRelay {
+    round: u32,
+    max_rounds: u32,
+    /// 之前各轮已转发给客户端的内容块数
+    index_offset: usize,
+    /// 之前各轮的 usage 累计
+    input_tokens: u64,
+    output_tokens: u64,
+    // 以下为当前轮状态
+    blocks: Vec<Value>,
+    tool_inputs: Vec<String>,
+    forwarded_blocks: usize,
+    /// 已出现合成工具调用，本轮剩余事件暂存
+    pending: bool,
+    held: Vec<SseEvent>,
+    round_input_tokens: u64,
+    round_output_tokens: u64,
+}
+
+impl ClaudeStreamRelay {
+    fn new(max_rounds: u32) -> Self {
+        Self {
+            max_rounds,
+            ..Default::default()
+        }
+    }
+
+    /// 处理一个上游事件，返回需要立即转发给客户端的内容
+    fn on_event(&mut self, event: SseEvent) -> Option<String> {
+        if event.is_comment() {
+            return Some(event.raw());
+        }
+        let Some(data) = event.json() else {
+            return self.forward_or_hold(event);
+        };
+        let index = data["index"].as_u64().map(|i| i as usize);
+        match data["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                self.round_input_tokens =
+                    data.pointer("/message/usage/input_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+                return (self.round == 0).then(|| event.raw());
+            }
+            "ping" => return Some(event.raw()),
+            "content_block_start" => {
+                let index = index.unwrap_or(self.blocks.len());
+                if self.blocks.len() <= index {
+                    self.blocks.resize(index + 1, Value::Null);
+                    self.tool_inputs.resize(index + 1, String::new());
+                }
+                let block = data["content_block"].clone();
+                if block["type"] == "tool_use" && block["name"].as_str().map(is_bridged_tool).unwrap_or(false) {
+                    self.pending = true;
+                }
+                self.blocks[index] = block;
+                if !self.pending {
+                    self.forwarded_blocks = self.forwarded_blocks.max(index + 1);
+                }
+            }
+            "content_block_delta" => {
+                if let Some(index) = index.filter(|i| *i < self.blocks.len()) {
+                    let delta = &data["delta"];
+                    let block = &mut self.blocks[index];
+                    match delta["type"].as_str().unwrap_or_default() {
+                        "text_delta" => append_str(block, "text", &delta["text"]),
+                        "thinking_delta" => append_str(block, "thinking", &delta["thinking"]),
+                        "signature_delta" => block["signature"] = delta["signature"].clone(),
+                        "input_json_delta" => self.tool_inputs[index]
+                            .push_str(delta["partial_json"].as_str().unwrap_or_default()),
+                        _ => {}
+                    }
+                }
+            }
+            "message_delta" => {
+                self.round_output_tokens =
+                    data.pointer("/usage/output_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+            }
+            _ => {}
+        }
+        self.forward_or_hold(event)
+    }
+
+    fn forward_or_hold(&mut self, event: SseEvent) -> Option<String> {
+        if self.pending {
+            self.held.push(event);
+            None
+        } else {
+            Some(self.render(&event))
+        }
+    }
+
+    /// 首轮原样转发；后续轮次改写 index 并在 message_delta 中累加 usage
+    fn render(&self, event: &SseEvent) -> String {
+        if self.round == 0 {
+            return event.raw();
+        }
+        let Some(mut data) = event.json() else {
+            return event.raw();
+        };
+        if let Some(index) = data["index"].as_u64() {
+            data["index"] = json!(index as usize + self.index_offset);
+        }
+        if data["type"] == "message_delta" {
+            if let Some(usage) = data.get_mut("usage").filter(|u| u.is_object()) {
+                usage["output_tokens"] = json!(self.output_tokens + usage["output_tokens"].as_u64().unwrap_or(0));
+                if let Some(input) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
+                    usage["input_tokens"] = json!(self.input_tokens + input);
+                }
+            }
+        }
+        let event_type = data["type"].as_str().unwrap_or("message").to_string();
+        sse_event(&event_type, &data)
+    }
+
+    fn finish(&mut self) -> RoundEnd {
+        for (block, input) in self.blocks.iter_mut().zip(&self.tool_inputs) {
+            if block["type"] == "tool_use" && !input.is_empty() {
+                block["input"] = serde_json::from_str(input).unwrap_or_else(|_| json!({}));
+            }
+        }
+        let content: Vec<Value> = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .filter(|b| !b.is_null())
+            .collect();
+        let calls = if self.pending {
+            claude_bridged_calls(&json!({ "content": content }))
+        } else {
+            Vec::new()
+        };
+
+        if calls.is_empty() || self.round >= self.max_rounds {
+            if !calls.is_empty() {
+                tracing::warn!("[MCP-Bridge] Tool round limit ({}) reached, returning tool calls to client", self.max_rounds);
+            }
+            let held = std::mem::take(&mut self.held);
+            return RoundEnd::Final(held.iter().map(|e| self.render(e)).collect());
+        }
+
+        self.index_offset += self.forwarded_blocks;
+        self.input_tokens += self.round_input_tokens;
+        self.output_tokens += self.round_output_tokens;
+        self.round += 1;
+        self.tool_inputs.clear();
+        self.forwarded_blocks = 0;
+        self.pending = false;
+        self.held.clear();
+        self.round_input_tokens = 0;
+        self.round_output_tokens = 0;
+        RoundEnd::ToolCalls {
+            assistant: json!(content),
+            calls,
+        }
+    }
+}
+
+/// Claude 流式工具循环: 首轮响应头直接返回，各轮事件由后台任务写入
+async fn run_claude_stream_loop(state: AppState, headers: HeaderMap, mut body: Value, max_rounds: u32) -> Response {
+    let response = super::claude::handle_messages_direct(state.clone(), headers.clone(), body.clone()).await;
+    if !response.status().is_success() || !is_event_stream(&response) {
+        return response;
+    }
+    let (parts, first_body) = response.into_parts();
+    let (tx, rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+
+    tokio::spawn(async move {
+        let mut relay = ClaudeStreamRelay::new(max_rounds);
+        let mut reader = SseEventReader::new(first_body);
+        loop {
+            while let Some(event) = reader.next_event().await {
+                if let Some(out) = relay.on_event(event) {
+                    if !send(&tx, out).await {
+                        return;
+                    }
+                }
+            }
+            let (assistant, calls) = match relay.finish() {
+                RoundEnd::ToolCalls { assistant, calls } => (assistant, calls),
+                RoundEnd::Final(events) => {
+                    for out in events {
+                        if !send(&tx, out).await {
+                            return;
+                        }
+                    }
+                    return;
+                }
+            };
+            let results = with_heartbeat(&tx, execute_claude_calls(relay.round, calls)).await;
+            push_claude_round(&mut body, assistant, results);
+            let response = with_heartbeat(
+                &tx,
+                super::claude::handle_messages_direct(state.clone(), headers.clone(), body.clone()),
+            )
+            .await;
+            match next_round_body(response).await {
+                Ok(next) => reader = SseEventReader::new(next),
+                Err((status, text)) => {
+                    tracing::warn!("[MCP-Bridge] Round {} failed with status {}", relay.round, status);
+                    let error = map_upstream_error(ErrorProtocol::Anthropic, status, &text);
+                    let _ = send(&tx, sse_event("error", &error)).await;
+                    return;
+                }
+            }
+        }
+    });
+
+    stream_response(parts, rx)
+}
+
+/// Claude Messages 服务端工具循环
+pub async fn run_claude_tool_loop(state: AppState, headers: HeaderMap, mut body: Value) -> Response {
+    let tools = mcp_bridge::list_tools().await;
+    if tools.is_empty() {
+        return super::claude::handle_messages_direct(state, headers, body).await;
+    }
+    mcp_bridge::append_claude_tools(&mut body, &tools);
+    let max_rounds = mcp_bridge::get_mcp_bridge_config().max_tool_rounds;
+    if body.get("stream").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return run_claude_stream_loop(state, headers, body, max_rounds).await;
+    }
+
+    let mut usage = json!({});
+    let mut round = 0;
+    loop {
+        let response = super::claude::handle_messages_direct(state.clone(), headers.clone(), body.clone()).await;
+        let (parts, mut message) = match read_json_response(response).await {
+            Ok(v) => v,
+            Err(resp) => return resp,
+        };
+        add_usage(&mut usage, message.get("usage"), &["input_tokens", "output_tokens"]);
+
+        let calls = claude_bridged_calls(&message);
+        if calls.is_empty() || round >= max_rounds {
+            if !calls.is_empty() {
+                tracing::warn!("[MCP-Bridge] Tool round limit ({}) reached, returning tool calls to client", max_rounds);
+            }
+            message["usage"]["input_tokens"] = usage["input_tokens"].clone();
+            message["usage"]["output_tokens"] = usage["output_tokens"].clone();
+            return final_response(parts, message.to_string());
+        }
+
+        round += 1;
+        let results = execute_claude_calls(round, calls).await;
+        let assistant = message["content"].clone();
+        push_claude_round(&mut body, assistant, results);
+    }
+}
+
+// ===== OpenAI =====
+
+fn openai_bridged_calls(completion: &Value) -> Vec<(String, String, Value)> {
+    let tool_calls: Vec<&Value> = completion
+        .pointer("/choices/0/message/tool_calls")
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+        .collect();
+    let all_bridged = tool_calls.iter().all(|c| {
+        c.pointer("/function/name")
+            .and_then(|n| n.as_str())
+            .map(is_bridged_tool)
+            .unwrap_or(false)
+    });
+    if !all_bridged {
+        return Vec::new();
+    }
+    tool_calls
+        .into_iter()
+        .map(|c| {
+            let arguments = c
+                .pointer("/function/arguments")
+                .and_then(|a| a.as_str())
+                .and_then(|a| serde_json::from_str(a).ok())
+                .unwrap_or_else(|| json!({}));
+            (
+                c["id"].as_str().unwrap_or_default().to_string(),
+                c["function"]["name"].as_str().unwrap_or_default().to_string(),
+                arguments,
+            )
+        })
+        .collect()
+}
+
+async fn execute_openai_calls(round: u32, calls: Vec<(String, String, Value)>) -> Vec<Value> {
+    let mut tool_messages = Vec::new();
+    for (id, name, arguments) in calls {
+        tracing::info!("[MCP-Bridge] Round {}: executing {}", round, name);
+        let output = mcp_bridge::call_tool(&name, arguments).await;
+        let content = if output.is_error {
+            format!("Error: {}", output.text)
+        } else {
+            output.text
+        };
+        tool_messages.push(json!({ "role": "tool", "tool_call_id": id, "content": content }));
+    }
+    tool_messages
+}
+
+fn push_openai_round(body: &mut Value, assistant: Value, tool_messages: Vec<Value>) {
+    if let Some(messages) = body.get_mut("messages").and_then(|m| m.as_array_mut()) {
+        messages.push(assistant);
+        messages.extend(tool_messages);
+    }
+}
+
+const OPENAI_USAGE_FIELDS: [&str; 3] = ["prompt_tokens", "completion_tokens", "total_tokens"];
+
+/// OpenAI chunk 流多轮拼接: 各轮 chunk 依次转发，[DONE] 只在最终轮发出，usage 累加到最终轮
+#[derive(Default)]
+struct OpenAIStreamRelay {
+    round: u32,
+    max_rounds: u32,
+    /// 之前各轮的 usage 累计
+    usage: Value,
+    // 以下为当前轮状态
+    content: String,
+    reasoning: String,
+    /// (id, name, arguments)
+    tool_calls: Vec<(String, String, String)>,
+    /// 已出现合成工具调用，本轮剩余事件暂存
+    pending: bool,
+    held: Vec<SseEvent>,
+    round_usage: Option<Value>,
+    saw_done: bool,
+}
+
+impl OpenAIStreamRelay {
+    fn new(max_rounds: u32) -> Self {
+        Self {
+            max_rounds,
+            usage: json!({}),
+            ..Default::default()
+        }
+    }
+
+    /// 处理一个上游事件，返回需要立即转发给客户端的内容
+    fn on_event(&mut self, event: SseEvent) -> Option<String> {
+        if event.is_comment() {
+            return Some(event.raw());
+        }
+        if event.data().as_deref().map(str::trim) == Some("[DONE]") {
+            self.saw_done = true;
+            return None;
+        }
+        let Some(chunk) = event.json() else {
+            return self.forward_or_hold(event);
+        };
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            self.round_usage = Some(usage.clone());
+        }
+        if let Some(delta) = chunk.pointer("/choices/0/delta") {
+            if let Some(text) = delta.get("content").and_then(|c| c.as_str()) {
+                self.content.push_str(text);
+            }
+            if let Some(text) = delta.get("reasoning_content").and_then(|c| c.as_str()) {
+                self.reasoning.push_str(text);
+            }
+            for call in delta.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+                let index = call["index"].as_u64().unwrap_or(0) as usize;
+                if self.tool_calls.len() <= index {
+                    self.tool_calls.resize(index + 1, Default::default());
+                }
+                let entry = &mut self.tool_calls[index];
+                if let Some(id) = call.get("id").and_then(|v| v.as_str()) {
+                    entry.0 = id.to_string();
+                }
+                if let Some(name) = call.pointer("/function/name").and_then(|v| v.as_str()) {
+                    entry.1 = name.to_string();
+                    if is_bridged_tool(name) {
+                        self.pending = true;
+                    }
+                }
+                if let Some(arguments) = call.pointer("/function/arguments").and_then(|v| v.as_str()) {
+                    entry.2.push_str(arguments);
+                }
+            }
+        }
+        self.forward_or_hold(event)
+    }
+
+    fn forward_or_hold(&mut self, event: SseEvent) -> Option<String> {
+        if self.pending {
+            self.held.push(event);
+            None
+        } else {
+            Some(self.render(&event))
+        }
+    }
+
+    /// 首轮原样转发；后续轮次在携带 usage 的 chunk 中累加之前各轮的用量
+    fn render(&self, event: &SseEvent) -> String {
+        if self.round == 0 {
+            return event.raw();
+        }
+        let Some(mut chunk) = event.json() else {
+            return event.raw();
+        };
+        let Some(usage) = chunk.get_mut("usage").filter(|u| u.is_object()) else {
+            return event.raw();
+        };
+        for field in OPENAI_USAGE_FIELDS {
+            let sum = self.usage[field].as_u64().unwrap_or(0) + usage[field].as_u64().unwrap_or(0);
+            usage[field] = json!(sum);
+        }
+        format!("data: {}\n\n", chunk)
+    }
+
+    fn assistant_message(&self) -> Value {
+        let mut message = json!({
+            "role": "assistant",
+            "content": if self.content.is_empty() { Value::Null } else { json!(self.content) },
+        });
+        if !self.reasoning.is_empty() {
+            message["reasoning_content"] = json!(self.reasoning);
+        }
+        if !self.tool_calls.is_empty() {
+            let tool_calls: Vec<Value> = self
+                .tool_calls
+                .iter()
+                .map(|(id, name, arguments)| {
+                    json!({ "id": id, "type": "function", "function": { "name": name, "arguments": arguments } })
+                })
+                .collect();
+            message["tool_calls"] = json!(tool_calls);
+        }
+        message
+    }
+
+    fn finish(&mut self) -> RoundEnd {
+        let assistant = self.assistant_message();
+        let calls = if self.pending {
+            openai_bridged_calls(&json!({ "choices": [{ "message": assistant }] }))
+        } else {
+            Vec::new()
+        };
+
+        if calls.is_empty() || self.round >= self.max_rounds {
+            if !calls.is_empty() {
+                tracing::warn!("[MCP-Bridge] Tool round limit ({}) reached, returning tool calls to client", self.max_rounds);
+            }
+            let held = std::mem::take(&mut self.held);
+            let mut events: Vec<String> = held.iter().map(|e| self.render(e)).collect();
+            if self.saw_done {
+                events.push("data: [DONE]\n\n".to_string());
+            }
+            return RoundEnd::Final(events);
+        }
+
+        add_usage(&mut self.usage, self.round_usage.as_ref(), &OPENAI_USAGE_FIELDS);
+        self.round += 1;
+        self.content.clear();
+        self.reasoning.clear();
+        self.tool_calls.clear();
+        self.pending = false;
+        self.held.clear();
+        self.round_usage = None;
+        self.saw_done = false;
+        RoundEnd::ToolCalls { assistant, calls }
+    }
+}
+
+/// OpenAI 流式工具循环: 首轮响应头直接返回，各轮 chunk 由后台任务写入
+async fn run_openai_stream_loop(state: AppState, headers: HeaderMap, mut body: Value, max_rounds: u32) -> Response {
+    let response = super::openai::handle_chat_completions_direct(state.clone(), headers.clone(), body.clone())
+        .await
+        .into_response();
+    if !response.status().is_success() || !is_event_stream(&response) {
+        return response;
+    }
+    let (parts, first_body) = response.into_parts();
+    let (tx, rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+
+    tokio::spawn(async move {
+        let mut relay = OpenAIStreamRelay::new(max_rounds);
+        let mut reader = SseEventReader::new(first_body);
+        loop {
+            while let Some(event) = reader.next_event().await {
+                if let Some(out) = relay.on_event(event) {
+                    if !send(&tx, out).await {
+                        return;
+                    }
+                }
+            }
+            let (assistant, calls) = match relay.finish() {
+                RoundEnd::ToolCalls { assistant, calls } => (assistant, calls),
+                RoundEnd::Final(events) => {
+                    for out in events {
+                        if !send(&tx, out).await {
+                            return;
+                        }
+                    }
+                    return;
+                }
+            };
+            let tool_messages = with_heartbeat(&tx, execute_openai_calls(relay.round, calls)).await;
+            push_openai_round(&mut body, assistant, tool_messages);
+            let response = with_heartbeat(&tx, async {
+                super::openai::handle_chat_completions_direct(state.clone(), headers.clone(), body.clone())
+                    .await
+                    .into_response()
+            })
+            .await;
+            match next_round_body(response).await {
+                Ok(next) => reader = SseEventReader::new(next),
+                Err((status, text)) => {
+                    tracing::warn!("[MCP-Bridge] Round {} failed with status {}", relay.round, status);
+                    let error = map_upstream_error(ErrorProtocol::OpenAI, status, &text);
+                    let _ = send(&tx, format!("data: {}\n\ndata: [DONE]\n\n", error)).await;
+                    return;
+                }
+            }
+        }
+    });
+
+    stream_response(parts, rx)
+}
+
+/// OpenAI Chat Completions 服务端工具循环
+pub async fn run_openai_tool_loop(state: AppState, headers: HeaderMap, mut body: Value) -> Response {
+    let tools = mcp_bridge::list_tools().await;
+    if tools.is_empty() {
+        return super::openai::handle_chat_completions_direct(state, headers, body)
+            .await
+            .into_response();
+    }
+    mcp_bridge::append_openai_tools(&mut body, &tools);
+    let max_rounds = mcp_bridge::get_mcp_bridge_config().max_tool_rounds;
+    if body.get("stream").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return run_openai_stream_loop(state, headers, body, max_rounds).await;
+    }
+
+    let mut usage = json!({});
+    let mut round = 0;
+    loop {
+        let response = super::openai::handle_chat_completions_direct(state.clone(), headers.clone(), body.clone())
+            .await
+            .into_response();
+        let (parts, mut completion) = match read_json_response(response).await {
+            Ok(v) => v,
+            Err(resp) => return resp,
+        };
+        add_usage(&mut usage, completion.get("usage"), &OPENAI_USAGE_FIELDS);
+
+        let calls = openai_bridged_calls(&completion);
+        if calls.is_empty() || round >= max_rounds {
+            if !calls.is_empty() {
+                tracing::warn!("[MCP-Bridge] Tool round limit ({}) reached, returning tool calls to client", max_rounds);
+            }
+            if completion.get("usage").is_some() {
+                for field in OPENAI_USAGE_FIELDS {
+                    completion["usage"][field] = usage[field].clone();
+                }
+            }
+            return final_response(parts, completion.to_string());
+        }
+
+        round += 1;
+        let assistant = completion.pointer("/choices/0/message").cloned().unwrap_or_default();
+        let tool_messages = execute_openai_calls(round, calls).await;
+        push_openai_round(&mut body, assistant, tool_messages);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claude_bridged_calls_require_all_bridged() {
+        let message = json!({
+            "content": [
+                { "type": "text", "text": "checking" },
+                { "type": "tool_use", "id": "t1", "name": "mcp__files__read_file", "input": { "path": "a" } }
+            ]
+        });
+        let calls = claude_bridged_calls(&message);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].2["path"], "a");
+
+        let mixed = json!({
+            "content": [
+                { "type": "tool_use", "id": "t1", "name": "mcp__files__read_file", "input": {} },
+                { "type": "tool_use", "id": "t2", "name": "Bash", "input": {} }
+            ]
+        });
+        assert!(claude_bridged_calls(&mixed).is_empty());
+    }
+
+    fn event(raw: &str) -> SseEvent {
+        SseEvent {
+            lines: raw.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect(),
+        }
+    }
+
+    fn claude_event(data: Value) -> SseEvent {
+        event(&sse_event(data["type"].as_str().unwrap(), &data))
+    }
+
+    #[test]
+    fn test_claude_relay_streams_rounds_without_bridged_calls() {
+        let mut relay = ClaudeStreamRelay::new(8);
+        let start = claude_event(json!({ "type": "message_start", "message": { "usage": { "input_tokens": 10 } } }));
+        assert_eq!(relay.on_event(start.clone()), Some(start.raw()));
+        let text = claude_event(json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }));
+        assert!(relay.on_event(text).is_some());
+        assert_eq!(relay.on_event(event(": ping")), Some(": ping\n\n".to_string()));
+        let stop = claude_event(json!({ "type": "message_stop" }));
+        assert!(relay.on_event(stop).is_some());
+        match relay.finish() {
+            RoundEnd::Final(events) => assert!(events.is_empty()),
+            RoundEnd::ToolCalls { .. } => panic!("expected final round"),
+        }
+    }
+
+    #[test]
+    fn test_claude_relay_holds_bridged_round_and_offsets_next_round() {
+        let mut relay = ClaudeStreamRelay::new(8);
+        relay.on_event(claude_event(json!({ "type": "message_start", "message": { "usage": { "input_tokens": 10 } } })));
+        relay.on_event(claude_event(json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } })));
+        relay.on_event(claude_event(json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "checking" } })));
+        let tool = claude_event(json!({
+            "type": "content_block_start", "index": 1,
+            "content_block": { "type": "tool_use", "id": "t1", "name": "mcp__files__read_file", "input": {} }
+        }));
+        assert!(relay.on_event(tool).is_none());
+        let input = claude_event(json!({
+            "type": "content_block_delta", "index": 1,
+            "delta": { "type": "input_json_delta", "partial_json": "{\"path\":\"a\"}" }
+        }));
+        assert!(relay.on_event(input).is_none());
+        assert!(relay
+            .on_event(claude_event(json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use" }, "usage": { "output_tokens": 4 } })))
+            .is_none());
+
+        let RoundEnd::ToolCalls { assistant, calls } = relay.finish() else {
+            panic!("expected tool round");
+        };
+        assert_eq!(assistant[0]["text"], "checking");
+        assert_eq!(calls[0].2["path"], "a");
+
+        // 第二轮: message_start 不再转发，index 顺延，usage 累加
+        assert!(relay
+            .on_event(claude_event(json!({ "type": "message_start", "message": { "usage": { "input_tokens": 20 } } })))
+            .is_none());
+        let text = relay
+            .on_event(claude_event(json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } })))
+            .unwrap();
+        assert!(text.contains("\"index\":1"));
+        let delta = relay
+            .on_event(claude_event(json!({ "type": "message_delta", "delta": { "stop_reason": "end_turn" }, "usage": { "output_tokens": 6 } })))
+            .unwrap();
+        assert!(delta.contains("\"output_tokens\":10"));
+    }
+
+    #[test]
+    fn test_openai_relay_holds_bridged_round_and_sums_usage() {
+        let mut relay = OpenAIStreamRelay::new(8);
+        let chunk = |data: Value| event(&format!("data: {}", data));
+        assert!(relay
+            .on_event(chunk(json!({ "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "hi" } }] })))
+            .is_some());
+        assert!(relay
+            .on_event(chunk(json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [{
+                "index": 0, "id": "call_1", "type": "function",
+                "function": { "name": "mcp__files__read_file", "arguments": "{\"path\":" }
+            }] } }] })))
+            .is_none());
+        relay.on_event(chunk(json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [{
+            "index": 0, "function": { "arguments": "\"a\"}" }
+        }] } }] })));
+        relay.on_event(chunk(json!({ "choices": [], "usage": { "prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5 } })));
+        assert!(relay.on_event(event("data: [DONE]")).is_none());
+
+        let RoundEnd::ToolCalls { assistant, calls } = relay.finish() else {
+            panic!("expected tool round");
+        };
+        assert_eq!(assistant["content"], "hi");
+        assert_eq!(assistant["tool_calls"][0]["function"]["arguments"], "{\"path\":\"a\"}");
+        assert_eq!(calls[0].0, "call_1");
+        assert_eq!(calls[0].2["path"], "a");
+
+        let usage = relay
+            .on_event(chunk(json!({ "choices": [], "usage": { "prompt_tokens": 4, "completion_tokens": 1, "total_tokens": 5 } })))
+            .unwrap();
+        assert!(usage.contains("\"total_tokens\":10"));
+        assert!(relay.on_event(event("data: [DONE]")).is_none());
+        match relay.finish() {
+            RoundEnd::Final(events) => assert_eq!(events, vec!["data: [DONE]\n\n".to_string()]),
+            RoundEnd::ToolCalls { .. } => panic!("expected final round"),
+        }
+    }
+
+    #[test]
+    fn test_openai_bridged_calls() {
+        let completion = json!({
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant", "content": null,
+                    "tool_calls": [{
+                        "id": "call_1", "type": "function",
+                        "function": { "name": "mcp__files__read_file", "arguments": "{\"path\":\"a\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        });
+        let calls = openai_bridged_calls(&completion);
+        assert_eq!(calls[0].0, "call_1");
+        assert_eq!(calls[0].2["path"], "a");
+    }
+}
@@ -5,7 +5,10 @@ pub mod claude;
 pub mod openai;
 pub mod gemini;
 pub mod mcp;
+pub mod mcp_bridge; // MCP 工具桥接 (服务端工具循环)
 pub mod common;
 pub mod audio;  // 音频转录处理器
 pub mod warmup; // 预热处理器
+pub mod realtime; // WebSocket 流式桥接
+pub mod debug; // Dry-run 请求转换 (mapper 调试)
 
@@ -1,6 +1,6 @@
 // OpenAI Handler
 use axum::{
-    extract::Json, extract::State, http::StatusCode, response::IntoResponse, response::Response,
+    extract::Json, extract::Path, extract::State, http::StatusCode, response::IntoResponse, response::Response,
 };
 use base64::Engine as _;
 use bytes::Bytes;
@@ -8,7 +8,7 @@ use serde_json::{json, Value};
 use tracing::{debug, error, info}; // Import Engine trait for encode method
 
 use crate::proxy::mappers::openai::{
-    transform_openai_request, transform_openai_response, OpenAIRequest,
+    transform_openai_request, transform_openai_response,
 };
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
 use crate::proxy::debug_logger;
@@ -17,19 +17,40 @@ use crate::proxy::upstream::client::mask_email;
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
 use super::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account, RetryStrategy,
+    apply_retry_strategy, determine_retry_strategy, should_rotate_account, try_rediscover_project,
+    RetryStrategy,
 };
 use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Adapter Registry
+use crate::proxy::common::malformed_call;
 use crate::proxy::session_manager::SessionManager;
 use axum::http::HeaderMap;
 use tokio::time::Duration;
 use crate::modules::account;
+use crate::proxy::middleware::request_id::request_id_from_headers;
+use crate::proxy::common::error_mapper::{build_error_body, map_upstream_error, ErrorProtocol};
+use crate::proxy::common::request_validation::{parse_openai_request, OpenAIJson};
 
 pub async fn handle_chat_completions(
     State(state): State<AppState>,
     headers: HeaderMap, // [CHANGED] Extract headers
-    Json(mut body): Json<Value>,
+    OpenAIJson(body): OpenAIJson,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // [NEW] MCP 服务端工具模式: 由代理执行合成工具调用循环
+    if crate::proxy::mcp_bridge::server_side_tools_enabled() {
+        return Ok(super::mcp_bridge::run_openai_tool_loop(state, headers, body).await);
+    }
+    Ok(handle_chat_completions_direct(state, headers, body).await.into_response())
+}
+
+/// 单次 Chat Completions 请求 (不经过 MCP 工具循环)
+pub(crate) async fn handle_chat_completions_direct(
+    state: AppState,
+    headers: HeaderMap,
+    mut body: Value,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // [NEW] 请求头覆盖模型 (x-agm-model / x-model-override)，在映射与图像重定向之前生效
+    crate::proxy::common::model_mapping::apply_model_override(&headers, &mut body);
+
     // [NEW] Check for Image Model Redirection
     let model_name = body.get("model").and_then(|v| v.as_str()).unwrap_or("").to_lowercase();
     if model_name.contains("image") || model_name.contains("dall-e") || model_name.contains("midjourney") {
@@ -90,8 +111,31 @@ pub async fn handle_chat_completions(
         }
     }
 
-    let mut openai_req: OpenAIRequest = serde_json::from_value(body)
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    let mut openai_req = match parse_openai_request(&body) {
+        Ok(req) => req,
+        Err(e) => {
+            debug!("[OpenAI] {}", e.describe());
+            return Ok(e.into_response(ErrorProtocol::OpenAI));
+        }
+    };
+    // [NEW] 系统提示词注入 (全局 / 按 API Key)
+    if let Some(rule) = crate::proxy::common::system_prompt::rule_for_headers(&headers) {
+        crate::proxy::common::system_prompt::inject_openai(&mut openai_req, &rule);
+    }
+
+    // [NEW] Gemini 无法读取远程图片地址，先下载转为 data URI
+    crate::proxy::common::image_fetch::inline_openai_image_urls(&mut openai_req).await;
+    // [NEW] 文件 (PDF) 输入：下载 file_url 并校验大小 / 类型
+    crate::proxy::common::document::inline_openai_file_urls(&mut openai_req).await;
+    if let Err(e) = crate::proxy::common::document::validate_openai_request(&openai_req)
+        .and_then(|_| crate::proxy::audio::AudioProcessor::validate_openai_request(&openai_req))
+    {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(build_error_body(ErrorProtocol::OpenAI, 400, &format!("Invalid request: {}", e), None)),
+        )
+            .into_response());
+    }
 
     // Safety: Ensure messages is not empty
     if openai_req.messages.is_empty() {
@@ -107,10 +151,11 @@ pub async fn handle_chat_completions(
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             });
     }
 
-    let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
+    let trace_id = request_id_from_headers(&headers);
     info!(
         "[{}] OpenAI Chat Request: {} | {} messages | stream: {}",
         trace_id,
@@ -149,6 +194,7 @@ pub async fn handle_chat_completions(
     // 1. 获取 UpstreamClient (Clone handle)
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
+    let client_api_key = crate::proxy::middleware::client_rate_limit::api_key_from_headers(&headers); // [NEW] 用于按 Key 的账号分组路由
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
     let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
@@ -162,6 +208,20 @@ pub async fn handle_chat_completions(
         &*state.custom_mapping.read().await,
     );
 
+    // [NEW] 按模型配置的上下文窗口管理 (默认关闭；summarize 在 OpenAI 协议下按裁剪处理)
+    if let Some(rule) = crate::proxy::common::context_trim::rule_for_model(&mapped_model) {
+        let budget = crate::proxy::common::context_trim::input_budget(&rule, &mapped_model);
+        if let Some(outcome) = crate::proxy::common::context_trim::trim_openai_request(&mut openai_req, budget) {
+            info!(
+                "[OpenAI] [Context-Trim] Dropped {} oldest messages for {} (~{} -> ~{} tokens, budget {})",
+                outcome.dropped, mapped_model, outcome.before, outcome.after, budget
+            );
+        }
+    }
+    let mut project_rediscovered = false;
+    let malformed_retry_enabled = state.experimental.read().await.enable_malformed_call_retry;
+    let mut malformed_retry_used = false;
+
     for attempt in 0..max_attempts {
         // 将 OpenAI 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = openai_req
@@ -184,11 +244,12 @@ pub async fn handle_chat_completions(
         // 4. 获取 Token (使用准确的 request_type)
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
         let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-            .get_token(
+            .get_token_for_key(
                 &config.request_type,
                 attempt > 0,
                 Some(&session_id),
                 &mapped_model,
+                client_api_key.as_deref(),
             )
             .await
         {
@@ -196,12 +257,8 @@ pub async fn handle_chat_completions(
             Err(e) => {
                 // [FIX] Attach headers to error response for logging visibility
                 let headers = [("X-Mapped-Model", mapped_model.as_str())];
-                return Ok((
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    headers,
-                    format!("Token error: {}", e),
-                )
-                    .into_response());
+                let (status, body) = super::common::token_error_response(ErrorProtocol::OpenAI, &e);
+                return Ok((status, headers, body).into_response());
             }
         };
 
@@ -247,6 +304,9 @@ pub async fn handle_chat_completions(
         let client_wants_stream = openai_req.stream;
         let force_stream_internally = !client_wants_stream;
         let actual_stream = client_wants_stream || force_stream_internally;
+        // 内部强制流式 (非流式请求) 时用量仍附在结束 chunk 上，供聚合器读取
+        let include_usage = client_wants_stream
+            && openai_req.stream_options.as_ref().is_some_and(|o| o.include_usage);
 
         if force_stream_internally {
             debug!(
@@ -353,13 +413,29 @@ pub async fn handle_chat_completions(
                     "upstream_url": upstream_url,
                 });
                 let gemini_stream = debug_logger::wrap_stream_with_debug(
-                    Box::pin(response.bytes_stream()),
+                    Box::pin(upstream.body_stream(response)),
                     debug_cfg.clone(),
                     trace_id.clone(),
                     "upstream_response",
                     meta,
                 );
 
+                // [NEW] 首个候选块为 MALFORMED_FUNCTION_CALL 时带纠正提示重试一次
+                let gemini_stream = if malformed_retry_enabled && !malformed_retry_used && attempt + 1 < max_attempts {
+                    match malformed_call::peek_stream(gemini_stream).await {
+                        Ok(stream) => stream,
+                        Err(message) => {
+                            tracing::warn!("[{}] MALFORMED_FUNCTION_CALL ({}), retrying once with corrective hint", trace_id, message);
+                            malformed_retry_used = true;
+                            crate::proxy::common::system_prompt::inject_openai(&mut openai_req, &malformed_call::corrective_rule());
+                            last_error = format!("MALFORMED_FUNCTION_CALL: {}", message);
+                            continue;
+                        }
+                    }
+                } else {
+                    gemini_stream
+                };
+
                 // [P1 FIX] Enhanced Peek logic to handle heartbeats and slow start
                 // Pre-read until we find meaningful content, skip heartbeats
                 use crate::proxy::mappers::openai::streaming::create_openai_sse_stream;
@@ -368,6 +444,7 @@ pub async fn handle_chat_completions(
                     openai_req.model.clone(),
                     session_id,
                     message_count,
+                    include_usage,
                 );
 
                 let mut first_data_chunk = None;
@@ -489,6 +566,17 @@ pub async fn handle_chat_completions(
                 .await
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
 
+            // [NEW] MALFORMED_FUNCTION_CALL: 带纠正提示重试一次
+            if malformed_retry_enabled && !malformed_retry_used && attempt + 1 < max_attempts {
+                if let Some(message) = malformed_call::malformed_call_message(&gemini_resp) {
+                    tracing::warn!("[{}] MALFORMED_FUNCTION_CALL ({}), retrying once with corrective hint", trace_id, message);
+                    malformed_retry_used = true;
+                    crate::proxy::common::system_prompt::inject_openai(&mut openai_req, &malformed_call::corrective_rule());
+                    last_error = format!("MALFORMED_FUNCTION_CALL: {}", message);
+                    continue;
+                }
+            }
+
             let openai_response =
                 transform_openai_response(&gemini_resp, Some(&session_id), message_count);
             return Ok((
@@ -544,6 +632,33 @@ pub async fn handle_chat_completions(
             .await;
         }
 
+        // [NEW] project_id 失效 (403/404): 重新发现后重试，不标记 forbidden
+        if try_rediscover_project(
+            &token_manager,
+            &account_id,
+            status_code,
+            &error_text,
+            &mut project_rediscovered,
+            &trace_id,
+        )
+        .await
+            && apply_retry_strategy(
+                RetryStrategy::FixedDelay(Duration::from_millis(200)),
+                attempt,
+                max_attempts,
+                status_code,
+                &trace_id,
+            )
+            .await
+        {
+            continue;
+        }
+
+        // [NEW] 连续 401/403 计数，达到阈值后自动停用账号
+        if status_code == 401 || status_code == 403 {
+            token_manager.record_auth_failure(&account_id, status_code, &error_text).await;
+        }
+
         // 确定重试策略
         let strategy = determine_retry_strategy(status_code, &error_text, false);
 
@@ -723,13 +838,7 @@ pub async fn handle_chat_completions(
                 ("X-Mapped-Model", mapped_model.as_str()),
             ],
             // [FIX] Return JSON error for better client compatibility
-            Json(json!({
-                "error": {
-                    "message": error_text,
-                    "type": "upstream_error",
-                    "code": status_code
-                }
-            })),
+            Json(map_upstream_error(ErrorProtocol::OpenAI, status_code, &error_text)),
         )
             .into_response());
     }
@@ -739,14 +848,24 @@ pub async fn handle_chat_completions(
         Ok((
             StatusCode::TOO_MANY_REQUESTS,
             [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+            Json(build_error_body(
+                ErrorProtocol::OpenAI,
+                429,
+                &format!("All accounts exhausted. Last error: {}", last_error),
+                None,
+            )),
         )
             .into_response())
     } else {
         Ok((
             StatusCode::TOO_MANY_REQUESTS,
             [("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+            Json(build_error_body(
+                ErrorProtocol::OpenAI,
+                429,
+                &format!("All accounts exhausted. Last error: {}", last_error),
+                None,
+            )),
         )
             .into_response())
     }
@@ -756,14 +875,22 @@ pub async fn handle_chat_completions(
 /// 将 Prompt 转换为 Chat Message 格式，复用 handle_chat_completions
 pub async fn handle_completions(
     State(state): State<AppState>,
-    Json(mut body): Json<Value>,
+    headers: HeaderMap,
+    OpenAIJson(mut body): OpenAIJson,
 ) -> Response {
     debug!(
         "Received /v1/completions or /v1/responses payload: {:?}",
         body
     );
+    crate::proxy::common::model_mapping::apply_model_override(&headers, &mut body);
 
     let is_codex_style = body.get("input").is_some() || body.get("instructions").is_some();
+    // echo=true 时在补全结果前回显原始 prompt (仅 legacy completions)
+    let echo_prompt = if !is_codex_style && body.get("echo").and_then(|v| v.as_bool()) == Some(true) {
+        body.get("prompt").and_then(legacy_prompt_text)
+    } else {
+        None
+    };
 
     // 1. Convert Payload to Messages (Shared Chat Format)
     if is_codex_style {
@@ -854,6 +981,13 @@ pub async fn handle_completions(
                                         }));
                                     }
                                 }
+                                // [NEW] 文件 / 音频输入 (input_file / file / input_audio)，原样保留交给后续转换
+                                else if matches!(
+                                    part.get("type").and_then(|v| v.as_str()),
+                                    Some("input_file") | Some("file") | Some("input_audio")
+                                ) {
+                                    image_parts.push(part.clone());
+                                }
                             }
                         }
 
@@ -990,21 +1124,22 @@ pub async fn handle_completions(
         if let Some(obj) = body.as_object_mut() {
             obj.insert("messages".to_string(), json!(messages));
         }
-    } else if let Some(prompt_val) = body.get("prompt") {
-        // Legacy OpenAI Style: prompt -> Chat
-        let prompt_str = match prompt_val {
-            Value::String(s) => s.clone(),
-            Value::Array(arr) => arr
-                .iter()
-                .filter_map(|v| v.as_str())
-                .collect::<Vec<_>>()
-                .join("\n"),
-            _ => prompt_val.to_string(),
+    } else if body.get("prompt").is_some() {
+        // Legacy OpenAI Style: prompt (+ suffix) -> Chat
+        let messages = match legacy_prompt_to_messages(&body) {
+            Ok(m) => m,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(build_error_body(ErrorProtocol::OpenAI, 400, &e, None)),
+                )
+                    .into_response();
+            }
         };
-        let messages = json!([ { "role": "user", "content": prompt_str } ]);
         if let Some(obj) = body.as_object_mut() {
             obj.remove("prompt");
-            obj.insert("messages".to_string(), messages);
+            obj.remove("suffix");
+            obj.insert("messages".to_string(), json!(messages));
         }
     }
 
@@ -1107,12 +1242,27 @@ pub async fn handle_completions(
         );
     }
 
-    let mut openai_req: OpenAIRequest = match serde_json::from_value(body.clone()) {
+    let mut openai_req = match parse_openai_request(&body) {
         Ok(req) => req,
         Err(e) => {
-            return (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)).into_response();
+            debug!("[Codex] {}", e.describe());
+            return e.into_response(ErrorProtocol::OpenAI);
         }
     };
+    if let Some(rule) = crate::proxy::common::system_prompt::rule_for_headers(&headers) {
+        crate::proxy::common::system_prompt::inject_openai(&mut openai_req, &rule);
+    }
+    crate::proxy::common::image_fetch::inline_openai_image_urls(&mut openai_req).await;
+    crate::proxy::common::document::inline_openai_file_urls(&mut openai_req).await;
+    if let Err(e) = crate::proxy::common::document::validate_openai_request(&openai_req)
+        .and_then(|_| crate::proxy::audio::AudioProcessor::validate_openai_request(&openai_req))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(build_error_body(ErrorProtocol::OpenAI, 400, &format!("Invalid request: {}", e), None)),
+        )
+            .into_response();
+    }
 
     // Safety: Inject empty message if needed
     if openai_req.messages.is_empty() {
@@ -1127,11 +1277,13 @@ pub async fn handle_completions(
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             });
     }
 
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
+    let client_api_key = crate::proxy::middleware::client_rate_limit::api_key_from_headers(&headers); // [NEW] 用于按 Key 的账号分组路由
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
     let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
@@ -1144,7 +1296,7 @@ pub async fn handle_completions(
         &openai_req.model,
         &*state.custom_mapping.read().await,
     );
-    let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
+    let trace_id = request_id_from_headers(&headers);
 
     for attempt in 0..max_attempts {
         // 3. 模型配置解析
@@ -1172,22 +1324,19 @@ pub async fn handle_completions(
         let force_rotate = attempt > 0;
 
         let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-            .get_token(
+            .get_token_for_key(
                 &config.request_type,
                 force_rotate,
                 session_id,
                 &mapped_model,
+                client_api_key.as_deref(),
             )
             .await
         {
             Ok(t) => t,
             Err(e) => {
-                return (
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    [("X-Mapped-Model", mapped_model)],
-                    format!("Token error: {}", e),
-                )
-                    .into_response()
+                let (status, body) = super::common::token_error_response(ErrorProtocol::OpenAI, &e);
+                return (status, [("X-Mapped-Model", mapped_model)], body).into_response();
             }
         };
 
@@ -1217,6 +1366,8 @@ pub async fn handle_completions(
         let client_wants_stream = openai_req.stream;
         let force_stream_internally = !client_wants_stream;
         let list_response = client_wants_stream || force_stream_internally;
+        let include_usage = client_wants_stream
+            && openai_req.stream_options.as_ref().is_some_and(|o| o.include_usage);
         let method = if list_response {
             "streamGenerateContent"
         } else {
@@ -1258,7 +1409,7 @@ pub async fn handle_completions(
                 use axum::response::Response;
                 use futures::StreamExt;
 
-                let gemini_stream = response.bytes_stream();
+                let gemini_stream = upstream.body_stream(response);
 
                 // DECISION: Which stream to create?
                 // If client wants stream: give them what they asked (Legacy/Codex SSE).
@@ -1281,6 +1432,7 @@ pub async fn handle_completions(
                             openai_req.model.clone(),
                             session_id,
                             message_count,
+                            include_usage,
                         )
                     };
 
@@ -1335,10 +1487,22 @@ pub async fn handle_completions(
                         continue;
                     }
 
-                    let combined_stream = futures::stream::once(async move {
-                        Ok::<Bytes, String>(first_data_chunk.unwrap())
-                    })
-                    .chain(openai_stream);
+                    // echo 在流的第一个分片中回显 prompt
+                    let echo_chunk = echo_prompt.as_ref().map(|prompt| {
+                        let chunk = json!({
+                            "id": "cmpl-echo",
+                            "object": "text_completion",
+                            "created": chrono::Utc::now().timestamp(),
+                            "model": openai_req.model,
+                            "choices": [{ "text": prompt, "index": 0, "logprobs": null, "finish_reason": null }]
+                        });
+                        Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", chunk)))
+                    });
+                    let combined_stream = futures::stream::iter(echo_chunk)
+                        .chain(futures::stream::once(async move {
+                            Ok::<Bytes, String>(first_data_chunk.unwrap())
+                        }))
+                        .chain(openai_stream);
 
                     return Response::builder()
                         .header("Content-Type", "text/event-stream")
@@ -1360,6 +1524,7 @@ pub async fn handle_completions(
                         openai_req.model.clone(),
                         session_id,
                         message_count,
+                        false,
                     );
 
                     // Peek Logic (Repeated for safety/correctness on this stream type)
@@ -1420,27 +1585,8 @@ pub async fn handle_completions(
                     use crate::proxy::mappers::openai::collector::collect_stream_to_json;
                     match collect_stream_to_json(Box::pin(combined_stream)).await {
                         Ok(chat_resp) => {
-                            // NOW: Convert Chat Response -> Legacy Response (Same logic as below)
-                            let choices = chat_resp.choices.iter().map(|c| {
-                                json!({
-                                    "text": match &c.message.content {
-                                        Some(crate::proxy::mappers::openai::OpenAIContent::String(s)) => s.clone(),
-                                        _ => "".to_string()
-                                    },
-                                    "index": c.index,
-                                    "logprobs": null,
-                                    "finish_reason": c.finish_reason
-                                })
-                            }).collect::<Vec<_>>();
-
-                            let legacy_resp = json!({
-                                "id": chat_resp.id,
-                                "object": "text_completion",
-                                "created": chat_resp.created,
-                                "model": chat_resp.model,
-                                "choices": choices,
-                                "usage": chat_resp.usage
-                            });
+                            // NOW: Convert Chat Response -> Legacy Response
+                            let legacy_resp = chat_to_legacy_completion(&chat_resp, echo_prompt.as_deref());
 
                             return (
                                 StatusCode::OK,
@@ -1478,26 +1624,7 @@ pub async fn handle_completions(
             let chat_resp = transform_openai_response(&gemini_resp, Some("session-123"), 1);
 
             // Map Chat Response -> Legacy Completions Response
-            let choices = chat_resp.choices.iter().map(|c| {
-                json!({
-                    "text": match &c.message.content {
-                        Some(crate::proxy::mappers::openai::OpenAIContent::String(s)) => s.clone(),
-                        _ => "".to_string()
-                    },
-                    "index": c.index,
-                    "logprobs": null,
-                    "finish_reason": c.finish_reason
-                })
-            }).collect::<Vec<_>>();
-
-            let legacy_resp = json!({
-                "id": chat_resp.id,
-                "object": "text_completion",
-                "created": chat_resp.created,
-                "model": chat_resp.model,
-                "choices": choices,
-                "usage": chat_resp.usage
-            });
+            let legacy_resp = chat_to_legacy_completion(&chat_resp, echo_prompt.as_deref());
 
             return (
                 StatusCode::OK,
@@ -1529,6 +1656,11 @@ pub async fn handle_completions(
             error_text
         );
 
+        // [NEW] 连续 401/403 计数，达到阈值后自动停用账号
+        if status_code == 401 || status_code == 403 {
+            token_manager.record_auth_failure(&account_id, status_code, &error_text).await;
+        }
+
         // 3. 标记限流状态(用于 UI 显示)
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
             token_manager
@@ -1556,7 +1688,7 @@ pub async fn handle_completions(
                     ("X-Account-Email", email.as_str()),
                     ("X-Mapped-Model", mapped_model.as_str()),
                 ],
-                error_text,
+                Json(map_upstream_error(ErrorProtocol::OpenAI, status_code, &error_text)),
             )
                 .into_response();
         }
@@ -1567,40 +1699,156 @@ pub async fn handle_completions(
         (
             StatusCode::TOO_MANY_REQUESTS,
             [("X-Account-Email", email), ("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+            Json(build_error_body(
+                ErrorProtocol::OpenAI,
+                429,
+                &format!("All accounts exhausted. Last error: {}", last_error),
+                None,
+            )),
         )
             .into_response()
     } else {
         (
             StatusCode::TOO_MANY_REQUESTS,
             [("X-Mapped-Model", mapped_model)],
-            format!("All accounts exhausted. Last error: {}", last_error),
+            Json(build_error_body(
+                ErrorProtocol::OpenAI,
+                429,
+                &format!("All accounts exhausted. Last error: {}", last_error),
+                None,
+            )),
         )
             .into_response()
     }
 }
 
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
+/// legacy prompt 转为文本: 字符串或字符串数组 (多个 prompt 合并为一条)，token 数组无法还原
+fn legacy_prompt_text(prompt: &Value) -> Option<String> {
+    match prompt {
+        Value::String(s) => Some(s.clone()),
+        Value::Array(arr) if arr.iter().all(|v| v.is_string()) => Some(
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => None,
+    }
+}
+
+/// 将 /v1/completions 的 prompt / suffix 转为 Chat 消息
+/// 带 suffix 时视为 fill-in-the-middle：要求模型只输出前后缀之间缺失的内容
+fn legacy_prompt_to_messages(body: &Value) -> Result<Vec<Value>, String> {
+    let prompt = body
+        .get("prompt")
+        .and_then(legacy_prompt_text)
+        .ok_or_else(|| "prompt must be a string or an array of strings (token arrays are not supported)".to_string())?;
+    let suffix = body
+        .get("suffix")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty());
 
-    let model_ids = get_all_dynamic_models(&state.custom_mapping, Some(&state.token_manager)).await;
+    Ok(match suffix {
+        Some(suffix) => vec![
+            json!({
+                "role": "system",
+                "content": "You are a code and text completion engine. Output ONLY the text that belongs between <prefix> and <suffix>. Do not repeat the prefix or suffix, and do not add explanations or markdown fences."
+            }),
+            json!({
+                "role": "user",
+                "content": format!("<prefix>{}</prefix><suffix>{}</suffix>", prompt, suffix)
+            }),
+        ],
+        None => vec![json!({ "role": "user", "content": prompt })],
+    })
+}
 
-    let data: Vec<_> = model_ids
-        .into_iter()
-        .map(|id| {
+/// Chat Completions 响应 -> legacy text_completion 响应
+fn chat_to_legacy_completion(
+    chat_resp: &crate::proxy::mappers::openai::OpenAIResponse,
+    echo: Option<&str>,
+) -> Value {
+    let choices = chat_resp
+        .choices
+        .iter()
+        .map(|c| {
+            let text = match &c.message.content {
+                Some(crate::proxy::mappers::openai::OpenAIContent::String(s)) => s.clone(),
+                _ => String::new(),
+            };
             json!({
-                "id": id,
-                "object": "model",
-                "created": 1706745600,
-                "owned_by": "antigravity"
+                "text": format!("{}{}", echo.unwrap_or_default(), text),
+                "index": c.index,
+                "logprobs": null,
+                "finish_reason": c.finish_reason
             })
         })
-        .collect();
+        .collect::<Vec<_>>();
+
+    json!({
+        "id": chat_resp.id.replacen("chatcmpl-", "cmpl-", 1),
+        "object": "text_completion",
+        "created": chat_resp.created,
+        "model": chat_resp.model,
+        "choices": choices,
+        "usage": chat_resp.usage
+    })
+}
+
+fn openai_model_entry(model: &crate::proxy::model_registry::ModelInfo) -> Value {
+    json!({
+        "id": model.id,
+        "object": "model",
+        "created": crate::proxy::model_registry::MODEL_CREATED_AT,
+        "owned_by": "antigravity",
+        "root": model.target,
+        "context_window": model.context_window,
+        "max_output_tokens": model.max_output_tokens,
+        "capabilities": {
+            "vision": model.supports_vision,
+            "tools": model.supports_tools,
+            "thinking": model.supports_thinking
+        }
+    })
+}
+
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: axum::extract::Query<super::claude::ListModelsQuery>,
+) -> Response {
+    // [NEW] Anthropic SDK 同样请求 /v1/models (携带 anthropic-version 头)，按 Anthropic 格式返回
+    if headers.contains_key("anthropic-version") {
+        return super::claude::handle_list_models(State(state), query)
+            .await
+            .into_response();
+    }
+    // 别名 + 底层 Gemini 模型 + 图像分辨率/比例变体 + -online 联网变体
+    let models = crate::proxy::model_registry::with_online_variants(
+        crate::proxy::model_registry::list_models(&state.custom_mapping, &state.token_manager).await,
+    );
+    let data: Vec<_> = models.iter().map(openai_model_entry).collect();
 
     Json(json!({
         "object": "list",
         "data": data
     }))
+    .into_response()
+}
+
+/// GET /v1/models/:model
+pub async fn handle_get_model(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(model): Path<String>,
+) -> Response {
+    if headers.contains_key("anthropic-version") {
+        return super::claude::handle_get_model(State(state), Path(model))
+            .await
+            .into_response();
+    }
+    let info = crate::proxy::model_registry::get_model(&model, &state.custom_mapping).await;
+    Json(openai_model_entry(&info)).into_response()
 }
 
 /// OpenAI Images API: POST /v1/images/generations
@@ -1608,9 +1856,9 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
 pub async fn handle_chat_redirection(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(body): Json<Value>,
+    OpenAIJson(body): OpenAIJson,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    handle_chat_completions(State(state), headers, Json(body)).await
+    handle_chat_completions(State(state), headers, OpenAIJson(body)).await
 }
 
 async fn intercept_chat_to_image(
@@ -1742,11 +1990,14 @@ pub async fn handle_images_generations(
     State(state): State<AppState>,
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let mapped_model = crate::proxy::mappers::common_utils::resolve_image_gen_model(
+        body.get("model").and_then(|v| v.as_str()).unwrap_or(""),
+    );
     match handle_images_generations_internal(state, body).await {
         Ok((email_header, openai_response)) => Ok((
             StatusCode::OK,
             [
-                ("X-Mapped-Model", "dall-e-3"),
+                ("X-Mapped-Model", mapped_model.as_str()),
                 ("X-Account-Email", email_header.as_str()),
             ],
             Json(openai_response),
@@ -1765,13 +2016,24 @@ pub async fn handle_images_generations_internal(
         StatusCode::BAD_REQUEST,
         "Missing 'prompt' field".to_string(),
     ))?;
+    if prompt.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "'prompt' must not be empty".to_string()));
+    }
 
-    let model = body
-        .get("model")
-        .and_then(|v| v.as_str())
-        .unwrap_or("gemini-3-pro-image");
-
-    let n = body.get("n").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    // [NEW] dall-e-3 / gpt-image-1 等 OpenAI 模型名统一映射到 Gemini 图像模型
+    let requested_model = body.get("model").and_then(|v| v.as_str()).unwrap_or("");
+    let model = crate::proxy::mappers::common_utils::resolve_image_gen_model(requested_model);
+    let model = model.as_str();
+
+    // OpenAI Images API 限制 n 在 1..=10 之间
+    let n = body.get("n").and_then(|v| v.as_u64()).unwrap_or(1);
+    if !(1..=10).contains(&n) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("'n' must be between 1 and 10, got {}", n),
+        ));
+    }
+    let n = n as usize;
 
     let size = body
         .get("size")
@@ -1781,6 +2043,12 @@ pub async fn handle_images_generations_internal(
         .get("response_format")
         .and_then(|v| v.as_str())
         .unwrap_or("b64_json");
+    if response_format != "b64_json" && response_format != "url" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unsupported response_format '{}', expected 'url' or 'b64_json'", response_format),
+        ));
+    }
 
     let quality = body
         .get("quality")
@@ -1797,8 +2065,9 @@ pub async fn handle_images_generations_internal(
         .unwrap_or("vivid");
 
     info!(
-        "[Images] Received request: model={}, prompt={:.50}..., n={}, size={}, quality={}, style={}",
+        "[Images] Received request: model={} (requested: {}), prompt={:.50}..., n={}, size={}, quality={}, style={}",
         model,
+        requested_model,
         prompt,
         n,
         size.unwrap_or("auto"),
@@ -1880,13 +2149,7 @@ pub async fn handle_images_generations_internal(
                             "candidateCount": 1, // 强制单张
                             "imageConfig": image_config // ✅ 使用完整配置（包含 aspectRatio 和 imageSize）
                         },
-                        "safetySettings": [
-                            { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": "OFF" },
-                        ]
+                        "safetySettings": crate::proxy::safety_settings::build_safety_settings(&model_to_use)
                     }
                 });
 
@@ -1921,7 +2184,7 @@ pub async fn handle_images_generations_internal(
                                         status_code,
                                         None,
                                         &err_text,
-                                        Some("dall-e-3"),
+                                        Some(&model_to_use),
                                     )
                                     .await;
                                 continue; // Retry loop
@@ -2013,7 +2276,10 @@ pub async fn handle_images_generations_internal(
         tracing::error!("[Images] All {} requests failed. Errors: {}", n, error_msg);
 
         // [FIX] Map upstream status codes correctly instead of forcing 502
-        let status = if error_msg.contains("429") || error_msg.contains("Quota exhausted") {
+        let status = if error_msg.contains("429")
+            || error_msg.contains("Quota exhausted")
+            || error_msg.contains(crate::proxy::token_manager::USAGE_LIMIT_ERROR_PREFIX)
+        {
             StatusCode::TOO_MANY_REQUESTS
         } else if error_msg.contains("503") || error_msg.contains("Service Unavailable") {
             StatusCode::SERVICE_UNAVAILABLE
@@ -2281,13 +2547,7 @@ pub async fn handle_images_edits(
                             "topP": 0.95,
                             "topK": 40
                         },
-                        "safetySettings": [
-                            { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": "OFF" },
-                        ]
+                        "safetySettings": crate::proxy::safety_settings::build_safety_settings(&model)
                     }
                 });
 
@@ -2445,3 +2705,44 @@ pub async fn handle_images_edits(
     )
         .into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_prompt_to_messages_with_suffix_uses_fim_template() {
+        let plain = legacy_prompt_to_messages(&json!({ "prompt": ["a", "b"] })).unwrap();
+        assert_eq!(plain, vec![json!({ "role": "user", "content": "a\nb" })]);
+
+        let fim = legacy_prompt_to_messages(&json!({ "prompt": "fn main() {", "suffix": "}" })).unwrap();
+        assert_eq!(fim.len(), 2);
+        assert_eq!(fim[0]["role"], "system");
+        assert_eq!(fim[1]["content"], "<prefix>fn main() {</prefix><suffix>}</suffix>");
+
+        // token 数组无法还原为文本
+        assert!(legacy_prompt_to_messages(&json!({ "prompt": [1, 2, 3] })).is_err());
+    }
+
+    #[test]
+    fn test_chat_to_legacy_completion_with_echo() {
+        let chat: crate::proxy::mappers::openai::OpenAIResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-abc",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gemini-2.5-flash",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": " world" },
+                "finish_reason": "stop"
+            }]
+        }))
+        .unwrap();
+
+        let legacy = chat_to_legacy_completion(&chat, Some("hello"));
+        assert_eq!(legacy["id"], "cmpl-abc");
+        assert_eq!(legacy["object"], "text_completion");
+        assert_eq!(legacy["choices"][0]["text"], "hello world");
+        assert_eq!(legacy["choices"][0]["finish_reason"], "stop");
+    }
+}
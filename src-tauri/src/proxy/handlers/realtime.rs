@@ -0,0 +1,267 @@
+// WebSocket 流式桥接
+// 为偏好 WebSocket 的 TUI / 编辑器插件提供简单 JSON 协议，内部复用 OpenAI Chat 流水线
+// (账号轮换、模型映射、重试均与 /v1/chat/completions 一致)，将 SSE 数据块逐条转发为 WS 消息。
+//
+// 客户端 -> 服务端:
+//   {"type":"chat.request","id":"r1","request":{...OpenAI Chat 请求体...}}
+//   {"type":"chat.cancel","id":"r1"}
+//   {"type":"ping"}
+// 服务端 -> 客户端:
+//   {"type":"chat.delta","id":"r1","data":{...chat.completion.chunk...}}
+//   {"type":"chat.done","id":"r1"}
+//   {"type":"error","id":"r1","error":{"status":400,"message":"..."}}
+//   {"type":"pong"}
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tracing::{debug, info, warn};
+
+use crate::proxy::server::AppState;
+
+/// 服务端主动 Ping 间隔
+const PING_INTERVAL_SECS: u64 = 20;
+/// 超过该时长未收到任何客户端消息 (含 Pong) 则断开
+const IDLE_TIMEOUT_SECS: u64 = 90;
+/// [NEW] 待写出消息的有界缓冲；客户端读得慢时反压上游 SSE 读取，而不是在内存中无限堆积
+const SEND_BUFFER_SIZE: usize = 64;
+
+pub async fn handle_realtime_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| run_session(socket, state, headers))
+}
+
+fn error_message(id: Option<&str>, status: u16, message: &str) -> Value {
+    json!({
+        "type": "error",
+        "id": id,
+        "error": { "status": status, "message": message }
+    })
+}
+
+async fn run_session(socket: WebSocket, state: AppState, headers: HeaderMap) {
+    let session_id = uuid::Uuid::new_v4().simple().to_string();
+    info!("[Realtime] WebSocket session {} opened", &session_id[..8]);
+
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<Message>(SEND_BUFFER_SIZE);
+
+    // 发送任务：统一写出所有消息，并定时 Ping 保活
+    let writer = tokio::spawn(async move {
+        let mut ping = tokio::time::interval(Duration::from_secs(PING_INTERVAL_SECS));
+        ping.tick().await;
+        loop {
+            tokio::select! {
+                msg = rx.recv() => {
+                    let Some(msg) = msg else { break };
+                    let is_close = matches!(msg, Message::Close(_));
+                    if sink.send(msg).await.is_err() || is_close {
+                        break;
+                    }
+                }
+                _ = ping.tick() => {
+                    if sink.send(Message::Ping(Vec::new())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let mut in_flight: HashMap<String, AbortHandle> = HashMap::new();
+    loop {
+        let next = tokio::time::timeout(Duration::from_secs(IDLE_TIMEOUT_SECS), stream.next()).await;
+        let msg = match next {
+            Ok(Some(Ok(msg))) => msg,
+            Ok(Some(Err(e))) => {
+                debug!("[Realtime] Session {} read error: {}", &session_id[..8], e);
+                break;
+            }
+            Ok(None) => break,
+            Err(_) => {
+                warn!("[Realtime] Session {} idle timeout", &session_id[..8]);
+                let _ = tx.send(Message::Close(None)).await;
+                break;
+            }
+        };
+
+        let text = match msg {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            // Ping 由 axum 自动回复 Pong；Pong 只用于刷新空闲计时
+            _ => continue,
+        };
+
+        let payload: Value = match serde_json::from_str(&text) {
+            Ok(v) => v,
+            Err(e) => {
+                send_json(&tx, error_message(None, 400, &format!("Invalid JSON: {}", e))).await;
+                continue;
+            }
+        };
+        let id = payload.get("id").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        in_flight.retain(|_, handle| !handle.is_finished());
+        match payload.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+            "ping" => send_json(&tx, json!({ "type": "pong" })).await,
+            "chat.request" => {
+                let id = id.unwrap_or_else(|| format!("req_{}", uuid::Uuid::new_v4().simple()));
+                if in_flight.contains_key(&id) {
+                    send_json(&tx, error_message(Some(&id), 409, "Request id already in flight")).await;
+                    continue;
+                }
+                let Some(request) = payload.get("request").cloned().filter(|r| r.is_object()) else {
+                    send_json(&tx, error_message(Some(&id), 400, "Missing request object")).await;
+                    continue;
+                };
+                let task = tokio::spawn(bridge_chat_request(
+                    state.clone(),
+                    headers.clone(),
+                    id.clone(),
+                    request,
+                    tx.clone(),
+                ));
+                in_flight.insert(id, task.abort_handle());
+            }
+            "chat.cancel" => {
+                if let Some(handle) = id.as_ref().and_then(|id| in_flight.remove(id)) {
+                    handle.abort();
+                    send_json(&tx, json!({ "type": "chat.cancelled", "id": id })).await;
+                }
+            }
+            other => send_json(
+                &tx,
+                error_message(id.as_deref(), 400, &format!("Unknown message type: {}", other)),
+            )
+            .await,
+        }
+    }
+
+    for handle in in_flight.values() {
+        handle.abort();
+    }
+    drop(tx);
+    let _ = writer.await;
+    info!("[Realtime] WebSocket session {} closed", &session_id[..8]);
+}
+
+/// 写入发送队列；队列满时等待，从而把客户端的读取速度反压到上游
+async fn send_json(tx: &mpsc::Sender<Message>, value: Value) {
+    let _ = tx.send(Message::Text(value.to_string())).await;
+}
+
+/// 通过 OpenAI Chat 流水线执行一次流式请求，并把 SSE 数据块转发为 WS 消息
+async fn bridge_chat_request(
+    state: AppState,
+    headers: HeaderMap,
+    id: String,
+    mut request: Value,
+    tx: mpsc::Sender<Message>,
+) {
+    request["stream"] = json!(true);
+
+    let response = match super::openai::handle_chat_completions(
+        State(state),
+        headers,
+        crate::proxy::common::request_validation::OpenAIJson(request),
+    )
+    .await {
+        Ok(resp) => resp.into_response(),
+        Err(err) => err.into_response(),
+    };
+
+    let status = response.status();
+    let mut body = response.into_body().into_data_stream();
+    if !status.is_success() {
+        let mut text = Vec::new();
+        while let Some(Ok(chunk)) = body.next().await {
+            text.extend_from_slice(&chunk);
+        }
+        let message = String::from_utf8_lossy(&text);
+        send_json(&tx, error_message(Some(&id), status.as_u16(), &message)).await;
+        return;
+    }
+
+    let mut decoder = crate::proxy::common::sse_decoder::SseLineDecoder::new();
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => {
+                send_json(&tx, error_message(Some(&id), 502, &format!("Stream error: {}", e))).await;
+                return;
+            }
+        };
+        for line in decoder.push(&chunk) {
+            if let Some(done) = forward_sse_line(&tx, &id, line.trim()).await {
+                if done {
+                    send_json(&tx, json!({ "type": "chat.done", "id": id })).await;
+                    return;
+                }
+            }
+        }
+    }
+    if let Some(line) = decoder.finish() {
+        forward_sse_line(&tx, &id, line.trim()).await;
+    }
+    send_json(&tx, json!({ "type": "chat.done", "id": id })).await;
+}
+
+/// 转发单行 SSE 数据；返回 Some(true) 表示收到 [DONE]
+async fn forward_sse_line(tx: &mpsc::Sender<Message>, id: &str, line: &str) -> Option<bool> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data == "[DONE]" {
+        return Some(true);
+    }
+    match serde_json::from_str::<Value>(data) {
+        Ok(chunk) if chunk.get("error").is_some() => {
+            let message = chunk["error"]
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("Upstream error")
+                .to_string();
+            send_json(tx, error_message(Some(id), 502, &message)).await;
+        }
+        Ok(chunk) => send_json(tx, json!({ "type": "chat.delta", "id": id, "data": chunk })).await,
+        Err(_) => debug!("[Realtime] Skipping non-JSON SSE line"),
+    }
+    Some(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_forward_sse_line() {
+        let (tx, mut rx) = mpsc::channel(SEND_BUFFER_SIZE);
+
+        assert_eq!(forward_sse_line(&tx, "r1", ": keep-alive").await, None);
+        assert_eq!(
+            forward_sse_line(&tx, "r1", r#"data: {"choices":[{"delta":{"content":"hi"}}]}"#).await,
+            Some(false)
+        );
+        assert_eq!(forward_sse_line(&tx, "r1", "data: [DONE]").await, Some(true));
+
+        let Message::Text(text) = rx.try_recv().unwrap() else {
+            panic!("expected text message");
+        };
+        let msg: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(msg["type"], "chat.delta");
+        assert_eq!(msg["id"], "r1");
+        assert_eq!(msg["data"]["choices"][0]["delta"]["content"], "hi");
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -131,6 +131,8 @@ pub async fn handle_warmup(
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         match crate::proxy::mappers::claude::transform_claude_request_in(
@@ -256,6 +258,7 @@ pub async fn handle_warmup(
                 response_body: None,
                 input_tokens: Some(0),
                 output_tokens: Some(0),
+                thoughts_tokens: None,
                 protocol: Some("warmup".to_string()),
                 username: None,
             };
@@ -350,6 +353,7 @@ pub async fn handle_warmup(
                 response_body: None,
                 input_tokens: None,
                 output_tokens: None,
+                thoughts_tokens: None,
                 protocol: Some("warmup".to_string()),
                 username: None,
             };
@@ -2,6 +2,7 @@
 // 用于非 Stream 请求的自动转换
 
 use super::models::*;
+use crate::proxy::common::sse_decoder::SseLineDecoder;
 use bytes::Bytes;
 use futures::StreamExt;
 use serde_json::{json, Value};
@@ -40,11 +41,22 @@ where
     let mut current_data = String::new();
 
     // 1. 收集所有 SSE 事件
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        let text = String::from_utf8_lossy(&chunk);
+    let mut decoder = SseLineDecoder::new();
+    let mut ended = false;
+    while !ended {
+        // 按字节缓冲成行再解码，避免跨分片的行 / 多字节字符被截断；结束时冲刷最后一行
+        let lines = match stream.next().await {
+            Some(chunk_result) => {
+                let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+                decoder.push(&chunk)
+            }
+            None => {
+                ended = true;
+                decoder.finish().into_iter().collect()
+            }
+        };
 
-        for line in text.lines() {
+        for line in &lines {
             if line.is_empty() {
                 // 空行表示事件结束
                 if !current_data.is_empty() {
@@ -66,6 +78,15 @@ where
             }
         }
     }
+    // 最后一个事件之后没有空行时同样收下
+    if !current_data.is_empty() {
+        if let Ok(data) = serde_json::from_str::<Value>(&current_data) {
+            events.push(SseEvent {
+                event_type: current_event_type.clone(),
+                data,
+            });
+        }
+    }
 
     // 2. 重建 ClaudeResponse
     let mut response = ClaudeResponse {
@@ -127,6 +148,12 @@ where
                                 current_tool_use = Some(content_block.clone());
                                 current_tool_input.clear();
                             }
+                            "image" | "server_tool_use" | "web_search_tool_result" => {
+                                // 图片 / web search 块在 start 事件中已完整携带，直接收集
+                                if let Ok(block) = serde_json::from_value::<ContentBlock>(content_block.clone()) {
+                                    response.content.push(block);
+                                }
+                            }
                             _ => {}
                         }
                     }
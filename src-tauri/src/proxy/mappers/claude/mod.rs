@@ -10,7 +10,7 @@ pub mod thinking_utils;
 pub mod collector;
 
 pub use models::*;
-pub use request::{transform_claude_request_in, clean_cache_control_from_messages, merge_consecutive_messages};
+pub use request::{declares_web_search_tool, transform_claude_request_in, clean_cache_control_from_messages, merge_consecutive_messages};
 pub use response::transform_response;
 pub use streaming::{PartProcessor, StreamingState};
 pub use thinking_utils::{close_tool_loop_for_thinking, filter_invalid_thinking_blocks_with_family};
@@ -33,13 +33,14 @@ pub fn create_claude_sse_stream<S, E>(
     message_count: usize, // [NEW v4.0.0] Message count for rewind detection
     client_adapter: Option<std::sync::Arc<dyn ClientAdapter>>, // [NEW] Adapter reference
     registered_tool_names: Vec<String>, // [FIX #MCP] Tool names for fuzzy matching
+    web_search_blocks: bool, // [NEW] Emit grounding as web search blocks instead of Markdown citations
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> 
 where
     S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
     E: std::fmt::Display + Send + 'static,
 {
     use async_stream::stream;
-    use bytes::BytesMut;
+    use crate::proxy::common::sse_decoder::SseLineDecoder;
     use futures::StreamExt;
 
     Box::pin(stream! {
@@ -51,7 +52,9 @@ where
         state.estimated_prompt_tokens = estimated_prompt_tokens; // [FIX] Pass estimated tokens
         state.set_client_adapter(client_adapter); // [NEW] Set adapter
         state.set_registered_tool_names(registered_tool_names); // [FIX #MCP] Set tool names
-        let mut buffer = BytesMut::new();
+        state.thinking_output = crate::proxy::config::get_thinking_budget_config().claude_thinking_output;
+        state.web_search_blocks = web_search_blocks;
+        let mut decoder = SseLineDecoder::new();
 
         loop {
             // [NEW] 60秒心跳保活: 延长超时时间以增加网络抖动容错
@@ -64,31 +67,24 @@ where
                 Ok(Some(chunk_result)) => {
                     match chunk_result {
                         Ok(chunk) => {
-                            buffer.extend_from_slice(&chunk);
-
                             // Process complete lines
-                            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                                let line_raw = buffer.split_to(pos + 1);
-                                if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                                    let line = line_str.trim();
-                                    if line.is_empty() { continue; }
-
-                                    if let Some(sse_chunks) = process_sse_line(line, &mut state, &trace_id, &email) {
-                                        for sse_chunk in sse_chunks {
-                                            yield Ok(sse_chunk);
-                                        }
+                            for line_str in decoder.push(&chunk) {
+                                let line = line_str.trim();
+                                if line.is_empty() { continue; }
+
+                                if let Some(sse_chunks) = process_sse_line(line, &mut state, &trace_id, &email) {
+                                    for sse_chunk in sse_chunks {
+                                        yield Ok(sse_chunk);
                                     }
                                 }
                             }
                         }
                         Err(e) => {
-                            let error_json = serde_json::json!({
-                                "error": {
-                                    "message": format!("Stream error: {}", e),
-                                    "type": "stream_error"
-                                }
-                            });
-                            yield Ok(Bytes::from(format!("data: {}\n\n", error_json)));
+                            // [FIX] 发送标准 Anthropic `event: error`，让客户端识别截断而不是当作完整输出
+                            tracing::error!("[{}] Claude Stream Error: {}", trace_id, e);
+                            for chunk in state.emit_stream_error(&e.to_string()) {
+                                yield Ok(chunk);
+                            }
                             break;
                         }
                     }
@@ -103,25 +99,22 @@ where
         
         // [FIX #1732] Mandatory Flush remaining buffer on stream termination
         // Prevents hangs when the last SSE chunk doesn't end with a newline (network fragmentation)
-        if !buffer.is_empty() {
-             if let Ok(line_str) = std::str::from_utf8(&buffer) {
-                 let line = line_str.trim();
-                 if !line.is_empty() {
-                     tracing::debug!("[{}] SSE Termination: Flushing remaining {} bytes in buffer", trace_id, buffer.len());
-                     if let Some(sse_chunks) = process_sse_line(line, &mut state, &trace_id, &email) {
-                         for sse_chunk in sse_chunks {
-                             yield Ok(sse_chunk);
-                         }
+        if let Some(line_str) = decoder.finish() {
+             let line = line_str.trim();
+             if !line.is_empty() {
+                 tracing::debug!("[{}] SSE Termination: Flushing remaining {} bytes in buffer", trace_id, line_str.len());
+                 if let Some(sse_chunks) = process_sse_line(line, &mut state, &trace_id, &email) {
+                     for sse_chunk in sse_chunks {
+                         yield Ok(sse_chunk);
                      }
                  }
              }
-             buffer.clear();
         }
 
         // [FIX #859] Post-thinking interruption recovery
         // If we have sent thinking but NO content (text/tool_use) and the stream ended (or timed out without DONE),
         // we must provide a fallback to prevent 0-token errors on client side.
-        if state.has_thinking && !state.has_content {
+        if state.has_thinking && !state.has_content && !state.stream_errored {
             tracing::warn!("[{}] Stream interrupted after thinking (No Content). Triggering recovery...", trace_id);
             
             // 1. Force close thinking block if open
@@ -211,13 +204,16 @@ fn process_sse_line(line: &str, state: &mut StreamingState, trace_id: &str, emai
     // 捕获 groundingMetadata (Web Search)
     if let Some(candidate) = raw_json.get("candidates").and_then(|c| c.get(0)) {
         if let Some(grounding) = candidate.get("groundingMetadata") {
-            // 提取搜索词
-            if let Some(query) = grounding.get("webSearchQueries")
-                .and_then(|v| v.as_array())
-                .and_then(|arr| arr.get(0))
-                .and_then(|v| v.as_str())
-            {
-                state.web_search_query = Some(query.to_string());
+            // 提取搜索词 (模型可能在一次回复中执行多次搜索)
+            if let Some(queries) = grounding.get("webSearchQueries").and_then(|v| v.as_array()) {
+                let queries: Vec<String> = queries
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.to_string())
+                    .collect();
+                if !queries.is_empty() {
+                    state.web_search_queries = queries;
+                }
             }
 
             // 提取结果块
@@ -245,22 +241,8 @@ fn process_sse_line(line: &str, state: &mut StreamingState, trace_id: &str, emai
         }
     }
 
-    // Process grounding metadata (googleSearch results) and append as citations
-    // [DISABLED] Temporarily disabled to fix Cherry Studio compatibility
-    // Cherry Studio doesn't recognize "web_search_tool_result" type, causing validation errors
-    // Search results are still displayed via Markdown text block in streaming.rs (lines 341-381)
-
-    /*
-    if let Some(grounding) = raw_json
-        .get("candidates")
-        .and_then(|c| c.get(0))
-        .and_then(|cand| cand.get("groundingMetadata"))
-    {
-        if let Some(citation_chunks) = process_grounding_metadata(grounding, state) {
-            chunks.extend(citation_chunks);
-        }
-    }
-    */
+    // grounding 已在上方累积到 state，由 StreamingState::emit_finish 统一输出为
+    // server_tool_use / web_search_tool_result 块
 
     // 检查是否结束
     if let Some(finish_reason) = raw_json
@@ -316,128 +298,6 @@ pub fn emit_force_stop(state: &mut StreamingState) -> Vec<Bytes> {
     vec![]
 }
 
-/// Process grounding metadata from Gemini's googleSearch and emit as Claude web_search blocks
-#[allow(dead_code)] // Temporarily disabled for Cherry Studio compatibility, kept for future use
-fn process_grounding_metadata(
-    metadata: &serde_json::Value,
-    state: &mut StreamingState,
-) -> Option<Vec<Bytes>> {
-    use serde_json::json;
-
-    // Extract search queries and grounding chunks
-    let search_queries = metadata
-        .get("webSearchQueries")
-        .and_then(|q| q.as_array())
-        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
-        .unwrap_or_default();
-
-    let grounding_chunks = metadata.get("groundingChunks").and_then(|c| c.as_array())?;
-
-    if grounding_chunks.is_empty() {
-        return None;
-    }
-
-    // Generate a unique tool_use_id
-    let tool_use_id = format!(
-        "srvtoolu_{}",
-        crate::proxy::common::utils::generate_random_id()
-    );
-
-    // Build search results array
-    let mut search_results = Vec::new();
-    for chunk in grounding_chunks.iter() {
-        if let Some(web) = chunk.get("web") {
-            let title = web
-                .get("title")
-                .and_then(|t| t.as_str())
-                .unwrap_or("Source");
-            let uri = web.get("uri").and_then(|u| u.as_str()).unwrap_or("");
-            if !uri.is_empty() {
-                search_results.push(json!({
-                    "url": uri,
-                    "title": title,
-                    "encrypted_content": "", // Gemini doesn't provide this
-                    "page_age": null
-                }));
-            }
-        }
-    }
-
-    if search_results.is_empty() {
-        return None;
-    }
-
-    let search_query = search_queries
-        .first()
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-
-    tracing::debug!(
-        "[Grounding] Emitting {} search results for query: {}",
-        search_results.len(),
-        search_query
-    );
-
-    let mut chunks = Vec::new();
-
-    // 1. Emit server_tool_use block (start)
-    let server_tool_use_start = json!({
-        "type": "content_block_start",
-        "index": state.block_index,
-        "content_block": {
-            "type": "server_tool_use",
-            "id": tool_use_id,
-            "name": "web_search",
-            "input": {
-                "query": search_query
-            }
-        }
-    });
-    chunks.push(Bytes::from(format!(
-        "event: content_block_start\ndata: {}\n\n",
-        server_tool_use_start
-    )));
-
-    // server_tool_use block stop
-    let server_tool_use_stop = json!({
-        "type": "content_block_stop",
-        "index": state.block_index
-    });
-    chunks.push(Bytes::from(format!(
-        "event: content_block_stop\ndata: {}\n\n",
-        server_tool_use_stop
-    )));
-    state.block_index += 1;
-
-    // 2. Emit web_search_tool_result block (start)
-    let tool_result_start = json!({
-        "type": "content_block_start",
-        "index": state.block_index,
-        "content_block": {
-            "type": "web_search_tool_result",
-            "tool_use_id": tool_use_id,
-            "content": search_results
-        }
-    });
-    chunks.push(Bytes::from(format!(
-        "event: content_block_start\ndata: {}\n\n",
-        tool_result_start
-    )));
-
-    // web_search_tool_result block stop
-    let tool_result_stop = json!({
-        "type": "content_block_stop",
-        "index": state.block_index
-    });
-    chunks.push(Bytes::from(format!(
-        "event: content_block_stop\ndata: {}\n\n",
-        tool_result_stop
-    )));
-    state.block_index += 1;
-
-    Some(chunks)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -513,6 +373,7 @@ mod tests {
             1, // message_count
             None, // client_adapter
             Vec::new(), // registered_tool_names
+            false, // web_search_blocks
         );
 
         // 3. 收集输出
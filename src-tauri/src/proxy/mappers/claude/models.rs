@@ -1,7 +1,9 @@
 // Claude 数据模型
 // Claude 协议相关数据模型
 
-use serde::{Deserialize, Serialize};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
 
 /// Claude API 请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +36,46 @@ pub struct ClaudeRequest {
     pub size: Option<String>,
     #[serde(default)]
     pub quality: Option<String>,
+    /// 工具选择策略 (auto / any / tool / none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// [NEW] 未建模的顶层字段 (container / mcp_servers / betas / service_tier 等)，原样保留不报错
+    #[serde(flatten, default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ClaudeRequest {
+    /// 记录未建模的字段 (每个字段只记录一次)，便于发现映射缺口
+    pub fn report_unknown_fields(&self) {
+        for key in self.extra.keys() {
+            log_unknown_once("ClaudeRequest field", key);
+        }
+    }
+}
+
+/// 未识别的字段 / 块类型只在首次出现时记录，避免刷屏
+pub fn log_unknown_once(kind: &str, name: &str) {
+    static SEEN: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let first_seen = SEEN
+        .get_or_init(|| Mutex::new(HashSet::new()))
+        .lock()
+        .map(|mut seen| seen.insert(format!("{}:{}", kind, name)))
+        .unwrap_or(false);
+    if first_seen {
+        tracing::info!("[Claude-Models] Unrecognized {} `{}` preserved but not mapped", kind, name);
+    }
+}
+
+/// Claude tool_choice
+/// {"type": "auto" | "any" | "tool" | "none", "name"?: string, "disable_parallel_tool_use"?: bool}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolChoice {
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub disable_parallel_tool_use: bool,
 }
 
 /// Thinking 配置
@@ -62,6 +104,9 @@ pub struct SystemBlock {
     #[serde(rename = "type")]
     pub block_type: String,
     pub text: String,
+    /// Prompt caching 断点 ({"type": "ephemeral"})
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<serde_json::Value>,
 }
 
 /// Message
@@ -104,6 +149,10 @@ pub enum ContentBlock {
     #[serde(rename = "document")]
     Document {
         source: DocumentSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         cache_control: Option<serde_json::Value>,
     },
@@ -142,22 +191,78 @@ pub enum ContentBlock {
         tool_use_id: String,
         content: serde_json::Value,
     },
+
+    /// [NEW] 未识别的块类型 (新版 API 扩展)，原样保留，转换时跳过
+    #[serde(untagged)]
+    Unknown(UnknownBlock),
+}
+
+/// 已知的内容块类型 (与 ContentBlock 的 rename 保持一致)
+const KNOWN_CONTENT_BLOCK_TYPES: [&str; 9] = [
+    "text",
+    "thinking",
+    "image",
+    "document",
+    "redacted_thinking",
+    "tool_use",
+    "tool_result",
+    "server_tool_use",
+    "web_search_tool_result",
+];
+
+/// 未识别类型内容块的原始 JSON
+/// 只接受未知的 type；已知类型但字段不合法时仍然报错，不会被静默吞掉
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct UnknownBlock(pub serde_json::Value);
+
+impl UnknownBlock {
+    pub fn block_type(&self) -> &str {
+        self.0.get("type").and_then(|t| t.as_str()).unwrap_or_default()
+    }
+}
+
+impl<'de> Deserialize<'de> for UnknownBlock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let block_type = value
+            .get("type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| D::Error::custom("content block is missing `type`"))?;
+        if KNOWN_CONTENT_BLOCK_TYPES.contains(&block_type) {
+            return Err(D::Error::custom(format!("invalid `{}` content block", block_type)));
+        }
+        log_unknown_once("content block type", block_type);
+        Ok(UnknownBlock(value))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageSource {
     #[serde(rename = "type")]
-    pub source_type: String,
+    pub source_type: String, // "base64" | "url"
+    #[serde(default)]
     pub media_type: String,
+    #[serde(default)]
     pub data: String,
+    /// [NEW] type = "url" 时的图片地址 (转换前会先下载内联)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentSource {
     #[serde(rename = "type")]
-    pub source_type: String, // "base64"
+    pub source_type: String, // "base64" | "text" | "content" | "url"
+    #[serde(default)]
     pub media_type: String, // e.g. "application/pdf"
-    pub data: String,       // base64 data
+    #[serde(default)]
+    pub data: String,       // base64 data (type = "text" 时为纯文本)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// type = "content" 时的内容块 (text / image)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<serde_json::Value>,
 }
 
 /// Tool - supports both client tools (with input_schema) and server tools (like web_search)
@@ -175,6 +280,9 @@ pub struct Tool {
     /// Input schema - required for client tools, absent for server tools
     #[serde(skip_serializing_if = "Option::is_none")]
     pub input_schema: Option<serde_json::Value>,
+    /// Prompt caching 断点，仅做兼容接收，不转发给上游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<serde_json::Value>,
 }
 
 impl Tool {
@@ -291,11 +399,36 @@ pub struct GeminiPart {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
+    /// 参数分片的后续片段不携带 name
+    #[serde(default)]
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub args: Option<serde_json::Value>,
+    /// [NEW] 参数分片 (streamFunctionCallArguments)
+    #[serde(rename = "partialArgs", default, skip_serializing_if = "Option::is_none")]
+    pub partial_args: Option<Vec<PartialArg>>,
+    /// [NEW] 为 true 时后续 chunk 还会继续发送该调用的参数
+    #[serde(rename = "willContinue", default, skip_serializing_if = "Option::is_none")]
+    pub will_continue: Option<bool>,
+}
+
+/// 函数调用参数分片：jsonPath 指向参数位置，字符串值可能被拆成多段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialArg {
+    pub json_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number_value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bool_value: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub null_value: Option<serde_json::Value>,
+    #[serde(default)]
+    pub will_continue: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -357,6 +490,10 @@ pub struct UsageMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "cachedContentTokenCount")]
     pub cached_content_token_count: Option<u32>,
+    /// [NEW] 思考过程消耗的 token (不含在 candidatesTokenCount 内)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "thoughtsTokenCount")]
+    pub thoughts_token_count: Option<u32>,
 }
 
 // ========== Grounding Metadata (for googleSearch results) ==========
@@ -425,3 +562,43 @@ pub struct SearchEntryPoint {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rendered_content: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unknown_request_fields_are_preserved() {
+        let body = json!({
+            "model": "claude-sonnet-4-6",
+            "messages": [{ "role": "user", "content": "hi" }],
+            "service_tier": "auto",
+            "mcp_servers": [{ "type": "url", "url": "https://example.com/mcp" }]
+        });
+        let req: ClaudeRequest = serde_json::from_value(body).unwrap();
+        assert_eq!(req.extra.len(), 2);
+        assert_eq!(req.extra["service_tier"], "auto");
+
+        let round_trip = serde_json::to_value(&req).unwrap();
+        assert_eq!(round_trip["mcp_servers"][0]["type"], "url");
+    }
+
+    #[test]
+    fn test_unknown_content_block_type_is_tolerated() {
+        let blocks: Vec<ContentBlock> = serde_json::from_value(json!([
+            { "type": "text", "text": "hi" },
+            { "type": "container_upload", "file_id": "file_1" }
+        ]))
+        .unwrap();
+        match &blocks[1] {
+            ContentBlock::Unknown(block) => assert_eq!(block.block_type(), "container_upload"),
+            other => panic!("unexpected block: {:?}", other),
+        }
+        assert_eq!(serde_json::to_value(&blocks[1]).unwrap()["file_id"], "file_1");
+
+        // 已知类型字段缺失仍然报错
+        assert!(serde_json::from_value::<ContentBlock>(json!({ "type": "text" })).is_err());
+        assert!(serde_json::from_value::<ContentBlock>(json!({ "text": "no type" })).is_err());
+    }
+}
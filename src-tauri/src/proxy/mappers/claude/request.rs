@@ -5,66 +5,10 @@ use super::models::*;
 use crate::proxy::mappers::signature_store::get_thought_signature; // Deprecated, kept for fallback
 use crate::proxy::mappers::tool_result_compressor;
 use crate::proxy::session_manager::SessionManager;
+use crate::proxy::tool_name_cache::ToolNameCache;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 
-// ===== Safety Settings Configuration =====
-
-/// Safety threshold levels for Gemini API
-/// Can be configured via GEMINI_SAFETY_THRESHOLD environment variable
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum SafetyThreshold {
-    /// Disable all safety filters (default for proxy compatibility)
-    Off,
-    /// Block low probability and above
-    BlockLowAndAbove,
-    /// Block medium probability and above
-    BlockMediumAndAbove,
-    /// Only block high probability content
-    BlockOnlyHigh,
-    /// Don't block anything (BLOCK_NONE)
-    BlockNone,
-}
-
-impl SafetyThreshold {
-    /// Get threshold from environment variable or default to Off
-    pub fn from_env() -> Self {
-        match std::env::var("GEMINI_SAFETY_THRESHOLD").as_deref() {
-            Ok("OFF") | Ok("off") => SafetyThreshold::Off,
-            Ok("LOW") | Ok("low") => SafetyThreshold::BlockLowAndAbove,
-            Ok("MEDIUM") | Ok("medium") => SafetyThreshold::BlockMediumAndAbove,
-            Ok("HIGH") | Ok("high") => SafetyThreshold::BlockOnlyHigh,
-            Ok("NONE") | Ok("none") => SafetyThreshold::BlockNone,
-            _ => SafetyThreshold::Off, // Default: maintain current behavior
-        }
-    }
-
-    /// Convert to Gemini API threshold string
-    pub fn to_gemini_threshold(&self) -> &'static str {
-        match self {
-            SafetyThreshold::Off => "OFF",
-            SafetyThreshold::BlockLowAndAbove => "BLOCK_LOW_AND_ABOVE",
-            SafetyThreshold::BlockMediumAndAbove => "BLOCK_MEDIUM_AND_ABOVE",
-            SafetyThreshold::BlockOnlyHigh => "BLOCK_ONLY_HIGH",
-            SafetyThreshold::BlockNone => "BLOCK_NONE",
-        }
-    }
-}
-
-/// Build safety settings based on configuration
-fn build_safety_settings() -> Value {
-    let threshold = SafetyThreshold::from_env();
-    let threshold_str = threshold.to_gemini_threshold();
-
-    json!([
-        { "category": "HARM_CATEGORY_HARASSMENT", "threshold": threshold_str },
-        { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": threshold_str },
-        { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": threshold_str },
-        { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": threshold_str },
-        { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": threshold_str },
-    ])
-}
-
 /// 清理消息中的 cache_control 字段
 ///
 /// 这个函数会深度遍历所有消息内容块,移除 cache_control 字段。
@@ -332,6 +276,23 @@ fn reorder_gemini_parts(parts: &mut Vec<Value>) {
     parts.extend(tool_parts);
 }
 
+/// [NEW] 请求是否声明了联网搜索工具 (server tool or built-in tool)
+pub fn declares_web_search_tool(claude_req: &ClaudeRequest) -> bool {
+    claude_req
+        .tools
+        .as_ref()
+        .map(|tools| {
+            tools.iter().any(|t| {
+                t.is_web_search()
+                    || t.name.as_deref() == Some("google_search")
+                    || t.name.as_deref() == Some("builtin_web_search")
+                    || t.type_.as_deref() == Some("web_search_20250305")
+                    || t.type_.as_deref() == Some("builtin_web_search")
+            })
+        })
+        .unwrap_or(false)
+}
+
 pub fn transform_claude_request_in(
     claude_req: &ClaudeRequest,
     project_id: &str,
@@ -382,19 +343,7 @@ pub fn transform_claude_request_in(
     tracing::debug!("[Claude-Request] Session ID: {}", session_id);
 
     // 检测是否有联网工具 (server tool or built-in tool)
-    let has_web_search_tool = claude_req
-        .tools
-        .as_ref()
-        .map(|tools| {
-            tools.iter().any(|t| {
-                t.is_web_search()
-                    || t.name.as_deref() == Some("google_search")
-                    || t.name.as_deref() == Some("builtin_web_search")
-                    || t.type_.as_deref() == Some("web_search_20250305")
-                    || t.type_.as_deref() == Some("builtin_web_search")
-            })
-        })
-        .unwrap_or(false);
+    let has_web_search_tool = declares_web_search_tool(claude_req);
 
     // 用于存储 tool_use id -> name 映射
     let mut tool_id_to_name: HashMap<String, String> = HashMap::new();
@@ -580,8 +529,8 @@ pub fn transform_claude_request_in(
     // 3. Tools
     let tools = build_tools(&claude_req.tools, has_web_search_tool, &mapped_model)?;
 
-    // 5. Safety Settings (configurable via GEMINI_SAFETY_THRESHOLD env var)
-    let safety_settings = build_safety_settings();
+    // 5. Safety Settings (ProxyConfig.safety_settings, 其次 GEMINI_SAFETY_THRESHOLD 环境变量)
+    let safety_settings = crate::proxy::safety_settings::build_safety_settings(&mapped_model);
 
     // Build inner request
     let mut inner_request = json!({
@@ -600,12 +549,27 @@ pub fn transform_claude_request_in(
 
     if let Some(tools_val) = tools {
         inner_request["tools"] = tools_val;
-        // 显式设置工具配置模式为 VALIDATED
-        inner_request["toolConfig"] = json!({
-            "functionCallingConfig": {
-                "mode": "VALIDATED"
+        // [NEW] 按 tool_choice 设置工具调用模式 (默认 VALIDATED)
+        inner_request["toolConfig"] = build_tool_config(claude_req.tool_choice.as_ref());
+
+        // Gemini 没有关闭并行调用的开关，只能通过系统指令约束
+        if claude_req
+            .tool_choice
+            .as_ref()
+            .is_some_and(|c| c.disable_parallel_tool_use)
+        {
+            let hint = json!({ "text": SINGLE_TOOL_CALL_HINT });
+            match inner_request
+                .get_mut("systemInstruction")
+                .and_then(|s| s.get_mut("parts"))
+                .and_then(|p| p.as_array_mut())
+            {
+                Some(parts) => parts.push(hint),
+                None => {
+                    inner_request["systemInstruction"] = json!({ "role": "user", "parts": [hint] });
+                }
             }
-        });
+        }
     }
 
 
@@ -651,6 +615,19 @@ pub fn transform_claude_request_in(
         }
     }
 
+    // [NEW] 引用 Gemini 上下文缓存 (模型绑定的缓存，或 cache_control 系统提示词对应的缓存)
+    // 放在所有工具注入之后，确保 cachedContent 与 tools / systemInstruction 不会同时出现
+    if config.request_type != "image_gen" {
+        let cached_text = cacheable_system_text(&claude_req.system);
+        crate::proxy::context_cache::apply_to_request(
+            &mut inner_request,
+            &claude_req.model,
+            &mapped_model,
+            account_id,
+            cached_text.as_deref(),
+        );
+    }
+
     // [ADDED v4.1.24] 注入稳定 sessionId 对齐官方规范
     if let Some(account_id) = account_id {
         inner_request["sessionId"] = json!(crate::proxy::common::session::derive_session_id(account_id));
@@ -807,6 +784,24 @@ fn has_valid_signature_for_function_calls(
     false
 }
 
+/// 可映射到 Gemini 上下文缓存的系统提示词：
+/// 仅当最后一个 system 块带有 cache_control 断点时 (整段系统提示词都在缓存前缀内) 返回其文本
+pub(crate) fn cacheable_system_text(system: &Option<SystemPrompt>) -> Option<String> {
+    let Some(SystemPrompt::Array(blocks)) = system else {
+        return None;
+    };
+    if blocks.last()?.cache_control.is_none() {
+        return None;
+    }
+    Some(
+        blocks
+            .iter()
+            .map(|b| b.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    )
+}
+
 /// 构建 System Instruction (支持动态身份映射与 Prompt 隔离)
 fn build_system_instruction(
     system: &Option<SystemPrompt>,
@@ -1109,17 +1104,25 @@ fn build_contents(
                                 }
                             }));
                             saw_non_thinking = true;
+                        } else if let Some(url) = &source.url {
+                            // [NEW] 远程图片下载失败时保留链接文本，避免图片被静默丢弃
+                            parts.push(json!({ "text": format!("[image: {}]", url) }));
+                            saw_non_thinking = true;
                         }
                     }
-                    ContentBlock::Document { source, .. } => {
-                        if source.source_type == "base64" {
-                            parts.push(json!({
-                                "inlineData": {
-                                    "mimeType": source.media_type,
-                                    "data": source.data
-                                }
-                            }));
-                            saw_non_thinking = true;
+                    ContentBlock::Document { source, title, context, .. } => {
+                        // [NEW] PDF / 文本文档 -> inlineData 或文本 (大小与类型已在 handler 中校验)
+                        match crate::proxy::common::document::claude_document_parts(
+                            source,
+                            title.as_deref(),
+                            context.as_deref(),
+                        ) {
+                            Ok(doc_parts) if !doc_parts.is_empty() => {
+                                parts.extend(doc_parts);
+                                saw_non_thinking = true;
+                            }
+                            Ok(_) => {}
+                            Err(e) => tracing::warn!("[Claude-Request] Skipping document block: {}", e),
                         }
                     }
                     ContentBlock::ToolUse {
@@ -1153,8 +1156,9 @@ fn build_contents(
                             pending_tool_use_ids.push(id.clone());
                         }
 
-                        // 存储 id -> name 映射
+                        // 存储 id -> name 映射 (同时写入会话缓存，供历史被截断后的请求使用)
                         tool_id_to_name.insert(id.clone(), name.clone());
+                        ToolNameCache::global().remember(session_id, id, name);
 
                         // Signature resolution logic
                         // Priority: Client -> Context -> Session Cache -> Tool Cache -> Global Store (deprecated)
@@ -1275,11 +1279,8 @@ fn build_contents(
                     } => {
                         // Mark this tool ID as resolved in this turn
                         current_turn_tool_result_ids.insert(tool_use_id.clone());
-                        // 优先使用之前记录的 name，否则用 tool_use_id
-                        let func_name = tool_id_to_name
-                            .get(tool_use_id)
-                            .cloned()
-                            .unwrap_or_else(|| tool_use_id.clone());
+                        // 优先使用之前记录的 name，其次会话缓存，否则用 tool_use_id
+                        let func_name = resolve_tool_name(tool_id_to_name, session_id, tool_use_id);
 
                         // [FIX #593] 工具输出压缩: 处理超大工具输出
                         // 使用智能压缩策略(浏览器快照、大文件提示等)
@@ -1288,38 +1289,9 @@ fn build_contents(
                             tool_result_compressor::sanitize_tool_result_blocks(blocks);
                         }
 
-                        // Smart Truncation: No longer stripping images from Tool Results
-                        // Tool results should pass transparency. If images are present, map them to inlineData.
-                        let mut extra_parts = Vec::new();
-
-                        let mut merged_content = match &compacted_content {
-                            serde_json::Value::String(s) => s.clone(),
-                            serde_json::Value::Array(arr) => {
-                                let mut texts = Vec::new();
-                                for block in arr {
-                                    if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                                        texts.push(text.to_string());
-                                    } else if block.get("source").is_some() {
-                                        if block.get("type").and_then(|v| v.as_str()) == Some("image") {
-                                            let source = block.get("source").unwrap();
-                                            if let (Some(media_type), Some(data)) = (
-                                                source.get("media_type").and_then(|v| v.as_str()),
-                                                source.get("data").and_then(|v| v.as_str())
-                                            ) {
-                                                extra_parts.push(json!({
-                                                    "inlineData": {
-                                                        "mimeType": media_type,
-                                                        "data": data
-                                                    }
-                                                }));
-                                            }
-                                        }
-                                    }
-                                }
-                                texts.join("\n")
-                            }
-                            _ => content.to_string(),
-                        };
+                        // [FIX] 数组形式的 tool_result: 文本合并为 result，图片作为独立 inlineData parts
+                        let (mut merged_content, extra_parts) =
+                            split_tool_result_content(&compacted_content);
 
                         // Smart Truncation: max chars limit
                         const MAX_TOOL_RESULT_CHARS: usize = 200_000;
@@ -1337,20 +1309,32 @@ fn build_contents(
                             merged_content = truncated;
                         }
 
+                        let is_error = is_error.unwrap_or(false);
+
                         // [优化] 如果结果为空，注入显式确认信号，防止模型幻觉
                         if merged_content.trim().is_empty() {
-                            if is_error.unwrap_or(false) {
+                            if is_error {
                                 merged_content =
                                     "Tool execution failed with no output.".to_string();
+                            } else if !extra_parts.is_empty() {
+                                merged_content =
+                                    format!("Tool returned {} image(s).", extra_parts.len());
                             } else {
                                 merged_content = "Command executed successfully.".to_string();
                             }
                         }
 
+                        // [FIX] is_error 映射为 Gemini 约定的 error 字段，避免模型把失败当作成功结果
+                        let response = if is_error {
+                            json!({ "error": merged_content })
+                        } else {
+                            json!({ "result": merged_content })
+                        };
+
                         let mut part = json!({
                             "functionResponse": {
                                 "name": func_name,
-                                "response": {"result": merged_content},
+                                "response": response,
                                 "id": tool_use_id
                             }
                         });
@@ -1376,6 +1360,10 @@ fn build_contents(
                         // 搜索结果 block 不应由客户端发回给上游 (已由 tool_result 替代)
                         continue;
                     }
+                    ContentBlock::Unknown(block) => {
+                        tracing::debug!("[Claude-Request] Skipping unsupported content block type: {}", block.block_type());
+                        continue;
+                    }
                 }
             }
         }
@@ -1393,7 +1381,7 @@ fn build_contents(
             tracing::warn!("[Elastic-Recovery] Injecting {} missing tool results into User message (IDs: {:?})", missing_ids.len(), missing_ids);
             for id in missing_ids.iter().rev() {
                 // Insert in reverse order to maintain order at index 0? No, just insert at 0.
-                let name = tool_id_to_name.get(id).cloned().unwrap_or(id.clone());
+                let name = resolve_tool_name(tool_id_to_name, session_id, id);
                 let synthetic_part = json!({
                     "functionResponse": {
                         "name": name,
@@ -1467,6 +1455,75 @@ fn build_contents(
     Ok(parts)
 }
 
+/// 解析 tool_use_id 对应的函数名: 当前请求历史 -> 会话缓存 -> 原始 ID
+fn resolve_tool_name(
+    tool_id_to_name: &HashMap<String, String>,
+    session_id: &str,
+    tool_id: &str,
+) -> String {
+    if let Some(name) = tool_id_to_name.get(tool_id) {
+        return name.clone();
+    }
+    ToolNameCache::global()
+        .lookup(session_id, tool_id)
+        .unwrap_or_else(|| {
+            tracing::warn!(
+                "[Claude-Request] No function name found for tool id {}, falling back to raw id",
+                tool_id
+            );
+            tool_id.to_string()
+        })
+}
+
+/// 拆分 tool_result 的 content: 返回合并后的文本与图片 parts
+/// 支持字符串与 Anthropic 数组形式 (text / image blocks)，未知 block 以 JSON 文本保留
+fn split_tool_result_content(content: &Value) -> (String, Vec<Value>) {
+    let blocks = match content {
+        Value::String(s) => return (s.clone(), Vec::new()),
+        Value::Null => return (String::new(), Vec::new()),
+        Value::Array(blocks) => blocks,
+        other => return (other.to_string(), Vec::new()),
+    };
+
+    let mut texts = Vec::new();
+    let mut images = Vec::new();
+    for block in blocks {
+        match block.get("type").and_then(|v| v.as_str()) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
+                    texts.push(text.to_string());
+                }
+            }
+            Some("image") => {
+                let source = block.get("source");
+                let source_type = source.and_then(|s| s.get("type")).and_then(|v| v.as_str());
+                let media_type = source.and_then(|s| s.get("media_type")).and_then(|v| v.as_str());
+                let data = source.and_then(|s| s.get("data")).and_then(|v| v.as_str());
+                let url = source.and_then(|s| s.get("url")).and_then(|v| v.as_str());
+                match (source_type, media_type, data, url) {
+                    (Some("base64") | None, Some(media_type), Some(data), _) => {
+                        images.push(json!({
+                            "inlineData": { "mimeType": media_type, "data": data }
+                        }));
+                    }
+                    // 与普通 image block 一致: 无法内联的远程图片保留链接文本
+                    (_, _, _, Some(url)) => texts.push(format!("[image: {}]", url)),
+                    _ => tracing::debug!("[Claude-Request] Skipping tool_result image without data"),
+                }
+            }
+            other => match block.get("text").and_then(|v| v.as_str()) {
+                Some(text) => texts.push(text.to_string()),
+                // 带 source 的二进制 block (如 document) 不展开原始数据
+                None if block.get("source").is_some() => {
+                    texts.push(format!("[{} omitted]", other.unwrap_or("attachment")))
+                }
+                None => texts.push(block.to_string()),
+            },
+        }
+    }
+    (texts.join("\n"), images)
+}
+
 /// 构建 Contents (Messages)
 fn build_google_content(
     msg: &Message,
@@ -1501,7 +1558,7 @@ fn build_google_content(
             .iter()
             .filter(|id| !existing_tool_result_ids.contains(*id)) // [FIX #632] Only inject if ID is truly missing
             .map(|id| {
-                let name = tool_id_to_name.get(id).cloned().unwrap_or(id.clone());
+                let name = resolve_tool_name(tool_id_to_name, session_id, id);
                 json!({
                     "functionResponse": {
                         "name": name,
@@ -1668,6 +1725,27 @@ fn merge_adjacent_roles(mut contents: Vec<Value>) -> Vec<Value> {
 }
 
 /// 构建 Tools
+const SINGLE_TOOL_CALL_HINT: &str =
+    "Call at most one tool per response. Wait for its result before calling another tool.";
+
+/// Claude tool_choice -> Gemini toolConfig.functionCallingConfig
+/// - 未指定 / auto: VALIDATED (等同 AUTO，并对参数做 schema 校验)
+/// - any: ANY (必须调用某个工具)
+/// - tool: ANY + allowedFunctionNames (强制调用指定工具)
+/// - none: NONE (禁止调用工具)
+fn build_tool_config(tool_choice: Option<&ToolChoice>) -> Value {
+    let config = match tool_choice.map(|c| (c.type_.as_str(), c.name.as_deref())) {
+        Some(("any", _)) => json!({ "mode": "ANY" }),
+        Some(("tool", Some(name))) => json!({
+            "mode": "ANY",
+            "allowedFunctionNames": [name]
+        }),
+        Some(("none", _)) => json!({ "mode": "NONE" }),
+        _ => json!({ "mode": "VALIDATED" }),
+    };
+    json!({ "functionCallingConfig": config })
+}
+
 fn build_tools(
     tools: &Option<Vec<Tool>>,
     has_web_search: bool,
@@ -2113,6 +2191,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-project", false, None, "test_session", None);
@@ -2210,6 +2290,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-project", false, None, "test_session", None);
@@ -2233,6 +2315,47 @@ mod tests {
         assert!(resp_text.contains("\n"));
     }
 
+    #[test]
+    fn test_tool_result_images_and_error_flag() {
+        let (text, images) = split_tool_result_content(&json!([
+            {"type": "text", "text": "screenshot taken"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}},
+            {"type": "image", "source": {"type": "url", "url": "https://example.com/a.png"}}
+        ]));
+        assert_eq!(text, "screenshot taken\n[image: https://example.com/a.png]");
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0]["inlineData"]["mimeType"], "image/png");
+
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-6",
+            "messages": [
+                {"role": "user", "content": "Take a screenshot"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "call_1", "name": "screenshot", "input": {}},
+                    {"type": "tool_use", "id": "call_2", "name": "read_file", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "call_1", "content": [
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+                    ]},
+                    {"type": "tool_result", "tool_use_id": "call_2", "is_error": true, "content": [
+                        {"type": "text", "text": "permission denied"}
+                    ]}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let body = transform_claude_request_in(&req, "test-project", false, None, "test_session", None).unwrap();
+        let parts = body["request"]["contents"][2]["parts"].as_array().unwrap();
+        let responses: Vec<_> = parts.iter().filter_map(|p| p.get("functionResponse")).collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["response"]["result"], "Tool returned 1 image(s).");
+        assert_eq!(responses[1]["response"]["error"], "permission denied");
+        assert!(responses[1]["response"].get("result").is_none());
+        assert!(parts.iter().any(|p| p["inlineData"]["data"] == "iVBORw0KGgo="));
+    }
+
     #[test]
     fn test_cache_control_cleanup() {
         // 模拟 VS Code 插件发送的包含 cache_control 的历史消息
@@ -2263,6 +2386,7 @@ mod tests {
                             source_type: "base64".to_string(),
                             media_type: "image/png".to_string(),
                             data: "iVBORw0KGgo=".to_string(),
+                            url: None,
                         },
                         cache_control: Some(json!({"type": "ephemeral"})), // 这个也应该被清理
                     }]),
@@ -2280,6 +2404,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-project", false, None, "test_session", None);
@@ -2338,7 +2464,7 @@ mod tests {
                 description: Some("List files".to_string()),
                 input_schema: Some(json!({"type": "object"})),
                 type_: None,
-                // cache_control: None, // removed
+                cache_control: None,
             }]),
             stream: false,
             max_tokens: None,
@@ -2354,6 +2480,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-project", false, None, "test_session", None);
@@ -2404,6 +2532,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-project", false, None, "test_session", None);
@@ -2460,6 +2590,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-project", false, None, "test_session", None);
@@ -2508,6 +2640,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-project", false, None, "test_session", None);
@@ -2699,6 +2833,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "test-v", false, None, "test_session", None).unwrap();
@@ -2736,6 +2872,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         let result = transform_claude_request_in(&req, "proj", false, None, "test_session", None).unwrap();
@@ -2764,6 +2902,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // Should cap
@@ -2796,6 +2936,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // Transform
@@ -2836,6 +2978,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // Transform
@@ -2873,6 +3017,8 @@ mod tests {
             output_config: None,
             size: Some("1024x1024".to_string()),
             quality: Some("hd".to_string()),
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // 3. Transform request
@@ -2895,6 +3041,8 @@ mod tests {
             mode: crate::proxy::config::ThinkingBudgetMode::Adaptive,
             custom_value: 0,
             effort: Some("high".to_string()),
+            hide_openai_reasoning: false,
+            claude_thinking_output: crate::proxy::config::ClaudeThinkingOutput::Blocks,
         };
         crate::proxy::config::update_thinking_budget_config(config);
 
@@ -2917,6 +3065,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // Transform
@@ -2960,6 +3110,7 @@ mod tests {
                         "location": {"type": "string"}
                     }
                 })),
+                cache_control: None,
             }]),
             stream: false,
             max_tokens: None,
@@ -2971,6 +3122,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // 模拟映射到 Gemini 2.0
@@ -3011,6 +3164,7 @@ mod tests {
                         "location": {"type": "string"}
                     }
                 })),
+                cache_control: None,
             }]),
             stream: false,
             max_tokens: None,
@@ -3022,6 +3176,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // 模拟映射到 Gemini 1.5
@@ -3040,4 +3196,63 @@ mod tests {
         assert!(!has_google_search, "Older Gemini models should NOT have mixed tools");
         assert!(has_functions);
     }
+
+    #[test]
+    fn test_tool_choice_maps_to_function_calling_config() {
+        let choice: ToolChoice = serde_json::from_value(serde_json::json!({
+            "type": "tool",
+            "name": "get_weather",
+            "disable_parallel_tool_use": true
+        }))
+        .unwrap();
+        assert!(choice.disable_parallel_tool_use);
+
+        let forced = build_tool_config(Some(&choice));
+        assert_eq!(forced["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(
+            forced["functionCallingConfig"]["allowedFunctionNames"],
+            serde_json::json!(["get_weather"])
+        );
+
+        let any: ToolChoice = serde_json::from_value(serde_json::json!({ "type": "any" })).unwrap();
+        assert_eq!(build_tool_config(Some(&any))["functionCallingConfig"]["mode"], "ANY");
+
+        let none: ToolChoice = serde_json::from_value(serde_json::json!({ "type": "none" })).unwrap();
+        assert_eq!(build_tool_config(Some(&none))["functionCallingConfig"]["mode"], "NONE");
+
+        // 未指定或 auto 保持 VALIDATED
+        assert_eq!(build_tool_config(None)["functionCallingConfig"]["mode"], "VALIDATED");
+    }
+
+    #[test]
+    fn test_cache_control_is_accepted_and_marks_cacheable_system() {
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-6",
+            "system": [
+                { "type": "text", "text": "static instructions" },
+                { "type": "text", "text": "project rules", "cache_control": { "type": "ephemeral" } }
+            ],
+            "messages": [{
+                "role": "user",
+                "content": [{ "type": "text", "text": "hi", "cache_control": { "type": "ephemeral" } }]
+            }],
+            "tools": [{
+                "name": "get_weather",
+                "input_schema": { "type": "object" },
+                "cache_control": { "type": "ephemeral" }
+            }]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            cacheable_system_text(&req.system).as_deref(),
+            Some("static instructions\n\nproject rules")
+        );
+        // Tool 上的 cache_control 不应透传给上游
+        let tools = build_tools(&req.tools, false, "gemini-2.5-flash").unwrap().unwrap();
+        assert!(!tools.to_string().contains("cache_control"));
+
+        let plain = Some(SystemPrompt::String("no breakpoint".to_string()));
+        assert!(cacheable_system_text(&plain).is_none());
+    }
 }
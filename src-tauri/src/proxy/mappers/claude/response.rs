@@ -152,6 +152,8 @@ pub struct NonStreamingProcessor {
     thinking_builder: String,
     thinking_signature: Option<String>,
     trailing_signature: Option<String>,
+    web_search_requests: usize,
+    pub web_search_blocks: bool, // [NEW] 以 web search 块输出搜索来源 (关闭时输出 Markdown 引文文本)
     pub has_tool_call: bool,
     pub scaling_enabled: bool,
     pub context_limit: u32,
@@ -168,6 +170,8 @@ impl NonStreamingProcessor {
             thinking_builder: String::new(),
             thinking_signature: None,
             trailing_signature: None,
+            web_search_requests: 0,
+            web_search_blocks: false,
             has_tool_call: false,
             scaling_enabled: false,
             context_limit: 1_048_576, // Default to 1M
@@ -201,17 +205,17 @@ impl NonStreamingProcessor {
             self.process_part(part);
         }
 
-        // 处理 grounding(web search) -> 转换为 server_tool_use / web_search_tool_result
+        // 刷新剩余内容
+        self.flush_thinking();
+        self.flush_text();
+
+        // 处理 grounding(web search) -> 转换为 server_tool_use / web_search_tool_result 或 Markdown 引文
         if let Some(candidate) = gemini_response.candidates.as_ref().and_then(|c| c.get(0)) {
             if let Some(grounding) = &candidate.grounding_metadata {
                 self.process_grounding(grounding);
             }
         }
 
-        // 刷新剩余内容
-        self.flush_thinking();
-        self.flush_text();
-
         // 处理 trailingSignature (空 text 带签名)
         if let Some(signature) = self.trailing_signature.take() {
             self.content_blocks.push(ContentBlock::Thinking {
@@ -371,56 +375,53 @@ impl NonStreamingProcessor {
             }
         }
 
-        // 3. InlineData (Image) 处理
-        if let Some(img) = &part.inline_data {
+        // 3. InlineData (Image) 处理 -> Claude image 内容块
+        // [NEW] Claude 协议没有音频内容块，音频输出直接跳过
+        if let Some(img) = part
+            .inline_data
+            .as_ref()
+            .filter(|img| !crate::proxy::audio::AudioProcessor::is_audio_mime_type(&img.mime_type))
+        {
             self.flush_thinking();
 
-            let mime_type = &img.mime_type;
-            let data = &img.data;
-            if !data.is_empty() {
-                let markdown_img = format!("![image](data:{};base64,{})", mime_type, data);
-                self.text_builder.push_str(&markdown_img);
+            if !img.data.is_empty() {
                 self.flush_text();
+                self.content_blocks.push(ContentBlock::Image {
+                    source: ImageSource {
+                        source_type: "base64".to_string(),
+                        media_type: img.mime_type.clone(),
+                        data: img.data.clone(),
+                        url: None,
+                    },
+                    cache_control: None,
+                });
             }
         }
     }
 
     /// 处理 Grounding 元数据 (Web Search 结果)
+    /// 追加在正文之后 (与流式输出顺序一致)：启用时为 server_tool_use + web_search_tool_result 块，
+    /// 否则为 Markdown 引文文本
     fn process_grounding(&mut self, grounding: &GroundingMetadata) {
-        let mut grounding_text = String::new();
-
-        // 1. 处理搜索词
-        if let Some(queries) = &grounding.web_search_queries {
-            if !queries.is_empty() {
-                grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
-                grounding_text.push_str(&queries.join(", "));
-            }
-        }
+        let queries = grounding.web_search_queries.as_deref().unwrap_or(&[]);
+        let chunks = grounding.grounding_chunks.as_deref().unwrap_or(&[]);
 
-        // 2. 处理来源链接 (Chunks)
-        if let Some(chunks) = &grounding.grounding_chunks {
-            let mut links = Vec::new();
-            for (i, chunk) in chunks.iter().enumerate() {
-                if let Some(web) = &chunk.web {
-                    let title = web.title.as_deref().unwrap_or("网页来源");
-                    let uri = web.uri.as_deref().unwrap_or("#");
-                    links.push(format!("[{}] [{}]({})", i + 1, title, uri));
-                }
-            }
-
-            if !links.is_empty() {
-                grounding_text.push_str("\n\n**🌐 来源引文：**\n");
-                grounding_text.push_str(&links.join("\n"));
+        if !self.web_search_blocks {
+            if let Some(text) = super::utils::build_web_search_markdown(queries, chunks) {
+                self.text_builder.push_str(&text);
+                self.flush_text();
             }
+            return;
         }
 
-        if !grounding_text.is_empty() {
-            // 在常规内容前后刷新并插入文本
-            self.flush_thinking();
-            self.flush_text();
-            self.text_builder.push_str(&grounding_text);
-            self.flush_text();
-        }
+        let Some((tool_use, tool_result)) =
+            super::utils::build_web_search_blocks(queries.first().map(|s| s.as_str()), chunks)
+        else {
+            return;
+        };
+        self.web_search_requests = super::utils::web_search_request_count(queries);
+        self.content_blocks.push(tool_use);
+        self.content_blocks.push(tool_result);
     }
 
     /// 刷新 text builder
@@ -502,15 +503,10 @@ impl NonStreamingProcessor {
             .and_then(|c| c.get(0))
             .and_then(|candidate| candidate.finish_reason.as_deref());
 
-        let stop_reason = if self.has_tool_call {
-            "tool_use"
-        } else if finish_reason == Some("MAX_TOKENS") {
-            "max_tokens"
-        } else {
-            "end_turn"
-        };
+        let stop_reason =
+            crate::proxy::mappers::finish_reason::to_claude_stop_reason(finish_reason, self.has_tool_call);
 
-        let usage = gemini_response
+        let mut usage = gemini_response
             .usage_metadata
             .as_ref()
             .map(|u| to_claude_usage(u, self.scaling_enabled, self.context_limit))
@@ -522,6 +518,11 @@ impl NonStreamingProcessor {
                 server_tool_use: None,
             });
 
+        // [NEW] 记录 web search 调用次数
+        if self.web_search_requests > 0 {
+            usage.server_tool_use = Some(json!({ "web_search_requests": self.web_search_requests }));
+        }
+
         ClaudeResponse {
             id: gemini_response.response_id.clone().unwrap_or_else(|| {
                 format!("msg_{}", crate::proxy::common::utils::generate_random_id())
@@ -544,9 +545,38 @@ pub fn transform_response(
     session_id: Option<String>,
     model_name: String,
     message_count: usize, // [NEW v4.0.0] Message count for rewind detection
+    web_search_blocks: bool, // [NEW] Emit grounding as web search blocks instead of Markdown citations
 ) -> Result<ClaudeResponse, String> {
     let mut processor = NonStreamingProcessor::new(session_id, model_name, message_count);
-    Ok(processor.process(gemini_response, scaling_enabled, context_limit))
+    processor.web_search_blocks = web_search_blocks;
+    let mut response = processor.process(gemini_response, scaling_enabled, context_limit);
+    // [NEW] 按配置调整思维链输出方式 (签名已在处理过程中写入缓存)
+    let output = crate::proxy::config::get_thinking_budget_config().claude_thinking_output;
+    response.content = apply_thinking_output(response.content, output);
+    Ok(response)
+}
+
+/// 按输出方式转换 thinking 块: Strip 直接丢弃，Tagged 转为 <thinking> 标签包裹的文本
+fn apply_thinking_output(
+    blocks: Vec<ContentBlock>,
+    output: crate::proxy::config::ClaudeThinkingOutput,
+) -> Vec<ContentBlock> {
+    use crate::proxy::config::ClaudeThinkingOutput;
+    if output == ClaudeThinkingOutput::Blocks {
+        return blocks;
+    }
+    blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            ContentBlock::Thinking { thinking, .. } => {
+                (output == ClaudeThinkingOutput::Tagged && !thinking.is_empty()).then(|| ContentBlock::Text {
+                    text: format!("<thinking>\n{}\n</thinking>\n\n", thinking),
+                })
+            }
+            ContentBlock::RedactedThinking { .. } => None,
+            other => Some(other),
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -577,6 +607,7 @@ mod tests {
                 candidates_token_count: Some(5),
                 total_token_count: Some(15),
                 cached_content_token_count: None,
+                thoughts_token_count: None,
             }),
             model_version: Some("gemini-2.5-flash".to_string()),
             response_id: Some("resp_123".to_string()),
@@ -589,6 +620,7 @@ mod tests {
             None,
             "gemini-2.5-flash".to_string(),
             1,
+            false,
         );
         assert!(result.is_ok());
 
@@ -646,6 +678,7 @@ mod tests {
             None,
             "gemini-2.5-flash".to_string(),
             1,
+            false,
         );
         assert!(result.is_ok());
 
@@ -671,4 +704,198 @@ mod tests {
             _ => panic!("Expected Text block"),
         }
     }
+
+    #[test]
+    fn test_apply_thinking_output() {
+        use crate::proxy::config::ClaudeThinkingOutput;
+        let blocks = vec![
+            ContentBlock::Thinking {
+                thinking: "step 1".to_string(),
+                signature: Some("sig".to_string()),
+                cache_control: None,
+            },
+            ContentBlock::Text { text: "answer".to_string() },
+            ContentBlock::Thinking {
+                thinking: String::new(),
+                signature: Some("trailing".to_string()),
+                cache_control: None,
+            },
+        ];
+
+        assert_eq!(apply_thinking_output(blocks.clone(), ClaudeThinkingOutput::Blocks).len(), 3);
+
+        let stripped = apply_thinking_output(blocks.clone(), ClaudeThinkingOutput::Strip);
+        assert_eq!(stripped.len(), 1);
+        assert!(matches!(&stripped[0], ContentBlock::Text { text } if text == "answer"));
+
+        let tagged = apply_thinking_output(blocks, ClaudeThinkingOutput::Tagged);
+        assert_eq!(tagged.len(), 2);
+        match &tagged[0] {
+            ContentBlock::Text { text } => assert_eq!(text, "<thinking>\nstep 1\n</thinking>\n\n"),
+            _ => panic!("Expected Text block"),
+        }
+    }
+
+    #[test]
+    fn test_inline_image_becomes_image_block() {
+        let gemini_resp = GeminiResponse {
+            candidates: Some(vec![Candidate {
+                content: Some(GeminiContent {
+                    role: "model".to_string(),
+                    parts: vec![
+                        GeminiPart {
+                            text: Some("Here is your cat".to_string()),
+                            thought: None,
+                            thought_signature: None,
+                            function_call: None,
+                            function_response: None,
+                            inline_data: None,
+                        },
+                        GeminiPart {
+                            text: None,
+                            thought: None,
+                            thought_signature: None,
+                            function_call: None,
+                            function_response: None,
+                            inline_data: Some(InlineData {
+                                mime_type: "image/png".to_string(),
+                                data: "iVBORw0KGgo=".to_string(),
+                            }),
+                        },
+                    ],
+                }),
+                finish_reason: Some("STOP".to_string()),
+                index: Some(0),
+                grounding_metadata: None,
+            }]),
+            usage_metadata: None,
+            model_version: Some("gemini-3-pro-image".to_string()),
+            response_id: Some("resp_img".to_string()),
+        };
+
+        let claude_resp = transform_response(
+            &gemini_resp,
+            false,
+            1_000_000,
+            None,
+            "gemini-3-pro-image".to_string(),
+            1,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(claude_resp.content.len(), 2);
+        assert!(matches!(&claude_resp.content[0], ContentBlock::Text { text } if text == "Here is your cat"));
+        match &claude_resp.content[1] {
+            ContentBlock::Image { source, .. } => {
+                assert_eq!(source.source_type, "base64");
+                assert_eq!(source.media_type, "image/png");
+                assert_eq!(source.data, "iVBORw0KGgo=");
+            }
+            _ => panic!("Expected Image block"),
+        }
+    }
+
+    fn grounded_response() -> GeminiResponse {
+        GeminiResponse {
+            candidates: Some(vec![Candidate {
+                content: Some(GeminiContent {
+                    role: "model".to_string(),
+                    parts: vec![GeminiPart {
+                        text: Some("Rust 1.80 was released.".to_string()),
+                        thought: None,
+                        thought_signature: None,
+                        function_call: None,
+                        function_response: None,
+                        inline_data: None,
+                    }],
+                }),
+                finish_reason: Some("STOP".to_string()),
+                index: Some(0),
+                grounding_metadata: Some(GroundingMetadata {
+                    web_search_queries: Some(vec!["rust release".to_string()]),
+                    grounding_chunks: Some(vec![
+                        GroundingChunk {
+                            web: Some(WebSource {
+                                uri: Some("https://blog.rust-lang.org".to_string()),
+                                title: Some("Rust Blog".to_string()),
+                            }),
+                        },
+                        GroundingChunk { web: None },
+                    ]),
+                    grounding_supports: None,
+                    search_entry_point: None,
+                }),
+            }]),
+            usage_metadata: None,
+            model_version: Some("gemini-2.5-flash".to_string()),
+            response_id: Some("resp_search".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_grounding_becomes_web_search_blocks() {
+        let claude_resp = transform_response(
+            &grounded_response(),
+            false,
+            1_000_000,
+            None,
+            "gemini-2.5-flash".to_string(),
+            1,
+            true,
+        )
+        .unwrap();
+
+        // 与流式输出一致: 正文在前，web search 块追加在后
+        assert_eq!(claude_resp.content.len(), 3);
+        assert!(matches!(&claude_resp.content[0], ContentBlock::Text { text } if text == "Rust 1.80 was released."));
+        let tool_id = match &claude_resp.content[1] {
+            ContentBlock::ServerToolUse { id, name, input } => {
+                assert_eq!(name, "web_search");
+                assert_eq!(input["query"], "rust release");
+                id.clone()
+            }
+            _ => panic!("Expected ServerToolUse block"),
+        };
+        match &claude_resp.content[2] {
+            ContentBlock::WebSearchToolResult { tool_use_id, content } => {
+                assert_eq!(tool_use_id, &tool_id);
+                let results = content.as_array().unwrap();
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0]["type"], "web_search_result");
+                assert_eq!(results[0]["url"], "https://blog.rust-lang.org");
+                assert_eq!(results[0]["title"], "Rust Blog");
+            }
+            _ => panic!("Expected WebSearchToolResult block"),
+        }
+        assert_eq!(
+            claude_resp.usage.server_tool_use,
+            Some(json!({ "web_search_requests": 1 }))
+        );
+    }
+
+    #[test]
+    fn test_grounding_defaults_to_markdown_citations() {
+        let claude_resp = transform_response(
+            &grounded_response(),
+            false,
+            1_000_000,
+            None,
+            "gemini-2.5-flash".to_string(),
+            1,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(claude_resp.content.len(), 2);
+        assert!(matches!(&claude_resp.content[0], ContentBlock::Text { text } if text == "Rust 1.80 was released."));
+        match &claude_resp.content[1] {
+            ContentBlock::Text { text } => {
+                assert!(text.contains("rust release"));
+                assert!(text.contains("[Rust Blog](https://blog.rust-lang.org)"));
+            }
+            _ => panic!("Expected citation text block"),
+        }
+        assert!(claude_resp.usage.server_tool_use.is_none());
+    }
 }
@@ -7,6 +7,7 @@ use crate::proxy::mappers::estimation_calibrator::get_calibrator;
 // use crate::proxy::mappers::signature_store::store_thought_signature; // Deprecated
 use crate::proxy::SignatureCache;
 use crate::proxy::common::client_adapter::{ClientAdapter, SignatureBufferStrategy}; // [NEW]
+use crate::proxy::config::ClaudeThinkingOutput;
 use bytes::Bytes;
 use serde_json::{json, Value};
 
@@ -164,6 +165,287 @@ pub fn remap_function_call_args(name: &str, args: &mut Value) {
     }
 }
 
+/// 单个 input_json_delta 的最大字符数，大参数拆成多个 delta 发送
+const INPUT_JSON_DELTA_CHUNK_CHARS: usize = 1024;
+
+/// 按字符边界切分参数 JSON
+fn split_json_chunks(json_str: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for (count, c) in json_str.chars().enumerate() {
+        if count > 0 && count % INPUT_JSON_DELTA_CHUNK_CHARS == 0 {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// remap_function_call_args 中有专用修正规则的工具，必须拿到完整参数后再输出
+fn needs_full_args(name: &str) -> bool {
+    matches!(
+        name.to_lowercase().as_str(),
+        "enterplanmode"
+            | "grep"
+            | "search"
+            | "search_code_definitions"
+            | "search_code_snippets"
+            | "glob"
+            | "read"
+            | "ls"
+    )
+}
+
+/// 参数分片转为增量 JSON 文本
+/// 顶层参数 ($.key) 边收边输出，字符串值按片段续写；嵌套路径的参数暂存，结束时一次性追加
+#[derive(Default)]
+struct ToolArgsWriter {
+    started: bool,
+    current_key: Option<String>,
+    in_string: bool,
+    written_keys: Vec<String>,
+    deferred: Value,
+}
+
+impl ToolArgsWriter {
+    fn open_key(&mut self, out: &mut String, key: &str) {
+        if !self.started {
+            out.push('{');
+            self.started = true;
+        }
+        if self.current_key.as_deref() == Some(key) {
+            return;
+        }
+        self.close_string(out);
+        if !self.written_keys.is_empty() {
+            out.push(',');
+        }
+        out.push_str(&serde_json::to_string(key).unwrap_or_default());
+        out.push(':');
+        self.written_keys.push(key.to_string());
+        self.current_key = Some(key.to_string());
+    }
+
+    fn close_string(&mut self, out: &mut String) {
+        if self.in_string {
+            out.push('"');
+            self.in_string = false;
+            self.current_key = None;
+        }
+    }
+
+    /// 写入一个完整的顶层参数值
+    fn push_value(&mut self, key: &str, value: &Value) -> String {
+        let mut out = String::new();
+        self.close_string(&mut out);
+        if self.written_keys.iter().any(|k| k == key) {
+            return out;
+        }
+        self.open_key(&mut out, key);
+        out.push_str(&serde_json::to_string(value).unwrap_or_else(|_| "null".to_string()));
+        self.current_key = None;
+        out
+    }
+
+    fn push_partial(&mut self, arg: &PartialArg) -> String {
+        let path = partial_arg_path(arg);
+        let value = partial_arg_value(arg);
+
+        // 嵌套路径无法在不知道整体结构的情况下流式输出，先暂存
+        if path.is_empty() || path.contains('.') || path.contains('[') {
+            set_json_path(&mut self.deferred, path, value);
+            return String::new();
+        }
+
+        let Some(piece) = arg.string_value.as_deref() else {
+            return self.push_value(path, &value);
+        };
+
+        let mut out = String::new();
+        if self.current_key.as_deref() != Some(path) || !self.in_string {
+            if self.written_keys.iter().any(|k| k == path) {
+                return out;
+            }
+            self.open_key(&mut out, path);
+            out.push('"');
+            self.in_string = true;
+        }
+        let escaped = serde_json::to_string(piece).unwrap_or_default();
+        out.push_str(&escaped[1..escaped.len() - 1]);
+        if !arg.will_continue {
+            self.close_string(&mut out);
+        }
+        out
+    }
+
+    fn finish(&mut self) -> String {
+        let mut out = String::new();
+        if !self.started {
+            out.push('{');
+            self.started = true;
+        }
+        self.close_string(&mut out);
+        let deferred = match std::mem::take(&mut self.deferred) {
+            Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        for (key, value) in deferred {
+            if self.written_keys.contains(&key) {
+                continue;
+            }
+            if !self.written_keys.is_empty() {
+                out.push(',');
+            }
+            out.push_str(&serde_json::to_string(&key).unwrap_or_default());
+            out.push(':');
+            out.push_str(&serde_json::to_string(&value).unwrap_or_else(|_| "null".to_string()));
+            self.written_keys.push(key);
+        }
+        out.push('}');
+        out
+    }
+}
+
+fn partial_arg_path(arg: &PartialArg) -> &str {
+    arg.json_path.trim_start_matches('$').trim_start_matches('.')
+}
+
+fn partial_arg_value(arg: &PartialArg) -> Value {
+    if let Some(s) = &arg.string_value {
+        json!(s)
+    } else if let Some(n) = arg.number_value {
+        json!(n)
+    } else if let Some(b) = arg.bool_value {
+        json!(b)
+    } else {
+        Value::Null
+    }
+}
+
+/// 正在分片接收参数的工具调用 (对应当前打开的 tool_use 块)
+struct StreamingToolCall {
+    id: String,
+    /// remap_function_call_args 使用的工具名
+    remap_name: String,
+    writer: ToolArgsWriter,
+    /// 需要完整参数才能修正的工具：先缓冲，块结束时统一输出
+    buffered: Option<Value>,
+}
+
+impl StreamingToolCall {
+    fn new(id: String, remap_name: String) -> Self {
+        let buffered = needs_full_args(&remap_name).then(|| json!({}));
+        Self {
+            id,
+            remap_name,
+            writer: ToolArgsWriter::default(),
+            buffered,
+        }
+    }
+
+    /// 写入一个分片，返回可立即发送的 partial_json 片段
+    fn feed(&mut self, fc: &FunctionCall) -> Vec<String> {
+        let partial_args = fc.partial_args.as_deref().unwrap_or_default();
+
+        if let Some(buffered) = self.buffered.as_mut() {
+            if let Some(Value::Object(args)) = &fc.args {
+                for (key, value) in args {
+                    set_json_path(buffered, key, value.clone());
+                }
+            }
+            for arg in partial_args {
+                set_json_path(buffered, partial_arg_path(arg), partial_arg_value(arg));
+            }
+            return Vec::new();
+        }
+
+        let mut pieces = Vec::new();
+        if let Some(Value::Object(args)) = &fc.args {
+            for (key, value) in args {
+                pieces.push(self.writer.push_value(key, value));
+            }
+        }
+        for arg in partial_args {
+            pieces.push(self.writer.push_partial(arg));
+        }
+        pieces.retain(|p| !p.is_empty());
+        pieces
+    }
+
+    /// 块结束时补齐剩余的参数 JSON
+    fn finish(mut self) -> Vec<String> {
+        match self.buffered.take() {
+            Some(mut args) => {
+                remap_function_call_args(&self.remap_name, &mut args);
+                split_json_chunks(&serde_json::to_string(&args).unwrap_or_else(|_| "{}".to_string()))
+            }
+            None => vec![self.writer.finish()],
+        }
+    }
+}
+
+/// 按 "a.b[0].c" 形式的路径写入值，字符串片段追加到已有字符串之后
+fn set_json_path(root: &mut Value, path: &str, value: Value) {
+    enum Segment {
+        Key(String),
+        Index(usize),
+    }
+
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let mut rest = part;
+        if let Some(bracket) = rest.find('[') {
+            if bracket > 0 {
+                segments.push(Segment::Key(rest[..bracket].to_string()));
+            }
+            rest = &rest[bracket..];
+            while let Some(end) = rest.find(']') {
+                if let Ok(idx) = rest[1..end].parse::<usize>() {
+                    segments.push(Segment::Index(idx));
+                }
+                rest = &rest[end + 1..];
+            }
+        } else if !rest.is_empty() {
+            segments.push(Segment::Key(rest.to_string()));
+        }
+    }
+
+    let mut current = root;
+    for segment in segments {
+        current = match segment {
+            Segment::Key(key) => {
+                if !current.is_object() {
+                    *current = json!({});
+                }
+                current
+                    .as_object_mut()
+                    .expect("object")
+                    .entry(key)
+                    .or_insert(Value::Null)
+            }
+            Segment::Index(idx) => {
+                if !current.is_array() {
+                    *current = json!([]);
+                }
+                let arr = current.as_array_mut().expect("array");
+                while arr.len() <= idx {
+                    arr.push(Value::Null);
+                }
+                &mut arr[idx]
+            }
+        };
+    }
+
+    if let (Value::String(existing), Value::String(piece)) = (&mut *current, &value) {
+        existing.push_str(piece);
+        return;
+    }
+    *current = value;
+}
+
 /// 块类型枚举
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockType {
@@ -171,6 +453,8 @@ pub enum BlockType {
     Text,
     Thinking,
     Function,
+    Image,
+    WebSearch,
 }
 
 /// 签名管理器
@@ -207,8 +491,10 @@ pub struct StreamingState {
     used_tool: bool,
     signatures: SignatureManager,
     trailing_signature: Option<String>,
-    pub web_search_query: Option<String>,
+    pub web_search_queries: Vec<String>,
     pub grounding_chunks: Option<Vec<serde_json::Value>>,
+    // [NEW] 以 web search 块输出搜索来源 (关闭时输出 Markdown 引文文本)
+    pub web_search_blocks: bool,
     // [IMPROVED] Error recovery 状态追踪 (prepared for future use)
     #[allow(dead_code)]
     parse_error_count: usize,
@@ -234,6 +520,14 @@ pub struct StreamingState {
     pub client_adapter: Option<std::sync::Arc<dyn ClientAdapter>>, // [FIX] Remove Box, use Arc<dyn> directly
     // [FIX #MCP] Registered tool names for fuzzy matching
     pub registered_tool_names: Vec<String>,
+    // [NEW] 上游流中途出错 (已发送 error 事件，不再发送正常结束事件)
+    pub stream_errored: bool,
+    // [NEW] 参数跨 chunk 分片传输的工具调用
+    pending_tool_call: Option<StreamingToolCall>,
+    // [NEW] 思维链输出方式 (thinking 块 / 丢弃 / <thinking> 标签文本)
+    pub thinking_output: ClaudeThinkingOutput,
+    // [NEW] Tagged 模式下 <thinking> 标签是否处于打开状态
+    thinking_tag_open: bool,
 }
 
 impl StreamingState {
@@ -246,8 +540,9 @@ impl StreamingState {
             used_tool: false,
             signatures: SignatureManager::new(),
             trailing_signature: None,
-            web_search_query: None,
+            web_search_queries: Vec::new(),
             grounding_chunks: None,
+            web_search_blocks: false,
             // [IMPROVED] 初始化 error recovery 字段
             parse_error_count: 0,
             last_valid_state: None,
@@ -263,6 +558,10 @@ impl StreamingState {
             message_count: 0,
             client_adapter: None,
             registered_tool_names: Vec::new(),
+            stream_errored: false,
+            pending_tool_call: None,
+            thinking_output: ClaudeThinkingOutput::Blocks,
+            thinking_tag_open: false,
         }
     }
 
@@ -362,7 +661,7 @@ impl StreamingState {
             return vec![];
         }
 
-        let mut chunks = Vec::new();
+        let mut chunks = self.close_thinking_tag();
 
         // Thinking 块结束时发送暂存的签名
         if self.block_type == BlockType::Thinking && self.signatures.has_pending() {
@@ -371,6 +670,15 @@ impl StreamingState {
             }
         }
 
+        // 分片参数的工具调用：补齐剩余的参数 JSON
+        if self.block_type == BlockType::Function {
+            if let Some(call) = self.pending_tool_call.take() {
+                for piece in call.finish() {
+                    chunks.push(self.emit_delta("input_json_delta", json!({ "partial_json": piece })));
+                }
+            }
+        }
+
         chunks.push(self.emit(
             "content_block_stop",
             json!({
@@ -429,68 +737,39 @@ impl StreamingState {
             // 不再追加 chunks.push(self.emit("content_block_start", ...))
         }
 
-        // 处理 grounding(web search) -> 转换为 Markdown 文本块
-        if self.web_search_query.is_some() || self.grounding_chunks.is_some() {
-            let mut grounding_text = String::new();
-
-            // 1. 处理搜索词
-            if let Some(query) = &self.web_search_query {
-                if !query.is_empty() {
-                    grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
-                    grounding_text.push_str(query);
-                }
-            }
-
-            // 2. 处理来源链接
-            if let Some(chunks) = &self.grounding_chunks {
-                let mut links = Vec::new();
-                for (i, chunk) in chunks.iter().enumerate() {
-                    if let Some(web) = chunk.get("web") {
-                        let title = web
-                            .get("title")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("网页来源");
-                        let uri = web.get("uri").and_then(|v| v.as_str()).unwrap_or("#");
-                        links.push(format!("[{}] [{}]({})", i + 1, title, uri));
-                    }
-                }
-
-                if !links.is_empty() {
-                    grounding_text.push_str("\n\n**🌐 来源引文：**\n");
-                    grounding_text.push_str(&links.join("\n"));
+        // 处理 grounding(web search) -> server_tool_use / web_search_tool_result 块或 Markdown 引文
+        // 流式场景下 groundingMetadata 通常随最后几个 chunk 到达，因此统一在正文之后追加 (非流式同序)
+        let mut web_search_requests = 0;
+        let queries = std::mem::take(&mut self.web_search_queries);
+        let grounding_chunks: Vec<GroundingChunk> = self
+            .grounding_chunks
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|c| serde_json::from_value(c).ok())
+            .collect();
+        if self.web_search_blocks {
+            if let Some((tool_use, tool_result)) =
+                super::utils::build_web_search_blocks(queries.first().map(|q| q.as_str()), &grounding_chunks)
+            {
+                for block in [tool_use, tool_result] {
+                    let block_json = serde_json::to_value(&block).unwrap_or_default();
+                    chunks.extend(self.start_block(BlockType::WebSearch, block_json));
+                    chunks.extend(self.end_block());
                 }
+                web_search_requests = super::utils::web_search_request_count(&queries);
             }
-
-            let trimmed_grounding = grounding_text.trim();
-            if !trimmed_grounding.is_empty() {
-                // 发送一个新的 text 块
-                chunks.push(self.emit(
-                    "content_block_start",
-                    json!({
-                        "type": "content_block_start",
-                        "index": self.block_index,
-                        "content_block": { "type": "text", "text": "" }
-                    }),
-                ));
-                chunks.push(self.emit_delta("text_delta", json!({ "text": trimmed_grounding })));
-                chunks.push(self.emit(
-                    "content_block_stop",
-                    json!({ "type": "content_block_stop", "index": self.block_index }),
-                ));
-                self.block_index += 1;
-            }
+        } else if let Some(text) = super::utils::build_web_search_markdown(&queries, &grounding_chunks) {
+            chunks.extend(self.start_block(BlockType::Text, json!({ "type": "text", "text": "" })));
+            chunks.push(self.emit_delta("text_delta", json!({ "text": text.trim() })));
+            chunks.extend(self.end_block());
         }
 
         // 确定 stop_reason
-        let stop_reason = if self.used_tool {
-            "tool_use"
-        } else if finish_reason == Some("MAX_TOKENS") {
-            "max_tokens"
-        } else {
-            "end_turn"
-        };
+        let stop_reason =
+            crate::proxy::mappers::finish_reason::to_claude_stop_reason(finish_reason, self.used_tool);
 
-        let usage = usage_metadata
+        let mut usage = usage_metadata
             .map(|u| {
                 // [FIX] Record actual token usage for calibrator learning
                 // Now properly pairs estimated tokens from request with actual tokens from response
@@ -517,6 +796,10 @@ impl StreamingState {
                 server_tool_use: None,
             });
 
+        if web_search_requests > 0 {
+            usage.server_tool_use = Some(json!({ "web_search_requests": web_search_requests }));
+        }
+
         chunks.push(self.emit(
             "message_delta",
             json!({
@@ -558,9 +841,22 @@ impl StreamingState {
 
     /// 设置 trailing signature
     pub fn set_trailing_signature(&mut self, signature: Option<String>) {
+        // 非 Blocks 模式不输出仅携带签名的空 thinking 块
+        if self.thinking_output != ClaudeThinkingOutput::Blocks {
+            return;
+        }
         self.trailing_signature = signature;
     }
 
+    /// Tagged 模式: 关闭尚未闭合的 <thinking> 标签
+    pub fn close_thinking_tag(&mut self) -> Vec<Bytes> {
+        if !self.thinking_tag_open {
+            return vec![];
+        }
+        self.thinking_tag_open = false;
+        vec![self.emit_delta("text_delta", json!({ "text": "\n</thinking>\n\n" }))]
+    }
+
     /// 获取 trailing signature (仅用于检查)
     pub fn has_trailing_signature(&self) -> bool {
         self.trailing_signature.is_some()
@@ -627,6 +923,38 @@ impl StreamingState {
         chunks
     }
 
+    /// 上游流中途出错: 关闭未完成的 block 并发送 Anthropic `error` 事件
+    ///
+    /// 按 Anthropic 规范，error 事件即为流的终止信号，之后不再发送 message_delta / message_stop，
+    /// 避免客户端把截断的输出当作正常完成 (stop_reason = end_turn)
+    pub fn emit_stream_error(&mut self, error: &str) -> Vec<Bytes> {
+        let mut chunks = Vec::new();
+        if self.block_type != BlockType::None {
+            chunks.extend(self.end_block());
+        }
+
+        let (_, user_msg, _) = crate::proxy::mappers::error_classifier::classify_stream_error(&error);
+        let error_type = if error.to_lowercase().contains("overloaded") {
+            "overloaded_error"
+        } else {
+            "api_error"
+        };
+        chunks.push(self.emit(
+            "error",
+            json!({
+                "type": "error",
+                "error": {
+                    "type": error_type,
+                    "message": format!("Upstream stream interrupted: {}", user_msg),
+                }
+            }),
+        ));
+
+        self.stream_errored = true;
+        self.message_stop_sent = true;
+        chunks
+    }
+
     /// 重置错误状态 (recovery 后调用)
     #[allow(dead_code)]
     pub fn reset_error_state(&mut self) {
@@ -678,6 +1006,17 @@ impl<'a> PartProcessor<'a> {
 
         // 1. FunctionCall 处理
         if let Some(fc) = &part.function_call {
+            // [NEW] 参数分片的后续片段：续写当前打开的 tool_use 块
+            let continues_open_call = self.state.current_block_type() == BlockType::Function
+                && self.state.pending_tool_call.as_ref().is_some_and(|call| {
+                    fc.name.is_empty() || fc.id.as_deref() == Some(call.id.as_str())
+                });
+            if continues_open_call {
+                chunks.extend(self.process_function_call_fragment(fc));
+                self.state.has_content = true;
+                return chunks;
+            }
+
             // 先处理 trailingSignature (B4/C3 场景)
             if self.state.has_trailing_signature() {
                 chunks.extend(self.state.end_block());
@@ -715,23 +1054,45 @@ impl<'a> PartProcessor<'a> {
                 chunks.extend(self.process_thinking(text, signature));
             } else {
                 // 普通 Text
+                if !text.is_empty() {
+                    chunks.extend(self.state.close_thinking_tag());
+                }
                 chunks.extend(self.process_text(text, signature));
             }
         }
 
         // 3. InlineData (Image) 处理
+        // [NEW] Claude 协议没有音频内容块，音频输出直接跳过
         if let Some(img) = &part.inline_data {
-            let mime_type = &img.mime_type;
-            let data = &img.data;
-            if !data.is_empty() {
-                let markdown_img = format!("![image](data:{};base64,{})", mime_type, data);
-                chunks.extend(self.process_text(&markdown_img, None));
+            if crate::proxy::audio::AudioProcessor::is_audio_mime_type(&img.mime_type) {
+                tracing::debug!("[Claude-SSE] Skipping audio inlineData ({})", img.mime_type);
+            } else if !img.data.is_empty() {
+                chunks.extend(self.process_image(&img.mime_type, &img.data));
             }
         }
 
         chunks
     }
 
+    /// 处理图片 (image_gen 输出)
+    /// Claude SSE 没有图片 delta 类型，因此在 content_block_start 中携带完整 image 块
+    fn process_image(&mut self, mime_type: &str, data: &str) -> Vec<Bytes> {
+        let mut chunks = self.state.start_block(
+            BlockType::Image,
+            json!({
+                "type": "image",
+                "source": {
+                    "type": "base64",
+                    "media_type": mime_type,
+                    "data": data
+                }
+            }),
+        );
+        chunks.extend(self.state.end_block());
+        self.state.has_content = true;
+        chunks
+    }
+
     /// 处理 Thinking
     fn process_thinking(&mut self, text: &str, signature: Option<String>) -> Vec<Bytes> {
         let mut chunks = Vec::new();
@@ -760,24 +1121,43 @@ impl<'a> PartProcessor<'a> {
             }
         }
 
-        // 开始或继续 thinking 块
-        if self.state.current_block_type() != BlockType::Thinking {
-            chunks.extend(self.state.start_block(
-                BlockType::Thinking,
-                json!({ "type": "thinking", "thinking": "" }),
-            ));
+        match self.state.thinking_output {
+            ClaudeThinkingOutput::Blocks => {
+                // 开始或继续 thinking 块
+                if self.state.current_block_type() != BlockType::Thinking {
+                    chunks.extend(self.state.start_block(
+                        BlockType::Thinking,
+                        json!({ "type": "thinking", "thinking": "" }),
+                    ));
+                }
+
+                if !text.is_empty() {
+                    chunks.push(
+                        self.state
+                            .emit_delta("thinking_delta", json!({ "thinking": text })),
+                    );
+                }
+            }
+            ClaudeThinkingOutput::Tagged if !text.is_empty() => {
+                // 以普通文本输出，首段思维链前打开 <thinking> 标签
+                if !self.state.thinking_tag_open {
+                    if self.state.current_block_type() != BlockType::Text {
+                        chunks.extend(
+                            self.state
+                                .start_block(BlockType::Text, json!({ "type": "text", "text": "" })),
+                        );
+                    }
+                    chunks.push(self.state.emit_delta("text_delta", json!({ "text": "<thinking>\n" })));
+                    self.state.thinking_tag_open = true;
+                }
+                chunks.push(self.state.emit_delta("text_delta", json!({ "text": text })));
+            }
+            _ => {}
         }
 
         // [FIX #859] Mark that we have received thinking content
         self.state.has_thinking = true;
 
-        if !text.is_empty() {
-            chunks.push(
-                self.state
-                    .emit_delta("thinking_delta", json!({ "thinking": text })),
-            );
-        }
-
         // [NEW] Apply Client Adapter Strategy
         let use_fifo = self.state.client_adapter.as_ref()
             .map(|a| a.signature_buffer_strategy() == SignatureBufferStrategy::Fifo)
@@ -820,7 +1200,10 @@ impl<'a> PartProcessor<'a> {
         // If FIFO, we strictly follow the sequence. The default logic is effectively LIFO for a single turn 
         // (store latest, consume at end). 
         // For opencode, we just want to ensure we capture IT.
-        self.state.store_signature(signature);
+        // 非 Blocks 模式没有 thinking 块可承载签名，仅依赖上面的全局缓存
+        if self.state.thinking_output == ClaudeThinkingOutput::Blocks {
+            self.state.store_signature(signature);
+        }
 
         chunks
     }
@@ -910,6 +1293,8 @@ impl<'a> PartProcessor<'a> {
                                 name: tool_name.to_string(),
                                 args: Some(input_json),
                                 id: Some(format!("{}-xml", tool_name)),
+                                partial_args: None,
+                                will_continue: None,
                             };
 
                             let tool_chunks = self.process_function_call(&fc, None);
@@ -1040,27 +1425,45 @@ impl<'a> PartProcessor<'a> {
 
         chunks.extend(self.state.start_block(BlockType::Function, tool_use));
 
-        // 2. 发送 input_json_delta (完整的参数 JSON 字符串)
+        // [OPTIMIZED] Only rename if it's "search" which is a known hallucination.
+        // Avoid renaming "grep" to "Grep" if possible to protect signature,
+        // unless we're sure Grep is the standard.
+        let mut final_tool_name = fc.name.clone();
+        if final_tool_name.to_lowercase() == "search" {
+            final_tool_name = "Grep".to_string();
+        }
+
+        // [NEW] 参数分片传输：边收边发 input_json_delta，块保持打开直到最后一个分片
+        if fc.will_continue == Some(true) || fc.partial_args.is_some() {
+            let mut call = StreamingToolCall::new(tool_id, final_tool_name);
+            let pieces = call.feed(fc);
+            for piece in pieces {
+                chunks.push(
+                    self.state
+                        .emit_delta("input_json_delta", json!({ "partial_json": piece })),
+                );
+            }
+            self.state.pending_tool_call = Some(call);
+            if fc.will_continue != Some(true) {
+                chunks.extend(self.state.end_block());
+            }
+            return chunks;
+        }
+
+        // 2. 发送 input_json_delta (参数 JSON 按长度拆分为多个 delta)
         // [FIX] Remap args before serialization for Gemini → Claude compatibility
         if let Some(args) = &fc.args {
             let mut remapped_args = args.clone();
-
-            let tool_name_title = fc.name.clone();
-            // [OPTIMIZED] Only rename if it's "search" which is a known hallucination.
-            // Avoid renaming "grep" to "Grep" if possible to protect signature,
-            // unless we're sure Grep is the standard.
-            let mut final_tool_name = tool_name_title;
-            if final_tool_name.to_lowercase() == "search" {
-                final_tool_name = "Grep".to_string();
-            }
             remap_function_call_args(&final_tool_name, &mut remapped_args);
 
             let json_str =
                 serde_json::to_string(&remapped_args).unwrap_or_else(|_| "{}".to_string());
-            chunks.push(
-                self.state
-                    .emit_delta("input_json_delta", json!({ "partial_json": json_str })),
-            );
+            for piece in split_json_chunks(&json_str) {
+                chunks.push(
+                    self.state
+                        .emit_delta("input_json_delta", json!({ "partial_json": piece })),
+                );
+            }
         }
 
         // 3. 结束块
@@ -1068,6 +1471,27 @@ impl<'a> PartProcessor<'a> {
 
         chunks
     }
+
+    /// 处理参数分片的后续片段
+    fn process_function_call_fragment(&mut self, fc: &FunctionCall) -> Vec<Bytes> {
+        let mut chunks = Vec::new();
+        let pieces = self
+            .state
+            .pending_tool_call
+            .as_mut()
+            .map(|call| call.feed(fc))
+            .unwrap_or_default();
+        for piece in pieces {
+            chunks.push(
+                self.state
+                    .emit_delta("input_json_delta", json!({ "partial_json": piece })),
+            );
+        }
+        if fc.will_continue != Some(true) {
+            chunks.extend(self.state.end_block());
+        }
+        chunks
+    }
 }
 
 /// [FIX #MCP] Fuzzy match an incorrect MCP tool name against registered tool names.
@@ -1206,6 +1630,25 @@ mod tests {
         assert!(s.contains("\"foo\":\"bar\""));
     }
 
+    #[test]
+    fn test_emit_stream_error_closes_block_and_terminates() {
+        let mut state = StreamingState::new();
+        state.message_start_sent = true;
+        state.start_block(BlockType::Text, json!({ "type": "text", "text": "" }));
+
+        let output: String = state
+            .emit_stream_error("error decoding response body")
+            .iter()
+            .map(|b| String::from_utf8(b.to_vec()).unwrap())
+            .collect();
+
+        assert!(output.contains("event: content_block_stop"));
+        assert!(output.contains("event: error"));
+        assert!(output.contains("\"type\":\"api_error\""));
+        assert!(!output.contains("message_stop"));
+        assert!(state.stream_errored && state.message_stop_sent);
+    }
+
     #[test]
     fn test_process_function_call_deltas() {
         let mut state = StreamingState::new();
@@ -1215,6 +1658,8 @@ mod tests {
             name: "test_tool".to_string(),
             args: Some(json!({"arg": "value"})),
             id: Some("call_123".to_string()),
+            partial_args: None,
+            will_continue: None,
         };
 
         // Create a dummy GeminiPart with function_call
@@ -1250,6 +1695,202 @@ mod tests {
         assert!(output.contains(r#""type":"content_block_stop""#));
     }
 
+    fn function_call_part(fc: Value) -> GeminiPart {
+        serde_json::from_value(json!({ "functionCall": fc })).unwrap()
+    }
+
+    fn collect_input_json(output: &str, index: usize) -> (String, usize) {
+        let mut json_text = String::new();
+        let mut deltas = 0;
+        for line in output.lines().filter_map(|l| l.strip_prefix("data: ")) {
+            let event: Value = serde_json::from_str(line).unwrap();
+            if event["delta"]["type"] == "input_json_delta" && event["index"] == index {
+                json_text.push_str(event["delta"]["partial_json"].as_str().unwrap());
+                deltas += 1;
+            }
+        }
+        (json_text, deltas)
+    }
+
+    #[test]
+    fn test_streamed_function_call_args_emit_incremental_deltas() {
+        let mut state = StreamingState::new();
+        let mut processor = PartProcessor::new(&mut state);
+
+        let parts = [
+            json!({
+                "name": "write_file",
+                "id": "call_1",
+                "willContinue": true,
+                "partialArgs": [{ "jsonPath": "$.path", "stringValue": "a.txt" }]
+            }),
+            json!({
+                "willContinue": true,
+                "partialArgs": [
+                    { "jsonPath": "$.content", "stringValue": "hel", "willContinue": true },
+                    { "jsonPath": "$.options.mode", "stringValue": "w" }
+                ]
+            }),
+            json!({ "partialArgs": [{ "jsonPath": "$.content", "stringValue": "lo\"" }] }),
+            // 同一响应中的第二个调用使用下一个 index
+            json!({ "name": "test_tool", "id": "call_2", "args": { "big": "x".repeat(3000) } }),
+        ];
+
+        let mut output = String::new();
+        for fc in parts {
+            for chunk in processor.process(&function_call_part(fc)) {
+                output.push_str(&String::from_utf8(chunk.to_vec()).unwrap());
+            }
+        }
+
+        let (first_json, first_deltas) = collect_input_json(&output, 0);
+        assert!(first_deltas >= 3, "args should stream as several deltas");
+        let first: Value = serde_json::from_str(&first_json).unwrap();
+        assert_eq!(
+            first,
+            json!({ "path": "a.txt", "content": "hello\"", "options": { "mode": "w" } })
+        );
+
+        let (second_json, second_deltas) = collect_input_json(&output, 1);
+        assert_eq!(second_deltas, 3, "large args are split into chunks");
+        let second: Value = serde_json::from_str(&second_json).unwrap();
+        assert_eq!(second["big"].as_str().unwrap().len(), 3000);
+        assert_eq!(output.matches(r#""type":"content_block_stop""#).count(), 2);
+    }
+
+    fn thought_part(text: &str) -> GeminiPart {
+        GeminiPart {
+            text: Some(text.to_string()),
+            function_call: None,
+            inline_data: None,
+            thought: Some(true),
+            thought_signature: None,
+            function_response: None,
+        }
+    }
+
+    #[test]
+    fn test_tagged_thinking_output_wraps_thoughts_in_text() {
+        let mut state = StreamingState::new();
+        state.thinking_output = ClaudeThinkingOutput::Tagged;
+        let mut processor = PartProcessor::new(&mut state);
+
+        let mut chunks = processor.process(&thought_part("step 1"));
+        chunks.extend(processor.process(&thought_part(" step 2")));
+        chunks.extend(processor.process(&GeminiPart {
+            thought: None,
+            ..thought_part("answer")
+        }));
+        chunks.extend(state.emit_finish(Some("STOP"), None));
+        let output = chunks
+            .iter()
+            .map(|b| String::from_utf8(b.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("");
+
+        assert!(!output.contains("thinking_delta"));
+        assert!(!output.contains(r#""type":"thinking""#));
+        let texts: Vec<String> = output
+            .lines()
+            .filter_map(|l| l.strip_prefix("data: "))
+            .filter_map(|d| serde_json::from_str::<Value>(d).ok())
+            .filter_map(|v| v["delta"]["text"].as_str().map(|s| s.to_string()))
+            .collect();
+        assert_eq!(texts.concat(), "<thinking>\nstep 1 step 2\n</thinking>\n\nanswer");
+    }
+
+    #[test]
+    fn test_strip_thinking_output_drops_thoughts() {
+        let mut state = StreamingState::new();
+        state.thinking_output = ClaudeThinkingOutput::Strip;
+        let mut processor = PartProcessor::new(&mut state);
+
+        let chunks = processor.process(&thought_part("hidden"));
+        assert!(chunks.is_empty());
+        assert!(state.has_thinking);
+    }
+
+    #[test]
+    fn test_process_inline_image_block() {
+        let mut state = StreamingState::new();
+        let mut processor = PartProcessor::new(&mut state);
+
+        let part = GeminiPart {
+            text: None,
+            function_call: None,
+            inline_data: Some(InlineData {
+                mime_type: "image/png".to_string(),
+                data: "iVBORw0KGgo=".to_string(),
+            }),
+            thought: None,
+            thought_signature: None,
+            function_response: None,
+        };
+
+        let output = processor
+            .process(&part)
+            .iter()
+            .map(|b| String::from_utf8(b.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("");
+
+        assert!(output.contains(r#""type":"image""#));
+        assert!(output.contains(r#""media_type":"image/png""#));
+        assert!(output.contains(r#""data":"iVBORw0KGgo=""#));
+        assert!(output.contains(r#""type":"content_block_stop""#));
+        assert!(!output.contains("![image]"));
+        assert_eq!(state.current_block_type(), BlockType::None);
+    }
+
+    #[test]
+    fn test_emit_finish_with_grounding_emits_web_search_blocks() {
+        let mut state = StreamingState::new();
+        state.web_search_blocks = true;
+        state.web_search_queries = vec!["rust release".to_string(), "rust 2024 edition".to_string()];
+        state.grounding_chunks = Some(vec![
+            json!({ "web": { "uri": "https://blog.rust-lang.org", "title": "Rust Blog" } }),
+            json!({ "web": { "uri": "https://blog.rust-lang.org", "title": "Rust Blog" } }),
+        ]);
+
+        let output = state
+            .emit_finish(Some("STOP"), None)
+            .iter()
+            .map(|b| String::from_utf8(b.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("");
+
+        assert!(output.contains(r#""type":"server_tool_use""#));
+        assert!(output.contains(r#""query":"rust release""#));
+        assert!(output.contains(r#""type":"web_search_tool_result""#));
+        assert!(output.contains(r#""url":"https://blog.rust-lang.org""#));
+        assert_eq!(output.matches(r#""url":"https://blog.rust-lang.org""#).count(), 1);
+        assert!(output.contains(r#""web_search_requests":2"#));
+        assert!(!output.contains("来源引文"));
+        assert_eq!(state.current_block_index(), 2);
+    }
+
+    #[test]
+    fn test_emit_finish_with_grounding_defaults_to_markdown_citations() {
+        let mut state = StreamingState::new();
+        state.web_search_queries = vec!["rust release".to_string()];
+        state.grounding_chunks = Some(vec![
+            json!({ "web": { "uri": "https://blog.rust-lang.org", "title": "Rust Blog" } }),
+        ]);
+
+        let output = state
+            .emit_finish(Some("STOP"), None)
+            .iter()
+            .map(|b| String::from_utf8(b.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("");
+
+        assert!(!output.contains("server_tool_use"));
+        assert!(!output.contains("web_search_tool_result"));
+        assert!(output.contains("来源引文"));
+        assert!(output.contains("[Rust Blog](https://blog.rust-lang.org)"));
+        assert_eq!(state.current_block_index(), 1);
+    }
+
     #[test]
     fn test_fuzzy_match_mcp_tool_exact_suffix() {
         let registered = vec![
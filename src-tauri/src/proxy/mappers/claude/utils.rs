@@ -89,15 +89,111 @@ pub fn to_claude_usage(usage_metadata: &super::models::UsageMetadata, scaling_en
         (scaled_total, None)
     };
     
+    // [NEW] Claude 的 output_tokens 包含 thinking 消耗，Gemini 将其单独计入 thoughtsTokenCount
+    let output_tokens = usage_metadata.candidates_token_count.unwrap_or(0)
+        + usage_metadata.thoughts_token_count.unwrap_or(0);
+
     super::models::Usage {
         input_tokens: reported_input,
-        output_tokens: usage_metadata.candidates_token_count.unwrap_or(0),
+        output_tokens,
         cache_read_input_tokens: reported_cache,
         cache_creation_input_tokens: Some(0),
         server_tool_use: None,
     }
 }
 
+/// 将 Gemini groundingMetadata 转换为 Claude 的 web_search 块对
+/// 返回 (server_tool_use, web_search_tool_result)，没有有效来源时返回 None
+pub fn build_web_search_blocks(
+    query: Option<&str>,
+    chunks: &[super::models::GroundingChunk],
+) -> Option<(super::models::ContentBlock, super::models::ContentBlock)> {
+    use super::models::ContentBlock;
+
+    // Gemini 的 groundingChunks 常对同一页面重复引用，按 URL 去重
+    let mut seen = std::collections::HashSet::new();
+    let results: Vec<serde_json::Value> = chunks
+        .iter()
+        .filter_map(|chunk| chunk.web.as_ref())
+        .filter_map(|web| {
+            let uri = web.uri.as_deref().filter(|u| !u.is_empty())?;
+            if !seen.insert(uri) {
+                return None;
+            }
+            Some(serde_json::json!({
+                "type": "web_search_result",
+                "url": uri,
+                "title": web.title.as_deref().unwrap_or(uri),
+                "encrypted_content": "", // Gemini 不提供该字段
+                "page_age": null
+            }))
+        })
+        .collect();
+
+    if results.is_empty() {
+        return None;
+    }
+
+    let tool_use_id = format!(
+        "srvtoolu_{}",
+        crate::proxy::common::utils::generate_random_id()
+    );
+
+    Some((
+        ContentBlock::ServerToolUse {
+            id: tool_use_id.clone(),
+            name: "web_search".to_string(),
+            input: serde_json::json!({ "query": query.unwrap_or_default() }),
+        },
+        ContentBlock::WebSearchToolResult {
+            tool_use_id,
+            content: serde_json::Value::Array(results),
+        },
+    ))
+}
+
+/// 将 groundingMetadata 渲染为 Markdown 引文文本 (未启用 web search 块时的兼容输出)
+pub fn build_web_search_markdown(
+    queries: &[String],
+    chunks: &[super::models::GroundingChunk],
+) -> Option<String> {
+    let mut text = String::new();
+
+    if !queries.is_empty() {
+        text.push_str("\n\n---\n**🔍 已为您搜索：** ");
+        text.push_str(&queries.join(", "));
+    }
+
+    let links: Vec<String> = chunks
+        .iter()
+        .filter_map(|chunk| chunk.web.as_ref())
+        .enumerate()
+        .map(|(i, web)| {
+            format!(
+                "[{}] [{}]({})",
+                i + 1,
+                web.title.as_deref().unwrap_or("网页来源"),
+                web.uri.as_deref().unwrap_or("#")
+            )
+        })
+        .collect();
+    if !links.is_empty() {
+        text.push_str("\n\n**🌐 来源引文：**\n");
+        text.push_str(&links.join("\n"));
+    }
+
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// web search 调用次数: 以 webSearchQueries 条数计 (无搜索词时按 1 次)
+pub fn web_search_request_count(queries: &[String]) -> usize {
+    queries.len().max(1)
+}
+
 /// 提取 thoughtSignature
 // 已移除未使用的 extract_thought_signature 函数
 
@@ -114,6 +210,7 @@ mod tests {
             candidates_token_count: Some(50),
             total_token_count: Some(150),
             cached_content_token_count: None,
+            thoughts_token_count: None,
         };
 
         let claude_usage = to_claude_usage(&usage, true, 1_000_000);
@@ -127,6 +224,7 @@ mod tests {
             candidates_token_count: Some(10),
             total_token_count: Some(500_010),
             cached_content_token_count: None,
+            thoughts_token_count: None,
         };
         let res_50 = to_claude_usage(&usage_50, true, 1_000_000);
         // 50% * 0.6 = 30% of 195k = 58,500
@@ -138,6 +236,7 @@ mod tests {
             candidates_token_count: Some(10),
             total_token_count: Some(700_010),
             cached_content_token_count: None,
+            thoughts_token_count: None,
         };
         let res_70 = to_claude_usage(&usage_70, true, 1_000_000);
         // 50% of 195k = 97,500
@@ -149,6 +248,7 @@ mod tests {
             candidates_token_count: Some(10),
             total_token_count: Some(850_010),
             cached_content_token_count: None,
+            thoughts_token_count: None,
         };
         let res_85 = to_claude_usage(&usage_85, true, 1_000_000);
         // 70% of 195k = 136,500
@@ -160,9 +260,29 @@ mod tests {
             candidates_token_count: Some(10),
             total_token_count: Some(1_000_010),
             cached_content_token_count: None,
+            thoughts_token_count: None,
         };
         let res_100 = to_claude_usage(&usage_100, true, 1_000_000);
         // 97% of 195k = 189,150
         assert!(res_100.input_tokens > 185_000 && res_100.input_tokens <= 190_000);
     }
+
+    #[test]
+    fn test_to_claude_usage_thoughts_and_cache() {
+        use super::super::models::UsageMetadata;
+
+        let usage: UsageMetadata = serde_json::from_value(serde_json::json!({
+            "promptTokenCount": 1000,
+            "candidatesTokenCount": 40,
+            "thoughtsTokenCount": 60,
+            "cachedContentTokenCount": 800,
+            "totalTokenCount": 1100
+        }))
+        .unwrap();
+
+        let claude_usage = to_claude_usage(&usage, false, 1_000_000);
+        assert_eq!(claude_usage.output_tokens, 100);
+        assert_eq!(claude_usage.cache_read_input_tokens, Some(800));
+        assert_eq!(claude_usage.input_tokens, 200);
+    }
 }
@@ -237,6 +237,22 @@ pub fn parse_image_config_with_params(
     )
 }
 
+/// 默认图像生成模型 (OpenAI Images API 的 dall-e / gpt-image 请求统一落到此模型)
+pub const DEFAULT_IMAGE_GEN_MODEL: &str = "gemini-3-pro-image";
+
+/// Resolve the model requested via the OpenAI Images API to a Gemini image model.
+///
+/// Gemini image model names (including -16x9 / -4k suffixes) are kept as-is so that
+/// `parse_image_config_with_params` can still infer aspectRatio/imageSize from them.
+/// Any other name (dall-e-3, gpt-image-1, empty, ...) falls back to `DEFAULT_IMAGE_GEN_MODEL`.
+pub fn resolve_image_gen_model(requested: &str) -> String {
+    let lower = requested.trim().to_lowercase();
+    if lower.starts_with("gemini-") && lower.contains("image") {
+        return lower;
+    }
+    DEFAULT_IMAGE_GEN_MODEL.to_string()
+}
+
 /// Helper function to clean image model names by removing resolution/aspect-ratio suffixes.
 /// E.g., "gemini-3.1-flash-image-16x9-4k" -> "gemini-3.1-flash-image"
 fn clean_image_model_name(model_name: &str) -> String {
@@ -662,6 +678,23 @@ mod tests {
         assert_eq!(model_override, "gemini-3-pro-image");
     }
 
+    #[test]
+    fn test_resolve_image_gen_model() {
+        assert_eq!(resolve_image_gen_model("dall-e-3"), "gemini-3-pro-image");
+        assert_eq!(resolve_image_gen_model("gpt-image-1"), "gemini-3-pro-image");
+        assert_eq!(resolve_image_gen_model(""), "gemini-3-pro-image");
+        assert_eq!(resolve_image_gen_model("gemini-2.5-flash"), "gemini-3-pro-image");
+        // Gemini 图像模型保留后缀，供后续解析 aspectRatio/imageSize
+        assert_eq!(
+            resolve_image_gen_model("gemini-3-pro-image-16x9-4k"),
+            "gemini-3-pro-image-16x9-4k"
+        );
+        assert_eq!(
+            resolve_image_gen_model("Gemini-3.1-Flash-Image"),
+            "gemini-3.1-flash-image"
+        );
+    }
+
     #[test]
     fn test_clean_image_model_name() {
         assert_eq!(clean_image_model_name("gemini-3.1-flash-image"), "gemini-3.1-flash-image");
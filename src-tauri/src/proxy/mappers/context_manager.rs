@@ -12,7 +12,7 @@ use tracing::{debug, info};
 /// - ASCII/English: ~4 characters per token
 /// - Unicode/CJK: ~1.5 characters per token (Chinese, Japanese, Korean are tokenized differently)
 /// - Adds 15% safety margin to prevent underestimation
-fn estimate_tokens_from_str(s: &str) -> u32 {
+pub(crate) fn estimate_tokens_from_str(s: &str) -> u32 {
     if s.is_empty() {
         return 0;
     }
@@ -123,59 +123,7 @@ impl ContextManager {
 
         // Messages
         for msg in &request.messages {
-            // Message overhead
-            total += 4;
-
-            match &msg.content {
-                MessageContent::String(s) => {
-                    total += estimate_tokens_from_str(s);
-                }
-                MessageContent::Array(blocks) => {
-                    for block in blocks {
-                        match block {
-                            ContentBlock::Text { text } => {
-                                total += estimate_tokens_from_str(text);
-                            }
-                            ContentBlock::Thinking { thinking, .. } => {
-                                total += estimate_tokens_from_str(thinking);
-                                // Signature overhead
-                                total += 100;
-                            }
-                            ContentBlock::RedactedThinking { data } => {
-                                total += estimate_tokens_from_str(data);
-                            }
-                            ContentBlock::ToolUse { name, input, .. } => {
-                                total += 20; // Function call overhead
-                                total += estimate_tokens_from_str(name);
-                                if let Ok(json_str) = serde_json::to_string(input) {
-                                    total += estimate_tokens_from_str(&json_str);
-                                }
-                            }
-                            ContentBlock::ToolResult { content, .. } => {
-                                total += 10; // Result overhead
-                                             // content is serde_json::Value
-                                if let Some(s) = content.as_str() {
-                                    total += estimate_tokens_from_str(s);
-                                } else if let Some(arr) = content.as_array() {
-                                    for item in arr {
-                                        if let Some(text) =
-                                            item.get("text").and_then(|t| t.as_str())
-                                        {
-                                            total += estimate_tokens_from_str(text);
-                                        }
-                                    }
-                                } else {
-                                    // Fallback for objects or other types
-                                    if let Ok(s) = serde_json::to_string(content) {
-                                        total += estimate_tokens_from_str(&s);
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
+            total += Self::estimate_message_tokens(msg);
         }
 
         // Tools definition overhead (rough estimate)
@@ -198,6 +146,65 @@ impl ContextManager {
         total
     }
 
+    /// Estimate token usage for a single message (including per-message overhead)
+    pub fn estimate_message_tokens(msg: &Message) -> u32 {
+        // Message overhead
+        let mut total = 4;
+
+        match &msg.content {
+            MessageContent::String(s) => {
+                total += estimate_tokens_from_str(s);
+            }
+            MessageContent::Array(blocks) => {
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text } => {
+                            total += estimate_tokens_from_str(text);
+                        }
+                        ContentBlock::Thinking { thinking, .. } => {
+                            total += estimate_tokens_from_str(thinking);
+                            // Signature overhead
+                            total += 100;
+                        }
+                        ContentBlock::RedactedThinking { data } => {
+                            total += estimate_tokens_from_str(data);
+                        }
+                        ContentBlock::ToolUse { name, input, .. } => {
+                            total += 20; // Function call overhead
+                            total += estimate_tokens_from_str(name);
+                            if let Ok(json_str) = serde_json::to_string(input) {
+                                total += estimate_tokens_from_str(&json_str);
+                            }
+                        }
+                        ContentBlock::ToolResult { content, .. } => {
+                            total += 10; // Result overhead
+                                         // content is serde_json::Value
+                            if let Some(s) = content.as_str() {
+                                total += estimate_tokens_from_str(s);
+                            } else if let Some(arr) = content.as_array() {
+                                for item in arr {
+                                    if let Some(text) =
+                                        item.get("text").and_then(|t| t.as_str())
+                                    {
+                                        total += estimate_tokens_from_str(text);
+                                    }
+                                }
+                            } else {
+                                // Fallback for objects or other types
+                                if let Ok(s) = serde_json::to_string(content) {
+                                    total += estimate_tokens_from_str(&s);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        total
+    }
+
     // ===== [Layer 2] Thinking Content Compression + Signature Preservation =====
     // Borrowed from learn-claude-code's "append-only log" principle
     // This layer compresses thinking text but PRESERVES signatures
@@ -428,7 +435,7 @@ fn has_tool_use(content: &MessageContent) -> bool {
 }
 
 /// Check if message content contains tool_result
-fn has_tool_result(content: &MessageContent) -> bool {
+pub(crate) fn has_tool_result(content: &MessageContent) -> bool {
     if let MessageContent::Array(blocks) = content {
         blocks
             .iter()
@@ -459,6 +466,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         }
     }
 
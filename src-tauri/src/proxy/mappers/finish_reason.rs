@@ -0,0 +1,86 @@
+// Gemini finishReason -> Claude stop_reason / OpenAI finish_reason 统一映射
+// 各协议转换器共用，避免各自 match 时把未知值一律落到 "end_turn" / "stop" 误导 Agent 循环。
+//
+// | Gemini finishReason                                  | Claude       | OpenAI         |
+// |------------------------------------------------------|--------------|----------------|
+// | STOP / FINISH_REASON_UNSPECIFIED / OTHER / 未知      | end_turn     | stop           |
+// | MAX_TOKENS                                           | max_tokens   | length         |
+// | SAFETY / RECITATION / BLOCKLIST / PROHIBITED_CONTENT |              |                |
+// | SPII / LANGUAGE / IMAGE_* (内容拦截)                 | refusal      | content_filter |
+// | MALFORMED_FUNCTION_CALL / UNEXPECTED_TOOL_CALL       |              |                |
+// | TOO_MANY_TOOL_CALLS (工具调用生成失败)               | pause_turn   | stop           |
+//
+// 已输出工具调用时: 内容拦截与截断仍按上表，其余一律为 tool_use / tool_calls。
+
+/// Gemini finishReason 的归类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinishKind {
+    Stop,
+    MaxTokens,
+    ContentFiltered,
+    MalformedToolCall,
+}
+
+impl FinishKind {
+    pub fn from_gemini(finish_reason: &str) -> Self {
+        match finish_reason {
+            "MAX_TOKENS" => FinishKind::MaxTokens,
+            "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "LANGUAGE"
+            | "IMAGE_SAFETY" | "IMAGE_PROHIBITED_CONTENT" | "IMAGE_RECITATION" => FinishKind::ContentFiltered,
+            "MALFORMED_FUNCTION_CALL" | "UNEXPECTED_TOOL_CALL" | "TOO_MANY_TOOL_CALLS" => {
+                FinishKind::MalformedToolCall
+            }
+            _ => FinishKind::Stop,
+        }
+    }
+}
+
+/// 映射为 Claude stop_reason
+pub fn to_claude_stop_reason(finish_reason: Option<&str>, has_tool_call: bool) -> &'static str {
+    let kind = finish_reason.map(FinishKind::from_gemini).unwrap_or(FinishKind::Stop);
+    match kind {
+        FinishKind::MaxTokens => "max_tokens",
+        FinishKind::ContentFiltered => "refusal",
+        _ if has_tool_call => "tool_use",
+        // 工具调用生成失败: pause_turn 让客户端带着当前上下文继续请求，由模型重新生成调用
+        FinishKind::MalformedToolCall => "pause_turn",
+        FinishKind::Stop => "end_turn",
+    }
+}
+
+/// 映射为 OpenAI finish_reason
+pub fn to_openai_finish_reason(finish_reason: &str, has_tool_call: bool) -> &'static str {
+    match FinishKind::from_gemini(finish_reason) {
+        FinishKind::MaxTokens => "length",
+        FinishKind::ContentFiltered => "content_filter",
+        _ if has_tool_call => "tool_calls",
+        FinishKind::MalformedToolCall | FinishKind::Stop => "stop",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claude_stop_reason_mapping() {
+        assert_eq!(to_claude_stop_reason(Some("STOP"), false), "end_turn");
+        assert_eq!(to_claude_stop_reason(None, false), "end_turn");
+        assert_eq!(to_claude_stop_reason(Some("STOP"), true), "tool_use");
+        assert_eq!(to_claude_stop_reason(Some("MAX_TOKENS"), true), "max_tokens");
+        assert_eq!(to_claude_stop_reason(Some("SAFETY"), false), "refusal");
+        assert_eq!(to_claude_stop_reason(Some("RECITATION"), true), "refusal");
+        assert_eq!(to_claude_stop_reason(Some("MALFORMED_FUNCTION_CALL"), false), "pause_turn");
+        assert_eq!(to_claude_stop_reason(Some("SOMETHING_NEW"), false), "end_turn");
+    }
+
+    #[test]
+    fn test_openai_finish_reason_mapping() {
+        assert_eq!(to_openai_finish_reason("STOP", false), "stop");
+        assert_eq!(to_openai_finish_reason("STOP", true), "tool_calls");
+        assert_eq!(to_openai_finish_reason("MAX_TOKENS", false), "length");
+        assert_eq!(to_openai_finish_reason("PROHIBITED_CONTENT", true), "content_filter");
+        assert_eq!(to_openai_finish_reason("MALFORMED_FUNCTION_CALL", true), "tool_calls");
+        assert_eq!(to_openai_finish_reason("OTHER", false), "stop");
+    }
+}
@@ -4,8 +4,10 @@
 use bytes::Bytes;
 use futures::StreamExt;
 use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use tracing::debug;
 
+use crate::proxy::common::sse_decoder::SseLineDecoder;
 use crate::proxy::SignatureCache; // Assuming this is available at crate root or re-exported
 
 /// Collects a Gemini SSE stream into a complete Gemini Response Value
@@ -18,28 +20,29 @@ where
     S: futures::Stream<Item = Result<Bytes, E>> + Unpin,
     E: std::fmt::Display,
 {
-    let mut collected_response = json!({
-        "candidates": [
-            {
-                "content": {
-                    "parts": [],
-                    "role": "model"
-                },
-                "finishReason": "STOP",
-                "index": 0
-            }
-        ]
-    });
+    let mut collected_response = json!({});
 
-    let mut content_parts: Vec<Value> = Vec::new(); // To accumulate parts
+    // [NEW] 按候选 index 分组累积 (支持 candidateCount > 1)
+    // index -> (parts, finishReason)
+    let mut candidates_map: BTreeMap<u64, (Vec<Value>, Option<String>)> = BTreeMap::new();
     let mut usage_metadata: Option<Value> = None;
-    let mut finish_reason: Option<String> = None;
 
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        let text = std::str::from_utf8(&chunk).unwrap_or(""); // Ignore invalid utf8 for simplicity or handle better
+    let mut decoder = SseLineDecoder::new();
+    let mut ended = false;
+    while !ended {
+        // 按字节缓冲成行再解码，避免跨分片的行 / 多字节字符被截断；结束时冲刷最后一行
+        let lines = match stream.next().await {
+            Some(chunk_result) => {
+                let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+                decoder.push(&chunk)
+            }
+            None => {
+                ended = true;
+                decoder.finish().into_iter().collect()
+            }
+        };
 
-        for line in text.lines() {
+        for line in &lines {
             let line = line.trim();
             if line.starts_with("data: ") {
                 let json_part = line.trim_start_matches("data: ").trim();
@@ -62,10 +65,14 @@ where
 
                      // 2. Capture Content & Signature
                      if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                         if let Some(candidate) = candidates.first() {
+                         for (pos, candidate) in candidates.iter().enumerate() {
+                             // 流式 chunk 中每个候选带有 index 字段，缺失时按位置推断
+                             let cand_index = candidate.get("index").and_then(|v| v.as_u64()).unwrap_or(pos as u64);
+                             let (content_parts, finish_reason) = candidates_map.entry(cand_index).or_default();
+
                              // Update finish reason if present
                              if let Some(fr) = candidate.get("finishReason").and_then(|v| v.as_str()) {
-                                 finish_reason = Some(fr.to_string());
+                                 *finish_reason = Some(fr.to_string());
                              }
 
                              if let Some(parts) = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
@@ -109,10 +116,23 @@ where
     }
 
     // Construct final response
-    collected_response["candidates"][0]["content"]["parts"] = json!(content_parts);
-    if let Some(fr) = finish_reason {
-        collected_response["candidates"][0]["finishReason"] = json!(fr);
+    if candidates_map.is_empty() {
+        candidates_map.insert(0, (Vec::new(), None));
     }
+    let candidates: Vec<Value> = candidates_map
+        .into_iter()
+        .map(|(index, (parts, finish_reason))| {
+            json!({
+                "content": {
+                    "parts": parts,
+                    "role": "model"
+                },
+                "finishReason": finish_reason.unwrap_or_else(|| "STOP".to_string()),
+                "index": index
+            })
+        })
+        .collect();
+    collected_response["candidates"] = json!(candidates);
     if let Some(usage) = usage_metadata {
         collected_response["usageMetadata"] = usage;
     }
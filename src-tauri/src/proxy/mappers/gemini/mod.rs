@@ -4,6 +4,7 @@
 pub mod models;
 pub mod wrapper;
 pub mod collector; // [NEW]
+pub mod streaming; // 原生 SSE 转发
 
 // No public exports needed here if unused
 pub use wrapper::*;
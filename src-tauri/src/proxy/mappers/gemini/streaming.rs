@@ -0,0 +1,187 @@
+// Gemini 原生 SSE 转发
+// 逐行解包 v1internal 的 `response` 包装后原样转发。尾部块 (usageMetadata / groundingMetadata / finishReason)
+// 必须完整保留；上游最后一块可能没有换行结尾，流结束时需要冲刷缓冲区，否则用量与引用信息会丢失。
+use bytes::Bytes;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::proxy::common::sse_decoder::{sse_data, SseLineDecoder};
+
+pub struct GeminiSseRelay {
+    decoder: SseLineDecoder,
+    session_id: String,
+    model: String,
+}
+
+impl GeminiSseRelay {
+    pub fn new(session_id: &str, model: &str) -> Self {
+        Self {
+            decoder: SseLineDecoder::new(),
+            session_id: session_id.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    /// 输入上游字节，返回已完整的 SSE 事件
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Bytes> {
+        let lines = self.decoder.push(bytes);
+        lines.iter().filter_map(|line| self.relay_line(line)).collect()
+    }
+
+    /// 流结束时冲刷未以换行结尾的最后一行
+    pub fn finish(&mut self) -> Vec<Bytes> {
+        let rest = self.decoder.finish();
+        rest.iter().filter_map(|line| self.relay_line(line)).collect()
+    }
+
+    fn relay_line(&self, line: &str) -> Option<Bytes> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        let Some(json_part) = sse_data(line).map(str::trim) else {
+            // Non-data lines (comments, etc.)
+            return Some(Bytes::from(format!("{}\n\n", line)));
+        };
+        if json_part == "[DONE]" {
+            return Some(Bytes::from("data: [DONE]\n\n"));
+        }
+
+        let json = match serde_json::from_str::<Value>(json_part) {
+            Ok(json) => json,
+            Err(e) => {
+                debug!("[Gemini-SSE] JSON parse error: {}, passing raw line", e);
+                return Some(Bytes::from(format!("{}\n\n", line)));
+            }
+        };
+
+        self.cache_signatures(&json);
+        crate::proxy::mappers::gemini::wrapper::cache_tool_signatures(&json);
+
+        // Unwrap v1internal response wrapper (其余字段如 usageMetadata / groundingMetadata 原样保留)
+        let mut inner = match json {
+            Value::Object(mut obj) if obj.contains_key("response") => {
+                obj.remove("response").unwrap_or(Value::Null)
+            }
+            other => other,
+        };
+        // [FIX #1522] Inject Tool ID into Stream Response (需在解包后注入，否则找不到 candidates)
+        crate::proxy::mappers::gemini::wrapper::inject_ids_to_response(&mut inner, &self.model);
+
+        Some(Bytes::from(format!(
+            "data: {}\n\n",
+            serde_json::to_string(&inner).unwrap_or_default()
+        )))
+    }
+
+    /// [FIX #765] Extract thoughtSignature from stream
+    fn cache_signatures(&self, json: &Value) {
+        let resp = json.get("response").unwrap_or(json);
+        let Some(candidates) = resp.get("candidates").and_then(|c| c.as_array()) else {
+            return;
+        };
+        for part in candidates
+            .iter()
+            .filter_map(|c| c.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()))
+            .flatten()
+        {
+            if let Some(sig) = part.get("thoughtSignature").and_then(|s| s.as_str()) {
+                crate::proxy::SignatureCache::global()
+                    .cache_session_signature(&self.session_id, sig.to_string(), 1);
+                debug!(
+                    "[Gemini-SSE] Cached signature (len: {}) for session: {}",
+                    sig.len(),
+                    self.session_id
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse_events(events: &[Bytes]) -> Vec<Value> {
+        events
+            .iter()
+            .map(|e| {
+                let s = std::str::from_utf8(e).unwrap();
+                serde_json::from_str(s.trim().strip_prefix("data: ").unwrap()).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fixture_stream_round_trips_losslessly() {
+        let inner_chunks = vec![
+            json!({
+                "candidates": [{"content": {"role": "model", "parts": [{"text": "Paris is"}]}}],
+                "modelVersion": "gemini-3-flash",
+                "responseId": "resp-1"
+            }),
+            json!({
+                "candidates": [{"content": {"role": "model", "parts": [{"text": " the capital."}]}}],
+                "modelVersion": "gemini-3-flash",
+                "responseId": "resp-1"
+            }),
+            json!({
+                "candidates": [{
+                    "content": {"role": "model", "parts": [{"text": ""}]},
+                    "finishReason": "STOP",
+                    "groundingMetadata": {
+                        "webSearchQueries": ["capital of France"],
+                        "groundingChunks": [{"web": {"uri": "https://example.com", "title": "example.com"}}],
+                        "groundingSupports": [{
+                            "segment": {"startIndex": 0, "endIndex": 21, "text": "Paris is the capital."},
+                            "groundingChunkIndices": [0]
+                        }]
+                    }
+                }],
+                "usageMetadata": {
+                    "promptTokenCount": 12,
+                    "candidatesTokenCount": 6,
+                    "thoughtsTokenCount": 40,
+                    "totalTokenCount": 58
+                },
+                "modelVersion": "gemini-3-flash",
+                "responseId": "resp-1"
+            }),
+        ];
+
+        let mut raw = String::new();
+        for chunk in &inner_chunks {
+            raw.push_str(&format!(
+                "data: {}\r\n\r\n",
+                json!({"response": chunk, "traceId": "trace-1"})
+            ));
+        }
+        // 最后一块不以换行结尾
+        let raw = raw.trim_end().to_string();
+
+        // 以任意边界切分输入，模拟网络分片
+        let mut relay = GeminiSseRelay::new("sid-test", "gemini-3-flash");
+        let mut events = Vec::new();
+        for piece in raw.as_bytes().chunks(37) {
+            events.extend(relay.push(piece));
+        }
+        events.extend(relay.finish());
+
+        assert_eq!(parse_events(&events), inner_chunks);
+    }
+
+    #[test]
+    fn test_unwrapped_chunks_and_done_pass_through() {
+        let chunk = json!({"candidates": [{"finishReason": "STOP"}], "usageMetadata": {"totalTokenCount": 3}});
+        let mut relay = GeminiSseRelay::new("sid-test", "gemini-3-flash");
+        let mut events = relay.push(format!("data: {}\n\n: keep-alive\n", chunk).as_bytes());
+        events.extend(relay.push(b"data: [DONE]\n"));
+        events.extend(relay.finish());
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(parse_events(&events[..1]), vec![chunk]);
+        assert_eq!(&events[1][..], b": keep-alive\n\n");
+        assert_eq!(&events[2][..], b"data: [DONE]\n\n");
+    }
+}
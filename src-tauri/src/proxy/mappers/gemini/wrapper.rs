@@ -1,5 +1,117 @@
 // Gemini v1internal 包装/解包
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
+
+/// 顶层字段的 snake_case 别名 (REST 接口同时接受两种写法，Google GenAI SDK 部分版本会发送 snake_case)
+const REQUEST_FIELD_ALIASES: &[(&str, &str)] = &[
+    ("system_instruction", "systemInstruction"),
+    ("generation_config", "generationConfig"),
+    ("tool_config", "toolConfig"),
+    ("safety_settings", "safetySettings"),
+    ("cached_content", "cachedContent"),
+];
+
+/// tools 数组元素中的 snake_case 别名
+const TOOL_FIELD_ALIASES: &[(&str, &str)] = &[
+    ("function_declarations", "functionDeclarations"),
+    ("google_search", "googleSearch"),
+    ("google_search_retrieval", "googleSearchRetrieval"),
+    ("code_execution", "codeExecution"),
+    ("url_context", "urlContext"),
+];
+
+fn snake_to_camel(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// 将 snake_case 键重命名为 camelCase (两种写法同时存在时保留 camelCase)
+fn rename_key(obj: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(value) = obj.remove(from) {
+        if !obj.contains_key(to) {
+            obj.insert(to.to_string(), value);
+        }
+    }
+}
+
+/// 只转换对象自身的键，不递归 (responseSchema 等内部的属性名由用户定义，不能改写)
+fn camelize_keys(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        let snake_keys: Vec<String> = obj.keys().filter(|k| k.contains('_')).cloned().collect();
+        for key in snake_keys {
+            rename_key(obj, &key, &snake_to_camel(&key));
+        }
+    }
+}
+
+/// 规范化 Gemini 原生请求的字段写法，保证后续处理 (身份注入、thinking、工具清洗) 能识别
+/// 客户端传入的 systemInstruction / generationConfig / tools / toolConfig / safetySettings
+fn normalize_native_request(request: &mut Value) {
+    let Some(obj) = request.as_object_mut() else {
+        return;
+    };
+    for (from, to) in REQUEST_FIELD_ALIASES {
+        rename_key(obj, from, to);
+    }
+
+    // systemInstruction 允许直接传字符串
+    if let Some(text) = obj.get("systemInstruction").and_then(|v| v.as_str()).map(|s| s.to_string()) {
+        obj.insert(
+            "systemInstruction".to_string(),
+            json!({"role": "user", "parts": [{"text": text}]}),
+        );
+    }
+
+    if let Some(gen_config) = obj.get_mut("generationConfig") {
+        camelize_keys(gen_config);
+        for nested in ["thinkingConfig", "imageConfig", "speechConfig"] {
+            if let Some(inner) = gen_config.get_mut(nested) {
+                camelize_keys(inner);
+            }
+        }
+    }
+
+    if let Some(tool_config) = obj.get_mut("toolConfig") {
+        camelize_keys(tool_config);
+        if let Some(fcc) = tool_config.get_mut("functionCallingConfig") {
+            camelize_keys(fcc);
+        }
+    }
+
+    if let Some(settings) = obj.get_mut("safetySettings").and_then(|v| v.as_array_mut()) {
+        for setting in settings {
+            camelize_keys(setting);
+        }
+    }
+
+    if let Some(tools) = obj.get_mut("tools").and_then(|v| v.as_array_mut()) {
+        for tool in tools {
+            if let Some(tool_obj) = tool.as_object_mut() {
+                for (from, to) in TOOL_FIELD_ALIASES {
+                    rename_key(tool_obj, from, to);
+                }
+                if let Some(decls) = tool_obj.get_mut("functionDeclarations").and_then(|v| v.as_array_mut()) {
+                    for decl in decls {
+                        if let Some(decl_obj) = decl.as_object_mut() {
+                            rename_key(decl_obj, "parameters_json_schema", "parametersJsonSchema");
+                            rename_key(decl_obj, "response_json_schema", "responseJsonSchema");
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
 
 /// 包装请求体为 v1internal 格式
 pub fn wrap_request(
@@ -35,6 +147,12 @@ pub fn wrap_request(
     // 深度清理 [undefined] 字符串 (Cherry Studio 等客户端常见注入)
     crate::proxy::mappers::common_utils::deep_clean_undefined(&mut inner_request, 0);
 
+    // 统一字段写法，避免下方逻辑因 snake_case 字段而重复创建 systemInstruction / generationConfig
+    normalize_native_request(&mut inner_request);
+
+    // [NEW] 保留客户端传入的 safetySettings，缺失类别按配置补齐
+    crate::proxy::safety_settings::apply_to_native_request(&mut inner_request, final_model_name);
+
     // [FIX #1522] Inject dummy IDs for Claude models in Gemini protocol
     // Google v1internal requires 'id' for tool calls when the model is Claude,
     // even though the standard Gemini protocol doesn't have it.
@@ -89,7 +207,16 @@ pub fn wrap_request(
                         // 3. 处理 thoughtSignature
                         if obj.contains_key("functionCall") && obj.get("thoughtSignature").is_none()
                         {
-                            if let Some(s_id) = session_id {
+                            // [NEW] 优先按 functionCall.id 回填该调用原始的签名
+                            let tool_sig = obj
+                                .get("functionCall")
+                                .and_then(|fc| fc.get("id"))
+                                .and_then(|id| id.as_str())
+                                .and_then(|id| crate::proxy::SignatureCache::global().get_tool_signature(id));
+                            if let Some(sig) = tool_sig {
+                                obj.insert("thoughtSignature".to_string(), json!(sig));
+                                tracing::debug!("[Gemini-Wrap] Injected tool signature (len: {})", sig.len());
+                            } else if let Some(s_id) = session_id {
                                 if let Some(sig) = crate::proxy::SignatureCache::global()
                                     .get_session_signature(s_id)
                                 {
@@ -343,9 +470,10 @@ pub fn wrap_request(
     );
 
     // Clean tool declarations (remove forbidden Schema fields like multipleOf, and remove redundant search decls)
+    let mut tools_emptied = false;
     if let Some(tools) = inner_request.get_mut("tools") {
         if let Some(tools_arr) = tools.as_array_mut() {
-            for tool in tools_arr {
+            for tool in tools_arr.iter_mut() {
                 if let Some(decls) = tool.get_mut("functionDeclarations") {
                     if let Some(decls_arr) = decls.as_array_mut() {
                         // 1. 过滤掉联网关键字函数
@@ -382,6 +510,24 @@ pub fn wrap_request(
                     }
                 }
             }
+
+            // 过滤联网函数后可能留下空的 functionDeclarations，上游会拒绝空声明
+            tools_arr.retain(|tool| {
+                let Some(obj) = tool.as_object() else {
+                    return true;
+                };
+                let empty_decls = obj
+                    .get("functionDeclarations")
+                    .and_then(|d| d.as_array())
+                    .map_or(false, |d| d.is_empty());
+                !(empty_decls && obj.len() == 1)
+            });
+            tools_emptied = tools_arr.is_empty();
+        }
+    }
+    if tools_emptied {
+        if let Some(obj) = inner_request.as_object_mut() {
+            obj.remove("tools");
         }
     }
 
@@ -508,6 +654,17 @@ pub fn wrap_request(
         });
     }
 
+    // [NEW] 模型绑定了 Gemini 上下文缓存时改为引用 cachedContent (客户端自带 cachedContent 时不覆盖)
+    if inner_request.get("cachedContent").is_none() {
+        crate::proxy::context_cache::apply_to_request(
+            &mut inner_request,
+            original_model,
+            final_model_name,
+            account_id,
+            None,
+        );
+    }
+
     // [ADDED v4.1.24] 注入基于账号的稳定 sessionId
     if let Some(account_id_str) = account_id {
         inner_request["sessionId"] = json!(crate::proxy::common::session::derive_session_id(account_id_str));
@@ -569,6 +726,28 @@ pub fn unwrap_response(response: &Value) -> Value {
     response.get("response").unwrap_or(response).clone()
 }
 
+/// [NEW] 缓存上游带 id 的 functionCall 的签名，供客户端丢弃签名时按 id 回填
+pub fn cache_tool_signatures(response: &Value) {
+    let inner = response.get("response").unwrap_or(response);
+    let Some(candidates) = inner.get("candidates").and_then(|c| c.as_array()) else {
+        return;
+    };
+    for part in candidates
+        .iter()
+        .filter_map(|c| c.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()))
+        .flatten()
+    {
+        let id = part
+            .get("functionCall")
+            .and_then(|fc| fc.get("id"))
+            .and_then(|id| id.as_str());
+        let sig = part.get("thoughtSignature").and_then(|s| s.as_str());
+        if let (Some(id), Some(sig)) = (id, sig) {
+            crate::proxy::SignatureCache::global().cache_tool_signature(id, sig.to_string());
+        }
+    }
+}
+
 /// [NEW v3.3.18] 为 Claude 模型的 Gemini 响应自动注入 Tool ID
 ///
 /// 目点是为了让客户端（如 OpenCode/Vercel AI SDK）能感知到 ID，
@@ -823,6 +1002,8 @@ mod tests {
             mode: ThinkingBudgetMode::Custom,
             custom_value: 1024, // Distinct value
             effort: None,
+            hide_openai_reasoning: false,
+            claude_thinking_output: crate::proxy::config::ClaudeThinkingOutput::Blocks,
         });
 
         let body = json!({
@@ -871,6 +1052,8 @@ mod tests {
                     mode: crate::proxy::config::ThinkingBudgetMode::Auto,
                     custom_value: 0,
                     effort: None,
+                    hide_openai_reasoning: false,
+                    claude_thinking_output: crate::proxy::config::ClaudeThinkingOutput::Blocks,
                 },
             );
 
@@ -920,6 +1103,8 @@ mod tests {
                 mode: crate::proxy::config::ThinkingBudgetMode::Auto,
                 custom_value: 24576,
                 effort: None,
+                hide_openai_reasoning: false,
+                claude_thinking_output: crate::proxy::config::ClaudeThinkingOutput::Blocks,
             },
         );
 
@@ -1022,4 +1207,65 @@ mod tests {
         assert!(has_functions, "Should contain functionDeclarations");
         assert!(has_google_search, "Should contain googleSearch (Gemini 2.0+ supports mixed tools)");
     }
+
+    #[test]
+    fn test_native_request_fields_preserved() {
+        let body = json!({
+            "contents": [{"role": "user", "parts": [{"text": "Weather?"}]}],
+            "system_instruction": {"parts": [{"text": "Be brief"}]},
+            "generation_config": {
+                "temperature": 0.2,
+                "response_mime_type": "application/json",
+                "response_schema": {"type": "OBJECT", "properties": {"city_name": {"type": "STRING"}}}
+            },
+            "tools": [{"function_declarations": [{
+                "name": "get_weather",
+                "parameters": {"type": "OBJECT", "properties": {"city": {"type": "STRING"}}}
+            }]}],
+            "tool_config": {"function_calling_config": {"mode": "ANY", "allowed_function_names": ["get_weather"]}},
+            "safety_settings": [{"category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_NONE"}]
+        });
+
+        let result = wrap_request(&body, "proj", "gemini-2.5-flash", None, None, None);
+        let req = &result["request"];
+
+        for key in ["system_instruction", "generation_config", "tool_config", "safety_settings"] {
+            assert!(req.get(key).is_none(), "snake_case field {} should be normalized", key);
+        }
+        // 用户的 systemInstruction 保留在身份注入之后
+        let parts = req["systemInstruction"]["parts"].as_array().unwrap();
+        assert!(parts.iter().any(|p| p["text"] == "Be brief"));
+
+        let gen = &req["generationConfig"];
+        assert_eq!(gen["temperature"], 0.2);
+        assert_eq!(gen["responseMimeType"], "application/json");
+        // responseSchema 内部的属性名不能被改写
+        assert!(gen["responseSchema"]["properties"].get("city_name").is_some());
+
+        assert_eq!(req["tools"][0]["functionDeclarations"][0]["name"], "get_weather");
+        assert_eq!(req["toolConfig"]["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(
+            req["toolConfig"]["functionCallingConfig"]["allowedFunctionNames"][0],
+            "get_weather"
+        );
+        assert_eq!(req["safetySettings"][0]["threshold"], "BLOCK_NONE");
+    }
+
+    #[test]
+    fn test_native_request_drops_emptied_search_declarations() {
+        let body = json!({
+            "contents": [{"role": "user", "parts": [{"text": "News?"}]}],
+            "systemInstruction": "Answer in English",
+            "tools": [{"functionDeclarations": [{"name": "google_search"}]}]
+        });
+
+        let result = wrap_request(&body, "proj", "gemini-2.5-flash", None, None, None);
+        let req = &result["request"];
+        let tools = req["tools"].as_array().map(|t| t.clone()).unwrap_or_default();
+        assert!(tools
+            .iter()
+            .all(|t| t.get("functionDeclarations").map_or(true, |d| !d.as_array().unwrap().is_empty())));
+        let parts = req["systemInstruction"]["parts"].as_array().unwrap();
+        assert!(parts.iter().any(|p| p["text"] == "Answer in English"));
+    }
 }
@@ -6,6 +6,7 @@ pub mod common_utils;
 pub mod context_manager;
 pub mod error_classifier;
 pub mod estimation_calibrator;
+pub mod finish_reason;
 pub mod gemini;
 pub mod model_limits;
 pub mod openai;
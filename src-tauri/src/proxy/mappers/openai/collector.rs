@@ -2,10 +2,11 @@
 // Used for auto-converting streaming responses to JSON for non-streaming requests
 
 use super::models::*;
+use crate::proxy::common::sse_decoder::SseLineDecoder;
 use bytes::Bytes;
 use futures::StreamExt;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Collects an OpenAI SSE stream into a complete OpenAIResponse
 pub async fn collect_stream_to_json<S, E>(
@@ -24,18 +25,25 @@ where
         usage: None,
     };
 
-    let mut role: Option<String> = None;
-    let mut content_parts: Vec<String> = Vec::new();
-    let mut reasoning_parts: Vec<String> = Vec::new();
-    let mut finish_reason: Option<String> = None;
-    // Tool calls aggregation: index -> (id, type, name, arguments_parts)
-    let mut tool_calls_map: HashMap<u32, (String, String, String, Vec<String>)> = HashMap::new();
+    // [NEW] 按 choice index 分别聚合 (支持 n > 1)
+    let mut choice_map: BTreeMap<u32, ChoiceAccumulator> = BTreeMap::new();
 
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        let text = String::from_utf8_lossy(&chunk);
+    let mut decoder = SseLineDecoder::new();
+    let mut ended = false;
+    while !ended {
+        // 按字节缓冲成行再解码，避免跨分片的行 / 多字节字符被截断；结束时冲刷最后一行
+        let lines = match stream.next().await {
+            Some(chunk_result) => {
+                let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+                decoder.push(&chunk)
+            }
+            None => {
+                ended = true;
+                decoder.finish().into_iter().collect()
+            }
+        };
 
-        for line in text.lines() {
+        for line in &lines {
             let line = line.trim();
             if line.starts_with("data: ") {
                 let data_str = line.trim_start_matches("data: ").trim();
@@ -64,21 +72,29 @@ where
 
                     // Collect Choices Delta
                     if let Some(choices) = json.get("choices").and_then(|v| v.as_array()) {
-                        if let Some(choice) = choices.first() {
+                        for choice in choices {
+                            let choice_index = choice.get("index").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+                            let acc = choice_map.entry(choice_index).or_default();
+
                             if let Some(delta) = choice.get("delta") {
                                 // Role
                                 if let Some(r) = delta.get("role").and_then(|v| v.as_str()) {
-                                    role = Some(r.to_string());
+                                    acc.role = Some(r.to_string());
                                 }
                                 
                                 // Content
                                 if let Some(c) = delta.get("content").and_then(|v| v.as_str()) {
-                                    content_parts.push(c.to_string());
+                                    acc.content_parts.push(c.to_string());
                                 }
 
                                 // Reasoning Content
                                 if let Some(rc) = delta.get("reasoning_content").and_then(|v| v.as_str()) {
-                                    reasoning_parts.push(rc.to_string());
+                                    acc.reasoning_parts.push(rc.to_string());
+                                }
+
+                                // [NEW] Audio output chunks
+                                if let Some(data) = delta.get("audio").and_then(|a| a.get("data")).and_then(|v| v.as_str()) {
+                                    super::response::append_audio_chunk(&mut acc.audio, data);
                                 }
 
                                 // Tool Calls aggregation by index
@@ -93,11 +109,11 @@ where
                                         // If this index already has a DIFFERENT id, it's a new tool call
                                         // Assign it a unique index to avoid merging
                                         let index = if !new_id.is_empty() {
-                                            if let Some(existing) = tool_calls_map.get(&raw_index) {
+                                            if let Some(existing) = acc.tool_calls_map.get(&raw_index) {
                                                 if !existing.0.is_empty() && existing.0 != new_id {
                                                     // Find next available index
                                                     let mut next_idx = raw_index + 1;
-                                                    while tool_calls_map.contains_key(&next_idx) {
+                                                    while acc.tool_calls_map.contains_key(&next_idx) {
                                                         next_idx += 1;
                                                     }
                                                     next_idx
@@ -111,7 +127,7 @@ where
                                             raw_index
                                         };
                                         
-                                        let entry = tool_calls_map.entry(index).or_insert_with(|| {
+                                        let entry = acc.tool_calls_map.entry(index).or_insert_with(|| {
                                             (String::new(), String::from("function"), String::new(), Vec::new())
                                         });
                                         
@@ -142,7 +158,7 @@ where
                             }
 
                             if let Some(fr) = choice.get("finish_reason").and_then(|v| v.as_str()) {
-                                finish_reason = Some(fr.to_string());
+                                acc.finish_reason = Some(fr.to_string());
                             }
                         }
                     }
@@ -151,49 +167,115 @@ where
         }
     }
 
-    // Construct final message
-    let full_content = content_parts.join("");
-    let full_reasoning = if reasoning_parts.is_empty() {
-        None
-    } else {
-        Some(reasoning_parts.join(""))
-    };
+    if choice_map.is_empty() {
+        choice_map.insert(0, ChoiceAccumulator::default());
+    }
+    response.choices = choice_map
+        .into_iter()
+        .map(|(index, acc)| acc.into_choice(index))
+        .collect();
+
+    Ok(response)
+}
+
+/// 单个 choice 的流式聚合状态
+#[derive(Default)]
+struct ChoiceAccumulator {
+    role: Option<String>,
+    content_parts: Vec<String>,
+    reasoning_parts: Vec<String>,
+    audio: Vec<u8>,
+    finish_reason: Option<String>,
+    // Tool calls aggregation: index -> (id, type, name, arguments_parts)
+    tool_calls_map: HashMap<u32, (String, String, String, Vec<String>)>,
+}
 
-    // Build aggregated tool_calls
-    let final_tool_calls: Option<Vec<ToolCall>> = if tool_calls_map.is_empty() {
-        None
-    } else {
-        let mut calls: Vec<(u32, ToolCall)> = tool_calls_map
-            .into_iter()
-            .map(|(index, (id, tc_type, name, args_parts))| {
-                (index, ToolCall {
-                    id,
-                    r#type: tc_type,
-                    function: ToolFunction {
-                        name,
-                        arguments: args_parts.join(""),
-                    },
+impl ChoiceAccumulator {
+    fn into_choice(self, index: u32) -> Choice {
+        // Construct final message
+        let full_content = self.content_parts.join("");
+        let full_reasoning = if self.reasoning_parts.is_empty() {
+            None
+        } else {
+            Some(self.reasoning_parts.join(""))
+        };
+
+        // Build aggregated tool_calls
+        let final_tool_calls: Option<Vec<ToolCall>> = if self.tool_calls_map.is_empty() {
+            None
+        } else {
+            let mut calls: Vec<(u32, ToolCall)> = self
+                .tool_calls_map
+                .into_iter()
+                .map(|(index, (id, tc_type, name, args_parts))| {
+                    (index, ToolCall {
+                        id,
+                        r#type: tc_type,
+                        function: ToolFunction {
+                            name,
+                            arguments: args_parts.join(""),
+                        },
+                    })
                 })
-            })
-            .collect();
-        calls.sort_by_key(|(index, _)| *index);
-        Some(calls.into_iter().map(|(_, tc)| tc).collect())
-    };
+                .collect();
+            calls.sort_by_key(|(index, _)| *index);
+            Some(calls.into_iter().map(|(_, tc)| tc).collect())
+        };
 
-    let message = OpenAIMessage {
-        role: role.unwrap_or("assistant".to_string()),
-        content: Some(OpenAIContent::String(full_content)),
-        reasoning_content: full_reasoning,
-        tool_calls: final_tool_calls,
-        tool_call_id: None,
-        name: None,
-    };
+        // [NEW] 还原流式过程中以 Markdown 形式输出的内联图片为 image_url 内容块
+        let (full_content, images) = super::response::extract_markdown_data_images(&full_content);
+        let audio = super::response::build_audio_output(self.audio, &full_content);
+        let content = if images.is_empty() {
+            Some(OpenAIContent::String(full_content))
+        } else {
+            super::response::build_message_content(full_content, images)
+        };
 
-    response.choices.push(Choice {
-        index: 0,
-        message,
-        finish_reason: finish_reason.or(Some("stop".to_string())),
-    });
+        let message = OpenAIMessage {
+            role: self.role.unwrap_or("assistant".to_string()),
+            content,
+            reasoning_content: full_reasoning,
+            tool_calls: final_tool_calls,
+            tool_call_id: None,
+            name: None,
+            audio,
+        };
 
-    Ok(response)
+        Choice {
+            index,
+            message,
+            finish_reason: self.finish_reason.or(Some("stop".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[tokio::test]
+    async fn test_collect_multiple_choices() {
+        let sse_data = vec![
+            "data: {\"id\":\"chatcmpl-1\",\"model\":\"gemini-2.5-flash\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hello\"},\"finish_reason\":null},{\"index\":1,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"id\":\"chatcmpl-1\",\"choices\":[{\"index\":1,\"delta\":{\"content\":\" there\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: {\"id\":\"chatcmpl-1\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" world\"},\"finish_reason\":\"length\"}]}\n\n",
+            "data: [DONE]\n\n",
+        ];
+
+        let byte_stream = stream::iter(
+            sse_data.into_iter().map(|s| Ok::<Bytes, std::io::Error>(Bytes::from(s)))
+        );
+
+        let response = collect_stream_to_json(byte_stream).await.unwrap();
+        assert_eq!(response.choices.len(), 2);
+
+        assert_eq!(response.choices[0].index, 0);
+        assert_eq!(response.choices[0].message.content, Some(OpenAIContent::String("Hello world".to_string())));
+        assert_eq!(response.choices[0].finish_reason, Some("length".to_string()));
+
+        assert_eq!(response.choices[1].index, 1);
+        assert_eq!(response.choices[1].message.content, Some(OpenAIContent::String("Hi there".to_string())));
+        assert_eq!(response.choices[1].finish_reason, Some("stop".to_string()));
+    }
 }
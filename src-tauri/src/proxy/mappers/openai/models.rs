@@ -16,9 +16,18 @@ pub struct OpenAIRequest {
     pub n: Option<u32>, // [NEW] 支持多候选结果数量
     #[serde(rename = "max_tokens")]
     pub max_tokens: Option<u32>,
+    // [NEW] 新版 OpenAI SDK 使用 max_completion_tokens 取代 max_tokens
+    #[serde(default)]
+    pub max_completion_tokens: Option<u32>,
     pub temperature: Option<f64>,
     #[serde(rename = "top_p")]
     pub top_p: Option<f64>,
+    #[serde(default)]
+    pub frequency_penalty: Option<f64>,
+    #[serde(default)]
+    pub presence_penalty: Option<f64>,
+    #[serde(default)]
+    pub seed: Option<i64>,
     pub stop: Option<Value>,
     pub response_format: Option<ResponseFormat>,
     #[serde(default)]
@@ -40,11 +49,34 @@ pub struct OpenAIRequest {
     // [NEW] Thinking/Extended Thinking 支持 (兼容 Anthropic/Claude 协议)
     #[serde(default)]
     pub thinking: Option<ThinkingConfig>,
+    // [NEW] OpenAI 推理强度: minimal / low / medium / high (映射为 thinkingBudget)
+    #[serde(default)]
+    pub reasoning_effort: Option<String>,
+    // [NEW] stream_options.include_usage: 流式结束前单独下发用量 chunk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<OpenAIStreamOptions>,
+    // [NEW] Responses API 形式: { "effort": "high" }
+    #[serde(default)]
+    pub reasoning: Option<OpenAIReasoning>,
     // [NEW] Direct imageSize support (for Gemini native parameter)
     #[serde(default, rename = "imageSize")]
     pub image_size: Option<String>,
 }
 
+/// 流式选项 (stream_options)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenAIStreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// Responses API 的 reasoning 参数
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct OpenAIReasoning {
+    #[serde(default)]
+    pub effort: Option<String>,
+}
+
 /// Thinking 配置 (兼容 Anthropic 和 OpenAI 扩展协议)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ThinkingConfig {
@@ -78,6 +110,38 @@ pub enum OpenAIContentBlock {
     ImageUrl { image_url: OpenAIImageUrl },
     #[serde(rename = "audio_url")]
     AudioUrl { audio_url: AudioUrlContent },
+    /// [NEW] 音频输入 (base64 + 格式)
+    #[serde(rename = "input_audio")]
+    InputAudio { input_audio: InputAudioContent },
+    /// [NEW] Chat Completions 文件输入 (PDF 等)
+    #[serde(rename = "file")]
+    File { file: OpenAIFileContent },
+    /// [NEW] Responses API 文件输入 (字段与 file 相同，但位于块顶层)
+    #[serde(rename = "input_file")]
+    InputFile {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_data: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_url: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct OpenAIFileContent {
+    /// data URI (data:application/pdf;base64,...) 或纯 base64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// 反代不托管文件，file_id 引用无法解析
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -92,6 +156,27 @@ pub struct AudioUrlContent {
     pub url: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InputAudioContent {
+    /// base64 编码的音频数据
+    pub data: String,
+    /// wav / mp3 / flac 等
+    pub format: String,
+}
+
+/// [NEW] 助手消息中的音频输出 (音频模型返回 audio/* inlineData 时)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct OpenAIAudioOutput {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub data: String,
+    #[serde(default)]
+    pub expires_at: u64,
+    #[serde(default)]
+    pub transcript: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIMessage {
     pub role: String,
@@ -105,6 +190,8 @@ pub struct OpenAIMessage {
     pub tool_call_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<OpenAIAudioOutput>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -138,7 +225,7 @@ pub struct Choice {
     pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OpenAIUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -149,13 +236,13 @@ pub struct OpenAIUsage {
     pub completion_tokens_details: Option<CompletionTokensDetails>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PromptTokensDetails {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cached_tokens: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionTokensDetails {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning_tokens: Option<u32>,
@@ -5,6 +5,28 @@ use crate::proxy::token_manager::ProxyToken;
 
 use serde_json::{json, Value};
 
+/// o1 / o3 / o4-mini 等 OpenAI 推理模型名
+fn is_openai_reasoning_model(model: &str) -> bool {
+    let lower = model.to_lowercase();
+    ["o1", "o3", "o4"].iter().any(|prefix| {
+        lower
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+    })
+}
+
+/// reasoning_effort -> thinkingBudget，不超过模型规格上限
+fn reasoning_effort_budget(effort: &str, max_budget: u64) -> Option<u32> {
+    let budget: u64 = match effort {
+        "minimal" => 1024,
+        "low" => 4096,
+        "medium" => 12288,
+        "high" | "xhigh" => max_budget,
+        _ => return None,
+    };
+    Some(budget.min(max_budget) as u32)
+}
+
 pub fn transform_openai_request(
     request: &OpenAIRequest,
     project_id: &str,
@@ -53,12 +75,31 @@ pub fn transform_openai_request(
     let is_thinking_model = is_gemini_3_thinking || is_claude_thinking || is_gemini_flash_thinking;
 
 
+    // [NEW] OpenAI reasoning_effort / reasoning.effort；o 系列模型未指定时按官方默认 medium
+    let reasoning_effort = request
+        .reasoning_effort
+        .clone()
+        .or_else(|| request.reasoning.as_ref().and_then(|r| r.effort.clone()))
+        .map(|e| e.to_lowercase())
+        .or_else(|| {
+            let thinking_capable = is_thinking_model || model_specs::is_thinking_model(mapped_model);
+            (thinking_capable && is_openai_reasoning_model(&request.model)).then(|| "medium".to_string())
+        })
+        .filter(|e| e != "none");
+
     // [NEW] 检查用户是否在请求中显式启用 thinking
     let user_enabled_thinking = request.thinking.as_ref()
         .map(|t| t.thinking_type.as_deref() == Some("enabled"))
-        .unwrap_or(false);
+        .unwrap_or(false)
+        || reasoning_effort.is_some();
     let user_thinking_budget = request.thinking.as_ref()
-        .and_then(|t| t.budget_tokens);
+        .and_then(|t| t.budget_tokens)
+        .or_else(|| {
+            let max_budget = model_specs::get_thinking_budget(mapped_model, token);
+            reasoning_effort
+                .as_deref()
+                .and_then(|effort| reasoning_effort_budget(effort, max_budget))
+        });
 
     // [NEW] 检查历史消息是否兼容思维模型 (是否有 Assistant 消息缺失 reasoning_content)
     let has_incompatible_assistant_history = request.messages.iter().any(|msg| {
@@ -298,11 +339,23 @@ pub fn transform_openai_request(
                                         }
                                     }
                                 }
-                                OpenAIContentBlock::AudioUrl { audio_url: _ } => {
-                                    // 暂时跳过 audio_url 处理
-                                    // 完整实现需要下载音频文件并转换为 Gemini inlineData 格式
-                                    // 这会与 v3.3.16 的 thinkingConfig 逻辑冲突，留待后续版本实现
-                                    tracing::debug!("[OpenAI-Request] Skipping audio_url (not yet implemented in v3.3.16)");
+                                OpenAIContentBlock::File { .. } | OpenAIContentBlock::InputFile { .. } => {
+                                    // [NEW] PDF / 文档输入 (大小与类型已在 handler 中校验)
+                                    if let Some(file) = crate::proxy::common::document::openai_file_content(block) {
+                                        match crate::proxy::common::document::openai_file_part(&file) {
+                                            Ok(part) => parts.push(part),
+                                            Err(e) => tracing::warn!("[OpenAI-Request] Skipping file block: {}", e),
+                                        }
+                                    }
+                                }
+                                OpenAIContentBlock::AudioUrl { .. } | OpenAIContentBlock::InputAudio { .. } => {
+                                    // [NEW] input_audio 与 data URI 形式的 audio_url 转为 inlineData (格式与大小已在 handler 中校验)
+                                    // 远程 audio_url 仍不支持
+                                    match crate::proxy::audio::AudioProcessor::openai_audio_part(block) {
+                                        Ok(Some(part)) => parts.push(part),
+                                        Ok(None) => tracing::debug!("[OpenAI-Request] Skipping remote audio_url (not supported)"),
+                                        Err(e) => tracing::warn!("[OpenAI-Request] Skipping audio block: {}", e),
+                                    }
                                 }
                             }
                         }
@@ -385,6 +438,13 @@ pub fn transform_openai_request(
                                         texts.push("[image link]".to_string());
                                     }
                                 }
+                                OpenAIContentBlock::File { .. } | OpenAIContentBlock::InputFile { .. } => {
+                                    if let Some(part) = crate::proxy::common::document::openai_file_content(block)
+                                        .and_then(|file| crate::proxy::common::document::openai_file_part(&file).ok())
+                                    {
+                                        extra_parts.push(part);
+                                    }
+                                }
                                 _ => {}
                             }
                         }
@@ -447,8 +507,11 @@ pub fn transform_openai_request(
         "topK": 40,
     });
 
+    // [NEW] max_completion_tokens 优先于已废弃的 max_tokens
+    let requested_max_tokens = request.max_completion_tokens.or(request.max_tokens);
+
     // [FIX] 移除旧的硬编码限额，改为动态查询 (v4.1.29)
-    if let Some(max_tokens) = request.max_tokens {
+    if let Some(max_tokens) = requested_max_tokens {
          gen_config["maxOutputTokens"] = json!(max_tokens);
     } else {
          // 使用动态优先的规格限额
@@ -461,6 +524,17 @@ pub fn transform_openai_request(
         gen_config["candidateCount"] = json!(n);
     }
 
+    // [NEW] 采样惩罚项与随机种子透传
+    if let Some(penalty) = request.frequency_penalty {
+        gen_config["frequencyPenalty"] = json!(penalty);
+    }
+    if let Some(penalty) = request.presence_penalty {
+        gen_config["presencePenalty"] = json!(penalty);
+    }
+    if let Some(seed) = request.seed {
+        gen_config["seed"] = json!(seed);
+    }
+
     // 为 thinking 模型注入 thinkingConfig (使用 thinkingBudget 而非 thinkingLevel)
     if actual_include_thinking {
         // [RESOLVE #1694] Check image thinking mode
@@ -519,7 +593,7 @@ pub fn transform_openai_request(
             let overhead = if config.request_type == "image_gen" { 2048 } else { 32768 };
             let min_overhead = if config.request_type == "image_gen" { 1024 } else { 8192 };
 
-            if let Some(max_tokens) = request.max_tokens {
+            if let Some(max_tokens) = requested_max_tokens {
                  if (max_tokens as i64) <= budget {
                      gen_config["maxOutputTokens"] = json!(budget + min_overhead);
                  }
@@ -558,13 +632,7 @@ pub fn transform_openai_request(
     let mut inner_request = json!({
         "contents": contents,
         "generationConfig": gen_config,
-        "safetySettings": [
-            { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF" },
-            { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "OFF" },
-            { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "OFF" },
-            { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF" },
-            { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": "OFF" },
-        ]
+        "safetySettings": crate::proxy::safety_settings::build_safety_settings(mapped_model)
     });
 
     // 深度清理 [undefined] 字符串 (Cherry Studio 等客户端常见注入)
@@ -723,6 +791,17 @@ pub fn transform_openai_request(
         }
     }
 
+    // [NEW] 模型绑定了 Gemini 上下文缓存时改为引用 cachedContent
+    if config.request_type != "image_gen" {
+        crate::proxy::context_cache::apply_to_request(
+            &mut inner_request,
+            &request.model,
+            mapped_model,
+            token.map(|t| t.account_id.as_str()),
+            None,
+        );
+    }
+
     // [ADDED v4.1.24] 注入稳定 sessionId 对齐官方规范
     if let Some(t) = token {
         inner_request["sessionId"] = json!(crate::proxy::common::session::derive_session_id(&t.account_id));
@@ -784,6 +863,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             ..Default::default()
         };
@@ -806,6 +886,8 @@ mod tests {
             mode: ThinkingBudgetMode::Custom,
             custom_value: 32000,
             effort: None,
+            hide_openai_reasoning: false,
+            claude_thinking_output: crate::proxy::config::ClaudeThinkingOutput::Blocks,
         });
 
         let req = OpenAIRequest {
@@ -817,6 +899,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             stream: false,
             n: None,
@@ -868,6 +951,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             stream: false,
             n: None,
@@ -903,6 +987,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             stream: false,
             n: None,
@@ -945,6 +1030,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             ..Default::default()
         };
@@ -972,6 +1058,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             stream: false,
             n: None,
@@ -1009,6 +1096,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             stream: false,
             n: None,
@@ -1061,6 +1149,7 @@ mod tests {
                 }]),
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             person_generation: None,
             ..Default::default()
@@ -1101,6 +1190,7 @@ mod tests {
                     }]),
                     tool_call_id: None,
                     name: None,
+                    audio: None,
                 }],
                 ..Default::default()
             };
@@ -1138,6 +1228,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 reasoning_content: None,
+                audio: None,
             }],
             tools: None,
             tool_choice: None,
@@ -1171,6 +1262,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                audio: None,
             }],
             tools: Some(vec![json!({
                 "type": "function",
@@ -1198,4 +1290,113 @@ mod tests {
         assert!(has_functions, "Should contain functionDeclarations");
         assert!(has_google_search, "Should contain googleSearch (Gemini 2.0+ supports mixed tools)");
     }
+
+    fn simple_user_request(model: &str) -> OpenAIRequest {
+        OpenAIRequest {
+            model: model.to_string(),
+            messages: vec![OpenAIMessage {
+                role: "user".to_string(),
+                content: Some(OpenAIContent::String("Hello".to_string())),
+                reasoning_content: None,
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+                audio: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sampling_params_mapping() {
+        let mut req = simple_user_request("gpt-4o");
+        req.temperature = Some(0.3);
+        req.top_p = Some(0.8);
+        req.frequency_penalty = Some(0.5);
+        req.presence_penalty = Some(-0.25);
+        req.seed = Some(42);
+        req.n = Some(2);
+
+        let (result, _, _) = transform_openai_request(&req, "proj", "gemini-2.5-flash", None);
+        let gen_config = &result["request"]["generationConfig"];
+
+        assert_eq!(gen_config["temperature"], 0.3);
+        assert_eq!(gen_config["topP"], 0.8);
+        assert_eq!(gen_config["frequencyPenalty"], 0.5);
+        assert_eq!(gen_config["presencePenalty"], -0.25);
+        assert_eq!(gen_config["seed"], 42);
+        assert_eq!(gen_config["candidateCount"], 2);
+    }
+
+    #[test]
+    fn test_sampling_params_omitted_when_absent() {
+        let req = simple_user_request("gpt-4o");
+
+        let (result, _, _) = transform_openai_request(&req, "proj", "gemini-2.5-flash", None);
+        let gen_config = &result["request"]["generationConfig"];
+
+        assert!(gen_config.get("frequencyPenalty").is_none());
+        assert!(gen_config.get("presencePenalty").is_none());
+        assert!(gen_config.get("seed").is_none());
+        assert!(gen_config.get("candidateCount").is_none());
+    }
+
+    #[test]
+    fn test_max_tokens_mapping() {
+        let mut req = simple_user_request("gpt-4o");
+        req.max_tokens = Some(1024);
+        let (result, _, _) = transform_openai_request(&req, "proj", "gemini-2.5-flash", None);
+        assert_eq!(result["request"]["generationConfig"]["maxOutputTokens"], 1024);
+
+        // max_completion_tokens 优先于 max_tokens
+        req.max_completion_tokens = Some(2048);
+        let (result, _, _) = transform_openai_request(&req, "proj", "gemini-2.5-flash", None);
+        assert_eq!(result["request"]["generationConfig"]["maxOutputTokens"], 2048);
+    }
+
+    #[test]
+    fn test_max_completion_tokens_deserialization() {
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+            "max_completion_tokens": 512,
+            "frequency_penalty": 1.0,
+            "presence_penalty": 0.5,
+            "seed": 7
+        }))
+        .unwrap();
+
+        assert_eq!(req.max_completion_tokens, Some(512));
+        assert_eq!(req.frequency_penalty, Some(1.0));
+        assert_eq!(req.presence_penalty, Some(0.5));
+        assert_eq!(req.seed, Some(7));
+    }
+
+    #[test]
+    fn test_reasoning_effort_maps_to_thinking_budget() {
+        assert!(is_openai_reasoning_model("o3-mini"));
+        assert!(is_openai_reasoning_model("o1"));
+        assert!(!is_openai_reasoning_model("omni-model"));
+        assert_eq!(reasoning_effort_budget("low", 24576), Some(4096));
+        assert_eq!(reasoning_effort_budget("high", 24576), Some(24576));
+        assert_eq!(reasoning_effort_budget("medium", 8192), Some(8192));
+        assert_eq!(reasoning_effort_budget("extreme", 8192), None);
+
+        let mut req = simple_user_request("gpt-4o");
+        req.reasoning_effort = Some("low".to_string());
+        let (result, _, _) = transform_openai_request(&req, "test-project", "gemini-2.5-flash", None);
+        // 预算最终值受全局 thinking_budget 模式影响 (其他测试会修改)，这里只校验已开启
+        assert_eq!(result["request"]["generationConfig"]["thinkingConfig"]["includeThoughts"], true);
+
+        // o 系列模型未指定时默认开启 (medium)
+        let req = simple_user_request("o3");
+        let (result, _, _) = transform_openai_request(&req, "test-project", "gemini-2.5-flash", None);
+        assert_eq!(result["request"]["generationConfig"]["thinkingConfig"]["includeThoughts"], true);
+
+        // reasoning_effort=none 不开启
+        let mut req = simple_user_request("gpt-4o");
+        req.reasoning_effort = Some("none".to_string());
+        let (result, _, _) = transform_openai_request(&req, "test-project", "gemini-2.5-flash", None);
+        assert!(result["request"]["generationConfig"].get("thinkingConfig").is_none());
+    }
 }
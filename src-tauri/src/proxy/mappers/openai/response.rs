@@ -2,18 +2,122 @@
 use super::models::*;
 use serde_json::Value;
 
+/// Gemini usageMetadata -> OpenAI usage
+/// OpenAI 的 completion_tokens 包含推理 token，因此需加上 thoughtsTokenCount，并单独填入 reasoning_tokens
+pub(crate) fn usage_from_metadata(u: &Value) -> OpenAIUsage {
+    let count = |key: &str| u.get(key).and_then(|v| v.as_u64()).map(|v| v as u32);
+
+    let prompt_tokens = count("promptTokenCount").unwrap_or(0);
+    let reasoning_tokens = count("thoughtsTokenCount").filter(|v| *v > 0);
+    let completion_tokens = count("candidatesTokenCount").unwrap_or(0) + reasoning_tokens.unwrap_or(0);
+    let total_tokens = count("totalTokenCount")
+        .filter(|v| *v > 0)
+        .unwrap_or(prompt_tokens + completion_tokens);
+
+    OpenAIUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens,
+        prompt_tokens_details: count("cachedContentTokenCount").map(|ct| PromptTokensDetails {
+            cached_tokens: Some(ct),
+        }),
+        completion_tokens_details: reasoning_tokens.map(|rt| CompletionTokensDetails {
+            reasoning_tokens: Some(rt),
+        }),
+    }
+}
+
+/// 组装 assistant 消息内容：无图片时保持字符串形式，
+/// 有图片时返回 text + image_url(data URI) 的数组形式
+pub(crate) fn build_message_content(
+    text: String,
+    images: Vec<OpenAIContentBlock>,
+) -> Option<OpenAIContent> {
+    if images.is_empty() {
+        return if text.is_empty() {
+            None
+        } else {
+            Some(OpenAIContent::String(text))
+        };
+    }
+
+    let mut blocks = Vec::with_capacity(images.len() + 1);
+    if !text.is_empty() {
+        blocks.push(OpenAIContentBlock::Text { text });
+    }
+    blocks.extend(images);
+    Some(OpenAIContent::Array(blocks))
+}
+
+/// 从流式文本中提取 `![image](data:...)` 形式的内联图片
+/// 流式 delta 只能携带字符串，收集为非流式响应时再还原为 image_url 内容块
+pub(crate) fn extract_markdown_data_images(text: &str) -> (String, Vec<OpenAIContentBlock>) {
+    const PREFIX: &str = "![image](data:";
+    let mut remaining = String::with_capacity(text.len());
+    let mut images = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(PREFIX) {
+        let url_start = start + "![image](".len();
+        match rest[url_start..].find(')') {
+            Some(end) => {
+                remaining.push_str(&rest[..start]);
+                images.push(OpenAIContentBlock::ImageUrl {
+                    image_url: OpenAIImageUrl {
+                        url: rest[url_start..url_start + end].to_string(),
+                        detail: None,
+                    },
+                });
+                rest = &rest[url_start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    remaining.push_str(rest);
+
+    (remaining, images)
+}
+
+/// 拼接多个 base64 音频片段 (各片段独立编码，需解码后再合并)
+pub(crate) fn append_audio_chunk(buffer: &mut Vec<u8>, base64_data: &str) {
+    use base64::Engine as _;
+    match base64::engine::general_purpose::STANDARD.decode(base64_data) {
+        Ok(bytes) => buffer.extend_from_slice(&bytes),
+        Err(e) => tracing::warn!("[OpenAI-Response] Invalid audio chunk: {}", e),
+    }
+}
+
+/// 组装助手消息的音频输出
+pub(crate) fn build_audio_output(audio: Vec<u8>, transcript: &str) -> Option<OpenAIAudioOutput> {
+    use base64::Engine as _;
+    if audio.is_empty() {
+        return None;
+    }
+    Some(OpenAIAudioOutput {
+        id: format!("audio_{}", uuid::Uuid::new_v4().simple()),
+        data: base64::engine::general_purpose::STANDARD.encode(&audio),
+        expires_at: chrono::Utc::now().timestamp() as u64 + 3600,
+        transcript: transcript.to_string(),
+    })
+}
+
 pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&str>, message_count: usize) -> OpenAIResponse {
     // 解包 response 字段
     let raw = gemini_response.get("response").unwrap_or(gemini_response);
 
     let mut choices = Vec::new();
+    // [NEW] 配置为隐藏时不输出 reasoning_content
+    let hide_reasoning = crate::proxy::config::get_thinking_budget_config().hide_openai_reasoning;
 
     // 支持多候选结果 (n > 1)
     if let Some(candidates) = raw.get("candidates").and_then(|c| c.as_array()) {
-        for (idx, candidate) in candidates.iter().enumerate() {
+        for (pos, candidate) in candidates.iter().enumerate() {
+            let idx = candidate.get("index").and_then(|v| v.as_u64()).unwrap_or(pos as u64) as u32;
             let mut content_out = String::new();
             let mut thought_out = String::new();
             let mut tool_calls = Vec::new();
+            let mut images = Vec::new();
+            let mut audio = Vec::new();
 
             // 提取 content 和 tool_calls
             if let Some(parts) = candidate
@@ -73,16 +177,23 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
                         });
                     }
 
-                    // 图片处理 (响应中直接返回图片的情况)
+                    // 图片处理 (image_gen 响应中直接返回图片的情况) -> image_url (data URI)
+                    // [NEW] 音频模型返回的 audio/* 数据 -> message.audio
                     if let Some(img) = part.get("inlineData") {
                         let mime_type = img
                             .get("mimeType")
                             .and_then(|v| v.as_str())
                             .unwrap_or("image/png");
                         let data = img.get("data").and_then(|v| v.as_str()).unwrap_or("");
-                        if !data.is_empty() {
-                            content_out
-                                .push_str(&format!("![image](data:{};base64,{})", mime_type, data));
+                        if crate::proxy::audio::AudioProcessor::is_audio_mime_type(mime_type) {
+                            append_audio_chunk(&mut audio, data);
+                        } else if !data.is_empty() {
+                            images.push(OpenAIContentBlock::ImageUrl {
+                                image_url: OpenAIImageUrl {
+                                    url: format!("data:{};base64,{}", mime_type, data),
+                                    detail: None,
+                                },
+                            });
                         }
                     }
                 }
@@ -128,28 +239,18 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
             }
 
             // 提取该候选结果的 finish_reason
-            let finish_reason = candidate
-                .get("finishReason")
-                .and_then(|f| f.as_str())
-                .map(|f| match f {
-                    "STOP" => "stop",
-                    "MAX_TOKENS" => "length",
-                    "SAFETY" => "content_filter",
-                    "RECITATION" => "content_filter",
-                    _ => "stop",
-                })
-                .unwrap_or("stop");
+            let finish_reason = crate::proxy::mappers::finish_reason::to_openai_finish_reason(
+                candidate.get("finishReason").and_then(|f| f.as_str()).unwrap_or("STOP"),
+                !tool_calls.is_empty(),
+            );
 
+            let audio = build_audio_output(audio, &content_out);
             choices.push(Choice {
-                index: idx as u32,
+                index: idx,
                 message: OpenAIMessage {
                     role: "assistant".to_string(),
-                    content: if content_out.is_empty() {
-                        None
-                    } else {
-                        Some(OpenAIContent::String(content_out))
-                    },
-                    reasoning_content: if thought_out.is_empty() {
+                    content: build_message_content(content_out, images),
+                    reasoning_content: if thought_out.is_empty() || hide_reasoning {
                         None
                     } else {
                         Some(thought_out)
@@ -161,6 +262,7 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
                     },
                     tool_call_id: None,
                     name: None,
+                    audio,
                 },
                 finish_reason: Some(finish_reason.to_string()),
             });
@@ -168,34 +270,7 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
     }
 
     // Extract and map usage metadata from Gemini to OpenAI format
-    let usage = raw.get("usageMetadata").and_then(|u| {
-        let prompt_tokens = u
-            .get("promptTokenCount")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0) as u32;
-        let completion_tokens = u
-            .get("candidatesTokenCount")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0) as u32;
-        let total_tokens = u
-            .get("totalTokenCount")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0) as u32;
-        let cached_tokens = u
-            .get("cachedContentTokenCount")
-            .and_then(|v| v.as_u64())
-            .map(|v| v as u32);
-
-        Some(super::models::OpenAIUsage {
-            prompt_tokens,
-            completion_tokens,
-            total_tokens,
-            prompt_tokens_details: cached_tokens.map(|ct| super::models::PromptTokensDetails {
-                cached_tokens: Some(ct),
-            }),
-            completion_tokens_details: None,
-        })
-    });
+    let usage = raw.get("usageMetadata").map(usage_from_metadata);
 
     OpenAIResponse {
         id: raw
@@ -243,6 +318,27 @@ mod tests {
         assert_eq!(result.choices[0].finish_reason, Some("stop".to_string()));
     }
 
+    #[test]
+    fn test_multiple_candidates_become_choices() {
+        let gemini_resp = json!({
+            "candidates": [
+                {"content": {"parts": [{"text": "First"}]}, "finishReason": "STOP", "index": 0},
+                {"content": {"parts": [{"text": "Second"}]}, "finishReason": "MAX_TOKENS", "index": 1}
+            ],
+            "modelVersion": "gemini-2.5-flash",
+            "responseId": "resp_multi"
+        });
+
+        let result = transform_openai_response(&gemini_resp, Some("session-123"), 1);
+        assert_eq!(result.choices.len(), 2);
+        assert_eq!(result.choices[1].index, 1);
+        assert_eq!(
+            result.choices[1].message.content,
+            Some(OpenAIContent::String("Second".to_string()))
+        );
+        assert_eq!(result.choices[1].finish_reason, Some("length".to_string()));
+    }
+
     #[test]
     fn test_usage_metadata_mapping() {
         let gemini_resp = json!({
@@ -271,6 +367,18 @@ mod tests {
         assert_eq!(usage.prompt_tokens_details.unwrap().cached_tokens, Some(25));
     }
 
+    #[test]
+    fn test_usage_includes_reasoning_tokens() {
+        let usage = usage_from_metadata(&json!({
+            "promptTokenCount": 10,
+            "candidatesTokenCount": 20,
+            "thoughtsTokenCount": 30
+        }));
+        assert_eq!(usage.completion_tokens, 50);
+        assert_eq!(usage.total_tokens, 60);
+        assert_eq!(usage.completion_tokens_details.unwrap().reasoning_tokens, Some(30));
+    }
+
     #[test]
     fn test_response_without_usage_metadata() {
         let gemini_resp = json!({
@@ -285,4 +393,69 @@ mod tests {
         let result = transform_openai_response(&gemini_resp, Some("session-123"), 1);
         assert!(result.usage.is_none());
     }
+
+    #[test]
+    fn test_inline_image_mapped_to_image_url() {
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"text": "Here you go"},
+                    {"inlineData": {"mimeType": "image/png", "data": "iVBORw0KGgo="}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "modelVersion": "gemini-3-pro-image",
+            "responseId": "resp_img"
+        });
+
+        let result = transform_openai_response(&gemini_resp, None, 1);
+        match result.choices[0].message.content.as_ref().unwrap() {
+            OpenAIContent::Array(blocks) => {
+                assert_eq!(blocks.len(), 2);
+                assert_eq!(blocks[0], OpenAIContentBlock::Text { text: "Here you go".to_string() });
+                match &blocks[1] {
+                    OpenAIContentBlock::ImageUrl { image_url } => {
+                        assert_eq!(image_url.url, "data:image/png;base64,iVBORw0KGgo=");
+                    }
+                    _ => panic!("Expected image_url block"),
+                }
+            }
+            _ => panic!("Expected array content"),
+        }
+    }
+
+    #[test]
+    fn test_inline_audio_mapped_to_message_audio() {
+        // "AAEC" + "AwQF" -> 0..=5，分片需解码后拼接
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"text": "hello"},
+                    {"inlineData": {"mimeType": "audio/wav", "data": "AAEC"}},
+                    {"inlineData": {"mimeType": "audio/wav", "data": "AwQF"}}
+                ]},
+                "finishReason": "STOP"
+            }]
+        });
+
+        let result = transform_openai_response(&gemini_resp, None, 1);
+        let message = &result.choices[0].message;
+        assert!(matches!(message.content, Some(OpenAIContent::String(ref s)) if s == "hello"));
+        let audio = message.audio.as_ref().expect("audio output");
+        assert_eq!(audio.data, "AAECAwQF");
+        assert_eq!(audio.transcript, "hello");
+    }
+
+    #[test]
+    fn test_extract_markdown_data_images() {
+        let (text, images) = extract_markdown_data_images(
+            "before ![image](data:image/png;base64,AAAA) after",
+        );
+        assert_eq!(text, "before  after");
+        assert_eq!(images.len(), 1);
+
+        let (text, images) = extract_markdown_data_images("plain ![pic](https://x/y.png)");
+        assert_eq!(text, "plain ![pic](https://x/y.png)");
+        assert!(images.is_empty());
+    }
 }
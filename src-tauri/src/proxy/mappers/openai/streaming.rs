@@ -1,5 +1,6 @@
 // OpenAI 流式转换
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
+use crate::proxy::common::sse_decoder::SseLineDecoder;
 use chrono::Utc;
 use futures::{Stream, StreamExt};
 use rand::Rng;
@@ -8,6 +9,9 @@ use std::pin::Pin;
 use tracing::debug;
 use uuid::Uuid;
 
+use super::response::usage_from_metadata;
+use crate::proxy::mappers::finish_reason::to_openai_finish_reason;
+
 
 
 /// 保存 thoughtSignature 到会话缓存
@@ -31,57 +35,31 @@ pub fn store_thought_signature(sig: &str, session_id: &str, message_count: usize
 
 
 
-/// Extract and convert Gemini usageMetadata to OpenAI usage format
-fn extract_usage_metadata(u: &Value) -> Option<super::models::OpenAIUsage> {
-    use super::models::{OpenAIUsage, PromptTokensDetails};
-
-    let prompt_tokens = u
-        .get("promptTokenCount")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as u32;
-    let completion_tokens = u
-        .get("candidatesTokenCount")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as u32;
-    let total_tokens = u
-        .get("totalTokenCount")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as u32;
-    let cached_tokens = u
-        .get("cachedContentTokenCount")
-        .and_then(|v| v.as_u64())
-        .map(|v| v as u32);
-
-    Some(OpenAIUsage {
-        prompt_tokens,
-        completion_tokens,
-        total_tokens,
-        prompt_tokens_details: cached_tokens.map(|ct| PromptTokensDetails {
-            cached_tokens: Some(ct),
-        }),
-        completion_tokens_details: None,
-    })
-}
-
+/// include_usage: 客户端传入 stream_options.include_usage=true 时，
+/// 用量改为在 [DONE] 前单独下发一个 choices 为空的 chunk (OpenAI 标准行为)；否则附在结束 chunk 上。
 pub fn create_openai_sse_stream<S, E>(
     mut gemini_stream: Pin<Box<S>>,
     model: String,
     session_id: String,
     message_count: usize,
+    include_usage: bool,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> 
 where
     S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
     E: std::fmt::Display + Send + 'static,
 {
-    let mut buffer = BytesMut::new();
+    let mut decoder = SseLineDecoder::new();
     let stream_id = format!("chatcmpl-{}", Uuid::new_v4());
     let created_ts = Utc::now().timestamp();
+    // [NEW] 配置为隐藏时不输出 reasoning_content
+    let hide_reasoning = crate::proxy::config::get_thinking_budget_config().hide_openai_reasoning;
 
     let stream = async_stream::stream! {
-        let mut emitted_tool_calls = std::collections::HashSet::new();
+        // [NEW] 工具调用状态按 choice index 隔离 (支持 n > 1)
+        let mut emitted_tool_calls: std::collections::HashMap<u32, std::collections::HashSet<String>> = std::collections::HashMap::new();
         let mut final_usage: Option<super::models::OpenAIUsage> = None;
         let mut error_occurred = false;
-        let mut tool_call_index = 0;
+        let mut tool_call_indices: std::collections::HashMap<u32, u32> = std::collections::HashMap::new();
 
         let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(15));
         heartbeat_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
@@ -89,207 +67,213 @@ where
         loop {
             tokio::select! {
                 item = gemini_stream.next() => {
-                    match item {
-                        Some(Ok(bytes)) => {
-                            buffer.extend_from_slice(&bytes);
-                            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                                let line_raw = buffer.split_to(pos + 1);
-                                if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                                    let line = line_str.trim();
-                                    if line.is_empty() { continue; }
-                                    if line.starts_with("data: ") {
-                                        let json_part = line.trim_start_matches("data: ").trim();
-                                        if json_part == "[DONE]" { continue; }
-                                        if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
-                                            let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
-                                            if let Some(u) = actual_data.get("usageMetadata") {
-                                                final_usage = extract_usage_metadata(u);
-                                            }
+                    let (lines, ended) = match item {
+                        Some(Ok(bytes)) => (decoder.push(&bytes), false),
+                        Some(Err(e)) => {
+                            use crate::proxy::mappers::error_classifier::classify_stream_error;
+                            let (error_type, user_msg, i18n_key) = classify_stream_error(&e);
+                            tracing::error!("OpenAI Stream Error: {}", e);
+                            let error_chunk = json!({
+                                "id": &stream_id, "object": "chat.completion.chunk", "created": created_ts, "model": &model, "choices": [],
+                                "error": { "type": error_type, "message": user_msg, "code": "stream_error", "i18n_key": i18n_key }
+                            });
+                            yield Ok(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&error_chunk).unwrap_or_default())));
+                            yield Ok(Bytes::from("data: [DONE]\n\n"));
+                            error_occurred = true;
+                            break;
+                        }
+                        // [FIX #1732] 流结束时冲刷未以换行结尾的最后一行，避免丢失尾块
+                        None => (decoder.finish().into_iter().collect::<Vec<_>>(), true),
+                    };
+                    for line_str in lines {
+                        let line = line_str.trim();
+                        if line.is_empty() { continue; }
+                        if line.starts_with("data: ") {
+                            let json_part = line.trim_start_matches("data: ").trim();
+                            if json_part == "[DONE]" { continue; }
+                            if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
+                                let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
+                                if let Some(u) = actual_data.get("usageMetadata") {
+                                    final_usage = Some(usage_from_metadata(u));
+                                }
 
-                                            if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                                                // [DEBUG] 打印原始 candidate 以排查空回复问题
-                                                if candidates.len() > 0 {
-                                                     tracing::debug!("[Stream-Debug] Raw Candidate: {:?}", candidates[0]);
+                                if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
+                                    // [DEBUG] 打印原始 candidate 以排查空回复问题
+                                    if candidates.len() > 0 {
+                                         tracing::debug!("[Stream-Debug] Raw Candidate: {:?}", candidates[0]);
+                                    }
+                                    for (pos, candidate) in candidates.iter().enumerate() {
+                                        // 多候选流式响应中每个 chunk 可能只携带部分候选，以 index 字段为准
+                                        let idx = candidate.get("index").and_then(|v| v.as_u64()).unwrap_or(pos as u64) as u32;
+                                        let choice_tool_calls = emitted_tool_calls.entry(idx).or_default();
+                                        let parts = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array());
+                                        let mut content_out = String::new();
+                                        let mut thought_out = String::new();
+
+                                        if let Some(parts_list) = parts {
+                                            for part in parts_list {
+                                                let is_thought_part = part.get("thought").and_then(|v| v.as_bool()).unwrap_or(false);
+                                                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                                    if is_thought_part { thought_out.push_str(text); }
+                                                    else { content_out.push_str(text); }
                                                 }
-                                                for (idx, candidate) in candidates.iter().enumerate() {
-                                                    let parts = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array());
-                                                    let mut content_out = String::new();
-                                                    let mut thought_out = String::new();
-
-                                                    if let Some(parts_list) = parts {
-                                                        for part in parts_list {
-                                                            let is_thought_part = part.get("thought").and_then(|v| v.as_bool()).unwrap_or(false);
-                                                            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                                                                if is_thought_part { thought_out.push_str(text); }
-                                                                else { content_out.push_str(text); }
-                                                            }
-                                                            if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
-                                                                store_thought_signature(sig, &session_id, message_count);
-                                                            }
-                                                            if let Some(img) = part.get("inlineData") {
-                                                                let mime_type = img.get("mimeType").and_then(|v| v.as_str()).unwrap_or("image/png");
-                                                                let data = img.get("data").and_then(|v| v.as_str()).unwrap_or("");
-                                                                if !data.is_empty() {
-                                                                    content_out.push_str(&format!("![image](data:{};base64,{})", mime_type, data));
-                                                                }
-                                                            }
-                                                            if let Some(func_call) = part.get("functionCall") {
-                                                                let call_key = serde_json::to_string(func_call).unwrap_or_default();
-                                                                if !emitted_tool_calls.contains(&call_key) {
-                                                                    emitted_tool_calls.insert(call_key);
-                                                                    let name = func_call.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
-                                                                    let mut args = func_call.get("args").unwrap_or(&json!({})).clone();
-                                                                    
-                                                                    // [FIX #1575] 标准化 shell 工具参数名称
-                                                                    // Gemini 可能使用 cmd/code/script 等替代参数名，统一为 command
-                                                                    if name == "shell" || name == "bash" || name == "local_shell" {
-                                                                        if let Some(obj) = args.as_object_mut() {
-                                                                            if !obj.contains_key("command") {
-                                                                                for alt_key in &["cmd", "code", "script", "shell_command"] {
-                                                                                    if let Some(val) = obj.remove(*alt_key) {
-                                                                                        obj.insert("command".to_string(), val);
-                                                                                        debug!("[OpenAI-Stream] Normalized shell arg '{}' -> 'command'", alt_key);
-                                                                                        break;
-                                                                                    }
-                                                                                }
-                                                                            }
-                                                                        }
-                                                                    }
-                                                                    
-                                                                    let args_str = serde_json::to_string(&args).unwrap_or_default();
-                                                                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                                                                    use std::hash::{Hash, Hasher};
-                                                                    serde_json::to_string(func_call).unwrap_or_default().hash(&mut hasher);
-                                                                    let call_id = format!("call_{:x}", hasher.finish());
- 
-                                                                    let tool_call_chunk = json!({
-                                                                        "id": &stream_id,
-                                                                        "object": "chat.completion.chunk",
-                                                                        "created": created_ts,
-                                                                        "model": &model,
-                                                                        "choices": [{
-                                                                            "index": idx as u32,
-                                                                            "delta": {
-                                                                                "role": "assistant",
-                                                                                "tool_calls": [{
-                                                                                    "index": tool_call_index,
-                                                                                    "id": call_id,
-                                                                                    "type": "function",
-                                                                                    "function": { "name": name, "arguments": args_str }
-                                                                                }]
-                                                                            },
-                                                                            "finish_reason": serde_json::Value::Null
-                                                                        }]
-                                                                    });
-                                                                    tool_call_index += 1;
-                                                                    let sse_out = format!("data: {}\n\n", serde_json::to_string(&tool_call_chunk).unwrap_or_default());
-                                                                    yield Ok::<Bytes, String>(Bytes::from(sse_out));
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-
-                                                    if let Some(grounding) = candidate.get("groundingMetadata") {
-                                                        let mut grounding_text = String::new();
-                                                        if let Some(queries) = grounding.get("webSearchQueries").and_then(|q| q.as_array()) {
-                                                            let query_list: Vec<&str> = queries.iter().filter_map(|v| v.as_str()).collect();
-                                                            if !query_list.is_empty() {
-                                                                grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
-                                                                grounding_text.push_str(&query_list.join(", "));
-                                                            }
-                                                        }
-                                                        if let Some(chunks) = grounding.get("groundingChunks").and_then(|c| c.as_array()) {
-                                                            let mut links = Vec::new();
-                                                            for (i, chunk) in chunks.iter().enumerate() {
-                                                                if let Some(web) = chunk.get("web") {
-                                                                    let title = web.get("title").and_then(|v| v.as_str()).unwrap_or("网页来源");
-                                                                    let uri = web.get("uri").and_then(|v| v.as_str()).unwrap_or("#");
-                                                                    links.push(format!("[{}] [{}]({})", i + 1, title, uri));
-                                                                }
-                                                            }
-                                                            if !links.is_empty() {
-                                                                grounding_text.push_str("\n\n**🌐 来源引文：**\n");
-                                                                grounding_text.push_str(&links.join("\n"));
-                                                            }
-                                                        }
-                                                        if !grounding_text.is_empty() { content_out.push_str(&grounding_text); }
-                                                    }
-
-                                                    let gemini_finish_reason = candidate.get("finishReason").and_then(|f| f.as_str()).map(|f| match f {
-                                                        "STOP" => "stop",
-                                                        "MAX_TOKENS" => "length",
-                                                        "SAFETY" => "content_filter",
-                                                        "RECITATION" => "content_filter",
-                                                        _ => f,
-                                                    });
-
-                                                    // [FIX #1575] 如果发射了工具调用，强制设置为 tool_calls
-                                                    // 解决 Gemini 返回 STOP 但有工具调用时，OpenAI 客户端认为对话已结束的问题
-                                                    let finish_reason = if !emitted_tool_calls.is_empty() && gemini_finish_reason.is_some() {
-                                                        Some("tool_calls")
-                                                    } else {
-                                                        gemini_finish_reason
-                                                    };
-
-                                                    if !thought_out.is_empty() {
-                                                        let reasoning_chunk = json!({
+                                                if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
+                                                    store_thought_signature(sig, &session_id, message_count);
+                                                }
+                                                if let Some(img) = part.get("inlineData") {
+                                                    let mime_type = img.get("mimeType").and_then(|v| v.as_str()).unwrap_or("image/png");
+                                                    let data = img.get("data").and_then(|v| v.as_str()).unwrap_or("");
+                                                    if !data.is_empty() && crate::proxy::audio::AudioProcessor::is_audio_mime_type(mime_type) {
+                                                        // [NEW] 音频输出以 delta.audio 分片发送
+                                                        let audio_chunk = json!({
                                                             "id": &stream_id,
                                                             "object": "chat.completion.chunk",
                                                             "created": created_ts,
                                                             "model": &model,
                                                             "choices": [{
-                                                                "index": idx as u32,
-                                                                "delta": { "role": "assistant", "content": serde_json::Value::Null, "reasoning_content": thought_out },
+                                                                "index": idx,
+                                                                "delta": { "audio": { "id": format!("audio_{}", stream_id), "data": data } },
                                                                 "finish_reason": serde_json::Value::Null
                                                             }]
                                                         });
-                                                        let sse_out = format!("data: {}\n\n", serde_json::to_string(&reasoning_chunk).unwrap_or_default());
+                                                        let sse_out = format!("data: {}\n\n", serde_json::to_string(&audio_chunk).unwrap_or_default());
                                                         yield Ok::<Bytes, String>(Bytes::from(sse_out));
+                                                    } else if !data.is_empty() {
+                                                        content_out.push_str(&format!("![image](data:{};base64,{})", mime_type, data));
                                                     }
+                                                }
+                                                if let Some(func_call) = part.get("functionCall") {
+                                                    let call_key = serde_json::to_string(func_call).unwrap_or_default();
+                                                    if !choice_tool_calls.contains(&call_key) {
+                                                        choice_tool_calls.insert(call_key);
+                                                        let name = func_call.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
+                                                        let mut args = func_call.get("args").unwrap_or(&json!({})).clone();
+
+                                                        // [FIX #1575] 标准化 shell 工具参数名称
+                                                        // Gemini 可能使用 cmd/code/script 等替代参数名，统一为 command
+                                                        if name == "shell" || name == "bash" || name == "local_shell" {
+                                                            if let Some(obj) = args.as_object_mut() {
+                                                                if !obj.contains_key("command") {
+                                                                    for alt_key in &["cmd", "code", "script", "shell_command"] {
+                                                                        if let Some(val) = obj.remove(*alt_key) {
+                                                                            obj.insert("command".to_string(), val);
+                                                                            debug!("[OpenAI-Stream] Normalized shell arg '{}' -> 'command'", alt_key);
+                                                                            break;
+                                                                        }
+                                                                    }
+                                                                }
+                                                            }
+                                                        }
+
+                                                        let args_str = serde_json::to_string(&args).unwrap_or_default();
+                                                        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                                                        use std::hash::{Hash, Hasher};
+                                                        serde_json::to_string(func_call).unwrap_or_default().hash(&mut hasher);
+                                                        let call_id = format!("call_{:x}", hasher.finish());
 
-                                                    if !content_out.is_empty() || finish_reason.is_some() {
-                                                        let mut openai_chunk = json!({
+                                                        let tool_call_index = tool_call_indices.entry(idx).or_insert(0);
+                                                        let tool_call_chunk = json!({
                                                             "id": &stream_id,
                                                             "object": "chat.completion.chunk",
                                                             "created": created_ts,
                                                             "model": &model,
                                                             "choices": [{
-                                                                "index": idx as u32,
-                                                                "delta": { "content": content_out },
-                                                                "finish_reason": finish_reason
+                                                                "index": idx,
+                                                                "delta": {
+                                                                    "role": "assistant",
+                                                                    "tool_calls": [{
+                                                                        "index": *tool_call_index,
+                                                                        "id": call_id,
+                                                                        "type": "function",
+                                                                        "function": { "name": name, "arguments": args_str }
+                                                                    }]
+                                                                },
+                                                                "finish_reason": serde_json::Value::Null
                                                             }]
                                                         });
-                                                        if finish_reason.is_some() {
-                                                            if let Some(ref usage) = final_usage {
-                                                                openai_chunk["usage"] = serde_json::to_value(usage).unwrap();
-                                                            }
-                                                        }
-                                                        if finish_reason.is_some() { final_usage = None; }
-                                                        let sse_out = format!("data: {}\n\n", serde_json::to_string(&openai_chunk).unwrap_or_default());
+                                                        *tool_call_index += 1;
+                                                        let sse_out = format!("data: {}\n\n", serde_json::to_string(&tool_call_chunk).unwrap_or_default());
                                                         yield Ok::<Bytes, String>(Bytes::from(sse_out));
                                                     }
                                                 }
                                             }
                                         }
+
+                                        if let Some(grounding) = candidate.get("groundingMetadata") {
+                                            let mut grounding_text = String::new();
+                                            if let Some(queries) = grounding.get("webSearchQueries").and_then(|q| q.as_array()) {
+                                                let query_list: Vec<&str> = queries.iter().filter_map(|v| v.as_str()).collect();
+                                                if !query_list.is_empty() {
+                                                    grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
+                                                    grounding_text.push_str(&query_list.join(", "));
+                                                }
+                                            }
+                                            if let Some(chunks) = grounding.get("groundingChunks").and_then(|c| c.as_array()) {
+                                                let mut links = Vec::new();
+                                                for (i, chunk) in chunks.iter().enumerate() {
+                                                    if let Some(web) = chunk.get("web") {
+                                                        let title = web.get("title").and_then(|v| v.as_str()).unwrap_or("网页来源");
+                                                        let uri = web.get("uri").and_then(|v| v.as_str()).unwrap_or("#");
+                                                        links.push(format!("[{}] [{}]({})", i + 1, title, uri));
+                                                    }
+                                                }
+                                                if !links.is_empty() {
+                                                    grounding_text.push_str("\n\n**🌐 来源引文：**\n");
+                                                    grounding_text.push_str(&links.join("\n"));
+                                                }
+                                            }
+                                            if !grounding_text.is_empty() { content_out.push_str(&grounding_text); }
+                                        }
+
+                                        // [FIX #1575] 如果发射了工具调用，映射为 tool_calls
+                                        // 解决 Gemini 返回 STOP 但有工具调用时，OpenAI 客户端认为对话已结束的问题
+                                        let finish_reason = candidate.get("finishReason").and_then(|f| f.as_str()).map(|f| {
+                                            to_openai_finish_reason(f, !choice_tool_calls.is_empty())
+                                        });
+
+                                        if !thought_out.is_empty() && !hide_reasoning {
+                                            let reasoning_chunk = json!({
+                                                "id": &stream_id,
+                                                "object": "chat.completion.chunk",
+                                                "created": created_ts,
+                                                "model": &model,
+                                                "choices": [{
+                                                    "index": idx,
+                                                    "delta": { "role": "assistant", "content": serde_json::Value::Null, "reasoning_content": thought_out },
+                                                    "finish_reason": serde_json::Value::Null
+                                                }]
+                                            });
+                                            let sse_out = format!("data: {}\n\n", serde_json::to_string(&reasoning_chunk).unwrap_or_default());
+                                            yield Ok::<Bytes, String>(Bytes::from(sse_out));
+                                        }
+
+                                        if !content_out.is_empty() || finish_reason.is_some() {
+                                            let mut openai_chunk = json!({
+                                                "id": &stream_id,
+                                                "object": "chat.completion.chunk",
+                                                "created": created_ts,
+                                                "model": &model,
+                                                "choices": [{
+                                                    "index": idx,
+                                                    "delta": { "content": content_out },
+                                                    "finish_reason": finish_reason
+                                                }]
+                                            });
+                                            if finish_reason.is_some() && !include_usage {
+                                                if let Some(ref usage) = final_usage {
+                                                    openai_chunk["usage"] = serde_json::to_value(usage).unwrap();
+                                                }
+                                                final_usage = None;
+                                            }
+                                            let sse_out = format!("data: {}\n\n", serde_json::to_string(&openai_chunk).unwrap_or_default());
+                                            yield Ok::<Bytes, String>(Bytes::from(sse_out));
+                                        }
                                     }
                                 }
                             }
                         }
-                        Some(Err(e)) => {
-                            use crate::proxy::mappers::error_classifier::classify_stream_error;
-                            let (error_type, user_msg, i18n_key) = classify_stream_error(&e);
-                            tracing::error!("OpenAI Stream Error: {}", e);
-                            let error_chunk = json!({
-                                "id": &stream_id, "object": "chat.completion.chunk", "created": created_ts, "model": &model, "choices": [],
-                                "error": { "type": error_type, "message": user_msg, "code": "stream_error", "i18n_key": i18n_key }
-                            });
-                            yield Ok(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&error_chunk).unwrap_or_default())));
-                            yield Ok(Bytes::from("data: [DONE]\n\n"));
-                            error_occurred = true;
-                            break;
-                        }
-                        None => break,
                     }
+                    if ended { break; }
                 }
                 _ = heartbeat_interval.tick() => {
                     yield Ok::<Bytes, String>(Bytes::from(": ping\n\n"));
@@ -297,23 +281,19 @@ where
             }
         }
 
-        // [FIX #1732] Flush remaining buffer to prevent hang on network fragmentation
-        if !buffer.is_empty() {
-            if let Ok(line_str) = std::str::from_utf8(&buffer) {
-                let line = line_str.trim();
-                if !line.is_empty() && line.starts_with("data: ") {
-                    let json_part = line.trim_start_matches("data: ").trim();
-                    if json_part != "[DONE]" {
-                        // Re-use logic for processing the last line
-                        // (Note: In a more complex refactor we'd extract this to a function, 
-                        // but for a targeted fix, processing the terminal data chunk is safer)
-                        tracing::debug!("[OpenAI-SSE] Flushing remaining {} bytes in buffer", buffer.len());
-                    }
-                }
-            }
-        }
-
         if !error_occurred {
+            // [NEW] stream_options.include_usage: 结束前单独下发用量 chunk
+            if include_usage {
+                let usage_chunk = json!({
+                    "id": &stream_id,
+                    "object": "chat.completion.chunk",
+                    "created": created_ts,
+                    "model": &model,
+                    "choices": [],
+                    "usage": final_usage.take().unwrap_or_default()
+                });
+                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&usage_chunk).unwrap_or_default())));
+            }
             yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
         }
     };
@@ -325,12 +305,13 @@ pub fn create_legacy_sse_stream<S, E>(
     model: String,
     session_id: String,
     message_count: usize,
+    include_usage: bool,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> 
 where
     S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
     E: std::fmt::Display + Send + 'static,
 {
-    let mut buffer = BytesMut::new();
+    let mut decoder = SseLineDecoder::new();
     let charset = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
     let mut rng = rand::thread_rng();
     let random_str: String = (0..28).map(|_| {
@@ -349,53 +330,8 @@ where
         loop {
             tokio::select! {
                 item = gemini_stream.next() => {
-                    match item {
-                        Some(Ok(bytes)) => {
-                            buffer.extend_from_slice(&bytes);
-                            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                                let line_raw = buffer.split_to(pos + 1);
-                                if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                                    let line = line_str.trim();
-                                    if line.is_empty() { continue; }
-                                    if line.starts_with("data: ") {
-                                        let json_part = line.trim_start_matches("data: ").trim();
-                                        if json_part == "[DONE]" { continue; }
-                                        if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
-                                            let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
-                                            if let Some(u) = actual_data.get("usageMetadata") { final_usage = extract_usage_metadata(u); }
-
-                                            let mut content_out = String::new();
-                                            if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                                                if let Some(candidate) = candidates.get(0) {
-                                                    if let Some(parts) = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
-                                                        for part in parts {
-                                                            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                                                                content_out.push_str(text);
-                                                            }
-                                                            if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
-                                                                store_thought_signature(sig, &session_id, message_count);
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-
-                                            let finish_reason = actual_data.get("candidates").and_then(|c| c.as_array()).and_then(|c| c.get(0)).and_then(|c| c.get("finishReason")).and_then(|f| f.as_str()).map(|f| match f {
-                                                "STOP" => "stop", "MAX_TOKENS" => "length", "SAFETY" => "content_filter", _ => f,
-                                            });
-
-                                            let mut legacy_chunk = json!({
-                                                "id": &stream_id, "object": "text_completion", "created": created_ts, "model": &model,
-                                                "choices": [{ "text": content_out, "index": 0, "logprobs": null, "finish_reason": finish_reason }]
-                                            });
-                                            if let Some(ref usage) = final_usage { legacy_chunk["usage"] = serde_json::to_value(usage).unwrap(); }
-                                            if finish_reason.is_some() { final_usage = None; }
-                                            yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&legacy_chunk).unwrap_or_default())));
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                    let (lines, ended) = match item {
+                        Some(Ok(bytes)) => (decoder.push(&bytes), false),
                         Some(Err(e)) => {
                             use crate::proxy::mappers::error_classifier::classify_stream_error;
                             let (error_type, user_msg, i18n_key) = classify_stream_error(&e);
@@ -409,13 +345,70 @@ where
                             error_occurred = true;
                             break;
                         }
-                        None => break,
+                        // [FIX #1732] 流结束时冲刷未以换行结尾的最后一行，避免丢失尾块
+                        None => (decoder.finish().into_iter().collect::<Vec<_>>(), true),
+                    };
+                    for line_str in lines {
+                        let line = line_str.trim();
+                        if line.is_empty() { continue; }
+                        if line.starts_with("data: ") {
+                            let json_part = line.trim_start_matches("data: ").trim();
+                            if json_part == "[DONE]" { continue; }
+                            if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
+                                let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
+                                if let Some(u) = actual_data.get("usageMetadata") { final_usage = Some(usage_from_metadata(u)); }
+
+                                let mut content_out = String::new();
+                                if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
+                                    if let Some(candidate) = candidates.get(0) {
+                                        if let Some(parts) = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
+                                            for part in parts {
+                                                // 思考内容不属于补全文本
+                                                let is_thought = part.get("thought").and_then(|t| t.as_bool()).unwrap_or(false);
+                                                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                                    if !is_thought {
+                                                        content_out.push_str(text);
+                                                    }
+                                                }
+                                                if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
+                                                    store_thought_signature(sig, &session_id, message_count);
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+
+                                let finish_reason = actual_data.get("candidates").and_then(|c| c.as_array()).and_then(|c| c.get(0)).and_then(|c| c.get("finishReason")).and_then(|f| f.as_str()).map(|f| to_openai_finish_reason(f, false));
+                                // 仅含思考内容的分片不下发
+                                if content_out.is_empty() && finish_reason.is_none() {
+                                    continue;
+                                }
+
+                                let mut legacy_chunk = json!({
+                                    "id": &stream_id, "object": "text_completion", "created": created_ts, "model": &model,
+                                    "choices": [{ "text": content_out, "index": 0, "logprobs": null, "finish_reason": finish_reason }]
+                                });
+                                if !include_usage {
+                                    if let Some(ref usage) = final_usage { legacy_chunk["usage"] = serde_json::to_value(usage).unwrap(); }
+                                    if finish_reason.is_some() { final_usage = None; }
+                                }
+                                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&legacy_chunk).unwrap_or_default())));
+                            }
+                        }
                     }
+                    if ended { break; }
                 }
                 _ = heartbeat_interval.tick() => { yield Ok::<Bytes, String>(Bytes::from(": ping\n\n")); }
             }
         }
         if !error_occurred {
+            if include_usage {
+                let usage_chunk = json!({
+                    "id": &stream_id, "object": "text_completion", "created": created_ts, "model": &model,
+                    "choices": [], "usage": final_usage.take().unwrap_or_default()
+                });
+                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&usage_chunk).unwrap_or_default())));
+            }
             yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
         }
     };
@@ -432,7 +425,7 @@ where
     S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
     E: std::fmt::Display + Send + 'static,
 {
-    let mut buffer = BytesMut::new();
+    let mut decoder = SseLineDecoder::new();
     let charset = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
     let mut rng = rand::thread_rng();
     let random_str: String = (0..24).map(|_| {
@@ -441,6 +434,7 @@ where
     }).collect();
     let response_id = format!("resp-{}", random_str);
     let item_id = format!("item-{}", &random_str[..16]);
+    let hide_reasoning = crate::proxy::config::get_thinking_budget_config().hide_openai_reasoning;
 
     let stream = async_stream::stream! {
         // 1. response.created
@@ -476,122 +470,151 @@ where
 
         let mut emitted_tool_calls = std::collections::HashSet::new();
         let mut accumulated_text = String::new();
+        let mut stream_error: Option<String> = None;
         let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(15));
         heartbeat_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
         loop {
             tokio::select! {
                 item = gemini_stream.next() => {
-                    match item {
-                        Some(Ok(bytes)) => {
-                            buffer.extend_from_slice(&bytes);
-                            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                                let line_raw = buffer.split_to(pos + 1);
-                                if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                                    let line = line_str.trim();
-                                    if line.is_empty() || !line.starts_with("data: ") { continue; }
-                                    let json_part = line.trim_start_matches("data: ").trim();
-                                    if json_part == "[DONE]" { continue; }
-
-                                    if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
-                                        let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
-                                        if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                                            if candidates.len() > 0 {
-                                                tracing::debug!("[Codex-Stream-Debug] Raw Candidate: {:?}", candidates[0]);
-                                            }
-                                            if let Some(candidate) = candidates.get(0) {
-                                                if let Some(parts) = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
-                                                    for part in parts {
-                                                        let is_thought = part.get("thought").and_then(|v| v.as_bool()).unwrap_or(false);
-                                                        if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                                                            if !text.is_empty() {
-                                                                if is_thought {
-                                                                    // 思维链内容 → response.reasoning.delta
-                                                                    let reasoning_ev = json!({
-                                                                        "type": "response.reasoning.delta",
-                                                                        "item_id": &item_id,
-                                                                        "output_index": 0,
-                                                                        "content_index": 0,
-                                                                        "delta": text
-                                                                    });
-                                                                    yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&reasoning_ev).unwrap())));
-                                                                } else {
-                                                                    accumulated_text.push_str(text);
-                                                                    // 4. response.output_text.delta - 文本增量
-                                                                    let delta_ev = json!({
-                                                                        "type": "response.output_text.delta",
-                                                                        "item_id": &item_id,
-                                                                        "output_index": 0,
-                                                                        "content_index": 0,
-                                                                        "delta": text
-                                                                    });
-                                                                    yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&delta_ev).unwrap())));
-                                                                }
-                                                            }
-                                                        }
-                                                        if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
-                                                            store_thought_signature(sig, &session_id, message_count);
-                                                        }
-                                                        if let Some(func_call) = part.get("functionCall") {
-                                                            let call_key = serde_json::to_string(func_call).unwrap_or_default();
-                                                            if !emitted_tool_calls.contains(&call_key) {
-                                                                emitted_tool_calls.insert(call_key);
-                                                            }
-                                                        }
-                                                    }
-
-                                                }
-
-                                                // 处理 groundingMetadata (搜索引文)
-                                                if let Some(grounding) = candidate.get("groundingMetadata") {
-                                                    let mut grounding_text = String::new();
-                                                    if let Some(queries) = grounding.get("webSearchQueries").and_then(|q| q.as_array()) {
-                                                        let query_list: Vec<&str> = queries.iter().filter_map(|v| v.as_str()).collect();
-                                                        if !query_list.is_empty() {
-                                                            grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
-                                                            grounding_text.push_str(&query_list.join(", "));
-                                                        }
-                                                    }
-                                                    if let Some(chunks) = grounding.get("groundingChunks").and_then(|c| c.as_array()) {
-                                                        let mut links = Vec::new();
-                                                        for (i, chunk) in chunks.iter().enumerate() {
-                                                            if let Some(web) = chunk.get("web") {
-                                                                let title = web.get("title").and_then(|v| v.as_str()).unwrap_or("网页来源");
-                                                                let uri = web.get("uri").and_then(|v| v.as_str()).unwrap_or("#");
-                                                                links.push(format!("[{}] [{}]({})", i + 1, title, uri));
-                                                            }
-                                                        }
-                                                        if !links.is_empty() {
-                                                            grounding_text.push_str("\n\n**🌐 来源引文：**\n");
-                                                            grounding_text.push_str(&links.join("\n"));
+                    let (lines, ended) = match item {
+                        Some(Ok(bytes)) => (decoder.push(&bytes), false),
+                        Some(Err(e)) => {
+                            tracing::error!("Codex Stream Error: {}", e);
+                            stream_error = Some(e.to_string());
+                            break;
+                        }
+                        // [FIX #1732] 流结束时冲刷未以换行结尾的最后一行，避免丢失尾块
+                        None => (decoder.finish().into_iter().collect::<Vec<_>>(), true),
+                    };
+                    for line_str in lines {
+                        let line = line_str.trim();
+                        if line.is_empty() || !line.starts_with("data: ") { continue; }
+                        let json_part = line.trim_start_matches("data: ").trim();
+                        if json_part == "[DONE]" { continue; }
+
+                        if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
+                            let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
+                            if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
+                                if candidates.len() > 0 {
+                                    tracing::debug!("[Codex-Stream-Debug] Raw Candidate: {:?}", candidates[0]);
+                                }
+                                if let Some(candidate) = candidates.get(0) {
+                                    if let Some(parts) = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
+                                        for part in parts {
+                                            let is_thought = part.get("thought").and_then(|v| v.as_bool()).unwrap_or(false);
+                                            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                                if !text.is_empty() {
+                                                    if is_thought {
+                                                        if hide_reasoning {
+                                                            continue;
                                                         }
-                                                    }
-                                                    if !grounding_text.is_empty() {
-                                                        accumulated_text.push_str(&grounding_text);
+                                                        // 思维链内容 → response.reasoning.delta
+                                                        let reasoning_ev = json!({
+                                                            "type": "response.reasoning.delta",
+                                                            "item_id": &item_id,
+                                                            "output_index": 0,
+                                                            "content_index": 0,
+                                                            "delta": text
+                                                        });
+                                                        yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&reasoning_ev).unwrap())));
+                                                    } else {
+                                                        accumulated_text.push_str(text);
+                                                        // 4. response.output_text.delta - 文本增量
                                                         let delta_ev = json!({
                                                             "type": "response.output_text.delta",
                                                             "item_id": &item_id,
                                                             "output_index": 0,
                                                             "content_index": 0,
-                                                            "delta": grounding_text
+                                                            "delta": text
                                                         });
                                                         yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&delta_ev).unwrap())));
                                                     }
                                                 }
                                             }
+                                            if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
+                                                store_thought_signature(sig, &session_id, message_count);
+                                            }
+                                            if let Some(func_call) = part.get("functionCall") {
+                                                let call_key = serde_json::to_string(func_call).unwrap_or_default();
+                                                if !emitted_tool_calls.contains(&call_key) {
+                                                    emitted_tool_calls.insert(call_key);
+                                                }
+                                            }
+                                        }
+
+                                    }
+
+                                    // 处理 groundingMetadata (搜索引文)
+                                    if let Some(grounding) = candidate.get("groundingMetadata") {
+                                        let mut grounding_text = String::new();
+                                        if let Some(queries) = grounding.get("webSearchQueries").and_then(|q| q.as_array()) {
+                                            let query_list: Vec<&str> = queries.iter().filter_map(|v| v.as_str()).collect();
+                                            if !query_list.is_empty() {
+                                                grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
+                                                grounding_text.push_str(&query_list.join(", "));
+                                            }
+                                        }
+                                        if let Some(chunks) = grounding.get("groundingChunks").and_then(|c| c.as_array()) {
+                                            let mut links = Vec::new();
+                                            for (i, chunk) in chunks.iter().enumerate() {
+                                                if let Some(web) = chunk.get("web") {
+                                                    let title = web.get("title").and_then(|v| v.as_str()).unwrap_or("网页来源");
+                                                    let uri = web.get("uri").and_then(|v| v.as_str()).unwrap_or("#");
+                                                    links.push(format!("[{}] [{}]({})", i + 1, title, uri));
+                                                }
+                                            }
+                                            if !links.is_empty() {
+                                                grounding_text.push_str("\n\n**🌐 来源引文：**\n");
+                                                grounding_text.push_str(&links.join("\n"));
+                                            }
+                                        }
+                                        if !grounding_text.is_empty() {
+                                            accumulated_text.push_str(&grounding_text);
+                                            let delta_ev = json!({
+                                                "type": "response.output_text.delta",
+                                                "item_id": &item_id,
+                                                "output_index": 0,
+                                                "content_index": 0,
+                                                "delta": grounding_text
+                                            });
+                                            yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&delta_ev).unwrap())));
                                         }
                                     }
                                 }
                             }
                         }
-                        Some(Err(_)) => break,
-                        None => break,
                     }
+                    if ended { break; }
                 }
                 _ = heartbeat_interval.tick() => { yield Ok::<Bytes, String>(Bytes::from(": ping\n\n")); }
             }
         }
 
+        // [NEW] 上游中途出错时发送 response.failed，而不是把截断的输出标记为 completed
+        if let Some(err) = stream_error {
+            use crate::proxy::mappers::error_classifier::classify_stream_error;
+            let (error_type, user_msg, _) = classify_stream_error(&err);
+            let failed_ev = json!({
+                "type": "response.failed",
+                "response": {
+                    "id": &response_id,
+                    "object": "response",
+                    "status": "failed",
+                    "error": { "code": error_type, "message": user_msg },
+                    "output": [{
+                        "id": &item_id,
+                        "type": "message",
+                        "role": "assistant",
+                        "status": "incomplete",
+                        "content": [{ "type": "output_text", "text": &accumulated_text }]
+                    }]
+                }
+            });
+            yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&failed_ev).unwrap())));
+            return;
+        }
+
         // 5. response.output_text.done - 文本完成
         let text_done = json!({
             "type": "response.output_text.done",
@@ -699,7 +722,8 @@ mod tests {
             gemini_stream,
             "gemini-1.5-flash".to_string(),
             "test-session".to_string(),
-            0
+            0,
+            false,
         );
 
         let mut chunks = Vec::new();
@@ -744,4 +768,37 @@ mod tests {
         assert!(found_usage, "Usage should be found in the last chunk");
         assert!(found_finish, "Finish reason should be strictly 'stop'");
     }
+
+    #[tokio::test]
+    async fn test_openai_streaming_include_usage_emits_usage_chunk() {
+        let chunk = json!({
+            "candidates": [{ "finishReason": "STOP", "content": { "parts": [{ "text": "Hi" }] } }],
+            "usageMetadata": { "promptTokenCount": 5, "candidatesTokenCount": 2, "thoughtsTokenCount": 3, "totalTokenCount": 10 }
+        });
+        let items: Vec<Result<Bytes, reqwest::Error>> = vec![Ok(Bytes::from(format!("data: {}\n\n", chunk)))];
+
+        let mut openai_stream = create_openai_sse_stream(
+            Box::pin(stream::iter(items)),
+            "gemini-3-flash".to_string(),
+            "test-session".to_string(),
+            0,
+            true,
+        );
+
+        let mut events = Vec::new();
+        while let Some(Ok(bytes)) = openai_stream.next().await {
+            let s = String::from_utf8_lossy(&bytes).to_string();
+            events.extend(s.lines().filter_map(|l| l.strip_prefix("data: ")).map(|l| l.to_string()));
+        }
+
+        assert_eq!(events.last().map(|s| s.as_str()), Some("[DONE]"));
+        let usage_chunk: Value = serde_json::from_str(&events[events.len() - 2]).unwrap();
+        assert_eq!(usage_chunk["choices"], json!([]));
+        assert_eq!(usage_chunk["usage"]["completion_tokens"], 5);
+        assert_eq!(usage_chunk["usage"]["completion_tokens_details"]["reasoning_tokens"], 3);
+
+        let finish_chunk: Value = serde_json::from_str(&events[events.len() - 3]).unwrap();
+        assert_eq!(finish_chunk["choices"][0]["finish_reason"], "stop");
+        assert!(finish_chunk.get("usage").is_none());
+    }
 }
@@ -0,0 +1,670 @@
+// MCP 工具桥接
+// 连接用户配置的 MCP 服务器 (Streamable HTTP 传输)，把其工具以合成工具 `mcp__<server>__<tool>` 的形式
+// 暴露给任意协议。服务端工具模式下由代理把工具注入请求并执行模型发起的调用 (循环见 handlers::mcp_bridge)。
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::proxy::config::{McpBridgeConfig, McpServerConfig};
+
+const PROTOCOL_VERSION: &str = "2025-03-26";
+const TOOL_PREFIX: &str = "mcp__";
+/// 工具列表缓存时间
+const TOOLS_TTL: Duration = Duration::from_secs(300);
+/// Gemini functionDeclarations 名称长度上限
+const MAX_TOOL_NAME_LEN: usize = 64;
+const MAX_LIST_PAGES: usize = 20;
+const SESSION_EXPIRED: &str = "MCP session expired";
+
+/// 以合成工具形式暴露的 MCP 工具
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgedTool {
+    /// 合成工具名 (mcp__<server>__<tool>)
+    pub name: String,
+    pub server: String,
+    /// MCP 服务器上的原始工具名
+    pub tool: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+impl BridgedTool {
+    pub fn to_claude_tool(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "input_schema": self.input_schema,
+        })
+    }
+
+    pub fn to_openai_tool(&self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.input_schema,
+            }
+        })
+    }
+}
+
+/// 工具调用结果 (已拍平为文本)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallOutput {
+    pub text: String,
+    pub is_error: bool,
+}
+
+impl ToolCallOutput {
+    fn error(message: impl Into<String>) -> Self {
+        Self { text: message.into(), is_error: true }
+    }
+}
+
+/// 单个 MCP 服务器的会话状态
+/// 只在读写会话 ID / 初始化状态 / 工具缓存时短暂加锁，HTTP 往返期间不持锁，
+/// 慢工具调用不会阻塞同一服务器的其他调用与工具列表读取
+struct ServerSession {
+    url: String,
+    generation: u64,
+    session_id: Option<String>,
+    initialized: bool,
+    next_id: u64,
+    tools: Vec<BridgedTool>,
+    fetched_at: Option<Instant>,
+}
+
+impl ServerSession {
+    fn new(url: &str, generation: u64) -> Self {
+        Self {
+            url: url.to_string(),
+            generation,
+            session_id: None,
+            initialized: false,
+            next_id: 0,
+            tools: Vec::new(),
+            fetched_at: None,
+        }
+    }
+}
+
+fn config() -> &'static RwLock<McpBridgeConfig> {
+    static CONFIG: OnceLock<RwLock<McpBridgeConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(McpBridgeConfig::default()))
+}
+
+/// 配置版本号，变化后已有会话在下次使用时重新初始化
+static CONFIG_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 单个服务器的会话句柄：init_lock 仅串行化初始化握手，避免并发请求重复 initialize
+struct ServerHandle {
+    session: Mutex<ServerSession>,
+    init_lock: tokio::sync::Mutex<()>,
+}
+
+fn sessions() -> &'static Mutex<HashMap<String, Arc<ServerHandle>>> {
+    static SESSIONS: OnceLock<Mutex<HashMap<String, Arc<ServerHandle>>>> = OnceLock::new();
+    SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+/// 更新 MCP 桥接配置 (启动与配置热更新时调用)
+pub fn update_mcp_bridge_config(new_config: McpBridgeConfig) {
+    if let Ok(mut current) = config().write() {
+        if *current != new_config {
+            CONFIG_GENERATION.fetch_add(1, Ordering::SeqCst);
+        }
+        *current = new_config;
+    }
+}
+
+pub fn get_mcp_bridge_config() -> McpBridgeConfig {
+    config().read().map(|c| c.clone()).unwrap_or_default()
+}
+
+/// 是否启用服务端工具模式 (至少有一个可用服务器)
+pub fn server_side_tools_enabled() -> bool {
+    config()
+        .read()
+        .map(|c| c.enabled && c.server_side_tools && c.servers.iter().any(|s| s.enabled))
+        .unwrap_or(false)
+}
+
+/// 校验服务器名与地址
+pub fn validate_mcp_bridge_config(cfg: &McpBridgeConfig) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for server in &cfg.servers {
+        let name = server.name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!(
+                "Invalid MCP server name '{}' (letters, digits, '_' and '-' only)",
+                server.name
+            ));
+        }
+        if !seen.insert(name.to_string()) {
+            return Err(format!("Duplicate MCP server name '{}'", name));
+        }
+        if !server.url.starts_with("http://") && !server.url.starts_with("https://") {
+            return Err(format!("Invalid MCP server url '{}' ({})", server.url, name));
+        }
+    }
+    Ok(())
+}
+
+/// 是否为桥接的合成工具名
+pub fn is_bridged_tool(name: &str) -> bool {
+    name.starts_with(TOOL_PREFIX)
+}
+
+/// 生成合成工具名：非法字符替换为 '_'，超长时截断
+pub fn synthetic_tool_name(server: &str, tool: &str) -> String {
+    let sanitized: String = tool
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    let mut name = format!("{}{}__{}", TOOL_PREFIX, server, sanitized);
+    name.truncate(MAX_TOOL_NAME_LEN);
+    name
+}
+
+/// 从 SSE 响应中取出指定 id 的 JSON-RPC 消息
+fn parse_sse_message(body: &str, id: u64) -> Option<Value> {
+    body.replace("\r\n", "\n").split("\n\n").find_map(|event| {
+        let data: Vec<&str> = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|d| d.trim_start())
+            .collect();
+        let message: Value = serde_json::from_str(&data.join("\n")).ok()?;
+        (message.get("id").and_then(|v| v.as_u64()) == Some(id)).then_some(message)
+    })
+}
+
+/// 发送 JSON-RPC 消息，返回响应与服务器下发的新会话 ID
+async fn post(
+    server: &McpServerConfig,
+    session_id: Option<&str>,
+    payload: &Value,
+    timeout: Duration,
+) -> Result<(reqwest::Response, Option<String>), String> {
+    let mut request = http_client()
+        .post(&server.url)
+        .timeout(timeout)
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json, text/event-stream")
+        .json(payload);
+    for (key, value) in &server.headers {
+        request = request.header(key.as_str(), value.as_str());
+    }
+    if let Some(session_id) = session_id {
+        request = request.header("Mcp-Session-Id", session_id);
+    }
+
+    let response = request.send().await.map_err(|e| format!("request failed: {}", e))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND && session_id.is_some() {
+        return Err(SESSION_EXPIRED.to_string());
+    }
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("HTTP {}: {}", status, text));
+    }
+    let new_session_id = response
+        .headers()
+        .get("mcp-session-id")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+    Ok((response, new_session_id))
+}
+
+/// 加锁读取会话；配置变化后重置
+fn lock_session<'a>(
+    handle: &'a ServerHandle,
+    server: &McpServerConfig,
+) -> std::sync::MutexGuard<'a, ServerSession> {
+    let mut session = handle.session.lock().unwrap_or_else(|e| e.into_inner());
+    let generation = CONFIG_GENERATION.load(Ordering::SeqCst);
+    if session.generation != generation || session.url != server.url {
+        *session = ServerSession::new(&server.url, generation);
+    }
+    session
+}
+
+/// 在锁内分配请求 ID 并取出会话 ID 快照
+fn begin_request(handle: &ServerHandle, server: &McpServerConfig) -> (u64, Option<String>) {
+    let mut session = lock_session(handle, server);
+    session.next_id += 1;
+    (session.next_id, session.session_id.clone())
+}
+
+/// 记录服务器下发的会话 ID；会话过期时重置 (仅当期间没有其他请求重新建立会话)
+fn finish_request(
+    handle: &ServerHandle,
+    server: &McpServerConfig,
+    used_session_id: Option<&str>,
+    outcome: Result<Option<String>, &str>,
+) {
+    let mut session = lock_session(handle, server);
+    match outcome {
+        Ok(Some(new_session_id)) => session.session_id = Some(new_session_id),
+        Ok(None) => {}
+        Err(e) if e == SESSION_EXPIRED && session.session_id.as_deref() == used_session_id => {
+            let generation = session.generation;
+            *session = ServerSession::new(&server.url, generation);
+        }
+        Err(_) => {}
+    }
+}
+
+async fn rpc(
+    server: &McpServerConfig,
+    handle: &ServerHandle,
+    method: &str,
+    params: Value,
+    timeout: Duration,
+) -> Result<Value, String> {
+    let (id, session_id) = begin_request(handle, server);
+    let payload = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    let response = match post(server, session_id.as_deref(), &payload, timeout).await {
+        Ok((response, new_session_id)) => {
+            finish_request(handle, server, session_id.as_deref(), Ok(new_session_id));
+            response
+        }
+        Err(e) => {
+            finish_request(handle, server, session_id.as_deref(), Err(&e));
+            return Err(e);
+        }
+    };
+
+    let is_sse = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with("text/event-stream"))
+        .unwrap_or(false);
+    let text = response.text().await.map_err(|e| format!("read failed: {}", e))?;
+    let message = if is_sse {
+        parse_sse_message(&text, id).ok_or_else(|| format!("no response for '{}' in event stream", method))?
+    } else {
+        serde_json::from_str::<Value>(&text).map_err(|e| format!("invalid JSON-RPC response: {}", e))?
+    };
+
+    if let Some(error) = message.get("error") {
+        return Err(format!(
+            "{} ({})",
+            error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error"),
+            error.get("code").cloned().unwrap_or(Value::Null)
+        ));
+    }
+    Ok(message.get("result").cloned().unwrap_or(Value::Null))
+}
+
+async fn ensure_initialized(
+    server: &McpServerConfig,
+    handle: &ServerHandle,
+    timeout: Duration,
+) -> Result<(), String> {
+    if lock_session(handle, server).initialized {
+        return Ok(());
+    }
+    let _init = handle.init_lock.lock().await;
+    // 等待期间可能已由其他请求完成初始化
+    if lock_session(handle, server).initialized {
+        return Ok(());
+    }
+    let params = json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "antigravity-manager", "version": env!("CARGO_PKG_VERSION") }
+    });
+    rpc(server, handle, "initialize", params, timeout).await?;
+    let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    let session_id = lock_session(handle, server).session_id.clone();
+    post(server, session_id.as_deref(), &notification, timeout).await?;
+    lock_session(handle, server).initialized = true;
+    Ok(())
+}
+
+/// 发送请求；会话过期 (404) 时重新初始化并重试一次
+async fn request(
+    server: &McpServerConfig,
+    handle: &ServerHandle,
+    method: &str,
+    params: Value,
+    timeout: Duration,
+) -> Result<Value, String> {
+    for attempt in 0..2 {
+        let result = match ensure_initialized(server, handle, timeout).await {
+            Ok(()) => rpc(server, handle, method, params.clone(), timeout).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(e) if e == SESSION_EXPIRED && attempt == 0 => {
+                tracing::debug!("[MCP-Bridge] Session expired on '{}', re-initializing", server.name);
+            }
+            other => return other,
+        }
+    }
+    Err(SESSION_EXPIRED.to_string())
+}
+
+fn session_handle(server: &McpServerConfig) -> Arc<ServerHandle> {
+    let generation = CONFIG_GENERATION.load(Ordering::SeqCst);
+    let mut map = sessions().lock().unwrap_or_else(|e| e.into_inner());
+    map.entry(server.name.clone())
+        .or_insert_with(|| {
+            Arc::new(ServerHandle {
+                session: Mutex::new(ServerSession::new(&server.url, generation)),
+                init_lock: tokio::sync::Mutex::new(()),
+            })
+        })
+        .clone()
+}
+
+/// 缓存中的工具列表 (未过期时)
+fn cached_tools(handle: &ServerHandle, server: &McpServerConfig) -> Option<Vec<BridgedTool>> {
+    let session = lock_session(handle, server);
+    let fresh = session.fetched_at.is_some_and(|fetched_at| fetched_at.elapsed() < TOOLS_TTL);
+    fresh.then(|| session.tools.clone())
+}
+
+async fn server_tools(server: &McpServerConfig, timeout: Duration) -> Result<Vec<BridgedTool>, String> {
+    let handle = session_handle(server);
+    if let Some(tools) = cached_tools(&handle, server) {
+        return Ok(tools);
+    }
+
+    let mut tools = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_LIST_PAGES {
+        let params = match &cursor {
+            Some(c) => json!({ "cursor": c }),
+            None => json!({}),
+        };
+        let result = request(server, &handle, "tools/list", params, timeout).await?;
+        for tool in result.get("tools").and_then(|t| t.as_array()).into_iter().flatten() {
+            let Some(tool_name) = tool.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            tools.push(BridgedTool {
+                name: synthetic_tool_name(&server.name, tool_name),
+                server: server.name.clone(),
+                tool: tool_name.to_string(),
+                description: tool
+                    .get("description")
+                    .and_then(|d| d.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                input_schema: tool
+                    .get("inputSchema")
+                    .cloned()
+                    .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+            });
+        }
+        cursor = result.get("nextCursor").and_then(|c| c.as_str()).map(String::from);
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    let mut session = lock_session(&handle, server);
+    session.tools = tools.clone();
+    session.fetched_at = Some(Instant::now());
+    Ok(tools)
+}
+
+/// 汇总所有启用服务器的工具；单个服务器失败时跳过并记录
+pub async fn list_tools() -> Vec<BridgedTool> {
+    let cfg = get_mcp_bridge_config();
+    if !cfg.enabled {
+        return Vec::new();
+    }
+    let timeout = Duration::from_secs(cfg.timeout_secs.max(1));
+    let mut all = Vec::new();
+    for server in cfg.servers.iter().filter(|s| s.enabled) {
+        match server_tools(server, timeout).await {
+            Ok(tools) => all.extend(tools),
+            Err(e) => tracing::warn!("[MCP-Bridge] Failed to list tools from '{}': {}", server.name, e),
+        }
+    }
+    all
+}
+
+/// MCP tools/call 结果拍平为文本
+fn format_call_result(result: &Value) -> ToolCallOutput {
+    let is_error = result.get("isError").and_then(|v| v.as_bool()).unwrap_or(false);
+    let parts: Vec<String> = result
+        .get("content")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .map(|item| match item.get("type").and_then(|t| t.as_str()) {
+            Some("text") => item.get("text").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+            Some("image") | Some("audio") => format!(
+                "[{} omitted: {}]",
+                item["type"].as_str().unwrap_or_default(),
+                item.get("mimeType").and_then(|m| m.as_str()).unwrap_or("unknown")
+            ),
+            Some("resource") => item
+                .pointer("/resource/text")
+                .and_then(|t| t.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| item["resource"].to_string()),
+            _ => item.to_string(),
+        })
+        .collect();
+
+    let text = if parts.is_empty() {
+        result.get("structuredContent").map(|v| v.to_string()).unwrap_or_default()
+    } else {
+        parts.join("\n")
+    };
+    ToolCallOutput { text, is_error }
+}
+
+/// 按合成工具名查找工具：优先读取已缓存的工具列表，仅在缓存中找不到时重新拉取
+async fn find_tool(cfg: &McpBridgeConfig, name: &str) -> Option<BridgedTool> {
+    let cached = cfg.servers.iter().filter(|s| s.enabled).find_map(|server| {
+        let handle = session_handle(server);
+        let session = lock_session(&handle, server);
+        session.tools.iter().find(|t| t.name == name).cloned()
+    });
+    match cached {
+        Some(tool) => Some(tool),
+        None => list_tools().await.into_iter().find(|t| t.name == name),
+    }
+}
+
+/// 执行合成工具调用
+pub async fn call_tool(name: &str, arguments: Value) -> ToolCallOutput {
+    let cfg = get_mcp_bridge_config();
+    let Some(tool) = find_tool(&cfg, name).await else {
+        return ToolCallOutput::error(format!("Unknown MCP tool: {}", name));
+    };
+    let Some(server) = cfg.servers.iter().find(|s| s.enabled && s.name == tool.server) else {
+        return ToolCallOutput::error(format!("MCP server '{}' is not available", tool.server));
+    };
+
+    let timeout = Duration::from_secs(cfg.timeout_secs.max(1));
+    let arguments = if arguments.is_object() { arguments } else { json!({}) };
+    let handle = session_handle(server);
+    let params = json!({ "name": tool.tool, "arguments": arguments });
+    match request(server, &handle, "tools/call", params, timeout).await {
+        Ok(result) => format_call_result(&result),
+        Err(e) => {
+            tracing::warn!("[MCP-Bridge] Tool '{}' on '{}' failed: {}", tool.tool, server.name, e);
+            ToolCallOutput::error(format!("MCP tool call failed: {}", e))
+        }
+    }
+}
+
+fn existing_tool_names(body: &Value, pointer: &str) -> Vec<String> {
+    body.get("tools")
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t.pointer(pointer).and_then(|n| n.as_str()).map(String::from))
+        .collect()
+}
+
+/// 向 Claude 请求注入合成工具 (与客户端工具重名时跳过)
+pub fn append_claude_tools(body: &mut Value, tools: &[BridgedTool]) {
+    let existing = existing_tool_names(body, "/name");
+    let additions: Vec<Value> = tools
+        .iter()
+        .filter(|t| !existing.contains(&t.name))
+        .map(BridgedTool::to_claude_tool)
+        .collect();
+    append_tools(body, additions);
+}
+
+/// 向 OpenAI 请求注入合成工具 (与客户端工具重名时跳过)
+pub fn append_openai_tools(body: &mut Value, tools: &[BridgedTool]) {
+    let existing = existing_tool_names(body, "/function/name");
+    let additions: Vec<Value> = tools
+        .iter()
+        .filter(|t| !existing.contains(&t.name))
+        .map(BridgedTool::to_openai_tool)
+        .collect();
+    append_tools(body, additions);
+}
+
+fn append_tools(body: &mut Value, additions: Vec<Value>) {
+    if additions.is_empty() {
+        return;
+    }
+    let Some(obj) = body.as_object_mut() else {
+        return;
+    };
+    match obj.get_mut("tools").and_then(|t| t.as_array_mut()) {
+        Some(tools) => tools.extend(additions),
+        None => {
+            obj.insert("tools".to_string(), Value::Array(additions));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str) -> BridgedTool {
+        BridgedTool {
+            name: synthetic_tool_name("files", name),
+            server: "files".to_string(),
+            tool: name.to_string(),
+            description: "read a file".to_string(),
+            input_schema: json!({ "type": "object", "properties": { "path": { "type": "string" } } }),
+        }
+    }
+
+    #[test]
+    fn test_synthetic_tool_name() {
+        assert_eq!(synthetic_tool_name("files", "read_file"), "mcp__files__read_file");
+        assert_eq!(synthetic_tool_name("gh", "repo.search/v2"), "mcp__gh__repo_search_v2");
+        assert_eq!(synthetic_tool_name("s", &"x".repeat(100)).len(), MAX_TOOL_NAME_LEN);
+        assert!(is_bridged_tool("mcp__files__read_file"));
+        assert!(!is_bridged_tool("read_file"));
+    }
+
+    #[test]
+    fn test_parse_sse_message_matches_id() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n\
+                    event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":3,\"result\":{\"tools\":[]}}\n\n";
+        let message = parse_sse_message(body, 3).unwrap();
+        assert_eq!(message["result"]["tools"], json!([]));
+        assert!(parse_sse_message(body, 4).is_none());
+    }
+
+    #[test]
+    fn test_format_call_result() {
+        let result = json!({
+            "content": [
+                { "type": "text", "text": "line 1" },
+                { "type": "image", "data": "AAAA", "mimeType": "image/png" }
+            ],
+            "isError": true
+        });
+        let output = format_call_result(&result);
+        assert!(output.is_error);
+        assert_eq!(output.text, "line 1\n[image omitted: image/png]");
+
+        let structured = format_call_result(&json!({ "content": [], "structuredContent": { "ok": 1 } }));
+        assert_eq!(structured.text, "{\"ok\":1}");
+    }
+
+    #[test]
+    fn test_append_tools_skips_existing_names() {
+        let mut body = json!({ "tools": [{ "name": "mcp__files__read_file", "input_schema": {} }] });
+        append_claude_tools(&mut body, &[tool("read_file"), tool("list_dir")]);
+        let names = existing_tool_names(&body, "/name");
+        assert_eq!(names, vec!["mcp__files__read_file", "mcp__files__list_dir"]);
+
+        let mut body = json!({ "messages": [] });
+        append_openai_tools(&mut body, &[tool("read_file")]);
+        assert_eq!(body["tools"][0]["function"]["parameters"]["type"], "object");
+    }
+
+    #[tokio::test]
+    async fn test_find_tool_uses_cached_list() {
+        let server = McpServerConfig {
+            name: "cached-files".to_string(),
+            url: "http://127.0.0.1:9/mcp".to_string(),
+            headers: HashMap::new(),
+            enabled: true,
+        };
+        let cfg = McpBridgeConfig {
+            enabled: true,
+            servers: vec![server.clone()],
+            ..Default::default()
+        };
+        let mut cached = tool("read_file");
+        cached.name = synthetic_tool_name("cached-files", "read_file");
+        cached.server = "cached-files".to_string();
+        {
+            let handle = session_handle(&server);
+            let mut session = lock_session(&handle, &server);
+            session.tools = vec![cached.clone()];
+            session.fetched_at = Some(Instant::now());
+        }
+
+        // 命中缓存时不访问服务器 (该地址不可达)
+        assert_eq!(find_tool(&cfg, &cached.name).await, Some(cached));
+        // 请求 ID 在锁内分配，释放锁后才发起 HTTP
+        let handle = session_handle(&server);
+        let (first, _) = begin_request(&handle, &server);
+        let (second, _) = begin_request(&handle, &server);
+        assert_eq!(second, first + 1);
+    }
+
+    #[test]
+    fn test_validate_mcp_bridge_config() {
+        let server = |name: &str, url: &str| McpServerConfig {
+            name: name.to_string(),
+            url: url.to_string(),
+            headers: HashMap::new(),
+            enabled: true,
+        };
+        let mut cfg = McpBridgeConfig {
+            servers: vec![server("files", "http://127.0.0.1:3000/mcp")],
+            ..Default::default()
+        };
+        assert!(validate_mcp_bridge_config(&cfg).is_ok());
+
+        cfg.servers.push(server("files", "https://example.com/mcp"));
+        assert!(validate_mcp_bridge_config(&cfg).is_err());
+
+        cfg.servers = vec![server("my files", "http://127.0.0.1:3000/mcp")];
+        assert!(validate_mcp_bridge_config(&cfg).is_err());
+
+        cfg.servers = vec![server("files", "stdio://cmd")];
+        assert!(validate_mcp_bridge_config(&cfg).is_err());
+    }
+}
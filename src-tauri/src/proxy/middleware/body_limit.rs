@@ -0,0 +1,177 @@
+// 请求体大小限制中间件
+// 配合 DefaultBodyLimit 使用: 提前拦截超限的 Content-Length，
+// 并把本地限制产生的纯文本 413 改写为各协议对应的 JSON 错误格式 (上游透传的 413 保持原样)
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 默认请求体大小限制 (MB)，与引入该配置前的固定上限一致，避免已有安装的大图片 / PDF 请求被拒绝
+pub const DEFAULT_MAX_BODY_SIZE_MB: u64 = 100;
+
+/// 请求体大小限制中间件
+pub async fn body_limit_middleware(
+    State(max_body_size): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+
+    // 1. 根据 Content-Length 提前拒绝，避免读取整个请求体
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+
+    if let Some(len) = content_length {
+        if len > max_body_size {
+            tracing::warn!(
+                "[BodyLimit] Rejected {} ({} bytes > limit {} bytes)",
+                path,
+                len,
+                max_body_size
+            );
+            return payload_too_large_response(&path, max_body_size);
+        }
+    }
+
+    // 2. 分块传输等无 Content-Length 的情况由 DefaultBodyLimit 在提取时拦截。
+    //    统计实际读取的字节数，只有确实超限时才改写 413，上游返回的 413 原样透传
+    let received = Arc::new(AtomicUsize::new(0));
+    let request = if content_length.is_none() {
+        let counter = received.clone();
+        request.map(|body| {
+            Body::from_stream(body.into_data_stream().inspect(move |chunk| {
+                if let Ok(bytes) = chunk {
+                    counter.fetch_add(bytes.len(), Ordering::Relaxed);
+                }
+            }))
+        })
+    } else {
+        request
+    };
+
+    let response = next.run(request).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE
+        && received.load(Ordering::Relaxed) > max_body_size
+    {
+        tracing::warn!("[BodyLimit] Request body of {} exceeded limit {} bytes", path, max_body_size);
+        return payload_too_large_response(&path, max_body_size);
+    }
+
+    response
+}
+
+/// 按请求路径构造对应协议的 413 响应
+fn payload_too_large_response(path: &str, max_body_size: usize) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(payload_too_large_body(path, max_body_size)),
+    )
+        .into_response()
+}
+
+fn payload_too_large_body(path: &str, max_body_size: usize) -> Value {
+    let message = format!(
+        "Request body too large: the limit is {} MB. Reduce image sizes or the number of attachments, or raise max_body_size_mb in the proxy settings.",
+        max_body_size / 1024 / 1024
+    );
+
+    if path.starts_with("/v1/messages") {
+        // Anthropic 格式
+        json!({
+            "type": "error",
+            "error": {
+                "type": "request_too_large",
+                "message": message
+            }
+        })
+    } else if path.starts_with("/v1beta") {
+        // Gemini 格式
+        json!({
+            "error": {
+                "code": 413,
+                "message": message,
+                "status": "INVALID_ARGUMENT"
+            }
+        })
+    } else {
+        // OpenAI 格式 (默认)
+        json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "code": "request_too_large"
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::{body::Bytes, routing::post, Router};
+    use tower::ServiceExt;
+
+    fn app(max_body_size: usize) -> Router {
+        Router::new()
+            .route("/v1/messages", post(|_body: Bytes| async { "ok" }))
+            .route(
+                "/v1/chat/completions",
+                post(|| async { (StatusCode::PAYLOAD_TOO_LARGE, "upstream: prompt too large") }),
+            )
+            .layer(axum::extract::DefaultBodyLimit::max(max_body_size))
+            .layer(axum::middleware::from_fn_with_state(max_body_size, body_limit_middleware))
+    }
+
+    fn chunked_body(len: usize) -> Body {
+        Body::from_stream(futures::stream::iter(vec![Ok::<_, std::io::Error>(Bytes::from(vec![
+            b'a';
+            len
+        ]))]))
+    }
+
+    #[tokio::test]
+    async fn test_local_limit_rewritten_but_upstream_413_passed_through() {
+        let response = app(16)
+            .oneshot(Request::post("/v1/messages").body(chunked_body(64)).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let response = app(16)
+            .oneshot(Request::post("/v1/chat/completions").body(chunked_body(8)).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"upstream: prompt too large");
+    }
+
+    #[test]
+    fn test_payload_too_large_body_per_protocol() {
+        let limit = 20 * 1024 * 1024;
+
+        let claude = payload_too_large_body("/v1/messages", limit);
+        assert_eq!(claude["type"], "error");
+        assert_eq!(claude["error"]["type"], "request_too_large");
+
+        let gemini = payload_too_large_body("/v1beta/models/gemini-2.5-pro:generateContent", limit);
+        assert_eq!(gemini["error"]["code"], 413);
+
+        let openai = payload_too_large_body("/v1/chat/completions", limit);
+        assert_eq!(openai["error"]["code"], "request_too_large");
+        assert!(openai["error"]["message"].as_str().unwrap().contains("20 MB"));
+    }
+}
@@ -0,0 +1,480 @@
+// 客户端限流中间件
+// 按 全局 / API Key / 客户端 IP 三个维度做令牌桶 (每分钟请求数) 与并发流限制，超限返回 429 + Retry-After。
+// 用于防止配置错误的 Agent 陷入重试循环时耗尽账号池配额。
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::proxy::config::{ClientRateLimitConfig, ClientRateLimitRule};
+
+/// 桶数量超过该值时清理长时间未使用的条目
+const PRUNE_THRESHOLD: usize = 4096;
+/// 未使用超过该时长的桶视为已回满，可安全移除
+const BUCKET_IDLE_TTL: Duration = Duration::from_secs(600);
+
+fn config() -> &'static RwLock<ClientRateLimitConfig> {
+    static CONFIG: OnceLock<RwLock<ClientRateLimitConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(ClientRateLimitConfig::default()))
+}
+
+/// 更新限流配置 (启动与配置热更新时调用)
+pub fn update_client_rate_limit_config(new_config: ClientRateLimitConfig) {
+    if let Ok(mut current) = config().write() {
+        *current = new_config;
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn full(capacity: f64, now: Instant) -> Self {
+        Self {
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// 按经过时间补充令牌 (容量随配置变化即时生效)
+    fn refill(&mut self, requests_per_minute: u32, now: Instant) {
+        let capacity = requests_per_minute as f64;
+        let rate = capacity / 60.0;
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.last_refill = now;
+    }
+
+    /// 距离下一个令牌可用的秒数
+    fn retry_after_secs(&self, requests_per_minute: u32) -> u64 {
+        let rate = requests_per_minute as f64 / 60.0;
+        ((1.0 - self.tokens) / rate).ceil().max(1.0) as u64
+    }
+}
+
+/// 限流状态：令牌桶与并发计数，键为 "global" / "key:<api_key>" / "ip:<ip>"
+#[derive(Default)]
+struct ClientRateLimiter {
+    buckets: DashMap<String, Bucket>,
+    in_flight: DashMap<String, u32>,
+}
+
+/// 并发占用，释放时计数减一
+pub struct ConcurrencyGuard {
+    scopes: Vec<String>,
+}
+
+impl Drop for ConcurrencyGuard {
+    fn drop(&mut self) {
+        for scope in &self.scopes {
+            if let Some(mut count) = limiter().in_flight.get_mut(scope) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        limiter().in_flight.retain(|_, count| *count > 0);
+    }
+}
+
+fn limiter() -> &'static ClientRateLimiter {
+    static LIMITER: OnceLock<ClientRateLimiter> = OnceLock::new();
+    LIMITER.get_or_init(ClientRateLimiter::default)
+}
+
+impl ClientRateLimiter {
+    /// 逐个维度在 entry 锁内完成"检查 + 扣减"，任一维度没有令牌时归还已扣减的维度，
+    /// 避免某一维度拒绝时白白消耗其他维度的令牌
+    fn take_tokens(&self, scopes: &[(String, u32)], now: Instant) -> Result<(), u64> {
+        let mut taken: Vec<&(String, u32)> = Vec::with_capacity(scopes.len());
+        let mut retry_after = 0;
+        for entry in scopes {
+            let (scope, rpm) = entry;
+            let mut bucket = self
+                .buckets
+                .entry(scope.clone())
+                .or_insert_with(|| Bucket::full(*rpm as f64, now));
+            bucket.refill(*rpm, now);
+            if bucket.tokens < 1.0 {
+                // 继续检查其余维度，Retry-After 取最长等待
+                retry_after = retry_after.max(bucket.retry_after_secs(*rpm));
+            } else if retry_after == 0 {
+                bucket.tokens -= 1.0;
+                taken.push(entry);
+            }
+        }
+        if retry_after > 0 {
+            for (scope, rpm) in taken {
+                if let Some(mut bucket) = self.buckets.get_mut(scope) {
+                    bucket.tokens = (bucket.tokens + 1.0).min(*rpm as f64);
+                }
+            }
+            return Err(retry_after);
+        }
+
+        if self.buckets.len() > PRUNE_THRESHOLD {
+            self.buckets
+                .retain(|_, b| now.saturating_duration_since(b.last_refill) < BUCKET_IDLE_TTL);
+        }
+        Ok(())
+    }
+
+    /// 逐个维度在 entry 锁内完成"检查 + 占用"，任一维度已满则回退已占用的维度
+    fn acquire_slots(&self, scopes: &[(String, u32)]) -> Option<ConcurrencyGuard> {
+        let mut guard = ConcurrencyGuard { scopes: Vec::with_capacity(scopes.len()) };
+        for (scope, max) in scopes {
+            let mut count = self.in_flight.entry(scope.clone()).or_insert(0);
+            if *count >= *max {
+                // 提前释放 entry 锁，guard 的 drop 会再次访问 in_flight
+                drop(count);
+                return None;
+            }
+            *count += 1;
+            drop(count);
+            guard.scopes.push(scope.clone());
+        }
+        Some(guard)
+    }
+}
+
+/// 限流拒绝原因
+#[derive(Debug, PartialEq)]
+pub enum RateLimitRejection {
+    Requests { retry_after: u64 },
+    Concurrency,
+}
+
+/// 按配置检查一次请求，通过时返回并发占用 (无并发限制时为空占用)
+fn check_request(
+    config: &ClientRateLimitConfig,
+    api_key: Option<&str>,
+    client_ip: Option<&str>,
+    now: Instant,
+) -> Result<ConcurrencyGuard, RateLimitRejection> {
+    let mut rules: Vec<(String, &ClientRateLimitRule)> = vec![("global".to_string(), &config.global)];
+    if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+        rules.push((format!("key:{}", key), config.rule_for_key(key)));
+    }
+    if let Some(ip) = client_ip.filter(|ip| !ip.is_empty()) {
+        rules.push((format!("ip:{}", ip), config.rule_for_ip(ip)));
+    }
+
+    let rpm_scopes: Vec<(String, u32)> = rules
+        .iter()
+        .filter_map(|(scope, rule)| rule.requests_per_minute.filter(|n| *n > 0).map(|n| (scope.clone(), n)))
+        .collect();
+    let concurrency_scopes: Vec<(String, u32)> = rules
+        .iter()
+        .filter_map(|(scope, rule)| rule.max_concurrent_streams.filter(|n| *n > 0).map(|n| (scope.clone(), n)))
+        .collect();
+
+    let limiter = limiter();
+    // 先占并发位，再扣令牌：并发已满的请求不消耗令牌
+    let guard = limiter
+        .acquire_slots(&concurrency_scopes)
+        .ok_or(RateLimitRejection::Concurrency)?;
+    limiter
+        .take_tokens(&rpm_scopes, now)
+        .map_err(|retry_after| RateLimitRejection::Requests { retry_after })?;
+    Ok(guard)
+}
+
+fn extract_api_key(request: &Request) -> Option<String> {
+    api_key_from_headers(request.headers())
+}
+
+/// 从 Authorization / x-api-key / x-goog-api-key 中提取客户端 API Key
+pub fn api_key_from_headers(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.strip_prefix("Bearer ").unwrap_or(s))
+        .or_else(|| headers.get("x-api-key").and_then(|h| h.to_str().ok()))
+        .or_else(|| headers.get("x-goog-api-key").and_then(|h| h.to_str().ok()))
+        .map(|s| s.trim().to_string())
+}
+
+/// 限流所用的客户端 IP：默认取 TCP 对端地址，仅当对端是配置的可信代理时才采用转发头
+fn rate_limit_client_ip(request: &Request, config: &ClientRateLimitConfig) -> Option<String> {
+    let peer = request
+        .extensions()
+        .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+        .map(|info| info.0.ip().to_string());
+    match peer {
+        Some(peer) if !config.is_trusted_proxy(&peer) => Some(peer),
+        _ => super::ip_filter::extract_client_ip(request),
+    }
+}
+
+/// 客户端限流中间件
+pub async fn client_rate_limit_middleware(request: Request, next: Next) -> Response {
+    let config = match config().read() {
+        Ok(config) if config.enabled => config.clone(),
+        _ => return next.run(request).await,
+    };
+
+    let path = request.uri().path().to_string();
+    let exempt = request.method() == Method::OPTIONS
+        || path == "/healthz"
+        || path == "/health"
+        || path.starts_with("/internal/")
+        || path.contains("event_logging");
+    if exempt {
+        return next.run(request).await;
+    }
+
+    let api_key = extract_api_key(&request);
+    let client_ip = rate_limit_client_ip(&request, &config);
+
+    let guard = match check_request(&config, api_key.as_deref(), client_ip.as_deref(), Instant::now()) {
+        Ok(guard) => guard,
+        Err(rejection) => {
+            tracing::warn!(
+                "[RateLimit] Rejected {} from {} ({:?})",
+                path,
+                client_ip.as_deref().unwrap_or("unknown"),
+                rejection
+            );
+            return rate_limited_response(&path, &rejection);
+        }
+    };
+
+    let response = next.run(request).await;
+
+    // 流式响应在输出结束前持续占用并发位
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    if !is_stream {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+fn rate_limited_response(path: &str, rejection: &RateLimitRejection) -> Response {
+    let retry_after = match rejection {
+        RateLimitRejection::Requests { retry_after } => *retry_after,
+        RateLimitRejection::Concurrency => 1,
+    };
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(rate_limited_body(path, rejection)),
+    )
+        .into_response();
+    if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+    }
+    response
+}
+
+fn rate_limited_body(path: &str, rejection: &RateLimitRejection) -> Value {
+    let message = match rejection {
+        RateLimitRejection::Requests { retry_after } => format!(
+            "Proxy rate limit exceeded. Retry after {} second(s).",
+            retry_after
+        ),
+        RateLimitRejection::Concurrency => {
+            "Too many concurrent requests for this client. Wait for in-flight streams to finish.".to_string()
+        }
+    };
+
+    if path.starts_with("/v1/messages") {
+        // Anthropic 格式
+        json!({
+            "type": "error",
+            "error": { "type": "rate_limit_error", "message": message }
+        })
+    } else if path.starts_with("/v1beta") {
+        // Gemini 格式
+        json!({
+            "error": { "code": 429, "message": message, "status": "RESOURCE_EXHAUSTED" }
+        })
+    } else {
+        // OpenAI 格式 (默认)
+        json!({
+            "error": {
+                "message": message,
+                "type": "rate_limit_error",
+                "code": "rate_limit_exceeded"
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_rejects_and_refills() {
+        let mut config = ClientRateLimitConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config.per_key.insert(
+            "sk-rl-test".to_string(),
+            ClientRateLimitRule {
+                requests_per_minute: Some(2),
+                max_concurrent_streams: None,
+            },
+        );
+
+        let start = Instant::now();
+        assert!(check_request(&config, Some("sk-rl-test"), None, start).is_ok());
+        assert!(check_request(&config, Some("sk-rl-test"), None, start).is_ok());
+        assert_eq!(
+            check_request(&config, Some("sk-rl-test"), None, start).err(),
+            Some(RateLimitRejection::Requests { retry_after: 30 })
+        );
+        // 其他 Key 不受影响
+        assert!(check_request(&config, Some("sk-rl-other"), None, start).is_ok());
+        // 30 秒补充一个令牌
+        assert!(check_request(&config, Some("sk-rl-test"), None, start + Duration::from_secs(30)).is_ok());
+    }
+
+    #[test]
+    fn test_concurrency_slots_released_on_drop() {
+        let config = ClientRateLimitConfig {
+            enabled: true,
+            per_ip: [(
+                "10.9.8.7".to_string(),
+                ClientRateLimitRule {
+                    requests_per_minute: None,
+                    max_concurrent_streams: Some(1),
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+
+        let now = Instant::now();
+        let guard = check_request(&config, None, Some("10.9.8.7"), now).unwrap();
+        assert_eq!(
+            check_request(&config, None, Some("10.9.8.7"), now).err(),
+            Some(RateLimitRejection::Concurrency)
+        );
+        drop(guard);
+        assert!(check_request(&config, None, Some("10.9.8.7"), now).is_ok());
+    }
+
+    #[test]
+    fn test_concurrency_rejection_rolls_back_other_scopes() {
+        let config = ClientRateLimitConfig {
+            enabled: true,
+            per_key: [(
+                "sk-rl-rollback".to_string(),
+                ClientRateLimitRule {
+                    requests_per_minute: None,
+                    max_concurrent_streams: Some(2),
+                },
+            )]
+            .into_iter()
+            .collect(),
+            per_ip: [(
+                "10.9.8.6".to_string(),
+                ClientRateLimitRule {
+                    requests_per_minute: None,
+                    max_concurrent_streams: Some(1),
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+
+        let now = Instant::now();
+        let _held = check_request(&config, Some("sk-rl-rollback"), Some("10.9.8.6"), now).unwrap();
+        // IP 维度已满，Key 维度占用的名额必须回退
+        assert_eq!(
+            check_request(&config, Some("sk-rl-rollback"), Some("10.9.8.6"), now).err(),
+            Some(RateLimitRejection::Concurrency)
+        );
+        assert_eq!(limiter().in_flight.get("key:sk-rl-rollback").map(|c| *c), Some(1));
+    }
+
+    #[test]
+    fn test_concurrent_requests_do_not_overdraw_bucket() {
+        let scopes = vec![("key:sk-rl-race".to_string(), 5u32)];
+        let now = Instant::now();
+        let passed = std::sync::atomic::AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            for _ in 0..32 {
+                s.spawn(|| {
+                    if limiter().take_tokens(&scopes, now).is_ok() {
+                        passed.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+        assert_eq!(passed.into_inner(), 5);
+    }
+
+    #[test]
+    fn test_request_rejection_refunds_other_scopes() {
+        let now = Instant::now();
+        let limiter = limiter();
+        let scopes = vec![
+            ("key:sk-rl-refund".to_string(), 10u32),
+            ("ip:10.9.8.5".to_string(), 1u32),
+        ];
+        assert!(limiter.take_tokens(&scopes, now).is_ok());
+        // IP 维度已无令牌，Key 维度扣减的令牌必须归还
+        assert_eq!(limiter.take_tokens(&scopes, now), Err(60));
+        let tokens = limiter.buckets.get("key:sk-rl-refund").map(|b| b.tokens);
+        assert_eq!(tokens, Some(9.0));
+    }
+
+    fn request_from(peer: &str, forwarded_for: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri("/v1/messages");
+        if let Some(xff) = forwarded_for {
+            builder = builder.header("x-forwarded-for", xff);
+        }
+        let mut request = builder.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(axum::extract::ConnectInfo(peer.parse::<std::net::SocketAddr>().unwrap()));
+        request
+    }
+
+    #[test]
+    fn test_forwarded_headers_only_trusted_from_configured_proxies() {
+        let mut config = ClientRateLimitConfig::default();
+        let spoofed = request_from("203.0.113.5:50000", Some("1.2.3.4"));
+        assert_eq!(rate_limit_client_ip(&spoofed, &config).as_deref(), Some("203.0.113.5"));
+
+        config.trusted_proxies = vec!["127.0.0.1".to_string()];
+        let proxied = request_from("127.0.0.1:50000", Some("198.51.100.7, 127.0.0.1"));
+        assert_eq!(rate_limit_client_ip(&proxied, &config).as_deref(), Some("198.51.100.7"));
+        assert_eq!(rate_limit_client_ip(&spoofed, &config).as_deref(), Some("203.0.113.5"));
+    }
+
+    #[test]
+    fn test_rate_limited_body_per_protocol() {
+        let rejection = RateLimitRejection::Requests { retry_after: 5 };
+        assert_eq!(rate_limited_body("/v1/messages", &rejection)["error"]["type"], "rate_limit_error");
+        assert_eq!(rate_limited_body("/v1beta/models/x:generateContent", &rejection)["error"]["code"], 429);
+        let openai = rate_limited_body("/v1/chat/completions", &rejection);
+        assert_eq!(openai["error"]["code"], "rate_limit_exceeded");
+
+        let response = rate_limited_response("/v1/chat/completions", &rejection);
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+    }
+}
@@ -1,33 +1,138 @@
 // CORS 中间件
-use tower_http::cors::{CorsLayer, Any};
-use axum::http::Method;
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+use crate::proxy::config::CorsConfig;
+
+/// 未配置时允许的请求方法
+const DEFAULT_METHODS: [Method; 7] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::HEAD,
+    Method::OPTIONS,
+    Method::PATCH,
+];
+
+fn origin_allowed(patterns: &[String], origin: &str) -> bool {
+    patterns
+        .iter()
+        .any(|p| crate::proxy::common::model_mapping::wildcard_match(p.trim().trim_end_matches('/'), origin))
+}
+
+fn allow_origin(config: &CorsConfig) -> AllowOrigin {
+    let any = config.allowed_origins.is_empty() || config.allowed_origins.iter().any(|o| o.trim() == "*");
+    if any {
+        // 携带凭据时不允许返回 `*`，改为镜像请求来源
+        return if config.allow_credentials {
+            AllowOrigin::mirror_request()
+        } else {
+            AllowOrigin::any()
+        };
+    }
+    let patterns = config.allowed_origins.clone();
+    AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+        origin.to_str().is_ok_and(|o| origin_allowed(&patterns, o))
+    })
+}
+
+fn allow_methods(config: &CorsConfig) -> AllowMethods {
+    let methods: Vec<Method> = config
+        .allowed_methods
+        .iter()
+        .filter_map(|m| Method::from_bytes(m.trim().to_ascii_uppercase().as_bytes()).ok())
+        .collect();
+    if methods.is_empty() {
+        AllowMethods::list(DEFAULT_METHODS)
+    } else {
+        AllowMethods::list(methods)
+    }
+}
+
+fn allow_headers(config: &CorsConfig) -> AllowHeaders {
+    let headers: Vec<HeaderName> = config
+        .allowed_headers
+        .iter()
+        .filter_map(|h| HeaderName::from_bytes(h.trim().to_ascii_lowercase().as_bytes()).ok())
+        .collect();
+    // `Access-Control-Allow-Headers: *` 不包含 Authorization，默认镜像预检请求头
+    if headers.is_empty() {
+        AllowHeaders::mirror_request()
+    } else {
+        AllowHeaders::list(headers)
+    }
+}
 
 /// 创建 CORS layer
-pub fn cors_layer() -> CorsLayer {
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let mut expose_headers = vec![HeaderName::from_static(
+        crate::proxy::middleware::request_id::REQUEST_ID_HEADER,
+    )];
+    expose_headers.extend(
+        config
+            .expose_headers
+            .iter()
+            .filter_map(|h| HeaderName::from_bytes(h.trim().to_ascii_lowercase().as_bytes()).ok()),
+    );
+
     CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::HEAD,
-            Method::OPTIONS,
-            Method::PATCH,
-        ])
-        .allow_headers(Any)
-        .allow_credentials(false)
-        .max_age(std::time::Duration::from_secs(3600))
+        .allow_origin(allow_origin(config))
+        .allow_methods(allow_methods(config))
+        .allow_headers(allow_headers(config))
+        .expose_headers(expose_headers)
+        .allow_credentials(config.allow_credentials)
+        .allow_private_network(config.allow_private_network)
+        .max_age(std::time::Duration::from_secs(config.max_age_secs))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::{body::Body, http::Request, routing::post, Router};
+    use tower::ServiceExt;
+
+    async fn preflight(config: &CorsConfig, origin: &str) -> axum::http::Response<Body> {
+        let app = Router::new()
+            .route("/v1/chat/completions", post(|| async { "ok" }))
+            .layer(cors_layer(config));
+        app.oneshot(
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/v1/chat/completions")
+                .header("origin", origin)
+                .header("access-control-request-method", "POST")
+                .header("access-control-request-headers", "authorization,content-type")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_default_preflight_allows_authorization() {
+        let resp = preflight(&CorsConfig::default(), "https://playground.example.com").await;
+        assert!(resp.status().is_success());
+        let headers = resp.headers();
+        assert_eq!(headers["access-control-allow-origin"], "*");
+        assert_eq!(headers["access-control-allow-headers"], "authorization,content-type");
+        assert_eq!(headers["access-control-max-age"], "3600");
+    }
+
+    #[tokio::test]
+    async fn test_origin_allow_list() {
+        let config = CorsConfig {
+            allowed_origins: vec!["chrome-extension://*".to_string(), "https://app.example.com/".to_string()],
+            ..Default::default()
+        };
+        let resp = preflight(&config, "chrome-extension://abcdef").await;
+        assert_eq!(resp.headers()["access-control-allow-origin"], "chrome-extension://abcdef");
+
+        let resp = preflight(&config, "https://app.example.com").await;
+        assert_eq!(resp.headers()["access-control-allow-origin"], "https://app.example.com");
 
-    #[test]
-    fn test_cors_layer_creation() {
-        let _layer = cors_layer();
-        // Layer 创建成功
-        assert!(true);
+        let resp = preflight(&config, "https://evil.example.com").await;
+        assert!(resp.headers().get("access-control-allow-origin").is_none());
     }
 }
@@ -144,7 +144,7 @@ pub async fn ip_filter_middleware(
 }
 
 /// 从请求中提取客户端 IP
-fn extract_client_ip(request: &Request) -> Option<String> {
+pub(crate) fn extract_client_ip(request: &Request) -> Option<String> {
     // 1. 优先从 X-Forwarded-For 提取 (取第一个 IP)
     request
         .headers()
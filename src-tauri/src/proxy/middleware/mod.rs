@@ -5,6 +5,9 @@ pub mod cors;
 pub mod logging;
 pub mod monitor;
 pub mod ip_filter;
+pub mod body_limit;
+pub mod request_id;
+pub mod client_rate_limit;
 
 pub mod service_status;
 
@@ -13,3 +16,6 @@ pub use monitor::monitor_middleware;
 pub use service_status::service_status_middleware;
 pub use auth::{auth_middleware, admin_auth_middleware};
 pub use ip_filter::ip_filter_middleware;
+pub use body_limit::body_limit_middleware;
+pub use request_id::request_id_middleware;
+pub use client_rate_limit::client_rate_limit_middleware;
@@ -7,12 +7,28 @@ use axum::{
 use std::time::Instant;
 use crate::proxy::server::AppState;
 use crate::proxy::monitor::ProxyRequestLog;
+use crate::proxy::activity::ProxyActivityEvent;
 use serde_json::Value;
 use crate::proxy::middleware::auth::UserTokenIdentity;
+use crate::proxy::middleware::request_id::RequestId;
 use futures::StreamExt;
 
 const MAX_REQUEST_LOG_SIZE: usize = 100 * 1024 * 1024; // 100MB
 const MAX_RESPONSE_LOG_SIZE: usize = 100 * 1024 * 1024; // 100MB for image responses
+/// [NEW] 流式响应日志最多保留的字节数，超出后只转发不累积 (用量从尾部数据提取)
+const MAX_STREAM_CAPTURE_SIZE: usize = 8 * 1024 * 1024;
+/// [NEW] 上游读取与下游发送之间的有界缓冲 (按块计)，客户端读得慢时反压上游读取
+const STREAM_CHANNEL_CAPACITY: usize = 32;
+
+/// 提取思考 token 数 (Gemini thoughtsTokenCount / OpenAI reasoning_tokens)
+fn extract_thoughts_tokens(usage: &Value) -> Option<u32> {
+    usage
+        .get("thoughtsTokenCount")
+        .or_else(|| usage.get("completion_tokens_details").and_then(|d| d.get("reasoning_tokens")))
+        .or_else(|| usage.get("output_tokens_details").and_then(|d| d.get("reasoning_tokens")))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+}
 
 /// Helper function to record User Token usage
 fn record_user_token_usage(
@@ -33,17 +49,27 @@ fn record_user_token_usage(
     }
 }
 
+/// 按状态码判断请求结果是否计入账号健康指标:
+/// 成功 -> Some(true)，鉴权 / 限流 / 上游错误 -> Some(false)，其他客户端错误与账号无关 -> None
+fn account_outcome(status: u16) -> Option<bool> {
+    match status {
+        200..=399 => Some(true),
+        401 | 403 | 408 | 429 | 500..=599 => Some(false),
+        _ => None,
+    }
+}
+
 pub async fn monitor_middleware(
     State(state): State<AppState>,
     request: Request,
     next: Next,
 ) -> Response {
-    let _logging_enabled = state.monitor.is_enabled();
+    let logging_enabled = state.monitor.is_enabled();
     
     let method = request.method().to_string();
     let uri = request.uri().to_string();
     
-    if uri.contains("event_logging") || uri.contains("/api/") || uri.starts_with("/internal/") {
+    if uri.contains("event_logging") || uri.contains("/api/") || uri.starts_with("/internal/") || uri.starts_with("/v1/ws") {
         return next.run(request).await;
     }
     
@@ -85,6 +111,12 @@ pub async fn monitor_middleware(
     // [FIX] 从请求 extensions 提取 UserTokenIdentity (由 Auth 中间件注入)
     // 必须在处理 request body 之前提取，因为 into_parts() 后需要保留这个值
     let user_token_identity = request.extensions().get::<UserTokenIdentity>().cloned();
+    // [NEW] 日志 ID 与 x-agm-request-id 保持一致，便于按请求 ID 检索
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|r| r.0.clone())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     
     let request = if method == "POST" {
         let (parts, body) = request.into_parts();
@@ -112,6 +144,13 @@ pub async fn monitor_middleware(
         request
     };
     
+    crate::proxy::activity::emit(ProxyActivityEvent::RequestStarted {
+        request_id: request_id.clone(),
+        method: method.clone(),
+        path: uri.clone(),
+        model: model.clone(),
+    });
+
     let response = next.run(request).await;
     
     // user_token_identity 已在上面从请求 extensions 中提取
@@ -131,6 +170,13 @@ pub async fn monitor_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    // [NEW] 更新账号运行指标 (流式响应的耗时为首包耗时)
+    if let (Some(email), Some(success)) = (account_email.as_deref(), account_outcome(status)) {
+        if let Some(account_id) = state.token_manager.get_account_id_by_email(email) {
+            state.token_manager.record_request_outcome(&account_id, success, duration);
+        }
+    }
+
     // Extract mapped model from X-Mapped-Model header if present
     let mapped_model = response
         .headers()
@@ -156,7 +202,7 @@ pub async fn monitor_middleware(
 
     let monitor = state.monitor.clone();
     let mut log = ProxyRequestLog {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: request_id,
         timestamp: chrono::Utc::now().timestamp_millis(),
         method,
         url: uri,
@@ -171,6 +217,7 @@ pub async fn monitor_middleware(
         response_body: None,
         input_tokens: None,
         output_tokens: None,
+        thoughts_tokens: None,
         protocol,
         username,
     };
@@ -179,15 +226,20 @@ pub async fn monitor_middleware(
     if content_type.contains("text/event-stream") {
         let (parts, body) = response.into_parts();
         let mut stream = body.into_data_stream();
-        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        let (tx, rx) = tokio::sync::mpsc::channel(STREAM_CHANNEL_CAPACITY);
         
         tokio::spawn(async move {
             let mut all_stream_data = Vec::new();
             let mut last_few_bytes = Vec::new();
+            let mut abort_reason: Option<String> = None;
             
             while let Some(chunk_res) = stream.next().await {
                 if let Ok(chunk) = chunk_res {
-                    all_stream_data.extend_from_slice(&chunk);
+                    // 监控关闭时不需要完整内容；开启时也只保留有限前缀，避免长流 / 慢客户端占满内存
+                    if logging_enabled {
+                        let remaining = MAX_STREAM_CAPTURE_SIZE.saturating_sub(all_stream_data.len());
+                        all_stream_data.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+                    }
                     
                     if chunk.len() > 8192 {
                         last_few_bytes = chunk.slice(chunk.len()-8192..).to_vec();
@@ -197,12 +249,34 @@ pub async fn monitor_middleware(
                             last_few_bytes.drain(0..last_few_bytes.len()-8192);
                         }
                     }
-                    let _ = tx.send(Ok::<_, axum::Error>(chunk)).await;
+                    if tx.send(Ok::<_, axum::Error>(chunk)).await.is_err() && abort_reason.is_none() {
+                        abort_reason = Some("client_disconnected".to_string());
+                    }
                 } else if let Err(e) = chunk_res {
+                    if abort_reason.is_none() {
+                        abort_reason = Some(format!("upstream_error: {}", e));
+                    }
                     let _ = tx.send(Err(axum::Error::new(e))).await;
                 }
             }
+
+            if let Some(reason) = abort_reason {
+                crate::proxy::activity::emit(ProxyActivityEvent::StreamAborted {
+                    request_id: log.id.clone(),
+                    path: log.url.clone(),
+                    reason,
+                });
+            }
             
+            // 截断位置可能落在多字节字符中间，去掉不完整的尾部
+            if all_stream_data.len() >= MAX_STREAM_CAPTURE_SIZE {
+                if let Err(e) = std::str::from_utf8(&all_stream_data) {
+                    if e.error_len().is_none() {
+                        all_stream_data.truncate(e.valid_up_to());
+                    }
+                }
+            }
+
             // Parse and consolidate stream data into readable format
             if let Ok(full_response) = std::str::from_utf8(&all_stream_data) {
                 let mut thinking_content = String::new();
@@ -350,6 +424,7 @@ pub async fn monitor_middleware(
                                 .or(usage.get("candidatesTokenCount"))
                                 .and_then(|v| v.as_u64())
                                 .map(|v| v as u32);
+                            log.thoughts_tokens = extract_thoughts_tokens(usage);
                             
                             if log.input_tokens.is_none() && log.output_tokens.is_none() {
                                 log.output_tokens = usage.get("total_tokens")
@@ -418,6 +493,7 @@ pub async fn monitor_middleware(
                                         .or(usage.get("candidatesTokenCount"))
                                         .and_then(|v| v.as_u64())
                                         .map(|v| v as u32);
+                                    log.thoughts_tokens = extract_thoughts_tokens(usage);
                                     break;
                                 }
                             }
@@ -455,6 +531,7 @@ pub async fn monitor_middleware(
                                 .or(usage.get("candidatesTokenCount"))
                                 .and_then(|v| v.as_u64())
                                 .map(|v| v as u32);
+                            log.thoughts_tokens = extract_thoughts_tokens(usage);
                                 
                             if log.input_tokens.is_none() && log.output_tokens.is_none() {
                                 log.output_tokens = usage.get("total_tokens")
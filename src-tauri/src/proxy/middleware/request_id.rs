@@ -0,0 +1,87 @@
+// Request ID 中间件
+// 为每个请求生成关联 ID，贯穿 tracing span 并通过响应头返回，便于用户反馈问题时定位日志
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+/// 请求 ID 响应头 (同时会写入请求头，供 handler 读取)
+pub const REQUEST_ID_HEADER: &str = "x-agm-request-id";
+
+/// 注入到请求 extensions 中的请求 ID
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// 生成新的请求 ID
+pub fn generate_request_id() -> String {
+    format!("req_{}", &uuid::Uuid::new_v4().simple().to_string()[..16])
+}
+
+/// 从请求头读取请求 ID (由中间件写入)，不存在时生成新的
+pub fn request_id_from_headers(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(|id| id.to_string())
+        .unwrap_or_else(generate_request_id)
+}
+
+/// 限制字符集与长度，防止日志注入
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
+    // 始终由服务端生成 (请求 ID 同时作为监控日志主键，不能信任客户端传入值)
+    let request_id = generate_request_id();
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        request.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    request
+        .extensions_mut()
+        .insert(RequestId(request_id.clone()));
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        account = tracing::field::Empty, // 由 TokenManager 选中账号后写入
+    );
+
+    let mut response = next.run(request).instrument(span).await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id_from_headers() {
+        let mut headers = HeaderMap::new();
+        let generated = request_id_from_headers(&headers);
+        assert!(generated.starts_with("req_"));
+        assert_eq!(generated.len(), 20);
+
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("client-trace_01"));
+        assert_eq!(request_id_from_headers(&headers), "client-trace_01");
+
+        // 非法字符被拒绝，重新生成
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("bad id\\n"));
+        assert!(request_id_from_headers(&headers).starts_with("req_"));
+    }
+}
@@ -8,23 +8,34 @@ pub mod server;
 pub mod token_manager;
 
 // 新架构模块
+pub mod activity; // 实时活动事件流
 pub mod audio; // 音频处理模块
+pub mod benchmark; // 反代压测 (延迟分位数 / 错误分类 / 按账号吞吐)
 pub mod cli_sync; // CLI 配置同步 (v3.3.35)
 pub mod droid_sync; // Droid (Factory CLI) 配置同步
 pub mod common; // 公共工具
+pub mod context_cache; // Gemini 上下文缓存 (cachedContent) 映射
 pub mod debug_logger;
 pub mod handlers; // API 端点处理器
 pub mod mappers; // 协议转换器
+pub mod mcp_bridge; // MCP 工具桥接 (外部 MCP 服务器 -> 合成工具)
 pub mod middleware; // Axum 中间件
 pub mod monitor; // 监控
 pub mod opencode_sync; // OpenCode 配置同步
 pub mod providers; // Extra upstream providers (z.ai, etc.)
 pub mod proxy_pool; // 代理池管理器
 pub mod rate_limit; // 限流跟踪
+pub mod runtime_state; // 运行时状态持久化
+pub mod safety_settings; // Gemini 安全过滤阈值配置
+pub mod redaction; // 请求内容脱敏 (DLP)
+pub mod replay; // 抓包请求重放与响应对比
 pub mod model_specs; // 模型规格管理 (v4.1.29)
+pub mod model_registry; // 模型注册表 (列表与能力元数据)
 pub mod session_manager; // 会话指纹管理
 pub mod signature_cache; // Signature Cache (v3.3.16)
 pub mod sticky_config; // 粘性调度配置
+pub mod tls; // 本地 HTTPS 支持
+pub mod tool_name_cache; // tool_use_id -> 函数名跨请求缓存
 pub mod upstream; // 上游客户端
 pub mod zai_vision_mcp; // Built-in Vision MCP server state
 pub mod zai_vision_tools; // Built-in Vision MCP tools (z.ai vision API) // 调试日志
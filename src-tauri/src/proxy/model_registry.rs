@@ -0,0 +1,325 @@
+// 模型注册表
+// 合并三类来源生成模型列表：账号配额刷新时 fetchAvailableModels 下发的模型元数据、内置/自定义映射、静态规格表。
+// 上游元数据扫描账号文件获得，带 TTL 缓存；映射部分每次请求实时解析，保证改完映射立即生效。
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::proxy::common::model_mapping::{get_all_dynamic_models, peek_model_route};
+use crate::proxy::token_manager::TokenManager;
+
+/// 上游元数据缓存有效期
+const UPSTREAM_META_TTL: Duration = Duration::from_secs(300);
+
+/// 模型对外创建时间 (OpenAI / Anthropic 列表需要，固定值)
+pub const MODEL_CREATED_AT: i64 = 1706745600;
+
+/// 模型能力描述
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelInfo {
+    /// 对外模型 ID (客户端请求使用的名称)
+    pub id: String,
+    pub display_name: String,
+    /// 实际路由到的上游模型
+    pub target: String,
+    pub context_window: u64,
+    pub max_output_tokens: u64,
+    pub supports_vision: bool,
+    pub supports_tools: bool,
+    pub supports_thinking: bool,
+    /// 元数据来源: upstream (官方下发) / builtin (静态规格推断)
+    pub source: &'static str,
+}
+
+/// fetchAvailableModels 下发的单个模型元数据 (多账号取最大值合并)
+#[derive(Debug, Clone, Default)]
+struct UpstreamModelMeta {
+    display_name: Option<String>,
+    max_tokens: Option<u64>,
+    max_output_tokens: Option<u64>,
+    supports_images: Option<bool>,
+    supports_thinking: Option<bool>,
+}
+
+impl UpstreamModelMeta {
+    fn merge(&mut self, quota: &crate::models::quota::ModelQuota) {
+        let max = |a: Option<u64>, b: Option<i32>| match (a, b.filter(|v| *v > 0).map(|v| v as u64)) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.max_tokens = max(self.max_tokens, quota.max_tokens);
+        self.max_output_tokens = max(self.max_output_tokens, quota.max_output_tokens);
+        if self.display_name.is_none() {
+            self.display_name = quota.display_name.clone();
+        }
+        self.supports_images = self.supports_images.or(quota.supports_images);
+        self.supports_thinking = self.supports_thinking.or(quota.supports_thinking);
+    }
+}
+
+type MetaCache = RwLock<Option<(Instant, HashMap<String, UpstreamModelMeta>)>>;
+
+fn meta_cache() -> &'static MetaCache {
+    static CACHE: OnceLock<MetaCache> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(None))
+}
+
+/// 使上游元数据缓存失效 (配额刷新后调用)
+pub fn invalidate() {
+    if let Ok(mut cache) = meta_cache().write() {
+        *cache = None;
+    }
+}
+
+fn load_upstream_meta() -> HashMap<String, UpstreamModelMeta> {
+    let mut meta: HashMap<String, UpstreamModelMeta> = HashMap::new();
+    let accounts = match crate::modules::account::list_accounts() {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            tracing::warn!("[ModelRegistry] Failed to list accounts: {}", e);
+            return meta;
+        }
+    };
+    for account in accounts.iter().filter(|a| !a.disabled) {
+        let Some(quota) = &account.quota else { continue };
+        for model in &quota.models {
+            meta.entry(model.name.clone()).or_default().merge(model);
+        }
+    }
+    meta
+}
+
+async fn upstream_meta() -> HashMap<String, UpstreamModelMeta> {
+    if let Ok(cache) = meta_cache().read() {
+        if let Some((loaded_at, meta)) = cache.as_ref() {
+            if loaded_at.elapsed() < UPSTREAM_META_TTL {
+                return meta.clone();
+            }
+        }
+    }
+    let meta = tokio::task::spawn_blocking(load_upstream_meta)
+        .await
+        .unwrap_or_default();
+    if let Ok(mut cache) = meta_cache().write() {
+        *cache = Some((Instant::now(), meta.clone()));
+    }
+    meta
+}
+
+pub(crate) fn default_context_window(model: &str) -> u64 {
+    if model.starts_with("claude-") {
+        200_000
+    } else if model.starts_with("gpt-oss") {
+        131_072
+    } else {
+        1_048_576
+    }
+}
+
+fn build_model_info(id: &str, target: &str, meta: Option<&UpstreamModelMeta>) -> ModelInfo {
+    let is_image_model = target.contains("-image");
+    let default_vision = !target.starts_with("gpt-oss");
+    ModelInfo {
+        id: id.to_string(),
+        display_name: meta
+            .and_then(|m| m.display_name.clone())
+            .filter(|_| id == target)
+            .unwrap_or_else(|| id.to_string()),
+        target: target.to_string(),
+        context_window: meta
+            .and_then(|m| m.max_tokens)
+            .unwrap_or_else(|| default_context_window(target)),
+        max_output_tokens: meta
+            .and_then(|m| m.max_output_tokens)
+            .unwrap_or_else(|| crate::proxy::model_specs::get_max_output_tokens(target, None)),
+        supports_vision: meta.and_then(|m| m.supports_images).unwrap_or(default_vision),
+        supports_tools: !is_image_model,
+        supports_thinking: meta
+            .and_then(|m| m.supports_thinking)
+            .unwrap_or_else(|| crate::proxy::model_specs::is_thinking_model(target)),
+        source: if meta.is_some() { "upstream" } else { "builtin" },
+    }
+}
+
+fn find_meta<'a>(meta: &'a HashMap<String, UpstreamModelMeta>, id: &str, target: &str) -> Option<&'a UpstreamModelMeta> {
+    meta.get(target).or_else(|| meta.get(id)).or_else(|| {
+        let standard = crate::proxy::common::model_mapping::normalize_to_standard_id(target)?;
+        meta.get(&standard)
+    })
+}
+
+/// 列出所有对外可用模型及能力
+pub async fn list_models(
+    custom_mapping: &tokio::sync::RwLock<HashMap<String, String>>,
+    token_manager: &TokenManager,
+) -> Vec<ModelInfo> {
+    let ids = get_all_dynamic_models(custom_mapping, Some(token_manager)).await;
+    let mapping = custom_mapping.read().await.clone();
+    let meta = upstream_meta().await;
+
+    ids.iter()
+        .map(|id| {
+            let target = peek_model_route(id, &mapping);
+            build_model_info(id, &target, find_meta(&meta, id, &target))
+        })
+        .collect()
+}
+
+/// [NEW] 为 Gemini 文本模型追加 `-online` 联网变体 (后缀触发 Google 搜索，路由目标不变)
+pub fn with_online_variants(models: Vec<ModelInfo>) -> Vec<ModelInfo> {
+    let existing: std::collections::HashSet<String> = models.iter().map(|m| m.id.clone()).collect();
+    let variants: Vec<ModelInfo> = models
+        .iter()
+        .filter(|m| m.supports_tools && m.target.starts_with("gemini-") && !m.id.ends_with("-online"))
+        .filter(|m| !existing.contains(&format!("{}-online", m.id)))
+        .map(|m| ModelInfo {
+            id: format!("{}-online", m.id),
+            display_name: format!("{} (Online)", m.display_name),
+            ..m.clone()
+        })
+        .collect();
+    let mut all = models;
+    all.extend(variants);
+    all.sort_by(|a, b| a.id.cmp(&b.id));
+    all
+}
+
+/// [NEW] 按游标分页 (Anthropic Models API 语义: after_id 向后翻页，before_id 向前翻页)
+/// 返回 (当前页, 是否还有更多)；游标 ID 不存在时返回空页
+pub fn paginate<'a>(
+    models: &'a [ModelInfo],
+    limit: usize,
+    after_id: Option<&str>,
+    before_id: Option<&str>,
+) -> (&'a [ModelInfo], bool) {
+    let position = |id: &str| models.iter().position(|m| m.id == id);
+    if let Some(before) = before_id {
+        let Some(end) = position(before) else {
+            return (&[], false);
+        };
+        let start = end.saturating_sub(limit);
+        return (&models[start..end], start > 0);
+    }
+    let start = match after_id {
+        Some(after) => match position(after) {
+            Some(idx) => idx + 1,
+            None => return (&[], false),
+        },
+        None => 0,
+    };
+    let end = (start + limit).min(models.len());
+    (&models[start..end], end < models.len())
+}
+
+/// 查询单个模型 (不在列表中的名称按路由结果推断，便于客户端探测通配映射)
+pub async fn get_model(
+    model: &str,
+    custom_mapping: &tokio::sync::RwLock<HashMap<String, String>>,
+) -> ModelInfo {
+    let mapping = custom_mapping.read().await.clone();
+    let meta = upstream_meta().await;
+    let target = peek_model_route(model, &mapping);
+    build_model_info(model, &target, find_meta(&meta, model, &target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_model_info_prefers_upstream_meta() {
+        let meta = UpstreamModelMeta {
+            display_name: Some("Gemini 3 Flash".to_string()),
+            max_tokens: Some(1_000_000),
+            max_output_tokens: Some(65_536),
+            supports_images: Some(true),
+            supports_thinking: Some(true),
+        };
+        let info = build_model_info("gemini-3-flash", "gemini-3-flash", Some(&meta));
+        assert_eq!(info.display_name, "Gemini 3 Flash");
+        assert_eq!(info.context_window, 1_000_000);
+        assert_eq!(info.source, "upstream");
+
+        // 映射别名保留自身名称
+        let alias = build_model_info("gpt-4o", "gemini-3-flash", Some(&meta));
+        assert_eq!(alias.display_name, "gpt-4o");
+        assert_eq!(alias.target, "gemini-3-flash");
+    }
+
+    #[test]
+    fn test_build_model_info_fallbacks() {
+        let claude = build_model_info("claude-sonnet-4-6", "claude-sonnet-4-6", None);
+        assert_eq!(claude.context_window, 200_000);
+        assert_eq!(claude.max_output_tokens, 64_000);
+        assert_eq!(claude.source, "builtin");
+
+        let image = build_model_info("gemini-3-pro-image-4k", "gemini-3-pro-image-4k", None);
+        assert!(!image.supports_tools);
+        assert!(image.supports_vision);
+
+        let oss = build_model_info("gpt-oss-120b-medium", "gpt-oss-120b-medium", None);
+        assert!(!oss.supports_vision);
+    }
+
+    #[test]
+    fn test_online_variants_only_for_gemini_text_models() {
+        let models = vec![
+            build_model_info("gpt-4o", "gemini-3-flash", None),
+            build_model_info("gemini-3-pro-image", "gemini-3-pro-image", None),
+            build_model_info("claude-sonnet-4-6", "claude-sonnet-4-6", None),
+        ];
+        let ids: Vec<String> = with_online_variants(models).into_iter().map(|m| m.id).collect();
+        assert!(ids.contains(&"gpt-4o-online".to_string()));
+        assert!(!ids.contains(&"gemini-3-pro-image-online".to_string()));
+        assert!(!ids.contains(&"claude-sonnet-4-6-online".to_string()));
+        assert_eq!(ids.len(), 4);
+    }
+
+    #[test]
+    fn test_paginate_with_cursors() {
+        let models: Vec<ModelInfo> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|id| build_model_info(id, id, None))
+            .collect();
+        let ids = |page: &[ModelInfo]| page.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+
+        let (page, has_more) = paginate(&models, 2, None, None);
+        assert_eq!(ids(page), vec!["a", "b"]);
+        assert!(has_more);
+
+        let (page, has_more) = paginate(&models, 2, Some("c"), None);
+        assert_eq!(ids(page), vec!["d", "e"]);
+        assert!(!has_more);
+
+        let (page, has_more) = paginate(&models, 2, None, Some("d"));
+        assert_eq!(ids(page), vec!["b", "c"]);
+        assert!(has_more);
+
+        assert!(paginate(&models, 2, Some("missing"), None).0.is_empty());
+    }
+
+    #[test]
+    fn test_upstream_meta_merge_takes_max() {
+        let mut meta = UpstreamModelMeta::default();
+        let mut quota = crate::models::quota::ModelQuota {
+            name: "gemini-3-flash".to_string(),
+            percentage: 100,
+            reset_time: String::new(),
+            display_name: None,
+            supports_images: Some(true),
+            supports_thinking: None,
+            thinking_budget: None,
+            recommended: None,
+            max_tokens: Some(500_000),
+            max_output_tokens: Some(8192),
+            supported_mime_types: None,
+        };
+        meta.merge(&quota);
+        quota.max_tokens = Some(1_048_576);
+        quota.max_output_tokens = None;
+        meta.merge(&quota);
+        assert_eq!(meta.max_tokens, Some(1_048_576));
+        assert_eq!(meta.max_output_tokens, Some(8192));
+    }
+}
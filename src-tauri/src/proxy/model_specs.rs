@@ -72,7 +72,6 @@ pub fn get_thinking_budget(model_id: &str, _token: Option<&ProxyToken>) -> u64 {
 }
 
 /// 判断是否为思维模型
-#[allow(dead_code)]
 pub fn is_thinking_model(model_id: &str) -> bool {
     let std_id = resolve_alias(model_id);
     if let Some(spec) = SPECS.models.get(&std_id) {
@@ -21,6 +21,8 @@ pub struct ProxyRequestLog {
     pub response_body: Option<String>,
     pub input_tokens: Option<u32>,
     pub output_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thoughts_tokens: Option<u32>, // 思考 token (Gemini thoughtsTokenCount / OpenAI reasoning_tokens)
     pub protocol: Option<String>,     // 协议类型: "openai", "anthropic", "gemini"
     pub username: Option<String>,     // User token username
 }
@@ -78,7 +80,18 @@ impl ProxyMonitor {
         self.enabled.load(Ordering::Relaxed)
     }
 
-    pub async fn log_request(&self, log: ProxyRequestLog) {
+    pub async fn log_request(&self, mut log: ProxyRequestLog) {
+        // [NEW] 实时活动面板不依赖监控开关
+        crate::proxy::activity::emit(crate::proxy::activity::ProxyActivityEvent::RequestFinished {
+            request_id: log.id.clone(),
+            method: log.method.clone(),
+            path: log.url.clone(),
+            status: log.status,
+            duration_ms: log.duration,
+            model: log.model.clone(),
+            account_email: log.account_email.clone(),
+        });
+
         if let (Some(account), Some(input), Some(output)) = (
             &log.account_email,
             log.input_tokens,
@@ -86,8 +99,9 @@ impl ProxyMonitor {
         ) {
             let model = log.model.clone().unwrap_or_else(|| "unknown".to_string());
             let account = account.clone();
+            let thoughts = log.thoughts_tokens.unwrap_or(0);
             tokio::spawn(async move {
-                if let Err(e) = crate::modules::token_stats::record_usage(&account, &model, input, output) {
+                if let Err(e) = crate::modules::token_stats::record_usage(&account, &model, input, output, thoughts) {
                     tracing::debug!("Failed to record token stats: {}", e);
                 }
             });
@@ -97,6 +111,9 @@ impl ProxyMonitor {
             return;
         }
         tracing::info!("[Monitor] Logging request: {} {}", log.method, log.url);
+        // [NEW] 按配置对记录的请求 / 响应内容脱敏
+        crate::proxy::redaction::redact_log_payload(&mut log.request_body);
+        crate::proxy::redaction::redact_log_payload(&mut log.response_body);
         // Update stats
         {
             let mut stats = self.stats.write().await;
@@ -146,17 +163,7 @@ impl ProxyMonitor {
                 }
             }
 
-            // Record token stats if available
-            if let (Some(account), Some(input), Some(output)) = (
-                &log_to_save.account_email,
-                log_to_save.input_tokens,
-                log_to_save.output_tokens,
-            ) {
-                let model = log_to_save.model.clone().unwrap_or_else(|| "unknown".to_string());
-                if let Err(e) = crate::modules::token_stats::record_usage(account, &model, input, output) {
-                    tracing::debug!("Failed to record token stats: {}", e);
-                }
-            }
+            // Token 统计已在函数入口处记录 (无论监控是否开启)，此处不再重复写入
         });
 
         // Emit event (send summary only, without body to reduce memory)
@@ -177,6 +184,7 @@ impl ProxyMonitor {
                 response_body: None, // Don't send body in event
                 input_tokens: log.input_tokens,
                 output_tokens: log.output_tokens,
+                thoughts_tokens: log.thoughts_tokens,
                 protocol: log.protocol.clone(),
                 username: log.username.clone(),
             };
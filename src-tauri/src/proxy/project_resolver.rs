@@ -1,46 +1,170 @@
 use serde_json::Value;
 
-/// 使用 Antigravity 的 loadCodeAssist API 获取 project_id
-/// 这是获取 cloudaicompanionProject 的正确方式
-pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
-    // 使用 Sandbox 环境，避免 Prod 环境的 429 错误
-    let url = "https://daily-cloudcode-pa.sandbox.googleapis.com/v1internal:loadCodeAssist";
-    
-    let request_body = serde_json::json!({
-        "metadata": {
-            "ideType": "ANTIGRAVITY"
-        }
-    });
-    
+/// 使用 Sandbox 环境，避免 Prod 环境的 429 错误
+const CODE_ASSIST_BASE: &str = "https://daily-cloudcode-pa.sandbox.googleapis.com/v1internal";
+/// 无法获取上游 project_id 时的稳定兜底 [FIX #1794]
+pub const FALLBACK_PROJECT_ID: &str = "bamboo-precept-lgxtn";
+/// onboardUser 长任务轮询次数与间隔
+const ONBOARD_MAX_POLLS: usize = 5;
+const ONBOARD_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+async fn post_code_assist(access_token: &str, method: &str, body: &Value) -> Result<Value, String> {
+    let url = format!("{}:{}", CODE_ASSIST_BASE, method);
     let client = crate::utils::http::get_client();
     let response = client
-        .post(url)
+        .post(&url)
         .bearer_auth(access_token)
-        // .header("Host", "cloudcode-pa.googleapis.com") // 移除 Host header，因为已切换域名
-
         .header("User-Agent", crate::constants::USER_AGENT.as_str())
         .header("Content-Type", "application/json")
-        .json(&request_body)
+        .json(body)
         .send()
         .await
-        .map_err(|e| format!("loadCodeAssist 请求失败: {}", e))?;
-    
+        .map_err(|e| format!("{} 请求失败: {}", method, e))?;
+
     if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
-        return Err(format!("loadCodeAssist 返回错误 {}: {}", status, body));
+        return Err(format!("{} 返回错误 {}: {}", method, status, body));
     }
-    
-    let data: Value = response.json()
+
+    response
+        .json()
         .await
-        .map_err(|e| format!("解析响应失败: {}", e))?;
-    
+        .map_err(|e| format!("解析响应失败: {}", e))
+}
+
+/// cloudaicompanionProject 可能是字符串，也可能是 {"id": "..."} 对象
+fn extract_project_id(value: Option<&Value>) -> Option<String> {
+    let value = value?;
+    value
+        .as_str()
+        .or_else(|| value.get("id").and_then(|v| v.as_str()))
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// 选择 onboardUser 使用的 tier (优先 allowedTiers 中的默认项)
+fn default_tier_id(load_res: &Value) -> String {
+    load_res
+        .get("allowedTiers")
+        .and_then(|v| v.as_array())
+        .and_then(|tiers| {
+            tiers
+                .iter()
+                .find(|t| t.get("isDefault").and_then(|v| v.as_bool()) == Some(true))
+                .or_else(|| tiers.first())
+        })
+        .and_then(|t| t.get("id"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("free-tier")
+        .to_string()
+}
+
+fn code_assist_metadata() -> Value {
+    serde_json::json!({
+        "metadata": {
+            "ideType": "ANTIGRAVITY"
+        }
+    })
+}
+
+/// 使用 Antigravity 的 loadCodeAssist API 获取 project_id
+/// 这是获取 cloudaicompanionProject 的正确方式
+pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
+    let data = post_code_assist(access_token, "loadCodeAssist", &code_assist_metadata()).await?;
+
     // 提取 cloudaicompanionProject
-    if let Some(project_id) = data.get("cloudaicompanionProject")
-        .and_then(|v| v.as_str()) {
-        return Ok(project_id.to_string());
+    if let Some(project_id) = extract_project_id(data.get("cloudaicompanionProject")) {
+        return Ok(project_id);
     }
-    
+
     // 如果没有返回 project_id，说明账号无资格，返回错误以触发 token_manager 的稳定兜底逻辑
     Err("账号无资格获取官方 cloudaicompanionProject".to_string())
 }
+
+/// [NEW] 发现 project_id，账号尚未开通时调用 onboardUser 进行开通 (provision)
+pub async fn provision_project_id(access_token: &str) -> Result<String, String> {
+    let load_res = post_code_assist(access_token, "loadCodeAssist", &code_assist_metadata()).await?;
+    if let Some(project_id) = extract_project_id(load_res.get("cloudaicompanionProject")) {
+        return Ok(project_id);
+    }
+
+    let tier_id = default_tier_id(&load_res);
+    tracing::info!("[ProjectResolver] No project bound, onboarding with tier {}", tier_id);
+
+    let mut body = code_assist_metadata();
+    body["tierId"] = Value::String(tier_id);
+
+    // onboardUser 返回长任务 (LRO)，未完成时重复调用直到 done
+    for attempt in 0..ONBOARD_MAX_POLLS {
+        let op = post_code_assist(access_token, "onboardUser", &body).await?;
+        if op.get("done").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return extract_project_id(
+                op.get("response")
+                    .and_then(|r| r.get("cloudaicompanionProject")),
+            )
+            .ok_or_else(|| "onboardUser 未返回 cloudaicompanionProject".to_string());
+        }
+        if attempt + 1 < ONBOARD_MAX_POLLS {
+            tokio::time::sleep(ONBOARD_POLL_INTERVAL).await;
+        }
+    }
+
+    Err("onboardUser 超时，project 仍在开通中".to_string())
+}
+
+/// [NEW] 判断上游 403/404 是否由 project_id 失效/缺失引起 (需要重新发现 project)
+/// 模型灰度导致的 404 ("Requested entity was not found") 不包含 project 字样，不会命中
+pub fn is_project_error(status_code: u16, error_text: &str) -> bool {
+    if status_code != 403 && status_code != 404 {
+        return false;
+    }
+    let lower = error_text.to_lowercase();
+    if lower.contains("validation_required") {
+        return false;
+    }
+    lower.contains("project")
+        && (lower.contains("not found")
+            || lower.contains("not_found")
+            || lower.contains("does not exist")
+            || lower.contains("permission_denied")
+            || lower.contains("permission denied")
+            || lower.contains("has not been used")
+            || lower.contains("invalid project"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_project_id_and_tier() {
+        let load = serde_json::json!({
+            "cloudaicompanionProject": { "id": "proj-123" },
+            "allowedTiers": [
+                { "id": "legacy-tier" },
+                { "id": "standard-tier", "isDefault": true }
+            ]
+        });
+        assert_eq!(
+            extract_project_id(load.get("cloudaicompanionProject")).as_deref(),
+            Some("proj-123")
+        );
+        assert_eq!(default_tier_id(&load), "standard-tier");
+        assert_eq!(extract_project_id(Some(&Value::String("p".into()))).as_deref(), Some("p"));
+        assert_eq!(extract_project_id(Some(&Value::String("".into()))), None);
+        assert_eq!(default_tier_id(&serde_json::json!({})), "free-tier");
+    }
+
+    #[test]
+    fn test_is_project_error() {
+        assert!(is_project_error(
+            403,
+            r#"{"error":{"code":403,"message":"Permission denied on resource project foo.","status":"PERMISSION_DENIED"}}"#
+        ));
+        assert!(is_project_error(404, "Project 'projects/foo' not found"));
+        assert!(!is_project_error(404, "Requested entity was not found."));
+        assert!(!is_project_error(403, "VALIDATION_REQUIRED: verify your account for project x"));
+        assert!(!is_project_error(429, "project not found"));
+    }
+}
@@ -1,9 +1,22 @@
 use dashmap::DashMap;
 use std::time::{SystemTime, Duration};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::proxy::runtime_state::{self, PersistedCooldown, PersistedFailureCount};
+
+/// [NEW] 推送冷却事件到实时活动面板
+fn emit_cooldown(account_id: &str, model: Option<String>, reason: RateLimitReason, seconds: u64) {
+    crate::proxy::activity::emit(crate::proxy::activity::ProxyActivityEvent::CooldownApplied {
+        account_id: account_id.to_string(),
+        model,
+        reason: format!("{:?}", reason),
+        seconds,
+    });
+}
 
 /// 限流原因类型
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum RateLimitReason {
     /// 配额耗尽 (QUOTA_EXHAUSTED)
     QuotaExhausted,
@@ -130,6 +143,7 @@ impl RateLimitTracker {
         
         let key = self.get_limit_key(account_id, model.as_deref());
         self.limits.insert(key, info);
+        emit_cooldown(account_id, model.clone(), reason, retry_sec);
         
         if let Some(m) = &model {
             tracing::info!(
@@ -315,6 +329,7 @@ impl RateLimitTracker {
         };
 
         self.limits.insert(key, info.clone());
+        emit_cooldown(account_id, model, reason, retry_sec);
         
         tracing::warn!(
             "账号 {} [{}] 限流类型: {:?}, 重置延时: {}秒",
@@ -559,6 +574,63 @@ impl RateLimitTracker {
         self.limits.clear();
         tracing::warn!("🔄 Optimistic reset: Cleared all {} rate limit record(s)", count);
     }
+
+    /// [NEW] 导出仍在冷却中的限流记录与未过期的连续失败计数 (用于重启持久化)
+    pub fn export_state(&self) -> (Vec<PersistedCooldown>, Vec<PersistedFailureCount>) {
+        let now = SystemTime::now();
+        let cooldowns = self
+            .limits
+            .iter()
+            .filter(|entry| entry.value().reset_time > now)
+            .map(|entry| PersistedCooldown::from_info(entry.key(), entry.value()))
+            .collect();
+        let failures = self
+            .failure_counts
+            .iter()
+            .filter(|entry| {
+                now.duration_since(entry.value().1)
+                    .map(|d| d.as_secs() <= FAILURE_COUNT_EXPIRY_SECONDS)
+                    .unwrap_or(true)
+            })
+            .map(|entry| PersistedFailureCount {
+                account_id: entry.key().clone(),
+                count: entry.value().0,
+                last_failure_at: runtime_state::to_unix(entry.value().1),
+            })
+            .collect();
+        (cooldowns, failures)
+    }
+
+    /// [NEW] 恢复持久化的限流状态，已过期的记录直接丢弃；不覆盖启动后新产生的记录。返回恢复的冷却数
+    pub fn restore_state(
+        &self,
+        cooldowns: &[PersistedCooldown],
+        failures: &[PersistedFailureCount],
+    ) -> usize {
+        let now = SystemTime::now();
+        let mut restored = 0;
+        for cooldown in cooldowns {
+            let info = cooldown.to_info(now);
+            if info.reset_time <= now || self.limits.contains_key(&cooldown.key) {
+                continue;
+            }
+            self.limits.insert(cooldown.key.clone(), info);
+            restored += 1;
+        }
+        for failure in failures {
+            let last = runtime_state::from_unix(failure.last_failure_at);
+            let fresh = now
+                .duration_since(last)
+                .map(|d| d.as_secs() <= FAILURE_COUNT_EXPIRY_SECONDS)
+                .unwrap_or(true);
+            if fresh && failure.count > 0 {
+                self.failure_counts
+                    .entry(failure.account_id.clone())
+                    .or_insert((failure.count, last));
+            }
+        }
+        restored
+    }
 }
 
 impl Default for RateLimitTracker {
@@ -679,4 +751,29 @@ mod tests {
         let info = tracker.parse_from_error("acc2", 429, None, quota_body, None, &backoff_steps);
         assert_eq!(info.unwrap().retry_after_sec, 7200);
     }
+
+    #[test]
+    fn test_export_and_restore_state() {
+        let tracker = RateLimitTracker::new();
+        tracker.set_lockout_until(
+            "acc1",
+            SystemTime::now() + Duration::from_secs(300),
+            RateLimitReason::QuotaExhausted,
+            None,
+        );
+        tracker.set_lockout_until(
+            "acc2",
+            SystemTime::now() - Duration::from_secs(1),
+            RateLimitReason::RateLimitExceeded,
+            None,
+        );
+        let (cooldowns, failures) = tracker.export_state();
+        assert_eq!(cooldowns.len(), 1);
+        assert_eq!(cooldowns[0].key, "acc1");
+
+        let restored = RateLimitTracker::new();
+        assert_eq!(restored.restore_state(&cooldowns, &failures), 1);
+        assert!(restored.is_rate_limited("acc1", None));
+        assert!(!restored.is_rate_limited("acc2", None));
+    }
 }
@@ -0,0 +1,365 @@
+// 抓包请求重放
+// 根据请求 ID (即监控日志 ID / trace_id) 读取调试日志中保存的 v1internal 请求体，在指定账号上重新发送，
+// 并与当时记录的上游响应逐行对比，用于复现偶发的上游错误。需要在请求发生时已开启 debug_logging。
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::proxy::debug_logger;
+
+/// 超过该行数不做逐行对齐 (LCS 为 O(n*m))
+const MAX_DIFF_LINES: usize = 1000;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayRequest {
+    /// 请求 ID (x-agm-request-id / 监控日志 ID)
+    #[serde(alias = "traceId")]
+    pub trace_id: String,
+    /// 重放使用的账号，缺省为当前账号
+    #[serde(default, alias = "accountId")]
+    pub account_id: Option<String>,
+    /// 重放第几次尝试的请求体，缺省为最后一次
+    #[serde(default)]
+    pub attempt: Option<u64>,
+}
+
+/// 一次上游响应的摘要 (记录值或重放结果)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReplayResponse {
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub thinking: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReplayResponse {
+    /// 用于逐行对比的文本形式
+    fn render(&self) -> String {
+        let mut sections = vec![format!(
+            "[status] {}",
+            self.status.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string())
+        )];
+        if !self.thinking.is_empty() {
+            sections.push(format!("[thinking]\n{}", self.thinking));
+        }
+        if !self.content.is_empty() {
+            sections.push(format!("[content]\n{}", self.content));
+        }
+        if let Some(error) = &self.error {
+            sections.push(format!("[error]\n{}", error));
+        }
+        sections.join("\n")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayResult {
+    pub trace_id: String,
+    pub account_id: String,
+    pub account_email: String,
+    pub protocol: Option<String>,
+    pub mapped_model: Option<String>,
+    pub attempt: Option<u64>,
+    pub duration_ms: u64,
+    /// 当时记录的上游响应 (未开启抓包响应或请求中断时为空)
+    pub recorded: Option<ReplayResponse>,
+    pub replayed: ReplayResponse,
+    pub identical: bool,
+    /// recorded -> replayed 的逐行差异
+    pub diff: Vec<DiffLine>,
+}
+
+/// 抓包目录中读取到的一次请求
+struct Capture {
+    request: Value,
+    response: Option<ReplayResponse>,
+}
+
+fn payload_attempt(payload: &Value) -> Option<u64> {
+    payload
+        .get("attempt")
+        .or_else(|| payload.get("meta").and_then(|m| m.get("attempt")))
+        .and_then(|v| v.as_u64())
+}
+
+fn recorded_response(payload: &Value) -> ReplayResponse {
+    let text = |key: &str| payload.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    if payload.get("kind").and_then(|v| v.as_str()) == Some("upstream_response_error") {
+        return ReplayResponse {
+            status: payload.get("status").and_then(|v| v.as_u64()).map(|s| s as u16),
+            error: Some(text("error_text")),
+            ..Default::default()
+        };
+    }
+    ReplayResponse {
+        status: payload
+            .get("meta")
+            .and_then(|m| m.get("status"))
+            .and_then(|v| v.as_u64())
+            .map(|s| s as u16),
+        thinking: text("thinking_content"),
+        content: text("response_content"),
+        error: None,
+    }
+}
+
+/// 按文件名 (时间戳前缀) 顺序选出目标尝试的请求与对应响应
+async fn load_capture(dir: &Path, attempt: Option<u64>) -> Result<Capture, String> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("No captured request found in {}: {}", dir.display(), e))?;
+    let mut files = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        files.push(entry.path());
+    }
+    files.sort();
+
+    let mut request: Option<Value> = None;
+    let mut response: Option<Value> = None;
+    for path in files {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+        let is_request = name.ends_with("_v1internal_request.json");
+        let is_response =
+            name.ends_with("_upstream_response.json") || name.ends_with("_upstream_response_error.json");
+        if !is_request && !is_response {
+            continue;
+        }
+        let payload: Value = match tokio::fs::read(&path).await.map(|b| serde_json::from_slice(&b)) {
+            Ok(Ok(v)) => v,
+            _ => {
+                tracing::warn!("[Replay] Skipping unreadable capture file {}", path.display());
+                continue;
+            }
+        };
+        if attempt.is_some() && payload_attempt(&payload) != attempt {
+            continue;
+        }
+        if is_request {
+            request = Some(payload);
+        } else {
+            response = Some(payload);
+        }
+    }
+
+    let request = request.ok_or_else(|| match attempt {
+        Some(n) => format!("No captured v1internal request for attempt {}", n),
+        None => "No captured v1internal request (was debug logging enabled?)".to_string(),
+    })?;
+    // 只取与所选请求同一次尝试的响应
+    let response = response
+        .filter(|r| payload_attempt(r) == payload_attempt(&request))
+        .map(|r| recorded_response(&r));
+    Ok(Capture { request, response })
+}
+
+/// 按行做 LCS 对比
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let line = |op, text: &str| DiffLine { op, text: text.to_string() };
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if a.len() > MAX_DIFF_LINES || b.len() > MAX_DIFF_LINES {
+        return a
+            .iter()
+            .map(|&t| line(DiffOp::Delete, t))
+            .chain(b.iter().map(|&t| line(DiffOp::Insert, t)))
+            .collect();
+    }
+
+    // lcs[i][j] = a[i..] 与 b[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            result.push(line(DiffOp::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            result.push(line(DiffOp::Delete, a[i]));
+            i += 1;
+        } else {
+            result.push(line(DiffOp::Insert, b[j]));
+            j += 1;
+        }
+    }
+    result.extend(a[i..].iter().map(|&t| line(DiffOp::Delete, t)));
+    result.extend(b[j..].iter().map(|&t| line(DiffOp::Insert, t)));
+    result
+}
+
+/// 重放抓包中的 v1internal 请求并与记录的响应对比
+pub async fn replay_captured_request(req: &ReplayRequest) -> Result<ReplayResult, String> {
+    let app_config = crate::modules::config::load_app_config()?;
+    let dir = debug_logger::capture_dir(&app_config.proxy.debug_logging, &req.trace_id)?;
+    let capture = load_capture(&dir, req.attempt).await?;
+
+    let mut body = capture
+        .request
+        .get("v1internal_request")
+        .filter(|v| v.is_object())
+        .cloned()
+        .ok_or_else(|| "Captured request has no v1internal body".to_string())?;
+
+    let account = match req.account_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(id) => crate::modules::account::load_account(id)?,
+        None => crate::modules::account::get_current_account()?
+            .ok_or_else(|| "No current account selected".to_string())?,
+    };
+    let (access_token, project_id) = crate::modules::quota::get_valid_token_for_warmup(&account).await?;
+    // project 与账号绑定，换账号重放时必须替换
+    body["project"] = json!(project_id);
+
+    let upstream = crate::proxy::upstream::client::UpstreamClient::new(
+        Some(app_config.proxy.upstream_proxy.clone()),
+        None,
+    );
+    upstream.set_endpoints(&app_config.proxy.upstream_endpoints);
+    // 账号专属上游代理: 重放也必须走该账号自己的出口
+    if let Some(proxy) = account
+        .upstream_proxy
+        .clone()
+        .filter(|p| p.enabled && !p.url.trim().is_empty())
+    {
+        let account_id = account.id.clone();
+        upstream.set_account_proxy_lookup(std::sync::Arc::new(move |id: &str| {
+            (id == account_id).then(|| proxy.clone())
+        }));
+    }
+
+    tracing::info!(
+        "[Replay] Replaying {} (attempt {:?}) on account {}",
+        req.trace_id,
+        payload_attempt(&capture.request),
+        crate::proxy::upstream::client::mask_email(&account.email)
+    );
+    let started = Instant::now();
+    let replayed = match upstream
+        .call_v1_internal("streamGenerateContent", &access_token, body, Some("alt=sse"), Some(&account.id))
+        .await
+    {
+        Ok(call) => {
+            let status = call.response.status();
+            let text = call
+                .response
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read replay response: {}", e))?;
+            if status.is_success() {
+                let (thinking, content) = debug_logger::parse_sse_stream(&text);
+                ReplayResponse { status: Some(status.as_u16()), thinking, content, error: None }
+            } else {
+                ReplayResponse { status: Some(status.as_u16()), error: Some(text), ..Default::default() }
+            }
+        }
+        Err(e) => ReplayResponse { error: Some(e), ..Default::default() },
+    };
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let diff = match &capture.response {
+        Some(recorded) => diff_lines(&recorded.render(), &replayed.render()),
+        None => Vec::new(),
+    };
+    let identical = capture.response.is_some() && diff.iter().all(|d| d.op == DiffOp::Equal);
+
+    let field = |key: &str| capture.request.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+    Ok(ReplayResult {
+        trace_id: req.trace_id.clone(),
+        account_id: account.id.clone(),
+        account_email: account.email.clone(),
+        protocol: field("protocol"),
+        mapped_model: field("mapped_model"),
+        attempt: payload_attempt(&capture.request),
+        duration_ms,
+        recorded: capture.response,
+        replayed,
+        identical,
+        diff,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc", "a\nx\nc\nd");
+        let ops: Vec<_> = diff.iter().map(|d| (d.op, d.text.as_str())).collect();
+        assert_eq!(
+            ops,
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Delete, "b"),
+                (DiffOp::Insert, "x"),
+                (DiffOp::Equal, "c"),
+                (DiffOp::Insert, "d"),
+            ]
+        );
+        assert!(diff_lines("same", "same").iter().all(|d| d.op == DiffOp::Equal));
+    }
+
+    #[tokio::test]
+    async fn test_load_capture_picks_matching_attempt() {
+        let dir = std::env::temp_dir().join(format!("agm-replay-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, value: Value| {
+            std::fs::write(dir.join(name), serde_json::to_vec(&value).unwrap()).unwrap();
+        };
+        write(
+            "20260101_000000.000_t1_v1internal_request.json",
+            json!({ "kind": "v1internal_request", "attempt": 0, "v1internal_request": { "model": "m0" } }),
+        );
+        write(
+            "20260101_000001.000_t1_upstream_response_error.json",
+            json!({ "kind": "upstream_response_error", "attempt": 0, "status": 503, "error_text": "overloaded" }),
+        );
+        write(
+            "20260101_000002.000_t1_v1internal_request.json",
+            json!({ "kind": "v1internal_request", "attempt": 1, "v1internal_request": { "model": "m1" } }),
+        );
+        write(
+            "20260101_000003.000_t1_upstream_response.json",
+            json!({ "kind": "upstream_response", "meta": { "attempt": 1, "status": 200 }, "response_content": "hi" }),
+        );
+
+        let latest = load_capture(&dir, None).await.unwrap();
+        assert_eq!(latest.request["v1internal_request"]["model"], "m1");
+        assert_eq!(latest.response.as_ref().unwrap().content, "hi");
+
+        let first = load_capture(&dir, Some(0)).await.unwrap();
+        let recorded = first.response.unwrap();
+        assert_eq!(recorded.status, Some(503));
+        assert_eq!(recorded.error.as_deref(), Some("overloaded"));
+
+        assert!(load_capture(&dir, Some(5)).await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,150 @@
+// 反代运行时状态持久化
+// 停止反代 / 退出应用时把限流冷却、连续失败计数、健康分与粘性会话表写入磁盘，下次启动时恢复，
+// 避免重启后立即把请求路由到已知被限流的账号。与流式请求无关的状态才会被保存。
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::proxy::rate_limit::{RateLimitInfo, RateLimitReason};
+
+const STATE_FILE: &str = "proxy_runtime_state.json";
+/// 超过该时长的快照视为过期 (会话绑定与健康分已无参考价值)
+const MAX_SNAPSHOT_AGE_SECS: i64 = 24 * 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PersistedCooldown {
+    /// 限流 Key: "account_id" 或 "account_id:model"
+    pub key: String,
+    /// 冷却结束时间 (Unix 秒)
+    pub reset_at: i64,
+    pub reason: RateLimitReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PersistedFailureCount {
+    pub account_id: String,
+    pub count: u32,
+    /// 最近一次失败时间 (Unix 秒)
+    pub last_failure_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuntimeStateSnapshot {
+    pub saved_at: i64,
+    #[serde(default)]
+    pub cooldowns: Vec<PersistedCooldown>,
+    #[serde(default)]
+    pub failure_counts: Vec<PersistedFailureCount>,
+    #[serde(default)]
+    pub auth_failure_streaks: HashMap<String, u32>,
+    #[serde(default)]
+    pub health_scores: HashMap<String, f32>,
+    /// SessionID -> AccountID
+    #[serde(default)]
+    pub session_accounts: HashMap<String, String>,
+}
+
+pub fn to_unix(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+pub fn from_unix(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+impl PersistedCooldown {
+    pub fn from_info(key: &str, info: &RateLimitInfo) -> Self {
+        Self {
+            key: key.to_string(),
+            reset_at: to_unix(info.reset_time),
+            reason: info.reason,
+            model: info.model.clone(),
+        }
+    }
+
+    pub fn to_info(&self, now: SystemTime) -> RateLimitInfo {
+        let reset_time = from_unix(self.reset_at);
+        RateLimitInfo {
+            reset_time,
+            retry_after_sec: reset_time
+                .duration_since(now)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            detected_at: now,
+            reason: self.reason,
+            model: self.model.clone(),
+        }
+    }
+}
+
+fn state_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(STATE_FILE)
+}
+
+/// 写入快照 (先写临时文件再重命名，避免退出时写到一半)
+pub fn save(data_dir: &Path, snapshot: &RuntimeStateSnapshot) -> Result<(), String> {
+    let path = state_path(data_dir);
+    let tmp = path.with_extension("json.tmp");
+    let content = serde_json::to_string(snapshot)
+        .map_err(|e| format!("failed_to_serialize_runtime_state: {}", e))?;
+    std::fs::write(&tmp, content).map_err(|e| format!("failed_to_write_runtime_state: {}", e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("failed_to_write_runtime_state: {}", e))
+}
+
+/// 读取快照；文件缺失、损坏或过期时返回 None
+pub fn load(data_dir: &Path) -> Option<RuntimeStateSnapshot> {
+    let content = std::fs::read_to_string(state_path(data_dir)).ok()?;
+    let snapshot: RuntimeStateSnapshot = match serde_json::from_str(&content) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::warn!("[RuntimeState] Ignoring corrupted runtime state: {}", e);
+            return None;
+        }
+    };
+    let age = chrono::Utc::now().timestamp() - snapshot.saved_at;
+    if age > MAX_SNAPSHOT_AGE_SECS {
+        tracing::info!("[RuntimeState] Ignoring runtime state saved {}s ago", age);
+        return None;
+    }
+    Some(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("runtime_state_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut snapshot = RuntimeStateSnapshot {
+            saved_at: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        };
+        snapshot.cooldowns.push(PersistedCooldown {
+            key: "acc1:claude".to_string(),
+            reset_at: snapshot.saved_at + 600,
+            reason: RateLimitReason::QuotaExhausted,
+            model: Some("claude".to_string()),
+        });
+        snapshot.session_accounts.insert("sid-1".to_string(), "acc1".to_string());
+        save(&dir, &snapshot).unwrap();
+
+        let loaded = load(&dir).unwrap();
+        assert_eq!(loaded.cooldowns, snapshot.cooldowns);
+        assert_eq!(loaded.session_accounts.get("sid-1").map(String::as_str), Some("acc1"));
+
+        // 过期快照被忽略
+        snapshot.saved_at -= MAX_SNAPSHOT_AGE_SECS + 1;
+        save(&dir, &snapshot).unwrap();
+        assert!(load(&dir).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
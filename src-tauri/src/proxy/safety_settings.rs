@@ -0,0 +1,269 @@
+// Gemini 安全过滤 (safetySettings) 配置
+// 阈值优先级: 按模型的类别阈值 > 按模型的统一阈值 > 全局类别阈值 > 全局统一阈值 > GEMINI_SAFETY_THRESHOLD 环境变量 > OFF
+// Gemini 原生协议请求中客户端显式传入的 safetySettings 优先，缺失的类别按配置补齐。
+use serde_json::{json, Value};
+use std::sync::{OnceLock, RwLock};
+
+use crate::proxy::config::{SafetyRule, SafetySettingsConfig};
+
+/// 下发给上游的安全类别
+pub const HARM_CATEGORIES: [&str; 5] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+    "HARM_CATEGORY_CIVIC_INTEGRITY",
+];
+
+/// Safety threshold levels for Gemini API
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SafetyThreshold {
+    /// Disable all safety filters (default for proxy compatibility)
+    Off,
+    /// Block low probability and above
+    BlockLowAndAbove,
+    /// Block medium probability and above
+    BlockMediumAndAbove,
+    /// Only block high probability content
+    BlockOnlyHigh,
+    /// Don't block anything (BLOCK_NONE)
+    BlockNone,
+}
+
+impl SafetyThreshold {
+    /// 解析阈值: 支持简写 (off/none/low/medium/high) 与 Gemini 原始名称
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "OFF" => Some(SafetyThreshold::Off),
+            "LOW" | "BLOCK_LOW_AND_ABOVE" => Some(SafetyThreshold::BlockLowAndAbove),
+            "MEDIUM" | "BLOCK_MEDIUM_AND_ABOVE" => Some(SafetyThreshold::BlockMediumAndAbove),
+            "HIGH" | "BLOCK_ONLY_HIGH" => Some(SafetyThreshold::BlockOnlyHigh),
+            "NONE" | "BLOCK_NONE" => Some(SafetyThreshold::BlockNone),
+            _ => None,
+        }
+    }
+
+    /// Get threshold from GEMINI_SAFETY_THRESHOLD environment variable or default to Off
+    pub fn from_env() -> Self {
+        std::env::var("GEMINI_SAFETY_THRESHOLD")
+            .ok()
+            .and_then(|v| Self::parse(&v))
+            .unwrap_or(SafetyThreshold::Off)
+    }
+
+    /// Convert to Gemini API threshold string
+    pub fn to_gemini_threshold(&self) -> &'static str {
+        match self {
+            SafetyThreshold::Off => "OFF",
+            SafetyThreshold::BlockLowAndAbove => "BLOCK_LOW_AND_ABOVE",
+            SafetyThreshold::BlockMediumAndAbove => "BLOCK_MEDIUM_AND_ABOVE",
+            SafetyThreshold::BlockOnlyHigh => "BLOCK_ONLY_HIGH",
+            SafetyThreshold::BlockNone => "BLOCK_NONE",
+        }
+    }
+}
+
+fn config() -> &'static RwLock<SafetySettingsConfig> {
+    static CONFIG: OnceLock<RwLock<SafetySettingsConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(SafetySettingsConfig::default()))
+}
+
+/// 更新安全过滤配置 (启动与配置热更新时调用)
+pub fn update_safety_settings_config(new_config: SafetySettingsConfig) {
+    if let Ok(mut current) = config().write() {
+        *current = new_config;
+    }
+}
+
+/// 校验配置中的阈值与类别名称
+pub fn validate_safety_settings_config(cfg: &SafetySettingsConfig) -> Result<(), String> {
+    let rules = std::iter::once(("global", &cfg.global))
+        .chain(cfg.per_model.iter().map(|(model, rule)| (model.as_str(), rule)));
+    for (scope, rule) in rules {
+        let thresholds = rule.threshold.iter().chain(rule.categories.values());
+        for threshold in thresholds {
+            if SafetyThreshold::parse(threshold).is_none() {
+                return Err(format!("Invalid safety threshold '{}' ({})", threshold, scope));
+            }
+        }
+        for category in rule.categories.keys() {
+            if normalize_category(category).is_none() {
+                return Err(format!("Unknown safety category '{}' ({})", category, scope));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 类别名称规范化: 支持省略 HARM_CATEGORY_ 前缀及小写写法
+fn normalize_category(category: &str) -> Option<&'static str> {
+    let upper = category.trim().to_ascii_uppercase();
+    let full = if upper.starts_with("HARM_CATEGORY_") {
+        upper
+    } else {
+        format!("HARM_CATEGORY_{}", upper)
+    };
+    HARM_CATEGORIES.iter().copied().find(|c| *c == full)
+}
+
+/// 按模型查找规则：精确匹配优先，其次最长的通配符模式
+fn rule_for_model<'a>(cfg: &'a SafetySettingsConfig, model: &str) -> Option<&'a SafetyRule> {
+    if let Some(rule) = cfg.per_model.get(model) {
+        return Some(rule);
+    }
+    cfg.per_model
+        .iter()
+        .filter(|(pattern, _)| {
+            pattern.contains('*') && crate::proxy::common::model_mapping::wildcard_match(pattern, model)
+        })
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, rule)| rule)
+}
+
+fn rule_threshold(rule: &SafetyRule, category: &str) -> Option<SafetyThreshold> {
+    rule.categories
+        .iter()
+        .find(|(name, _)| normalize_category(name) == Some(category))
+        .and_then(|(_, threshold)| SafetyThreshold::parse(threshold))
+        .or_else(|| rule.threshold.as_deref().and_then(SafetyThreshold::parse))
+}
+
+fn resolve_threshold(cfg: &SafetySettingsConfig, model: &str, category: &str) -> SafetyThreshold {
+    rule_for_model(cfg, model)
+        .and_then(|rule| rule_threshold(rule, category))
+        .or_else(|| rule_threshold(&cfg.global, category))
+        .unwrap_or_else(SafetyThreshold::from_env)
+}
+
+fn build_with_config(cfg: &SafetySettingsConfig, model: &str) -> Value {
+    let settings: Vec<Value> = HARM_CATEGORIES
+        .iter()
+        .map(|category| {
+            json!({
+                "category": category,
+                "threshold": resolve_threshold(cfg, model, category).to_gemini_threshold()
+            })
+        })
+        .collect();
+    Value::Array(settings)
+}
+
+/// 为指定 (映射后的) 模型生成 safetySettings
+pub fn build_safety_settings(model: &str) -> Value {
+    match config().read() {
+        Ok(cfg) => build_with_config(&cfg, model),
+        Err(_) => build_with_config(&SafetySettingsConfig::default(), model),
+    }
+}
+
+/// Gemini 原生请求: 保留客户端传入的类别，缺失的类别按配置补齐
+pub fn apply_to_native_request(inner_request: &mut Value, model: &str) {
+    let defaults = build_safety_settings(model);
+    merge_client_settings(inner_request, defaults);
+}
+
+fn merge_client_settings(inner_request: &mut Value, defaults: Value) {
+    let Some(obj) = inner_request.as_object_mut() else {
+        return;
+    };
+    let mut merged: Vec<Value> = obj
+        .get("safetySettings")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let has_category = |settings: &[Value], category: &str| {
+        settings
+            .iter()
+            .any(|s| s.get("category").and_then(|c| c.as_str()) == Some(category))
+    };
+    for setting in defaults.as_array().into_iter().flatten() {
+        let category = setting["category"].as_str().unwrap_or_default();
+        if !has_category(&merged, category) {
+            merged.push(setting.clone());
+        }
+    }
+    obj.insert("safetySettings".to_string(), Value::Array(merged));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn threshold_of(settings: &Value, category: &str) -> String {
+        settings
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|s| s["category"] == category)
+            .map(|s| s["threshold"].as_str().unwrap().to_string())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_threshold_precedence() {
+        let cfg = SafetySettingsConfig {
+            global: SafetyRule {
+                threshold: Some("high".to_string()),
+                categories: HashMap::from([("harassment".to_string(), "medium".to_string())]),
+            },
+            per_model: HashMap::from([
+                (
+                    "gemini-3-*".to_string(),
+                    SafetyRule {
+                        threshold: Some("BLOCK_NONE".to_string()),
+                        categories: HashMap::new(),
+                    },
+                ),
+                (
+                    "gemini-3-pro-*".to_string(),
+                    SafetyRule {
+                        threshold: None,
+                        categories: HashMap::from([(
+                            "HARM_CATEGORY_DANGEROUS_CONTENT".to_string(),
+                            "low".to_string(),
+                        )]),
+                    },
+                ),
+            ]),
+        };
+
+        let flash = build_with_config(&cfg, "gemini-2.5-flash");
+        assert_eq!(threshold_of(&flash, "HARM_CATEGORY_HARASSMENT"), "BLOCK_MEDIUM_AND_ABOVE");
+        assert_eq!(threshold_of(&flash, "HARM_CATEGORY_HATE_SPEECH"), "BLOCK_ONLY_HIGH");
+
+        let g3 = build_with_config(&cfg, "gemini-3-flash");
+        assert_eq!(threshold_of(&g3, "HARM_CATEGORY_HARASSMENT"), "BLOCK_NONE");
+
+        // 最长通配符优先；未覆盖的类别回落到全局配置
+        let pro = build_with_config(&cfg, "gemini-3-pro-high");
+        assert_eq!(threshold_of(&pro, "HARM_CATEGORY_DANGEROUS_CONTENT"), "BLOCK_LOW_AND_ABOVE");
+        assert_eq!(threshold_of(&pro, "HARM_CATEGORY_HARASSMENT"), "BLOCK_MEDIUM_AND_ABOVE");
+    }
+
+    #[test]
+    fn test_merge_keeps_client_settings() {
+        let mut req = json!({
+            "contents": [],
+            "safetySettings": [{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH" }]
+        });
+        merge_client_settings(&mut req, build_with_config(&SafetySettingsConfig::default(), "gemini-3-flash"));
+
+        let settings = &req["safetySettings"];
+        assert_eq!(settings.as_array().unwrap().len(), HARM_CATEGORIES.len());
+        assert_eq!(threshold_of(settings, "HARM_CATEGORY_HARASSMENT"), "BLOCK_ONLY_HIGH");
+    }
+
+    #[test]
+    fn test_validate_safety_settings_config() {
+        let mut cfg = SafetySettingsConfig::default();
+        assert!(validate_safety_settings_config(&cfg).is_ok());
+
+        cfg.global.threshold = Some("strict".to_string());
+        assert!(validate_safety_settings_config(&cfg).is_err());
+
+        cfg.global.threshold = None;
+        cfg.global.categories.insert("violence".to_string(), "low".to_string());
+        assert!(validate_safety_settings_config(&cfg).is_err());
+    }
+}
@@ -31,6 +31,8 @@ impl ProxySecurityConfig {
                     ProxyAuthMode::Off
                 }
             }
+            // [NEW] 开放局域网访问时不允许关闭鉴权，至少保护除健康检查外的所有路由
+            ProxyAuthMode::Off if self.allow_lan_access => ProxyAuthMode::AllExceptHealth,
             ref other => other.clone(),
         }
     }
@@ -68,5 +70,21 @@ mod tests {
             ProxyAuthMode::AllExceptHealth
         ));
     }
+
+    #[test]
+    fn off_mode_is_enforced_when_lan_access_enabled() {
+        let s = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Off,
+            api_key: "sk-test".to_string(),
+            admin_password: None,
+            allow_lan_access: true,
+            port: 8080,
+            security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+        };
+        assert!(matches!(
+            s.effective_auth_mode(),
+            ProxyAuthMode::AllExceptHealth
+        ));
+    }
 }
 
@@ -10,7 +10,7 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::OnceLock;
 use tokio::sync::oneshot;
@@ -210,6 +210,26 @@ fn to_account_response(
     }
 }
 
+/// 连接 IO 抽象 (明文 TCP 或 TLS)
+trait ConnectionIo: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send {}
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send> ConnectionIo for T {}
+
+/// 活跃连接计数守卫 (连接任务结束时自动减一)
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl ConnectionGuard {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 /// Axum 服务器实例
 #[derive(Clone)]
 pub struct AxumServer {
@@ -227,6 +247,9 @@ pub struct AxumServer {
     pub token_manager: Arc<TokenManager>, // [NEW] 暴露出 TokenManager 供反代服务复用
     pub proxy_pool_state: Arc<tokio::sync::RwLock<crate::proxy::config::ProxyPoolConfig>>, // [NEW] 代理池配置状态
     pub proxy_pool_manager: Arc<crate::proxy::proxy_pool::ProxyPoolManager>, // [NEW] 暴露代理池管理器供命令调用
+    drain_tx: Arc<tokio::sync::watch::Sender<bool>>, // [NEW] 通知存量连接进入优雅关闭
+    active_connections: Arc<AtomicUsize>, // [NEW] 当前存活的连接数 (含流式请求)
+    connections_cancel: tokio_util::sync::CancellationToken, // [NEW] 宽限期结束后强制结束存量连接任务
 }
 
 impl AxumServer {
@@ -235,12 +258,18 @@ impl AxumServer {
             let mut m = self.custom_mapping.write().await;
             *m = config.custom_mapping.clone();
         }
+        crate::proxy::common::model_mapping::update_mapping_rules(&config.model_mapping_rules);
+        crate::proxy::common::model_mapping::update_quota_group_overrides(&config.quota_group_overrides);
         tracing::debug!("模型映射 (Custom) 已全量热更新");
     }
 
     /// 更新代理配置
+    /// [NEW] 上游代理变化时重建 UpstreamClient 的默认 HTTP 客户端，否则新地址不会生效
     pub async fn update_proxy(&self, new_config: crate::proxy::config::UpstreamProxyConfig) {
         let mut proxy = self.proxy_state.write().await;
+        if *proxy != new_config {
+            self.upstream.rebuild_default_client(new_config.clone());
+        }
         *proxy = new_config;
         tracing::info!("上游代理配置已热更新");
     }
@@ -249,9 +278,71 @@ impl AxumServer {
     pub async fn update_proxy_pool(&self, new_config: crate::proxy::config::ProxyPoolConfig) {
         let mut pool = self.proxy_pool_state.write().await;
         *pool = new_config;
+        // 代理条目可能被编辑，丢弃按条目缓存的客户端
+        self.upstream.clear_client_cache();
         tracing::info!("代理池配置已热更新");
     }
 
+    /// [NEW] 更新上游 v1internal 端点 (主端点覆盖 + 备用端点)
+    pub fn update_upstream_endpoints(&self, config: &crate::proxy::config::ProxyConfig) {
+        self.upstream.set_endpoints(&config.upstream_endpoints);
+    }
+
+    /// [NEW] 更新上游客户端连接池 / HTTP2 参数 (仅在参数变化时重建客户端)
+    pub fn update_upstream_client(&self, config: &crate::proxy::config::ProxyConfig) {
+        self.upstream.set_tuning(config.upstream_client.clone());
+    }
+
+    /// [NEW] 将保存后的 ProxyConfig 整体应用到运行中的服务 (无需重启)
+    /// 端口、监听地址、TLS、请求体上限仍需重启才能生效
+    pub async fn apply_config(&self, config: &crate::proxy::config::ProxyConfig) {
+        self.update_mapping(config).await;
+        self.update_proxy(config.upstream_proxy.clone()).await;
+        self.update_security(config).await;
+        self.update_zai(config).await;
+        self.update_experimental(config).await;
+        self.update_debug_logging(config).await;
+        self.update_user_agent(config).await;
+        self.update_upstream_endpoints(config);
+        self.update_upstream_client(config);
+        self.update_proxy_pool(config.proxy_pool.clone()).await;
+        self.token_manager
+            .update_sticky_config(config.scheduling.clone())
+            .await;
+        self.token_manager
+            .update_usage_limits(config.usage_limits.clone())
+            .await;
+        self.token_manager
+            .update_account_groups(config.account_groups.clone())
+            .await;
+        self.token_manager
+            .update_auto_disable_config(config.auto_disable.clone())
+            .await;
+        if self
+            .token_manager
+            .update_account_subset(&config.account_subset)
+            .await
+        {
+            if let Err(e) = self.token_manager.load_accounts().await {
+                tracing::warn!("账号子集变化后重新加载账号失败: {}", e);
+            }
+        }
+        crate::proxy::update_thinking_budget_config(config.thinking_budget.clone());
+        crate::proxy::update_global_system_prompt_config(config.global_system_prompt.clone());
+        crate::proxy::update_image_thinking_mode(config.image_thinking_mode.clone());
+        crate::proxy::context_cache::update_bindings(config.context_cache.bindings.clone());
+        crate::proxy::middleware::client_rate_limit::update_client_rate_limit_config(
+            config.client_rate_limit.clone(),
+        );
+        crate::proxy::upstream::retry::update_retry_policy_config(config.retry_policy.clone());
+        crate::proxy::safety_settings::update_safety_settings_config(config.safety_settings.clone());
+        crate::proxy::mcp_bridge::update_mcp_bridge_config(config.mcp_bridge.clone());
+        crate::proxy::common::context_trim::update_context_trim_config(config.context_trim.clone());
+        crate::proxy::redaction::update_redaction_config(config.redaction.clone());
+        crate::proxy::common::system_prompt::update_system_prompt_injection_config(config.system_prompt_injection.clone());
+        tracing::info!("反代服务配置已整体热更新");
+    }
+
     pub async fn update_security(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut sec = self.security_state.write().await;
         *sec = crate::proxy::ProxySecurityConfig::from_proxy_config(config);
@@ -267,6 +358,7 @@ impl AxumServer {
     pub async fn update_experimental(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut exp = self.experimental.write().await;
         *exp = config.experimental.clone();
+        crate::proxy::SignatureCache::global().set_enabled(exp.enable_signature_cache);
         tracing::info!("实验性配置已热更新");
     }
 
@@ -280,6 +372,8 @@ impl AxumServer {
         self.upstream
             .set_user_agent_override(config.user_agent_override.clone())
             .await;
+        let client_version = crate::constants::resolve_client_version(&config.client_version);
+        self.upstream.set_client_version(Some(client_version)).await;
         tracing::info!("User-Agent 配置已热更新: {:?}", config.user_agent_override);
     }
 
@@ -296,6 +390,9 @@ impl AxumServer {
         token_manager: Arc<TokenManager>,
         custom_mapping: std::collections::HashMap<String, String>,
         _request_timeout: u64,
+        max_body_size_mb: u64,
+        tls_config: crate::proxy::config::TlsConfig,
+        cors_config: crate::proxy::config::CorsConfig,
         upstream_proxy: crate::proxy::config::UpstreamProxyConfig,
         user_agent_override: Option<String>,
         security_config: crate::proxy::ProxySecurityConfig,
@@ -319,6 +416,7 @@ impl AxumServer {
         let zai_state = Arc::new(RwLock::new(zai_config));
         let provider_rr = Arc::new(AtomicUsize::new(0));
         let zai_vision_mcp_state = Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new());
+        crate::proxy::SignatureCache::global().set_enabled(experimental_config.enable_signature_cache);
         let experimental_state = Arc::new(RwLock::new(experimental_config));
         let debug_logging_state = Arc::new(RwLock::new(debug_logging));
         let is_running_state = Arc::new(RwLock::new(true));
@@ -336,6 +434,8 @@ impl AxumServer {
                     Some(upstream_proxy.clone()),
                     Some(proxy_pool_manager.clone()),
                 ));
+                // 账号专属上游代理随账号数据加载到 TokenManager
+                u.set_account_proxy_lookup(token_manager.account_proxy_lookup());
                 // 初始化 User-Agent 覆盖
                 if user_agent_override.is_some() {
                     u.set_user_agent_override(user_agent_override).await;
@@ -364,8 +464,8 @@ impl AxumServer {
         // 构建路由 - 使用新架构的 handlers！
         use crate::proxy::handlers;
         use crate::proxy::middleware::{
-            admin_auth_middleware, auth_middleware, cors_layer, ip_filter_middleware,
-            monitor_middleware, service_status_middleware,
+            admin_auth_middleware, auth_middleware, client_rate_limit_middleware, cors_layer,
+            ip_filter_middleware, monitor_middleware, service_status_middleware,
         };
 
         // 1. 构建主 AI 代理路由 (遵循 auth_mode 配置)
@@ -374,6 +474,7 @@ impl AxumServer {
             .route("/healthz", get(health_check_handler))
             // OpenAI Protocol
             .route("/v1/models", get(handlers::openai::handle_list_models))
+            .route("/v1/models/:model", get(handlers::openai::handle_get_model))
             .route(
                 "/v1/chat/completions",
                 post(handlers::openai::handle_chat_completions),
@@ -395,6 +496,8 @@ impl AxumServer {
                 "/v1/audio/transcriptions",
                 post(handlers::audio::handle_audio_transcription),
             ) // 音频转录 API
+            .route("/v1/audio/speech", post(handlers::audio::handle_audio_speech)) // 语音合成 API
+            .route("/v1/ws", get(handlers::realtime::handle_realtime_ws)) // WebSocket 流式桥接
             // Claude Protocol
             .route("/v1/messages", post(handlers::claude::handle_messages))
             .route(
@@ -415,9 +518,11 @@ impl AxumServer {
                 "/mcp/zai-mcp-server/mcp",
                 any(handlers::mcp::handle_zai_mcp_server),
             )
+            // MCP 工具桥接 (用户配置的 MCP 服务器)
+            .route("/v1/mcp/tools", get(handlers::mcp_bridge::handle_list_tools))
             // Gemini Protocol (Native)
             .route("/v1beta/models", get(handlers::gemini::handle_list_models))
-            // Handle both GET (get info) and POST (generateContent with colon) at the same route
+            // Handle both GET (get info) and POST (generateContent / streamGenerateContent / countTokens with colon) at the same route
             .route(
                 "/v1beta/models/:model",
                 get(handlers::gemini::handle_get_model).post(handlers::gemini::handle_generate),
@@ -431,13 +536,15 @@ impl AxumServer {
                 post(handlers::common::handle_detect_model),
             )
             .route("/internal/warmup", post(handlers::warmup::handle_warmup)) // 内部预热端点
+            .route("/debug/transform", post(handlers::debug::handle_debug_transform)) // Dry-run 转换 (需开启 debug_logging.transform_endpoint)
             .route("/v1/api/event_logging/batch", post(silent_ok_handler))
             .route("/v1/api/event_logging", post(silent_ok_handler))
             // 应用 AI 服务特定的层
             // 注意：Axum layer 执行顺序是从下往上（洋葱模型）
-            // 请求: ip_filter -> auth -> monitor -> handler
-            // 响应: handler -> monitor -> auth -> ip_filter
-            // monitor 需要在 auth 之后执行才能获取 UserTokenIdentity
+            // 请求: ip_filter -> auth -> monitor -> rate_limit -> handler
+            // 响应: handler -> rate_limit -> monitor -> auth -> ip_filter
+            // monitor 需要在 auth 之后执行才能获取 UserTokenIdentity；限流在 monitor 内侧，429 也会被记录
+            .layer(axum::middleware::from_fn(client_rate_limit_middleware))
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 monitor_middleware,
@@ -525,6 +632,12 @@ impl AxumServer {
             .route("/proxy/start", post(admin_start_proxy_service))
             .route("/proxy/stop", post(admin_stop_proxy_service))
             .route("/proxy/mapping", post(admin_update_model_mapping))
+            .route(
+                "/proxy/mapping-rules",
+                get(admin_list_model_mapping_rules).post(admin_save_model_mapping_rule),
+            )
+            .route("/proxy/mapping-rules/:ruleId", delete(admin_delete_model_mapping_rule))
+            .route("/proxy/mapping/resolve", post(admin_resolve_model_mapping))
             .route("/proxy/api-key/generate", post(admin_generate_api_key))
             .route(
                 "/proxy/session-bindings/clear",
@@ -539,12 +652,34 @@ impl AxumServer {
                 "/proxy/preferred-account",
                 get(admin_get_preferred_account).post(admin_set_preferred_account),
             )
+            .route("/proxy/account-metrics", get(admin_get_account_metrics))
+            .route(
+                "/proxy/pinned-account",
+                get(admin_get_pinned_account)
+                    .post(admin_pin_account)
+                    .delete(admin_unpin_account),
+            )
             .route("/accounts/oauth/prepare", post(admin_prepare_oauth_url))
             .route("/accounts/oauth/start", post(admin_start_oauth_login))
             .route("/accounts/oauth/complete", post(admin_complete_oauth_login))
             .route("/accounts/oauth/cancel", post(admin_cancel_oauth_login))
             .route("/accounts/oauth/submit-code", post(admin_submit_oauth_code))
+            .route("/accounts/oauth/manual/begin", post(admin_begin_manual_oauth))
+            .route("/accounts/oauth/manual/finish", post(admin_finish_manual_oauth))
             .route("/accounts/oauth/clients", get(admin_list_oauth_clients))
+            .route("/accounts/db/backup", post(admin_backup_account_db))
+            .route("/accounts/db/vacuum", post(admin_vacuum_account_db))
+            .route(
+                "/backups",
+                get(admin_list_backup_snapshots).post(admin_create_backup_snapshot),
+            )
+            .route("/backups/:snapshotId", delete(admin_delete_backup_snapshot))
+            .route("/backups/:snapshotId/restore", post(admin_restore_backup_snapshot))
+            .route(
+                "/context-caches",
+                get(admin_list_cached_contents).post(admin_create_cached_content),
+            )
+            .route("/context-caches/:cacheId", delete(admin_delete_cached_content))
             .route(
                 "/accounts/oauth/client",
                 get(admin_get_active_oauth_client).post(admin_set_active_oauth_client),
@@ -566,10 +701,12 @@ impl AxumServer {
             .route("/proxy/cloudflared/stop", post(admin_cloudflared_stop))
             .route("/system/open-folder", post(admin_open_folder))
             .route("/proxy/stats", get(admin_get_proxy_stats))
+            .route("/proxy/benchmark", post(admin_run_proxy_benchmark))
             .route("/logs", get(admin_get_proxy_logs_filtered))
             .route("/logs/count", get(admin_get_proxy_logs_count_filtered))
             .route("/logs/clear", post(admin_clear_proxy_logs))
             .route("/logs/:logId", get(admin_get_proxy_log_detail))
+            .route("/logs/replay", post(admin_replay_captured_request))
             // Debug Console (Log Bridge)
             .route("/debug/enable", post(admin_enable_debug_console))
             .route("/debug/disable", post(admin_disable_debug_console))
@@ -586,6 +723,9 @@ impl AxumServer {
             )
             .route("/stats/token/summary", get(admin_get_token_stats_summary))
             .route("/stats/token/by-model", get(admin_get_token_stats_by_model))
+            .route("/stats/quota/history", get(admin_get_quota_history))
+            .route("/stats/quota/trend/daily", get(admin_get_quota_trend_daily))
+            .route("/stats/quota/resets", get(admin_get_quota_reset_predictions))
             .route(
                 "/stats/token/model-trend/hourly",
                 get(admin_get_token_stats_model_trend_hourly),
@@ -632,12 +772,18 @@ impl AxumServer {
             )
             .route("/system/antigravity/path", get(admin_get_antigravity_path))
             .route("/system/antigravity/args", get(admin_get_antigravity_args))
+            .route("/system/antigravity/version", get(admin_check_antigravity_version))
+            .route("/system/client-version", get(admin_get_client_version_info))
             .route("/system/cache/clear", post(admin_clear_antigravity_cache))
+            // MCP 工具桥接: 直接执行工具仅限管理接口
+            .route("/mcp/tools/call", post(handlers::mcp_bridge::handle_call_tool))
             .route(
                 "/system/cache/paths",
                 get(admin_get_antigravity_cache_paths),
             )
             .route("/system/logs/clear-cache", post(admin_clear_log_cache))
+            .route("/system/logs", get(admin_query_logs))
+            .route("/system/logs/files", get(admin_list_log_files))
             // Security / IP Monitoring
             .route("/security/logs", get(admin_get_ip_access_logs))
             .route("/security/logs/clear", post(admin_clear_ip_access_logs))
@@ -664,11 +810,11 @@ impl AxumServer {
             ));
 
         // 3. 整合并应用全局层
-        // 从环境变量读取 body 大小限制，默认 50MB
+        // body 大小限制: 环境变量 ABV_MAX_BODY_SIZE (字节) 优先，否则使用配置值
         let max_body_size: usize = std::env::var("ABV_MAX_BODY_SIZE")
             .ok()
             .and_then(|s| s.parse().ok())
-            .unwrap_or(100 * 1024 * 1024); // 默认 100MB
+            .unwrap_or((max_body_size_mb.max(1) * 1024 * 1024) as usize);
         tracing::info!("请求体大小限制: {} MB", max_body_size / 1024 / 1024);
 
         let app = Router::new()
@@ -681,8 +827,17 @@ impl AxumServer {
                 state.clone(),
                 service_status_middleware,
             ))
-            .layer(cors_layer())
-            .layer(DefaultBodyLimit::max(max_body_size)) // 放宽 body 大小限制
+            .layer(cors_layer(&cors_config))
+            .layer(DefaultBodyLimit::max(max_body_size))
+            // 超限时返回协议对应的 413 错误 (外层，确保能改写所有 413)
+            .layer(axum::middleware::from_fn_with_state(
+                max_body_size,
+                crate::proxy::middleware::body_limit_middleware,
+            ))
+            // [NEW] 请求 ID (最外层，所有响应都携带 x-agm-request-id)
+            .layer(axum::middleware::from_fn(
+                crate::proxy::middleware::request_id_middleware,
+            ))
             .with_state(state.clone());
 
         // 静态文件托管 (用于 Headless/Docker 模式)
@@ -702,10 +857,23 @@ impl AxumServer {
             .await
             .map_err(|e| format!("地址 {} 绑定失败: {}", addr, e))?;
 
-        tracing::info!("反代服务器启动在 http://{}", addr);
+        // [NEW] 可选 HTTPS
+        let tls_acceptor = if tls_config.enabled {
+            Some(crate::proxy::tls::build_tls_acceptor(&tls_config, &host)?)
+        } else {
+            None
+        };
+        let scheme = if tls_acceptor.is_some() { "https" } else { "http" };
+
+        tracing::info!("反代服务器启动在 {}://{}", scheme, addr);
 
         // 创建关闭通道
         let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+        // [NEW] 连接排空通道与活跃连接计数
+        let (drain_tx, drain_rx) = tokio::sync::watch::channel(false);
+        let drain_tx = Arc::new(drain_tx);
+        let active_connections = Arc::new(AtomicUsize::new(0));
+        let connections_cancel = tokio_util::sync::CancellationToken::new();
 
         let server_instance = Self {
             shutdown_tx: Arc::new(tokio::sync::Mutex::new(Some(shutdown_tx))),
@@ -721,6 +889,9 @@ impl AxumServer {
             token_manager: token_manager.clone(),
             proxy_pool_state,
             proxy_pool_manager,
+            drain_tx,
+            active_connections: active_connections.clone(),
+            connections_cancel: connections_cancel.clone(),
         };
 
         // 在新任务中启动服务器
@@ -734,8 +905,6 @@ impl AxumServer {
                     res = listener.accept() => {
                         match res {
                             Ok((stream, remote_addr)) => {
-                                let io = TokioIo::new(stream);
-                                
                                 // 注入 ConnectInfo (用于获取真实 IP)
                                 use tower::ServiceExt;
                                 use hyper::body::Incoming;
@@ -745,14 +914,49 @@ impl AxumServer {
                                 });
 
                                 let service = TowerToHyperService::new(app_with_info);
+                                let mut drain_rx = drain_rx.clone();
+                                let conn_guard = ConnectionGuard::new(active_connections.clone());
+                                let tls_acceptor = tls_acceptor.clone();
+                                let cancel = connections_cancel.clone();
 
                                 tokio::task::spawn(async move {
-                                    if let Err(err) = http1::Builder::new()
-                                        .serve_connection(io, service)
-                                        .with_upgrades() // 支持 WebSocket (如果以后需要)
-                                        .await
-                                    {
-                                        debug!("连接处理结束或出错: {:?}", err);
+                                    let _conn_guard = conn_guard;
+                                    let serve = async move {
+                                        // TLS 握手放在连接任务内，避免阻塞 accept 循环
+                                        let stream: Box<dyn ConnectionIo> = match tls_acceptor {
+                                            Some(acceptor) => match acceptor.accept(stream).await {
+                                                Ok(tls_stream) => Box::new(tls_stream),
+                                                Err(e) => {
+                                                    debug!("TLS 握手失败 ({}): {:?}", remote_addr, e);
+                                                    return;
+                                                }
+                                            },
+                                            None => Box::new(stream),
+                                        };
+                                        let io = TokioIo::new(stream);
+                                        let conn = http1::Builder::new()
+                                            .serve_connection(io, service)
+                                            .with_upgrades(); // 支持 WebSocket (如果以后需要)
+                                        tokio::pin!(conn);
+
+                                        let result = tokio::select! {
+                                            res = conn.as_mut() => res,
+                                            _ = drain_rx.wait_for(|draining| *draining) => {
+                                                // 收到排空信号: 不再接受新请求，等待当前请求 (含流式) 完成
+                                                conn.as_mut().graceful_shutdown();
+                                                conn.await
+                                            }
+                                        };
+                                        if let Err(err) = result {
+                                            debug!("连接处理结束或出错: {:?}", err);
+                                        }
+                                    };
+                                    tokio::select! {
+                                        // 宽限期结束仍未完成: 直接丢弃连接，避免旧服务的请求继续运行
+                                        _ = cancel.cancelled() => {
+                                            debug!("连接在宽限期后被强制关闭: {}", remote_addr);
+                                        }
+                                        _ = serve => {}
                                     }
                                 });
                             }
@@ -772,6 +976,39 @@ impl AxumServer {
         Ok((server_instance, handle))
     }
 
+    /// 优雅关闭服务器
+    /// 立即停止监听 (释放端口)，并在宽限期内等待存量请求 (含流式响应) 完成；
+    /// 宽限期结束后仍未完成的连接任务会被取消，不会继续在旧服务的状态上运行
+    /// 返回被强制关闭的连接数
+    pub async fn shutdown_gracefully(&self, grace: std::time::Duration) -> usize {
+        if let Some(tx) = self.shutdown_tx.lock().await.take() {
+            let _ = tx.send(());
+        }
+        let _ = self.drain_tx.send(true);
+
+        let deadline = tokio::time::Instant::now() + grace;
+        loop {
+            let remaining = self.active_connections.load(Ordering::SeqCst);
+            if remaining == 0 {
+                tracing::info!("所有连接已排空，服务器已停止");
+                return 0;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                tracing::warn!("优雅关闭宽限期结束，强制关闭 {} 个未完成的连接", remaining);
+                self.connections_cancel.cancel();
+                // 连接任务在下一次被调度时即退出，这里只短暂等待计数归零
+                let cancel_deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(2);
+                while self.active_connections.load(Ordering::SeqCst) > 0
+                    && tokio::time::Instant::now() < cancel_deadline
+                {
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                return remaining;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
+
     /// 停止服务器
     pub fn stop(&self) {
         let tx_mutex = self.shutdown_tx.clone();
@@ -1172,6 +1409,181 @@ async fn admin_submit_oauth_code(
     Ok(StatusCode::OK)
 }
 
+/// 备份账号数据库 (Web 模式下只允许保存到数据目录的默认位置)
+async fn admin_backup_account_db() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let result = tokio::task::spawn_blocking(|| crate::modules::account_db::backup_account_db(None))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+    Ok(Json(result))
+}
+
+async fn admin_vacuum_account_db() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let result = tokio::task::spawn_blocking(crate::modules::account_db::vacuum_account_db)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+    Ok(Json(result))
+}
+
+fn backup_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e }))
+}
+
+async fn admin_list_backup_snapshots() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let snapshots = tokio::task::spawn_blocking(crate::modules::backup::list_snapshots)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map_err(backup_error)?;
+    Ok(Json(snapshots))
+}
+
+async fn admin_create_backup_snapshot() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let keep = config::load_app_config().map_err(backup_error)?.backup.keep as usize;
+    let snapshot = tokio::task::spawn_blocking(move || {
+        let snapshot = crate::modules::backup::create_snapshot(crate::modules::backup::SnapshotReason::Manual)?;
+        crate::modules::backup::prune_snapshots(keep)?;
+        Ok::<_, String>(snapshot)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r)
+    .map_err(backup_error)?;
+    Ok(Json(snapshot))
+}
+
+async fn admin_delete_backup_snapshot(
+    Path(snapshot_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::modules::backup::delete_snapshot(&snapshot_id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn admin_restore_backup_snapshot(
+    State(state): State<AppState>,
+    Path(snapshot_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let pre_restore =
+        tokio::task::spawn_blocking(move || crate::modules::backup::restore_snapshot(&snapshot_id))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r)
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    // 复用保存配置的热更新逻辑，使恢复后的配置立即生效
+    let restored_config = config::load_app_config().map_err(backup_error)?;
+    admin_save_config(
+        State(state.clone()),
+        Json(SaveConfigWrapper {
+            config: restored_config,
+        }),
+    )
+    .await?;
+
+    if let Err(e) = state.token_manager.load_accounts().await {
+        logger::log_error(&format!(
+            "[API] Failed to reload accounts after restoring snapshot: {}",
+            e
+        ));
+    }
+
+    Ok(Json(pre_restore))
+}
+
+#[derive(Deserialize, Default)]
+struct ContextCacheAccountQuery {
+    #[serde(default, alias = "accountId")]
+    account_id: Option<String>,
+}
+
+fn context_cache_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    (StatusCode::BAD_GATEWAY, Json(ErrorResponse { error: e }))
+}
+
+async fn admin_list_cached_contents(
+    Query(params): Query<ContextCacheAccountQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let caches = crate::proxy::context_cache::list_cached_contents(params.account_id.as_deref())
+        .await
+        .map_err(context_cache_error)?;
+    Ok(Json(caches))
+}
+
+async fn admin_create_cached_content(
+    Json(payload): Json<crate::proxy::context_cache::CreateCachedContentRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let cache = crate::proxy::context_cache::create_cached_content(&payload)
+        .await
+        .map_err(context_cache_error)?;
+    Ok(Json(cache))
+}
+
+async fn admin_delete_cached_content(
+    Path(cache_id): Path<String>,
+    Query(params): Query<ContextCacheAccountQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::proxy::context_cache::delete_cached_content(params.account_id.as_deref(), &cache_id)
+        .await
+        .map_err(context_cache_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Default)]
+struct BeginManualOAuthRequest {
+    #[serde(default, alias = "clientKey", alias = "oauthClientKey")]
+    client_key: Option<String>,
+}
+
+async fn admin_begin_manual_oauth(
+    State(state): State<AppState>,
+    payload: Option<Json<BeginManualOAuthRequest>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let start = state
+        .account_service
+        .begin_manual_oauth_login(payload.client_key)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    Ok(Json(start))
+}
+
+#[derive(Deserialize)]
+struct FinishManualOAuthRequest {
+    /// 回调 URL (含 code/state) 或单独的授权码
+    #[serde(alias = "code", alias = "url")]
+    input: String,
+    state: Option<String>,
+}
+
+async fn admin_finish_manual_oauth(
+    State(state): State<AppState>,
+    Json(payload): Json<FinishManualOAuthRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let account = state
+        .account_service
+        .finish_manual_oauth_login(&payload.input, payload.state)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    if let Err(e) = state.token_manager.load_accounts().await {
+        logger::log_error(&format!(
+            "[API] Failed to reload accounts after manual OAuth: {}",
+            e
+        ));
+    }
+
+    let current_id = state.account_service.get_current_id().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    Ok(Json(to_account_response(&account, &current_id)))
+}
+
 #[derive(Deserialize)]
 struct SetOAuthClientRequest {
     #[serde(alias = "clientKey", alias = "oauthClientKey")]
@@ -1302,6 +1714,14 @@ async fn admin_save_config(
     Json(payload): Json<SaveConfigWrapper>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     let new_config = payload.config;
+    crate::proxy::safety_settings::validate_safety_settings_config(&new_config.proxy.safety_settings)
+        .and_then(|_| crate::proxy::mcp_bridge::validate_mcp_bridge_config(&new_config.proxy.mcp_bridge))
+        .and_then(|_| crate::proxy::common::context_trim::validate_context_trim_config(&new_config.proxy.context_trim))
+        .and_then(|_| crate::proxy::redaction::validate_redaction_config(&new_config.proxy.redaction))
+        .and_then(|_| new_config.proxy.account_groups.validate())
+        .and_then(|_| crate::proxy::common::model_mapping::validate_quota_group_overrides(&new_config.proxy.quota_group_overrides))
+        .and_then(|_| crate::modules::migration::validate_token_encryption_setting(new_config.encrypt_tokens_at_rest))
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
     // 1. 持久化
     config::save_app_config(&new_config).map_err(|e| {
         (
@@ -1310,6 +1730,19 @@ async fn admin_save_config(
         )
     })?;
 
+    // [NEW] Token 静态加密开关变化时迁移账号文件
+    if new_config.encrypt_tokens_at_rest != crate::utils::crypto::is_token_encryption_enabled() {
+        crate::modules::migration::apply_token_encryption_setting(new_config.encrypt_tokens_at_rest)
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse { error: e }),
+                )
+            })?;
+    }
+
+    crate::modules::logger::apply_logging_config(&new_config.logging);
+
     // 2. 热更新内存状态
     // 这里我们直接复用内部组件的 update 方法
     // 注意：AppState 本身持有各个组件的 Arc<RwLock> 或直接持有引用
@@ -1322,11 +1755,18 @@ async fn admin_save_config(
     {
         let mut mapping = state.custom_mapping.write().await;
         *mapping = new_config.clone().proxy.custom_mapping;
+        crate::proxy::common::model_mapping::update_mapping_rules(&new_config.proxy.model_mapping_rules);
+        crate::proxy::common::model_mapping::update_quota_group_overrides(&new_config.proxy.quota_group_overrides);
     }
 
-    // 更新上游代理
+    // 更新上游代理 (变化时重建上游 HTTP 客户端)
     {
         let mut proxy = state.upstream_proxy.write().await;
+        if *proxy != new_config.proxy.upstream_proxy {
+            state
+                .upstream
+                .rebuild_default_client(new_config.proxy.upstream_proxy.clone());
+        }
         *proxy = new_config.clone().proxy.upstream_proxy;
     }
 
@@ -1346,14 +1786,67 @@ async fn admin_save_config(
     {
         let mut exp = state.experimental.write().await;
         *exp = new_config.clone().proxy.experimental;
+        crate::proxy::SignatureCache::global().set_enabled(exp.enable_signature_cache);
     }
 
     // 更新代理池配置（Web/Docker 保存配置时热更新）
     {
         let mut pool = state.proxy_pool_state.write().await;
         *pool = new_config.clone().proxy.proxy_pool;
+        state.upstream.clear_client_cache();
     }
 
+    // [NEW] 与桌面端 save_config 保持一致的其余热更新项
+    {
+        let mut dbg_cfg = state.debug_logging.write().await;
+        *dbg_cfg = new_config.proxy.debug_logging.clone();
+    }
+    state
+        .upstream
+        .set_user_agent_override(new_config.proxy.user_agent_override.clone())
+        .await;
+    state
+        .upstream
+        .set_client_version(Some(crate::constants::resolve_client_version(
+            &new_config.proxy.client_version,
+        )))
+        .await;
+    state.upstream.set_endpoints(&new_config.proxy.upstream_endpoints);
+    state.upstream.set_tuning(new_config.proxy.upstream_client.clone());
+    crate::proxy::update_thinking_budget_config(new_config.proxy.thinking_budget.clone());
+    crate::proxy::update_global_system_prompt_config(new_config.proxy.global_system_prompt.clone());
+    crate::proxy::update_image_thinking_mode(new_config.proxy.image_thinking_mode.clone());
+    crate::proxy::context_cache::update_bindings(new_config.proxy.context_cache.bindings.clone());
+    crate::proxy::middleware::client_rate_limit::update_client_rate_limit_config(
+        new_config.proxy.client_rate_limit.clone(),
+    );
+    crate::proxy::upstream::retry::update_retry_policy_config(new_config.proxy.retry_policy.clone());
+    crate::proxy::safety_settings::update_safety_settings_config(new_config.proxy.safety_settings.clone());
+    crate::proxy::mcp_bridge::update_mcp_bridge_config(new_config.proxy.mcp_bridge.clone());
+    crate::proxy::common::context_trim::update_context_trim_config(new_config.proxy.context_trim.clone());
+    crate::proxy::redaction::update_redaction_config(new_config.proxy.redaction.clone());
+    crate::proxy::common::system_prompt::update_system_prompt_injection_config(new_config.proxy.system_prompt_injection.clone());
+    state
+        .token_manager
+        .update_sticky_config(new_config.proxy.scheduling.clone())
+        .await;
+    state
+        .token_manager
+        .update_usage_limits(new_config.proxy.usage_limits.clone())
+        .await;
+    state
+        .token_manager
+        .update_account_groups(new_config.proxy.account_groups.clone())
+        .await;
+    state
+        .token_manager
+        .update_auto_disable_config(new_config.proxy.auto_disable.clone())
+        .await;
+    state
+        .token_manager
+        .update_circuit_breaker_config(new_config.circuit_breaker.clone())
+        .await;
+
     Ok(StatusCode::OK)
 }
 
@@ -1451,6 +1944,20 @@ async fn admin_get_proxy_status(
     })))
 }
 
+async fn admin_run_proxy_benchmark(
+    State(state): State<AppState>,
+    Json(payload): Json<crate::proxy::benchmark::BenchmarkRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let mut proxy_config = crate::modules::config::load_app_config()
+        .map(|c| c.proxy)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+    proxy_config.port = state.port;
+    let report = crate::proxy::benchmark::run_benchmark(&proxy_config, &payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    Ok(Json(report))
+}
+
 async fn admin_start_proxy_service(State(state): State<AppState>) -> impl IntoResponse {
     // 1. 持久化配置 (修复 #1166)
     if let Ok(mut config) = crate::modules::config::load_app_config() {
@@ -1488,6 +1995,50 @@ struct UpdateMappingWrapper {
     config: crate::proxy::config::ProxyConfig,
 }
 
+fn mapping_rule_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e }))
+}
+
+async fn admin_list_model_mapping_rules(
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let rules = crate::proxy::common::model_mapping::list_mapping_rules().map_err(mapping_rule_error)?;
+    Ok(Json(rules))
+}
+
+#[derive(Deserialize)]
+struct SaveMappingRuleRequest {
+    rule: crate::proxy::config::ModelMappingRule,
+}
+
+async fn admin_save_model_mapping_rule(
+    Json(payload): Json<SaveMappingRuleRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let rules = crate::proxy::common::model_mapping::save_mapping_rule(payload.rule)
+        .map_err(mapping_rule_error)?;
+    Ok(Json(rules))
+}
+
+async fn admin_delete_model_mapping_rule(
+    Path(rule_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let rules = crate::proxy::common::model_mapping::delete_mapping_rule(&rule_id)
+        .map_err(mapping_rule_error)?;
+    Ok(Json(rules))
+}
+
+#[derive(Deserialize)]
+struct ResolveMappingRequest {
+    model: String,
+}
+
+async fn admin_resolve_model_mapping(
+    Json(payload): Json<ResolveMappingRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let resolution = crate::proxy::common::model_mapping::dry_run_model_route(&payload.model)
+        .map_err(mapping_rule_error)?;
+    Ok(Json(resolution))
+}
+
 async fn admin_update_model_mapping(
     State(state): State<AppState>,
     Json(payload): Json<UpdateMappingWrapper>,
@@ -1557,6 +2108,10 @@ async fn admin_get_preferred_account(State(state): State<AppState>) -> impl Into
     Json(pref)
 }
 
+async fn admin_get_account_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.token_manager.get_account_metrics())
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct SetPreferredAccountRequest {
@@ -1574,6 +2129,35 @@ async fn admin_set_preferred_account(
     StatusCode::OK
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PinAccountRequest {
+    account_id: String,
+}
+
+async fn admin_get_pinned_account(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.token_manager.get_pinned_account().await)
+}
+
+async fn admin_pin_account(
+    State(state): State<AppState>,
+    Json(payload): Json<PinAccountRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let account_id = state
+        .token_manager
+        .pin_account(payload.account_id.trim())
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, Json(ErrorResponse { error: e })))?;
+    logger::log_warn(&format!("[API] 反代已锁定账号 {} (调试模式)", account_id));
+    Ok(Json(account_id))
+}
+
+async fn admin_unpin_account(State(state): State<AppState>) -> impl IntoResponse {
+    state.token_manager.unpin_account().await;
+    logger::log_info("[API] 已解除反代账号锁定");
+    StatusCode::OK
+}
+
 async fn admin_fetch_zai_models(
     Path(_id): Path<String>,
     Json(payload): Json<serde_json::Value>, // 复用前端传来的参数
@@ -1693,6 +2277,15 @@ async fn admin_clear_proxy_logs() -> impl IntoResponse {
     StatusCode::OK
 }
 
+async fn admin_replay_captured_request(
+    Json(payload): Json<crate::proxy::replay::ReplayRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let result = crate::proxy::replay::replay_captured_request(&payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    Ok(Json(result))
+}
+
 async fn admin_get_proxy_log_detail(
     Path(log_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
@@ -1873,6 +2466,32 @@ async fn admin_get_antigravity_path() -> Result<impl IntoResponse, (StatusCode,
     Ok(Json(path))
 }
 
+async fn admin_check_antigravity_version() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
+{
+    let report = crate::commands::check_antigravity_version(Some(true))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: e }),
+            )
+        })?;
+    Ok(Json(report))
+}
+
+async fn admin_get_client_version_info() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
+{
+    let info = crate::commands::proxy::get_client_version_info()
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: e }),
+            )
+        })?;
+    Ok(Json(info))
+}
+
 async fn admin_get_antigravity_args() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)>
 {
     let args = crate::commands::get_antigravity_args().await.map_err(|e| {
@@ -1908,6 +2527,23 @@ async fn admin_get_antigravity_cache_paths(
     Ok(Json(res))
 }
 
+async fn admin_query_logs(
+    Query(q): Query<crate::modules::logger::LogQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    tokio::task::spawn_blocking(move || crate::modules::logger::query_logs(&q))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))
+}
+
+async fn admin_list_log_files() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::modules::logger::list_log_files()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))
+}
+
 async fn admin_clear_log_cache() -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     crate::commands::clear_log_cache().await.map_err(|e| {
         (
@@ -1948,6 +2584,94 @@ async fn admin_get_token_stats_hourly(
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaHistoryQuery {
+    hours: Option<i64>,
+    account_id: Option<String>,
+    model_group: Option<String>,
+}
+
+async fn admin_get_quota_history(
+    Query(q): Query<QuotaHistoryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let hours = q.hours.unwrap_or(24 * 7);
+    let res = tokio::task::spawn_blocking(move || {
+        crate::modules::quota_history::get_quota_history(
+            hours,
+            q.account_id.as_deref(),
+            q.model_group.as_deref(),
+        )
+    })
+    .await;
+
+    match res {
+        Ok(Ok(history)) => Ok(Json(history)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaResetQuery {
+    account_id: Option<String>,
+}
+
+async fn admin_get_quota_reset_predictions(
+    Query(q): Query<QuotaResetQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let res = tokio::task::spawn_blocking(move || {
+        crate::modules::quota_history::predict_resets(q.account_id.as_deref())
+    })
+    .await;
+
+    match res {
+        Ok(Ok(predictions)) => Ok(Json(predictions)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+async fn admin_get_quota_trend_daily(
+    Query(p): Query<StatsPeriodQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let days = p.days.unwrap_or(30);
+    let res =
+        tokio::task::spawn_blocking(move || crate::modules::quota_history::get_quota_trend_daily(days))
+            .await;
+
+    match res {
+        Ok(Ok(trend)) => Ok(Json(trend)),
+        Ok(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
 async fn admin_get_token_stats_daily(
     Query(p): Query<StatsPeriodQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
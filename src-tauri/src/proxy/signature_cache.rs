@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Mutex, OnceLock};
 use std::time::{Duration, SystemTime};
 
@@ -58,6 +59,10 @@ pub struct SignatureCache {
     /// Value: The most recent valid thought signature for this session
     /// This prevents signature pollution between different conversations
     session_signatures: Mutex<HashMap<String, CacheEntry<SessionSignatureEntry>>>,
+
+    /// [NEW] 签名恢复开关 (experimental.enable_signature_cache)
+    /// 关闭后 Layer 1/3 不再存储与回填签名，Layer 2 的模型族信息仍用于跨模型检查
+    enabled: AtomicBool,
 }
 
 impl SignatureCache {
@@ -66,9 +71,28 @@ impl SignatureCache {
             tool_signatures: Mutex::new(HashMap::new()),
             thinking_families: Mutex::new(HashMap::new()),
             session_signatures: Mutex::new(HashMap::new()),
+            enabled: AtomicBool::new(true),
+        }
+    }
+
+    /// 开启/关闭签名恢复，关闭时清空已缓存的工具与会话签名
+    pub fn set_enabled(&self, enabled: bool) {
+        let was_enabled = self.enabled.swap(enabled, Ordering::Relaxed);
+        if was_enabled && !enabled {
+            if let Ok(mut cache) = self.tool_signatures.lock() {
+                cache.clear();
+            }
+            if let Ok(mut cache) = self.session_signatures.lock() {
+                cache.clear();
+            }
+            tracing::info!("[SignatureCache] Signature recovery disabled, cached signatures cleared");
         }
     }
 
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
     /// Global singleton instance
     pub fn global() -> &'static SignatureCache {
         static INSTANCE: OnceLock<SignatureCache> = OnceLock::new();
@@ -77,7 +101,7 @@ impl SignatureCache {
 
     /// Store a tool call signature
     pub fn cache_tool_signature(&self, tool_use_id: &str, signature: String) {
-        if signature.len() < MIN_SIGNATURE_LENGTH {
+        if !self.is_enabled() || signature.len() < MIN_SIGNATURE_LENGTH {
             return;
         }
         
@@ -99,6 +123,9 @@ impl SignatureCache {
 
     /// Retrieve a signature for a tool_use_id
     pub fn get_tool_signature(&self, tool_use_id: &str) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
         if let Ok(cache) = self.tool_signatures.lock() {
             if let Some(entry) = cache.get(tool_use_id) {
                 if !entry.is_expired() {
@@ -155,7 +182,7 @@ impl SignatureCache {
     /// * `signature` - The thought signature to store
     /// * `message_count` - The current message count of the conversation (to detect Rewind)
     pub fn cache_session_signature(&self, session_id: &str, signature: String, message_count: usize) {
-        if signature.len() < MIN_SIGNATURE_LENGTH {
+        if !self.is_enabled() || signature.len() < MIN_SIGNATURE_LENGTH {
             return;
         }
 
@@ -222,6 +249,9 @@ impl SignatureCache {
     /// Retrieve the latest thinking signature for a session.
     /// Returns None if not found or expired.
     pub fn get_session_signature(&self, session_id: &str) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
         if let Ok(cache) = self.session_signatures.lock() {
             if let Some(entry) = cache.get(session_id) {
                 if !entry.is_expired() {
@@ -329,6 +359,27 @@ mod tests {
         assert!(cache.get_session_signature("sid-other").is_none());
     }
 
+    #[test]
+    fn test_disabled_cache_skips_recovery() {
+        let cache = SignatureCache::new();
+        let sig = "x".repeat(60);
+        cache.cache_tool_signature("tool_1", sig.clone());
+        cache.cache_session_signature("sid-1", sig.clone(), 1);
+        cache.cache_thinking_family(sig.clone(), "gemini".to_string());
+
+        cache.set_enabled(false);
+        assert!(cache.get_tool_signature("tool_1").is_none());
+        assert!(cache.get_session_signature("sid-1").is_none());
+        cache.cache_tool_signature("tool_2", sig.clone());
+        // 模型族信息不受开关影响
+        assert_eq!(cache.get_signature_family(&sig), Some("gemini".to_string()));
+
+        // 重新开启后之前的签名已被清空
+        cache.set_enabled(true);
+        assert!(cache.get_tool_signature("tool_1").is_none());
+        assert!(cache.get_tool_signature("tool_2").is_none());
+    }
+
     #[test]
     fn test_clear_all_caches() {
         let cache = SignatureCache::new();
@@ -9,6 +9,8 @@ pub enum SchedulingMode {
     Balance,
     /// 性能优先 (Performance-first): 纯轮询模式 (Round-robin)，账号负载最均衡，但不利用缓存
     PerformanceFirst,
+    /// 今日最少使用 (Least-used-today): 保持会话绑定，新分配时选择今日 Token 用量最少的账号，均衡每日消耗
+    LeastUsedToday,
 }
 
 impl Default for SchedulingMode {
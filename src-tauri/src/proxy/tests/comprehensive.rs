@@ -39,6 +39,8 @@ mod tests {
             output_config: None,
             size: None,
             quality: None,
+            tool_choice: None,
+            extra: Default::default(),
         };
 
         // 2. 执行转换
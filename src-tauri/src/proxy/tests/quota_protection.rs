@@ -28,7 +28,7 @@ mod tests {
             expires_in: 3600,
             timestamp: chrono::Utc::now().timestamp() + 3600,
             email: email.to_string(),
-            account_path: PathBuf::from(format!("/tmp/test_accounts/{}.json", account_id)),
+            accounts_dir: PathBuf::from("/tmp/test_accounts"),
             project_id: Some("test-project".to_string()),
             subscription_tier: Some("PRO".to_string()),
             remaining_quota,
@@ -40,6 +40,7 @@ mod tests {
             validation_url: None,
             model_quotas: std::collections::HashMap::new(),
             model_limits: std::collections::HashMap::new(),
+            upstream_proxy: None,
         }
     }
 
@@ -940,15 +941,16 @@ mod tests {
             }
         });
 
-        // 使用 std::env::temp_dir() 创建临时文件
-        let temp_dir = std::env::temp_dir();
-        let account_path = temp_dir.join(format!("test_quota_{}.json", uuid::Uuid::new_v4()));
-        std::fs::write(&account_path, account_json.to_string()).expect("Failed to write temp file");
+        // 使用独立的临时账号目录 (账号数据存储在目录下的 accounts.db)
+        let temp_dir = std::env::temp_dir().join(format!("test_quota_{}", uuid::Uuid::new_v4()));
+        crate::modules::account_db::write_account(&temp_dir, "account", &account_json)
+            .expect("Failed to write temp account");
 
         // 测试读取 claude 的 quota
         let sonnet_quota =
             crate::proxy::token_manager::TokenManager::get_model_quota_from_json_for_test(
-                &account_path,
+                &temp_dir,
+                "account",
                 "claude",
             );
         assert_eq!(
@@ -960,7 +962,8 @@ mod tests {
         // 测试读取 gemini-3-flash 的 quota
         let gemini_quota =
             crate::proxy::token_manager::TokenManager::get_model_quota_from_json_for_test(
-                &account_path,
+                &temp_dir,
+                "account",
                 "gemini-3-flash",
             );
         assert_eq!(gemini_quota, Some(100), "gemini-3-flash 应该返回 100%");
@@ -968,13 +971,14 @@ mod tests {
         // 测试读取不存在的模型
         let unknown_quota =
             crate::proxy::token_manager::TokenManager::get_model_quota_from_json_for_test(
-                &account_path,
+                &temp_dir,
+                "account",
                 "unknown-model",
             );
         assert_eq!(unknown_quota, None, "不存在的模型应该返回 None");
 
-        // 清理临时文件
-        let _ = std::fs::remove_file(&account_path);
+        // 清理临时目录
+        let _ = std::fs::remove_dir_all(&temp_dir);
     }
 
     // ==================================================================================
@@ -1021,20 +1025,16 @@ mod tests {
             }
         });
 
-        // 写入临时文件
-        let path_a = temp_dir.join("account_a.json");
-        let path_b = temp_dir.join("account_b.json");
-        let path_c = temp_dir.join("account_c.json");
-
-        std::fs::write(&path_a, account_a_json.to_string()).unwrap();
-        std::fs::write(&path_b, account_b_json.to_string()).unwrap();
-        std::fs::write(&path_c, account_c_json.to_string()).unwrap();
+        // 写入临时账号数据库
+        crate::modules::account_db::write_account(&temp_dir, "a", &account_a_json).unwrap();
+        crate::modules::account_db::write_account(&temp_dir, "b", &account_b_json).unwrap();
+        crate::modules::account_db::write_account(&temp_dir, "c", &account_c_json).unwrap();
 
         // 创建 tokens，remaining_quota 使用 max 值（模拟旧逻辑）
         let mut tokens = vec![
-            create_mock_token_with_path("a", "carmelioventori@example.com", vec![], Some(100), path_a.clone()),
-            create_mock_token_with_path("b", "kiriyamaleo@example.com", vec![], Some(100), path_b.clone()),
-            create_mock_token_with_path("c", "mizusawakai9@example.com", vec![], Some(100), path_c.clone()),
+            create_mock_token_with_path("a", "carmelioventori@example.com", vec![], Some(100), temp_dir.clone()),
+            create_mock_token_with_path("b", "kiriyamaleo@example.com", vec![], Some(100), temp_dir.clone()),
+            create_mock_token_with_path("c", "mizusawakai9@example.com", vec![], Some(100), temp_dir.clone()),
         ];
 
         // 目标模型: claude
@@ -1043,12 +1043,14 @@ mod tests {
         // 使用修复后的排序逻辑：读取目标模型的 quota
         tokens.sort_by(|a, b| {
             let quota_a = crate::proxy::token_manager::TokenManager::get_model_quota_from_json_for_test(
-                &a.account_path,
+                &a.accounts_dir,
+                &a.account_id,
                 target_model,
             )
             .unwrap_or(0);
             let quota_b = crate::proxy::token_manager::TokenManager::get_model_quota_from_json_for_test(
-                &b.account_path,
+                &b.accounts_dir,
+                &b.account_id,
                 target_model,
             )
             .unwrap_or(0);
@@ -1091,9 +1093,9 @@ mod tests {
             }
         });
 
-        let temp_dir = std::env::temp_dir();
-        let account_path = temp_dir.join(format!("test_normalized_{}.json", uuid::Uuid::new_v4()));
-        std::fs::write(&account_path, account_json.to_string()).expect("Failed to write temp file");
+        let temp_dir = std::env::temp_dir().join(format!("test_normalized_{}", uuid::Uuid::new_v4()));
+        crate::modules::account_db::write_account(&temp_dir, "account", &account_json)
+            .expect("Failed to write temp account");
 
         // 请求 claude-opus-4-5-thinking，应该归一化为 claude
         let request_model = "claude-opus-4-5-thinking";
@@ -1104,7 +1106,8 @@ mod tests {
 
         // 读取归一化后模型的 quota
         let quota = crate::proxy::token_manager::TokenManager::get_model_quota_from_json_for_test(
-            &account_path,
+            &temp_dir,
+            "account",
             &normalized,
         );
 
@@ -1114,17 +1117,17 @@ mod tests {
             "claude-opus-4-5-thinking 归一化后应该读取 claude 的 quota (75%)"
         );
 
-        // 清理临时文件
-        let _ = std::fs::remove_file(&account_path);
+        // 清理临时目录
+        let _ = std::fs::remove_dir_all(&temp_dir);
     }
 
-    /// 辅助函数：创建带有自定义 account_path 的 mock token
+    /// 辅助函数：创建带有自定义账号目录的 mock token
     fn create_mock_token_with_path(
         account_id: &str,
         email: &str,
         protected_models: Vec<&str>,
         remaining_quota: Option<i32>,
-        account_path: PathBuf,
+        accounts_dir: PathBuf,
     ) -> ProxyToken {
         ProxyToken {
             account_id: account_id.to_string(),
@@ -1133,7 +1136,7 @@ mod tests {
             expires_in: 3600,
             timestamp: chrono::Utc::now().timestamp() + 3600,
             email: email.to_string(),
-            account_path,
+            accounts_dir,
             project_id: Some("test-project".to_string()),
             subscription_tier: Some("PRO".to_string()),
             remaining_quota,
@@ -1145,6 +1148,7 @@ mod tests {
             validation_url: None,
             model_quotas: std::collections::HashMap::new(),
             model_limits: std::collections::HashMap::new(),
+            upstream_proxy: None,
         }
     }
 }
@@ -43,7 +43,7 @@ fn create_test_token(
         expires_in: 3600,
         timestamp: chrono::Utc::now().timestamp() + 3600,
         email: email.to_string(),
-        account_path: PathBuf::from("/tmp/test"),
+        accounts_dir: PathBuf::from("/tmp/test"),
         project_id: None,
         subscription_tier: tier.map(|s| s.to_string()),
         remaining_quota,
@@ -55,6 +55,7 @@ fn create_test_token(
         validation_url: None,
         model_quotas,
         model_limits: std::collections::HashMap::new(),
+        upstream_proxy: None,
     }
 }
 
@@ -0,0 +1,244 @@
+// TLS 支持 - 为本地反代服务器提供 HTTPS
+// 支持用户提供证书/私钥，或自动生成并复用自签名证书
+
+use crate::proxy::config::TlsConfig;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+
+const SELF_SIGNED_DIR: &str = "tls";
+const SELF_SIGNED_CERT: &str = "cert.pem";
+const SELF_SIGNED_KEY: &str = "key.pem";
+/// 记录生成证书时使用的 SAN 列表，变化时重新生成证书
+const SELF_SIGNED_SANS: &str = "sans.txt";
+
+/// 根据配置构建 TLS acceptor
+/// - 配置了 cert_path + key_path: 使用用户证书
+/// - 否则: 在数据目录下生成 (或复用) 自签名证书，SAN 覆盖监听地址与本机网卡 IP
+pub fn build_tls_acceptor(config: &TlsConfig, bind_address: &str) -> Result<TlsAcceptor, String> {
+    let (cert_path, key_path) = match (non_empty(&config.cert_path), non_empty(&config.key_path)) {
+        (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+        (None, None) => {
+            let dir = crate::modules::account::get_data_dir()?.join(SELF_SIGNED_DIR);
+            ensure_self_signed_cert(&dir, &self_signed_subject_alt_names(bind_address))?
+        }
+        _ => return Err("TLS 证书与私钥路径需同时配置".to_string()),
+    };
+
+    let certs = load_certs(&cert_path)?;
+    let key = load_private_key(&key_path)?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS 协议配置失败: {}", e))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("TLS 证书无效: {}", e))?;
+    // 服务器仅支持 HTTP/1.1
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    tracing::info!("TLS 已启用，证书: {}", cert_path.display());
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// 自签名证书的 SAN 列表: localhost / 回环地址 + 监听地址 + 本机网卡 IP
+/// 局域网客户端通过监听地址或网卡 IP 访问时才能通过主机名校验
+fn self_signed_subject_alt_names(bind_address: &str) -> Vec<String> {
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+
+    // 0.0.0.0 / :: 不是可访问的主机名，仅由网卡 IP 覆盖
+    let bind_address = bind_address
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let is_unspecified = bind_address
+        .parse::<std::net::IpAddr>()
+        .map(|ip| ip.is_unspecified())
+        .unwrap_or(false);
+    if !bind_address.is_empty() && !is_unspecified {
+        names.push(bind_address.to_string());
+    }
+
+    match if_addrs::get_if_addrs() {
+        Ok(interfaces) => names.extend(
+            interfaces
+                .iter()
+                .filter(|iface| !iface.is_loopback())
+                .map(|iface| iface.ip().to_string()),
+        ),
+        Err(e) => tracing::warn!("枚举本机网卡地址失败，证书 SAN 将不含局域网 IP: {}", e),
+    }
+
+    // 排序去重，保证 SAN 记录可稳定比较
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// 确保自签名证书存在且 SAN 与当前地址一致，否则 (重新) 生成
+fn ensure_self_signed_cert(
+    dir: &Path,
+    subject_alt_names: &[String],
+) -> Result<(PathBuf, PathBuf), String> {
+    let cert_path = dir.join(SELF_SIGNED_CERT);
+    let key_path = dir.join(SELF_SIGNED_KEY);
+    let sans_path = dir.join(SELF_SIGNED_SANS);
+    let recorded_sans = subject_alt_names.join("\n");
+    if cert_path.exists() && key_path.exists() {
+        // 早期版本以默认权限写入私钥，复用时收紧
+        restrict_key_permissions(&key_path)?;
+        let sans_unchanged = std::fs::read_to_string(&sans_path)
+            .map(|existing| existing.trim() == recorded_sans)
+            .unwrap_or(false);
+        if sans_unchanged {
+            return Ok((cert_path, key_path));
+        }
+        tracing::info!("监听地址或本机网卡 IP 已变化，重新生成自签名 TLS 证书");
+    }
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建 TLS 目录失败: {}", e))?;
+
+    let rcgen::CertifiedKey { cert, key_pair } =
+        rcgen::generate_simple_self_signed(subject_alt_names.to_vec())
+            .map_err(|e| format!("生成自签名证书失败: {}", e))?;
+
+    std::fs::write(&cert_path, cert.pem()).map_err(|e| format!("写入证书失败: {}", e))?;
+    write_private_key(&key_path, key_pair.serialize_pem().as_bytes())?;
+    std::fs::write(&sans_path, &recorded_sans)
+        .map_err(|e| format!("写入证书 SAN 记录失败: {}", e))?;
+
+    tracing::info!("已生成自签名 TLS 证书: {}", cert_path.display());
+    Ok((cert_path, key_path))
+}
+
+/// 写入私钥，Unix 下以 0600 创建，避免其他本地用户读取
+fn write_private_key(path: &Path, pem: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("写入私钥失败: {}", e))?;
+    // 文件已存在时 mode 不生效，显式收紧权限
+    restrict_key_permissions(path)?;
+    file.write_all(pem).map_err(|e| format!("写入私钥失败: {}", e))
+}
+
+#[cfg(unix)]
+fn restrict_key_permissions(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("设置私钥权限失败: {}", e))
+}
+
+#[cfg(not(unix))]
+fn restrict_key_permissions(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+fn load_certs(path: &Path) -> Result<Vec<rustls::pki_types::CertificateDer<'static>>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("读取证书 {} 失败: {}", path.display(), e))?;
+    let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析证书失败: {}", e))?;
+    if certs.is_empty() {
+        return Err(format!("证书文件 {} 中未找到证书", path.display()));
+    }
+    Ok(certs)
+}
+
+fn load_private_key(path: &Path) -> Result<rustls::pki_types::PrivateKeyDer<'static>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("读取私钥 {} 失败: {}", path.display(), e))?;
+    rustls_pemfile::private_key(&mut std::io::BufReader::new(file))
+        .map_err(|e| format!("解析私钥失败: {}", e))?
+        .ok_or_else(|| format!("私钥文件 {} 中未找到私钥", path.display()))
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_signed_cert_generated_once() {
+        let dir = std::env::temp_dir().join(format!("agm-tls-test-{}", uuid::Uuid::new_v4()));
+
+        let sans = self_signed_subject_alt_names("127.0.0.1");
+        let (cert, key) = ensure_self_signed_cert(&dir, &sans).unwrap();
+        assert!(cert.exists() && key.exists());
+        let first = std::fs::read_to_string(&cert).unwrap();
+
+        // 再次调用应复用已有证书
+        ensure_self_signed_cert(&dir, &sans).unwrap();
+        assert_eq!(first, std::fs::read_to_string(&cert).unwrap());
+
+        assert!(!load_certs(&cert).unwrap().is_empty());
+        assert!(load_private_key(&key).is_ok());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&key).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_self_signed_cert_regenerated_when_sans_change() {
+        let dir = std::env::temp_dir().join(format!("agm-tls-test-{}", uuid::Uuid::new_v4()));
+
+        let (cert, _) = ensure_self_signed_cert(&dir, &["localhost".to_string()]).unwrap();
+        let first = std::fs::read_to_string(&cert).unwrap();
+
+        let sans = vec!["192.168.1.10".to_string(), "localhost".to_string()];
+        ensure_self_signed_cert(&dir, &sans).unwrap();
+        assert_ne!(first, std::fs::read_to_string(&cert).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(dir.join(SELF_SIGNED_SANS)).unwrap(),
+            sans.join("\n")
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_subject_alt_names_include_bind_address() {
+        let sans = self_signed_subject_alt_names("192.168.1.10");
+        assert!(sans.contains(&"192.168.1.10".to_string()));
+        assert!(sans.contains(&"localhost".to_string()));
+
+        // 通配地址不写入 SAN
+        let sans = self_signed_subject_alt_names("0.0.0.0");
+        assert!(!sans.contains(&"0.0.0.0".to_string()));
+        assert!(!self_signed_subject_alt_names("[::]").contains(&"::".to_string()));
+    }
+
+    #[test]
+    fn test_partial_paths_rejected() {
+        let config = TlsConfig {
+            enabled: true,
+            cert_path: Some("/tmp/cert.pem".to_string()),
+            key_path: None,
+        };
+        assert!(build_tls_acceptor(&config, "127.0.0.1").is_err());
+    }
+}
@@ -1,7 +1,7 @@
 // 移除冗余的顶层导入，因为这些在代码中已由 full path 或局部导入处理
 use dashmap::DashMap;
 use std::collections::{HashSet, HashMap};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
@@ -9,6 +9,87 @@ use tokio_util::sync::CancellationToken;
 use crate::proxy::rate_limit::RateLimitTracker;
 use crate::proxy::sticky_config::StickySessionConfig;
 
+/// 用量上限错误前缀 (handler 据此返回 429 而非 503)
+pub const USAGE_LIMIT_ERROR_PREFIX: &str = "Usage limit exceeded";
+
+/// 用量统计快照缓存时长，避免每次选号都查询 SQLite
+const USAGE_SNAPSHOT_TTL: std::time::Duration = std::time::Duration::from_secs(5);
+
+type UsageSnapshot = HashMap<String, crate::modules::token_stats::AccountUsageWindow>;
+
+/// 账号运行指标的滚动窗口大小 (最近 N 次请求)
+const METRICS_WINDOW: usize = 50;
+
+/// 单个账号的运行时指标 (滚动窗口)
+#[derive(Debug, Default)]
+struct AccountMetricsState {
+    outcomes: std::collections::VecDeque<bool>,
+    latencies_ms: std::collections::VecDeque<u64>,
+    total_requests: u64,
+    total_failures: u64,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    last_used_at: Option<i64>,
+    last_failure_at: Option<i64>,
+}
+
+impl AccountMetricsState {
+    fn record(&mut self, success: bool, latency_ms: u64, now: i64) {
+        if self.outcomes.len() >= METRICS_WINDOW {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(success);
+        if self.latencies_ms.len() >= METRICS_WINDOW {
+            self.latencies_ms.pop_front();
+        }
+        self.latencies_ms.push_back(latency_ms);
+
+        self.total_requests += 1;
+        self.last_used_at = Some(now);
+        if success {
+            self.consecutive_successes += 1;
+            self.consecutive_failures = 0;
+        } else {
+            self.total_failures += 1;
+            self.consecutive_failures += 1;
+            self.consecutive_successes = 0;
+            self.last_failure_at = Some(now);
+        }
+    }
+
+    fn success_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            return 1.0;
+        }
+        self.outcomes.iter().filter(|ok| **ok).count() as f64 / self.outcomes.len() as f64
+    }
+
+    fn avg_latency_ms(&self) -> u64 {
+        if self.latencies_ms.is_empty() {
+            return 0;
+        }
+        self.latencies_ms.iter().sum::<u64>() / self.latencies_ms.len() as u64
+    }
+}
+
+/// 账号运行指标快照 (供 UI 展示)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccountMetrics {
+    pub account_id: String,
+    pub email: String,
+    pub total_requests: u64,
+    pub total_failures: u64,
+    /// 最近 METRICS_WINDOW 次请求的成功率 (0.0 - 1.0)
+    pub success_rate: f64,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    /// 最近 METRICS_WINDOW 次请求的平均耗时
+    pub avg_latency_ms: u64,
+    pub health_score: f32,
+    pub last_used_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OnDiskAccountState {
     Enabled,
@@ -24,7 +105,7 @@ pub struct ProxyToken {
     pub expires_in: i64,
     pub timestamp: i64,
     pub email: String,
-    pub account_path: PathBuf, // 账号文件路径，用于更新
+    pub accounts_dir: PathBuf, // 账号数据库所在目录，用于回写
     pub project_id: Option<String>,
     pub subscription_tier: Option<String>, // "FREE" | "PRO" | "ULTRA"
     pub remaining_quota: Option<i32>,      // [FIX #563] Remaining quota for priority sorting
@@ -36,6 +117,7 @@ pub struct ProxyToken {
     pub validation_url: Option<String>,    // [NEW] Validation URL (#1522)
     pub model_quotas: HashMap<String, i32>, // [OPTIMIZATION] In-memory cache for model-specific quotas
     pub model_limits: HashMap<String, u64>, // [NEW] max_output_tokens per model from quota data
+    pub upstream_proxy: Option<crate::proxy::config::UpstreamProxyConfig>, // [NEW] 账号专属上游代理 (优先于代理池与全局上游代理)
 }
 
 pub struct TokenManager {
@@ -47,8 +129,17 @@ pub struct TokenManager {
     sticky_config: Arc<tokio::sync::RwLock<StickySessionConfig>>, // 新增：调度配置
     session_accounts: Arc<DashMap<String, String>>, // 新增：会话与账号映射 (SessionID -> AccountID)
     preferred_account_id: Arc<tokio::sync::RwLock<Option<String>>>, // [FIX #820] 优先使用的账号ID（固定账号模式）
+    pinned_account_id: Arc<tokio::sync::RwLock<Option<String>>>, // [NEW] 调试锁定账号 (不回退、不持久化)
     health_scores: Arc<DashMap<String, f32>>,                       // account_id -> health_score
+    account_metrics: Arc<DashMap<String, AccountMetricsState>>,     // [NEW] account_id -> 运行指标
+    auth_failure_streaks: Arc<DashMap<String, u32>>,                // [NEW] account_id -> 连续 401/403 次数
+    auto_disable: Arc<tokio::sync::RwLock<crate::proxy::config::AutoDisableConfig>>, // [NEW] 自动停用配置
     circuit_breaker_config: Arc<tokio::sync::RwLock<crate::models::CircuitBreakerConfig>>, // [NEW] 熔断配置缓存
+    usage_limits: Arc<tokio::sync::RwLock<crate::proxy::config::UsageLimitsConfig>>, // [NEW] 用量上限配置
+    usage_snapshot: Arc<tokio::sync::Mutex<Option<(std::time::Instant, Arc<UsageSnapshot>)>>>,
+    project_rediscovered_at: Arc<DashMap<String, std::time::Instant>>, // [NEW] project_id 重新发现节流
+    account_subset: Arc<tokio::sync::RwLock<HashSet<String>>>, // [NEW] 参与轮询的账号子集 (空 = 全部)
+    account_groups: Arc<tokio::sync::RwLock<crate::proxy::config::AccountGroupsConfig>>, // [NEW] 账号分组路由
     /// 支持优雅关闭时主动 abort 后台任务
     auto_cleanup_handle: Arc<tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
     cancel_token: CancellationToken,
@@ -66,15 +157,35 @@ impl TokenManager {
             sticky_config: Arc::new(tokio::sync::RwLock::new(StickySessionConfig::default())),
             session_accounts: Arc::new(DashMap::new()),
             preferred_account_id: Arc::new(tokio::sync::RwLock::new(None)), // [FIX #820]
+            pinned_account_id: Arc::new(tokio::sync::RwLock::new(None)),
             health_scores: Arc::new(DashMap::new()),
+            account_metrics: Arc::new(DashMap::new()),
+            auth_failure_streaks: Arc::new(DashMap::new()),
+            auto_disable: Arc::new(tokio::sync::RwLock::new(
+                crate::proxy::config::AutoDisableConfig::default(),
+            )),
             circuit_breaker_config: Arc::new(tokio::sync::RwLock::new(
                 crate::models::CircuitBreakerConfig::default(),
             )),
+            usage_limits: Arc::new(tokio::sync::RwLock::new(
+                crate::proxy::config::UsageLimitsConfig::default(),
+            )),
+            usage_snapshot: Arc::new(tokio::sync::Mutex::new(None)),
+            project_rediscovered_at: Arc::new(DashMap::new()),
+            account_subset: Arc::new(tokio::sync::RwLock::new(HashSet::new())),
+            account_groups: Arc::new(tokio::sync::RwLock::new(
+                crate::proxy::config::AccountGroupsConfig::default(),
+            )),
             auto_cleanup_handle: Arc::new(tokio::sync::Mutex::new(None)),
             cancel_token: CancellationToken::new(),
         }
     }
 
+    /// 账号数据库所在目录
+    fn accounts_dir(&self) -> PathBuf {
+        self.data_dir.join("accounts")
+    }
+
     /// 启动限流记录自动清理后台任务（每15秒检查并清除过期记录）
     pub async fn start_auto_cleanup(&self) {
         let tracker = self.rate_limit_tracker.clone();
@@ -128,23 +239,21 @@ impl TokenManager {
             *last_used = None;
         }
 
-        let entries = std::fs::read_dir(&accounts_dir)
+        // [NEW] 账号数据存储在 SQLite，遗留的 JSON 文件会在此自动导入
+        let account_ids = crate::modules::account_db::list_account_ids(&accounts_dir)
             .map_err(|e| format!("读取账号目录失败: {}", e))?;
 
+        let subset = self.account_subset.read().await.clone();
         let mut count = 0;
 
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("读取目录项失败: {}", e))?;
-            let path = entry.path();
-
-            if path.extension().and_then(|s| s.to_str()) != Some("json") {
-                continue;
-            }
-
+        for account_id in account_ids {
             // 尝试加载账号
-            match self.load_single_account(&path).await {
+            match self.load_single_account(&account_id).await {
                 Ok(Some(token)) => {
                     let account_id = token.account_id.clone();
+                    if !subset.is_empty() && !subset.contains(&account_id) {
+                        continue;
+                    }
                     self.tokens.insert(account_id, token);
                     count += 1;
                 }
@@ -152,7 +261,7 @@ impl TokenManager {
                     // 跳过无效账号
                 }
                 Err(e) => {
-                    tracing::debug!("加载账号失败 {:?}: {}", path, e);
+                    tracing::debug!("加载账号失败 {}: {}", account_id, e);
                 }
             }
         }
@@ -162,15 +271,19 @@ impl TokenManager {
 
     /// 重新加载指定账号（用于配额更新后的实时同步）
     pub async fn reload_account(&self, account_id: &str) -> Result<(), String> {
-        let path = self
-            .data_dir
-            .join("accounts")
-            .join(format!("{}.json", account_id));
-        if !path.exists() {
-            return Err(format!("账号文件不存在: {:?}", path));
+        if !crate::modules::account_db::account_exists(&self.accounts_dir(), account_id) {
+            return Err(format!("账号不存在: {}", account_id));
+        }
+
+        {
+            let subset = self.account_subset.read().await;
+            if !subset.is_empty() && !subset.contains(account_id) {
+                self.tokens.remove(account_id);
+                return Ok(());
+            }
         }
 
-        match self.load_single_account(&path).await {
+        match self.load_single_account(account_id).await {
             Ok(Some(token)) => {
                 self.tokens.insert(account_id.to_string(), token);
                 // [NEW] 重新加载账号时自动清除该账号的限流记录
@@ -203,6 +316,8 @@ impl TokenManager {
             tracing::info!("[Proxy] Removed account {} from memory cache", account_id);
         }
         self.health_scores.remove(account_id);
+        self.account_metrics.remove(account_id);
+        self.auth_failure_streaks.remove(account_id);
         self.clear_rate_limit(account_id);
         self.session_accounts.retain(|_, v| v != account_id);
         if let Ok(mut preferred) = self.preferred_account_id.try_write() {
@@ -213,6 +328,12 @@ impl TokenManager {
         }
     }
 
+    /// 账号专属上游代理查询 (绑定随账号数据加载，供 UpstreamClient 选择出口)
+    pub fn account_proxy_lookup(&self) -> crate::proxy::upstream::client::AccountProxyLookup {
+        let tokens = self.tokens.clone();
+        Arc::new(move |account_id: &str| tokens.get(account_id).and_then(|t| t.upstream_proxy.clone()))
+    }
+
     /// 根据账号 ID 获取完整的 ProxyToken 对象 (v4.1.29)
     pub fn get_token_by_id(&self, account_id: &str) -> Option<ProxyToken> {
         self.tokens.get(account_id).map(|t| t.clone())
@@ -226,25 +347,23 @@ impl TokenManager {
     /// Note: this is intentionally tolerant to transient read/parse failures (e.g. concurrent
     /// writes). Failures are reported as `Unknown` so callers can skip without purging the in-memory
     /// token pool.
-    async fn get_account_state_on_disk(account_path: &std::path::PathBuf) -> OnDiskAccountState {
+    async fn get_account_state_on_disk(accounts_dir: &Path, account_id: &str) -> OnDiskAccountState {
         const MAX_RETRIES: usize = 2;
         const RETRY_DELAY_MS: u64 = 5;
 
         for attempt in 0..=MAX_RETRIES {
-            let content = match tokio::fs::read_to_string(account_path).await {
-                Ok(c) => c,
+            let content = match crate::modules::account_db::read_account(accounts_dir, account_id) {
+                Ok(Some(c)) => c,
+                // If the account is gone, the in-memory token is definitely stale.
+                Ok(None) => return OnDiskAccountState::Disabled,
                 Err(e) => {
-                    // If the file is gone, the in-memory token is definitely stale.
-                    if e.kind() == std::io::ErrorKind::NotFound {
-                        return OnDiskAccountState::Disabled;
-                    }
                     if attempt < MAX_RETRIES {
                         tokio::time::sleep(std::time::Duration::from_millis(RETRY_DELAY_MS)).await;
                         continue;
                     }
                     tracing::debug!(
-                        "Failed to read account file on disk {:?}: {}",
-                        account_path,
+                        "Failed to read account {} on disk: {}",
+                        account_id,
                         e
                     );
                     return OnDiskAccountState::Unknown;
@@ -259,8 +378,8 @@ impl TokenManager {
                         continue;
                     }
                     tracing::debug!(
-                        "Failed to parse account JSON on disk {:?}: {}",
-                        account_path,
+                        "Failed to parse account JSON on disk {}: {}",
+                        account_id,
                         e
                     );
                     return OnDiskAccountState::Unknown;
@@ -292,11 +411,11 @@ impl TokenManager {
     }
 
     /// 加载单个账号
-    async fn load_single_account(&self, path: &PathBuf) -> Result<Option<ProxyToken>, String> {
-        let content = std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?;
-
-        let mut account: serde_json::Value =
-            serde_json::from_str(&content).map_err(|e| format!("解析 JSON 失败: {}", e))?;
+    async fn load_single_account(&self, id: &str) -> Result<Option<ProxyToken>, String> {
+        let accounts_dir = self.accounts_dir();
+        let mut account = crate::modules::account_db::read_account_value(&accounts_dir, id)
+            .map_err(|e| format!("读取账号失败: {}", e))?
+            .ok_or_else(|| format!("读取账号失败: 账号不存在 {}", id))?;
 
         // [修复 #1344] 先检查账号是否被手动禁用(非配额保护原因)
         let is_proxy_disabled = account
@@ -312,8 +431,8 @@ impl TokenManager {
         if is_proxy_disabled && disabled_reason != "quota_protection" {
             // Account manually disabled
             tracing::debug!(
-                "Account skipped due to manual disable: {:?} (email={}, reason={})",
-                path,
+                "Account skipped due to manual disable: {} (email={}, reason={})",
+                id,
                 account
                     .get("email")
                     .and_then(|v| v.as_str())
@@ -339,8 +458,8 @@ impl TokenManager {
             if now < block_until {
                 // Still blocked
                 tracing::debug!(
-                    "Skipping validation-blocked account: {:?} (email={}, blocked until {})",
-                    path,
+                    "Skipping validation-blocked account: {} (email={}, blocked until {})",
+                    id,
                     account
                         .get("email")
                         .and_then(|v| v.as_str())
@@ -356,9 +475,12 @@ impl TokenManager {
                 account["validation_blocked_until"] = serde_json::json!(0);
                 account["validation_blocked_reason"] = serde_json::Value::Null;
 
-                let updated_json =
-                    serde_json::to_string_pretty(&account).map_err(|e| e.to_string())?;
-                std::fs::write(path, updated_json).map_err(|e| e.to_string())?;
+                Self::patch_account_fields(
+                    &accounts_dir,
+                    id,
+                    &account,
+                    &["validation_blocked", "validation_blocked_until", "validation_blocked_reason"],
+                )?;
                 tracing::info!(
                     "Validation block expired and cleared for account: {}",
                     account
@@ -376,8 +498,8 @@ impl TokenManager {
             .unwrap_or(false)
         {
             tracing::debug!(
-                "Skipping disabled account file: {:?} (email={})",
-                path,
+                "Skipping disabled account: {} (email={})",
+                id,
                 account
                     .get("email")
                     .and_then(|v| v.as_str())
@@ -387,17 +509,17 @@ impl TokenManager {
         }
 
         // Safety check: verify state on disk again to handle concurrent mid-parse writes
-        if Self::get_account_state_on_disk(path).await == OnDiskAccountState::Disabled {
-            tracing::debug!("Account file {:?} is disabled on disk, skipping.", path);
+        if Self::get_account_state_on_disk(&accounts_dir, id).await == OnDiskAccountState::Disabled {
+            tracing::debug!("Account {} is disabled on disk, skipping.", id);
             return Ok(None);
         }
 
         // 配额保护检查 - 只处理配额保护逻辑
         // 这样可以在加载时自动恢复配额已恢复的账号
-        if self.check_and_protect_quota(&mut account, path).await {
+        if self.check_and_protect_quota(&mut account, id).await {
             tracing::debug!(
-                "Account skipped due to quota protection: {:?} (email={})",
-                path,
+                "Account skipped due to quota protection: {} (email={})",
+                id,
                 account
                     .get("email")
                     .and_then(|v| v.as_str())
@@ -413,8 +535,8 @@ impl TokenManager {
             .unwrap_or(false)
         {
             tracing::debug!(
-                "Skipping proxy-disabled account file: {:?} (email={})",
-                path,
+                "Skipping proxy-disabled account: {} (email={})",
+                id,
                 account
                     .get("email")
                     .and_then(|v| v.as_str())
@@ -434,13 +556,14 @@ impl TokenManager {
         let token_obj = account["token"].as_object()
             .ok_or("缺少 token 字段")?;
 
-        let access_token = token_obj["access_token"].as_str()
-            .ok_or("缺少 access_token")?
-            .to_string();
+        // [NEW] Token 可能已静态加密，此处仅解密内存副本 (account JSON 可能被回写，保持原样)
+        let access_token = crate::utils::crypto::decrypt_token(
+            token_obj["access_token"].as_str().ok_or("缺少 access_token")?,
+        )?;
 
-        let refresh_token = token_obj["refresh_token"].as_str()
-            .ok_or("缺少 refresh_token")?
-            .to_string();
+        let refresh_token = crate::utils::crypto::decrypt_token(
+            token_obj["refresh_token"].as_str().ok_or("缺少 refresh_token")?,
+        )?;
 
         let expires_in = token_obj["expires_in"].as_i64()
             .ok_or("缺少 expires_in")?;
@@ -507,6 +630,12 @@ impl TokenManager {
             }
         }
 
+        // [NEW] 账号专属上游代理 (未启用或地址为空视为未设置)
+        let upstream_proxy = account
+            .get("upstream_proxy")
+            .and_then(|v| serde_json::from_value::<crate::proxy::config::UpstreamProxyConfig>(v.clone()).ok())
+            .filter(|p| p.enabled && !p.url.trim().is_empty());
+
         // [NEW] 启动时自动同步持久化的淘汰模型路由表，注入热更新拦截器
         if let Some(rules) = account.get("quota").and_then(|q| q.get("model_forwarding_rules")).and_then(|r| r.as_object()) {
             for (k, v) in rules {
@@ -526,7 +655,7 @@ impl TokenManager {
             expires_in,
             timestamp,
             email,
-            account_path: path.clone(),
+            accounts_dir,
             project_id,
             subscription_tier,
             remaining_quota,
@@ -538,6 +667,7 @@ impl TokenManager {
             validation_url: account.get("validation_url").and_then(|v| v.as_str()).map(|s| s.to_string()),
             model_quotas,
             model_limits,
+            upstream_proxy,
         }))
     }
 
@@ -546,7 +676,7 @@ impl TokenManager {
     async fn check_and_protect_quota(
         &self,
         account_json: &mut serde_json::Value,
-        account_path: &PathBuf,
+        account_id: &str,
     ) -> bool {
         // 1. 加载配额保护配置
         let config = match crate::modules::config::load_app_config() {
@@ -578,7 +708,7 @@ impl TokenManager {
         if is_proxy_disabled && reason == "quota_protection" {
             // 如果是被旧版账号级保护禁用的,尝试恢复并转为模型级
             return self
-                .check_and_restore_quota(account_json, account_path, &quota, &config)
+                .check_and_restore_quota(account_json, account_id, &quota, &config)
                 .await;
         }
 
@@ -608,11 +738,6 @@ impl TokenManager {
 
         // 6. 遍历受监控的 Standard ID，根据组内“最差状态”执行锁定或恢复
         let threshold = config.threshold_percentage as i32;
-        let account_id = account_json
-            .get("id")
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown")
-            .to_string();
         let mut changed = false;
 
         for std_id in &config.monitored_models {
@@ -624,8 +749,7 @@ impl TokenManager {
                 if self
                     .trigger_quota_protection(
                         account_json,
-                        &account_id,
-                        account_path,
+                        account_id,
                         min_pct,
                         threshold,
                         std_id,
@@ -649,8 +773,7 @@ impl TokenManager {
                     if self
                         .restore_quota_protection(
                             account_json,
-                            &account_id,
-                            account_path,
+                            account_id,
                             std_id,
                         )
                         .await
@@ -700,12 +823,12 @@ impl TokenManager {
     /// 从磁盘读取特定模型的 quota 百分比 [FIX] 排序使用目标模型的 quota 而非 max
     ///
     /// # 参数
-    /// * `account_path` - 账号 JSON 文件路径
+    /// * `accounts_dir` - 账号数据库所在目录
+    /// * `account_id` - 账号 ID
     /// * `model_name` - 目标模型名称（已标准化）
     #[allow(dead_code)] // 预留给精确配额读取逻辑
-    fn get_model_quota_from_json(account_path: &PathBuf, model_name: &str) -> Option<i32> {
-        let content = std::fs::read_to_string(account_path).ok()?;
-        let account: serde_json::Value = serde_json::from_str(&content).ok()?;
+    fn get_model_quota_from_json(accounts_dir: &Path, account_id: &str, model_name: &str) -> Option<i32> {
+        let account = crate::modules::account_db::read_account_value(accounts_dir, account_id).ok()??;
         let models = account.get("quota")?.get("models")?.as_array()?;
 
         for model in models {
@@ -724,9 +847,8 @@ impl TokenManager {
         None
     }
 
-    fn get_available_models_from_json(account_path: &PathBuf) -> Option<HashSet<String>> {
-        let content = std::fs::read_to_string(account_path).ok()?;
-        let account: serde_json::Value = serde_json::from_str(&content).ok()?;
+    fn get_available_models_from_json(accounts_dir: &Path, account_id: &str) -> Option<HashSet<String>> {
+        let account = crate::modules::account_db::read_account_value(accounts_dir, account_id).ok()??;
         let models = account.get("quota")?.get("models")?.as_array()?;
         let mut result = HashSet::new();
         for model in models {
@@ -792,12 +914,12 @@ impl TokenManager {
             None => return mapped_model.to_string(),
         };
 
-        let account_path = match self.tokens.get(account_id) {
-            Some(token) => token.account_path.clone(),
+        let accounts_dir = match self.tokens.get(account_id) {
+            Some(token) => token.accounts_dir.clone(),
             None => return mapped_model.to_string(),
         };
 
-        let available_models = match Self::get_available_models_from_json(&account_path) {
+        let available_models = match Self::get_available_models_from_json(&accounts_dir, account_id) {
             Some(models) if !models.is_empty() => models,
             _ => return mapped_model.to_string(),
         };
@@ -821,8 +943,12 @@ impl TokenManager {
 
     /// 测试辅助函数：公开访问 get_model_quota_from_json
     #[cfg(test)]
-    pub fn get_model_quota_from_json_for_test(account_path: &PathBuf, model_name: &str) -> Option<i32> {
-        Self::get_model_quota_from_json(account_path, model_name)
+    pub fn get_model_quota_from_json_for_test(
+        accounts_dir: &Path,
+        account_id: &str,
+        model_name: &str,
+    ) -> Option<i32> {
+        Self::get_model_quota_from_json(accounts_dir, account_id, model_name)
     }
 
     /// 触发配额保护，限制特定模型 (Issue #621)
@@ -831,7 +957,6 @@ impl TokenManager {
         &self,
         account_json: &mut serde_json::Value,
         account_id: &str,
-        account_path: &PathBuf,
         current_val: i32,
         threshold: i32,
         model_name: &str,
@@ -859,7 +984,7 @@ impl TokenManager {
             );
 
             // 3. 写入磁盘
-            std::fs::write(account_path, serde_json::to_string_pretty(account_json).unwrap())
+            Self::patch_account_fields(&self.accounts_dir(), account_id, account_json, &["protected_models"])
                 .map_err(|e| format!("写入文件失败: {}", e))?;
 
             // [FIX] 触发 TokenManager 的账号重新加载信号，确保内存中的 protected_models 同步
@@ -875,7 +1000,7 @@ impl TokenManager {
     async fn check_and_restore_quota(
         &self,
         account_json: &mut serde_json::Value,
-        account_path: &PathBuf,
+        account_id: &str,
         quota: &serde_json::Value,
         config: &crate::models::QuotaProtectionConfig,
     ) -> bool {
@@ -910,7 +1035,12 @@ impl TokenManager {
 
         account_json["protected_models"] = serde_json::Value::Array(protected_list);
 
-        let _ = std::fs::write(account_path, serde_json::to_string_pretty(account_json).unwrap());
+        let _ = Self::patch_account_fields(
+            &self.accounts_dir(),
+            account_id,
+            account_json,
+            &["proxy_disabled", "proxy_disabled_reason", "proxy_disabled_at", "protected_models"],
+        );
 
         false // 返回 false 表示现在已可以尝试加载该账号（模型级过滤会在 get_token 时发生）
     }
@@ -921,7 +1051,6 @@ impl TokenManager {
         &self,
         account_json: &mut serde_json::Value,
         account_id: &str,
-        account_path: &PathBuf,
         model_name: &str,
     ) -> Result<bool, String> {
         if let Some(arr) = account_json
@@ -937,11 +1066,8 @@ impl TokenManager {
                     account_id,
                     model_name
                 );
-                std::fs::write(
-                    account_path,
-                    serde_json::to_string_pretty(account_json).unwrap(),
-                )
-                .map_err(|e| format!("写入文件失败: {}", e))?;
+                Self::patch_account_fields(&self.accounts_dir(), account_id, account_json, &["protected_models"])
+                    .map_err(|e| format!("写入文件失败: {}", e))?;
                 return Ok(true);
             }
         }
@@ -949,6 +1075,51 @@ impl TokenManager {
         Ok(false)
     }
 
+    /// 按调度模式选择候选账号: 今日最少使用模式取当日 Token 用量最低者，其余模式使用 P2C
+    async fn select_for_mode<'a>(
+        &self,
+        mode: crate::proxy::sticky_config::SchedulingMode,
+        candidates: &'a [ProxyToken],
+        attempted: &HashSet<String>,
+        normalized_target: &str,
+        quota_protection_enabled: bool,
+    ) -> Option<&'a ProxyToken> {
+        if mode != crate::proxy::sticky_config::SchedulingMode::LeastUsedToday {
+            return self.select_with_p2c(candidates, attempted, normalized_target, quota_protection_enabled);
+        }
+        let usage = self.get_usage_snapshot().await;
+        let selected = Self::select_least_used(
+            candidates,
+            attempted,
+            normalized_target,
+            quota_protection_enabled,
+            &usage,
+        );
+        if let Some(t) = selected {
+            tracing::debug!(
+                "[LeastUsedToday] Selected {} ({} tokens today)",
+                t.email,
+                usage.get(&t.email).map(|w| w.daily_tokens).unwrap_or(0)
+            );
+        }
+        selected
+    }
+
+    /// 选择今日 Token 用量最少的账号；用量相同时保持候选顺序 (订阅等级 / 配额排序)
+    fn select_least_used<'a>(
+        candidates: &'a [ProxyToken],
+        attempted: &HashSet<String>,
+        normalized_target: &str,
+        quota_protection_enabled: bool,
+        usage: &UsageSnapshot,
+    ) -> Option<&'a ProxyToken> {
+        candidates
+            .iter()
+            .filter(|t| !attempted.contains(&t.account_id))
+            .filter(|t| !quota_protection_enabled || !t.protected_models.contains(normalized_target))
+            .min_by_key(|t| usage.get(&t.email).map(|w| w.daily_tokens).unwrap_or(0))
+    }
+
     /// P2C 算法的候选池大小 - 从前 N 个最优候选中随机选择
     const P2C_POOL_SIZE: usize = 5;
 
@@ -1018,6 +1189,7 @@ impl TokenManager {
     /// * `timeout` - 等待任务完成的超时时间
     pub async fn graceful_shutdown(&self, timeout: std::time::Duration) {
         tracing::info!("Initiating graceful shutdown of background tasks...");
+        self.save_runtime_state();
 
         // 发送取消信号给所有后台任务
         self.cancel_token.cancel();
@@ -1029,6 +1201,83 @@ impl TokenManager {
         }
     }
 
+    /// [NEW] 将限流冷却、失败计数、健康分与粘性会话表写入磁盘，供下次启动恢复
+    pub fn save_runtime_state(&self) {
+        let (cooldowns, failure_counts) = self.rate_limit_tracker.export_state();
+        let snapshot = crate::proxy::runtime_state::RuntimeStateSnapshot {
+            saved_at: chrono::Utc::now().timestamp(),
+            cooldowns,
+            failure_counts,
+            auth_failure_streaks: self
+                .auth_failure_streaks
+                .iter()
+                .map(|e| (e.key().clone(), *e.value()))
+                .collect(),
+            health_scores: self
+                .health_scores
+                .iter()
+                .map(|e| (e.key().clone(), *e.value()))
+                .collect(),
+            session_accounts: self
+                .session_accounts
+                .iter()
+                .map(|e| (e.key().clone(), e.value().clone()))
+                .collect(),
+        };
+        match crate::proxy::runtime_state::save(&self.data_dir, &snapshot) {
+            Ok(()) => tracing::info!(
+                "[RuntimeState] Saved {} cooldown(s), {} session binding(s)",
+                snapshot.cooldowns.len(),
+                snapshot.session_accounts.len()
+            ),
+            Err(e) => tracing::warn!("[RuntimeState] {}", e),
+        }
+    }
+
+    /// [NEW] 恢复上次停止时保存的运行时状态 (需在 load_accounts 之后调用)
+    /// 只恢复仍在账号池中的账号；已过期的冷却会被丢弃
+    pub fn restore_runtime_state(&self) {
+        let Some(snapshot) = crate::proxy::runtime_state::load(&self.data_dir) else {
+            return;
+        };
+        let known = |account_id: &str| self.tokens.contains_key(account_id);
+
+        let cooldowns: Vec<_> = snapshot
+            .cooldowns
+            .into_iter()
+            .filter(|c| known(c.key.split(':').next().unwrap_or(&c.key)))
+            .collect();
+        let failures: Vec<_> = snapshot
+            .failure_counts
+            .into_iter()
+            .filter(|f| known(&f.account_id))
+            .collect();
+        let restored = self.rate_limit_tracker.restore_state(&cooldowns, &failures);
+
+        for (account_id, streak) in snapshot.auth_failure_streaks {
+            if known(&account_id) {
+                self.auth_failure_streaks.entry(account_id).or_insert(streak);
+            }
+        }
+        for (account_id, score) in snapshot.health_scores {
+            if known(&account_id) {
+                self.health_scores.insert(account_id, score);
+            }
+        }
+        let mut sessions = 0;
+        for (session_id, account_id) in snapshot.session_accounts {
+            if known(&account_id) {
+                self.session_accounts.entry(session_id).or_insert(account_id);
+                sessions += 1;
+            }
+        }
+        tracing::info!(
+            "[RuntimeState] Restored {} cooldown(s), {} session binding(s)",
+            restored,
+            sessions
+        );
+    }
+
     /// 中止并等待所有后台任务完成
     /// abort() 仅设置取消标志，必须 await 确认清理完成
     pub async fn abort_background_tasks(&self) {
@@ -1067,6 +1316,19 @@ impl TokenManager {
         force_rotate: bool,
         session_id: Option<&str>,
         target_model: &str,
+    ) -> Result<(String, String, String, String, u64), String> {
+        self.get_token_for_key(quota_group, force_rotate, session_id, target_model, None)
+            .await
+    }
+
+    /// 同 `get_token`，额外传入客户端 API Key 以应用按 Key 的账号分组路由
+    pub async fn get_token_for_key(
+        &self,
+        quota_group: &str,
+        force_rotate: bool,
+        session_id: Option<&str>,
+        target_model: &str,
+        api_key: Option<&str>,
     ) -> Result<(String, String, String, String, u64), String> {
         // [FIX] 检查并处理待重新加载的账号（配额保护同步）
         let pending_reload = crate::proxy::server::take_pending_reload_accounts();
@@ -1093,9 +1355,9 @@ impl TokenManager {
 
         // 【优化 Issue #284】添加 5 秒超时，防止死锁
         let timeout_duration = std::time::Duration::from_secs(5);
-        match tokio::time::timeout(
+        let result = match tokio::time::timeout(
             timeout_duration,
-            self.get_token_internal(quota_group, force_rotate, session_id, target_model),
+            self.get_token_internal(quota_group, force_rotate, session_id, target_model, api_key),
         )
         .await
         {
@@ -1103,7 +1365,47 @@ impl TokenManager {
             Err(_) => Err(
                 "Token acquisition timeout (5s) - system too busy or deadlock detected".to_string(),
             ),
+        };
+        if let Ok((_, _, email, _, _)) = &result {
+            record_request_account(email);
+        }
+        result
+    }
+
+    /// 直接使用指定账号 (固定账号模式 / 调试锁定)：必要时刷新 token 并确保有 project_id
+    async fn use_single_account(&self, mut token: ProxyToken) -> (String, String, String, String, u64) {
+        // 检查 token 是否过期（提前5分钟刷新）
+        let now = chrono::Utc::now().timestamp();
+        if now >= token.timestamp - 300 {
+            tracing::debug!("账号 {} 的 token 即将过期，正在刷新...", token.email);
+            match crate::modules::oauth::refresh_access_token(&token.refresh_token, Some(&token.account_id))
+                .await
+            {
+                Ok(token_response) => {
+                    token.access_token = token_response.access_token.clone();
+                    token.expires_in = token_response.expires_in;
+                    token.timestamp = now + token_response.expires_in;
+
+                    if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
+                        entry.access_token = token.access_token.clone();
+                        entry.expires_in = token.expires_in;
+                        entry.timestamp = token.timestamp;
+                    }
+                    let _ = self
+                        .save_refreshed_token(&token.account_id, &token_response)
+                        .await;
+                }
+                Err(e) => {
+                    tracing::warn!("Account {} token refresh failed: {}", token.email, e);
+                    // 继续使用旧 token，让后续逻辑处理失败
+                }
+            }
         }
+
+        // 确保有 project_id (filter empty strings to trigger re-fetch)
+        let project_id = self.resolve_project_id(&token).await;
+
+        (token.access_token, project_id, token.email, token.account_id, 0)
     }
 
     /// 内部实现：获取 Token 的核心逻辑
@@ -1113,7 +1415,20 @@ impl TokenManager {
         force_rotate: bool,
         session_id: Option<&str>,
         target_model: &str,
+        api_key: Option<&str>,
     ) -> Result<(String, String, String, String, u64), String> {
+        // [NEW] 调试锁定：所有请求固定走同一账号，不做任何过滤与轮换，便于复现单账号问题
+        if let Some(pinned_id) = self.pinned_account_id.read().await.clone() {
+            let token = self.tokens.get(&pinned_id).map(|t| t.value().clone()).ok_or_else(|| {
+                format!(
+                    "Pinned account {} is not in the token pool (disabled, forbidden or removed); unpin it to resume rotation",
+                    pinned_id
+                )
+            })?;
+            tracing::info!("📌 Using pinned account {} (rotation bypassed)", token.email);
+            return Ok(self.use_single_account(token).await);
+        }
+
         let mut tokens_snapshot: Vec<ProxyToken> =
             self.tokens.iter().map(|e| e.value().clone()).collect();
         let mut total = tokens_snapshot.len();
@@ -1147,6 +1462,13 @@ impl TokenManager {
             return Err("Token pool is empty".to_string());
         }
 
+        // [NEW] 账号分组路由：仅保留该模型 / API Key 对应分组内的账号
+        self.apply_account_groups(&mut tokens_snapshot, target_model, api_key).await?;
+
+        // [NEW] 用量上限过滤：超出每日/每月上限的账号在窗口重置前不参与调度
+        self.apply_usage_limits(&mut tokens_snapshot).await?;
+        total = tokens_snapshot.len();
+
         tokens_snapshot.sort_by(|a, b| {
             // Priority 0: 严格的订阅等级排序 (ULTRA > PRO > FREE)
             // 用户要求：轮询应当遵循 Ultra -> Pro -> Free
@@ -1233,7 +1555,7 @@ impl TokenManager {
                 .cloned()
             {
                 // 检查账号是否可用（未限流、未被配额保护）
-                match Self::get_account_state_on_disk(&preferred_token.account_path).await {
+                match Self::get_account_state_on_disk(&preferred_token.accounts_dir, &preferred_token.account_id).await {
                     OnDiskAccountState::Disabled => {
                         tracing::warn!(
                             "🔒 [FIX #820] Preferred account {} is disabled on disk, purging and falling back",
@@ -1288,60 +1610,7 @@ impl TokenManager {
                     );
 
                     // 直接使用优先账号，跳过轮询逻辑
-                    let mut token = preferred_token.clone();
-
-                    // 检查 token 是否过期（提前5分钟刷新）
-                    let now = chrono::Utc::now().timestamp();
-                    if now >= token.timestamp - 300 {
-                        tracing::debug!("账号 {} 的 token 即将过期，正在刷新...", token.email);
-                        match crate::modules::oauth::refresh_access_token(&token.refresh_token, Some(&token.account_id))
-                            .await
-                        {
-                            Ok(token_response) => {
-                                token.access_token = token_response.access_token.clone();
-                                token.expires_in = token_response.expires_in;
-                                token.timestamp = now + token_response.expires_in;
-
-                                if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
-                                    entry.access_token = token.access_token.clone();
-                                    entry.expires_in = token.expires_in;
-                                    entry.timestamp = token.timestamp;
-                                }
-                                let _ = self
-                                    .save_refreshed_token(&token.account_id, &token_response)
-                                    .await;
-                            }
-                            Err(e) => {
-                                tracing::warn!("Preferred account token refresh failed: {}", e);
-                                // 继续使用旧 token，让后续逻辑处理失败
-                            }
-                        }
-                    }
-
-                    // 确保有 project_id (filter empty strings to trigger re-fetch)
-                    let project_id = if let Some(pid) = &token.project_id {
-                        if pid.is_empty() { None } else { Some(pid.clone()) }
-                    } else {
-                        None
-                    };
-                    let project_id = if let Some(pid) = project_id {
-                        pid
-                    } else {
-                        match crate::proxy::project_resolver::fetch_project_id(&token.access_token)
-                            .await
-                        {
-                            Ok(pid) => {
-                                if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
-                                    entry.project_id = Some(pid.clone());
-                                }
-                                let _ = self.save_project_id(&token.account_id, &pid).await;
-                                pid
-                            }
-                            Err(_) => "bamboo-precept-lgxtn".to_string(), // fallback
-                        }
-                    };
-
-                    return Ok((token.access_token, project_id, token.email, token.account_id, 0));
+                    return Ok(self.use_single_account(preferred_token.clone()).await);
                 } else {
                     if is_rate_limited {
                         tracing::warn!("🔒 [FIX #820] Preferred account {} is rate-limited, falling back to round-robin", preferred_token.email);
@@ -1439,7 +1708,9 @@ impl TokenManager {
                 && scheduling.mode != SchedulingMode::PerformanceFirst
             {
                 // 【优化】使用预先获取的快照，不再在循环内加锁
-                if let Some((account_id, last_time)) = &last_used_account_id {
+                // [NEW] 今日最少使用模式跳过 60s 锁定，否则会持续复用同一账号
+                let reuse_window = scheduling.mode != SchedulingMode::LeastUsedToday;
+                if let Some((account_id, last_time)) = last_used_account_id.as_ref().filter(|_| reuse_window) {
                     // [FIX #3] 60s 锁定逻辑应检查 `attempted` 集合，避免重复尝试失败的账号
                     if last_time.elapsed().as_secs() < 60 && !attempted.contains(account_id) {
                         if let Some(found) =
@@ -1484,9 +1755,9 @@ impl TokenManager {
                         }
                     }
 
-                    if let Some(selected) = self.select_with_p2c(
-                        &non_limited, &attempted, &normalized_target, quota_protection_enabled
-                    ) {
+                    if let Some(selected) = self.select_for_mode(
+                        scheduling.mode, &non_limited, &attempted, &normalized_target, quota_protection_enabled
+                    ).await {
                         target_token = Some(selected.clone());
                         need_update_last_used = Some((selected.account_id.clone(), std::time::Instant::now()));
 
@@ -1519,9 +1790,9 @@ impl TokenManager {
                     }
                 }
 
-                if let Some(selected) = self.select_with_p2c(
-                    &non_limited, &attempted, &normalized_target, quota_protection_enabled
-                ) {
+                if let Some(selected) = self.select_for_mode(
+                    scheduling.mode, &non_limited, &attempted, &normalized_target, quota_protection_enabled
+                ).await {
                     tracing::debug!("  {} - SELECTED via P2C", selected.email);
                     target_token = Some(selected.clone());
 
@@ -1604,7 +1875,7 @@ impl TokenManager {
 
             // Safety net: avoid selecting an account that has been disabled on disk but still
             // exists in the in-memory snapshot (e.g. stale cache + sticky session binding).
-            match Self::get_account_state_on_disk(&token.account_path).await {
+            match Self::get_account_state_on_disk(&token.accounts_dir, &token.account_id).await {
                 OnDiskAccountState::Disabled => {
                     tracing::warn!(
                         "Selected account {} is disabled on disk, purging and retrying",
@@ -1689,33 +1960,14 @@ impl TokenManager {
             }
 
             // 4. 确保有 project_id (filter empty strings to trigger re-fetch)
-            let project_id = if let Some(pid) = &token.project_id {
-                if pid.is_empty() { None } else { Some(pid.clone()) }
-            } else {
-                None
-            };
-            let project_id = if let Some(pid) = project_id {
-                pid
-            } else {
-                tracing::debug!("账号 {} 缺少 project_id，尝试获取...", token.email);
-                match crate::proxy::project_resolver::fetch_project_id(&token.access_token).await {
-                    Ok(pid) => {
-                        if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
-                            entry.project_id = Some(pid.clone());
-                        }
-                        let _ = self.save_project_id(&token.account_id, &pid).await;
-                        pid
-                    }
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to fetch project_id for {}, using fallback: {}",
-                            token.email, e
-                        );
-                        // [FIX #1794] 为 503 问题提供稳定兜底，不跳过该账号
-                        "bamboo-precept-lgxtn".to_string()
-                    }
-                }
-            };
+            let project_id = self.resolve_project_id(&token).await;
+
+            // [NEW] 账号切换时推送活动事件
+            crate::proxy::activity::note_account_served(
+                &token.account_id,
+                &token.email,
+                if attempt > 0 { "failover" } else { "scheduled" },
+            );
 
             // 【优化】在成功返回前，统一更新 last_used_account（如果需要）
             if let Some((new_account_id, new_time)) = need_update_last_used {
@@ -1736,50 +1988,130 @@ impl TokenManager {
         Err(last_error.unwrap_or_else(|| "All accounts failed".to_string()))
     }
 
-    async fn disable_account(&self, account_id: &str, reason: &str) -> Result<(), String> {
-        let path = if let Some(entry) = self.tokens.get(account_id) {
-            entry.account_path.clone()
-        } else {
-            self.data_dir
-                .join("accounts")
-                .join(format!("{}.json", account_id))
-        };
-
-        let mut content: serde_json::Value = serde_json::from_str(
-            &std::fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?,
-        )
-        .map_err(|e| format!("解析 JSON 失败: {}", e))?;
+    /// 将内存中修改过的字段写回账号存储 (事务内只覆盖指定字段，其余字段以存储中的最新值为准)
+    fn patch_account_fields(
+        accounts_dir: &Path,
+        account_id: &str,
+        source: &serde_json::Value,
+        fields: &[&str],
+    ) -> Result<(), String> {
+        crate::modules::account_db::update_account(accounts_dir, account_id, |stored| {
+            for field in fields {
+                stored[*field] = source.get(*field).cloned().unwrap_or(serde_json::Value::Null);
+            }
+            Ok(())
+        })
+        .map(|_| ())
+    }
 
+    async fn disable_account(&self, account_id: &str, reason: &str) -> Result<(), String> {
         let now = chrono::Utc::now().timestamp();
-        content["disabled"] = serde_json::Value::Bool(true);
-        content["disabled_at"] = serde_json::Value::Number(now.into());
-        content["disabled_reason"] = serde_json::Value::String(truncate_reason(reason, 800));
-
-        std::fs::write(&path, serde_json::to_string_pretty(&content).unwrap())
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+        crate::modules::account_db::update_account(&self.accounts_dir(), account_id, |content| {
+            content["disabled"] = serde_json::Value::Bool(true);
+            content["disabled_at"] = serde_json::Value::Number(now.into());
+            content["disabled_reason"] = serde_json::Value::String(truncate_reason(reason, 800));
+            // [NEW] 仅在 invalid_grant 时调用: 标记需要重新授权，供 UI 提示一键重新登录
+            content["needs_reauth"] = serde_json::Value::Bool(true);
+            Ok(())
+        })
+        .map_err(|e| format!("写入文件失败: {}", e))?;
+        let _ = crate::modules::account::sync_needs_reauth_summary(account_id);
 
         // 【修复 Issue #3】从内存中移除禁用的账号，防止被60s锁定逻辑继续使用
         self.tokens.remove(account_id);
 
-        tracing::warn!("Account disabled: {} ({:?})", account_id, path);
+        tracing::warn!("Account disabled: {}", account_id);
         Ok(())
     }
 
+    /// [NEW] 更新参与轮询的账号子集，返回是否发生变化 (变化时调用方需重新加载账号)
+    pub async fn update_account_subset(&self, account_ids: &[String]) -> bool {
+        let new_subset: HashSet<String> = account_ids.iter().cloned().collect();
+        let mut subset = self.account_subset.write().await;
+        if *subset == new_subset {
+            return false;
+        }
+        tracing::info!("账号子集已更新: {} 个账号 (0 表示全部)", new_subset.len());
+        *subset = new_subset;
+        true
+    }
+
+    /// 获取账号 project_id: 优先使用 TokenData 缓存，缺失时调用上游发现 / 开通并写回
+    async fn resolve_project_id(&self, token: &ProxyToken) -> String {
+        if let Some(pid) = token.project_id.as_ref().filter(|p| !p.is_empty()) {
+            return pid.clone();
+        }
+
+        tracing::debug!("账号 {} 缺少 project_id，尝试获取...", token.email);
+        match crate::proxy::project_resolver::provision_project_id(&token.access_token).await {
+            Ok(pid) => {
+                self.cache_project_id(&token.account_id, &pid).await;
+                pid
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to fetch project_id for {}, using fallback: {}",
+                    token.email, e
+                );
+                // [FIX #1794] 为 503 问题提供稳定兜底，不跳过该账号
+                crate::proxy::project_resolver::FALLBACK_PROJECT_ID.to_string()
+            }
+        }
+    }
+
+    async fn cache_project_id(&self, account_id: &str, project_id: &str) {
+        if let Some(mut entry) = self.tokens.get_mut(account_id) {
+            entry.project_id = Some(project_id.to_string());
+        }
+        let _ = self.save_project_id(account_id, project_id).await;
+    }
+
+    /// [NEW] 上游因 project_id 失效返回 403/404 时重新发现并缓存 project_id
+    /// 同一账号 60 秒内只重新发现一次，避免并发请求反复调用上游
+    pub async fn rediscover_project_id(&self, account_id: &str) -> Result<String, String> {
+        const REDISCOVER_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(60);
+
+        if let Some(last) = self.project_rediscovered_at.get(account_id) {
+            if last.elapsed() < REDISCOVER_COOLDOWN {
+                return Err("project_id was rediscovered recently".to_string());
+            }
+        }
+        self.project_rediscovered_at
+            .insert(account_id.to_string(), std::time::Instant::now());
+
+        let (access_token, old_project_id) = self
+            .tokens
+            .get(account_id)
+            .map(|t| (t.access_token.clone(), t.project_id.clone()))
+            .ok_or("账号不存在")?;
+
+        let project_id =
+            crate::proxy::project_resolver::provision_project_id(&access_token).await?;
+        if old_project_id.as_deref() != Some(project_id.as_str()) {
+            tracing::warn!(
+                "Project id for account {} changed: {:?} -> {}",
+                account_id,
+                old_project_id,
+                project_id
+            );
+        }
+        self.cache_project_id(account_id, &project_id).await;
+        Ok(project_id)
+    }
+
     /// 保存 project_id 到账号文件
     async fn save_project_id(&self, account_id: &str, project_id: &str) -> Result<(), String> {
         let entry = self.tokens.get(account_id)
             .ok_or("账号不存在")?;
 
-        let path = &entry.account_path;
-
-        let mut content: serde_json::Value = serde_json::from_str(
-            &std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?
-        ).map_err(|e| format!("解析 JSON 失败: {}", e))?;
+        let accounts_dir = entry.accounts_dir.clone();
+        drop(entry);
 
-        content["token"]["project_id"] = serde_json::Value::String(project_id.to_string());
-
-        std::fs::write(path, serde_json::to_string_pretty(&content).unwrap())
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+        crate::modules::account_db::update_account(&accounts_dir, account_id, |content| {
+            content["token"]["project_id"] = serde_json::Value::String(project_id.to_string());
+            Ok(())
+        })
+        .map_err(|e| format!("写入文件失败: {}", e))?;
 
         tracing::debug!("已保存 project_id 到账号 {}", account_id);
         Ok(())
@@ -1790,20 +2122,21 @@ impl TokenManager {
         let entry = self.tokens.get(account_id)
             .ok_or("账号不存在")?;
 
-        let path = &entry.account_path;
-
-        let mut content: serde_json::Value = serde_json::from_str(
-            &std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?
-        ).map_err(|e| format!("解析 JSON 失败: {}", e))?;
+        let accounts_dir = entry.accounts_dir.clone();
+        drop(entry);
 
         let now = chrono::Utc::now().timestamp();
-
-        content["token"]["access_token"] = serde_json::Value::String(token_response.access_token.clone());
-        content["token"]["expires_in"] = serde_json::Value::Number(token_response.expires_in.into());
-        content["token"]["expiry_timestamp"] = serde_json::Value::Number((now + token_response.expires_in).into());
-
-        std::fs::write(path, serde_json::to_string_pretty(&content).unwrap())
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+        let sealed_access_token = crate::utils::crypto::seal_token(&token_response.access_token)?;
+
+        // 事务内只更新 Token 字段，避免覆盖 UI 同时修改的其他字段
+        crate::modules::account_db::update_account(&accounts_dir, account_id, |content| {
+            content["token"]["access_token"] = serde_json::Value::String(sealed_access_token);
+            content["token"]["expires_in"] = serde_json::Value::Number(token_response.expires_in.into());
+            content["token"]["expiry_timestamp"] =
+                serde_json::Value::Number((now + token_response.expires_in).into());
+            Ok(())
+        })
+        .map_err(|e| format!("写入文件失败: {}", e))?;
 
         tracing::debug!("已保存刷新后的 token 到账号 {}", account_id);
         Ok(())
@@ -1813,6 +2146,18 @@ impl TokenManager {
         self.tokens.len()
     }
 
+    /// 当前未处于账号级限流中的账号数量 (托盘状态展示用)
+    pub async fn healthy_count(&self) -> usize {
+        let ids: Vec<String> = self.tokens.iter().map(|e| e.key().clone()).collect();
+        let mut healthy = 0;
+        for id in ids {
+            if !self.is_rate_limited(&id, None).await {
+                healthy += 1;
+            }
+        }
+        healthy
+    }
+
     /// 通过 email 获取指定账号的 Token（用于预热等需要指定账号的场景）
     /// 此方法会自动刷新过期的 token
     pub async fn get_token_by_email(
@@ -1855,7 +2200,9 @@ impl TokenManager {
 
         let project_id = project_id_opt
             .filter(|s| !s.is_empty())
-            .unwrap_or_else(|| "bamboo-precept-lgxtn".to_string());
+            .unwrap_or_else(|| crate::proxy::project_resolver::FALLBACK_PROJECT_ID.to_string());
+
+        record_request_account(email);
 
         // 检查是否过期 (提前5分钟)
         if now < timestamp + expires_in - 300 {
@@ -1984,6 +2331,7 @@ impl TokenManager {
     /// 下次失败时从最短的锁定时间开始（智能限流）。
     pub fn mark_account_success(&self, account_id: &str) {
         self.rate_limit_tracker.mark_success(account_id);
+        self.auth_failure_streaks.remove(account_id);
     }
 
     /// 检查是否有可用的 Google 账号
@@ -2057,13 +2405,10 @@ impl TokenManager {
     /// 返回该账号最近的配额刷新时间字符串（ISO 8601 格式）
     ///
     /// # 参数
-    /// - `account_id`: 账号 ID（用于查找账号文件）
+    /// - `account_id`: 账号 ID（用于查找账号数据）
     pub fn get_quota_reset_time(&self, account_id: &str) -> Option<String> {
-        // 直接用 account_id 查找账号文件（文件名是 {account_id}.json）
-        let account_path = self.data_dir.join("accounts").join(format!("{}.json", account_id));
-
-        let content = std::fs::read_to_string(&account_path).ok()?;
-        let account: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let account =
+            crate::modules::account_db::read_account_value(&self.accounts_dir(), account_id).ok()??;
 
         // 获取 quota.models 中最早的 reset_time（最保守的锁定策略）
         account
@@ -2321,6 +2666,192 @@ impl TokenManager {
         self.circuit_breaker_config.read().await.clone()
     }
 
+    /// [NEW] 更新用量上限配置
+    pub async fn update_usage_limits(&self, config: crate::proxy::config::UsageLimitsConfig) {
+        *self.usage_limits.write().await = config;
+        // 配置变更后立即以最新用量重新判断
+        *self.usage_snapshot.lock().await = None;
+        tracing::debug!("Usage limits configuration updated");
+    }
+
+    /// 更新自动停用配置
+    pub async fn update_auto_disable_config(&self, config: crate::proxy::config::AutoDisableConfig) {
+        *self.auto_disable.write().await = config;
+        tracing::debug!("Auto-disable configuration updated");
+    }
+
+    /// [NEW] 记录一次上游 401/403，返回当前连续失败次数
+    ///
+    /// 只统计 401 与账号级 403；项目或模型范围的 PERMISSION_DENIED 不计入。
+    /// 每次失败都会让下次选中该账号时强制刷新 access_token；阈值至少为 2，
+    /// 保证停用前已经历过一次刷新。达到阈值后停用账号并从轮询池移除。
+    pub async fn record_auth_failure(&self, account_id: &str, status_code: u16, error_text: &str) -> u32 {
+        if !counts_toward_auto_disable(status_code, error_text) {
+            return 0;
+        }
+
+        let streak = {
+            let mut entry = self.auth_failure_streaks.entry(account_id.to_string()).or_insert(0);
+            *entry += 1;
+            *entry
+        };
+        if let Some(mut token) = self.tokens.get_mut(account_id) {
+            token.timestamp = 0;
+        }
+
+        let config = self.auto_disable.read().await.clone();
+        if !config.enabled || streak < config.consecutive_auth_failures.max(2) {
+            tracing::debug!(
+                "Account {} auth failure streak {} (status {})",
+                account_id, streak, status_code
+            );
+            return streak;
+        }
+
+        let reason = format!(
+            "{} consecutive upstream auth failures (last {}): {}",
+            streak,
+            status_code,
+            truncate_reason(error_text, 200)
+        );
+        match crate::modules::account::auto_disable_account(account_id, &reason) {
+            Ok(()) => {
+                tracing::warn!("⛔ Account {} auto-disabled: {}", account_id, reason);
+                self.remove_account(account_id);
+            }
+            Err(e) => tracing::error!("Failed to auto-disable account {}: {}", account_id, e),
+        }
+        streak
+    }
+
+    /// 更新账号分组路由配置
+    pub async fn update_account_groups(&self, config: crate::proxy::config::AccountGroupsConfig) {
+        *self.account_groups.write().await = config;
+        tracing::debug!("Account groups configuration updated");
+    }
+
+    /// 按分组路由过滤候选账号
+    async fn apply_account_groups(
+        &self,
+        tokens: &mut Vec<ProxyToken>,
+        target_model: &str,
+        api_key: Option<&str>,
+    ) -> Result<(), String> {
+        let config = self.account_groups.read().await;
+        let Some(groups) = config.groups_for(target_model, api_key) else {
+            return Ok(());
+        };
+
+        let in_group: Vec<ProxyToken> = tokens
+            .iter()
+            .filter(|t| config.contains(groups, &t.account_id, &t.email))
+            .cloned()
+            .collect();
+        if in_group.is_empty() {
+            if config.fallback_to_all {
+                tracing::warn!(
+                    "[AccountGroups] No accounts in {:?} for {}, falling back to all accounts",
+                    groups,
+                    target_model
+                );
+                return Ok(());
+            }
+            return Err(format!(
+                "No accounts available in account group(s) {} for model: {}",
+                groups.join(", "),
+                target_model
+            ));
+        }
+        tracing::debug!(
+            "[AccountGroups] {} -> {:?} ({} of {} accounts)",
+            target_model,
+            groups,
+            in_group.len(),
+            tokens.len()
+        );
+        *tokens = in_group;
+        Ok(())
+    }
+
+    /// 获取当前日/月用量快照 (带短时缓存)
+    async fn get_usage_snapshot(&self) -> Arc<UsageSnapshot> {
+        let mut cache = self.usage_snapshot.lock().await;
+        if let Some((fetched_at, snapshot)) = cache.as_ref() {
+            if fetched_at.elapsed() < USAGE_SNAPSHOT_TTL {
+                return snapshot.clone();
+            }
+        }
+
+        let snapshot = match tokio::task::spawn_blocking(crate::modules::token_stats::get_usage_windows).await {
+            Ok(Ok(windows)) => Arc::new(windows),
+            Ok(Err(e)) => {
+                tracing::warn!("[UsageLimit] Failed to load usage windows: {}", e);
+                Arc::new(HashMap::new())
+            }
+            Err(e) => {
+                tracing::warn!("[UsageLimit] Spawn blocking failed: {}", e);
+                Arc::new(HashMap::new())
+            }
+        };
+        *cache = Some((std::time::Instant::now(), snapshot.clone()));
+        snapshot
+    }
+
+    /// 按用量上限过滤候选账号；全局超限或全部账号超限时返回 USAGE_LIMIT_ERROR_PREFIX 开头的错误
+    async fn apply_usage_limits(&self, tokens: &mut Vec<ProxyToken>) -> Result<(), String> {
+        let limits = self.usage_limits.read().await.clone();
+        if !limits.enabled {
+            return Ok(());
+        }
+
+        let usage = self.get_usage_snapshot().await;
+
+        let global = usage.values().fold(
+            crate::modules::token_stats::AccountUsageWindow::default(),
+            |mut acc, w| {
+                acc.daily_tokens += w.daily_tokens;
+                acc.daily_requests += w.daily_requests;
+                acc.monthly_tokens += w.monthly_tokens;
+                acc.monthly_requests += w.monthly_requests;
+                acc
+            },
+        );
+        if let Some(reason) = limits.global.exceeded_by(
+            global.daily_tokens,
+            global.daily_requests,
+            global.monthly_tokens,
+            global.monthly_requests,
+        ) {
+            tracing::warn!("[UsageLimit] Global limit reached: {}", reason);
+            return Err(format!("{} (global {})", USAGE_LIMIT_ERROR_PREFIX, reason));
+        }
+
+        let before = tokens.len();
+        tokens.retain(|t| {
+            let Some(w) = usage.get(&t.email) else {
+                return true;
+            };
+            match limits.limit_for(&t.email).exceeded_by(
+                w.daily_tokens,
+                w.daily_requests,
+                w.monthly_tokens,
+                w.monthly_requests,
+            ) {
+                Some(reason) => {
+                    tracing::debug!("[UsageLimit] Skipping {}: {}", t.email, reason);
+                    false
+                }
+                None => true,
+            }
+        });
+
+        if tokens.is_empty() && before > 0 {
+            tracing::warn!("[UsageLimit] All {} candidate accounts reached their usage limits", before);
+            return Err(format!("{} (all accounts reached their limits)", USAGE_LIMIT_ERROR_PREFIX));
+        }
+        Ok(())
+    }
+
     /// 清除特定会话的粘性映射
     #[allow(dead_code)]
     pub fn clear_session_binding(&self, session_id: &str) {
@@ -2351,6 +2882,34 @@ impl TokenManager {
         self.preferred_account_id.read().await.clone()
     }
 
+    /// [NEW] 调试锁定账号 (账号 ID 或邮箱)，返回账号 ID
+    ///
+    /// 与固定账号模式不同：锁定后不回退到轮询，也不受限流、配额保护与分组过滤影响，
+    /// 该账号的真实错误会直接暴露给客户端。仅保存在内存中，重启反代后失效。
+    pub async fn pin_account(&self, account: &str) -> Result<String, String> {
+        let account_id = if self.tokens.contains_key(account) {
+            account.to_string()
+        } else {
+            self.get_account_id_by_email(account)
+                .ok_or_else(|| format!("Account {} is not in the token pool", account))?
+        };
+        *self.pinned_account_id.write().await = Some(account_id.clone());
+        tracing::warn!("📌 Proxy pinned to account {} (debug mode, rotation disabled)", account_id);
+        Ok(account_id)
+    }
+
+    /// [NEW] 解除调试锁定，恢复正常轮换
+    pub async fn unpin_account(&self) {
+        if self.pinned_account_id.write().await.take().is_some() {
+            tracing::info!("📌 Proxy account pin removed, rotation resumed");
+        }
+    }
+
+    /// [NEW] 获取当前调试锁定的账号 ID
+    pub async fn get_pinned_account(&self) -> Option<String> {
+        self.pinned_account_id.read().await.clone()
+    }
+
     /// 使用 Authorization Code 交换 Refresh Token (Web OAuth)
     pub async fn exchange_code(&self, code: &str, redirect_uri: &str) -> Result<String, String> {
         crate::modules::oauth::exchange_code(code, redirect_uri)
@@ -2387,9 +2946,9 @@ impl TokenManager {
             .map_err(|e| format!("Invalid refresh token: {}", e))?;
 
         // 2. 获取项目 ID (Project ID)
-        let project_id = crate::proxy::project_resolver::fetch_project_id(&token_info.access_token)
+        let project_id = crate::proxy::project_resolver::provision_project_id(&token_info.access_token)
             .await
-            .unwrap_or_else(|_| "bamboo-precept-lgxtn".to_string()); // Fallback
+            .unwrap_or_else(|_| crate::proxy::project_resolver::FALLBACK_PROJECT_ID.to_string()); // Fallback
 
         // 3. 委托给 modules::account::add_account 处理 (包含文件写入、索引更新、锁)
         let email_clone = email.to_string();
@@ -2435,6 +2994,60 @@ impl TokenManager {
         tracing::warn!("📉 Health score decreased for account {}", account_id);
     }
 
+    /// [NEW] 记录一次请求结果 (由监控中间件在响应返回后调用)
+    ///
+    /// 同时更新滚动指标与健康分，选号排序会实时参考健康分。
+    pub fn record_request_outcome(&self, account_id: &str, success: bool, latency_ms: u64) {
+        self.account_metrics
+            .entry(account_id.to_string())
+            .or_default()
+            .record(success, latency_ms, chrono::Utc::now().timestamp());
+        if success {
+            self.auth_failure_streaks.remove(account_id);
+            self.record_success(account_id);
+        } else {
+            self.record_failure(account_id);
+        }
+    }
+
+    /// [NEW] 获取所有账号的运行指标快照 (未产生请求的账号也会列出)
+    pub fn get_account_metrics(&self) -> Vec<AccountMetrics> {
+        let mut result: Vec<AccountMetrics> = self
+            .tokens
+            .iter()
+            .map(|entry| {
+                let token = entry.value();
+                let health_score = self.health_scores.get(&token.account_id).map(|v| *v).unwrap_or(1.0);
+                let mut metrics = AccountMetrics {
+                    account_id: token.account_id.clone(),
+                    email: token.email.clone(),
+                    total_requests: 0,
+                    total_failures: 0,
+                    success_rate: 1.0,
+                    consecutive_failures: 0,
+                    consecutive_successes: 0,
+                    avg_latency_ms: 0,
+                    health_score,
+                    last_used_at: None,
+                    last_failure_at: None,
+                };
+                if let Some(state) = self.account_metrics.get(&token.account_id) {
+                    metrics.total_requests = state.total_requests;
+                    metrics.total_failures = state.total_failures;
+                    metrics.success_rate = state.success_rate();
+                    metrics.consecutive_failures = state.consecutive_failures;
+                    metrics.consecutive_successes = state.consecutive_successes;
+                    metrics.avg_latency_ms = state.avg_latency_ms();
+                    metrics.last_used_at = state.last_used_at;
+                    metrics.last_failure_at = state.last_failure_at;
+                }
+                metrics
+            })
+            .collect();
+        result.sort_by(|a, b| a.email.cmp(&b.email));
+        result
+    }
+
     /// [NEW] 从账号配额信息中提取最近的刷新时间戳
     ///
     /// Claude 模型（sonnet/opus）共用同一个刷新时间，只需取 claude 系列的 reset_time
@@ -2530,21 +3143,11 @@ impl TokenManager {
         }
 
         // 2. Persist to disk
-        let path = self.data_dir.join("accounts").join(format!("{}.json", account_id));
-        if !path.exists() {
-             return Err(format!("Account file not found: {:?}", path));
+        let accounts_dir = self.accounts_dir();
+        if !crate::modules::account_db::account_exists(&accounts_dir, account_id) {
+             return Err(format!("Account not found: {}", account_id));
         }
 
-        let content = std::fs::read_to_string(&path)
-             .map_err(|e| format!("Failed to read account file: {}", e))?;
-
-        let mut account: serde_json::Value = serde_json::from_str(&content)
-             .map_err(|e| format!("Failed to parse account JSON: {}", e))?;
-
-        account["validation_blocked"] = serde_json::Value::Bool(true);
-        account["validation_blocked_until"] = serde_json::Value::Number(serde_json::Number::from(block_until));
-        account["validation_blocked_reason"] = serde_json::Value::String(reason.to_string());
-
         // [NEW] 尝试从消息中提取验证链接 (#1522)
         let extracted_url = if let Ok(parsed_json) = serde_json::from_str::<serde_json::Value>(reason) {
              // 尝试从特定的 Google RPC error 结构中取
@@ -2575,21 +3178,25 @@ impl TokenManager {
              })
         };
         
-        if let Some(url) = extracted_url {
-             account["validation_url"] = serde_json::Value::String(url.clone());
+        if let Some(ref url) = extracted_url {
              if let Some(mut token) = self.tokens.get_mut(account_id) {
-                 token.validation_url = Some(url);
+                 token.validation_url = Some(url.clone());
              }
         }
 
         // Clear sticky session if blocked
         self.session_accounts.retain(|_, v| *v != account_id);
 
-        let json_str = serde_json::to_string_pretty(&account)
-             .map_err(|e| format!("Failed to serialize account JSON: {}", e))?;
-
-        std::fs::write(&path, json_str)
-             .map_err(|e| format!("Failed to write account file: {}", e))?;
+        crate::modules::account_db::update_account(&accounts_dir, account_id, |account| {
+             account["validation_blocked"] = serde_json::Value::Bool(true);
+             account["validation_blocked_until"] = serde_json::Value::Number(serde_json::Number::from(block_until));
+             account["validation_blocked_reason"] = serde_json::Value::String(reason.to_string());
+             if let Some(url) = extracted_url {
+                 account["validation_url"] = serde_json::Value::String(url);
+             }
+             Ok(())
+        })
+        .map_err(|e| format!("Failed to write account file: {}", e))?;
 
         tracing::info!(
              "🚫 Account {} validation blocked until {} (reason: {})",
@@ -2627,6 +3234,51 @@ impl TokenManager {
     }
 }
 
+/// [NEW] 判断上游 401/403 是否属于账号级鉴权失败
+///
+/// VALIDATION_REQUIRED 属于临时验证阻止；项目错误由 project_resolver 重新发现处理；
+/// 模型级权限错误只说明账号无权使用该模型，三者都不应导致停用账号。
+fn counts_toward_auto_disable(status_code: u16, error_text: &str) -> bool {
+    match status_code {
+        401 => true,
+        403 => {
+            let text = error_text.to_lowercase();
+            !(text.contains("validation_required")
+                || text.contains("verify your account")
+                || crate::proxy::project_resolver::is_project_error(status_code, error_text)
+                || is_model_permission_error(error_text))
+        }
+        _ => false,
+    }
+}
+
+/// 模型级 PERMISSION_DENIED：优先解析 JSON 错误体的 status + message，
+/// 非 JSON 时只匹配明确指向模型的措辞，避免错误体其他字段里的 "model" 误判
+fn is_model_permission_error(error_text: &str) -> bool {
+    if let Ok(body) = serde_json::from_str::<serde_json::Value>(error_text) {
+        let error = body.get("error").unwrap_or(&body);
+        let status = error.get("status").and_then(|v| v.as_str()).unwrap_or_default();
+        let message = error
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        return status == "PERMISSION_DENIED" && message.contains("model");
+    }
+    let text = error_text.to_lowercase();
+    (text.contains("permission_denied") || text.contains("permission denied"))
+        && [
+            "access to model",
+            "access to the model",
+            "permission to use model",
+            "permission to use the model",
+            "permission denied on model",
+            "for model",
+        ]
+        .iter()
+        .any(|phrase| text.contains(phrase))
+}
+
 /// 截断过长的原因字符串
 fn truncate_reason(reason: &str, max_len: usize) -> String {
     if reason.len() <= max_len {
@@ -2643,11 +3295,86 @@ fn truncate_reason(reason: &str, max_len: usize) -> String {
     }
 }
 
+/// 将选中的账号写入当前 request span (request_id 中间件声明的 `account` 字段)，供 JSON 日志关联
+fn record_request_account(email: &str) {
+    tracing::Span::current().record("account", email);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::cmp::Ordering;
 
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for LogBuffer {
+        type Writer = LogBuffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_selected_account_is_recorded_on_request_span() {
+        use axum::{body::Body, http::Request, routing::post, Router};
+        use tower::ServiceExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let buffer = LogBuffer::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(crate::modules::log_json::JsonLogLayer::new(buffer.clone()));
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let manager = Arc::new(TokenManager::new(PathBuf::from("/tmp/test")));
+        let mut token = create_test_token("span@test.com", None, 1.0, None, None);
+        token.project_id = Some("project-span".to_string());
+        manager.tokens.insert(token.account_id.clone(), token);
+        manager.pin_account("span@test.com").await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/v1/messages",
+                post(move || {
+                    let manager = manager.clone();
+                    async move {
+                        manager
+                            .get_token_for_key("claude", false, None, "claude-sonnet-4-5", None)
+                            .await
+                            .unwrap();
+                        tracing::info!("upstream request sent");
+                    }
+                }),
+            )
+            .layer(axum::middleware::from_fn(
+                crate::proxy::middleware::request_id::request_id_middleware,
+            ));
+        app.oneshot(Request::post("/v1/messages").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = output
+            .lines()
+            .filter_map(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+            .find(|l| l["message"] == "upstream request sent")
+            .unwrap();
+        assert_eq!(line["account"], "span@test.com");
+        assert!(line["request_id"].as_str().unwrap().starts_with("req_"));
+    }
+
     #[tokio::test]
     async fn test_reload_account_purges_cache_when_account_becomes_proxy_disabled() {
         let tmp_root = std::env::temp_dir().join(format!(
@@ -2660,7 +3387,6 @@ mod tests {
         let account_id = "acc1";
         let email = "a@test.com";
         let now = chrono::Utc::now().timestamp();
-        let account_path = accounts_dir.join(format!("{}.json", account_id));
 
         let account_json = serde_json::json!({
             "id": account_id,
@@ -2676,7 +3402,7 @@ mod tests {
             "created_at": now,
             "last_used": now
         });
-        std::fs::write(&account_path, serde_json::to_string_pretty(&account_json).unwrap()).unwrap();
+        crate::modules::account_db::write_account(&accounts_dir, account_id, &account_json).unwrap();
 
         let manager = TokenManager::new(tmp_root.clone());
         manager.load_accounts().await.unwrap();
@@ -2696,7 +3422,7 @@ mod tests {
         disabled_json["proxy_disabled"] = serde_json::Value::Bool(true);
         disabled_json["proxy_disabled_reason"] = serde_json::Value::String("manual".to_string());
         disabled_json["proxy_disabled_at"] = serde_json::Value::Number(now.into());
-        std::fs::write(&account_path, serde_json::to_string_pretty(&disabled_json).unwrap()).unwrap();
+        crate::modules::account_db::write_account(&accounts_dir, account_id, &disabled_json).unwrap();
 
         manager.reload_account(account_id).await.unwrap();
 
@@ -2719,7 +3445,6 @@ mod tests {
         let now = chrono::Utc::now().timestamp();
 
         let write_account = |id: &str, email: &str, proxy_disabled: bool| {
-            let account_path = accounts_dir.join(format!("{}.json", id));
             let json = serde_json::json!({
                 "id": id,
                 "email": email,
@@ -2736,7 +3461,7 @@ mod tests {
                 "created_at": now,
                 "last_used": now
             });
-            std::fs::write(&account_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+            crate::modules::account_db::write_account(&accounts_dir, id, &json).unwrap();
         };
 
         // Two accounts in pool.
@@ -2778,7 +3503,6 @@ mod tests {
         let now = chrono::Utc::now().timestamp();
 
         let write_account = |id: &str, email: &str, percentage: i64, proxy_disabled: bool| {
-            let account_path = accounts_dir.join(format!("{}.json", id));
             let json = serde_json::json!({
                 "id": id,
                 "email": email,
@@ -2800,7 +3524,7 @@ mod tests {
                 "created_at": now,
                 "last_used": now
             });
-            std::fs::write(&account_path, serde_json::to_string_pretty(&json).unwrap()).unwrap();
+            crate::modules::account_db::write_account(&accounts_dir, id, &json).unwrap();
         };
 
         // Two accounts in pool. acc1 has higher quota -> should be selected and bound first.
@@ -2856,7 +3580,7 @@ mod tests {
             expires_in: 3600,
             timestamp: chrono::Utc::now().timestamp() + 3600,
             email: email.to_string(),
-            account_path: PathBuf::from("/tmp/test"),
+            accounts_dir: PathBuf::from("/tmp/test"),
             project_id: None,
             subscription_tier: tier.map(|s| s.to_string()),
             remaining_quota,
@@ -2868,6 +3592,7 @@ mod tests {
             validation_url: None,
             model_quotas: HashMap::new(),
             model_limits: HashMap::new(),
+            upstream_proxy: None,
         }
     }
 
@@ -3114,7 +3839,7 @@ mod tests {
             expires_in: 3600,
             timestamp: chrono::Utc::now().timestamp() + 3600,
             email: email.to_string(),
-            account_path: PathBuf::from("/tmp/test"),
+            accounts_dir: PathBuf::from("/tmp/test"),
             project_id: None,
             subscription_tier: Some("PRO".to_string()),
             remaining_quota,
@@ -3126,6 +3851,7 @@ mod tests {
             validation_url: None,
             model_quotas: HashMap::new(),
             model_limits: HashMap::new(),
+            upstream_proxy: None,
         }
     }
 
@@ -3150,6 +3876,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_least_used_today_selection() {
+        use crate::modules::token_stats::AccountUsageWindow;
+
+        let busy = create_test_token("busy@test.com", Some("ULTRA"), 1.0, None, Some(90));
+        let idle = create_test_token("idle@test.com", Some("PRO"), 1.0, None, Some(50));
+        let fresh = create_test_token("fresh@test.com", Some("PRO"), 1.0, None, Some(40));
+        let candidates = vec![busy, idle, fresh];
+
+        let mut usage: UsageSnapshot = HashMap::new();
+        usage.insert(
+            "busy@test.com".to_string(),
+            AccountUsageWindow { daily_tokens: 500_000, ..Default::default() },
+        );
+        usage.insert(
+            "idle@test.com".to_string(),
+            AccountUsageWindow { daily_tokens: 1_000, ..Default::default() },
+        );
+
+        // 今日无记录的账号用量视为 0，优先被选中
+        let mut attempted: HashSet<String> = HashSet::new();
+        let selected = TokenManager::select_least_used(&candidates, &attempted, "claude", false, &usage);
+        assert_eq!(selected.unwrap().email, "fresh@test.com");
+
+        attempted.insert("fresh@test.com".to_string());
+        let selected = TokenManager::select_least_used(&candidates, &attempted, "claude", false, &usage);
+        assert_eq!(selected.unwrap().email, "idle@test.com");
+    }
+
     #[test]
     fn test_p2c_skips_attempted() {
         // P2C 应跳过已尝试的账号
@@ -3472,4 +4227,158 @@ mod tests {
             "Sonnet should sort by quota first, then by tier as tiebreaker"
         );
     }
+
+    #[tokio::test]
+    async fn test_account_groups_filter_candidates() {
+        use crate::proxy::config::{AccountGroup, AccountGroupRoute, AccountGroupsConfig};
+
+        let manager = TokenManager::new(PathBuf::from("/tmp/test"));
+        let mut config = AccountGroupsConfig {
+            enabled: true,
+            model_routes: vec![AccountGroupRoute {
+                pattern: "gemini-*-flash*".to_string(),
+                groups: vec!["flash-only".to_string()],
+            }],
+            ..Default::default()
+        };
+        config.groups.insert(
+            "flash-only".to_string(),
+            AccountGroup { accounts: vec!["Flash@Test.com".to_string()], description: None },
+        );
+        config.groups.insert(
+            "personal".to_string(),
+            AccountGroup { accounts: vec!["me@test.com".to_string()], description: None },
+        );
+        config.key_routes.insert("sk-me".to_string(), vec!["personal".to_string()]);
+        manager.update_account_groups(config).await;
+
+        let pool = || {
+            vec![
+                create_test_token("flash@test.com", Some("PRO"), 1.0, None, Some(50)),
+                create_test_token("me@test.com", Some("PRO"), 1.0, None, Some(50)),
+            ]
+        };
+
+        let mut tokens = pool();
+        manager.apply_account_groups(&mut tokens, "gemini-2.5-flash", None).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].email, "flash@test.com");
+
+        // API Key 路由优先于模型路由
+        let mut tokens = pool();
+        manager.apply_account_groups(&mut tokens, "gemini-2.5-flash", Some("sk-me")).await.unwrap();
+        assert_eq!(tokens[0].email, "me@test.com");
+
+        // 未命中任何路由时不过滤
+        let mut tokens = pool();
+        manager.apply_account_groups(&mut tokens, "claude-sonnet-4-6", None).await.unwrap();
+        assert_eq!(tokens.len(), 2);
+
+        // 分组内无账号时报错
+        let mut tokens = vec![create_test_token("other@test.com", None, 1.0, None, None)];
+        assert!(manager.apply_account_groups(&mut tokens, "gemini-3-flash", None).await.is_err());
+    }
+
+    #[test]
+    fn test_account_metrics_rolling_window() {
+        let mut state = AccountMetricsState::default();
+        state.record(true, 100, 1);
+        state.record(false, 300, 2);
+        state.record(false, 200, 3);
+        assert_eq!(state.consecutive_failures, 2);
+        assert_eq!(state.consecutive_successes, 0);
+        assert_eq!(state.avg_latency_ms(), 200);
+        assert_eq!(state.last_failure_at, Some(3));
+        assert!((state.success_rate() - 1.0 / 3.0).abs() < 1e-9);
+
+        for i in 0..METRICS_WINDOW {
+            state.record(true, 50, 10 + i as i64);
+        }
+        assert_eq!(state.outcomes.len(), METRICS_WINDOW);
+        assert_eq!(state.success_rate(), 1.0);
+        assert_eq!(state.consecutive_failures, 0);
+        assert_eq!(state.total_requests, 3 + METRICS_WINDOW as u64);
+        assert_eq!(state.total_failures, 2);
+    }
+
+    #[tokio::test]
+    async fn test_auth_failure_streak_resets_on_success() {
+        let manager = TokenManager::new(PathBuf::from("/tmp/test"));
+        manager
+            .update_auto_disable_config(crate::proxy::config::AutoDisableConfig {
+                enabled: false,
+                consecutive_auth_failures: 3,
+            })
+            .await;
+
+        assert_eq!(manager.record_auth_failure("acc1", 401, "UNAUTHENTICATED").await, 1);
+        assert_eq!(manager.record_auth_failure("acc1", 401, "UNAUTHENTICATED").await, 2);
+        // 临时验证阻止不计入
+        assert_eq!(manager.record_auth_failure("acc1", 403, "VALIDATION_REQUIRED").await, 0);
+        // 项目与模型范围的 403 不计入
+        assert_eq!(
+            manager
+                .record_auth_failure("acc1", 403, "PERMISSION_DENIED: Permission denied on project foo")
+                .await,
+            0
+        );
+        assert_eq!(
+            manager
+                .record_auth_failure("acc1", 403, "PERMISSION_DENIED: no access to model gemini-3-pro")
+                .await,
+            0
+        );
+        assert_eq!(
+            manager
+                .record_auth_failure(
+                    "acc1",
+                    403,
+                    r#"{"error":{"code":403,"status":"PERMISSION_DENIED","message":"Permission denied on model gemini-3-pro"}}"#
+                )
+                .await,
+            0
+        );
+        assert_eq!(manager.record_auth_failure("acc1", 429, "RESOURCE_EXHAUSTED").await, 0);
+        assert_eq!(manager.record_auth_failure("acc1", 401, "UNAUTHENTICATED").await, 3);
+        // 错误体其他字段中出现 "model" 不影响账号级 403 的计数
+        assert_eq!(
+            manager
+                .record_auth_failure(
+                    "acc1",
+                    403,
+                    r#"{"error":{"code":403,"status":"PERMISSION_DENIED","message":"The caller does not have permission"},"model":"gemini-3-flash"}"#
+                )
+                .await,
+            4
+        );
+
+        manager.record_request_outcome("acc1", true, 120);
+        assert_eq!(manager.record_auth_failure("acc1", 403, "PERMISSION_DENIED").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pin_account_resolves_email_and_bypasses_filters() {
+        let manager = TokenManager::new(PathBuf::from("/tmp/test"));
+        let mut pinned = create_test_token("pinned@test.com", Some("FREE"), 0.1, None, Some(0));
+        pinned.project_id = Some("project-pinned".to_string());
+        manager.tokens.insert(pinned.account_id.clone(), pinned.clone());
+
+        assert!(manager.pin_account("missing@test.com").await.is_err());
+        assert_eq!(manager.pin_account("pinned@test.com").await.unwrap(), pinned.account_id);
+
+        // 即使没有目标模型配额也直接使用锁定账号
+        let (_, project_id, email, _, _) = manager
+            .get_token_internal("gemini", true, None, "gemini-3-flash", None)
+            .await
+            .unwrap();
+        assert_eq!(email, "pinned@test.com");
+        assert_eq!(project_id, "project-pinned");
+
+        manager.unpin_account().await;
+        assert!(manager.get_pinned_account().await.is_none());
+        assert!(manager
+            .get_token_internal("gemini", true, None, "gemini-3-flash", None)
+            .await
+            .is_err());
+    }
 }
@@ -0,0 +1,130 @@
+// 工具调用 ID -> 函数名缓存
+// tool_id_to_name 每次请求都从历史消息重建，客户端截断历史后 tool_result 找不到对应的 tool_use，
+// 回退为原始 ID 会被 Gemini 当作非法函数名拒绝。这里按会话保存有界 LRU，跨请求补全映射。
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// 单个会话最多保留的映射数
+const MAX_TOOLS_PER_SESSION: usize = 512;
+/// 最多保留的会话数 (按最近使用淘汰)
+const MAX_SESSIONS: usize = 500;
+
+struct SessionToolNames {
+    names: HashMap<String, String>,
+    /// 插入顺序，最旧的在前
+    order: VecDeque<String>,
+    last_used: Instant,
+}
+
+impl SessionToolNames {
+    fn new() -> Self {
+        Self {
+            names: HashMap::new(),
+            order: VecDeque::new(),
+            last_used: Instant::now(),
+        }
+    }
+
+    fn insert(&mut self, tool_id: &str, name: &str) {
+        self.last_used = Instant::now();
+        if let Some(existing) = self.names.get_mut(tool_id) {
+            *existing = name.to_string();
+            return;
+        }
+        self.names.insert(tool_id.to_string(), name.to_string());
+        self.order.push_back(tool_id.to_string());
+        while self.order.len() > MAX_TOOLS_PER_SESSION {
+            if let Some(oldest) = self.order.pop_front() {
+                self.names.remove(&oldest);
+            }
+        }
+    }
+}
+
+pub struct ToolNameCache {
+    sessions: Mutex<HashMap<String, SessionToolNames>>,
+}
+
+impl ToolNameCache {
+    fn new() -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Global singleton instance
+    pub fn global() -> &'static ToolNameCache {
+        static INSTANCE: OnceLock<ToolNameCache> = OnceLock::new();
+        INSTANCE.get_or_init(ToolNameCache::new)
+    }
+
+    /// 记录会话内的 tool_use_id -> name
+    pub fn remember(&self, session_id: &str, tool_id: &str, name: &str) {
+        if tool_id.is_empty() || name.is_empty() || tool_id == name {
+            return;
+        }
+        let Ok(mut sessions) = self.sessions.lock() else {
+            return;
+        };
+        if !sessions.contains_key(session_id) && sessions.len() >= MAX_SESSIONS {
+            if let Some(oldest) = sessions
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            {
+                sessions.remove(&oldest);
+            }
+        }
+        sessions
+            .entry(session_id.to_string())
+            .or_insert_with(SessionToolNames::new)
+            .insert(tool_id, name);
+    }
+
+    /// 查找函数名: 优先当前会话；历史截断可能导致会话指纹漂移，因此再回退到其他会话
+    pub fn lookup(&self, session_id: &str, tool_id: &str) -> Option<String> {
+        let mut sessions = self.sessions.lock().ok()?;
+        if let Some(entry) = sessions.get_mut(session_id) {
+            if let Some(name) = entry.names.get(tool_id) {
+                entry.last_used = Instant::now();
+                return Some(name.clone());
+            }
+        }
+        let found = sessions
+            .values()
+            .find_map(|entry| entry.names.get(tool_id).cloned());
+        if found.is_some() {
+            tracing::debug!(
+                "[ToolNameCache] Resolved tool id {} from another session (sid: {})",
+                tool_id,
+                session_id
+            );
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remember_and_lookup_with_bounds() {
+        let cache = ToolNameCache::new();
+        cache.remember("sid-a", "toolu_1", "read_file");
+        assert_eq!(cache.lookup("sid-a", "toolu_1").as_deref(), Some("read_file"));
+        // 会话指纹漂移后仍能从其他会话解析
+        assert_eq!(cache.lookup("sid-b", "toolu_1").as_deref(), Some("read_file"));
+        assert!(cache.lookup("sid-a", "toolu_missing").is_none());
+
+        for i in 0..=MAX_TOOLS_PER_SESSION {
+            cache.remember("sid-a", &format!("toolu_x{}", i), "bash");
+        }
+        assert!(cache.lookup("sid-a", "toolu_1").is_none());
+        assert_eq!(
+            cache.lookup("sid-a", &format!("toolu_x{}", MAX_TOOLS_PER_SESSION)).as_deref(),
+            Some("bash")
+        );
+    }
+}
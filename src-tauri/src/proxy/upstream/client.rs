@@ -56,11 +56,20 @@ const V1_INTERNAL_BASE_URL_FALLBACKS: [&str; 3] = [
     V1_INTERNAL_BASE_URL_PROD,    // 优先级 3: Prod (仅作为兜底)
 ];
 
+/// 按账号 ID 查询账号专属上游代理 (绑定保存在账号数据上，通常由 TokenManager 提供)
+pub type AccountProxyLookup =
+    Arc<dyn Fn(&str) -> Option<crate::proxy::config::UpstreamProxyConfig> + Send + Sync>;
+
 pub struct UpstreamClient {
-    default_client: Client,
+    default_client: parking_lot::RwLock<Client>, // 可热重建 (上游代理变更时)
     proxy_pool: Option<Arc<crate::proxy::proxy_pool::ProxyPoolManager>>,
     client_cache: DashMap<String, Client>, // proxy_id -> Client
     user_agent_override: RwLock<Option<String>>,
+    client_version: RwLock<Option<String>>, // [NEW] 按配置解析的客户端版本 (None = 自动解析结果)
+    endpoints: parking_lot::RwLock<Vec<String>>, // [NEW] v1internal 端点尝试顺序 (可配置)
+    proxy_config: parking_lot::RwLock<Option<crate::proxy::config::UpstreamProxyConfig>>, // 当前默认客户端使用的上游代理
+    tuning: parking_lot::RwLock<crate::proxy::config::UpstreamClientConfig>, // [NEW] 连接池 / HTTP2 调优参数
+    account_proxy_lookup: parking_lot::RwLock<Option<AccountProxyLookup>>, // [NEW] 账号专属上游代理来源
 }
 
 impl UpstreamClient {
@@ -68,14 +77,43 @@ impl UpstreamClient {
         proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
         proxy_pool: Option<Arc<crate::proxy::proxy_pool::ProxyPoolManager>>,
     ) -> Self {
-        let default_client = match Self::build_client_internal(proxy_config.clone()) {
+        let tuning = crate::proxy::config::UpstreamClientConfig::default();
+        Self {
+            default_client: parking_lot::RwLock::new(Self::build_default_client(
+                proxy_config.clone(),
+                &tuning,
+            )),
+            proxy_pool,
+            client_cache: DashMap::new(),
+            user_agent_override: RwLock::new(None),
+            client_version: RwLock::new(None),
+            endpoints: parking_lot::RwLock::new(
+                V1_INTERNAL_BASE_URL_FALLBACKS.iter().map(|u| u.to_string()).collect(),
+            ),
+            proxy_config: parking_lot::RwLock::new(proxy_config),
+            tuning: parking_lot::RwLock::new(tuning),
+            account_proxy_lookup: parking_lot::RwLock::new(None),
+        }
+    }
+
+    /// 设置账号专属上游代理的查询来源
+    pub fn set_account_proxy_lookup(&self, lookup: AccountProxyLookup) {
+        *self.account_proxy_lookup.write() = Some(lookup);
+    }
+
+    /// Build the default client, degrading to no proxy / bare client on failure
+    fn build_default_client(
+        proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
+        tuning: &crate::proxy::config::UpstreamClientConfig,
+    ) -> Client {
+        match Self::build_client_internal(proxy_config, tuning) {
             Ok(client) => client,
             Err(err_with_proxy) => {
                 tracing::error!(
                     error = %err_with_proxy,
                     "Failed to create default HTTP client with configured upstream proxy; retrying without proxy"
                 );
-                match Self::build_client_internal(None) {
+                match Self::build_client_internal(None, tuning) {
                     Ok(client) => client,
                     Err(err_without_proxy) => {
                         tracing::error!(
@@ -86,30 +124,77 @@ impl UpstreamClient {
                     }
                 }
             }
-        };
+        }
+    }
 
-        Self {
-            default_client,
-            proxy_pool,
-            client_cache: DashMap::new(),
-            user_agent_override: RwLock::new(None),
+    /// Rebuild the default client after the upstream proxy setting changed (hot reload).
+    /// In-flight requests keep using the client they already cloned.
+    pub fn rebuild_default_client(&self, proxy_config: crate::proxy::config::UpstreamProxyConfig) {
+        let client = Self::build_default_client(Some(proxy_config.clone()), &self.tuning.read());
+        *self.default_client.write() = client;
+        *self.proxy_config.write() = Some(proxy_config);
+        tracing::info!("UpstreamClient default client rebuilt");
+    }
+
+    /// Apply connection pool / HTTP2 tuning.
+    /// Clients are shared and only rebuilt when the tuning actually changes.
+    pub fn set_tuning(&self, tuning: crate::proxy::config::UpstreamClientConfig) {
+        if *self.tuning.read() == tuning {
+            return;
         }
+        let client = Self::build_default_client(self.proxy_config.read().clone(), &tuning);
+        *self.tuning.write() = tuning;
+        *self.default_client.write() = client;
+        self.clear_client_cache();
+        tracing::info!("UpstreamClient tuning updated: {:?}", *self.tuning.read());
     }
 
-    /// Internal helper to build a client with optional upstream proxy config
-    fn build_client_internal(
-        proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
-    ) -> Result<Client, rquest::Error> {
+    /// Drop cached per-proxy clients so that edited pool entries take effect
+    pub fn clear_client_cache(&self) {
+        self.client_cache.clear();
+    }
+
+    /// Shared builder settings for every upstream client
+    fn base_builder(tuning: &crate::proxy::config::UpstreamClientConfig) -> rquest::ClientBuilder {
         let mut builder = Client::builder()
             .emulation(rquest_util::Emulation::Chrome123)
             // Connection settings (优化连接复用，减少建立开销)
-            .connect_timeout(Duration::from_secs(20))
-            .pool_max_idle_per_host(16) // 每主机最多 16 个空闲连接
-            .pool_idle_timeout(Duration::from_secs(90)) // 空闲连接保持 90 秒
-            .tcp_keepalive(Duration::from_secs(60)) // TCP 保活探测 60 秒
-            .timeout(Duration::from_secs(600));
+            .connect_timeout(Duration::from_secs(tuning.connect_timeout_secs))
+            .pool_max_idle_per_host(tuning.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(tuning.pool_idle_timeout_secs))
+            .tcp_keepalive(Duration::from_secs(tuning.tcp_keepalive_secs));
+
+        // 总超时仅作兜底；首字节与流空闲超时分别在 send() 与 body_stream() 中处理
+        if let Some(total) = super::timeouts::secs(tuning.total_timeout_secs) {
+            builder = builder.timeout(total);
+        }
+
+        if tuning.http2_prior_knowledge {
+            // 跳过 ALPN 协商直接使用 HTTP/2 (适用于支持 h2c 的自建中转)
+            builder = builder.http2_only();
+        }
+
+        // [NEW] 上游响应压缩: 客户端按 Content-Encoding 自动解压，流式 (bytes_stream) 与非流式路径均透明。
+        // 显式声明 Accept-Encoding，避免浏览器指纹默认头声明我们无法解码的编码 (zstd / deflate)
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            header::HeaderValue::from_static(Self::accept_encoding(tuning)),
+        );
+        builder = builder
+            .gzip(tuning.accept_compression)
+            .brotli(tuning.accept_compression)
+            .default_headers(headers);
 
-        builder = Self::apply_default_user_agent(builder);
+        Self::apply_default_user_agent(builder)
+    }
+
+    /// Internal helper to build a client with optional upstream proxy config
+    fn build_client_internal(
+        proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
+        tuning: &crate::proxy::config::UpstreamClientConfig,
+    ) -> Result<Client, rquest::Error> {
+        let mut builder = Self::base_builder(tuning);
 
         if let Some(config) = proxy_config {
             if config.enabled && !config.url.is_empty() {
@@ -129,17 +214,18 @@ impl UpstreamClient {
         &self,
         proxy_config: crate::proxy::proxy_pool::PoolProxyConfig,
     ) -> Result<Client, rquest::Error> {
-        // Reuse base settings similar to default client but with specific proxy
-        let builder = Client::builder()
-            .emulation(rquest_util::Emulation::Chrome123)
-            .connect_timeout(Duration::from_secs(20))
-            .pool_max_idle_per_host(16)
-            .pool_idle_timeout(Duration::from_secs(90))
-            .tcp_keepalive(Duration::from_secs(60))
-            .timeout(Duration::from_secs(600))
-            .proxy(proxy_config.proxy); // Apply the specific proxy
-
-        Self::apply_default_user_agent(builder).build()
+        // Reuse base settings of the default client but with specific proxy
+        Self::base_builder(&self.tuning.read())
+            .proxy(proxy_config.proxy) // Apply the specific proxy
+            .build()
+    }
+
+    fn accept_encoding(tuning: &crate::proxy::config::UpstreamClientConfig) -> &'static str {
+        if tuning.accept_compression {
+            "gzip, br"
+        } else {
+            "identity"
+        }
     }
 
     fn apply_default_user_agent(builder: rquest::ClientBuilder) -> rquest::ClientBuilder {
@@ -155,6 +241,12 @@ impl UpstreamClient {
         }
     }
 
+    /// 上游响应体字节流，附带可配置的空闲超时 (连续无数据块时中断并返回超时错误)
+    pub fn body_stream(&self, response: Response) -> super::timeouts::UpstreamByteStream {
+        let idle = super::timeouts::secs(self.tuning.read().stream_idle_timeout_secs);
+        super::timeouts::with_idle_timeout(response.bytes_stream(), idle)
+    }
+
     /// Set dynamic User-Agent override
     pub async fn set_user_agent_override(&self, ua: Option<String>) {
         let mut lock = self.user_agent_override.write().await;
@@ -162,17 +254,57 @@ impl UpstreamClient {
         tracing::debug!("UpstreamClient User-Agent override updated: {:?}", lock);
     }
 
+    /// Set the v1internal endpoint order (custom primary + fallbacks)
+    pub fn set_endpoints(&self, config: &crate::proxy::config::UpstreamEndpointsConfig) {
+        let endpoints = config.resolve(&V1_INTERNAL_BASE_URL_FALLBACKS);
+        tracing::info!("UpstreamClient endpoints updated: {:?}", endpoints);
+        *self.endpoints.write() = endpoints;
+    }
+
+    /// Set the client version reported upstream (x-client-version and default User-Agent)
+    pub async fn set_client_version(&self, version: Option<String>) {
+        let mut lock = self.client_version.write().await;
+        *lock = version;
+        tracing::debug!("UpstreamClient client version updated: {:?}", lock);
+    }
+
+    /// Get current client version
+    pub async fn get_client_version(&self) -> String {
+        self.client_version
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(|| crate::constants::CURRENT_VERSION.clone())
+    }
+
     /// Get current User-Agent
+    /// Priority: explicit override > UA built from configured client version > resolved default
     pub async fn get_user_agent(&self) -> String {
-        let ua_override = self.user_agent_override.read().await;
-        ua_override
-            .as_ref()
-            .cloned()
-            .unwrap_or_else(|| crate::constants::USER_AGENT.clone())
+        if let Some(ua) = self.user_agent_override.read().await.as_ref() {
+            return ua.clone();
+        }
+        match self.client_version.read().await.as_deref() {
+            Some(version) => crate::constants::build_user_agent(version),
+            None => crate::constants::USER_AGENT.clone(),
+        }
     }
 
     /// Get client for a specific account (or default if no proxy bound)
-    pub async fn get_client(&self, account_id: Option<&str>) -> Client {
+    /// Priority: account-specific upstream proxy > proxy pool > default client
+    /// An account with its own upstream proxy never falls back to another exit: if that
+    /// client cannot be built, the request fails instead of leaking the account's traffic.
+    pub async fn get_client(&self, account_id: Option<&str>) -> Result<Client, String> {
+        if let Some(acc_id) = account_id {
+            let account_proxy = self
+                .account_proxy_lookup
+                .read()
+                .as_ref()
+                .and_then(|lookup| lookup(acc_id));
+            if let Some(proxy_config) = account_proxy {
+                return self.get_account_proxy_client(acc_id, &proxy_config);
+            }
+        }
+
         if let Some(pool) = &self.proxy_pool {
             if let Some(acc_id) = account_id {
                 // Try to get per-account proxy
@@ -180,7 +312,7 @@ impl UpstreamClient {
                     Ok(Some(proxy_cfg)) => {
                         // Check cache
                         if let Some(client) = self.client_cache.get(&proxy_cfg.entry_id) {
-                            return client.clone();
+                            return Ok(client.clone());
                         }
                         // Build new client and cache it
                         match self.build_client_with_proxy(proxy_cfg.clone()) {
@@ -192,7 +324,7 @@ impl UpstreamClient {
                                     proxy_cfg.entry_id,
                                     acc_id
                                 );
-                                return client;
+                                return Ok(client);
                             }
                             Err(e) => {
                                 tracing::error!("Failed to build client for proxy {}: {}, falling back to default", proxy_cfg.entry_id, e);
@@ -213,7 +345,41 @@ impl UpstreamClient {
             }
         }
         // Fallback to default client
-        self.default_client.clone()
+        Ok(self.default_client.read().clone())
+    }
+
+    /// Client for an account that carries its own upstream proxy (cached by account + url)
+    fn get_account_proxy_client(
+        &self,
+        account_id: &str,
+        proxy_config: &crate::proxy::config::UpstreamProxyConfig,
+    ) -> Result<Client, String> {
+        let cache_key = format!("account:{}:{}", account_id, proxy_config.url);
+        if let Some(client) = self.client_cache.get(&cache_key) {
+            return Ok(client.clone());
+        }
+
+        let url = crate::proxy::config::normalize_proxy_url(&proxy_config.url);
+        let client = rquest::Proxy::all(&url)
+            .map_err(|e| e.to_string())
+            .and_then(|proxy| {
+                self.build_client_with_proxy(crate::proxy::proxy_pool::PoolProxyConfig {
+                    proxy,
+                    entry_id: cache_key.clone(),
+                })
+                .map_err(|e| e.to_string())
+            })
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to build client with account upstream proxy for {}: {}",
+                    account_id,
+                    e
+                );
+                format!("Account upstream proxy unavailable for {}: {}", account_id, e)
+            })?;
+        self.client_cache.insert(cache_key, client.clone());
+        tracing::info!("Using account upstream proxy {} for account: {}", url, account_id);
+        Ok(client)
     }
 
     /// Build v1internal URL
@@ -267,8 +433,12 @@ impl UpstreamClient {
         extra_headers: std::collections::HashMap<String, String>,
         account_id: Option<&str>, // [NEW] Account ID
     ) -> Result<UpstreamCallResult, String> {
+        // [NEW] 发往上游前统一脱敏 (三种协议共用)
+        let mut body = body;
+        crate::proxy::redaction::redact_request_body(&mut body);
+
         // [NEW] Get client based on account (cached in proxy pool manager)
-        let client = self.get_client(account_id).await;
+        let client = self.get_client(account_id).await?;
 
         // 构建 Headers (所有端点复用)
         let mut headers = header::HeaderMap::new();
@@ -296,7 +466,7 @@ impl UpstreamClient {
             "x-client-name",
             header::HeaderValue::from_static("antigravity"),
         );
-        if let Ok(ver) = header::HeaderValue::from_str(&crate::constants::CURRENT_VERSION) {
+        if let Ok(ver) = header::HeaderValue::from_str(&self.get_client_version().await) {
             headers.insert("x-client-version", ver);
         }
 
@@ -333,16 +503,25 @@ impl UpstreamClient {
         let mut fallback_attempts: Vec<FallbackAttemptLog> = Vec::new();
 
         // 遍历所有端点，失败时自动切换
-        for (idx, base_url) in V1_INTERNAL_BASE_URL_FALLBACKS.iter().enumerate() {
+        let endpoints = self.endpoints.read().clone();
+        let first_byte_timeout = super::timeouts::secs(self.tuning.read().first_byte_timeout_secs);
+        for (idx, base_url) in endpoints.iter().enumerate() {
             let url = Self::build_url(base_url, method, query_string);
-            let has_next = idx + 1 < V1_INTERNAL_BASE_URL_FALLBACKS.len();
+            let has_next = idx + 1 < endpoints.len();
 
-            let response = client
+            let send = client
                 .post(&url)
                 .headers(headers.clone())
                 .json(&body)
-                .send()
-                .await;
+                .send();
+            // [NEW] 首字节超时: 响应头迟迟不到时按网络错误处理并切换端点
+            let response = match first_byte_timeout {
+                Some(limit) => match tokio::time::timeout(limit, send).await {
+                    Ok(result) => result.map_err(|e| e.to_string()),
+                    Err(_) => Err(format!("first byte timeout after {}s", limit.as_secs())),
+                },
+                None => send.await.map_err(|e| e.to_string()),
+            };
 
             match response {
                 Ok(resp) => {
@@ -353,7 +532,7 @@ impl UpstreamClient {
                                 "✓ Upstream fallback succeeded | Endpoint: {} | Status: {} | Next endpoints available: {}",
                                 base_url,
                                 status,
-                                V1_INTERNAL_BASE_URL_FALLBACKS.len() - idx - 1
+                                endpoints.len() - idx - 1
                             );
                         } else {
                             tracing::debug!(
@@ -1,8 +1,16 @@
 // 429 重试策略
-// Duration 解析
+// Duration 解析 / 退避抖动 / 全局重试预算
 
 use regex::Regex;
 use once_cell::sync::Lazy;
+use rand::Rng;
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
+
+use crate::proxy::config::RetryPolicyConfig;
+
+/// 退避抖动比例 (±20%)
+const JITTER_FACTOR: f64 = 0.2;
 
 static DURATION_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"([\d.]+)\s*(ms|s|m|h)").unwrap()
@@ -66,6 +74,79 @@ pub fn parse_retry_delay(error_text: &str) -> Option<u64> {
     None
 }
 
+static RETRY_POLICY: Lazy<RwLock<RetryPolicyConfig>> =
+    Lazy::new(|| RwLock::new(RetryPolicyConfig::default()));
+
+/// 更新重试策略 (启动与配置热更新时调用)
+pub fn update_retry_policy_config(config: RetryPolicyConfig) {
+    if let Ok(mut current) = RETRY_POLICY.write() {
+        *current = config;
+    }
+}
+
+/// 当前重试策略
+pub fn retry_policy() -> RetryPolicyConfig {
+    RETRY_POLICY
+        .read()
+        .map(|c| c.clone())
+        .unwrap_or_default()
+}
+
+/// 固定窗口 (1 秒) 的重试预算
+pub struct RetryBudget {
+    window: Mutex<(Instant, u32)>,
+}
+
+impl RetryBudget {
+    pub fn new() -> Self {
+        Self {
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// 尝试占用一次重试额度，当前窗口已耗尽时返回 false (per_second 为 0 时不限制)
+    pub fn try_acquire(&self, per_second: u32) -> bool {
+        if per_second == 0 {
+            return true;
+        }
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if window.0.elapsed().as_secs() >= 1 {
+            *window = (Instant::now(), 0);
+        }
+        if window.1 >= per_second {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static GLOBAL_RETRY_BUDGET: Lazy<RetryBudget> = Lazy::new(RetryBudget::new);
+
+/// 按配置的每秒额度占用全局重试预算，防止 429 风暴时重试被放大
+pub fn acquire_retry_budget() -> bool {
+    GLOBAL_RETRY_BUDGET.try_acquire(retry_policy().budget_per_second)
+}
+
+/// 为退避延迟加入 ±20% 抖动 (仅在 retry_policy.jitter 开启时生效)
+pub fn apply_jitter(delay_ms: u64) -> u64 {
+    if !retry_policy().jitter {
+        return delay_ms;
+    }
+    jittered(delay_ms)
+}
+
+fn jittered(delay_ms: u64) -> u64 {
+    let factor = rand::thread_rng().gen_range((1.0 - JITTER_FACTOR)..=(1.0 + JITTER_FACTOR));
+    (delay_ms as f64 * factor).round() as u64
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +172,28 @@ mod tests {
 
         assert_eq!(parse_retry_delay(error_json), Some(1204));
     }
+
+    #[test]
+    fn test_retry_budget_exhausts_within_window() {
+        let budget = RetryBudget::new();
+        assert!(budget.try_acquire(2));
+        assert!(budget.try_acquire(2));
+        assert!(!budget.try_acquire(2));
+        // 0 = 不限制
+        assert!(budget.try_acquire(0));
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        for _ in 0..100 {
+            let delay = jittered(10_000);
+            assert!((8_000..=12_000).contains(&delay));
+        }
+    }
+
+    #[test]
+    fn test_apply_jitter_off_by_default() {
+        assert!(!RetryPolicyConfig::default().jitter);
+        assert_eq!(apply_jitter(10_000), 10_000);
+    }
 }
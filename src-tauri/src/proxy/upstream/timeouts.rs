@@ -0,0 +1,71 @@
+// 上游分阶段超时
+// 连接超时由客户端负责；这里处理首字节超时之后的阶段：流式响应在指定时间内没有任何新数据块时主动中断，
+// 并返回可重试的超时错误 (错误文本包含 "timeout"，会被 error_classifier 归类为 timeout_error)。
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::time::Duration;
+
+pub type UpstreamByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>>;
+
+/// 为上游字节流加上空闲超时；`idle` 为 None 时只做错误类型转换
+pub fn with_idle_timeout<S, E>(stream: S, idle: Option<Duration>) -> UpstreamByteStream
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    let stream = stream.map(|item| item.map_err(|e| e.to_string()));
+    let Some(idle) = idle else {
+        return Box::pin(stream);
+    };
+
+    Box::pin(futures::stream::unfold(
+        (Box::pin(stream), false),
+        move |(mut stream, timed_out)| async move {
+            if timed_out {
+                return None;
+            }
+            match tokio::time::timeout(idle, stream.next()).await {
+                Ok(Some(item)) => Some((item, (stream, false))),
+                Ok(None) => None,
+                Err(_) => {
+                    let message = format!("upstream stream idle timeout: no data for {:?}", idle);
+                    tracing::warn!("[Upstream] {}, aborting", message);
+                    Some((Err(message),
+                        (stream, true),
+                    ))
+                }
+            }
+        },
+    ))
+}
+
+/// 秒数配置转换为超时时长 (0 表示不限制)
+pub fn secs(value: u64) -> Option<Duration> {
+    (value > 0).then(|| Duration::from_secs(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_idle_stream_aborts_with_timeout_error() {
+        let chunks = futures::stream::iter(vec![Ok::<_, String>(Bytes::from_static(b"data: {}\n\n"))])
+            .chain(futures::stream::pending());
+        let mut stream = with_idle_timeout(chunks, Some(Duration::from_millis(50)));
+
+        assert_eq!(stream.next().await, Some(Ok(Bytes::from_static(b"data: {}\n\n"))));
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.contains("timeout"));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finished_stream_passes_through() {
+        let chunks = futures::stream::iter(vec![Ok::<_, String>(Bytes::from_static(b"a")), Ok(Bytes::from_static(b"b"))]);
+        let items: Vec<_> = with_idle_timeout(chunks, secs(5)).collect().await;
+        assert_eq!(items, vec![Ok(Bytes::from_static(b"a")), Ok(Bytes::from_static(b"b"))]);
+        assert!(secs(0).is_none());
+    }
+}
@@ -111,6 +111,252 @@ pub fn decrypt_string(encrypted: &str) -> Result<String, String> {
     }
 }
 
+// ===== 账号 Token 静态加密 =====
+// 随机 nonce + AES-256-GCM；密钥优先保存在系统钥匙串 (keyring)，不可用时 (如 Docker/无 DBus)
+// 使用 AGM_TOKEN_PASSPHRASE 主密码经 PBKDF2 派生。两者都没有时拒绝加密：设备派生密钥在容器中
+// 可能是常量，只读兼容旧的 ag_tok_d: 密文，不再写入。
+// 密文前缀记录密钥来源，避免钥匙串时有时无导致无法解密。
+
+const TOKEN_PREFIX_KEYCHAIN: &str = "ag_tok_k:";
+const TOKEN_PREFIX_PASSPHRASE: &str = "ag_tok_p:";
+const TOKEN_PREFIX_DEVICE: &str = "ag_tok_d:";
+const KEYRING_SERVICE: &str = "Antigravity Tools";
+const KEYRING_USER: &str = "token-encryption-key";
+/// 主密码环境变量 (兼容 ABV_ 前缀)
+const PASSPHRASE_ENV_VARS: [&str; 2] = ["AGM_TOKEN_PASSPHRASE", "ABV_TOKEN_PASSPHRASE"];
+const PASSPHRASE_SALT_LEN: usize = 16;
+#[cfg(not(test))]
+const PASSPHRASE_KDF_ITERATIONS: u32 = 600_000;
+#[cfg(test)]
+const PASSPHRASE_KDF_ITERATIONS: u32 = 1_000;
+
+static TOKEN_ENCRYPTION_ENABLED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+static KEYCHAIN_KEY: std::sync::OnceLock<Option<[u8; 32]>> = std::sync::OnceLock::new();
+/// 主密码派生密钥缓存 (salt -> key)，PBKDF2 只对每个 salt 计算一次
+static PASSPHRASE_KEYS: std::sync::OnceLock<
+    std::sync::Mutex<std::collections::HashMap<[u8; PASSPHRASE_SALT_LEN], [u8; 32]>>,
+> = std::sync::OnceLock::new();
+/// 本进程写入时使用的 salt
+static PASSPHRASE_WRITE_SALT: std::sync::OnceLock<[u8; PASSPHRASE_SALT_LEN]> = std::sync::OnceLock::new();
+
+/// 设置是否在写入账号文件时加密 Token (由配置 encrypt_tokens_at_rest 驱动)
+pub fn set_token_encryption_enabled(enabled: bool) {
+    TOKEN_ENCRYPTION_ENABLED.store(enabled, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn is_token_encryption_enabled() -> bool {
+    TOKEN_ENCRYPTION_ENABLED.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// 从系统钥匙串读取 (不存在时生成) Token 加密密钥
+fn load_keychain_key() -> Option<[u8; 32]> {
+    *KEYCHAIN_KEY.get_or_init(|| {
+        let entry = match keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("[Crypto] Keychain unavailable, using device key: {}", e);
+                return None;
+            }
+        };
+
+        match entry.get_password() {
+            Ok(encoded) => {
+                let bytes = general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+                bytes.try_into().ok()
+            }
+            Err(keyring::Error::NoEntry) => {
+                let mut key = [0u8; 32];
+                rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut key);
+                match entry.set_password(&general_purpose::STANDARD.encode(key)) {
+                    Ok(()) => Some(key),
+                    Err(e) => {
+                        tracing::warn!("[Crypto] Failed to store key in keychain, using device key: {}", e);
+                        None
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("[Crypto] Keychain read failed, using device key: {}", e);
+                None
+            }
+        }
+    })
+}
+
+fn token_passphrase() -> Option<String> {
+    PASSPHRASE_ENV_VARS
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+fn derive_passphrase_key(passphrase: &str, salt: &[u8; PASSPHRASE_SALT_LEN]) -> [u8; 32] {
+    let cache = PASSPHRASE_KEYS.get_or_init(Default::default);
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    *cache.entry(*salt).or_insert_with(|| {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, PASSPHRASE_KDF_ITERATIONS, &mut key);
+        key
+    })
+}
+
+/// 是否有可用于加密 Token 的密钥来源 (系统钥匙串或主密码)
+pub fn has_secure_token_key() -> bool {
+    load_keychain_key().is_some() || token_passphrase().is_some()
+}
+
+pub fn is_encrypted_token(value: &str) -> bool {
+    value.starts_with(TOKEN_PREFIX_KEYCHAIN)
+        || value.starts_with(TOKEN_PREFIX_PASSPHRASE)
+        || value.starts_with(TOKEN_PREFIX_DEVICE)
+}
+
+/// 旧版设备派生密钥加密的 Token，开启加密时需用安全密钥重新加密
+pub fn is_device_key_token(value: &str) -> bool {
+    value.starts_with(TOKEN_PREFIX_DEVICE)
+}
+
+fn encrypt_token_with(key: &[u8; 32], prefix: &str, plaintext: &str) -> Result<String, String> {
+    let cipher = Aes256Gcm::new(key.into());
+    let mut nonce = [0u8; 12];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|e| format!("Token encryption failed: {}", e))?;
+
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", prefix, general_purpose::STANDARD.encode(blob)))
+}
+
+fn decrypt_token_with(key: &[u8; 32], encoded: &str) -> Result<String, String> {
+    let blob = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Base64 decode failed: {}", e))?;
+    if blob.len() < 12 {
+        return Err("Token ciphertext too short".to_string());
+    }
+    let (nonce, ciphertext) = blob.split_at(12);
+    let plaintext = Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|e| format!("Token decryption failed: {}", e))?;
+    String::from_utf8(plaintext).map_err(|e| format!("UTF-8 conversion failed: {}", e))
+}
+
+/// 主密码加密：密文为 salt + nonce + ciphertext
+fn encrypt_token_with_passphrase(passphrase: &str, plaintext: &str) -> Result<String, String> {
+    let salt = PASSPHRASE_WRITE_SALT.get_or_init(|| {
+        let mut salt = [0u8; PASSPHRASE_SALT_LEN];
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut salt);
+        salt
+    });
+    let key = derive_passphrase_key(passphrase, salt);
+    let sealed = encrypt_token_with(&key, "", plaintext)?;
+    let mut blob = salt.to_vec();
+    blob.extend_from_slice(
+        &general_purpose::STANDARD
+            .decode(sealed)
+            .map_err(|e| format!("Base64 decode failed: {}", e))?,
+    );
+    Ok(format!("{}{}", TOKEN_PREFIX_PASSPHRASE, general_purpose::STANDARD.encode(blob)))
+}
+
+fn decrypt_token_with_passphrase(passphrase: &str, encoded: &str) -> Result<String, String> {
+    let blob = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Base64 decode failed: {}", e))?;
+    if blob.len() < PASSPHRASE_SALT_LEN {
+        return Err("Token ciphertext too short".to_string());
+    }
+    let (salt, rest) = blob.split_at(PASSPHRASE_SALT_LEN);
+    let salt: [u8; PASSPHRASE_SALT_LEN] = salt.try_into().map_err(|_| "Invalid salt".to_string())?;
+    let key = derive_passphrase_key(passphrase, &salt);
+    decrypt_token_with(&key, &general_purpose::STANDARD.encode(rest))
+}
+
+/// 加密 Token (已加密或为空时原样返回)
+/// 钥匙串与主密码都不可用时返回错误，不会退回到设备派生密钥
+pub fn encrypt_token(plaintext: &str) -> Result<String, String> {
+    if plaintext.is_empty() || (is_encrypted_token(plaintext) && !is_device_key_token(plaintext)) {
+        return Ok(plaintext.to_string());
+    }
+    if is_device_key_token(plaintext) {
+        return encrypt_token(&decrypt_token(plaintext)?);
+    }
+    if let Some(key) = load_keychain_key() {
+        return encrypt_token_with(&key, TOKEN_PREFIX_KEYCHAIN, plaintext);
+    }
+    match token_passphrase() {
+        Some(passphrase) => encrypt_token_with_passphrase(&passphrase, plaintext),
+        None => Err(format!(
+            "No secure key for token encryption: OS keychain is unavailable and {} is not set",
+            PASSPHRASE_ENV_VARS[0]
+        )),
+    }
+}
+
+/// 解密 Token (明文原样返回，兼容未加密的旧数据)
+pub fn decrypt_token(value: &str) -> Result<String, String> {
+    if let Some(encoded) = value.strip_prefix(TOKEN_PREFIX_KEYCHAIN) {
+        let key = load_keychain_key()
+            .ok_or_else(|| "Token was encrypted with the OS keychain, which is unavailable".to_string())?;
+        decrypt_token_with(&key, encoded)
+    } else if let Some(encoded) = value.strip_prefix(TOKEN_PREFIX_PASSPHRASE) {
+        let passphrase = token_passphrase().ok_or_else(|| {
+            format!("Token was encrypted with a passphrase, but {} is not set", PASSPHRASE_ENV_VARS[0])
+        })?;
+        decrypt_token_with_passphrase(&passphrase, encoded)
+    } else if let Some(encoded) = value.strip_prefix(TOKEN_PREFIX_DEVICE) {
+        // 仅兼容读取旧数据
+        decrypt_token_with(&get_encryption_key(), encoded)
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// 写盘前按配置处理 Token：开启加密时加密，关闭时保持明文
+/// 运行中失去安全密钥 (如钥匙串被锁) 时不阻断账号写入，以明文写入并记录错误
+pub fn seal_token(plaintext: &str) -> Result<String, String> {
+    if !is_token_encryption_enabled() {
+        return Ok(plaintext.to_string());
+    }
+    match encrypt_token(plaintext) {
+        Ok(sealed) => Ok(sealed),
+        Err(e) if !has_secure_token_key() => {
+            tracing::error!("[Crypto] Token encryption is enabled but {}; token written UNENCRYPTED", e);
+            decrypt_token(plaintext)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 对账号 JSON 中的 token.access_token / token.refresh_token 执行 f
+fn map_account_token_fields(
+    account: &mut serde_json::Value,
+    f: impl Fn(&str) -> Result<String, String>,
+) -> Result<(), String> {
+    if let Some(token) = account.get_mut("token").and_then(|t| t.as_object_mut()) {
+        for field in ["access_token", "refresh_token"] {
+            if let Some(value) = token.get(field).and_then(|v| v.as_str()) {
+                let mapped = f(value)?;
+                token.insert(field.to_string(), serde_json::Value::String(mapped));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 写入账号文件前加密 Token 字段 (未开启时不做处理)
+pub fn seal_account_tokens(account: &mut serde_json::Value) -> Result<(), String> {
+    map_account_token_fields(account, seal_token)
+}
+
+/// 读取账号文件后解密 Token 字段
+pub fn unseal_account_tokens(account: &mut serde_json::Value) -> Result<(), String> {
+    map_account_token_fields(account, decrypt_token)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,4 +389,47 @@ mod tests {
         let decrypted = decrypt_string(&legacy_encrypted).unwrap();
         assert_eq!(password, decrypted);
     }
+
+    #[test]
+    fn test_token_encryption_roundtrip() {
+        let key = [7u8; 32];
+        let sealed = encrypt_token_with(&key, TOKEN_PREFIX_DEVICE, "1//refresh-token").unwrap();
+        assert!(is_encrypted_token(&sealed));
+        // 随机 nonce：同一明文两次加密结果不同
+        let sealed_again = encrypt_token_with(&key, TOKEN_PREFIX_DEVICE, "1//refresh-token").unwrap();
+        assert_ne!(sealed, sealed_again);
+
+        let encoded = sealed.strip_prefix(TOKEN_PREFIX_DEVICE).unwrap();
+        assert_eq!(decrypt_token_with(&key, encoded).unwrap(), "1//refresh-token");
+        assert!(decrypt_token_with(&[8u8; 32], encoded).is_err());
+
+        // 明文 Token 原样返回
+        assert_eq!(decrypt_token("ya29.plain").unwrap(), "ya29.plain");
+    }
+
+    #[test]
+    fn test_passphrase_token_roundtrip() {
+        let sealed = encrypt_token_with_passphrase("correct horse battery", "1//refresh-token").unwrap();
+        let encoded = sealed.strip_prefix(TOKEN_PREFIX_PASSPHRASE).unwrap();
+        assert!(is_encrypted_token(&sealed));
+        assert!(!is_device_key_token(&sealed));
+        assert_eq!(
+            decrypt_token_with_passphrase("correct horse battery", encoded).unwrap(),
+            "1//refresh-token"
+        );
+        assert!(decrypt_token_with_passphrase("wrong passphrase", encoded).is_err());
+    }
+
+    #[test]
+    fn test_unseal_account_tokens() {
+        let device_sealed = encrypt_token_with(&get_encryption_key(), TOKEN_PREFIX_DEVICE, "rt").unwrap();
+        let mut account = serde_json::json!({
+            "id": "a",
+            "token": { "access_token": "ya29.plain", "refresh_token": device_sealed, "expires_in": 3600 }
+        });
+        unseal_account_tokens(&mut account).unwrap();
+        assert_eq!(account["token"]["access_token"], "ya29.plain");
+        assert_eq!(account["token"]["refresh_token"], "rt");
+        assert_eq!(account["token"]["expires_in"], 3600);
+    }
 }
@@ -16,6 +16,12 @@ pub static SHARED_STANDARD_CLIENT: Lazy<Client> = Lazy::new(|| create_standard_c
 /// Global shared standard HTTP client (Long timeout: 60s, NO JA3 Emulation)
 pub static SHARED_STANDARD_CLIENT_LONG: Lazy<Client> = Lazy::new(|| create_standard_client(60));
 
+/// Standard client that never follows redirects (15s timeout)
+/// Used to download user-supplied URLs so each redirect hop can be validated
+pub static SHARED_NO_REDIRECT_CLIENT: Lazy<Client> = Lazy::new(|| {
+    build_standard_client(15, rquest::redirect::Policy::none())
+});
+
 /// Base client creation logic with JA3 Emulation
 fn create_base_client(timeout_secs: u64) -> Client {
     let mut builder = Client::builder()
@@ -56,9 +62,14 @@ pub fn get_long_client() -> Client {
 
 /// Base client creation logic strictly WITHOUT JA3 Emulation (Pure Native)
 fn create_standard_client(timeout_secs: u64) -> Client {
+    build_standard_client(timeout_secs, rquest::redirect::Policy::default())
+}
+
+fn build_standard_client(timeout_secs: u64, redirect: rquest::redirect::Policy) -> Client {
     let mut builder = Client::builder()
         // No .emulation(Emulation::Chrome123) here!
-        .timeout(std::time::Duration::from_secs(timeout_secs));
+        .timeout(std::time::Duration::from_secs(timeout_secs))
+        .redirect(redirect);
 
     if let Ok(config) = load_app_config() {
         let proxy_config = config.proxy.upstream_proxy;
@@ -87,6 +98,11 @@ pub fn get_standard_client() -> Client {
     SHARED_STANDARD_CLIENT.clone()
 }
 
+/// Get standard HTTP client that does not follow redirects (15s timeout)
+pub fn get_no_redirect_client() -> Client {
+    SHARED_NO_REDIRECT_CLIENT.clone()
+}
+
 /// Get long timeout standard HTTP client without JA3 Emulation (60s timeout)
 pub fn get_long_standard_client() -> Client {
     SHARED_STANDARD_CLIENT_LONG.clone()
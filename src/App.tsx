@@ -20,6 +20,7 @@ import { listen } from '@tauri-apps/api/event';
 import { isTauri } from './utils/env';
 import { request as invoke } from './utils/request';
 import { AdminAuthGuard } from './components/common/AdminAuthGuard';
+import { showToast } from './components/common/ToastContainer';
 
 const router = createBrowserRouter([
   {
@@ -65,7 +66,7 @@ const router = createBrowserRouter([
 function App() {
   const { config, loadConfig } = useConfigStore();
   const { fetchCurrentAccount, fetchAccounts } = useAccountStore();
-  const { i18n } = useTranslation();
+  const { t, i18n } = useTranslation();
 
   useEffect(() => {
     loadConfig();
@@ -107,6 +108,20 @@ function App() {
       })
     );
 
+    // 监听托盘跳转请求 (例如额度汇总 -> 账号详情)
+    unlistenPromises.push(
+      listen<string>('tray://navigate', (event) => {
+        router.navigate(event.payload);
+      })
+    );
+
+    // 监听托盘切换模型映射预设，重新加载配置
+    unlistenPromises.push(
+      listen('tray://mapping-changed', () => {
+        loadConfig();
+      })
+    );
+
     // 监听后端全量刷新事件 (Command / Scheduler)
     unlistenPromises.push(
       listen('accounts://refreshed', () => {
@@ -116,13 +131,28 @@ function App() {
       })
     );
 
+    // 监听反代自动停用账号事件 (连续 401/403)
+    unlistenPromises.push(
+      listen<{ account_id: string; email: string; reason: string }>('accounts://auto-disabled', (event) => {
+        showToast(t('accounts.auto_disabled_toast', { email: event.payload.email }), 'warning', 6000);
+        fetchAccounts();
+      })
+    );
+
+    // 监听后台调度器的单账号刷新事件，实时更新列表
+    unlistenPromises.push(
+      listen('quota://account-refreshed', () => {
+        fetchAccounts();
+      })
+    );
+
     // Cleanup
     return () => {
       Promise.all(unlistenPromises).then(unlisteners => {
         unlisteners.forEach(unlisten => unlisten());
       });
     };
-  }, [fetchCurrentAccount, fetchAccounts]);
+  }, [fetchCurrentAccount, fetchAccounts, loadConfig, t]);
 
   // Update notification state
   const [showUpdateNotification, setShowUpdateNotification] = useState(false);
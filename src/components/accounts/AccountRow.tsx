@@ -1,8 +1,12 @@
-import { ArrowRightLeft, RefreshCw, Trash2, Download, Info, Lock, Ban, Diamond, Gem, Circle, Clock, ToggleLeft, ToggleRight, Fingerprint } from 'lucide-react';
+import { useState } from 'react';
+import { ArrowRightLeft, RefreshCw, Trash2, Download, Info, Lock, Ban, Diamond, Gem, Circle, Clock, ToggleLeft, ToggleRight, Fingerprint, KeyRound } from 'lucide-react';
 import { Account } from '../../types/account';
 import { getQuotaColor, formatTimeRemaining, getTimeRemainingColor } from '../../utils/format';
 import { cn } from '../../utils/cn';
 import { useTranslation } from 'react-i18next';
+import { reauthorizeAccount } from '../../services/accountService';
+import { useAccountStore } from '../../stores/useAccountStore';
+import { showToast } from '../common/ToastContainer';
 
 interface AccountRowProps {
     account: Account;
@@ -46,6 +50,23 @@ function AccountRow({ account, selected, onSelect, isCurrent, isRefreshing, isSw
         .filter(m => claudeGroupNames.includes(m.name.toLowerCase()))
         .sort((a, b) => (a.percentage || 0) - (b.percentage || 0))[0];
     const isDisabled = Boolean(account.disabled);
+    const [isReauthorizing, setIsReauthorizing] = useState(false);
+
+    // [NEW] refresh_token 失效 (invalid_grant) 时一键重新授权
+    const handleReauthorize = async (e: React.MouseEvent) => {
+        e.stopPropagation();
+        if (isReauthorizing) return;
+        setIsReauthorizing(true);
+        try {
+            await reauthorizeAccount(account.id);
+            showToast(t('accounts.reauth_success'), 'success');
+            await useAccountStore.getState().fetchAccounts();
+        } catch (error) {
+            showToast(`${t('accounts.reauth_failed')}: ${error}`, 'error');
+        } finally {
+            setIsReauthorizing(false);
+        }
+    };
 
     // 颜色映射，避免动态类名被 Tailwind purge
     const getColorClass = (percentage: number) => {
@@ -111,6 +132,18 @@ function AccountRow({ account, selected, onSelect, isCurrent, isRefreshing, isSw
                             </span>
                         )}
 
+                        {account.needs_reauth && (
+                            <button
+                                className="px-2 py-0.5 rounded-md bg-amber-100 dark:bg-amber-900/50 text-amber-700 dark:text-amber-300 text-[10px] font-bold flex items-center gap-1 shadow-sm border border-amber-200/50 hover:bg-amber-200 dark:hover:bg-amber-800/50 disabled:opacity-60"
+                                title={t('accounts.reauth_tooltip')}
+                                onClick={handleReauthorize}
+                                disabled={isReauthorizing}
+                            >
+                                <KeyRound className={cn("w-2.5 h-2.5", isReauthorizing && "animate-pulse")} />
+                                <span>{t('accounts.reauth')}</span>
+                            </button>
+                        )}
+
                         {account.proxy_disabled && (
                             <span
                                 className="px-2 py-0.5 rounded-md bg-orange-100 dark:bg-orange-900/50 text-orange-700 dark:text-orange-300 text-[10px] font-bold flex items-center gap-1 shadow-sm border border-orange-200/50"
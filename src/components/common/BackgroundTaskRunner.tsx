@@ -11,11 +11,11 @@ function BackgroundTaskRunner() {
     const prevAutoSyncRef = useRef(false);
 
     // Auto Refresh Quota Effect
+    // 定时刷新由后端调度器负责 (按账号错峰，事件 quota://account-refreshed)，这里只处理开启时立即刷新一次
     useEffect(() => {
         if (!config) return;
 
-        let intervalId: ReturnType<typeof setTimeout> | null = null;
-        const { auto_refresh, refresh_interval } = config;
+        const { auto_refresh } = config;
 
         // Check if we just turned it on
         if (auto_refresh && !prevAutoRefreshRef.current) {
@@ -23,22 +23,7 @@ function BackgroundTaskRunner() {
             refreshAllQuotas();
         }
         prevAutoRefreshRef.current = auto_refresh;
-
-        if (auto_refresh && refresh_interval > 0) {
-            console.log(`[BackgroundTask] Starting auto-refresh quota timer: ${refresh_interval} mins`);
-            intervalId = setInterval(() => {
-                console.log('[BackgroundTask] Auto-refreshing all quotas...');
-                refreshAllQuotas();
-            }, Math.min(refresh_interval * 60 * 1000, 2147483647));
-        }
-
-        return () => {
-            if (intervalId) {
-                console.log('[BackgroundTask] Clearing auto-refresh timer');
-                clearInterval(intervalId);
-            }
-        };
-    }, [config?.auto_refresh, config?.refresh_interval]);
+    }, [config?.auto_refresh]);
 
     // Auto Sync Current Account Effect
     useEffect(() => {
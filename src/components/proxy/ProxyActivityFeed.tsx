@@ -0,0 +1,123 @@
+import React, { useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { useTranslation } from 'react-i18next';
+import { Activity, ArrowRightLeft, CheckCircle, Hourglass, Play, Unplug, XCircle } from 'lucide-react';
+
+import { request as invoke } from '../../utils/request';
+import { isTauri } from '../../utils/env';
+import { cn } from '../../utils/cn';
+
+type ProxyActivity =
+    | { id: number; timestamp: number; kind: 'request_started'; request_id: string; method: string; path: string; model?: string }
+    | { id: number; timestamp: number; kind: 'request_finished'; request_id: string; method: string; path: string; status: number; duration_ms: number; model?: string; account_email?: string }
+    | { id: number; timestamp: number; kind: 'account_rotated'; from_account_id?: string; to_account_id: string; to_email: string; reason: string }
+    | { id: number; timestamp: number; kind: 'cooldown_applied'; account_id: string; model?: string; reason: string; seconds: number }
+    | { id: number; timestamp: number; kind: 'stream_aborted'; request_id: string; path: string; reason: string };
+
+const MAX_EVENTS = 200;
+
+interface ProxyActivityFeedProps {
+    className?: string;
+}
+
+export const ProxyActivityFeed: React.FC<ProxyActivityFeedProps> = ({ className }) => {
+    const { t } = useTranslation();
+    const [events, setEvents] = useState<ProxyActivity[]>([]);
+
+    useEffect(() => {
+        if (!isTauri()) return;
+        let disposed = false;
+        let unlistenFn: (() => void) | null = null;
+
+        invoke<ProxyActivity[]>('get_proxy_activity', { limit: 100 })
+            .then((recent) => {
+                if (disposed) return;
+                setEvents((prev) => {
+                    const seen = new Set(prev.map((e) => e.id));
+                    return [...prev, ...recent.filter((e) => !seen.has(e.id)).reverse()].slice(0, MAX_EVENTS);
+                });
+            })
+            .catch((e) => console.error('Failed to load proxy activity', e));
+
+        listen<ProxyActivity>('proxy://activity', (event) => {
+            setEvents((prev) => [event.payload, ...prev].slice(0, MAX_EVENTS));
+        }).then((fn) => {
+            if (disposed) fn();
+            else unlistenFn = fn;
+        });
+
+        return () => {
+            disposed = true;
+            if (unlistenFn) unlistenFn();
+        };
+    }, []);
+
+    const describe = (e: ProxyActivity): { icon: React.ReactNode; text: string } => {
+        switch (e.kind) {
+            case 'request_started':
+                return {
+                    icon: <Play size={12} className="text-blue-500" />,
+                    text: t('monitor.activity.request_started', { method: e.method, path: e.path, model: e.model || '-' }),
+                };
+            case 'request_finished':
+                return {
+                    icon: e.status < 400
+                        ? <CheckCircle size={12} className="text-green-500" />
+                        : <XCircle size={12} className="text-red-500" />,
+                    text: t('monitor.activity.request_finished', {
+                        status: e.status,
+                        path: e.path,
+                        duration: e.duration_ms,
+                        account: e.account_email || '-',
+                    }),
+                };
+            case 'account_rotated':
+                return {
+                    icon: <ArrowRightLeft size={12} className="text-purple-500" />,
+                    text: t(`monitor.activity.account_rotated_${e.reason === 'failover' ? 'failover' : 'scheduled'}`, { email: e.to_email }),
+                };
+            case 'cooldown_applied':
+                return {
+                    icon: <Hourglass size={12} className="text-orange-500" />,
+                    text: t('monitor.activity.cooldown_applied', {
+                        account: e.account_id,
+                        model: e.model || '-',
+                        seconds: e.seconds,
+                        reason: e.reason,
+                    }),
+                };
+            case 'stream_aborted':
+                return {
+                    icon: <Unplug size={12} className="text-red-500" />,
+                    text: t('monitor.activity.stream_aborted', { path: e.path, reason: e.reason }),
+                };
+        }
+    };
+
+    return (
+        <div className={cn('flex flex-col bg-white dark:bg-base-100 rounded-xl border border-gray-100 dark:border-base-200 overflow-hidden', className)}>
+            <div className="flex items-center gap-2 px-4 py-2 border-b border-gray-100 dark:border-base-200 text-sm font-semibold text-gray-700 dark:text-gray-200">
+                <Activity size={14} />
+                {t('monitor.activity.title')}
+            </div>
+            <div className="flex-1 overflow-y-auto font-mono text-xs">
+                {events.length === 0 ? (
+                    <div className="p-4 text-center text-gray-400">{t('monitor.activity.empty')}</div>
+                ) : (
+                    events.map((e) => {
+                        const { icon, text } = describe(e);
+                        return (
+                            <div key={e.id} className="flex items-center gap-2 px-4 py-1 hover:bg-gray-50 dark:hover:bg-base-200">
+                                <span className="text-gray-400 shrink-0">{new Date(e.timestamp).toLocaleTimeString()}</span>
+                                {icon}
+                                <span className="truncate text-gray-700 dark:text-gray-300" title={text}>{text}</span>
+                            </div>
+                        );
+                    })
+                )}
+            </div>
+        </div>
+    );
+};
+
+export default ProxyActivityFeed;
@@ -1,6 +1,7 @@
 import { useState, useEffect, useMemo } from 'react';
 import { useTranslation } from 'react-i18next';
 import { request as invoke } from '../utils/request';
+import { listen } from '@tauri-apps/api/event';
 import { isTauri } from '../utils/env';
 import { copyToClipboard } from '../utils/clipboard';
 import {
@@ -24,7 +25,7 @@ import {
     Edit2,
     Save
 } from 'lucide-react';
-import { AppConfig, ProxyConfig, StickySessionConfig, ExperimentalConfig } from '../types/config';
+import { AppConfig, ProxyConfig, StickySessionConfig, ExperimentalConfig, ClientVersionConfig, ClientVersionInfo } from '../types/config';
 import HelpTooltip from '../components/common/HelpTooltip';
 import ModalDialog from '../components/common/ModalDialog';
 import { showToast } from '../components/common/ToastContainer';
@@ -204,6 +205,7 @@ export default function ApiProxy() {
     });
     const [cfLoading, setCfLoading] = useState(false);
     const [cfMode, setCfMode] = useState<'quick' | 'auth'>('quick');
+    const [clientVersionInfo, setClientVersionInfo] = useState<ClientVersionInfo | null>(null);
     const [cfToken, setCfToken] = useState('');
     const [cfUseHttp2, setCfUseHttp2] = useState(true); // 默认启用HTTP/2，更稳定
 
@@ -234,11 +236,17 @@ export default function ApiProxy() {
         loadPreferredAccount();
         loadCfStatus();
         loadCustomPresets();
+        loadClientVersionInfo();
         const interval = setInterval(loadStatus, 3000);
         const cfInterval = setInterval(loadCfStatus, 5000);
+        // 托盘 / 其他入口启停反代后立即同步状态
+        const unlistenStatus = isTauri()
+            ? listen<ProxyStatus>('proxy://status', (event) => setStatus(event.payload))
+            : null;
         return () => {
             clearInterval(interval);
             clearInterval(cfInterval);
+            unlistenStatus?.then(fn => fn());
         };
     }, []);
 
@@ -254,6 +262,15 @@ export default function ApiProxy() {
         }
     };
 
+    // [NEW] 客户端版本 / 默认 User-Agent (首次探测本地安装与远程版本可能需要数秒)
+    const loadClientVersionInfo = async () => {
+        try {
+            setClientVersionInfo(await invoke<ClientVersionInfo>('get_client_version_info'));
+        } catch (error) {
+            console.error('Failed to load client version info:', error);
+        }
+    };
+
     // Cloudflared: 检查状态
     const loadCfStatus = async () => {
         try {
@@ -698,6 +715,19 @@ export default function ApiProxy() {
         saveConfig(newConfig);
     };
 
+    const updateClientVersionConfig = async (updates: Partial<ClientVersionConfig>) => {
+        if (!appConfig) return;
+        const current = appConfig.proxy.client_version || { mode: 'auto' };
+        await saveConfig({
+            ...appConfig,
+            proxy: {
+                ...appConfig.proxy,
+                client_version: { ...current, ...updates }
+            }
+        });
+        loadClientVersionInfo();
+    };
+
     const updateSchedulingConfig = (updates: Partial<StickySessionConfig>) => {
         if (!appConfig) return;
         const currentScheduling = appConfig.proxy.scheduling || { mode: 'Balance', max_wait_seconds: 60 };
@@ -1465,7 +1495,7 @@ print(response.choices[0].message.content)`;
                                             const enabled = e.target.checked;
                                             if (enabled) {
                                                 // Restore saved override from config or use default
-                                                const restoredValue = appConfig.proxy.saved_user_agent || 'antigravity/1.15.8 darwin/arm64';
+                                                const restoredValue = appConfig.proxy.saved_user_agent || clientVersionInfo?.default_user_agent || '';
                                                 updateProxyConfig({
                                                     user_agent_override: restoredValue,
                                                     saved_user_agent: restoredValue
@@ -1493,14 +1523,54 @@ print(response.choices[0].message.content)`;
                                             className="w-full px-2.5 py-1.5 border border-gray-300 dark:border-base-200 rounded-lg bg-white dark:bg-base-200 text-xs font-mono text-gray-900 dark:text-base-content focus:ring-2 focus:ring-blue-500 focus:border-transparent"
                                             placeholder={t('proxy.config.request.user_agent_placeholder', { defaultValue: 'Enter custom User-Agent string...' })}
                                         />
-                                        <div className="bg-gray-50 dark:bg-base-300 rounded p-2 text-[10px] text-gray-500 font-mono break-all">
-                                            <span className="font-bold select-none mr-2">{t('common.example', { defaultValue: 'Example' })}:</span>
-                                            antigravity/1.15.8 darwin/arm64
-                                        </div>
+                                        {clientVersionInfo && (
+                                            <div className="bg-gray-50 dark:bg-base-300 rounded p-2 text-[10px] text-gray-500 font-mono break-all">
+                                                <span className="font-bold select-none mr-2">{t('proxy.config.request.user_agent_default', { defaultValue: 'Default' })}:</span>
+                                                {clientVersionInfo.default_user_agent}
+                                            </div>
+                                        )}
                                     </div>
                                 )}
                             </div>
 
+                            {/* Client Version */}
+                            <div className="border-t border-gray-200 dark:border-base-300 pt-3 mt-3 space-y-2">
+                                <label className="text-xs font-medium text-gray-700 dark:text-gray-300 inline-flex items-center gap-1">
+                                    {t('proxy.config.request.client_version', { defaultValue: 'Client Version' })}
+                                    <HelpTooltip text={t('proxy.config.request.client_version_tooltip', { defaultValue: 'Version reported upstream in x-client-version and the default User-Agent.' })} />
+                                </label>
+                                <div className="flex gap-2">
+                                    <select
+                                        value={appConfig.proxy.client_version?.mode || 'auto'}
+                                        onChange={(e) => updateClientVersionConfig({ mode: e.target.value as ClientVersionConfig['mode'] })}
+                                        className="flex-1 px-2.5 py-1.5 border border-gray-300 dark:border-base-200 rounded-lg bg-white dark:bg-base-200 text-xs text-gray-900 dark:text-base-content focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                                    >
+                                        <option value="auto">{t('proxy.config.request.client_version_modes.auto', { defaultValue: 'Auto (newest known)' })}</option>
+                                        <option value="installed">{t('proxy.config.request.client_version_modes.installed', { defaultValue: 'Follow installed Antigravity' })}</option>
+                                        <option value="fixed">{t('proxy.config.request.client_version_modes.fixed', { defaultValue: 'Fixed version' })}</option>
+                                    </select>
+                                    {appConfig.proxy.client_version?.mode === 'fixed' && (
+                                        <input
+                                            type="text"
+                                            value={appConfig.proxy.client_version?.version || ''}
+                                            onChange={(e) => updateClientVersionConfig({ version: e.target.value || undefined })}
+                                            className="w-28 px-2.5 py-1.5 border border-gray-300 dark:border-base-200 rounded-lg bg-white dark:bg-base-200 text-xs font-mono text-gray-900 dark:text-base-content focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                                            placeholder={clientVersionInfo?.resolved_version || '4.1.31'}
+                                        />
+                                    )}
+                                </div>
+                                {clientVersionInfo && (
+                                    <p className="text-[10px] text-gray-500 dark:text-gray-400 font-mono">
+                                        {t('proxy.config.request.client_version_status', {
+                                            defaultValue: 'Reporting {{effective}} · installed {{installed}} · auto {{resolved}}',
+                                            effective: clientVersionInfo.effective_version,
+                                            installed: clientVersionInfo.installed_version || '-',
+                                            resolved: clientVersionInfo.resolved_version,
+                                        })}
+                                    </p>
+                                )}
+                            </div>
+
 
                         </div>
                     </div>
@@ -1775,7 +1845,7 @@ print(response.choices[0].message.content)`;
                                                 </div>
                                             </div>
                                             <div className="grid grid-cols-1 gap-2">
-                                                {(['CacheFirst', 'Balance', 'PerformanceFirst'] as const).map(mode => (
+                                                {(['CacheFirst', 'Balance', 'PerformanceFirst', 'LeastUsedToday'] as const).map(mode => (
                                                     <label
                                                         key={mode}
                                                         className={`flex items-start gap-3 p-3 rounded-xl border cursor-pointer transition-all duration-200 ${(appConfig.proxy.scheduling?.mode || 'Balance') === mode
@@ -1797,7 +1867,8 @@ print(response.choices[0].message.content)`;
                                                                 {t(`proxy.config.scheduling.modes_desc.${mode}`, {
                                                                     defaultValue: mode === 'CacheFirst' ? 'Binds session to account, waits precisely if limited (Maximizes Prompt Cache hits).' :
                                                                         mode === 'Balance' ? 'Binds session, auto-switches to available account if limited (Balanced cache & availability).' :
-                                                                            'No session binding, pure round-robin rotation (Best for high concurrency).'
+                                                                            mode === 'LeastUsedToday' ? 'Binds session, new sessions go to the account with the fewest tokens used today (Spreads daily consumption evenly).' :
+                                                                                'No session binding, pure round-robin rotation (Best for high concurrency).'
                                                                 })}
                                                             </div>
                                                         </div>
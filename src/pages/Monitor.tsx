@@ -1,10 +1,13 @@
 import React from 'react';
 import { ProxyMonitor } from '../components/proxy/ProxyMonitor';
+import { ProxyActivityFeed } from '../components/proxy/ProxyActivityFeed';
+import { isTauri } from '../utils/env';
 
 const Monitor: React.FC = () => {
     return (
         <div className="h-full flex flex-col p-5 gap-4 max-w-7xl mx-auto w-full">
             <ProxyMonitor className="flex-1" />
+            {isTauri() && <ProxyActivityFeed className="h-56 shrink-0" />}
         </div>
     );
 };
@@ -94,6 +94,14 @@ function Settings() {
 
     // Update check state
     const [isCheckingUpdate, setIsCheckingUpdate] = useState(false);
+    const [isCheckingAgVersion, setIsCheckingAgVersion] = useState(false);
+    const [agVersion, setAgVersion] = useState<{
+        executable_path?: string;
+        installed_version?: string;
+        latest_version?: string;
+        update_available: boolean;
+        error?: string;
+    } | null>(null);
     const [updateInfo, setUpdateInfo] = useState<{
         hasUpdate: boolean;
         latestVersion: string;
@@ -236,6 +244,7 @@ function Settings() {
                     proxy: {
                         ...formData.proxy,
                         debug_logging: {
+                            ...formData.proxy?.debug_logging,
                             enabled: formData.proxy?.debug_logging?.enabled ?? false,
                             output_dir: selected,
                         },
@@ -258,6 +267,17 @@ function Settings() {
         }
     };
 
+    const handleCheckAntigravityVersion = async () => {
+        setIsCheckingAgVersion(true);
+        try {
+            setAgVersion(await invoke<NonNullable<typeof agVersion>>('check_antigravity_version', { checkLatest: true }));
+        } catch (error) {
+            showToast(`${t('common.error')}: ${error}`, 'error');
+        } finally {
+            setIsCheckingAgVersion(false);
+        }
+    };
+
     const handleCheckUpdate = async () => {
         setIsCheckingUpdate(true);
         setUpdateInfo(null);
@@ -921,6 +941,29 @@ function Settings() {
                                     <p className="text-sm text-gray-500 dark:text-gray-400 mt-2">
                                         {t('settings.advanced.antigravity_path_desc')}
                                     </p>
+                                    <div className="flex items-center gap-3 mt-2 text-sm">
+                                        <button
+                                            className="px-3 py-1.5 border border-gray-200 dark:border-base-300 text-gray-700 dark:text-gray-300 rounded-lg hover:bg-gray-50 dark:hover:bg-base-200 transition-colors inline-flex items-center gap-1.5 disabled:opacity-50"
+                                            onClick={handleCheckAntigravityVersion}
+                                            disabled={isCheckingAgVersion}
+                                        >
+                                            <RefreshCw size={14} className={isCheckingAgVersion ? 'animate-spin' : ''} />
+                                            {t('settings.advanced.antigravity_version_check')}
+                                        </button>
+                                        {agVersion && (
+                                            agVersion.installed_version ? (
+                                                <span className={agVersion.update_available ? 'text-orange-600 dark:text-orange-400' : 'text-gray-600 dark:text-gray-400'}>
+                                                    {agVersion.update_available
+                                                        ? t('settings.advanced.antigravity_version_outdated', { installed: agVersion.installed_version, latest: agVersion.latest_version })
+                                                        : t('settings.advanced.antigravity_version_current', { installed: agVersion.installed_version })}
+                                                </span>
+                                            ) : (
+                                                <span className="text-red-600 dark:text-red-400">
+                                                    {t('settings.advanced.antigravity_version_unknown', { error: agVersion.error || '-' })}
+                                                </span>
+                                            )
+                                        )}
+                                    </div>
                                 </div>
 
                                 {/* 反重力程序启动参数 */}
@@ -959,6 +1002,39 @@ function Settings() {
                                     </p>
                                 </div>
 
+                                {/* [NEW] 守护模式 */}
+                                {isTauri() && (
+                                    <div className="flex items-center justify-between p-4 bg-gray-50 dark:bg-base-200 rounded-lg border border-gray-100 dark:border-base-300">
+                                        <div>
+                                            <div className="font-medium text-gray-900 dark:text-base-content">{t('settings.advanced.watchdog')}</div>
+                                            <p className="text-sm text-gray-600 dark:text-gray-400 mt-1">
+                                                {t('settings.advanced.watchdog_desc', {
+                                                    max: formData.watchdog?.max_restarts ?? 3,
+                                                    minutes: formData.watchdog?.window_minutes ?? 10,
+                                                })}
+                                            </p>
+                                        </div>
+                                        <label className="relative inline-flex items-center cursor-pointer">
+                                            <input
+                                                type="checkbox"
+                                                className="sr-only peer"
+                                                checked={formData.watchdog?.enabled ?? false}
+                                                onChange={(e) => setFormData({
+                                                    ...formData,
+                                                    watchdog: {
+                                                        check_interval_secs: 15,
+                                                        max_restarts: 3,
+                                                        window_minutes: 10,
+                                                        ...formData.watchdog,
+                                                        enabled: e.target.checked,
+                                                    },
+                                                })}
+                                            />
+                                            <div className="w-11 h-6 bg-gray-200 dark:bg-base-300 peer-focus:outline-none peer-focus:ring-4 peer-focus:ring-blue-300 dark:peer-focus:ring-blue-800 rounded-full peer peer-checked:after:translate-x-full peer-checked:after:border-white after:content-[''] after:absolute after:top-[2px] after:left-[2px] after:bg-white after:border-gray-300 after:border after:rounded-full after:h-5 after:w-5 after:transition-all peer-checked:bg-blue-500"></div>
+                                        </label>
+                                    </div>
+                                )}
+
                                 {/* 日志缓存清理 */}
                                 <div className="border-t border-gray-200 dark:border-base-200 pt-4">
                                     <h3 className="font-medium text-gray-900 dark:text-base-content mb-3">{t('settings.advanced.logs_title')}</h3>
@@ -1017,6 +1093,7 @@ function Settings() {
                                                         proxy: {
                                                             ...formData.proxy,
                                                             debug_logging: {
+                                                                ...formData.proxy?.debug_logging,
                                                                 enabled: e.target.checked,
                                                                 output_dir: formData.proxy?.debug_logging?.output_dir,
                                                             },
@@ -1048,6 +1125,7 @@ function Settings() {
                                                                 proxy: {
                                                                     ...formData.proxy,
                                                                     debug_logging: {
+                                                                        ...formData.proxy?.debug_logging,
                                                                         enabled: formData.proxy?.debug_logging?.enabled ?? false,
                                                                         output_dir: e.target.value || undefined,
                                                                     },
@@ -1,6 +1,7 @@
 import i18n from '../i18n';
 import { Account, DeviceProfile, DeviceProfileVersion, QuotaData } from '../types/account';
 import { request as invoke } from '../utils/request';
+import { isTauri } from '../utils/env';
 
 // 检查环境 (可选)
 function ensureTauriEnvironment() {
@@ -37,7 +38,9 @@ export async function deleteAccounts(accountIds: string[]): Promise<void> {
 }
 
 export async function switchAccount(accountId: string): Promise<void> {
-    return await invoke('switch_account', { accountId });
+    // 桌面版: 关闭 → 切换 → 带原工作区重启 Antigravity 一步完成 (进度事件 account://switch-progress)
+    const command = isTauri() ? 'switch_account_and_restart' : 'switch_account';
+    return await invoke(command, { accountId });
 }
 
 export async function fetchAccountQuota(accountId: string): Promise<QuotaData> {
@@ -90,11 +93,77 @@ export async function completeOAuthLogin(): Promise<Account> {
     }
 }
 
+// 重新授权 refresh_token 失效 (invalid_grant) 的账号
+export async function reauthorizeAccount(accountId: string): Promise<Account> {
+    ensureTauriEnvironment();
+    return await invoke('reauthorize_account', { accountId });
+}
+
 export async function cancelOAuthLogin(): Promise<void> {
     ensureTauriEnvironment();
     return await invoke('cancel_oauth_login');
 }
 
+export interface ManualOAuthStart {
+    auth_url: string;
+    state: string;
+    redirect_uri: string;
+    expires_in: number;
+}
+
+// 手动粘贴登录 (无浏览器 / 远程环境): 先获取授权链接，再提交回调 URL 或授权码
+export async function beginManualOAuthLogin(oauthClientKey?: string): Promise<ManualOAuthStart> {
+    return await invoke('begin_manual_oauth_login', oauthClientKey ? { oauthClientKey } : undefined);
+}
+
+export async function finishManualOAuthLogin(input: string, state?: string): Promise<Account> {
+    return await invoke('finish_manual_oauth_login', { input, state: state ?? null });
+}
+
+// 批量登录队列 (进度事件: login-queue://progress)
+export type LoginItemStatus = 'pending' | 'in_progress' | 'succeeded' | 'failed' | 'cancelled';
+
+export interface LoginQueueItem {
+    email: string;
+    status: LoginItemStatus;
+    error?: string | null;
+    account_id?: string | null;
+    authenticated_email?: string | null;
+    attempts: number;
+}
+
+export interface LoginQueueProgress {
+    total: number;
+    succeeded: number;
+    failed: number;
+    pending: number;
+    running: boolean;
+    current_email?: string | null;
+    items: LoginQueueItem[];
+}
+
+export async function startLoginQueue(emails: string[], oauthClientKey?: string): Promise<LoginQueueProgress> {
+    ensureTauriEnvironment();
+    return await invoke('start_login_queue', { emails, oauthClientKey: oauthClientKey ?? null });
+}
+
+export async function resumeLoginQueue(): Promise<LoginQueueProgress> {
+    ensureTauriEnvironment();
+    return await invoke('resume_login_queue');
+}
+
+export async function cancelLoginQueue(): Promise<void> {
+    return await invoke('cancel_login_queue');
+}
+
+export async function clearLoginQueue(): Promise<void> {
+    return await invoke('clear_login_queue');
+}
+
+export async function getLoginQueueStatus(): Promise<LoginQueueProgress> {
+    return await invoke('get_login_queue_status');
+}
+
 export interface OAuthClientInfo {
     key: string;
     label: string;
@@ -140,6 +209,13 @@ export async function toggleProxyStatus(accountId: string, enable: boolean, reas
     return await invoke('toggle_proxy_status', { accountId, enable, reason });
 }
 
+/**
+ * 设置账号专属的 Antigravity 启动参数 (例如 ['--user-data-dir', '/path/to/profile'])，传 null 清除
+ */
+export async function updateAccountLaunchArgs(accountId: string, launchArgs: string[] | null): Promise<void> {
+    return await invoke('update_account_launch_args', { accountId, launchArgs });
+}
+
 /**
  * 重新排序账号列表
  * @param accountIds 按新顺序排列的账号ID数组
@@ -214,6 +290,41 @@ export async function exportAccounts(accountIds: string[]): Promise<ExportAccoun
     return await invoke('export_accounts', { accountIds });
 }
 
+// 加密账号包 (迁移 / 备份)
+export type BundleMergeStrategy = 'skip' | 'overwrite';
+
+export interface BundleImportResult {
+    imported: number;
+    updated: number;
+    skipped: number;
+    duplicates: string[];
+    accounts: Account[];
+}
+
+export async function exportAccountsBundle(accountIds: string[], passphrase: string, path: string): Promise<number> {
+    return await invoke('export_accounts_bundle', { accountIds, passphrase, path });
+}
+
+export async function importAccountsBundle(path: string, passphrase: string, strategy: BundleMergeStrategy = 'skip'): Promise<BundleImportResult> {
+    return await invoke('import_accounts_bundle', { path, passphrase, strategy });
+}
+
+// 账号数据库维护 (SQLite)
+export interface AccountDbMaintenance {
+    path: string;
+    size_before: number;
+    size_after: number;
+    account_count: number;
+}
+
+export async function backupAccountDb(targetPath?: string): Promise<AccountDbMaintenance> {
+    return await invoke('backup_account_db', { targetPath });
+}
+
+export async function vacuumAccountDb(): Promise<AccountDbMaintenance> {
+    return await invoke('vacuum_account_db');
+}
+
 // 自定义标签相关
 export async function updateAccountLabel(accountId: string, label: string): Promise<void> {
     return await invoke('update_account_label', { accountId, label });
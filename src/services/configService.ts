@@ -1,5 +1,12 @@
 import { request as invoke } from '../utils/request';
-import { AppConfig } from '../types/config';
+import {
+    AppConfig,
+    CachedContentInfo,
+    CreateCachedContentRequest,
+    ModelMappingRule,
+    ModelRouteResolution,
+    ProxyConfig,
+} from '../types/config';
 
 export async function loadConfig(): Promise<AppConfig> {
     return await invoke('load_config');
@@ -8,3 +15,110 @@ export async function loadConfig(): Promise<AppConfig> {
 export async function saveConfig(config: AppConfig): Promise<void> {
     return await invoke('save_config', { config });
 }
+
+// 多配置档案
+export interface ConfigProfile {
+    name: string;
+    proxy: ProxyConfig;
+    created_at: number;
+    updated_at: number;
+}
+
+export interface ConfigProfileSummary {
+    name: string;
+    port: number;
+    mapping_count: number;
+    account_count: number;
+    is_active: boolean;
+    updated_at: number;
+}
+
+export async function listConfigProfiles(): Promise<{ active?: string | null; profiles: ConfigProfile[] }> {
+    return await invoke('list_config_profiles');
+}
+
+export async function createConfigProfile(name: string): Promise<ConfigProfileSummary[]> {
+    return await invoke('create_config_profile', { name });
+}
+
+export async function saveActiveConfigProfile(): Promise<ConfigProfileSummary[]> {
+    return await invoke('save_active_config_profile');
+}
+
+export async function deleteConfigProfile(name: string): Promise<ConfigProfileSummary[]> {
+    return await invoke('delete_config_profile', { name });
+}
+
+export async function switchConfigProfile(name: string): Promise<AppConfig> {
+    return await invoke('switch_config_profile', { name });
+}
+
+// 存储 schema 迁移记录
+export interface MigrationReport {
+    target: string;
+    from_version: number;
+    to_version: number;
+    applied: { version: number; description: string }[];
+    backup_path?: string | null;
+    migrated_at: number;
+}
+
+export async function getMigrationReports(): Promise<MigrationReport[]> {
+    return await invoke('get_migration_reports');
+}
+
+// 自动备份快照 (账号数据库 + 配置)
+export interface BackupSnapshot {
+    id: string;
+    created_at: number;
+    reason: 'scheduled' | 'manual' | 'pre_restore';
+    account_count: number;
+    files: string[];
+    size: number;
+}
+
+export async function listBackupSnapshots(): Promise<BackupSnapshot[]> {
+    return await invoke('list_backup_snapshots');
+}
+
+export async function createBackupSnapshot(): Promise<BackupSnapshot> {
+    return await invoke('create_backup_snapshot');
+}
+
+export async function deleteBackupSnapshot(snapshotId: string): Promise<void> {
+    return await invoke('delete_backup_snapshot', { snapshotId });
+}
+
+// 恢复前需由调用方向用户确认；返回恢复前自动生成的快照，可用于撤销
+export async function restoreBackupSnapshot(snapshotId: string): Promise<BackupSnapshot> {
+    return await invoke('restore_backup_snapshot', { snapshotId });
+}
+
+// Gemini 上下文缓存 (accountId 缺省为当前账号)
+export async function listCachedContents(accountId?: string): Promise<CachedContentInfo[]> {
+    return await invoke('list_cached_contents', { accountId });
+}
+
+export async function createCachedContent(request: CreateCachedContentRequest): Promise<CachedContentInfo> {
+    return await invoke('create_cached_content', { request });
+}
+
+export async function deleteCachedContent(cacheId: string, accountId?: string): Promise<void> {
+    return await invoke('delete_cached_content', { cacheId, accountId });
+}
+
+export async function listModelMappingRules(): Promise<ModelMappingRule[]> {
+    return await invoke('list_model_mapping_rules');
+}
+
+export async function saveModelMappingRule(rule: ModelMappingRule): Promise<ModelMappingRule[]> {
+    return await invoke('save_model_mapping_rule', { rule });
+}
+
+export async function deleteModelMappingRule(ruleId: string): Promise<ModelMappingRule[]> {
+    return await invoke('delete_model_mapping_rule', { ruleId });
+}
+
+export async function resolveModelMapping(model: string): Promise<ModelRouteResolution> {
+    return await invoke('resolve_model_mapping', { model });
+}
@@ -0,0 +1,39 @@
+import { request as invoke } from '../utils/request';
+
+// 日志文件查询 (实时日志仍通过 log-event 事件推送)
+export interface LogRecord {
+    timestamp: string;
+    level: 'TRACE' | 'DEBUG' | 'INFO' | 'WARN' | 'ERROR';
+    target: string;
+    message: string;
+    file: string;
+}
+
+export interface LogPage {
+    entries: LogRecord[];
+    offset: number;
+    limit: number;
+    has_more: boolean;
+}
+
+export interface LogQuery {
+    level?: string; // 最低级别
+    search?: string;
+    module?: string; // 模块前缀
+    offset?: number;
+    limit?: number;
+}
+
+export interface LogFileInfo {
+    name: string;
+    size: number;
+    modified: number;
+}
+
+export async function queryLogs(query: LogQuery = {}): Promise<LogPage> {
+    return await invoke('query_logs', { ...query });
+}
+
+export async function listLogFiles(): Promise<LogFileInfo[]> {
+    return await invoke('list_log_files');
+}
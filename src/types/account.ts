@@ -1,3 +1,5 @@
+import type { UpstreamProxyConfig } from './config';
+
 export interface Account {
     id: string;
     email: string;
@@ -9,11 +11,15 @@ export interface Account {
     disabled?: boolean;
     disabled_reason?: string;
     disabled_at?: number;
+    needs_reauth?: boolean;
+    auto_disabled?: boolean; // [NEW] 连续鉴权失败后由反代自动停用
     proxy_disabled?: boolean;
     proxy_disabled_reason?: string;
     proxy_disabled_at?: number;
     protected_models?: string[];
     custom_label?: string;  // 用户自定义标签
+    upstream_proxy?: UpstreamProxyConfig;  // 账号专属上游代理
+    launch_args?: string[];  // 切换到该账号时 Antigravity 的专属启动参数 (如 --user-data-dir)
     validation_blocked?: boolean;
     validation_blocked_until?: number;
     validation_blocked_reason?: string;
@@ -54,6 +60,16 @@ export interface ModelQuota {
     supported_mime_types?: Record<string, boolean>;
 }
 
+export interface QuotaResetPrediction {
+    account_id: string;
+    email: string;
+    model_group: string;
+    percentage: number;
+    reset_at: number;       // Unix 秒
+    resets_in_secs: number;
+    source: 'reported' | 'observed';
+}
+
 export interface DeviceProfile {
     machine_id: string;
     mac_machine_id: string;
@@ -6,13 +6,19 @@ export interface UpstreamProxyConfig {
 export interface ProxyConfig {
     enabled: boolean;
     allow_lan_access?: boolean;
+    bind_address?: string; // [NEW] 自定义监听地址 (仅局域网访问开启时生效)
     auth_mode?: 'off' | 'strict' | 'all_except_health' | 'auto';
     port: number;
     api_key: string;
     admin_password?: string;
     auto_start: boolean;
     custom_mapping?: Record<string, string>;
+    tray_mapping_backup?: string; // [NEW] 托盘 Claude 预设生效前的 claude-* 映射
+    model_mapping_rules?: ModelMappingRule[]; // [NEW] 通配符 / 正则映射规则 (按优先级)
+    quota_group_overrides?: QuotaGroupOverride[]; // [NEW] 模型 -> 配额组覆盖
     request_timeout: number;
+    max_body_size_mb?: number; // [NEW] 请求体大小上限 (MB)
+    shutdown_grace_secs?: number; // [NEW] 重启/改绑端口时的连接排空宽限期 (秒)
     enable_logging: boolean;
     debug_logging?: DebugLoggingConfig;
     upstream_proxy: UpstreamProxyConfig;
@@ -20,11 +26,258 @@ export interface ProxyConfig {
     scheduling?: StickySessionConfig;
     experimental?: ExperimentalConfig;
     user_agent_override?: string;
+    client_version?: ClientVersionConfig; // [NEW] 上报给上游的客户端版本 (auto / installed / fixed)
     saved_user_agent?: string;
     thinking_budget?: ThinkingBudgetConfig;
     global_system_prompt?: GlobalSystemPromptConfig;
     image_thinking_mode?: 'enabled' | 'disabled'; // [NEW] 图像思维模式开关
     proxy_pool?: ProxyPoolConfig;
+    tls?: TlsConfig; // [NEW] 本地 HTTPS 配置
+    cors?: CorsConfig; // [NEW] 浏览器跨域配置 (重启服务生效)
+    upstream_endpoints?: UpstreamEndpointsConfig; // [NEW] 上游端点覆盖与备用端点
+    upstream_client?: UpstreamClientConfig; // [NEW] 上游连接池 / HTTP2 调优
+    usage_limits?: UsageLimitsConfig; // [NEW] 每日/每月用量上限
+    auto_disable?: AutoDisableConfig; // [NEW] 连续 401/403 后自动停用账号
+    client_rate_limit?: ClientRateLimitConfig; // [NEW] 按 API Key / IP 的客户端限流
+    retry_policy?: RetryPolicyConfig; // [NEW] 上游重试预算 / 退避抖动
+    safety_settings?: SafetySettingsConfig; // [NEW] Gemini 安全过滤阈值 (全局 / 按模型)
+    mcp_bridge?: McpBridgeConfig; // [NEW] MCP 工具桥接
+    context_trim?: ContextTrimConfig; // [NEW] 超长对话裁剪 / 摘要 (按模型，默认关闭)
+    system_prompt_injection?: SystemPromptInjectionConfig; // [NEW] 系统提示词注入 (全局 / 按 API Key)
+    redaction?: RedactionConfig; // [NEW] 请求内容脱敏 (DLP)
+    account_subset?: string[]; // [NEW] 参与轮询的账号 ID 子集 (空 = 全部)
+    account_groups?: AccountGroupsConfig; // [NEW] 账号分组与按模型 / API Key 路由
+    context_cache?: ContextCacheConfig; // [NEW] 模型 -> Gemini 上下文缓存绑定
+}
+
+// ============================================================================
+// Gemini 上下文缓存 (cachedContents)
+// ============================================================================
+
+export interface ContextCacheBinding {
+    /** 上游缓存名，例如 "cachedContents/abc123" */
+    name: string;
+    /** 创建缓存的账号，其他账号的请求不会注入该缓存 */
+    account_id?: string | null;
+}
+
+export interface ContextCacheConfig {
+    /** 模型名 (支持 * 通配符) -> 缓存 */
+    bindings: Record<string, ContextCacheBinding>;
+}
+
+export interface CachedContentInfo {
+    name: string;
+    model: string;
+    display_name?: string | null;
+    create_time?: string | null;
+    expire_time?: string | null;
+    total_token_count?: number | null;
+}
+
+export interface CreateCachedContentRequest {
+    accountId?: string;
+    model: string;
+    systemInstruction?: string;
+    contents?: unknown[];
+    tools?: unknown[];
+    ttlSeconds?: number;
+    displayName?: string;
+}
+
+export type ModelMappingRuleKind = 'wildcard' | 'regex';
+
+export interface QuotaGroupOverride {
+    pattern: string; // 支持 * 通配符，大小写不敏感
+    group: string; // gemini-3-flash / gemini-3-pro-high / gemini-3-pro-image / claude
+}
+
+export interface ModelMappingRule {
+    id: string; // 为空时由后端生成
+    pattern: string;
+    target: string; // 正则规则可引用捕获组 $1 / ${name}
+    kind: ModelMappingRuleKind;
+    priority: number; // 数值越大越先匹配
+    enabled: boolean;
+}
+
+export interface ModelRouteResolution {
+    model: string;
+    target: string;
+    matched_by: 'forwarding' | 'exact' | 'rule' | 'wildcard' | 'default';
+    rule_id?: string;
+    pattern?: string;
+}
+
+export interface UsageLimit {
+    daily_tokens?: number;
+    daily_requests?: number;
+    monthly_tokens?: number;
+    monthly_requests?: number;
+}
+
+export interface UsageLimitsConfig {
+    enabled: boolean;
+    global: UsageLimit; // 全局超限时直接返回 429
+    per_account_default: UsageLimit;
+    per_account: Record<string, UsageLimit>; // key: 账号邮箱
+}
+
+export interface AutoDisableConfig {
+    enabled: boolean;
+    consecutive_auth_failures: number; // 默认 3
+}
+
+export interface AccountGroup {
+    accounts: string[]; // 账号 ID 或邮箱
+    description?: string;
+}
+
+export interface AccountGroupRoute {
+    pattern: string; // 映射后的模型名，支持通配符
+    groups: string[];
+}
+
+export interface AccountGroupsConfig {
+    enabled: boolean;
+    groups: Record<string, AccountGroup>; // key: 分组名
+    model_routes: AccountGroupRoute[]; // 按顺序匹配第一条
+    key_routes: Record<string, string[]>; // key: API Key，优先于模型路由
+    fallback_to_all: boolean; // 分组内无可用账号时回退到全部账号
+}
+
+export interface ClientRateLimitRule {
+    requests_per_minute?: number;
+    max_concurrent_streams?: number; // 流式响应结束前持续占用
+}
+
+export interface RetryPolicyConfig {
+    budget_per_second: number; // 全局每秒重试次数上限 (0 = 不限制)
+    jitter: boolean; // 退避抖动 ±20% (默认关闭)
+}
+
+export interface ClientRateLimitConfig {
+    enabled: boolean;
+    global: ClientRateLimitRule;
+    per_key_default: ClientRateLimitRule;
+    per_key: Record<string, ClientRateLimitRule>; // key: API Key
+    per_ip_default: ClientRateLimitRule;
+    per_ip: Record<string, ClientRateLimitRule>; // key: 客户端 IP
+    trusted_proxies?: string[]; // 可信反向代理 IP，仅来自这些地址的请求才采用 X-Forwarded-For / X-Real-IP
+}
+
+/** 安全过滤阈值: off / none / low / medium / high (或 Gemini 原始名称如 BLOCK_ONLY_HIGH) */
+export interface SafetyRule {
+    threshold?: string;
+    categories?: Record<string, string>; // key: 类别，如 harassment / HARM_CATEGORY_DANGEROUS_CONTENT
+}
+
+export interface SafetySettingsConfig {
+    global: SafetyRule;
+    per_model: Record<string, SafetyRule>; // key: 映射后的模型名 (支持通配符)
+}
+
+/** 超出上下文窗口时的处理方式: off / trim (丢弃最早轮次) / summarize (摘要，仅 Claude 协议) */
+export type ContextTrimMode = 'off' | 'trim' | 'summarize';
+
+export interface ContextTrimRule {
+    mode: ContextTrimMode;
+    max_context_tokens?: number; // 为空时使用模型默认窗口
+    reserve_tokens: number; // 为输出预留
+}
+
+export interface ContextTrimConfig {
+    per_model: Record<string, ContextTrimRule>; // key: 映射后的模型名 (支持通配符)
+}
+
+export interface SystemPromptRule {
+    prefix?: string;
+    suffix?: string;
+}
+
+export interface SystemPromptInjectionConfig {
+    enabled: boolean;
+    global: SystemPromptRule;
+    per_key: Record<string, SystemPromptRule>; // key: API Key，命中时替代全局规则
+}
+
+export type RedactionPreset = 'email' | 'api_key' | 'bearer_token' | 'private_key';
+
+export interface RedactionRule {
+    name: string;
+    pattern: string; // Rust regex 语法
+    replacement: string; // 默认 "[REDACTED]"，支持 $1 捕获组
+    enabled: boolean;
+}
+
+export interface RedactionConfig {
+    enabled: boolean;
+    presets: RedactionPreset[];
+    rules: RedactionRule[];
+    redact_logs: boolean; // 同时对监控日志脱敏
+}
+
+/** MCP 服务器 (Streamable HTTP)，工具以 mcp__<name>__<tool> 形式暴露 */
+export interface McpServerConfig {
+    name: string;
+    url: string;
+    headers?: Record<string, string>; // 如 Authorization
+    enabled: boolean;
+}
+
+export interface McpBridgeConfig {
+    enabled: boolean;
+    server_side_tools: boolean; // 注入工具并由代理内部执行工具调用循环
+    max_tool_rounds: number;
+    timeout_secs: number;
+    servers: McpServerConfig[];
+}
+
+export type ClientVersionMode = 'auto' | 'installed' | 'fixed';
+
+export interface ClientVersionConfig {
+    mode: ClientVersionMode;
+    version?: string; // fixed 模式使用的版本号 (X.Y.Z)
+}
+
+export interface ClientVersionInfo {
+    installed_version?: string;
+    resolved_version: string;
+    effective_version: string;
+    default_user_agent: string;
+}
+
+export interface UpstreamClientConfig {
+    pool_max_idle_per_host: number;
+    pool_idle_timeout_secs: number;
+    tcp_keepalive_secs: number;
+    connect_timeout_secs: number;
+    http2_prior_knowledge: boolean;
+    accept_compression?: boolean; // [NEW] 请求 gzip/br 压缩的上游响应 (默认开启)
+    first_byte_timeout_secs?: number; // [NEW] 首字节超时 (0 = 不限制)
+    stream_idle_timeout_secs?: number; // [NEW] 流空闲超时，无新数据块即中断 (0 = 不限制)
+    total_timeout_secs?: number; // [NEW] 单次请求总超时兜底 (0 = 不限制)
+}
+
+export interface UpstreamEndpointsConfig {
+    base_url?: string; // 为空时使用内置端点，需包含 /v1internal 路径
+    fallback_urls: string[];
+}
+
+export interface CorsConfig {
+    allowed_origins: string[]; // 空或包含 "*" = 任意来源，支持 chrome-extension://* 通配
+    allowed_methods: string[]; // 空 = 默认列表
+    allowed_headers: string[]; // 空 = 镜像预检请求头
+    expose_headers: string[];
+    max_age_secs: number;
+    allow_credentials: boolean;
+    allow_private_network: boolean;
+}
+
+export interface TlsConfig {
+    enabled: boolean;
+    cert_path?: string; // 与 key_path 均为空时自动生成自签名证书
+    key_path?: string;
 }
 
 // ============================================================================
@@ -37,6 +290,9 @@ export type ThinkingBudgetMode = 'auto' | 'passthrough' | 'custom' | 'adaptive';
 /** Thinking Effort 等级 (仅 adaptive 模式) */
 export type ThinkingEffort = 'low' | 'medium' | 'high';
 
+/** Claude 协议下思维链输出方式 */
+export type ClaudeThinkingOutput = 'blocks' | 'strip' | 'tagged';
+
 /** Thinking Budget 配置 */
 export interface ThinkingBudgetConfig {
     /** 模式选择 */
@@ -45,6 +301,10 @@ export interface ThinkingBudgetConfig {
     custom_value: number;
     /** 思考强度 (仅在 mode=adaptive 时生效) */
     effort?: ThinkingEffort;
+    /** OpenAI 协议下隐藏思维链 (不输出 reasoning_content) */
+    hide_openai_reasoning?: boolean;
+    /** Claude 协议下思维链输出方式: blocks (thinking 块) / strip (丢弃) / tagged (<thinking> 标签文本) */
+    claude_thinking_output?: ClaudeThinkingOutput;
 }
 
 // ============================================================================
@@ -62,9 +322,12 @@ export interface GlobalSystemPromptConfig {
 export interface DebugLoggingConfig {
     enabled: boolean;
     output_dir?: string;
+    capture_raw_upstream?: boolean; // [NEW] 保存上游原始 SSE 响应
+    max_capture_bytes?: number; // [NEW] 原始响应最大保存字节数
+    transform_endpoint?: boolean; // [NEW] 开放 POST /debug/transform dry-run 转换
 }
 
-export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
+export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst' | 'LeastUsedToday';
 
 export interface StickySessionConfig {
     mode: SchedulingMode;
@@ -107,6 +370,35 @@ export interface QuotaProtectionConfig {
     monitored_models: string[];
 }
 
+export interface QuotaAlertConfig {
+    enabled: boolean;
+    threshold_percentage: number; // 剩余配额 <= 该值时告警
+    monitored_models: string[]; // 为空表示全部模型
+    desktop_notification: boolean;
+    webhook_url?: string; // Slack / Discord / 自建 Webhook
+}
+
+export interface BackupConfig {
+    enabled: boolean;
+    interval_hours: number; // 自动快照间隔 (小时)
+    keep: number; // 保留的定时快照数量 (手动/恢复前快照不参与轮转)
+}
+
+export interface WatchdogConfig {
+    enabled: boolean;
+    check_interval_secs: number; // 进程检查间隔 (秒)
+    max_restarts: number; // 窗口内最多重启次数，超过后暂停 (防崩溃循环)
+    window_minutes: number;
+}
+
+export interface LoggingConfig {
+    level: string; // trace / debug / info / warn / error
+    module_levels: Record<string, string>; // 模块 -> 级别
+    max_file_size_mb: number; // 单个日志文件大小上限 (0 = 仅按天轮转)
+    retention_days: number;
+    json_format?: boolean; // 日志文件输出 JSON Lines (重启后生效)
+}
+
 export interface PinnedQuotaModelsConfig {
     models: string[];
 }
@@ -116,6 +408,8 @@ export interface ExperimentalConfig {
     context_compression_threshold_l1?: number;
     context_compression_threshold_l2?: number;
     context_compression_threshold_l3?: number;
+    enable_malformed_call_retry?: boolean;
+    enable_web_search_blocks?: boolean;
 }
 
 export interface CircuitBreakerConfig {
@@ -144,6 +438,12 @@ export interface AppConfig {
     circuit_breaker: CircuitBreakerConfig; // [NEW] 熔断器配置
     proxy: ProxyConfig;
     cloudflared: CloudflaredConfig; // [NEW] Cloudflared 配置
+    quota_alert?: QuotaAlertConfig; // [NEW] 低配额告警
+    encrypt_tokens_at_rest?: boolean; // [NEW] 账号 Token 静态加密 (系统钥匙串 / 设备密钥)
+    schema_version?: number; // [NEW] 配置 schema 版本 (由后端迁移维护)
+    backup?: BackupConfig; // [NEW] 自动备份 (账号数据库 + 配置快照)
+    logging?: LoggingConfig; // [NEW] 日志级别与文件轮转
+    watchdog?: WatchdogConfig; // [NEW] Antigravity 意外退出后自动重启
 }
 
 // ============================================================================
@@ -35,24 +35,34 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'start_proxy_service': { url: '/api/proxy/start', method: 'POST' },
   'stop_proxy_service': { url: '/api/proxy/stop', method: 'POST' },
   'update_model_mapping': { url: '/api/proxy/mapping', method: 'POST' },
+  'list_model_mapping_rules': { url: '/api/proxy/mapping-rules', method: 'GET' },
+  'save_model_mapping_rule': { url: '/api/proxy/mapping-rules', method: 'POST' },
+  'delete_model_mapping_rule': { url: '/api/proxy/mapping-rules/:ruleId', method: 'DELETE' },
+  'resolve_model_mapping': { url: '/api/proxy/mapping/resolve', method: 'POST' },
   'generate_api_key': { url: '/api/proxy/api-key/generate', method: 'POST' },
   'clear_proxy_session_bindings': { url: '/api/proxy/session-bindings/clear', method: 'POST' },
   'clear_proxy_rate_limit': { url: '/api/proxy/rate-limits/:accountId', method: 'DELETE' },
   'clear_all_proxy_rate_limits': { url: '/api/proxy/rate-limits', method: 'DELETE' },
+  'get_account_metrics': { url: '/api/proxy/account-metrics', method: 'GET' },
   'check_proxy_health': { url: '/api/proxy/health-check/trigger', method: 'POST' },
   'get_preferred_account': { url: '/api/proxy/preferred-account', method: 'GET' },
   'set_preferred_account': { url: '/api/proxy/preferred-account', method: 'POST' },
+  'pin_proxy_account': { url: '/api/proxy/pinned-account', method: 'POST' },
+  'unpin_proxy_account': { url: '/api/proxy/pinned-account', method: 'DELETE' },
+  'get_pinned_proxy_account': { url: '/api/proxy/pinned-account', method: 'GET' },
   'fetch_zai_models': { url: '/api/zai/models/fetch', method: 'POST' },
   'load_config': { url: '/api/config', method: 'GET' },
   'save_config': { url: '/api/config', method: 'POST' },
   'get_proxy_stats': { url: '/api/proxy/stats', method: 'GET' },
   'set_proxy_monitor_enabled': { url: '/api/proxy/monitor/toggle', method: 'POST' },
+  'run_proxy_benchmark': { url: '/api/proxy/benchmark', method: 'POST' },
 
   // Logs & Monitoring
   'get_proxy_logs_filtered': { url: '/api/logs', method: 'GET' },
   'get_proxy_logs_count_filtered': { url: '/api/logs/count', method: 'GET' },
   'clear_proxy_logs': { url: '/api/logs/clear', method: 'POST' },
   'get_proxy_log_detail': { url: '/api/logs/:logId', method: 'GET' },
+  'replay_captured_request': { url: '/api/logs/replay', method: 'POST' },
 
   // Debug Console
   'enable_debug_console': { url: '/api/debug/enable', method: 'POST' },
@@ -86,9 +96,14 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'get_token_stats_account_trend_hourly': { url: '/api/stats/token/account-trend/hourly', method: 'GET' },
   'get_token_stats_account_trend_daily': { url: '/api/stats/token/account-trend/daily', method: 'GET' },
   'clear_token_stats': { url: '/api/stats/token/clear', method: 'POST' },
+  'get_quota_history': { url: '/api/stats/quota/history', method: 'GET' },
+  'get_quota_trend_daily': { url: '/api/stats/quota/trend/daily', method: 'GET' },
+  'get_quota_reset_predictions': { url: '/api/stats/quota/resets', method: 'GET' },
 
   // System
   'get_data_dir_path': { url: '/api/system/data-dir', method: 'GET' },
+  'query_logs': { url: '/api/system/logs', method: 'GET' },
+  'list_log_files': { url: '/api/system/logs/files', method: 'GET' },
   'get_update_settings': { url: '/api/system/updates/settings', method: 'GET' },
   'save_update_settings': { url: '/api/system/updates/save', method: 'POST' },
   'is_auto_launch_enabled': { url: '/api/system/autostart/status', method: 'GET' },
@@ -97,6 +112,8 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'save_http_api_settings': { url: '/api/system/http-api/settings', method: 'POST' },
   'get_antigravity_path': { url: '/api/system/antigravity/path', method: 'GET' },
   'get_antigravity_args': { url: '/api/system/antigravity/args', method: 'GET' },
+  'check_antigravity_version': { url: '/api/system/antigravity/version', method: 'GET' },
+  'get_client_version_info': { url: '/api/system/client-version', method: 'GET' },
 
   // Cloudflared
   'cloudflared_install': { url: '/api/proxy/cloudflared/install', method: 'POST' },
@@ -115,6 +132,17 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'complete_oauth_login': { url: '/api/accounts/oauth/complete', method: 'POST' },
   'cancel_oauth_login': { url: '/api/accounts/oauth/cancel', method: 'POST' },
   'submit_oauth_code': { url: '/api/accounts/oauth/submit-code', method: 'POST' },
+  'backup_account_db': { url: '/api/accounts/db/backup', method: 'POST' },
+  'vacuum_account_db': { url: '/api/accounts/db/vacuum', method: 'POST' },
+  'list_backup_snapshots': { url: '/api/backups', method: 'GET' },
+  'create_backup_snapshot': { url: '/api/backups', method: 'POST' },
+  'delete_backup_snapshot': { url: '/api/backups/:snapshotId', method: 'DELETE' },
+  'restore_backup_snapshot': { url: '/api/backups/:snapshotId/restore', method: 'POST' },
+  'list_cached_contents': { url: '/api/context-caches', method: 'GET' },
+  'create_cached_content': { url: '/api/context-caches', method: 'POST' },
+  'delete_cached_content': { url: '/api/context-caches/:cacheId', method: 'DELETE' },
+  'begin_manual_oauth_login': { url: '/api/accounts/oauth/manual/begin', method: 'POST' },
+  'finish_manual_oauth_login': { url: '/api/accounts/oauth/manual/finish', method: 'POST' },
   'list_oauth_clients': { url: '/api/accounts/oauth/clients', method: 'GET' },
   'get_active_oauth_client': { url: '/api/accounts/oauth/client', method: 'GET' },
   'set_active_oauth_client': { url: '/api/accounts/oauth/client', method: 'POST' },